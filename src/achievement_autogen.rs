@@ -0,0 +1,323 @@
+// 建立任務時自動生成對應成就：使用者自行開啟（預設關閉），全站可由設定停用
+//
+// 每位使用者每 N 分鐘最多生成一次；期間建立的任務只保留最新的一個，於時段結束後生成。
+// 子任務、職業主線任務，以及匯入 / 複製 / 接受職業任務建立的任務（各有批次流程）不生成。
+// 生成經由背景工作佇列執行，所有略過都記錄原因。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::AchievementAutogenConfig;
+use crate::models::Task;
+
+/// 不自動生成成就的建立來源（CreateTaskRequest.source）
+pub const BATCH_SOURCES: [&str; 3] = ["import", "clone", "career"];
+
+static ACHIEVEMENT_AUTOGEN_CONFIG: OnceLock<AchievementAutogenConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: AchievementAutogenConfig) {
+    if config.enabled {
+        log::info!("自動生成任務成就: 每位使用者每 {} 分鐘最多一次（需使用者開啟）", config.debounce_minutes);
+    } else {
+        log::info!("自動生成任務成就: 全站停用");
+    }
+    if ACHIEVEMENT_AUTOGEN_CONFIG.set(config).is_err() {
+        log::warn!("自動生成任務成就設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static AchievementAutogenConfig {
+    ACHIEVEMENT_AUTOGEN_CONFIG.get_or_init(AchievementAutogenConfig::default)
+}
+
+/// 不需查詢資料庫即可判斷的略過原因
+pub fn skip_reason(task: &Task, source: Option<&str>) -> Option<String> {
+    if task.parent_task_id.is_some() {
+        return Some("子任務不生成成就".to_string());
+    }
+    if task.career_mainline_id.is_some() {
+        return Some("職業主線任務由主線批次生成".to_string());
+    }
+    match source {
+        Some(source) if BATCH_SOURCES.contains(&source) => Some(format!("來源為 {}，由批次流程處理", source)),
+        _ => None,
+    }
+}
+
+// 每位使用者的去抖動狀態；pending 有值時代表已排定一次延後的生成
+#[derive(Default)]
+struct UserSlot {
+    last_run: Option<Instant>,
+    pending: Option<Task>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Decision {
+    // 立即生成
+    RunNow,
+    // 時段內已生成過，延後到時段結束
+    Defer(Duration),
+    // 已有延後的生成，改用較新的任務（回傳被取代的任務標題）
+    Replace(String),
+}
+
+fn decide(slot: &mut UserSlot, task: &Task, now: Instant, window: Duration) -> Decision {
+    if let Some(previous) = slot.pending.replace(task.clone()) {
+        return Decision::Replace(previous.title.unwrap_or_default());
+    }
+    match slot.last_run {
+        Some(last) if now.duration_since(last) < window => Decision::Defer(window - now.duration_since(last)),
+        _ => {
+            slot.pending = None;
+            slot.last_run = Some(now);
+            Decision::RunNow
+        }
+    }
+}
+
+static SLOTS: OnceLock<Mutex<HashMap<String, UserSlot>>> = OnceLock::new();
+
+fn slots() -> &'static Mutex<HashMap<String, UserSlot>> {
+    SLOTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 使用者是否開啟自動生成（未設定時為關閉）
+pub async fn load_enabled(rb: &RBatis, user_id: &str) -> std::result::Result<bool, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT achievement_autogen FROM user_settings WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(rows.first().and_then(|r| r["achievement_autogen"].as_i64()) == Some(1))
+}
+
+/// 建立任務後呼叫：依設定、來源與去抖動規則決定是否生成對應成就
+pub async fn on_task_created(rb: &RBatis, task: &Task, source: Option<&str>) {
+    let title = task.title.as_deref().unwrap_or("未命名任務");
+    if !config().enabled {
+        log::debug!("略過任務「{}」的成就生成：全站已停用", title);
+        return;
+    }
+    if let Some(reason) = skip_reason(task, source) {
+        log::info!("略過任務「{}」的成就生成：{}", title, reason);
+        return;
+    }
+    let Some(user_id) = task.user_id.clone() else {
+        return;
+    };
+    match load_enabled(rb, &user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            log::info!("略過任務「{}」的成就生成：使用者未開啟", title);
+            return;
+        }
+        Err(e) => {
+            log::warn!("略過任務「{}」的成就生成：讀取使用者設定失敗: {}", title, e);
+            return;
+        }
+    }
+
+    let window = Duration::from_secs(config().debounce_minutes.max(0) as u64 * 60);
+    let decision = match slots().lock() {
+        Ok(mut slots) => decide(slots.entry(user_id.clone()).or_default(), task, Instant::now(), window),
+        Err(_) => return,
+    };
+    match decision {
+        Decision::RunNow => {
+            crate::ai_tasks_achievement::spawn_generate_achievement_for_task(rb.clone(), task.clone());
+        }
+        Decision::Replace(previous) => {
+            log::info!("略過任務「{}」的成就生成：{} 分鐘內已生成過，改為稍後以「{}」生成", previous, config().debounce_minutes, title);
+        }
+        Decision::Defer(delay) => {
+            log::info!("任務「{}」的成就生成延後 {} 秒（{} 分鐘內已生成過）", title, delay.as_secs(), config().debounce_minutes);
+            let rb = rb.clone();
+            let deferred_user = user_id.clone();
+            let submitted = crate::background_jobs::submit_after("generate_achievement_for_task", delay, async move {
+                let task = slots().lock().ok().and_then(|mut slots| {
+                    let slot = slots.entry(deferred_user).or_default();
+                    slot.last_run = Some(Instant::now());
+                    slot.pending.take()
+                });
+                if let Some(task) = task {
+                    if let Err(e) = crate::ai_tasks_achievement::generate_achievement_for_task(&rb, &task).await {
+                        log::error!("異步生成成就失敗: {}", e);
+                    }
+                }
+            });
+            if !submitted {
+                if let Ok(mut slots) = slots().lock() {
+                    slots.entry(user_id).or_default().pending = None;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutogenSettings {
+    pub enabled: bool,
+    // 全站是否允許（false 時使用者設定不生效）
+    pub available: bool,
+    pub debounce_minutes: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAutogenSettingsRequest {
+    pub enabled: bool,
+}
+
+fn settings(enabled: bool) -> AutogenSettings {
+    AutogenSettings {
+        enabled,
+        available: config().enabled,
+        debounce_minutes: config().debounce_minutes,
+    }
+}
+
+/// 取得自動生成任務成就設定
+pub async fn get_autogen_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_enabled(rb.get_ref(), &user_id).await {
+        Ok(enabled) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings(enabled)),
+            message: "獲取自動生成成就設定成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取自動生成成就設定失敗: {}", e),
+        })),
+    }
+}
+
+/// 開啟或關閉自動生成任務成就
+pub async fn update_autogen_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<UpdateAutogenSettingsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let result = rb
+        .exec(
+            "INSERT INTO user_settings (user_id, achievement_autogen, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET
+                 achievement_autogen = excluded.achievement_autogen,
+                 updated_at = excluded.updated_at",
+            vec![
+                rbs::Value::String(user_id),
+                rbs::Value::I32(body.enabled as i32),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings(body.enabled)),
+            message: "自動生成成就設定已更新".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("更新自動生成成就設定失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    fn task(title: &str) -> Task {
+        serde_json::from_value(serde_json::json!({ "title": title })).unwrap()
+    }
+
+    #[test]
+    fn test_debounce_keeps_latest_task() {
+        let window = Duration::from_secs(600);
+        let start = Instant::now();
+        let mut slot = UserSlot::default();
+
+        assert_eq!(decide(&mut slot, &task("第一個"), start, window), Decision::RunNow);
+        // 時段內：延後到時段結束，期間只保留最新的任務
+        assert_eq!(
+            decide(&mut slot, &task("第二個"), start + Duration::from_secs(60), window),
+            Decision::Defer(Duration::from_secs(540))
+        );
+        assert_eq!(
+            decide(&mut slot, &task("第三個"), start + Duration::from_secs(120), window),
+            Decision::Replace("第二個".to_string())
+        );
+        assert_eq!(slot.pending.as_ref().and_then(|t| t.title.as_deref()), Some("第三個"));
+
+        // 延後的生成執行後（清空 pending），時段過去即可立即生成
+        slot.pending = None;
+        slot.last_run = Some(start + Duration::from_secs(600));
+        assert_eq!(decide(&mut slot, &task("第四個"), start + Duration::from_secs(1300), window), Decision::RunNow);
+    }
+
+    #[test]
+    fn test_skip_reasons() {
+        assert!(skip_reason(&task("一般任務"), None).is_none());
+        assert!(skip_reason(&task("一般任務"), Some("manual")).is_none());
+        assert!(skip_reason(&task("匯入的任務"), Some("import")).unwrap().contains("import"));
+        let mut subtask = task("子任務");
+        subtask.parent_task_id = Some("p".to_string());
+        assert!(skip_reason(&subtask, None).is_some());
+        let mut mainline = task("主線任務");
+        mainline.career_mainline_id = Some("m".to_string());
+        assert!(skip_reason(&mainline, Some("manual")).is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_autogen_setting_defaults_off() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "autogen").await;
+        let uri = format!("/api/users/{}/achievements/auto-generate", user.id);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["enabled"], false);
+        assert!(!load_enabled(&rb, &user.id).await.unwrap());
+
+        let req = actix_web::test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({"enabled": true}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["enabled"], true);
+        assert!(load_enabled(&rb, &user.id).await.unwrap());
+
+        let other = test_utils::create_user(&app, "autogen-other").await;
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+}
@@ -0,0 +1,224 @@
+// 任務完成後的背景成就檢查：每位使用者去抖動並避免並行
+//
+// 完成任務、週期任務達標與挑戰成功都會觸發檢查；連續觸發時只在使用者靜止 debounce_ms 後執行一次
+// （從第一次觸發起最多延後 10 倍，避免持續觸發時永遠不執行）。同一使用者同時只有一個檢查在執行，
+// 執行期間的觸發合併為結束後的一次補跑，確保最後一次完成的任務也會被計入。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use rbatis::RBatis;
+
+use crate::achievement_service::AchievementService;
+use crate::config::AchievementCheckConfig;
+
+// 連續觸發時，從第一次觸發起最多延後的倍數
+const MAX_DELAY_FACTOR: u32 = 10;
+
+static ACHIEVEMENT_CHECK_CONFIG: OnceLock<AchievementCheckConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: AchievementCheckConfig) {
+    log::info!("背景成就檢查: 使用者靜止 {}ms 後執行", config.debounce_ms);
+    if ACHIEVEMENT_CHECK_CONFIG.set(config).is_err() {
+        log::warn!("背景成就檢查設定已初始化，忽略重複設定");
+    }
+}
+
+fn debounce() -> Duration {
+    Duration::from_millis(ACHIEVEMENT_CHECK_CONFIG.get_or_init(AchievementCheckConfig::default).debounce_ms)
+}
+
+// 每位使用者排定中的檢查；存在即代表已有一個背景工作負責該使用者
+struct PendingCheck {
+    first_trigger: Instant,
+    last_trigger: Instant,
+    running: bool,
+    rerun: bool,
+}
+
+fn pending() -> MutexGuard<'static, HashMap<String, PendingCheck>> {
+    static PENDING: OnceLock<Mutex<HashMap<String, PendingCheck>>> = OnceLock::new();
+    PENDING
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 排定一次成就檢查；已有排定或執行中的檢查時只記錄觸發，不另開背景工作
+pub fn schedule(rb: &RBatis, user_id: &str) {
+    let now = Instant::now();
+    {
+        let mut pending = pending();
+        if let Some(check) = pending.get_mut(user_id) {
+            check.last_trigger = now;
+            if check.running {
+                check.rerun = true;
+            }
+            return;
+        }
+        pending.insert(
+            user_id.to_string(),
+            PendingCheck { first_trigger: now, last_trigger: now, running: false, rerun: false },
+        );
+    }
+    tokio::spawn(run(rb.clone(), user_id.to_string()));
+}
+
+// 距離可執行還需等待多久；None 表示已可執行
+fn remaining_wait(check: &PendingCheck, now: Instant, debounce: Duration) -> Option<Duration> {
+    let quiet_at = check.last_trigger + debounce;
+    let deadline = check.first_trigger + debounce * MAX_DELAY_FACTOR;
+    let due = quiet_at.min(deadline);
+    (due > now).then(|| due - now)
+}
+
+// 背景工作異常結束時移除排定，避免該使用者之後的觸發被永久忽略
+struct PendingGuard<'a> {
+    user_id: &'a str,
+    armed: bool,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            pending().remove(self.user_id);
+        }
+    }
+}
+
+async fn run(rb: RBatis, user_id: String) {
+    let mut guard = PendingGuard { user_id: &user_id, armed: true };
+    loop {
+        // 等到使用者靜止；標記執行中與判斷可執行在同一次鎖定內，期間的觸發不會遺漏
+        loop {
+            let wait = {
+                let mut pending = pending();
+                let Some(check) = pending.get_mut(&user_id) else { return };
+                let wait = remaining_wait(check, Instant::now(), debounce());
+                if wait.is_none() {
+                    check.running = true;
+                }
+                wait
+            };
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => break,
+            }
+        }
+
+        #[cfg(test)]
+        record_run(&user_id);
+        match AchievementService::check_and_unlock_achievements(&rb, &user_id).await {
+            Ok(unlocked) if !unlocked.is_empty() => {
+                let names: Vec<String> = unlocked.iter().map(|a| a.name.clone().unwrap_or_default()).collect();
+                log::info!("🎉 用戶 {} 解鎖了 {} 個成就: {}", user_id, unlocked.len(), names.join(", "));
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("檢查成就解鎖失敗: {}", e),
+        }
+
+        let mut pending = pending();
+        match pending.get_mut(&user_id) {
+            Some(check) if check.rerun => {
+                check.running = false;
+                check.rerun = false;
+                check.first_trigger = check.last_trigger;
+            }
+            _ => {
+                pending.remove(&user_id);
+                guard.armed = false;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+static RUNS: OnceLock<Mutex<HashMap<String, usize>>> = OnceLock::new();
+
+#[cfg(test)]
+fn record_run(user_id: &str) {
+    let runs = RUNS.get_or_init(|| Mutex::new(HashMap::new()));
+    *runs.lock().unwrap().entry(user_id.to_string()).or_default() += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json, mock_ai};
+    use actix_web::{http::StatusCode, test::TestRequest};
+    use serde_json::json;
+
+    fn runs(user_id: &str) -> usize {
+        RUNS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap().get(user_id).copied().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_remaining_wait_is_capped_by_first_trigger() {
+        let start = Instant::now();
+        let debounce = Duration::from_millis(100);
+        let mut check = PendingCheck { first_trigger: start, last_trigger: start, running: false, rerun: false };
+        assert_eq!(remaining_wait(&check, start, debounce), Some(debounce));
+        assert_eq!(remaining_wait(&check, start + debounce, debounce), None);
+
+        // 持續觸發時不超過第一次觸發後 10 倍
+        check.last_trigger = start + Duration::from_millis(950);
+        assert_eq!(remaining_wait(&check, start + Duration::from_millis(960), debounce), Some(Duration::from_millis(40)));
+        assert_eq!(remaining_wait(&check, start + Duration::from_secs(1), debounce), None);
+    }
+
+    #[actix_web::test]
+    async fn test_burst_of_completions_runs_bounded_checks() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let _mock = mock_ai::install(mock_ai::MockAIService::default());
+        let user = test_utils::create_user(&app, "burst_completer").await;
+        rb.exec(
+            "INSERT INTO achievement (id, name, requirement_type, requirement_value, experience_reward) VALUES ('twenty-tasks', '二十連發', 'task_complete', 20, 50)",
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        let mut tasks = Vec::new();
+        for i in 0..20 {
+            let req = TestRequest::post()
+                .uri("/api/tasks")
+                .insert_header(user.auth())
+                .set_json(json!({"user_id": user.id, "title": format!("練習 {}", i), "task_type": "side", "difficulty": 1, "experience": 10}))
+                .to_request();
+            let (_, body) = call_json(&app, req).await;
+            tasks.push((body["data"]["id"].as_str().unwrap().to_string(), body["data"]["version"].clone()));
+        }
+
+        let completions = tasks.iter().map(|(task_id, version)| {
+            let req = TestRequest::put()
+                .uri(&format!("/api/tasks/{}", task_id))
+                .insert_header(user.auth())
+                .set_json(json!({"status": crate::models::TaskStatus::Completed.to_i32(), "version": version}))
+                .to_request();
+            call_json(&app, req)
+        });
+        for (status, body) in futures::future::join_all(completions).await {
+            assert_eq!(status, StatusCode::OK, "{}", body);
+        }
+
+        // 最後一次完成也被計入：解鎖需要全部 20 個任務完成
+        let unlocked = test_utils::wait_until(|| async {
+            let rows: Vec<crate::models::UserAchievement> = crate::models::UserAchievement::select_by_map(
+                &rb,
+                rbs::value!{"user_id": user.id.clone(), "achievement_id": "twenty-tasks"},
+            )
+            .await
+            .unwrap_or_default();
+            !rows.is_empty()
+        })
+        .await;
+        assert!(unlocked, "連續完成 20 個任務後應解鎖成就");
+        assert!(test_utils::wait_until(|| async { !pending().contains_key(&user.id) }).await);
+        let runs = runs(&user.id);
+        assert!((1..=2).contains(&runs), "20 次完成應合併為至多 2 次檢查，實際 {}", runs);
+    }
+}
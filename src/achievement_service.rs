@@ -7,8 +7,132 @@ use log::{info, error};
 
 pub struct AchievementService;
 
+/// 單次檢查內共用的統計：多個成就依賴同一個數字時只查詢一次
+#[derive(Default)]
+struct ProgressCache {
+    completed_tasks: Option<u64>,
+    learning_tasks: Option<u64>,
+    max_skill_level: Option<i32>,
+    consecutive_days: Option<i32>,
+    completed_since_cancel: Option<Option<i32>>,
+    attributes: Option<Option<UserAttributes>>,
+    focus_sessions: Option<u64>,
+    daily_quests: Option<u64>,
+}
+
+impl ProgressCache {
+    async fn completed_tasks(&mut self, rb: &RBatis, user_id: &str) -> Result<u64, anyhow::Error> {
+        if let Some(count) = self.completed_tasks {
+            return Ok(count);
+        }
+        let sql = "SELECT COUNT(*) FROM task WHERE user_id = ? AND status = ?";
+        let args = vec![user_id.into(), TaskStatus::Completed.to_i32().into()];
+        let count: u64 = rb.query_decode(sql, args).await?;
+        self.completed_tasks = Some(count);
+        Ok(count)
+    }
+
+    async fn learning_tasks(&mut self, rb: &RBatis, user_id: &str) -> Result<u64, anyhow::Error> {
+        if let Some(count) = self.learning_tasks {
+            return Ok(count);
+        }
+        let sql = "SELECT COUNT(*) FROM task WHERE user_id = ? AND status = ? AND skill_tags LIKE ?";
+        let args = vec![user_id.into(), TaskStatus::Completed.to_i32().into(), rbs::Value::String("%智慧%".to_string())];
+        let count: u64 = rb.query_decode(sql, args).await?;
+        self.learning_tasks = Some(count);
+        Ok(count)
+    }
+
+    async fn max_skill_level(&mut self, rb: &RBatis, user_id: &str) -> Result<i32, anyhow::Error> {
+        if let Some(level) = self.max_skill_level {
+            return Ok(level);
+        }
+        let skills = Skill::select_by_map(rb, value!{"user_id": user_id}).await?;
+        let level = skills.iter().map(|s| s.level.unwrap_or(0)).max().unwrap_or(0);
+        self.max_skill_level = Some(level);
+        Ok(level)
+    }
+
+    async fn consecutive_days(&mut self, rb: &RBatis, user_id: &str) -> Result<i32, anyhow::Error> {
+        if let Some(days) = self.consecutive_days {
+            return Ok(days);
+        }
+        let days = UserProfile::select_by_map(rb, value!{"user_id": user_id})
+            .await?
+            .first()
+            .map(|profile| profile.consecutive_login_days.unwrap_or(0))
+            .unwrap_or(0);
+        self.consecutive_days = Some(days);
+        Ok(days)
+    }
+
+    // 定義：自上次取消任務(last_cancelled_at 最大值)之後完成(updated_at)的任務數量；沒有取消紀錄時為 None
+    async fn completed_since_cancel(&mut self, rb: &RBatis, user_id: &str) -> Result<Option<i32>, anyhow::Error> {
+        if let Some(count) = self.completed_since_cancel {
+            return Ok(count);
+        }
+        let tasks = Task::select_by_map(rb, value!{"user_id": user_id}).await?;
+        let latest_cancel_time = tasks.iter().filter_map(|t| t.last_cancelled_at).max();
+        let count = latest_cancel_time.map(|latest_cancel_time| {
+            tasks
+                .iter()
+                .filter(|t| t.status == Some(TaskStatus::Completed.to_i32()))
+                .filter_map(|t| t.updated_at)
+                .filter(|updated| *updated > latest_cancel_time)
+                .count() as i32
+        });
+        self.completed_since_cancel = Some(count);
+        Ok(count)
+    }
+
+    async fn attribute(&mut self, rb: &RBatis, user_id: &str, attribute_name: &str) -> Result<Option<i32>, anyhow::Error> {
+        if self.attributes.is_none() {
+            let attributes = UserAttributes::select_by_map(rb, value!{"user_id": user_id}).await?.into_iter().next();
+            self.attributes = Some(attributes);
+        }
+        let Some(Some(attributes)) = &self.attributes else {
+            return Ok(None);
+        };
+        let value = match attribute_name {
+            "intelligence" => attributes.intelligence,
+            "endurance" => attributes.endurance,
+            "creativity" => attributes.creativity,
+            "social" => attributes.social,
+            "focus" => attributes.focus,
+            "adaptability" => attributes.adaptability,
+            _ => {
+                error!("未知的屬性類型: {}", attribute_name);
+                return Ok(None);
+            }
+        };
+        Ok(Some(value.unwrap_or(0)))
+    }
+
+    async fn focus_sessions(&mut self, rb: &RBatis, user_id: &str) -> Result<u64, anyhow::Error> {
+        if let Some(count) = self.focus_sessions {
+            return Ok(count);
+        }
+        let sql = "SELECT COUNT(*) FROM focus_session WHERE user_id = ? AND status = 'completed'";
+        let count: u64 = rb.query_decode(sql, vec![user_id.into()]).await?;
+        self.focus_sessions = Some(count);
+        Ok(count)
+    }
+
+    async fn daily_quests(&mut self, rb: &RBatis, user_id: &str) -> Result<u64, anyhow::Error> {
+        if let Some(count) = self.daily_quests {
+            return Ok(count);
+        }
+        let sql = "SELECT COUNT(*) FROM daily_quest WHERE user_id = ? AND bonus_awarded = 1";
+        let count: u64 = rb.query_decode(sql, vec![user_id.into()]).await?;
+        self.daily_quests = Some(count);
+        Ok(count)
+    }
+}
+
 impl AchievementService {
     /// 檢查並可能解鎖使用者的成就
+    ///
+    /// 任務完成等事件觸發的背景檢查請改用 achievement_check::schedule（同一使用者不並行、連續觸發會合併）。
     pub async fn check_and_unlock_achievements(rb: &RBatis, user_id: &str) -> Result<Vec<Achievement>, anyhow::Error> {
         // 1. 獲取所有成就定義 和 使用者已解鎖的成就ID
         let all_achievements = Achievement::select_all(rb).await?;
@@ -16,6 +140,7 @@ impl AchievementService {
         let unlocked_ids: std::collections::HashSet<Option<String>> = user_unlocked_achievements.into_iter().map(|ua| ua.achievement_id).collect();
 
         let mut newly_unlocked = Vec::new();
+        let mut progress = ProgressCache::default();
 
         // 2. 遍歷所有未解鎖的成就
         for achievement in all_achievements {
@@ -39,71 +164,50 @@ impl AchievementService {
                         count > 0
                     } else {
                         // 沒有 related_task_id，檢查完成任務總數
-                        let sql = "SELECT COUNT(*) FROM task WHERE user_id = ? AND status = ?";
-                        let args = vec![user_id.into(), TaskStatus::Completed.to_i32().into()];
-                        let count: u64 = rb.query_decode(sql, args).await?;
-                        count >= requirement_value as u64
+                        progress.completed_tasks(rb, user_id).await? >= requirement_value as u64
                     }
                 },
                 Some(AchievementRequirementType::LearningTaskComplete) => {
-                    let sql = "SELECT COUNT(*) FROM task WHERE user_id = ? AND status = ? AND skill_tags LIKE ?";
-                    let args = vec![user_id.into(), TaskStatus::Completed.to_i32().into(), rbs::Value::String("%智慧%".to_string())];
-                    let count: u64 = rb.query_decode(sql, args).await?;
-                    count >= requirement_value as u64
+                    progress.learning_tasks(rb, user_id).await? >= requirement_value as u64
                 },
                 Some(AchievementRequirementType::SkillLevel) => {
                     // 檢查是否有任何一個技能達到等級
-                    let skills = Skill::select_by_map(rb, value!{"user_id": user_id}).await?;
-                    skills.iter().any(|s| s.level.unwrap_or(0) >= requirement_value)
+                    progress.max_skill_level(rb, user_id).await? >= requirement_value
                 },
                 Some(AchievementRequirementType::ConsecutiveDays) => {
-                    if let Some(profile) = UserProfile::select_by_map(rb, value!{"user_id": user_id}).await?.first() {
-                        profile.consecutive_login_days.unwrap_or(0) >= requirement_value
-                    } else { false }
+                    progress.consecutive_days(rb, user_id).await? >= requirement_value
                 },
                 Some(AchievementRequirementType::StreakRecovery) => {
-                    // 定義：自上次取消任務(last_cancelled_at 最大值)之後，
-                    // 用戶完成的任務數量達到 requirement_value 視為從低潮中恢復
-                    // 1) 找出用戶最近一次取消時間
-                    let tasks_with_cancel = Task::select_by_map(rb, value!{"user_id": user_id}).await?;
-                    let latest_cancel_time = tasks_with_cancel
-                        .iter()
-                        .filter_map(|t| t.last_cancelled_at)
-                        .max();
-
-                    if let Some(latest_cancel_time) = latest_cancel_time {
-                        // 2) 統計在該時間點之後完成(updated_at)的任務數量
-                        let completed_tasks_after = tasks_with_cancel
-                            .iter()
-                            .filter(|t| t.status == Some(TaskStatus::Completed.to_i32()))
-                            .filter_map(|t| t.updated_at)
-                            .filter(|updated| *updated > latest_cancel_time)
-                            .count() as i32;
-
-                        completed_tasks_after >= requirement_value
-                    } else {
-                        // 沒有取消紀錄則不符合「恢復」定義
-                        false
-                    }
+                    // 沒有取消紀錄則不符合「恢復」定義
+                    progress
+                        .completed_since_cancel(rb, user_id)
+                        .await?
+                        .is_some_and(|count| count >= requirement_value)
                 },
                 // 屬性相關成就
                 Some(AchievementRequirementType::IntelligenceAttribute) => {
-                    Self::check_attribute_requirement(rb, user_id, "intelligence", requirement_value).await?
+                    Self::check_attribute_requirement(&mut progress, rb, user_id, "intelligence", requirement_value).await?
                 },
                 Some(AchievementRequirementType::EnduranceAttribute) => {
-                    Self::check_attribute_requirement(rb, user_id, "endurance", requirement_value).await?
+                    Self::check_attribute_requirement(&mut progress, rb, user_id, "endurance", requirement_value).await?
                 },
                 Some(AchievementRequirementType::CreativityAttribute) => {
-                    Self::check_attribute_requirement(rb, user_id, "creativity", requirement_value).await?
+                    Self::check_attribute_requirement(&mut progress, rb, user_id, "creativity", requirement_value).await?
                 },
                 Some(AchievementRequirementType::SocialAttribute) => {
-                    Self::check_attribute_requirement(rb, user_id, "social", requirement_value).await?
+                    Self::check_attribute_requirement(&mut progress, rb, user_id, "social", requirement_value).await?
                 },
                 Some(AchievementRequirementType::FocusAttribute) => {
-                    Self::check_attribute_requirement(rb, user_id, "focus", requirement_value).await?
+                    Self::check_attribute_requirement(&mut progress, rb, user_id, "focus", requirement_value).await?
                 },
                 Some(AchievementRequirementType::AdaptabilityAttribute) => {
-                    Self::check_attribute_requirement(rb, user_id, "adaptability", requirement_value).await?
+                    Self::check_attribute_requirement(&mut progress, rb, user_id, "adaptability", requirement_value).await?
+                },
+                Some(AchievementRequirementType::FocusSessionComplete) => {
+                    progress.focus_sessions(rb, user_id).await? >= requirement_value as u64
+                },
+                Some(AchievementRequirementType::DailyQuestComplete) => {
+                    progress.daily_quests(rb, user_id).await? >= requirement_value as u64
                 },
                 None => {
                     error!("成就 {} 沒有設置達成條件類型", achievement.name.as_deref().unwrap_or("未知"));
@@ -123,6 +227,11 @@ impl AchievementService {
                 };
                 if UserAchievement::insert(rb, &user_achievement).await.is_ok() {
                     info!("成功解鎖成就: {}", achievement.name.as_deref().unwrap_or("未知"));
+                    if let Some(achievement_id) = achievement.id.as_deref() {
+                        if let Err(e) = crate::services::achievement_stats::increment_achievement_completion_count(rb, achievement_id).await {
+                            log::warn!("更新成就統計失敗: {}", e);
+                        }
+                    }
                     newly_unlocked.push(achievement);
                 } else {
                     error!("解鎖成就 {} 失敗", achievement.name.as_deref().unwrap_or("未知"));
@@ -130,32 +239,30 @@ impl AchievementService {
             }
         }
 
+        // 4. 同一批解鎖合併為一則通知（寫入歷史、SSE、推送），不阻塞呼叫端
+        if !newly_unlocked.is_empty() {
+            let rb_clone = rb.clone();
+            let user_id_clone = user_id.to_string();
+            let unlocked_clone = newly_unlocked.clone();
+            tokio::spawn(async move {
+                crate::event_notifier::notify_achievements_unlocked(&rb_clone, &user_id_clone, &unlocked_clone).await;
+            });
+        }
+
         Ok(newly_unlocked)
     }
 
     /// 檢查用戶屬性是否達到要求
     async fn check_attribute_requirement(
-        rb: &RBatis, 
-        user_id: &str, 
-        attribute_name: &str, 
+        progress: &mut ProgressCache,
+        rb: &RBatis,
+        user_id: &str,
+        attribute_name: &str,
         requirement_value: i32
     ) -> Result<bool, anyhow::Error> {
-        if let Some(attributes) = UserAttributes::select_by_map(rb, value!{"user_id": user_id}).await?.first() {
-            let current_value = match attribute_name {
-                "intelligence" => attributes.intelligence,
-                "endurance" => attributes.endurance,
-                "creativity" => attributes.creativity,
-                "social" => attributes.social,
-                "focus" => attributes.focus,
-                "adaptability" => attributes.adaptability,
-                _ => {
-                    error!("未知的屬性類型: {}", attribute_name);
-                    return Ok(false);
-                }
-            };
-            Ok(current_value.unwrap_or(0) >= requirement_value)
-        } else {
-            Ok(false)
-        }
+        Ok(progress
+            .attribute(rb, user_id, attribute_name)
+            .await?
+            .is_some_and(|value| value >= requirement_value))
     }
 }
\ No newline at end of file
@@ -0,0 +1,346 @@
+// 成就分享：擁有者為已解鎖的成就產生公開分享 token，其他人憑 token 查看成就卡片
+//
+// 每個已解鎖成就最多一個 token，重複產生會回傳同一個；撤銷後舊連結立即失效。
+// /share/a/{token} 提供帶 OpenGraph 標籤的 HTML，讓社群平台能產生連結預覽。
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::mailer::escape_html;
+
+#[derive(Debug, Serialize)]
+pub struct ShareLink {
+    pub user_achievement_id: String,
+    pub token: String,
+    pub share_url: String,
+}
+
+/// 公開的成就卡片內容
+#[derive(Debug, Serialize)]
+pub struct SharedAchievement {
+    pub name: String,
+    pub icon: Option<String>,
+    pub description: Option<String>,
+    pub unlocked_at: Option<String>,
+    pub user_display_name: String,
+    pub level: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareQuery {
+    pub token: Option<String>,
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+fn internal_error(e: rbatis::Error) -> HttpResponse {
+    log::error!("成就分享查詢失敗: {}", e);
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("成就分享查詢失敗: {}", e))
+}
+
+fn not_found() -> HttpResponse {
+    error_response(StatusCode::NOT_FOUND, "分享連結不存在或已撤銷")
+}
+
+/// 分享頁的完整網址（依請求的 scheme 與 host 組成）
+fn share_url(http_req: &HttpRequest, token: &str) -> String {
+    let info = http_req.connection_info();
+    format!("{}://{}/share/a/{}", info.scheme(), info.host(), token)
+}
+
+/// 使用者已解鎖成就的 user_achievement.id（未解鎖時為 None）
+async fn unlocked_id(rb: &RBatis, user_id: &str, achievement_id: &str) -> std::result::Result<Option<String>, rbatis::Error> {
+    rb.query_decode(
+        "SELECT id FROM user_achievement WHERE user_id = ? AND achievement_id = ?",
+        vec![
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::String(achievement_id.to_string()),
+        ],
+    )
+    .await
+}
+
+/// 依條件讀取分享中的成就卡片（condition 為 achievement_share 的 WHERE 條件）
+async fn load_shared(
+    rb: &RBatis,
+    condition: &str,
+    args: Vec<rbs::Value>,
+) -> std::result::Result<Option<SharedAchievement>, rbatis::Error> {
+    let sql = format!(
+        "SELECT a.name, a.icon, a.description, ua.achieved_at, u.name AS user_name, p.level
+         FROM achievement_share s
+         JOIN user_achievement ua ON ua.id = s.user_achievement_id
+         JOIN achievement a ON a.id = ua.achievement_id
+         JOIN user u ON u.id = s.user_id
+         LEFT JOIN user_profile p ON p.user_id = s.user_id
+         WHERE {}",
+        condition
+    );
+    let rows: Vec<serde_json::Value> = rb.query_decode(&sql, args).await?;
+    Ok(rows.first().map(|row| SharedAchievement {
+        name: row["name"].as_str().unwrap_or("未知成就").to_string(),
+        icon: row["icon"].as_str().map(str::to_string),
+        description: row["description"].as_str().map(str::to_string),
+        unlocked_at: row["achieved_at"].as_str().map(str::to_string),
+        user_display_name: row["user_name"].as_str().unwrap_or("冒險者").to_string(),
+        level: row["level"].as_i64().unwrap_or(1),
+    }))
+}
+
+/// 產生（或取得既有的）分享連結
+pub async fn create_share(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, achievement_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let rb = rb.get_ref();
+    let user_achievement_id = match unlocked_id(rb, &user_id, &achievement_id).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return Ok(error_response(StatusCode::NOT_FOUND, "尚未解鎖此成就，無法分享")),
+        Err(e) => return Ok(internal_error(e)),
+    };
+
+    // 已有 token 時保留原本的，同時送出的請求也只會產生一個
+    if let Err(e) = rb
+        .exec(
+            "INSERT INTO achievement_share (id, user_achievement_id, user_id, token, created_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(user_achievement_id) DO NOTHING",
+            vec![
+                rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                rbs::Value::String(user_achievement_id.clone()),
+                rbs::Value::String(user_id.clone()),
+                rbs::Value::String(uuid::Uuid::new_v4().simple().to_string()),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await
+    {
+        return Ok(internal_error(e));
+    }
+    let token: Option<String> = match rb
+        .query_decode(
+            "SELECT token FROM achievement_share WHERE user_achievement_id = ?",
+            vec![rbs::Value::String(user_achievement_id.clone())],
+        )
+        .await
+    {
+        Ok(token) => token,
+        Err(e) => return Ok(internal_error(e)),
+    };
+    let Some(token) = token else {
+        return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, "產生分享連結失敗"));
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(ShareLink {
+            share_url: share_url(&http_req, &token),
+            user_achievement_id,
+            token,
+        }),
+        message: "成就分享連結已產生".to_string(),
+    }))
+}
+
+/// 撤銷分享連結
+pub async fn revoke_share(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, achievement_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let result = rb
+        .exec(
+            "DELETE FROM achievement_share
+             WHERE user_id = ? AND user_achievement_id IN (
+                 SELECT id FROM user_achievement WHERE user_id = ? AND achievement_id = ?
+             )",
+            vec![
+                rbs::Value::String(user_id.clone()),
+                rbs::Value::String(user_id),
+                rbs::Value::String(achievement_id),
+            ],
+        )
+        .await;
+    match result {
+        Ok(result) if result.rows_affected > 0 => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "成就分享連結已撤銷".to_string(),
+        })),
+        Ok(_) => Ok(error_response(StatusCode::NOT_FOUND, "此成就沒有分享連結")),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+/// 公開 API：憑 token 取得成就卡片內容（不需登入）
+pub async fn get_shared_achievement(
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<ShareQuery>,
+) -> Result<HttpResponse> {
+    let user_achievement_id = path.into_inner();
+    let Some(token) = query.into_inner().token.filter(|t| !t.is_empty()) else {
+        return Ok(not_found());
+    };
+    match load_shared(
+        rb.get_ref(),
+        "s.user_achievement_id = ? AND s.token = ?",
+        vec![rbs::Value::String(user_achievement_id), rbs::Value::String(token)],
+    )
+    .await
+    {
+        Ok(Some(card)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(card),
+            message: "獲取分享成就成功".to_string(),
+        })),
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+fn render_share_page(card: &SharedAchievement, url: &str) -> String {
+    let title = escape_html(&format!("{} 解鎖了成就「{}」", card.user_display_name, card.name));
+    let description = escape_html(&format!(
+        "Lv.{} · {}",
+        card.level,
+        card.description.as_deref().unwrap_or("在 LifeUp 持續成長中")
+    ));
+    let icon = escape_html(card.icon.as_deref().unwrap_or("🏆"));
+    let url = escape_html(url);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-TW">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<meta property="og:type" content="website">
+<meta property="og:site_name" content="LifeUp">
+<meta property="og:title" content="{title}">
+<meta property="og:description" content="{description}">
+<meta property="og:url" content="{url}">
+<meta name="twitter:card" content="summary">
+</head>
+<body>
+<main style="font-family: sans-serif; text-align: center; padding: 48px 16px;">
+<div style="font-size: 64px;">{icon}</div>
+<h1>{title}</h1>
+<p>{description}</p>
+</main>
+</body>
+</html>
+"#
+    )
+}
+
+/// 公開分享頁：帶 OpenGraph 標籤的 HTML
+pub async fn share_page(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let token = path.into_inner();
+    match load_shared(rb.get_ref(), "s.token = ?", vec![rbs::Value::String(token.clone())]).await {
+        Ok(Some(card)) => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(render_share_page(&card, &share_url(&http_req, &token)))),
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_share_lifecycle() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "share_owner").await;
+        let other = test_utils::create_user(&app, "share_other").await;
+        rb.exec(
+            "INSERT INTO achievement (id, name, description, icon, requirement_type) VALUES ('ach-share', '<早起>鳥', '連續早起 7 天', '🌅', 'consecutive_days')",
+            vec![],
+        )
+        .await
+        .unwrap();
+        let share_uri = format!("/api/users/{}/achievements/ach-share/share", user.id);
+
+        // 未解鎖不能分享
+        let req = actix_web::test::TestRequest::post().uri(&share_uri).insert_header(user.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 404);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/users/{}/achievements/ach-share/unlock", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 201);
+
+        let req = actix_web::test::TestRequest::post().uri(&share_uri).insert_header(other.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 403);
+
+        let req = actix_web::test::TestRequest::post().uri(&share_uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let token = body["data"]["token"].as_str().unwrap().to_string();
+        let user_achievement_id = body["data"]["user_achievement_id"].as_str().unwrap().to_string();
+        assert!(body["data"]["share_url"].as_str().unwrap().ends_with(&format!("/share/a/{}", token)));
+
+        // 重複產生回傳同一個 token
+        let req = actix_web::test::TestRequest::post().uri(&share_uri).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["token"], token.as_str());
+
+        // 公開 API 不需登入，但需要正確的 token
+        let public_uri = format!("/api/achievements/share/{}", user_achievement_id);
+        let req = actix_web::test::TestRequest::get().uri(&format!("{}?token={}", public_uri, token)).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["name"], "<早起>鳥");
+        assert_eq!(body["data"]["user_display_name"], "share_owner");
+        assert_eq!(body["data"]["level"], 1);
+        assert!(body["data"]["unlocked_at"].is_string());
+        let req = actix_web::test::TestRequest::get().uri(&format!("{}?token=wrong", public_uri)).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 404);
+
+        let req = actix_web::test::TestRequest::get().uri(&format!("/share/a/{}", token)).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let html = String::from_utf8(actix_web::test::read_body(resp).await.to_vec()).unwrap();
+        assert!(html.contains("og:title"));
+        assert!(html.contains("&lt;早起&gt;鳥"));
+        assert!(!html.contains("<早起>"));
+
+        // 撤銷後連結失效
+        let req = actix_web::test::TestRequest::delete().uri(&share_uri).insert_header(user.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let req = actix_web::test::TestRequest::get().uri(&format!("{}?token={}", public_uri, token)).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 404);
+        let req = actix_web::test::TestRequest::get().uri(&format!("/share/a/{}", token)).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 404);
+    }
+}
@@ -0,0 +1,720 @@
+// 每位使用者的 AI 使用額度：依類別計算每日次數（使用者時區的日期），超過時回傳 429；
+// 同時記錄每次 AI 請求實際使用的模型（ai_usage_log）
+
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::body::EitherBody;
+use actix_web::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::Method;
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use futures::future::LocalBoxFuture;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_service::AIServedModel;
+use crate::ai_tasks::ApiResponse;
+use crate::config::AiQuotaConfig;
+
+/// 回應標頭：本次請求所屬類別今日剩餘次數
+pub const REMAINING_HEADER: &str = "x-ai-quota-remaining";
+/// 回應標頭：額度重置時間（RFC 3339）
+pub const RESET_HEADER: &str = "x-ai-quota-reset";
+/// 回應標頭：實際處理請求的 AI 模型
+pub const MODEL_HEADER: &str = "x-ai-model";
+
+/// 計算額度的 AI 功能類別
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiQuotaCategory {
+    TaskGeneration,
+    CareerGeneration,
+    AchievementGeneration,
+    Chat,
+    PersonalityCompare,
+}
+
+impl AiQuotaCategory {
+    pub const ALL: [AiQuotaCategory; 5] = [
+        AiQuotaCategory::TaskGeneration,
+        AiQuotaCategory::CareerGeneration,
+        AiQuotaCategory::AchievementGeneration,
+        AiQuotaCategory::Chat,
+        AiQuotaCategory::PersonalityCompare,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiQuotaCategory::TaskGeneration => "task_generation",
+            AiQuotaCategory::CareerGeneration => "career_generation",
+            AiQuotaCategory::AchievementGeneration => "achievement_generation",
+            AiQuotaCategory::Chat => "chat",
+            AiQuotaCategory::PersonalityCompare => "personality_compare",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Option<AiQuotaCategory> {
+        AiQuotaCategory::ALL.into_iter().find(|c| c.as_str() == value)
+    }
+
+    fn default_limit(&self, config: &AiQuotaConfig) -> i32 {
+        match self {
+            AiQuotaCategory::TaskGeneration => config.task_generation,
+            AiQuotaCategory::CareerGeneration => config.career_generation,
+            AiQuotaCategory::AchievementGeneration => config.achievement_generation,
+            AiQuotaCategory::Chat => config.chat,
+            AiQuotaCategory::PersonalityCompare => config.personality_compare,
+        }
+    }
+}
+
+/// 判斷請求是否呼叫 AI，以及屬於哪個額度類別
+pub fn classify(method: &Method, path: &str) -> Option<AiQuotaCategory> {
+    if method != Method::POST {
+        return None;
+    }
+    let path = path.trim_end_matches('/');
+    match path {
+        "/api/tasks/generate"
+        | "/api/tasks/generate-json"
+        | "/api/tasks/generate-daily-task-json"
+        | "/api/tasks/generate-from-chat"
+        | "/api/tasks/generate-with-expert"
+        | "/api/tasks/match-expert"
+        | "/api/tasks/expert-analysis"
+        | "/api/tasks/generate-subtasks"
+        | "/api/tasks/classify-intent"
+        | "/api/tasks/generate-skill-tags" => Some(AiQuotaCategory::TaskGeneration),
+        "/api/career/generate-tasks" | "/api/career/generate-tasks-progressive" => {
+            Some(AiQuotaCategory::CareerGeneration)
+        }
+        "/api/achievements/generate" => Some(AiQuotaCategory::AchievementGeneration),
+        "/api/chat/chatgpt" | "/api/chat/personality" | "/api/chat/test-personality" => Some(AiQuotaCategory::Chat),
+        // 一次呼叫多個個性，成本是一般聊天的數倍，另外計算額度
+        "/api/chat/compare-personalities" => Some(AiQuotaCategory::PersonalityCompare),
+        _ if path.starts_with("/api/tasks/") && path.ends_with("/comments/ask-coach") => Some(AiQuotaCategory::Chat),
+        _ if path.starts_with("/api/users/") && path.ends_with("/memories/extract") => Some(AiQuotaCategory::Chat),
+        _ if path.starts_with("/api/achievements/generate-from-tasks/")
+            || (path.starts_with("/api/career/mainlines/") && path.ends_with("/generate-achievements")) =>
+        {
+            Some(AiQuotaCategory::AchievementGeneration)
+        }
+        _ => None,
+    }
+}
+
+static AI_QUOTA: OnceLock<AiQuotaConfig> = OnceLock::new();
+
+fn config() -> &'static AiQuotaConfig {
+    AI_QUOTA.get_or_init(AiQuotaConfig::default)
+}
+
+/// 啟動時套用設定
+pub fn init(config: AiQuotaConfig) {
+    log::info!(
+        "AI 每日額度: 任務生成 {} / 職業生成 {} / 成就生成 {} / 聊天 {} / 個性比較 {}（-1 表示不限制）",
+        config.task_generation,
+        config.career_generation,
+        config.achievement_generation,
+        config.chat,
+        config.personality_compare
+    );
+    if AI_QUOTA.set(config).is_err() {
+        log::warn!("AI 額度已初始化，忽略重複設定");
+    }
+}
+
+/// 使用者時區下一個午夜（額度重置時間）
+pub fn next_reset_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = crate::local_date::local_date(now).succ_opt().unwrap_or(NaiveDate::MAX);
+    crate::local_date::default_timezone()
+        .from_local_datetime(&tomorrow.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn usage_date(now: DateTime<Utc>) -> String {
+    crate::local_date::local_date(now).format("%Y-%m-%d").to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct LimitRow {
+    daily_limit: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageRow {
+    category: String,
+    request_count: i32,
+}
+
+/// 使用者在該類別的每日上限（管理員調整值優先，否則使用設定值；負數表示不限制）
+pub async fn daily_limit(rb: &RBatis, user_id: &str, category: AiQuotaCategory) -> rbatis::Result<i32> {
+    let row: Option<LimitRow> = rb
+        .query_decode(
+            "SELECT daily_limit FROM ai_quota_override WHERE user_id = ? AND category = ?",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(category.as_str().to_string())],
+        )
+        .await?;
+    Ok(row.map(|r| r.daily_limit).unwrap_or_else(|| category.default_limit(config())))
+}
+
+/// 額度檢查結果
+#[derive(Debug, PartialEq)]
+pub enum QuotaDecision {
+    // remaining 為 None 表示不限制
+    Allowed { remaining: Option<i32> },
+    Exceeded { limit: i32 },
+}
+
+/// 使用一次額度；已達上限時不計數
+pub async fn try_consume(
+    rb: &RBatis,
+    user_id: &str,
+    category: AiQuotaCategory,
+    now: DateTime<Utc>,
+) -> rbatis::Result<QuotaDecision> {
+    let limit = daily_limit(rb, user_id, category).await?;
+    if limit == 0 {
+        return Ok(QuotaDecision::Exceeded { limit });
+    }
+    let date = usage_date(now);
+    let args = vec![
+        rbs::Value::String(user_id.to_string()),
+        rbs::Value::String(date.clone()),
+        rbs::Value::String(category.as_str().to_string()),
+        rbs::Value::String(now.to_rfc3339()),
+    ];
+
+    // 以單一 UPSERT 遞增，上限檢查寫在 WHERE 中，並發請求不會超用
+    let result = if limit < 0 {
+        rb.exec(
+            "INSERT INTO ai_usage_daily (user_id, usage_date, category, request_count, updated_at) VALUES (?, ?, ?, 1, ?) \
+             ON CONFLICT(user_id, usage_date, category) DO UPDATE SET request_count = request_count + 1, updated_at = excluded.updated_at",
+            args,
+        )
+        .await?
+    } else {
+        let mut args = args;
+        args.push(rbs::Value::I32(limit));
+        rb.exec(
+            "INSERT INTO ai_usage_daily (user_id, usage_date, category, request_count, updated_at) VALUES (?, ?, ?, 1, ?) \
+             ON CONFLICT(user_id, usage_date, category) DO UPDATE SET request_count = request_count + 1, updated_at = excluded.updated_at \
+             WHERE ai_usage_daily.request_count < ?",
+            args,
+        )
+        .await?
+    };
+    if result.rows_affected == 0 {
+        return Ok(QuotaDecision::Exceeded { limit });
+    }
+    if limit < 0 {
+        return Ok(QuotaDecision::Allowed { remaining: None });
+    }
+
+    let used = used_today(rb, user_id, &date).await?;
+    let used = used.iter().find(|r| r.category == category.as_str()).map(|r| r.request_count).unwrap_or(0);
+    Ok(QuotaDecision::Allowed { remaining: Some((limit - used).max(0)) })
+}
+
+/// 記錄一次 AI 請求（含實際使用的模型與是否為單次覆寫）；寫入失敗只記錄警告
+pub async fn record_usage(
+    rb: &RBatis,
+    user_id: &str,
+    category: AiQuotaCategory,
+    endpoint: &str,
+    served: Option<&AIServedModel>,
+    response_status: u16,
+    now: DateTime<Utc>,
+) {
+    let result = rb
+        .exec(
+            "INSERT INTO ai_usage_log (id, user_id, category, endpoint, provider, model, is_override, response_status, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            vec![
+                rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(category.as_str().to_string()),
+                rbs::Value::String(endpoint.to_string()),
+                served.map(|s| rbs::Value::String(s.provider.clone())).unwrap_or(rbs::Value::Null),
+                served.map(|s| rbs::Value::String(s.model.clone())).unwrap_or(rbs::Value::Null),
+                rbs::Value::I32(served.map(|s| s.overridden as i32).unwrap_or(0)),
+                rbs::Value::I32(response_status as i32),
+                rbs::Value::String(now.to_rfc3339()),
+            ],
+        )
+        .await;
+    if let Err(e) = result {
+        log::warn!("寫入 AI 使用紀錄失敗 (user_id: {}): {}", user_id, e);
+    }
+}
+
+/// 本次請求要記錄的模型：混合路由時每個實際呼叫的服務提供者/模型各記一筆，否則為路由解析出的模型
+fn served_models(resolved: Option<AIServedModel>, dispatched: Vec<AIServedModel>) -> Vec<AIServedModel> {
+    if dispatched.is_empty() {
+        return resolved.into_iter().collect();
+    }
+    let overridden = resolved.map(|r| r.overridden).unwrap_or(false);
+    let mut served: Vec<AIServedModel> = Vec::new();
+    for model in dispatched {
+        let model = AIServedModel { overridden, ..model };
+        if !served.contains(&model) {
+            served.push(model);
+        }
+    }
+    served
+}
+
+/// 請求失敗時退回額度（AI 呼叫失敗不應扣次數）
+pub async fn refund(rb: &RBatis, user_id: &str, category: AiQuotaCategory, now: DateTime<Utc>) {
+    let result = rb
+        .exec(
+            "UPDATE ai_usage_daily SET request_count = request_count - 1 WHERE user_id = ? AND usage_date = ? AND category = ? AND request_count > 0",
+            vec![
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(usage_date(now)),
+                rbs::Value::String(category.as_str().to_string()),
+            ],
+        )
+        .await;
+    if let Err(e) = result {
+        log::warn!("退回 AI 額度失敗 (user_id: {}): {}", user_id, e);
+    }
+}
+
+async fn used_today(rb: &RBatis, user_id: &str, date: &str) -> rbatis::Result<Vec<UsageRow>> {
+    rb.query_decode(
+        "SELECT category, request_count FROM ai_usage_daily WHERE user_id = ? AND usage_date = ?",
+        vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(date.to_string())],
+    )
+    .await
+}
+
+#[derive(Debug, Serialize)]
+pub struct AiQuotaStatus {
+    pub category: String,
+    // null 表示不限制
+    pub daily_limit: Option<i32>,
+    pub used: i32,
+    pub remaining: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AiQuotaResponse {
+    pub user_id: String,
+    pub usage_date: String,
+    pub resets_at: String,
+    // 管理員不受額度限制
+    pub exempt: bool,
+    pub quotas: Vec<AiQuotaStatus>,
+}
+
+/// 使用者今日各類別的額度與使用量
+pub async fn quota_status(rb: &RBatis, user_id: &str, now: DateTime<Utc>) -> rbatis::Result<Vec<AiQuotaStatus>> {
+    let used = used_today(rb, user_id, &usage_date(now)).await?;
+    let mut statuses = Vec::new();
+    for category in AiQuotaCategory::ALL {
+        let limit = daily_limit(rb, user_id, category).await?;
+        let used = used.iter().find(|r| r.category == category.as_str()).map(|r| r.request_count).unwrap_or(0);
+        let limit = (limit >= 0).then_some(limit);
+        statuses.push(AiQuotaStatus {
+            category: category.as_str().to_string(),
+            daily_limit: limit,
+            used,
+            remaining: limit.map(|l| (l - used).max(0)),
+        });
+    }
+    Ok(statuses)
+}
+
+fn quota_exceeded_response(category: AiQuotaCategory, limit: i32, now: DateTime<Utc>) -> HttpResponse {
+    let resets_at = next_reset_at(now);
+    let retry_after = (resets_at - now).num_seconds().max(1);
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, retry_after.to_string()))
+        .insert_header((REMAINING_HEADER, "0"))
+        .insert_header((RESET_HEADER, resets_at.to_rfc3339()))
+        .json(ApiResponse {
+            success: false,
+            data: Some(serde_json::json!({
+                "category": category.as_str(),
+                "daily_limit": limit,
+                "resets_at": resets_at.to_rfc3339(),
+            })),
+            message: format!(
+                "今日 AI 使用次數已達上限（{} 次），將於 {} 重置",
+                limit,
+                resets_at.with_timezone(&crate::local_date::default_timezone()).format("%Y-%m-%d %H:%M")
+            ),
+        })
+}
+
+// AI 額度中間件：需放在 JwtAuth 之內（依賴 JwtAuth 寫入的 user_id 與 Claims）
+//
+// 只處理 classify 判定為 AI 呼叫的請求；管理員不計額度。每次請求都寫入 ai_usage_log，
+// 處理函數透過 request extensions 提供實際使用的模型（見 ai_tasks::resolve_ai_service）。
+// 回應失敗（非 2xx）時退回額度，資料庫錯誤時放行並記錄錯誤，避免額度系統拖垮 AI 功能。
+pub struct AiQuota;
+
+impl<S, B> Transform<S, ServiceRequest> for AiQuota
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AiQuotaMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AiQuotaMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct AiQuotaMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AiQuotaMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let category = classify(req.method(), &crate::api_version::unversioned_path(req.path()));
+        let user_id = req.extensions().get::<String>().cloned();
+        let rb = req.app_data::<web::Data<RBatis>>().cloned();
+
+        let (Some(category), Some(user_id), Some(rb)) = (category, user_id, rb) else {
+            return Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) });
+        };
+        let is_admin = crate::auth::is_admin_request(req.request());
+        let endpoint = crate::api_version::unversioned_path(req.path()).into_owned();
+
+        Box::pin(async move {
+            let now = Utc::now();
+            // 管理員與額度檢查失敗時不扣次數（charged 為 None）
+            let charged = if is_admin {
+                None
+            } else {
+                match try_consume(rb.get_ref(), &user_id, category, now).await {
+                    Ok(QuotaDecision::Allowed { remaining }) => Some(remaining),
+                    Ok(QuotaDecision::Exceeded { limit }) => {
+                        log::warn!("AI 額度已用完 (user_id: {}, category: {}, limit: {})", user_id, category.as_str(), limit);
+                        let response = quota_exceeded_response(category, limit, now);
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                    Err(e) => {
+                        log::error!("檢查 AI 額度失敗，放行本次請求: {}", e);
+                        None
+                    }
+                }
+            };
+
+            let (res, dispatched) = crate::ai_service::track_dispatches(service.call(req)).await;
+            let mut res = res?;
+            let status = res.status();
+            let resolved = res.request().extensions().get::<AIServedModel>().cloned();
+            let served = served_models(resolved, dispatched);
+            if let Some(model) = served.last().and_then(|s| HeaderValue::from_str(&s.model).ok()) {
+                res.headers_mut().insert(HeaderName::from_static(MODEL_HEADER), model);
+            }
+            if served.is_empty() {
+                record_usage(rb.get_ref(), &user_id, category, &endpoint, None, status.as_u16(), now).await;
+            }
+            for model in &served {
+                record_usage(rb.get_ref(), &user_id, category, &endpoint, Some(model), status.as_u16(), now).await;
+            }
+
+            if let Some(remaining) = charged {
+                if !status.is_success() {
+                    refund(rb.get_ref(), &user_id, category, now).await;
+                    return Ok(res.map_into_left_body());
+                }
+                if let Some(remaining) = remaining {
+                    res.headers_mut().insert(HeaderName::from_static(REMAINING_HEADER), HeaderValue::from(remaining));
+                }
+                if let Ok(reset) = HeaderValue::from_str(&next_reset_at(now).to_rfc3339()) {
+                    res.headers_mut().insert(HeaderName::from_static(RESET_HEADER), reset);
+                }
+            }
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+// 查詢使用者今日的 AI 額度（本人或管理員）
+pub async fn get_ai_quota(
+    rb: web::Data<RBatis>,
+    http_req: HttpRequest,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_admin = crate::auth::is_admin_request(&http_req);
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !is_admin {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "無權限查看此使用者的 AI 額度".to_string(),
+        }));
+    }
+
+    let now = Utc::now();
+    match quota_status(rb.get_ref(), &user_id, now).await {
+        Ok(quotas) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(AiQuotaResponse {
+                user_id,
+                usage_date: usage_date(now),
+                resets_at: next_reset_at(now).to_rfc3339(),
+                exempt: is_self && is_admin,
+                quotas,
+            }),
+            message: "獲取 AI 額度成功".to_string(),
+        })),
+        Err(e) => {
+            log::error!("查詢 AI 額度失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢 AI 額度失敗: {}", e),
+            }))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAiQuotaRequest {
+    pub category: String,
+    // null 表示恢復預設值；-1 表示不限制
+    pub daily_limit: Option<i32>,
+}
+
+// 管理員調整使用者的 AI 每日上限
+pub async fn update_ai_quota(
+    rb: web::Data<RBatis>,
+    http_req: HttpRequest,
+    path: web::Path<String>,
+    req: web::Json<UpdateAiQuotaRequest>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+
+    let user_id = path.into_inner();
+    let Some(category) = AiQuotaCategory::from_string(&req.category) else {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("未知的 AI 額度類別: {}", req.category),
+        }));
+    };
+    if req.daily_limit.is_some_and(|limit| limit < -1) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "daily_limit 必須大於等於 0，或為 -1（不限制）".to_string(),
+        }));
+    }
+
+    let result = match req.daily_limit {
+        Some(limit) => {
+            rb.exec(
+                "INSERT INTO ai_quota_override (user_id, category, daily_limit, updated_at) VALUES (?, ?, ?, ?) \
+                 ON CONFLICT(user_id, category) DO UPDATE SET daily_limit = excluded.daily_limit, updated_at = excluded.updated_at",
+                vec![
+                    rbs::Value::String(user_id.clone()),
+                    rbs::Value::String(category.as_str().to_string()),
+                    rbs::Value::I32(limit),
+                    rbs::Value::String(Utc::now().to_rfc3339()),
+                ],
+            )
+            .await
+        }
+        None => {
+            rb.exec(
+                "DELETE FROM ai_quota_override WHERE user_id = ? AND category = ?",
+                vec![rbs::Value::String(user_id.clone()), rbs::Value::String(category.as_str().to_string())],
+            )
+            .await
+        }
+    };
+    if let Err(e) = result {
+        log::error!("更新 AI 額度失敗: {}", e);
+        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("更新 AI 額度失敗: {}", e),
+        }));
+    }
+
+    crate::audit_log::record(
+        rb.get_ref(),
+        crate::audit_log::ACTION_AI_QUOTA_UPDATED,
+        Some(&user_id),
+        None,
+        serde_json::json!({
+            "category": category.as_str(),
+            "daily_limit": req.daily_limit,
+            "admin_user_id": crate::auth::current_user_id(&http_req),
+        }),
+    )
+    .await;
+
+    match quota_status(rb.get_ref(), &user_id, Utc::now()).await {
+        Ok(quotas) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(quotas),
+            message: "AI 額度已更新".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("查詢 AI 額度失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use chrono::Duration;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+
+    #[test]
+    fn test_classify_ai_routes() {
+        assert_eq!(classify(&Method::POST, "/api/tasks/generate-json"), Some(AiQuotaCategory::TaskGeneration));
+        assert_eq!(classify(&Method::POST, "/api/career/generate-tasks"), Some(AiQuotaCategory::CareerGeneration));
+        assert_eq!(
+            classify(&Method::POST, "/api/career/mainlines/m1/generate-achievements"),
+            Some(AiQuotaCategory::AchievementGeneration)
+        );
+        assert_eq!(classify(&Method::POST, "/api/chat/personality"), Some(AiQuotaCategory::Chat));
+        assert_eq!(classify(&Method::POST, "/api/tasks/t1/comments/ask-coach"), Some(AiQuotaCategory::Chat));
+        assert_eq!(classify(&Method::POST, "/api/users/u1/memories/extract"), Some(AiQuotaCategory::Chat));
+        assert_eq!(classify(&Method::POST, "/api/users/u1/memories"), None);
+        assert_eq!(
+            classify(&Method::POST, "/api/chat/compare-personalities"),
+            Some(AiQuotaCategory::PersonalityCompare)
+        );
+        assert_eq!(classify(&Method::POST, "/api/tasks/t1/comments"), None);
+        assert_eq!(classify(&Method::POST, "/api/tasks"), None);
+        assert_eq!(classify(&Method::GET, "/api/tasks/generate-json"), None);
+        // 模擬回覆的聊天不呼叫 AI
+        assert_eq!(classify(&Method::POST, "/api/chat/send"), None);
+    }
+
+    #[test]
+    fn test_reset_is_next_local_midnight() {
+        // 台灣 2026-03-01 23:30 → 台灣 3/2 00:00（UTC 3/1 16:00）
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 15, 30, 0).unwrap();
+        assert_eq!(next_reset_at(now), Utc.with_ymd_and_hms(2026, 3, 1, 16, 0, 0).unwrap());
+        assert_eq!(next_reset_at(now) - now, Duration::minutes(30));
+    }
+
+    #[actix_web::test]
+    async fn test_quota_blocks_after_limit_and_resets_next_day() {
+        let rb = test_utils::setup_db().await;
+        let now = Utc::now();
+        rb.exec(
+            "INSERT INTO ai_quota_override (user_id, category, daily_limit) VALUES ('u1', 'chat', 2)",
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        let chat = AiQuotaCategory::Chat;
+        assert_eq!(try_consume(&rb, "u1", chat, now).await.unwrap(), QuotaDecision::Allowed { remaining: Some(1) });
+        assert_eq!(try_consume(&rb, "u1", chat, now).await.unwrap(), QuotaDecision::Allowed { remaining: Some(0) });
+        assert_eq!(try_consume(&rb, "u1", chat, now).await.unwrap(), QuotaDecision::Exceeded { limit: 2 });
+        // 其他類別與其他使用者不受影響
+        assert!(matches!(
+            try_consume(&rb, "u1", AiQuotaCategory::TaskGeneration, now).await.unwrap(),
+            QuotaDecision::Allowed { .. }
+        ));
+        assert!(matches!(try_consume(&rb, "u2", chat, now).await.unwrap(), QuotaDecision::Allowed { .. }));
+
+        // 退回後可再使用一次
+        refund(&rb, "u1", chat, now).await;
+        assert_eq!(try_consume(&rb, "u1", chat, now).await.unwrap(), QuotaDecision::Allowed { remaining: Some(0) });
+
+        let tomorrow = next_reset_at(now);
+        assert_eq!(try_consume(&rb, "u1", chat, tomorrow).await.unwrap(), QuotaDecision::Allowed { remaining: Some(1) });
+    }
+
+    #[actix_web::test]
+    async fn test_ai_route_returns_429_with_quota_headers() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(MockAIService::default())).await;
+        let user = test_utils::create_user(&app, "heavy").await;
+        rb.exec(
+            "INSERT INTO ai_quota_override (user_id, category, daily_limit) VALUES (?, 'task_generation', 1)",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        let generate = || {
+            actix_web::test::TestRequest::post()
+                .uri("/api/tasks/generate-json")
+                .insert_header(user.auth())
+                .set_json(json!({"description": "我想養成閱讀習慣"}))
+                .to_request()
+        };
+        let resp = actix_web::test::call_service(&app, generate()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(REMAINING_HEADER).unwrap(), "0");
+
+        let resp = actix_web::test::call_service(&app, generate()).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().get(RETRY_AFTER).is_some());
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["data"]["category"], "task_generation");
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/ai-quota", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let tasks = &body["data"]["quotas"][0];
+        assert_eq!(tasks["category"], "task_generation");
+        assert_eq!(tasks["used"], 1);
+        assert_eq!(tasks["remaining"], 0);
+
+        // 其他使用者不能查看，非管理員不能調整額度
+        let other = test_utils::create_user(&app, "curious").await;
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/ai-quota", user.id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("/api/admin/users/{}/ai-quota", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"category": "task_generation", "daily_limit": 100}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+    }
+}
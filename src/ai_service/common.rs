@@ -4,10 +4,11 @@ use chrono::Utc;
 use crate::models::AchievementRequirementType;
 use crate::behavior_analytics::UserBehaviorSummary;
 use crate::ai_tasks::AnalysisDirection;
+use crate::task_types::TaskType;
 use std::collections::HashMap;
 
 // 模型等級枚舉
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ModelTier {
     Small,      // 超輕量（簡單文字處理、格式轉換、基礎驗證）
     Fast,       // 快速回應（簡單對話、快速回覆、任務預覽）
@@ -16,6 +17,26 @@ pub enum ModelTier {
     Background, // 背景處理（大量數據分析、批次處理、深度研究）
 }
 
+/// AI 供應商以安全政策擋下內容時回傳的錯誤，路由據此告知使用者內容被過濾而非一般失敗
+#[derive(Debug, Clone)]
+pub struct AIContentFilteredError {
+    pub provider: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for AIContentFilteredError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} 因安全政策過濾了內容 ({})", self.provider, self.reason)
+    }
+}
+
+impl std::error::Error for AIContentFilteredError {}
+
+// 判斷錯誤是否為內容安全過濾
+pub fn is_content_filtered(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<AIContentFilteredError>().is_some()
+}
+
 // 格式化 AI 輸出為單行日誌
 pub fn format_ai_output(text: &str) -> String {
     text.replace("\\n", " ")
@@ -28,6 +49,47 @@ pub fn format_ai_output(text: &str) -> String {
         .join(" ")
 }
 
+/// 不支援看圖的模型收到圖片時回傳的錯誤訊息
+pub const VISION_UNSUPPORTED: &str = "此模型不支援圖片";
+
+/// 聊天附帶的圖片（已驗證大小與類型）
+#[derive(Debug, Clone)]
+pub struct ChatImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ChatImage {
+    /// 以 data URL 傳給多模態模型
+    pub fn data_url(&self) -> String {
+        use base64::Engine;
+        format!("data:{};base64,{}", self.mime_type, base64::engine::general_purpose::STANDARD.encode(&self.data))
+    }
+}
+
+/// OpenAI 相容 API 的看圖對話訊息：歷史對話、系統提示詞，最後是文字與圖片組成的使用者訊息
+pub fn build_vision_messages(
+    system_prompt: &str,
+    history: &[(String, String)],
+    message: &str,
+    image_url: &str,
+) -> Vec<serde_json::Value> {
+    let mut messages = vec![];
+    for (user_msg, assistant_msg) in history {
+        messages.push(serde_json::json!({"role": "user", "content": user_msg}));
+        messages.push(serde_json::json!({"role": "assistant", "content": assistant_msg}));
+    }
+    messages.push(serde_json::json!({"role": "system", "content": system_prompt}));
+    messages.push(serde_json::json!({
+        "role": "user",
+        "content": [
+            {"type": "text", "text": message},
+            {"type": "image_url", "image_url": {"url": image_url}}
+        ]
+    }));
+    messages
+}
+
 // 專家信息結構
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Expert {
@@ -60,6 +122,22 @@ pub struct AIGeneratedTask {
     pub start_date: Option<String>,
     pub end_date: Option<String>,
     pub completion_target: Option<f64>,
+    #[serde(default)]
+    pub difficulty_adjustment: Option<i32>,   // 相對模板難度的調整（-1/0/+1）
+    #[serde(default)]
+    pub adjustment_reason: Option<String>,    // 調整原因（給前端顯示）
+}
+
+// 近期完成紀錄摘要（用於每日任務生成時調整難度）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompletionHistorySummary {
+    pub window_days: i64,
+    pub total_tasks: i32,
+    pub completed_tasks: i32,
+    pub completion_ratio: f64,
+    pub current_streak: i32,
+    pub avg_completed_difficulty: Option<f64>,
+    pub template_difficulty: Option<i32>,
 }
 
 // AI 生成的任務計劃（包含主任務和子任務）
@@ -135,7 +213,7 @@ impl AIGeneratedTask {
         Self {
             title: self.title.or(Some("未命名任務".to_string())),
             description: self.description,
-            task_type: self.task_type.or(Some("side".to_string())),
+            task_type: self.task_type.or_else(|| Some(TaskType::Side.to_string())),
             priority: self.priority.or(Some(1)),
             difficulty: self.difficulty.or(Some(2)),
             experience: self.experience.or(Some(30)),
@@ -145,6 +223,8 @@ impl AIGeneratedTask {
             start_date: self.start_date,
             end_date: self.end_date,
             completion_target: self.completion_target,
+            difficulty_adjustment: self.difficulty_adjustment,
+            adjustment_reason: self.adjustment_reason,
         }
     }
 
@@ -230,6 +310,21 @@ pub fn get_expert_database() -> Vec<Expert> {
     ]
 }
 
+/// 專家匹配失敗時使用的通用專家（聊天仍可繼續，只是沒有特定領域）
+pub fn fallback_expert_match() -> ExpertMatch {
+    let expert = Expert {
+        name: "通用生活教練".to_string(),
+        description: "陪伴使用者規劃生活、建立習慣並持續成長的教練".to_string(),
+        expertise_areas: vec!["生活規劃".to_string(), "習慣養成".to_string(), "個人成長".to_string()],
+        emoji: "🧭".to_string(),
+    };
+    ExpertMatch {
+        ai_expert_name: expert.name.clone(),
+        ai_expert_description: expert.description.clone(),
+        expert,
+    }
+}
+
 // 根据用户行为摘要构建成就生成的 prompt
 pub fn build_achievement_prompt_from_summary(summary: &UserBehaviorSummary) -> String {
     // 格式化分类统计
@@ -297,6 +392,7 @@ pub fn build_achievement_prompt_from_summary(summary: &UserBehaviorSummary) -> S
 - 考慮用戶的優勢領域（完成率高的分類）和潛力領域
 - 避免與現有成就重複
 - 如果有明顯的連續記錄，可以考慮相關的持續性成就
+- 成就名稱與描述的語言：{language_directive}
 
 **成就分類：**
 - task_mastery: 任務精通類
@@ -317,6 +413,8 @@ pub fn build_achievement_prompt_from_summary(summary: &UserBehaviorSummary) -> S
 - social_attribute: 社交力屬性達成
 - focus_attribute: 專注力屬性達成
 - adaptability_attribute: 適應力屬性達成
+- focus_session_complete: 完成專注時段次數
+- daily_quest_complete: 完成每日三任務次數
 
 **經驗值獎勵計算：**
 - 基於難度：簡單成就 50-100，中等 100-200，困難 200-500
@@ -347,6 +445,7 @@ pub fn build_achievement_prompt_from_summary(summary: &UserBehaviorSummary) -> S
         recent_cancellations = if recent_cancellations.is_empty() { "  （暫無數據）".to_string() } else { recent_cancellations.join("\n") },
         milestones = if milestones.is_empty() { "  （暫無數據）".to_string() } else { milestones.join("\n") },
         achievements = if summary.unlocked_achievements.is_empty() { "（暫無）".to_string() } else { summary.unlocked_achievements.join("、") },
+        language_directive = crate::language::directive(),
     )
 }
 
@@ -360,15 +459,19 @@ pub fn validate_generated_task(task: &AIGeneratedTask) -> Result<AIGeneratedTask
         corrected_task.title = Some("未命名任務".to_string());
     }
 
-    // 修正任務類型
-    if let Some(task_type) = &corrected_task.task_type {
-        if !["main", "side", "challenge", "daily"].contains(&task_type.as_str()) {
-            log::warn!("無效的任務類型: {}，設為預設值 'side'", task_type);
-            corrected_task.task_type = Some("side".to_string());
+    // 修正任務類型（AI 只能產生 main / side / challenge / daily）
+    match corrected_task.task_type.as_deref().map(str::parse::<TaskType>) {
+        Some(Ok(task_type)) if task_type.is_ai_generatable() => {
+            corrected_task.task_type = Some(task_type.to_string());
+        }
+        Some(_) => {
+            log::warn!("無效的任務類型: {:?}，設為預設值 'side'", corrected_task.task_type);
+            corrected_task.task_type = Some(TaskType::Side.to_string());
+        }
+        None => {
+            log::warn!("任務類型為空，設為預設值 'side'");
+            corrected_task.task_type = Some(TaskType::Side.to_string());
         }
-    } else {
-        log::warn!("任務類型為空，設為預設值 'side'");
-        corrected_task.task_type = Some("side".to_string());
     }
 
     // 修正優先級（clamp 到 0-2）
@@ -449,6 +552,20 @@ pub fn validate_generated_task(task: &AIGeneratedTask) -> Result<AIGeneratedTask
     Ok(corrected_task)
 }
 
+// 將 AI 建議的難度調整限制在模板難度 ±1 之內，並套用到任務難度
+pub fn clamp_difficulty_adjustment(task: &mut AIGeneratedTask, template_difficulty: Option<i32>) {
+    let adjustment = task.difficulty_adjustment.unwrap_or(0);
+    let clamped = adjustment.clamp(-1, 1);
+    if clamped != adjustment {
+        log::warn!("難度調整 {} 超出範圍，調整為 {}", adjustment, clamped);
+    }
+    task.difficulty_adjustment = Some(clamped);
+
+    if let Some(base) = template_difficulty {
+        task.difficulty = Some((base + clamped).clamp(1, 5));
+    }
+}
+
 // 驗證生成的成就
 pub fn validate_generated_achievement(achievement: &AIGeneratedAchievement) -> Result<()> {
     // 驗證成就分類
@@ -472,8 +589,9 @@ pub fn validate_generated_achievement(achievement: &AIGeneratedAchievement) -> R
     }
 
     // 驗證經驗值獎勵
-    if achievement.experience_reward < 50 || achievement.experience_reward > 500 {
-        return Err(anyhow::anyhow!("經驗值獎勵必須在 50-500 之間"));
+    let xp_range = crate::reward_config::current().ai_achievement_xp;
+    if !xp_range.contains(achievement.experience_reward) {
+        return Err(anyhow::anyhow!("經驗值獎勵必須在 {}-{} 之間", xp_range.min, xp_range.max));
     }
 
     // 驗證成就名稱長度
@@ -484,16 +602,18 @@ pub fn validate_generated_achievement(achievement: &AIGeneratedAchievement) -> R
     Ok(())
 }
 
-// 將 AI 生成的任務轉換為資料庫模型
+// 將 AI 生成的任務轉換為資料庫模型（先清理內容，第二個值表示是否有修改）
 pub fn convert_to_task_model(
-    ai_task: AIGeneratedTask,
+    mut ai_task: AIGeneratedTask,
     user_id: String,
-) -> crate::models::Task {
+) -> (crate::models::Task, bool) {
     use uuid::Uuid;
 
     let now = Utc::now();
+    let sanitized = super::sanitize::sanitize_ai_task(&mut ai_task);
+    let challenge_fail_penalty = crate::reward_config::challenge_fail_penalty_for(ai_task.task_type.as_deref());
 
-    crate::models::Task {
+    let task = crate::models::Task {
         id: Some(Uuid::new_v4().to_string()),
         user_id: Some(user_id),
         title: ai_task.title,
@@ -522,21 +642,30 @@ pub fn convert_to_task_model(
         career_mainline_id: None,
         task_category: None,
         attributes: None,
-    }
+        completion_mode: None,
+        version: Some(0),
+        require_proof: Some(0),
+        stake: None,
+        retro_completed: Some(0),
+        requires_confirmation: Some(0),
+        challenge_fail_penalty,
+    };
+    (task, sanitized)
 }
 
-// 將 AI 生成的成就轉換為資料庫模型
+// 將 AI 生成的成就轉換為資料庫模型（先清理內容，第二個值表示是否有修改）
 pub fn convert_to_achievement_model(
-    ai_achievement: AIGeneratedAchievement,
-) -> crate::models::Achievement {
+    mut ai_achievement: AIGeneratedAchievement,
+) -> (crate::models::Achievement, bool) {
     use uuid::Uuid;
 
     let now = Utc::now();
+    let sanitized = super::sanitize::sanitize_ai_achievement(&mut ai_achievement);
 
     // 將字符串轉換為枚舉
     let requirement_type = AchievementRequirementType::from_string(&ai_achievement.requirement_type);
 
-    crate::models::Achievement {
+    let achievement = crate::models::Achievement {
         id: Some(Uuid::new_v4().to_string()),
         name: Some(ai_achievement.name),
         description: ai_achievement.description,
@@ -548,17 +677,20 @@ pub fn convert_to_achievement_model(
         career_mainline_id: None,
         related_task_id: None,
         created_at: Some(now),
-    }
+    };
+    (achievement, sanitized)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_task_generation_prompt(
     user_input: &str,
-    expert_match: &ExpertMatch,
+    expert_match: Option<&ExpertMatch>,
     selected_options: Option<Vec<String>>,
     selected_directions: Option<Vec<AnalysisDirection>>,
     expert_outputs: Option<HashMap<String, String>>,
     skill_label: &str,
     duration_label: &str,
+    completion_history: Option<&CompletionHistorySummary>,
 ) -> String {
     let mut prompt = String::new();
     prompt.push_str(user_input);
@@ -588,10 +720,77 @@ pub fn build_task_generation_prompt(
             }
         }
     }
-    prompt.push_str(&format!(
-        "\n\n請根據以上資訊，並以{} ({}) 的視角，產出符合要求的任務規劃。",
-        expert_match.expert.name, expert_match.expert.description
-    ));
+    if let Some(history) = completion_history {
+        prompt.push_str(&format!(
+            "\n\n使用者近 {} 天完成紀錄：完成 {}/{} 個（完成率 {:.0}%），目前連續 {} 天",
+            history.window_days,
+            history.completed_tasks,
+            history.total_tasks,
+            history.completion_ratio * 100.0,
+            history.current_streak
+        ));
+        if let Some(avg) = history.avg_completed_difficulty {
+            prompt.push_str(&format!("，已完成任務平均難度 {:.1}", avg));
+        }
+        if let Some(base) = history.template_difficulty {
+            prompt.push_str(&format!("，目前任務難度 {}", base));
+        }
+        prompt.push_str(
+            "\n請依此調整難度：連續未完成請降低難度，表現穩定且完成率高請提高難度。\
+             difficulty_adjustment 只能是 -1、0 或 1，並在 adjustment_reason 用一句話說明原因。",
+        );
+    }
+
+    if let Some(expert_match) = expert_match {
+        prompt.push_str(&format!(
+            "\n\n請根據以上資訊，並以{} ({}) 的視角，產出符合要求的任務規劃。",
+            expert_match.expert.name, expert_match.expert.description
+        ));
+    }
 
     prompt
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily_task(difficulty: i32, adjustment: Option<i32>) -> AIGeneratedTask {
+        AIGeneratedTask {
+            title: Some("晨跑 30 分鐘".to_string()),
+            description: None,
+            task_type: Some("daily".to_string()),
+            priority: Some(1),
+            difficulty: Some(difficulty),
+            experience: Some(10),
+            due_date: None,
+            is_recurring: Some(false),
+            recurrence_pattern: None,
+            start_date: None,
+            end_date: None,
+            completion_target: None,
+            difficulty_adjustment: adjustment,
+            adjustment_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_clamp_difficulty_adjustment() {
+        // 超出範圍的調整會被限制在 ±1
+        let mut task = daily_task(5, Some(-3));
+        clamp_difficulty_adjustment(&mut task, Some(3));
+        assert_eq!(task.difficulty_adjustment, Some(-1));
+        assert_eq!(task.difficulty, Some(2));
+
+        // 調整後難度不會超出 1-5
+        let mut task = daily_task(5, Some(1));
+        clamp_difficulty_adjustment(&mut task, Some(5));
+        assert_eq!(task.difficulty, Some(5));
+
+        // 沒有模板難度時只限制調整值
+        let mut task = daily_task(2, None);
+        clamp_difficulty_adjustment(&mut task, None);
+        assert_eq!(task.difficulty_adjustment, Some(0));
+        assert_eq!(task.difficulty, Some(2));
+    }
+}
@@ -0,0 +1,576 @@
+// 混合服務提供者路由：各模型等級可用「provider:model」指定服務提供者
+//
+// 例如 AI_MODEL_SMALL=gemini:gemini-2.0-flash、AI_MODEL_THINK=openrouter:anthropic/claude-3.5-sonnet、
+// AI_MODEL_FAST=ollama:llama3.1。每個被引用的服務提供者只建立一個 HTTP 客戶端，
+// 每次呼叫依該功能使用的等級轉送，並各自擁有斷路器。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rbatis::RBatis;
+
+use crate::config::AIConfig;
+use super::common::{
+    is_content_filtered, AIGeneratedAchievement, AIGeneratedSkillTags, AIGeneratedTask, AIGeneratedTaskPlan,
+    ChatImage, ExpertMatch, ModelTier,
+};
+use super::r#trait::AIService;
+use super::{build_provider_service, normalize_provider, primary_model, provider_api_key, AIServedModel};
+
+const ALL_TIERS: [ModelTier; 5] = [
+    ModelTier::Small,
+    ModelTier::Fast,
+    ModelTier::Normal,
+    ModelTier::Think,
+    ModelTier::Background,
+];
+
+/// 解析模型設定：「provider:model」回傳 (Some(服務提供者), 模型)，一般模型名稱回傳 (None, 模型)
+///
+/// 只有冒號前是已知的服務提供者才視為前綴，避免誤判 `google/gemma-3-4b-it:free` 這類模型名稱。
+pub fn parse_model_spec(spec: &str) -> (Option<&'static str>, &str) {
+    if let Some((prefix, model)) = spec.split_once(':') {
+        if let Some(provider) = normalize_provider(prefix) {
+            return (Some(provider), model.trim());
+        }
+    }
+    (None, spec.trim())
+}
+
+fn tier_spec(config: &AIConfig, tier: ModelTier) -> &str {
+    match tier {
+        ModelTier::Small => &config.model_small,
+        ModelTier::Fast => &config.model_fast,
+        ModelTier::Normal => &config.model_normal,
+        ModelTier::Think => &config.model_think,
+        ModelTier::Background => &config.model_background,
+    }
+}
+
+/// 是否有任何等級（含看圖模型）以前綴指定服務提供者；全部為一般模型名稱時維持單一服務提供者
+pub fn uses_tier_routing(config: &AIConfig) -> bool {
+    ALL_TIERS.iter().any(|tier| parse_model_spec(tier_spec(config, *tier)).0.is_some())
+        || config.model_vision.as_deref().is_some_and(|spec| parse_model_spec(spec).0.is_some())
+}
+
+// 未指定前綴的等級與模型由 API_OPTION 處理
+fn default_provider(config: &AIConfig) -> Result<&'static str> {
+    normalize_provider(&config.api_option)
+        .ok_or_else(|| anyhow::anyhow!("不支援的 AI 服務選項: {}", config.api_option))
+}
+
+/// 檢查設定中引用的每個服務提供者都有 API key（API_OPTION 一律視為已引用）
+pub fn validate_provider_keys(config: &AIConfig) -> std::result::Result<(), String> {
+    let default = default_provider(config).map_err(|e| e.to_string())?;
+    let mut providers = vec![default];
+    let specs = ALL_TIERS.iter().map(|tier| tier_spec(config, *tier)).chain(config.model_vision.as_deref());
+    for spec in specs {
+        if let (Some(provider), _) = parse_model_spec(spec) {
+            if !providers.contains(&provider) {
+                providers.push(provider);
+            }
+        }
+    }
+
+    let missing: Vec<String> = providers
+        .into_iter()
+        .filter_map(|provider| provider_api_key(config, provider).err().map(|e| e.to_string()))
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing.join("; "))
+    }
+}
+
+// 各功能使用的模型等級（與 ModelTier 的說明一致：對話與輕量判斷走 Fast，生成走 Normal，專家規劃走 Think）
+fn tier_for(method: &str) -> ModelTier {
+    match method {
+        "generate_task_preview"
+        | "generate_task_preview_with_history"
+        | "generate_daily_task_from_text"
+        | "classify_user_intent"
+        | "generate_skill_tags" => ModelTier::Fast,
+        "generate_task_with_expert" | "analyze_with_expert" | "generate_subtasks_for_main_task" => ModelTier::Think,
+        _ => ModelTier::Normal,
+    }
+}
+
+/// 單一服務提供者的斷路器：連續失敗達門檻後暫停呼叫，冷卻結束後放行試探請求
+#[derive(Debug)]
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker { threshold, cooldown, state: Mutex::new(BreakerState::default()) }
+    }
+
+    fn allow(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.open_until {
+            Some(until) if now < until => false,
+            Some(_) => {
+                // 半開：放行一次試探，再失敗一次就重新開啟
+                state.open_until = None;
+                state.consecutive_failures = self.threshold.saturating_sub(1);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    // 回傳本次失敗是否讓斷路器開啟
+    fn record_failure(&self, now: Instant) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.threshold && state.open_until.is_none() {
+            state.open_until = Some(now + self.cooldown);
+            return true;
+        }
+        false
+    }
+}
+
+tokio::task_local! {
+    static DISPATCHES: Arc<Mutex<Vec<AIServedModel>>>;
+}
+
+/// 執行 future 並收集期間混合路由實際呼叫的服務提供者與模型（AI 額度中間件據此逐一記錄使用量）
+pub async fn track_dispatches<F: Future>(future: F) -> (F::Output, Vec<AIServedModel>) {
+    let dispatches = Arc::new(Mutex::new(Vec::new()));
+    let output = DISPATCHES.scope(dispatches.clone(), future).await;
+    let recorded = dispatches.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    (output, recorded)
+}
+
+fn record_dispatch(served: AIServedModel) {
+    // 不在追蹤範圍內（例如背景任務）時略過
+    let _ = DISPATCHES.try_with(|d| d.lock().unwrap_or_else(|e| e.into_inner()).push(served));
+}
+
+struct Route {
+    provider: &'static str,
+    model: String,
+    service: Arc<dyn AIService + Send + Sync>,
+}
+
+/// 依模型等級將呼叫轉送到不同服務提供者的 AI 服務
+pub struct CompositeAIService {
+    tiers: HashMap<ModelTier, Route>,
+    // API_OPTION 的主要模型，處理未指定前綴且不屬於任何等級的 generate_with_model
+    default_route: Route,
+    // AI_MODEL_VISION（聊天附圖）
+    vision_route: Option<Route>,
+    breakers: HashMap<&'static str, CircuitBreaker>,
+}
+
+impl CompositeAIService {
+    pub fn from_config(config: &AIConfig) -> Result<Self> {
+        validate_provider_keys(config).map_err(|e| anyhow::anyhow!(e))?;
+        Self::build(config, |provider, model, client| build_provider_service(config, provider, model, client))
+    }
+
+    fn build<F>(config: &AIConfig, make_service: F) -> Result<Self>
+    where
+        F: Fn(&'static str, &str, reqwest::Client) -> Result<Arc<dyn AIService + Send + Sync>>,
+    {
+        let default = default_provider(config)?;
+        let mut clients: HashMap<&'static str, reqwest::Client> = HashMap::new();
+        let mut route = |provider: &'static str, model: &str| -> Result<Route> {
+            let client = clients.entry(provider).or_default().clone();
+            Ok(Route { provider, model: model.to_string(), service: make_service(provider, model, client)? })
+        };
+
+        let mut tiers = HashMap::new();
+        for tier in ALL_TIERS {
+            let (provider, model) = parse_model_spec(tier_spec(config, tier));
+            let provider = provider.unwrap_or(default);
+            log::info!("AI 混合路由: {:?} -> {}:{}", tier, provider, model);
+            tiers.insert(tier, route(provider, model)?);
+        }
+        let (provider, model) = parse_model_spec(primary_model(config));
+        let default_route = route(provider.unwrap_or(default), model)?;
+        let vision_route = match config.model_vision.as_deref().map(parse_model_spec) {
+            Some((provider, model)) => {
+                let provider = provider.unwrap_or(default);
+                log::info!("AI 混合路由: Vision -> {}:{}", provider, model);
+                Some(route(provider, model)?)
+            }
+            None => None,
+        };
+
+        let cooldown = Duration::from_secs(config.circuit_breaker_cooldown_secs);
+        let breakers = tiers
+            .values()
+            .chain(std::iter::once(&default_route))
+            .chain(vision_route.as_ref())
+            .map(|r| (r.provider, CircuitBreaker::new(config.circuit_breaker_threshold, cooldown)))
+            .collect();
+
+        Ok(CompositeAIService { tiers, default_route, vision_route, breakers })
+    }
+
+    // generate_with_model 的模型可帶前綴；未帶前綴時若符合某個等級的模型就用該等級的服務提供者
+    fn route_for_model(&self, spec: &str) -> Result<(&Route, String)> {
+        let (provider, model) = parse_model_spec(spec);
+        let route = match provider {
+            Some(provider) => std::iter::once(&self.default_route)
+                .chain(self.tiers.values())
+                .chain(self.vision_route.as_ref())
+                .find(|r| r.provider == provider)
+                .ok_or_else(|| anyhow::anyhow!("AI 服務提供者 {} 未在模型等級設定中使用", provider))?,
+            None => self
+                .tiers
+                .values()
+                .chain(self.vision_route.as_ref())
+                .find(|r| r.model == model)
+                .unwrap_or(&self.default_route),
+        };
+        Ok((route, model.to_string()))
+    }
+
+    fn begin(&self, method: &str, route: &Route, model: &str) -> Result<()> {
+        if let Some(breaker) = self.breakers.get(route.provider) {
+            if !breaker.allow(Instant::now()) {
+                return Err(anyhow::anyhow!("AI 服務提供者 {} 暫時停用（斷路器開啟中），請稍後再試", route.provider));
+            }
+        }
+        log::info!("[AI ROUTE][{}] {}:{}", method, route.provider, model);
+        record_dispatch(AIServedModel {
+            provider: route.provider.to_string(),
+            model: model.to_string(),
+            overridden: false,
+        });
+        Ok(())
+    }
+
+    // 內容被安全過濾代表服務提供者正常運作，不計入斷路器失敗
+    fn finish<T>(&self, route: &Route, result: Result<T>) -> Result<T> {
+        if let Some(breaker) = self.breakers.get(route.provider) {
+            match &result {
+                Ok(_) => breaker.record_success(),
+                Err(e) if is_content_filtered(e) => breaker.record_success(),
+                Err(_) => {
+                    if breaker.record_failure(Instant::now()) {
+                        log::warn!("AI 服務提供者 {} 連續失敗，斷路器開啟", route.provider);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn tier_route(&self, method: &str) -> Result<&Route> {
+        let route = &self.tiers[&tier_for(method)];
+        self.begin(method, route, &route.model)?;
+        Ok(route)
+    }
+}
+
+#[async_trait::async_trait]
+impl AIService for CompositeAIService {
+    async fn generate_achievement_from_text(&self, user_input: &str) -> Result<AIGeneratedAchievement> {
+        let route = self.tier_route("generate_achievement_from_text")?;
+        self.finish(route, route.service.generate_achievement_from_text(user_input).await)
+    }
+
+    async fn generate_achievement_from_user_id(&self, rb: &RBatis, user_id: &str) -> Result<AIGeneratedAchievement> {
+        let route = self.tier_route("generate_achievement_from_user_id")?;
+        self.finish(route, route.service.generate_achievement_from_user_id(rb, user_id).await)
+    }
+
+    async fn generate_task_preview(&self, prompt: &str) -> Result<String> {
+        let route = self.tier_route("generate_task_preview")?;
+        self.finish(route, route.service.generate_task_preview(prompt).await)
+    }
+
+    async fn generate_task_preview_with_history(&self, system_prompt: &str, history: &[(String, String)], current_message: &str) -> Result<String> {
+        let route = self.tier_route("generate_task_preview_with_history")?;
+        let result = route.service.generate_task_preview_with_history(system_prompt, history, current_message).await;
+        self.finish(route, result)
+    }
+
+    async fn generate_task_from_text(&self, user_input: &str) -> Result<AIGeneratedTask> {
+        let route = self.tier_route("generate_task_from_text")?;
+        self.finish(route, route.service.generate_task_from_text(user_input).await)
+    }
+
+    async fn match_expert_for_task(&self, user_input: &str) -> Result<ExpertMatch> {
+        let route = self.tier_route("match_expert_for_task")?;
+        self.finish(route, route.service.match_expert_for_task(user_input).await)
+    }
+
+    async fn generate_task_with_expert(&self, user_input: &str, expert_match: &ExpertMatch) -> Result<AIGeneratedTaskPlan> {
+        let route = self.tier_route("generate_task_with_expert")?;
+        self.finish(route, route.service.generate_task_with_expert(user_input, expert_match).await)
+    }
+
+    async fn analyze_with_expert(&self, user_input: &str, expert_name: &str, expert_description: &str, analysis_type: &str) -> Result<String> {
+        let route = self.tier_route("analyze_with_expert")?;
+        let result = route.service.analyze_with_expert(user_input, expert_name, expert_description, analysis_type).await;
+        self.finish(route, result)
+    }
+
+    async fn generate_subtasks_for_main_task(&self, main_task_title: &str, main_task_description: &str, expert_match: &ExpertMatch) -> Result<Vec<AIGeneratedTask>> {
+        let route = self.tier_route("generate_subtasks_for_main_task")?;
+        let result = route.service.generate_subtasks_for_main_task(main_task_title, main_task_description, expert_match).await;
+        self.finish(route, result)
+    }
+
+    async fn generate_with_model(&self, model: &str, prompt: &str) -> Result<String> {
+        let (route, model) = self.route_for_model(model)?;
+        self.begin("generate_with_model", route, &model)?;
+        self.finish(route, route.service.generate_with_model(&model, prompt).await)
+    }
+
+    fn supports_vision(&self, model: &str) -> bool {
+        self.route_for_model(model)
+            .map(|(route, model)| route.service.supports_vision(&model))
+            .unwrap_or(false)
+    }
+
+    async fn generate_with_image(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        history: &[(String, String)],
+        message: &str,
+        image: &ChatImage,
+    ) -> Result<String> {
+        let (route, model) = self.route_for_model(model)?;
+        self.begin("generate_with_image", route, &model)?;
+        let result = route.service.generate_with_image(&model, system_prompt, history, message, image).await;
+        self.finish(route, result)
+    }
+
+    async fn generate_daily_task_from_text(&self, user_input: &str) -> Result<AIGeneratedTask> {
+        let route = self.tier_route("generate_daily_task_from_text")?;
+        self.finish(route, route.service.generate_daily_task_from_text(user_input).await)
+    }
+
+    async fn classify_user_intent(&self, user_input: &str) -> Result<crate::ai_tasks::ClassifyIntentResponse> {
+        let route = self.tier_route("classify_user_intent")?;
+        self.finish(route, route.service.classify_user_intent(user_input).await)
+    }
+
+    async fn generate_skill_tags(
+        &self,
+        task_title: &str,
+        task_description: Option<&str>,
+        user_existing_skills: &[String]
+    ) -> Result<AIGeneratedSkillTags> {
+        let route = self.tier_route("generate_skill_tags")?;
+        let result = route.service.generate_skill_tags(task_title, task_description, user_existing_skills).await;
+        self.finish(route, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_ai::MockAIService;
+
+    fn mixed_config() -> AIConfig {
+        let mut config = crate::config::Config::from_env().app.ai;
+        config.api_option = "OpenRouter".to_string();
+        config.openrouter_api_key = Some("or-key".to_string());
+        config.openrouter_model = "openai/gpt-4o-mini".to_string();
+        config.gemini_api_key = Some("gemini-key".to_string());
+        config.model_small = "google/gemma-3-4b-it:free".to_string();
+        config.model_fast = "ollama:llama3.1".to_string();
+        config.model_normal = "gemini:gemini-2.0-flash".to_string();
+        config.model_think = "openrouter:anthropic/claude-3.5-sonnet".to_string();
+        config.model_background = "qwen/qwen3-8b".to_string();
+        config.circuit_breaker_threshold = 2;
+        config.circuit_breaker_cooldown_secs = 60;
+        config
+    }
+
+    // 每個服務提供者各一個模擬服務
+    fn build_with_mocks(config: &AIConfig, mocks: &HashMap<&'static str, MockAIService>) -> CompositeAIService {
+        CompositeAIService::build(config, |provider, _model, _client| {
+            Ok(Arc::new(mocks.get(provider).cloned().unwrap_or_default()))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_model_spec() {
+        assert_eq!(
+            parse_model_spec("openrouter:anthropic/claude-3.5-sonnet"),
+            (Some("OpenRouter"), "anthropic/claude-3.5-sonnet")
+        );
+        assert_eq!(parse_model_spec("Gemini:gemini-2.0-flash"), (Some("Gemini"), "gemini-2.0-flash"));
+        assert_eq!(parse_model_spec("google/gemma-3-4b-it:free"), (None, "google/gemma-3-4b-it:free"));
+        assert_eq!(parse_model_spec("gpt-4o-mini"), (None, "gpt-4o-mini"));
+    }
+
+    #[test]
+    fn test_tier_routing_detection_and_key_validation() {
+        let mut config = mixed_config();
+        assert!(uses_tier_routing(&config));
+        assert!(validate_provider_keys(&config).is_ok());
+
+        config.gemini_api_key = None;
+        let err = validate_provider_keys(&config).unwrap_err();
+        assert!(err.contains("Gemini"), "{}", err);
+        assert!(CompositeAIService::from_config(&config).is_err());
+
+        for field in [
+            &mut config.model_fast,
+            &mut config.model_normal,
+            &mut config.model_think,
+        ] {
+            *field = parse_model_spec(field).1.to_string();
+        }
+        assert!(!uses_tier_routing(&config));
+    }
+
+    #[actix_web::test]
+    async fn test_calls_dispatch_to_tier_provider_and_are_tracked() {
+        let config = mixed_config();
+        let mocks: HashMap<&'static str, MockAIService> = ["OpenRouter", "Gemini", "Ollama"]
+            .into_iter()
+            .map(|p| (p, MockAIService::default()))
+            .collect();
+        let service = build_with_mocks(&config, &mocks);
+
+        let (result, dispatched) = track_dispatches(async {
+            service.generate_task_preview("聊天").await?;
+            service.generate_task_from_text("想學游泳").await?;
+            service.generate_with_model("gemini:gemini-2.5-pro", "大綱").await?;
+            service.generate_with_model("perplexity/sonar", "資源").await
+        })
+        .await;
+        result.unwrap();
+
+        assert_eq!(mocks["Ollama"].prompts("generate_task_preview"), vec!["聊天".to_string()]);
+        assert_eq!(mocks["Gemini"].prompts("generate_task_from_text"), vec!["想學游泳".to_string()]);
+        assert_eq!(mocks["Gemini"].prompts("generate_with_model"), vec!["[gemini-2.5-pro] 大綱".to_string()]);
+        assert_eq!(mocks["OpenRouter"].prompts("generate_with_model"), vec!["[perplexity/sonar] 資源".to_string()]);
+
+        let providers: Vec<(&str, &str)> = dispatched
+            .iter()
+            .map(|d| (d.provider.as_str(), d.model.as_str()))
+            .collect();
+        assert_eq!(
+            providers,
+            vec![
+                ("Ollama", "llama3.1"),
+                ("Gemini", "gemini-2.0-flash"),
+                ("Gemini", "gemini-2.5-pro"),
+                ("OpenRouter", "perplexity/sonar"),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_vision_model_routes_to_its_provider() {
+        let mut config = mixed_config();
+        config.openai_api_key = Some("openai-key".to_string());
+        config.model_vision = Some("openai:gpt-4o-mini".to_string());
+        assert!(validate_provider_keys(&config).is_ok());
+        let mocks: HashMap<&'static str, MockAIService> = [
+            ("OpenRouter", MockAIService::default()),
+            ("Gemini", MockAIService::default()),
+            ("Ollama", MockAIService::default().without_vision()),
+            ("OpenAI", MockAIService::with_replies(&["看起來是早餐"])),
+        ]
+        .into_iter()
+        .collect();
+        let service = build_with_mocks(&config, &mocks);
+        assert!(service.supports_vision("openai:gpt-4o-mini"));
+        assert!(!service.supports_vision("llama3.1"));
+
+        let image = ChatImage { mime_type: "image/png".to_string(), data: vec![1, 2, 3] };
+        let reply = service.generate_with_image("openai:gpt-4o-mini", "系統", &[], "這是什麼", &image).await.unwrap();
+        assert_eq!(reply, "看起來是早餐");
+        assert_eq!(mocks["OpenAI"].prompts("generate_with_image").len(), 1);
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_and_half_opens() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(breaker.allow(now));
+        assert!(!breaker.record_failure(now));
+        assert!(breaker.record_failure(now));
+        assert!(!breaker.allow(now + Duration::from_secs(30)));
+
+        // 冷卻結束放行試探；再失敗立即重新開啟
+        let later = now + Duration::from_secs(61);
+        assert!(breaker.allow(later));
+        assert!(breaker.record_failure(later));
+        assert!(!breaker.allow(later + Duration::from_secs(1)));
+
+        breaker.record_success();
+        assert!(breaker.allow(later + Duration::from_secs(1)));
+    }
+
+    #[actix_web::test]
+    async fn test_content_filtered_errors_do_not_trip_breaker() {
+        let config = mixed_config();
+        let mocks: HashMap<&'static str, MockAIService> =
+            [("Gemini", MockAIService::content_filtered())].into_iter().collect();
+        let service = build_with_mocks(&config, &mocks);
+
+        for _ in 0..3 {
+            let err = service.generate_task_from_text("不當內容").await.unwrap_err();
+            assert!(is_content_filtered(&err), "{}", err);
+        }
+        assert!(service.breakers["Gemini"].allow(Instant::now()));
+    }
+
+    #[actix_web::test]
+    async fn test_usage_is_logged_per_underlying_provider() {
+        use actix_web::test;
+
+        let rb = crate::test_utils::setup_db().await;
+        let mocks = HashMap::new();
+        let service = build_with_mocks(&mixed_config(), &mocks);
+        let app = crate::test_utils::init_app_with_ai(&rb, Arc::new(service)).await;
+        let user = crate::test_utils::create_user(&app, "mixer").await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/generate-json")
+            .insert_header(user.auth())
+            .set_json(serde_json::json!({"description": "我想學游泳"}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(res.headers().get(crate::ai_quota::MODEL_HEADER).unwrap(), "gemini-2.0-flash");
+
+        let logs: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT provider, model FROM ai_usage_log WHERE user_id = ?",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0]["provider"], "Gemini");
+        assert_eq!(logs[0]["model"], "gemini-2.0-flash");
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+use serde_json::json;
+use super::chat_service::{ChatReply, ChatService, ChatTransport};
+use super::common::AIContentFilteredError;
+
+// 視為安全過濾的 finishReason
+const SAFETY_FINISH_REASONS: &[&str] = &["SAFETY", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII", "IMAGE_SAFETY"];
+
+/// 將 OpenAI 格式的請求轉為 Gemini generateContent 請求，回傳 (模型, 請求內容)
+/// - system 訊息合併為 systemInstruction
+/// - assistant 對應 Gemini 的 model 角色
+/// - response_format json_object 對應 JSON 模式 (responseMimeType)
+fn to_gemini_request(request: &serde_json::Value) -> Result<(String, serde_json::Value)> {
+    let model = request["model"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Gemini 請求缺少模型名稱"))?
+        .trim_start_matches("models/")
+        .to_string();
+
+    let mut system_parts = Vec::new();
+    let mut contents = Vec::new();
+    for message in request["messages"].as_array().into_iter().flatten() {
+        let text = message["content"].as_str().unwrap_or_default();
+        match message["role"].as_str().unwrap_or("user") {
+            "system" => system_parts.push(json!({ "text": text })),
+            "assistant" => contents.push(json!({ "role": "model", "parts": [{ "text": text }] })),
+            _ => contents.push(json!({ "role": "user", "parts": [{ "text": text }] })),
+        }
+    }
+
+    let mut generation_config = json!({});
+    if let Some(max_tokens) = request["max_completion_tokens"].as_i64() {
+        generation_config["maxOutputTokens"] = json!(max_tokens);
+    }
+    if request["response_format"]["type"].as_str() == Some("json_object") {
+        generation_config["responseMimeType"] = json!("application/json");
+    }
+
+    let mut body = json!({
+        "contents": contents,
+        "generationConfig": generation_config,
+    });
+    if !system_parts.is_empty() {
+        body["systemInstruction"] = json!({ "parts": system_parts });
+    }
+
+    Ok((model, body))
+}
+
+/// 將 Gemini 回應轉為 OpenAI 相容格式；被安全政策擋下時回傳 AIContentFilteredError
+fn from_gemini_response(body: &serde_json::Value) -> Result<serde_json::Value> {
+    let filtered = |reason: &str| {
+        anyhow::Error::new(AIContentFilteredError {
+            provider: "Gemini".to_string(),
+            reason: reason.to_string(),
+        })
+    };
+
+    if let Some(reason) = body["promptFeedback"]["blockReason"].as_str() {
+        return Err(filtered(reason));
+    }
+
+    let candidate = &body["candidates"][0];
+    if let Some(reason) = candidate["finishReason"].as_str() {
+        if SAFETY_FINISH_REASONS.contains(&reason) {
+            return Err(filtered(reason));
+        }
+    }
+
+    if candidate.is_null() {
+        return Ok(json!({ "choices": [] }));
+    }
+
+    let content: String = candidate["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|part| !part["thought"].as_bool().unwrap_or(false))
+        .filter_map(|part| part["text"].as_str())
+        .collect();
+
+    Ok(json!({
+        "choices": [{ "message": { "role": "assistant", "content": content } }]
+    }))
+}
+
+/// Gemini 傳輸層：請求沿用 OpenAI 相容格式，送出前轉換為 generateContent 格式
+pub struct GeminiTransport {
+    api_key: String,
+    client: reqwest::Client,
+}
+
+#[async_trait::async_trait]
+impl ChatTransport for GeminiTransport {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    // 呼叫 generateContent，錯誤狀態碼原樣回傳，成功時轉換為 OpenAI 相容格式
+    async fn send(&self, request: &serde_json::Value) -> Result<ChatReply> {
+        let (model, body) = to_gemini_request(request)?;
+
+        let response = self.client
+            .post(format!("https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent", model))
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Ok(ChatReply::new(status, text));
+        }
+
+        let parsed: serde_json::Value = serde_json::from_str(&text)?;
+        let converted = from_gemini_response(&parsed)?;
+        Ok(ChatReply::new(status, converted.to_string()))
+    }
+
+    // Pro / 思考模型給予較大的輸出空間
+    fn max_output_tokens(&self, model: &str) -> i32 {
+        if model.contains("pro") || model.contains("thinking") {
+            8192
+        } else {
+            4000
+        }
+    }
+}
+
+pub type GeminiService = ChatService<GeminiTransport>;
+
+impl GeminiService {
+    pub fn new(api_key: String, model: String, model_small: String, model_fast: String, model_normal: String, model_think: String, model_background: String) -> Self {
+        let transport = GeminiTransport {
+            api_key,
+            client: reqwest::Client::new(),
+        };
+        ChatService::with_transport(transport, model, model_small, model_fast, model_normal, model_think, model_background)
+    }
+
+    /// 共用同一個 HTTP 客戶端（混合路由時同一服務提供者的各等級共用連線池）
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.transport.client = client;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_maps_roles_system_instruction_and_json_mode() {
+        let request = json!({
+            "model": "gemini-2.0-flash",
+            "messages": [
+                {"role": "user", "content": "之前的問題"},
+                {"role": "assistant", "content": "之前的回答"},
+                {"role": "system", "content": "你是任務助手"},
+                {"role": "user", "content": "幫我規劃讀書"}
+            ],
+            "max_completion_tokens": 4000,
+            "response_format": {"type": "json_object"}
+        });
+
+        let (model, body) = to_gemini_request(&request).unwrap();
+        assert_eq!(model, "gemini-2.0-flash");
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "你是任務助手");
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["text"], "之前的回答");
+        assert_eq!(contents[2]["role"], "user");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 4000);
+        assert_eq!(body["generationConfig"]["responseMimeType"], "application/json");
+    }
+
+    #[test]
+    fn test_plain_text_request_has_no_json_mode() {
+        let request = json!({
+            "model": "models/gemini-2.5-pro",
+            "messages": [{"role": "user", "content": "你好"}],
+            "max_completion_tokens": 100
+        });
+
+        let (model, body) = to_gemini_request(&request).unwrap();
+        assert_eq!(model, "gemini-2.5-pro");
+        assert!(body.get("systemInstruction").is_none());
+        assert!(body["generationConfig"].get("responseMimeType").is_none());
+    }
+
+    #[test]
+    fn test_response_joins_text_parts_and_skips_thoughts() {
+        let body = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "思考中", "thought": true},
+                    {"text": "{\"title\":"},
+                    {"text": "\"讀書\"}"}
+                ]},
+                "finishReason": "STOP"
+            }]
+        });
+
+        let converted = from_gemini_response(&body).unwrap();
+        assert_eq!(converted["choices"][0]["message"]["content"], "{\"title\":\"讀書\"}");
+    }
+
+    #[test]
+    fn test_safety_blocks_surface_as_content_filtered_error() {
+        let prompt_blocked = json!({"promptFeedback": {"blockReason": "SAFETY"}});
+        let err = from_gemini_response(&prompt_blocked).unwrap_err();
+        assert!(super::super::is_content_filtered(&err));
+
+        let candidate_blocked = json!({
+            "candidates": [{"finishReason": "SAFETY", "safetyRatings": []}]
+        });
+        let err = from_gemini_response(&candidate_blocked).unwrap_err();
+        let filtered = err.downcast_ref::<AIContentFilteredError>().unwrap();
+        assert_eq!(filtered.reason, "SAFETY");
+    }
+}
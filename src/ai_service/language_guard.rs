@@ -0,0 +1,183 @@
+// 生成內容的語言檢查：包住實際的 AI 服務
+//
+// 結構化生成（任務、子任務、成就）在使用者輸入後附上目前語言的指示（language::directive），
+// 回傳後檢查 title / description / name 是否為預期語言；不符時以嚴格指示重試一次，
+// 仍不符就回傳錯誤，不把混雜語言的標題寫進資料庫。其餘呼叫直接轉交，語言指示由各提示詞自行加上。
+
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rbatis::RBatis;
+use serde::Serialize;
+
+use super::common::{AIGeneratedAchievement, AIGeneratedSkillTags, AIGeneratedTask, AIGeneratedTaskPlan, ChatImage, ExpertMatch};
+use super::AIService;
+use crate::language::{self, Language};
+
+pub struct LanguageGuard {
+    inner: Arc<dyn AIService + Send + Sync>,
+}
+
+impl LanguageGuard {
+    pub fn new(inner: Arc<dyn AIService + Send + Sync>) -> Self {
+        LanguageGuard { inner }
+    }
+}
+
+fn with_directive(user_input: &str) -> String {
+    format!("{}\n\n{}", user_input, language::directive())
+}
+
+/// 執行生成並檢查語言；不符時在嚴格指示的範圍內重試一次
+async fn checked<T, F, Fut>(method: &str, generate: F) -> Result<T>
+where
+    T: Serialize,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let language = language::current();
+    let first = generate().await?;
+    let Some((field, text)) = mismatch(&first, language) else {
+        return Ok(first);
+    };
+    log::warn!("{} 生成的 {}「{}」不是{}，以嚴格的語言指示重試", method, field, text, language.name());
+
+    let retried = language::scope_strict(language, generate()).await?;
+    match mismatch(&retried, language) {
+        None => Ok(retried),
+        Some((field, text)) => Err(anyhow::anyhow!(
+            "AI 生成內容的語言與設定不符（預期{}，{}：{}）",
+            language.name(),
+            field,
+            text
+        )),
+    }
+}
+
+fn mismatch<T: Serialize>(generated: &T, language: Language) -> Option<(String, String)> {
+    let value = serde_json::to_value(generated).ok()?;
+    language::mismatched_field(&value, language)
+}
+
+#[async_trait::async_trait]
+impl AIService for LanguageGuard {
+    async fn generate_achievement_from_text(&self, user_input: &str) -> Result<AIGeneratedAchievement> {
+        checked("generate_achievement_from_text", || async {
+            self.inner.generate_achievement_from_text(&with_directive(user_input)).await
+        })
+        .await
+    }
+
+    // 提示詞在服務內組合（build_achievement_prompt_from_summary 已附上語言指示）
+    async fn generate_achievement_from_user_id(&self, rb: &RBatis, user_id: &str) -> Result<AIGeneratedAchievement> {
+        checked("generate_achievement_from_user_id", || self.inner.generate_achievement_from_user_id(rb, user_id)).await
+    }
+
+    async fn generate_task_preview(&self, prompt: &str) -> Result<String> {
+        self.inner.generate_task_preview(prompt).await
+    }
+
+    async fn generate_task_preview_with_history(&self, system_prompt: &str, history: &[(String, String)], current_message: &str) -> Result<String> {
+        self.inner.generate_task_preview_with_history(system_prompt, history, current_message).await
+    }
+
+    async fn generate_task_from_text(&self, user_input: &str) -> Result<AIGeneratedTask> {
+        checked("generate_task_from_text", || async {
+            self.inner.generate_task_from_text(&with_directive(user_input)).await
+        })
+        .await
+    }
+
+    async fn match_expert_for_task(&self, user_input: &str) -> Result<ExpertMatch> {
+        self.inner.match_expert_for_task(user_input).await
+    }
+
+    async fn generate_task_with_expert(&self, user_input: &str, expert_match: &ExpertMatch) -> Result<AIGeneratedTaskPlan> {
+        checked("generate_task_with_expert", || async {
+            self.inner.generate_task_with_expert(&with_directive(user_input), expert_match).await
+        })
+        .await
+    }
+
+    async fn analyze_with_expert(&self, user_input: &str, expert_name: &str, expert_description: &str, analysis_type: &str) -> Result<String> {
+        self.inner.analyze_with_expert(user_input, expert_name, expert_description, analysis_type).await
+    }
+
+    async fn generate_subtasks_for_main_task(&self, main_task_title: &str, main_task_description: &str, expert_match: &ExpertMatch) -> Result<Vec<AIGeneratedTask>> {
+        checked("generate_subtasks_for_main_task", || async {
+            self.inner
+                .generate_subtasks_for_main_task(main_task_title, &with_directive(main_task_description), expert_match)
+                .await
+        })
+        .await
+    }
+
+    async fn generate_with_model(&self, model: &str, prompt: &str) -> Result<String> {
+        self.inner.generate_with_model(model, prompt).await
+    }
+
+    async fn generate_daily_task_from_text(&self, user_input: &str) -> Result<AIGeneratedTask> {
+        checked("generate_daily_task_from_text", || async {
+            self.inner.generate_daily_task_from_text(&with_directive(user_input)).await
+        })
+        .await
+    }
+
+    async fn classify_user_intent(&self, user_input: &str) -> Result<crate::ai_tasks::ClassifyIntentResponse> {
+        self.inner.classify_user_intent(user_input).await
+    }
+
+    // 技能名稱優先沿用使用者現有的技能（可能是其他語言），不檢查語言
+    async fn generate_skill_tags(
+        &self,
+        task_title: &str,
+        task_description: Option<&str>,
+        user_existing_skills: &[String]
+    ) -> Result<AIGeneratedSkillTags> {
+        self.inner.generate_skill_tags(task_title, task_description, user_existing_skills).await
+    }
+
+    fn supports_vision(&self, model: &str) -> bool {
+        self.inner.supports_vision(model)
+    }
+
+    async fn generate_with_image(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        history: &[(String, String)],
+        message: &str,
+        image: &ChatImage,
+    ) -> Result<String> {
+        self.inner.generate_with_image(model, system_prompt, history, message, image).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_ai::MockAIService;
+
+    #[tokio::test]
+    async fn test_guard_appends_directive_and_retries_once_in_strict_language() {
+        let mock = MockAIService::default();
+        let guard = LanguageGuard::new(Arc::new(mock.clone()));
+
+        // 模擬回應（fixtures）為繁體中文：預設語言一次通過
+        let task = guard.generate_task_from_text("每天讀書").await.unwrap();
+        assert!(!task.title.clone().unwrap_or_default().is_empty());
+        let prompts = mock.prompts("generate_task_from_text");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].ends_with("一律使用繁體中文回答。"), "{}", prompts[0]);
+
+        // 英文使用者：第一次不符，以嚴格指示重試一次後放棄
+        let result = language::scope(Language::En, guard.generate_task_from_text("read every day")).await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("English"), "{}", err);
+        let prompts = mock.prompts("generate_task_from_text");
+        assert_eq!(prompts.len(), 3);
+        assert!(prompts[1].ends_with("Always respond in English."), "{}", prompts[1]);
+        assert!(prompts[2].contains("上一次的輸出語言錯誤"), "{}", prompts[2]);
+    }
+}
@@ -3,31 +3,73 @@
 // 子模組聲明
 mod r#trait;  // 使用 r# 前綴因為 trait 是保留字
 mod common;
+mod chat_service;
 mod openai;
 mod openrouter;
+mod gemini;
+mod composite;
+mod sanitize;
+mod language_guard;
 
 // 重新導出公開的 API
 pub use r#trait::AIService;
 pub use common::{
     AIGeneratedAchievement, AIGeneratedTask, AIGeneratedTaskPlan, AIGeneratedSkillTags,
-    ExpertMatch, Expert, ModelTier,
-    get_expert_database, convert_to_achievement_model, convert_to_task_model,
-    build_task_generation_prompt
+    SkillWithAttribute, ExpertMatch, Expert, CompletionHistorySummary,
+    fallback_expert_match, convert_to_achievement_model,
+    build_task_generation_prompt, clamp_difficulty_adjustment,
+    AIContentFilteredError, is_content_filtered, ChatImage, VISION_UNSUPPORTED
 };
 pub use openai::OpenAIService;
 pub use openrouter::OpenRouterService;
+pub use gemini::GeminiService;
+pub use composite::{CompositeAIService, parse_model_spec, track_dispatches, validate_provider_keys};
+pub use sanitize::{init as init_content_sanitizer, is_single_emoji, sanitize_ai_task};
+pub use language_guard::LanguageGuard;
 
 // 工廠函數
+use std::sync::Arc;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use crate::config::AIConfig;
 
+/// 為聊天訊息匹配專家；匹配失敗時改用通用生活教練，不讓整個請求失敗
+pub async fn match_expert_or_fallback(ai_service: &(dyn AIService + Send + Sync), message: &str) -> ExpertMatch {
+    match ai_service.match_expert_for_task(message).await {
+        Ok(expert_match) => {
+            log::info!("成功匹配專家: {}", expert_match.expert.name);
+            expert_match
+        }
+        Err(e) => {
+            log::warn!("專家匹配失敗，改用通用生活教練: {}", e);
+            fallback_expert_match()
+        }
+    }
+}
+
 // AI 服務工廠函數
-pub fn create_ai_service(config: &AIConfig) -> Result<Box<dyn AIService + Send + Sync>> {
+pub fn create_ai_service(config: &AIConfig) -> Result<Arc<dyn AIService + Send + Sync>> {
+    // 測試時若已安裝模擬服務，直接使用（見 test_utils::mock_ai）
+    #[cfg(test)]
+    if let Some(mock) = crate::test_utils::mock_ai::installed() {
+        return Ok(Arc::new(mock));
+    }
+
+    // 結構化生成的輸出語言由 LanguageGuard 檢查（見 language_guard）
+    Ok(Arc::new(LanguageGuard::new(build_ai_service(config)?)))
+}
+
+fn build_ai_service(config: &AIConfig) -> Result<Arc<dyn AIService + Send + Sync>> {
+    // 任一等級以 provider:model 指定服務提供者時，改用混合路由
+    if composite::uses_tier_routing(config) {
+        return Ok(Arc::new(CompositeAIService::from_config(config)?));
+    }
+
     match config.api_option.as_str() {
         "OpenAI" => {
             let api_key = config.openai_api_key.as_ref()
                 .ok_or_else(|| anyhow::anyhow!("OpenAI API key 未設定"))?;
-            Ok(Box::new(OpenAIService::new(
+            Ok(Arc::new(OpenAIService::new(
                 api_key.clone(),
                 config.openai_model.clone(),
                 config.model_small.clone(),
@@ -40,7 +82,7 @@ pub fn create_ai_service(config: &AIConfig) -> Result<Box<dyn AIService + Send +
         "OpenRouter" => {
             let api_key = config.openrouter_api_key.as_ref()
                 .ok_or_else(|| anyhow::anyhow!("OpenRouter API key 未設定"))?;
-            Ok(Box::new(OpenRouterService::new(
+            Ok(Arc::new(OpenRouterService::new(
                 api_key.clone(),
                 config.openrouter_model.clone(),
                 config.model_small.clone(),
@@ -50,6 +92,282 @@ pub fn create_ai_service(config: &AIConfig) -> Result<Box<dyn AIService + Send +
                 config.model_background.clone(),
             )))
         }
+        "Gemini" => {
+            let api_key = config.gemini_api_key.as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Gemini API key 未設定"))?;
+            Ok(Arc::new(GeminiService::new(
+                api_key.clone(),
+                config.gemini_model.clone(),
+                config.model_small.clone(),
+                config.model_fast.clone(),
+                config.model_normal.clone(),
+                config.model_think.clone(),
+                config.model_background.clone(),
+            )))
+        }
+        "Ollama" => {
+            Ok(Arc::new(OpenAIService::new(
+                String::new(),
+                config.ollama_model.clone(),
+                config.model_small.clone(),
+                config.model_fast.clone(),
+                config.model_normal.clone(),
+                config.model_think.clone(),
+                config.model_background.clone(),
+            ).with_base_url(&config.ollama_base_url)))
+        }
         _ => Err(anyhow::anyhow!("不支援的 AI 服務選項: {}", config.api_option))
     }
-}
\ No newline at end of file
+}
+
+// 服務提供者的 API key（本機 Ollama 不需要）
+fn provider_api_key(config: &AIConfig, provider: &str) -> Result<String> {
+    let key = match provider {
+        "OpenAI" => &config.openai_api_key,
+        "OpenRouter" => &config.openrouter_api_key,
+        "Gemini" => &config.gemini_api_key,
+        "Ollama" => return Ok(String::new()),
+        _ => return Err(anyhow::anyhow!("不支援的 AI 服務選項: {}", provider)),
+    };
+    key.clone()
+        .filter(|k| !k.trim().is_empty())
+        .ok_or_else(|| anyhow::anyhow!("{} API key 未設定", provider))
+}
+
+/// 建立單一服務提供者的實作，所有等級都使用同一個模型（混合路由時每個等級各建一個，共用該服務提供者的客戶端）
+fn build_provider_service(
+    config: &AIConfig,
+    provider: &str,
+    model: &str,
+    client: reqwest::Client,
+) -> Result<Arc<dyn AIService + Send + Sync>> {
+    let api_key = provider_api_key(config, provider)?;
+    let m = || model.to_string();
+    let service: Arc<dyn AIService + Send + Sync> = match provider {
+        "OpenAI" => Arc::new(OpenAIService::new(api_key, m(), m(), m(), m(), m(), m()).with_client(client)),
+        "OpenRouter" => Arc::new(OpenRouterService::new(api_key, m(), m(), m(), m(), m(), m()).with_client(client)),
+        "Gemini" => Arc::new(GeminiService::new(api_key, m(), m(), m(), m(), m(), m()).with_client(client)),
+        "Ollama" => Arc::new(
+            OpenAIService::new(api_key, m(), m(), m(), m(), m(), m())
+                .with_base_url(&config.ollama_base_url)
+                .with_client(client),
+        ),
+        _ => return Err(anyhow::anyhow!("不支援的 AI 服務選項: {}", provider)),
+    };
+    Ok(service)
+}
+
+/// 單次請求的 AI 呼叫選項（比較模型時使用，不影響全域設定）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AICallOptions {
+    // 指定模型：非管理員只能選擇 AI_MODEL_ALLOWLIST 中的模型
+    #[serde(default, alias = "model")]
+    pub model_override: Option<String>,
+    // 指定服務提供者（OpenAI / OpenRouter / Gemini / Ollama），僅限管理員
+    #[serde(default, alias = "provider")]
+    pub provider_override: Option<String>,
+}
+
+impl AICallOptions {
+    /// 請求內容的設定優先，其次是查詢參數（?model=&provider=）
+    pub fn or(self, fallback: AICallOptions) -> AICallOptions {
+        AICallOptions {
+            model_override: self.model_override.or(fallback.model_override),
+            provider_override: self.provider_override.or(fallback.provider_override),
+        }
+    }
+
+    pub fn is_override(&self) -> bool {
+        self.model_override.is_some() || self.provider_override.is_some()
+    }
+}
+
+/// 實際處理請求的服務提供者與模型（寫入 request extensions，由 AI 額度中間件回報並記錄）
+#[derive(Debug, Clone, PartialEq)]
+pub struct AIServedModel {
+    pub provider: String,
+    // 主要模型；未覆寫時部分輕量功能會改用 fast 等級的模型
+    pub model: String,
+    pub overridden: bool,
+}
+
+// 服務提供者名稱正規化（不分大小寫）
+fn normalize_provider(provider: &str) -> Option<&'static str> {
+    match provider.trim().to_lowercase().as_str() {
+        "openai" => Some("OpenAI"),
+        "openrouter" => Some("OpenRouter"),
+        "gemini" => Some("Gemini"),
+        "ollama" => Some("Ollama"),
+        _ => None,
+    }
+}
+
+fn primary_model(config: &AIConfig) -> &str {
+    match config.api_option.as_str() {
+        "OpenAI" => &config.openai_model,
+        "Gemini" => &config.gemini_model,
+        "Ollama" => &config.ollama_model,
+        _ => &config.openrouter_model,
+    }
+}
+
+/// 套用覆寫後的設定：指定模型時所有等級都改用該模型
+fn apply_call_options(config: &AIConfig, options: &AICallOptions) -> AIConfig {
+    let mut config = config.clone();
+    if let Some(provider) = options.provider_override.as_deref().and_then(normalize_provider) {
+        config.api_option = provider.to_string();
+        // 指定服務提供者時，混合路由的等級也全部改由該服務提供者處理
+        for field in [
+            &mut config.model_small,
+            &mut config.model_fast,
+            &mut config.model_normal,
+            &mut config.model_think,
+            &mut config.model_background,
+        ] {
+            *field = parse_model_spec(field).1.to_string();
+        }
+        if let Some(vision) = config.model_vision.as_mut() {
+            *vision = parse_model_spec(vision).1.to_string();
+        }
+    }
+    if let Some(model) = &options.model_override {
+        for field in [
+            &mut config.openai_model,
+            &mut config.openrouter_model,
+            &mut config.gemini_model,
+            &mut config.ollama_model,
+            &mut config.model_small,
+            &mut config.model_fast,
+            &mut config.model_normal,
+            &mut config.model_think,
+            &mut config.model_background,
+        ] {
+            *field = model.clone();
+        }
+    }
+    config
+}
+
+type AIServiceFactory = dyn Fn(&AIConfig) -> Result<Arc<dyn AIService + Send + Sync>> + Send + Sync;
+
+/// 啟動時建立一次、存放於 app data 的 AI 服務
+///
+/// 設定錯誤（例如缺少 API key）時保留錯誤訊息，由各路由回應「AI 服務初始化失敗」，
+/// 與每次請求各自建立服務時的行為相同。指定模型或服務提供者的請求另外以工廠建立服務。
+/// 並行呼叫 AI 時以 acquire_permit 取得名額，同時進行的呼叫數不超過 AI_MAX_CONCURRENT_CALLS。
+#[derive(Clone)]
+pub struct SharedAIService {
+    service: Result<Arc<dyn AIService + Send + Sync>, String>,
+    config: AIConfig,
+    factory: Arc<AIServiceFactory>,
+    permits: Arc<tokio::sync::Semaphore>,
+}
+
+impl SharedAIService {
+    pub fn from_config(config: &AIConfig) -> Self {
+        SharedAIService {
+            service: create_ai_service(config).map_err(|e| e.to_string()),
+            config: config.clone(),
+            factory: Arc::new(create_ai_service),
+            permits: Arc::new(tokio::sync::Semaphore::new(config.max_concurrent_calls.max(1))),
+        }
+    }
+
+    /// 直接注入指定的實作（測試時注入模擬服務；覆寫模型時也回傳同一個實作）
+    #[cfg(test)]
+    pub fn new(service: Arc<dyn AIService + Send + Sync>) -> Self {
+        let injected = service.clone();
+        SharedAIService {
+            service: Ok(service),
+            config: crate::config::Config::from_env().app.ai,
+            factory: Arc::new(move |_| Ok(injected.clone())),
+            permits: Arc::new(tokio::sync::Semaphore::new(4)),
+        }
+    }
+
+    /// 設定允許非管理員指定的模型（測試用）
+    #[cfg(test)]
+    pub fn with_model_allowlist(mut self, models: &[&str]) -> Self {
+        self.config.model_allowlist = models.iter().map(|m| m.to_string()).collect();
+        self
+    }
+
+    /// 設定看圖模型（測試用）
+    #[cfg(test)]
+    pub fn with_vision_model(mut self, model: &str) -> Self {
+        self.config.model_vision = Some(model.to_string());
+        self
+    }
+
+    pub fn get(&self) -> Result<Arc<dyn AIService + Send + Sync>> {
+        self.service.clone().map_err(|e| anyhow::anyhow!(e))
+    }
+
+    /// 取得一個 AI 呼叫名額，名額用完時等待其他呼叫結束
+    pub async fn acquire_permit(&self) -> tokio::sync::OwnedSemaphorePermit {
+        self.permits.clone().acquire_owned().await.expect("AI 呼叫名額的 semaphore 不會被關閉")
+    }
+
+    /// 背景處理等級的模型（搭配 generate_with_model 使用，排程批次工作用）
+    pub fn background_model(&self) -> &str {
+        &self.config.model_background
+    }
+
+    /// 快速回應等級的模型（搭配 generate_with_model 使用，聊天快速模式）
+    pub fn fast_model(&self) -> &str {
+        &self.config.model_fast
+    }
+
+    /// 看圖等級的模型（搭配 generate_with_image 使用，聊天附圖）；未設定時不接受圖片
+    pub fn vision_model(&self) -> Option<&str> {
+        self.config.model_vision.as_deref()
+    }
+
+    /// 一般等級的模型（搭配 generate_with_model 使用，職業主線成就等一次性生成）
+    pub fn normal_model(&self) -> &str {
+        &self.config.model_normal
+    }
+
+    /// 超輕量等級的模型（搭配 generate_with_model 使用，簡短的建議生成）
+    pub fn small_model(&self) -> &str {
+        &self.config.model_small
+    }
+
+    /// 檢查覆寫是否允許：服務提供者僅限管理員，非管理員的模型必須在允許清單中
+    pub fn validate_options(&self, options: &AICallOptions, is_admin: bool) -> std::result::Result<(), String> {
+        if let Some(provider) = &options.provider_override {
+            if !is_admin {
+                return Err("只有管理員可以指定 AI 服務提供者".to_string());
+            }
+            if normalize_provider(provider).is_none() {
+                return Err(format!("不支援的 AI 服務提供者: {}", provider));
+            }
+        }
+        if let Some(model) = &options.model_override {
+            if model.trim().is_empty() {
+                return Err("model_override 不可為空白".to_string());
+            }
+            if !is_admin && !self.config.model_allowlist.iter().any(|m| m == model) {
+                return Err(format!("不允許使用的模型: {}", model));
+            }
+        }
+        Ok(())
+    }
+
+    /// 依呼叫選項取得 AI 服務與實際使用的模型；未覆寫時回傳共享的實例
+    pub fn get_with_options(&self, options: &AICallOptions) -> Result<(Arc<dyn AIService + Send + Sync>, AIServedModel)> {
+        let config = apply_call_options(&self.config, options);
+        let (provider, model) = parse_model_spec(primary_model(&config));
+        let served = AIServedModel {
+            provider: provider.map(str::to_string).unwrap_or_else(|| config.api_option.clone()),
+            model: model.to_string(),
+            overridden: options.is_override(),
+        };
+        let service = if options.is_override() {
+            (self.factory)(&config)?
+        } else {
+            self.get()?
+        };
+        Ok((service, served))
+    }
+}
@@ -0,0 +1,303 @@
+// AI 生成內容清理：寫入資料庫前移除程式碼區塊標記、AI 套話、連續 emoji 與多餘空白，
+// 並限制標題 / 描述長度；圖示只保留單一 emoji。有任何修改時回傳 true，
+// 由 API 回應帶上 sanitized 標記，方便追查提示詞問題。
+
+use std::sync::OnceLock;
+
+use super::common::{AIGeneratedAchievement, AIGeneratedTask};
+use crate::config::AiSanitizeConfig;
+
+const DEFAULT_ICON: &str = "🏆";
+
+static AI_SANITIZE_CONFIG: OnceLock<AiSanitizeConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: AiSanitizeConfig) {
+    log::info!(
+        "AI 生成內容清理: 標題上限 {} 字、描述上限 {} 字、{} 條套話規則",
+        config.title_max_chars,
+        config.description_max_chars,
+        config.blocklist.len()
+    );
+    if AI_SANITIZE_CONFIG.set(config).is_err() {
+        log::warn!("AI 生成內容清理設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static AiSanitizeConfig {
+    AI_SANITIZE_CONFIG.get_or_init(AiSanitizeConfig::default)
+}
+
+/// 清理 AI 生成的任務（標題、描述、難度調整原因），有修改時回傳 true
+pub fn sanitize_ai_task(task: &mut AIGeneratedTask) -> bool {
+    let config = config();
+    let mut changed = false;
+    changed |= clean_optional(&mut task.title, "任務標題", config.title_max_chars, false, config);
+    changed |= clean_optional(&mut task.description, "任務描述", config.description_max_chars, true, config);
+    changed |= clean_optional(&mut task.adjustment_reason, "難度調整原因", config.description_max_chars, true, config);
+    changed
+}
+
+/// 清理 AI 生成的成就（名稱、描述、圖示），有修改時回傳 true
+pub fn sanitize_ai_achievement(achievement: &mut AIGeneratedAchievement) -> bool {
+    let config = config();
+    let mut changed = false;
+
+    let (name, name_changed) = clean_text(&achievement.name, "成就名稱", config.title_max_chars, false, config);
+    if name_changed {
+        achievement.name = if name.is_empty() { "未命名成就".to_string() } else { name };
+        changed = true;
+    }
+    changed |= clean_optional(&mut achievement.description, "成就描述", config.description_max_chars, true, config);
+
+    if let Some(icon) = &achievement.icon {
+        let single = single_emoji(icon).unwrap_or_else(|| DEFAULT_ICON.to_string());
+        if &single != icon {
+            log::info!("AI 生成的成就圖示「{}」改為「{}」", icon, single);
+            achievement.icon = Some(single);
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn clean_optional(
+    field: &mut Option<String>,
+    label: &str,
+    max_chars: usize,
+    multiline: bool,
+    config: &AiSanitizeConfig,
+) -> bool {
+    let Some(raw) = field.as_deref() else {
+        return false;
+    };
+    let (cleaned, changed) = clean_text(raw, label, max_chars, multiline, config);
+    if changed {
+        *field = if cleaned.is_empty() { None } else { Some(cleaned) };
+    }
+    changed
+}
+
+/// 依序移除程式碼區塊標記、套話、連續 emoji，整理空白後截斷；回傳（結果, 是否有修改）
+fn clean_text(raw: &str, label: &str, max_chars: usize, multiline: bool, config: &AiSanitizeConfig) -> (String, bool) {
+    let mut text = strip_code_fences(raw);
+    for phrase in &config.blocklist {
+        text = remove_phrase(&text, phrase);
+    }
+    text = collapse_emoji_runs(&text);
+    text = collapse_whitespace(&text, multiline);
+
+    let length = text.chars().count();
+    if length > max_chars {
+        log::warn!("AI 生成的{}超過 {} 字（{} 字），已截斷", label, max_chars, length);
+        text = text.chars().take(max_chars.saturating_sub(1)).collect::<String>().trim_end().to_string();
+        text.push('…');
+    }
+    let changed = text != raw;
+    (text, changed)
+}
+
+/// 移除 ``` 開頭的整行（```json 等）與殘留的反引號標記
+fn strip_code_fences(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .replace("```", "")
+}
+
+/// 不分大小寫移除套話，連同緊接在後的標點
+fn remove_phrase(text: &str, phrase: &str) -> String {
+    let phrase: Vec<char> = phrase.chars().flat_map(char::to_lowercase).collect();
+    if phrase.is_empty() {
+        return text.to_string();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some(matched) = match_ignore_case(&chars[i..], &phrase) {
+            i += matched;
+            while i < chars.len() && matches!(chars[i], ',' | '，' | '、' | ':' | '：' | '.' | '。' | '!' | '！') {
+                i += 1;
+            }
+            continue;
+        }
+        result.push(chars[i]);
+        i += 1;
+    }
+    result
+}
+
+// 回傳 chars 開頭符合 phrase（已轉小寫）時所佔的字元數
+fn match_ignore_case(chars: &[char], phrase: &[char]) -> Option<usize> {
+    let mut lowered = Vec::with_capacity(phrase.len());
+    for (consumed, c) in chars.iter().enumerate() {
+        if lowered.len() >= phrase.len() {
+            return (lowered == phrase).then_some(consumed);
+        }
+        lowered.extend(c.to_lowercase());
+        if !phrase.starts_with(&lowered[..lowered.len().min(phrase.len())]) {
+            return None;
+        }
+    }
+    (lowered == phrase).then_some(chars.len())
+}
+
+/// 連續的 emoji 只保留第一個（例如「🔥🔥🔥💪」→「🔥」）
+fn collapse_emoji_runs(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    let mut previous_was_emoji = false;
+    while i < chars.len() {
+        let length = emoji_grapheme_len(&chars[i..]);
+        if length > 0 {
+            if !previous_was_emoji {
+                result.extend(&chars[i..i + length]);
+            }
+            previous_was_emoji = true;
+            i += length;
+            continue;
+        }
+        previous_was_emoji = false;
+        result.push(chars[i]);
+        i += 1;
+    }
+    result
+}
+
+/// 行內連續空白合併為一個空格；多行文字保留換行但移除空行，單行文字則把換行也合併
+fn collapse_whitespace(text: &str, multiline: bool) -> String {
+    let lines = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty());
+    let separator = if multiline { "\n" } else { " " };
+    lines.collect::<Vec<_>>().join(separator)
+}
+
+/// 圖示必須是單一 emoji：取第一個 emoji，沒有則回傳 None
+fn single_emoji(icon: &str) -> Option<String> {
+    let chars: Vec<char> = icon.trim().chars().collect();
+    (0..chars.len()).find_map(|start| {
+        let length = emoji_grapheme_len(&chars[start..]);
+        (length > 0).then(|| chars[start..start + length].iter().collect())
+    })
+}
+
+/// 整個字串（去除前後空白）是否恰好為一個 emoji
+pub fn is_single_emoji(value: &str) -> bool {
+    let chars: Vec<char> = value.trim().chars().collect();
+    !chars.is_empty() && emoji_grapheme_len(&chars) == chars.len()
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2300..=0x23FF | 0x2B05..=0x2B55 | 0x3030 | 0x303D | 0x3297 | 0x3299
+    )
+}
+
+// 開頭的 emoji 字素所佔字元數（含變體選擇符、膚色、ZWJ 組合與國旗），不是 emoji 時為 0
+fn emoji_grapheme_len(chars: &[char]) -> usize {
+    let Some(&first) = chars.first() else {
+        return 0;
+    };
+    if !is_emoji(first) {
+        return 0;
+    }
+    let is_regional = |c: char| (0x1F1E6..=0x1F1FF).contains(&(c as u32));
+    let mut length = 1;
+    if is_regional(first) && chars.get(1).is_some_and(|&c| is_regional(c)) {
+        return 2;
+    }
+    while let Some(&c) = chars.get(length) {
+        match c as u32 {
+            // 變體選擇符、膚色、鍵帽、標籤字元
+            0xFE0F | 0x1F3FB..=0x1F3FF | 0x20E3 | 0xE0020..=0xE007F => length += 1,
+            // ZWJ 組合（例如 👨‍💻）
+            0x200D if chars.get(length + 1).is_some_and(|&next| is_emoji(next)) => length += 2,
+            _ => break,
+        }
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(title: &str, description: &str) -> AIGeneratedTask {
+        AIGeneratedTask {
+            title: Some(title.to_string()),
+            description: Some(description.to_string()),
+            task_type: Some("main".to_string()),
+            priority: Some(1),
+            difficulty: Some(3),
+            experience: Some(50),
+            due_date: None,
+            is_recurring: None,
+            recurrence_pattern: None,
+            start_date: None,
+            end_date: None,
+            completion_target: None,
+            difficulty_adjustment: None,
+            adjustment_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_sanitize_offending_task_samples() {
+        // 模型把 JSON 包在程式碼區塊裡，描述開頭附上套話
+        let mut sample = task(
+            "```json\n學習 Rust   所有權\n```",
+            "As an AI language model, I suggest:\n\n\n每天閱讀   The Book 一章\t並完成練習",
+        );
+        assert!(sanitize_ai_task(&mut sample));
+        assert_eq!(sample.title.as_deref(), Some("學習 Rust 所有權"));
+        assert_eq!(sample.description.as_deref(), Some("I suggest:\n每天閱讀 The Book 一章 並完成練習"));
+
+        // emoji 洗版
+        let mut sample = task("🔥🔥🔥🔥 每天跑步 💪💪💪💪", "作為一個AI語言模型，我建議每天跑步 30 分鐘🏃‍♂️🏃‍♂️🏃‍♂️");
+        assert!(sanitize_ai_task(&mut sample));
+        assert_eq!(sample.title.as_deref(), Some("🔥 每天跑步 💪"));
+        assert_eq!(sample.description.as_deref(), Some("我建議每天跑步 30 分鐘🏃‍♂️"));
+
+        // 過長描述截斷（預設 500 字）
+        let mut sample = task("整理房間", &"把書桌上的東西分類收好。".repeat(500));
+        assert!(sanitize_ai_task(&mut sample));
+        let description = sample.description.unwrap();
+        assert_eq!(description.chars().count(), 500);
+        assert!(description.ends_with('…'));
+
+        // 正常內容不變動
+        let mut sample = task("閱讀 30 分鐘 📚", "選一本書\n讀完一個章節");
+        assert!(!sanitize_ai_task(&mut sample));
+        assert_eq!(sample.title.as_deref(), Some("閱讀 30 分鐘 📚"));
+    }
+
+    #[test]
+    fn test_sanitize_achievement_icon_and_name() {
+        let mut achievement = AIGeneratedAchievement {
+            name: "Here's the JSON: 早起鳥兒🐦🐦🐦".to_string(),
+            description: Some("連續 7 天早起".to_string()),
+            icon: Some("🌅🌄 sunrise".to_string()),
+            category: "habit".to_string(),
+            requirement_type: "consecutive_days".to_string(),
+            requirement_value: 7,
+            experience_reward: 100,
+        };
+        assert!(sanitize_ai_achievement(&mut achievement));
+        assert_eq!(achievement.name, "早起鳥兒🐦");
+        assert_eq!(achievement.icon.as_deref(), Some("🌅"));
+
+        // 圖示名稱（非 emoji）改為預設圖示；組合 emoji 與國旗視為單一字素
+        achievement.icon = Some("trophy".to_string());
+        assert!(sanitize_ai_achievement(&mut achievement));
+        assert_eq!(achievement.icon.as_deref(), Some(DEFAULT_ICON));
+        assert_eq!(single_emoji("👨‍💻").as_deref(), Some("👨‍💻"));
+        assert_eq!(single_emoji("🇹🇼🇯🇵").as_deref(), Some("🇹🇼"));
+        assert_eq!(single_emoji("⭐️").as_deref(), Some("⭐️"));
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use rbatis::RBatis;
-use super::common::{AIGeneratedAchievement, AIGeneratedTask, AIGeneratedTaskPlan, AIGeneratedSkillTags, ExpertMatch};
+use super::common::{AIGeneratedAchievement, AIGeneratedTask, AIGeneratedTaskPlan, AIGeneratedSkillTags, ChatImage, ExpertMatch, VISION_UNSUPPORTED};
 
 // AI 服務 trait
 #[async_trait::async_trait]
@@ -33,4 +33,21 @@ pub trait AIService {
         task_description: Option<&str>,
         user_existing_skills: &[String]
     ) -> Result<AIGeneratedSkillTags>;
+
+    // 指定模型是否能接收圖片（只有 OpenAI / OpenRouter 的多模態模型支援）
+    fn supports_vision(&self, _model: &str) -> bool {
+        false
+    }
+
+    // 帶圖片的對話：使用指定的看圖模型，圖片附在最後一則使用者訊息
+    async fn generate_with_image(
+        &self,
+        _model: &str,
+        _system_prompt: &str,
+        _history: &[(String, String)],
+        _message: &str,
+        _image: &ChatImage,
+    ) -> Result<String> {
+        Err(anyhow::anyhow!(VISION_UNSUPPORTED))
+    }
 }
\ No newline at end of file
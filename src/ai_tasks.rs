@@ -1,4 +1,4 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
 use rbatis::RBatis;
 use uuid::Uuid;
 use chrono::{Utc, Datelike};
@@ -6,26 +6,30 @@ use rbs::value;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::models::{Task, User, GenerateTaskRequest, TaskStatus, Achievement, UserAchievement};
 use crate::career_routes::parse_ai_tasks_response;
-use crate::ai_service::{convert_to_achievement_model, AIGeneratedTaskPlan};
+use crate::ai_service::{convert_to_achievement_model, AICallOptions, AIGeneratedTaskPlan, AIService, SharedAIService};
 use crate::achievement_service::AchievementService;
+use crate::task_types::TaskType;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub message: String,
-}
+pub use crate::services::ApiResponse;
 
 // ============= 第一步：AI 生成 JSON =============
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GenerateTaskJsonRequest {
     pub description: String,
+    #[serde(default)]
+    pub parent_task_id: Option<String>,  // 每日任務所屬的父任務，用於參考近期完成紀錄
+    #[serde(flatten)]
+    pub ai_options: AICallOptions,  // 單次請求的模型/服務提供者覆寫
 }
 
+// 參考近期完成紀錄的天數
+const COMPLETION_HISTORY_WINDOW_DAYS: i64 = 14;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateTaskInput {
     pub title: String,
@@ -58,32 +62,79 @@ pub struct CreateTaskFromJsonRequest {
     pub end_date: Option<String>,
     pub completion_target: Option<f64>,
     pub user_id: Option<String>,  // 可選的用戶 ID
+    pub client_request_id: Option<String>,  // 客戶端請求 ID（冪等鍵）
 }
 
-// API 1: AI 生成符合 task_schema.md 的 JSON
-pub async fn generate_task_json(
-    req: web::Json<GenerateTaskJsonRequest>,
-) -> Result<HttpResponse> {
-    // 載入配置
-    let config = crate::config::Config::from_env();
-    
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
-        Ok(service) => service,
+// AI 呼叫失敗的回應：內容被供應商安全政策過濾時回 422 並明確告知，其餘維持 500
+pub fn ai_failure_response(context: &str, e: &anyhow::Error) -> HttpResponse {
+    if crate::ai_service::is_content_filtered(e) {
+        log::warn!("{}: {}", context, e);
+        return HttpResponse::UnprocessableEntity().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "內容被 AI 安全機制過濾，請調整描述後再試".to_string(),
+        });
+    }
+    log::error!("{}: {}", context, e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("{}: {}", context, e),
+    })
+}
+
+/// 取得本次請求使用的 AI 服務（套用模型/服務提供者覆寫）；失敗時回傳應直接送出的回應
+///
+/// 實際使用的模型寫入 request extensions，由 AI 額度中間件加到回應標頭並記錄到 ai_usage_log。
+fn resolve_ai_service(
+    ai: &SharedAIService,
+    http_req: &HttpRequest,
+    options: AICallOptions,
+) -> std::result::Result<Arc<dyn AIService + Send + Sync>, HttpResponse> {
+    if let Err(message) = ai.validate_options(&options, crate::auth::is_admin_request(http_req)) {
+        return Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message,
+        }));
+    }
+    match ai.get_with_options(&options) {
+        Ok((service, served)) => {
+            if served.overridden {
+                log::info!("本次請求指定 AI 模型: {} ({})", served.model, served.provider);
+            }
+            http_req.extensions_mut().insert(served);
+            Ok(service)
+        }
         Err(e) => {
             log::error!("AI 服務初始化失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
                 success: false,
                 data: None,
                 message: format!("AI 服務初始化失敗: {}", e),
-            }));
+            }))
         }
+    }
+}
+
+// API 1: AI 生成符合 task_schema.md 的 JSON
+pub async fn generate_task_json(
+    req: web::Json<GenerateTaskJsonRequest>,
+    ai: web::Data<SharedAIService>,
+    http_req: HttpRequest,
+    query: web::Query<AICallOptions>,
+) -> Result<HttpResponse> {
+    // 取得 AI 服務（可依請求指定模型）
+    let ai_service = match resolve_ai_service(&ai, &http_req, req.ai_options.clone().or(query.into_inner())) {
+        Ok(service) => service,
+        Err(response) => return Ok(response),
     };
     
     // 使用 AI 生成任務 JSON
     match ai_service.generate_task_from_text(&req.description).await {
-        Ok(ai_task) => {
+        Ok(mut ai_task) => {
             log::info!("AI 成功生成任務 JSON: {:?}", ai_task);
+            let sanitized = crate::ai_service::sanitize_ai_task(&mut ai_task);
             
             // 將 AI 生成的任務轉換為符合 schema 的 JSON
             let task_json = CreateTaskInput {
@@ -101,53 +152,87 @@ pub async fn generate_task_json(
                 completion_target: ai_task.completion_target,
             };
             
+            // 額外的 sanitized 欄位不影響前端把 data 原樣送回建立任務
+            let mut data = serde_json::to_value(&task_json).unwrap_or_else(|_| serde_json::json!({}));
+            data["sanitized"] = serde_json::json!(sanitized);
             Ok(HttpResponse::Ok().json(ApiResponse {
                 success: true,
-                data: Some(task_json),
+                data: Some(data),
                 message: "AI 成功生成任務 JSON".to_string(),
             }))
         }
         Err(e) => {
-            log::error!("AI 生成任務 JSON 失敗: {}", e);
-            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 生成任務 JSON 失敗: {}", e),
-            }))
+            Ok(ai_failure_response("AI 生成任務 JSON 失敗", &e))
         }
     }
 }
 
 // API: 專門生成每日任務 JSON（使用針對每日任務優化的提示詞）
 pub async fn generate_daily_task_json(
+    rb: web::Data<RBatis>,
     req: web::Json<GenerateTaskJsonRequest>,
+    ai: web::Data<SharedAIService>,
+    http_req: HttpRequest,
+    query: web::Query<AICallOptions>,
 ) -> Result<HttpResponse> {
-    // 載入配置
-    let config = crate::config::Config::from_env();
-
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得 AI 服務（可依請求指定模型）
+    let ai_service = match resolve_ai_service(&ai, &http_req, req.ai_options.clone().or(query.into_inner())) {
         Ok(service) => service,
-        Err(e) => {
-            log::error!("AI 服務初始化失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 服務初始化失敗: {}", e),
-            }));
+        Err(response) => return Ok(response),
+    };
+
+    // 若指定父任務，整理近期完成紀錄讓 AI 調整難度（完成紀錄會回傳給呼叫者，只有擁有者可以指定）
+    let completion_history = match &req.parent_task_id {
+        Some(parent_task_id) => {
+            let parent_task = match crate::ownership::assert_task_owner(rb.get_ref(), parent_task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+                Ok(task) => task,
+                Err(e) => return Ok(e.into_response()),
+            };
+            let tz = crate::recurring_progress::owner_timezone(rb.get_ref(), &parent_task).await;
+            match collect_completion_history(rb.get_ref(), parent_task_id, tz, COMPLETION_HISTORY_WINDOW_DAYS).await {
+                Ok(history) => Some(history),
+                Err(e) => {
+                    log::warn!("整理父任務 {} 的完成紀錄失敗，略過難度調整: {}", parent_task_id, e);
+                    None
+                }
+            }
         }
+        None => None,
     };
 
+    let ai_input_prompt = crate::ai_service::build_task_generation_prompt(
+        &req.description,
+        None,
+        None,
+        None,
+        None,
+        "",
+        "",
+        completion_history.as_ref(),
+    );
+
     // 使用 AI 生成每日任務 JSON（專門的每日任務方法）
-    match ai_service.generate_daily_task_from_text(&req.description).await {
-        Ok(ai_task) => {
+    match ai_service.generate_daily_task_from_text(&ai_input_prompt).await {
+        Ok(mut ai_task) => {
             log::info!("AI 成功生成每日任務 JSON: {:?}", ai_task);
 
+            // 難度調整只在有完成紀錄時生效，且限制在模板難度 ±1
+            match &completion_history {
+                Some(history) => crate::ai_service::clamp_difficulty_adjustment(&mut ai_task, history.template_difficulty),
+                None => {
+                    ai_task.difficulty_adjustment = None;
+                    ai_task.adjustment_reason = None;
+                }
+            }
+            let sanitized = crate::ai_service::sanitize_ai_task(&mut ai_task);
+            let difficulty_adjustment = ai_task.difficulty_adjustment;
+            let reason = ai_task.adjustment_reason.clone();
+
             // 將 AI 生成的任務轉換為符合 schema 的 JSON
             let task_json = CreateTaskInput {
                 title: ai_task.title.unwrap_or_else(|| "未命名每日任務".to_string()),
                 description: ai_task.description,
-                task_type: Some("daily".to_string()), // 強制設定為 daily
+                task_type: Some(TaskType::Daily.to_string()), // 強制設定為 daily
                 priority: ai_task.priority,
                 difficulty: ai_task.difficulty,
                 experience: ai_task.experience,
@@ -161,35 +246,156 @@ pub async fn generate_daily_task_json(
 
             Ok(HttpResponse::Ok().json(ApiResponse {
                 success: true,
-                data: Some(serde_json::json!({ "task_json": task_json })),
+                data: Some(serde_json::json!({
+                    "task_json": task_json,
+                    "difficulty_adjustment": difficulty_adjustment,
+                    "reason": reason,
+                    "completion_history": completion_history,
+                    "sanitized": sanitized,
+                })),
                 message: "AI 成功生成每日任務 JSON".to_string(),
             }))
         }
         Err(e) => {
-            log::error!("AI 生成每日任務 JSON 失敗: {}", e);
-            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 生成每日任務 JSON 失敗: {}", e),
-            }))
+            Ok(ai_failure_response("AI 生成每日任務 JSON 失敗", &e))
         }
     }
 }
 
+// 整理父任務近 N 天子任務的完成紀錄（完成率、連續天數、已完成平均難度）
+async fn collect_completion_history(
+    rb: &RBatis,
+    parent_task_id: &str,
+    tz: chrono::FixedOffset,
+    window_days: i64,
+) -> Result<crate::ai_service::CompletionHistorySummary, anyhow::Error> {
+    #[derive(Debug, Deserialize)]
+    struct SubtaskRow {
+        task_date: Option<String>,
+        status: Option<i32>,
+        difficulty: Option<i32>,
+    }
+
+    // 每日子任務的 task_date 以擁有者時區蓋章
+    let today = crate::local_date::local_today_in(tz);
+    let window_start = today - chrono::Duration::days(window_days - 1);
+
+    let rows: Vec<SubtaskRow> = rb
+        .query_decode(
+            "SELECT task_date, status, difficulty FROM task
+             WHERE parent_task_id = ? AND task_date IS NOT NULL AND task_date >= ? AND task_date <= ?",
+            vec![
+                rbs::Value::String(parent_task_id.to_string()),
+                rbs::Value::String(window_start.format("%Y-%m-%d").to_string()),
+                rbs::Value::String(today.format("%Y-%m-%d").to_string()),
+            ],
+        )
+        .await?;
+
+    let is_completed = |status: Option<i32>| {
+        status == Some(TaskStatus::Completed.to_i32()) || status == Some(TaskStatus::DailyCompleted.to_i32())
+    };
+
+    let total_tasks = rows.len() as i32;
+    let completed: Vec<&SubtaskRow> = rows.iter().filter(|r| is_completed(r.status)).collect();
+    let completed_tasks = completed.len() as i32;
+    let completion_ratio = if total_tasks > 0 { completed_tasks as f64 / total_tasks as f64 } else { 0.0 };
+
+    let difficulties: Vec<i32> = completed.iter().filter_map(|r| r.difficulty).collect();
+    let avg_completed_difficulty = if difficulties.is_empty() {
+        None
+    } else {
+        Some(difficulties.iter().sum::<i32>() as f64 / difficulties.len() as f64)
+    };
+
+    // 連續天數：從今天（今天尚未完成則從昨天）往回，每天的子任務需全部完成
+    let mut days: HashMap<chrono::NaiveDate, (i32, i32)> = HashMap::new();
+    for row in &rows {
+        if let Some(date) = row.task_date.as_deref().and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+            let entry = days.entry(date).or_insert((0, 0));
+            entry.0 += 1;
+            if is_completed(row.status) {
+                entry.1 += 1;
+            }
+        }
+    }
+    let day_done = |date: &chrono::NaiveDate| days.get(date).is_some_and(|(total, done)| *total > 0 && done == total);
+    let mut check_date = if day_done(&today) { today } else { today - chrono::Duration::days(1) };
+    let mut current_streak = 0;
+    while check_date >= window_start && day_done(&check_date) {
+        current_streak += 1;
+        check_date -= chrono::Duration::days(1);
+    }
+
+    // 模板難度：優先使用重複性任務模板的平均難度，否則使用父任務難度
+    let templates = crate::models::RecurringTaskTemplate::select_by_map(rb, value!{"parent_task_id": parent_task_id}).await?;
+    let template_difficulties: Vec<i32> = templates.iter().filter_map(|t| t.difficulty).collect();
+    let template_difficulty = if !template_difficulties.is_empty() {
+        Some((template_difficulties.iter().sum::<i32>() as f64 / template_difficulties.len() as f64).round() as i32)
+    } else {
+        Task::select_by_map(rb, value!{"id": parent_task_id})
+            .await?
+            .first()
+            .and_then(|t| t.difficulty)
+    };
+
+    Ok(crate::ai_service::CompletionHistorySummary {
+        window_days,
+        total_tasks,
+        completed_tasks,
+        completion_ratio,
+        current_streak,
+        avg_completed_difficulty,
+        template_difficulty,
+    })
+}
+
 // ============= 第二步：JSON 插入資料庫 =============
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InsertTaskRequest {
     pub task_json: CreateTaskInput,
     pub user_id: Option<String>,
+    // 客戶端請求 ID（冪等鍵，亦可用 Idempotency-Key 標頭）
+    pub client_request_id: Option<String>,
 }
 
 // API 2: 將 JSON 轉換為任務並插入資料庫
 pub async fn insert_task_from_json(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<InsertTaskRequest>,
+) -> Result<HttpResponse> {
+    let req = req.into_inner();
+    let db = rb.get_ref().clone();
+    let fingerprint = crate::idempotency::request_fingerprint(&req);
+    crate::idempotency::run_idempotent(
+        &db,
+        &http_req,
+        req.user_id.clone(),
+        req.client_request_id.clone(),
+        "insert_task_from_json",
+        fingerprint,
+        move || insert_task_record(rb, web::Json(req)),
+    )
+    .await
+}
+
+async fn insert_task_record(
     rb: web::Data<RBatis>,
     req: web::Json<InsertTaskRequest>,
 ) -> Result<HttpResponse> {
     let task_input = &req.task_json;
+    let task_type = match task_input.task_type.as_deref().map(str::parse::<TaskType>).transpose() {
+        Ok(task_type) => task_type,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message,
+            }));
+        }
+    };
     
     // 決定使用者 ID（過濾空字串）
     let user_id = if let Some(id) = req.user_id.clone().filter(|s| !s.trim().is_empty()) {
@@ -271,11 +477,11 @@ pub async fn insert_task_from_json(
         description: task_input.description.clone(),
         status: Some(0), // 預設為待處理
         priority: task_input.priority,
-        task_type: task_input.task_type.clone(),
+        task_type: task_type.as_ref().map(TaskType::to_string),
         difficulty: task_input.difficulty,
         experience: task_input.experience,
         parent_task_id: None,
-        is_parent_task: if task_input.task_type == Some("main".to_string()) || task_input.is_recurring.unwrap_or(false) {
+        is_parent_task: if task_type == Some(TaskType::Main) || task_input.is_recurring.unwrap_or(false) {
             Some(1)
         } else {
             Some(0)
@@ -309,6 +515,13 @@ pub async fn insert_task_from_json(
         career_mainline_id: None,
         task_category: None,
         attributes: None,
+        completion_mode: None,
+        version: Some(0),
+        require_proof: Some(0),
+        stake: None,
+        retro_completed: Some(0),
+        requires_confirmation: Some(0),
+        challenge_fail_penalty: crate::reward_config::challenge_fail_penalty_for(task_type.as_ref().map(TaskType::as_str)),
     };
     
     // 儲存主任務到資料庫
@@ -350,11 +563,14 @@ pub async fn insert_task_from_json(
                 
                 // 批量收集要插入的任務
                 let mut tasks_to_insert = Vec::new();
+                let tz = crate::user_settings::user_timezone(rb.get_ref(), &user_id).await;
                 
                 for day_offset in 0..days_to_generate {
                     let current_date = start_date + chrono::Duration::days(day_offset);
-                    let weekday = current_date.weekday();
-                    let date_str = current_date.format("%Y-%m-%d").to_string();
+                    // task_date 以使用者時區的日期為準
+                    let local_date = crate::local_date::local_date_in(current_date, tz);
+                    let weekday = local_date.weekday();
+                    let date_str = local_date.format("%Y-%m-%d").to_string();
                     
                     // 根據重複模式決定是否在這一天建立任務
                     let should_create = match pattern {
@@ -368,7 +584,7 @@ pub async fn insert_task_from_json(
                             weekday == chrono::Weekday::Sun
                         },
                         "weekly" => {
-                            weekday == start_date.weekday()
+                            weekday == crate::local_date::local_date_in(start_date, tz).weekday()
                         },
                         _ => false,
                     };
@@ -381,7 +597,7 @@ pub async fn insert_task_from_json(
                             description: task_input.description.clone(),
                             status: Some(0), // 所有新創建的任務都設為待完成
                             priority: task_input.priority,
-                            task_type: Some("daily_recurring".to_string()),
+                            task_type: Some(TaskType::DailyRecurring.to_string()),
                             difficulty: task_input.difficulty,
                             experience: task_input.experience,
                             parent_task_id: task.id.clone(),
@@ -403,6 +619,13 @@ pub async fn insert_task_from_json(
                             career_mainline_id: None,
                             task_category: None,
                             attributes: None,
+                            completion_mode: None,
+                            version: Some(0),
+                            require_proof: Some(0),
+                            stake: None,
+                            retro_completed: Some(0),
+                            requires_confirmation: Some(0),
+                            challenge_fail_penalty: None,
                         };
                         
                         tasks_to_insert.push(daily_task);
@@ -439,24 +662,24 @@ pub async fn insert_task_from_json(
                             task.description.as_ref().map(|d| rbs::Value::String(d.clone())).unwrap_or(rbs::Value::Null),
                             rbs::Value::I32(task.status.unwrap_or(0)),
                             rbs::Value::I32(task.priority.unwrap_or(1)),
-                            rbs::Value::String(task.task_type.clone().unwrap_or("daily_recurring".to_string())),
+                            rbs::Value::String(task.task_type.clone().unwrap_or_else(|| TaskType::DailyRecurring.to_string())),
                             rbs::Value::I32(task.difficulty.unwrap_or(1)),
                             rbs::Value::I32(task.experience.unwrap_or(10)),
                             task.parent_task_id.as_ref().map(|p| rbs::Value::String(p.clone())).unwrap_or(rbs::Value::Null),
                             rbs::Value::Bool(task.is_parent_task.unwrap_or(0) == 1),
                             rbs::Value::I32(task.task_order.unwrap_or(0)),
-                            task.due_date.as_ref().map(|d| rbs::Value::String(d.format("%Y-%m-%d %H:%M:%S%.3f").to_string())).unwrap_or(rbs::Value::Null),
-                            rbs::Value::String(task.created_at.unwrap().format("%Y-%m-%d %H:%M:%S%.3f").to_string()),
-                            rbs::Value::String(task.updated_at.unwrap().format("%Y-%m-%d %H:%M:%S%.3f").to_string()),
+                            task.due_date.as_ref().map(|d| rbs::Value::String(d.to_rfc3339())).unwrap_or(rbs::Value::Null),
+                            rbs::Value::String(task.created_at.unwrap().to_rfc3339()),
+                            rbs::Value::String(task.updated_at.unwrap().to_rfc3339()),
                             rbs::Value::Bool(task.is_recurring.unwrap_or(0) == 1),
                             task.recurrence_pattern.as_ref().map(|r| rbs::Value::String(r.clone())).unwrap_or(rbs::Value::Null),
-                            task.start_date.as_ref().map(|s| rbs::Value::String(s.format("%Y-%m-%d %H:%M:%S%.3f").to_string())).unwrap_or(rbs::Value::Null),
-                            task.end_date.as_ref().map(|e| rbs::Value::String(e.format("%Y-%m-%d %H:%M:%S%.3f").to_string())).unwrap_or(rbs::Value::Null),
+                            task.start_date.as_ref().map(|s| rbs::Value::String(s.to_rfc3339())).unwrap_or(rbs::Value::Null),
+                            task.end_date.as_ref().map(|e| rbs::Value::String(e.to_rfc3339())).unwrap_or(rbs::Value::Null),
                             task.completion_target.map(|c| rbs::Value::F64(c)).unwrap_or(rbs::Value::Null),
                             task.completion_rate.map(|c| rbs::Value::F64(c)).unwrap_or(rbs::Value::Null),
                             task.task_date.as_ref().map(|t| rbs::Value::String(t.clone())).unwrap_or(rbs::Value::Null),
                             rbs::Value::I32(task.cancel_count.unwrap_or(0)),
-                            task.last_cancelled_at.as_ref().map(|l| rbs::Value::String(l.format("%Y-%m-%d %H:%M:%S%.3f").to_string())).unwrap_or(rbs::Value::Null),
+                            task.last_cancelled_at.as_ref().map(|l| rbs::Value::String(l.to_rfc3339())).unwrap_or(rbs::Value::Null),
                             task.skill_tags.as_ref().map(|s| rbs::Value::String(serde_json::to_string(s).unwrap_or("[]".to_string()))).unwrap_or(rbs::Value::Null),
                         ]);
                     }
@@ -530,17 +753,17 @@ pub async fn insert_task_from_json(
 pub async fn generate_task_with_ai(
     rb: web::Data<RBatis>,
     req: web::Json<GenerateTaskRequest>,
+    ai: web::Data<SharedAIService>,
 ) -> Result<HttpResponse> {
     // 先生成 JSON
     let json_req = GenerateTaskJsonRequest {
         description: req.description.clone(),
+        parent_task_id: None,
+        ai_options: AICallOptions::default(),
     };
     
-    // 載入配置
-    let config = crate::config::Config::from_env();
-    
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得共享的 AI 服務
+    let ai_service = match ai.get() {
         Ok(service) => service,
         Err(e) => {
             log::error!("AI 服務初始化失敗: {}", e);
@@ -554,7 +777,9 @@ pub async fn generate_task_with_ai(
     
     // 使用 AI 生成任務
     match ai_service.generate_task_from_text(&req.description).await {
-        Ok(ai_task) => {
+        Ok(mut ai_task) => {
+            crate::ai_service::sanitize_ai_task(&mut ai_task);
+
             // 轉換為 CreateTaskInput
             let task_input = CreateTaskInput {
                 title: ai_task.title.unwrap_or_else(|| "未命名任務".to_string()),
@@ -575,17 +800,13 @@ pub async fn generate_task_with_ai(
             let insert_req = InsertTaskRequest {
                 task_json: task_input,
                 user_id: req.user_id.clone(),
+                client_request_id: None,
             };
             
-            insert_task_from_json(rb, web::Json(insert_req)).await
+            insert_task_record(rb, web::Json(insert_req)).await
         }
         Err(e) => {
-            log::error!("AI 生成任務失敗: {}", e);
-            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 生成任務失敗: {}", e),
-            }))
+            Ok(ai_failure_response("AI 生成任務失敗", &e))
         }
     }
 }
@@ -738,12 +959,10 @@ pub struct GenerateTaskFromChatRequest {
 
 pub async fn generate_task_from_chat(
     req: web::Json<GenerateTaskFromChatRequest>,
+    ai: web::Data<SharedAIService>,
 ) -> Result<HttpResponse> {
-    // 載入配置
-    let config = crate::config::Config::from_env();
-    
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得共享的 AI 服務
+    let ai_service = match ai.get() {
         Ok(service) => service,
         Err(e) => {
             return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
@@ -793,11 +1012,7 @@ pub async fn generate_task_from_chat(
             }
         }
         Err(e) => {
-            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("生成任務失敗: {}", e),
-            }))
+            Ok(ai_failure_response("生成任務失敗", &e))
         }
     }
 }
@@ -806,6 +1021,7 @@ pub async fn generate_task_from_chat(
 
 // API 3: 直接從 JSON 創建任務（用戶友好版本）
 pub async fn create_task_from_json(
+    http_req: HttpRequest,
     rb: web::Data<RBatis>,
     req: web::Json<CreateTaskFromJsonRequest>,
 ) -> Result<HttpResponse> {
@@ -829,10 +1045,22 @@ pub async fn create_task_from_json(
     let insert_req = InsertTaskRequest {
         task_json: task_input,
         user_id: req.user_id.clone(),
+        client_request_id: req.client_request_id.clone(),
     };
     
-    // 調用現有的插入邏輯
-    insert_task_from_json(rb, web::Json(insert_req)).await
+    // 調用現有的插入邏輯（以原始請求計算冪等指紋）
+    let db = rb.get_ref().clone();
+    let fingerprint = crate::idempotency::request_fingerprint(&req.into_inner());
+    crate::idempotency::run_idempotent(
+        &db,
+        &http_req,
+        insert_req.user_id.clone(),
+        insert_req.client_request_id.clone(),
+        "create_task_from_json",
+        fingerprint,
+        move || insert_task_record(rb, web::Json(insert_req)),
+    )
+    .await
 }
 
 // ============= 自動成就生成功能 =============
@@ -894,12 +1122,16 @@ pub struct GeneratedAchievementResponse {
     pub achievement: Achievement,
     pub is_unlocked: bool,
     pub task_summary: TaskSummaryData,
+    // AI 生成內容是否經過清理（截斷、移除套話等）
+    pub sanitized: bool,
 }
 
 // API: 從用戶任務數據自動生成成就
 pub async fn generate_achievement_from_tasks(
+    http_req: actix_web::HttpRequest,
     rb: web::Data<RBatis>,
-    path: web::Path<String>, // user_id
+    path: web::Path<String>, // user_id,
+    ai: web::Data<SharedAIService>,
 ) -> Result<HttpResponse> {
     let user_id = path.into_inner();
     
@@ -911,6 +1143,9 @@ pub async fn generate_achievement_from_tasks(
             message: "用戶ID不能為空".to_string(),
         }));
     }
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
     
     log::info!("開始為用戶 {} 生成成就", user_id);
     
@@ -941,8 +1176,7 @@ pub async fn generate_achievement_from_tasks(
     log::info!("生成的 AI 提示長度: {} 字符", ai_prompt.len());
     
     // 4. 調用 AI 生成成就
-    let config = crate::config::Config::from_env();
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    let ai_service = match ai.get() {
         Ok(service) => service,
         Err(e) => {
             log::error!("AI 服務初始化失敗: {}", e);
@@ -957,12 +1191,7 @@ pub async fn generate_achievement_from_tasks(
     let ai_achievement = match ai_service.generate_achievement_from_text(&ai_prompt).await {
         Ok(achievement) => achievement,
         Err(e) => {
-            log::error!("AI 生成成就失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 生成成就失敗: {}", e),
-            }));
+            return Ok(ai_failure_response("AI 生成成就失敗", &e));
         }
     };
     
@@ -979,7 +1208,7 @@ pub async fn generate_achievement_from_tasks(
     log::info!("成就「{}」通過相似性檢查", ai_achievement.name);
     
     // 6. 轉換為數據庫模型並保存
-    let achievement_model = convert_to_achievement_model(ai_achievement);
+    let (achievement_model, sanitized) = convert_to_achievement_model(ai_achievement);
     
     match Achievement::insert(rb.get_ref(), &achievement_model).await {
         Ok(_) => {
@@ -1005,6 +1234,7 @@ pub async fn generate_achievement_from_tasks(
                     achievement: achievement_model,
                     is_unlocked,
                     task_summary: task_data,
+                    sanitized,
                 }),
                 message: format!(
                     "成功生成成就「{}」{}",
@@ -1446,6 +1676,8 @@ pub struct GenerateTaskWithExpertRequest {
     pub expert_outputs: Option<HashMap<String, String>>,
     pub skill_level_label: Option<String>,
     pub learning_duration_label: Option<String>,
+    #[serde(flatten)]
+    pub ai_options: AICallOptions,  // 單次請求的模型/服務提供者覆寫
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1473,6 +1705,8 @@ pub struct ExpertAnalysisRequest {
     pub expert_description: String,
     pub analysis_type: String, // "analyze", "goals", "resources"
     pub user_id: Option<String>,
+    #[serde(flatten)]
+    pub ai_options: AICallOptions,  // 單次請求的模型/服務提供者覆寫
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1505,6 +1739,9 @@ pub struct AnalysisResource {
 pub async fn generate_task_with_expert(
     rb: web::Data<RBatis>,
     req: web::Json<GenerateTaskWithExpertRequest>,
+    ai: web::Data<SharedAIService>,
+    http_req: HttpRequest,
+    query: web::Query<AICallOptions>,
 ) -> Result<HttpResponse> {
     let prompt_description = req.prompt_description.clone().unwrap_or_else(|| req.description.clone());
     let skill_label = req.skill_level_label.clone().unwrap_or_else(|| "".to_string());
@@ -1518,20 +1755,10 @@ pub async fn generate_task_with_expert(
         req.selected_directions.as_ref().map(|d| d.iter().map(|item| item.title.clone()).collect::<Vec<_>>())
     );
 
-    // 載入配置
-    let config = crate::config::Config::from_env();
-    
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得 AI 服務（可依請求指定模型）
+    let ai_service = match resolve_ai_service(&ai, &http_req, req.ai_options.clone().or(query.into_inner())) {
         Ok(service) => service,
-        Err(e) => {
-            log::error!("AI 服務初始化失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 服務初始化失敗: {}", e),
-            }));
-        }
+        Err(response) => return Ok(response),
     };
     
     let expert_match = if let Some(existing_match) = req.expert_match.clone() {
@@ -1551,12 +1778,13 @@ pub async fn generate_task_with_expert(
     
     let ai_input_prompt = crate::ai_service::build_task_generation_prompt(
         &prompt_description,
-        &expert_match,
+        Some(&expert_match),
         req.selected_options.clone(),
         req.selected_directions.clone(),
         req.expert_outputs.clone(),
         &skill_label,
-        &duration_label
+        &duration_label,
+        None,
     );
     log::info!("[generate_task_with_expert] 構建的提示詞長度: {}", ai_input_prompt.len());
     
@@ -1568,18 +1796,17 @@ pub async fn generate_task_with_expert(
     log::info!("[generate_task_with_expert] 送往 AI 描述長度: {}", ai_input_prompt.len());
 
     let ai_task_plan = match ai_service.generate_task_with_expert(&ai_input_prompt, &expert_match).await {
-        Ok(task_plan) => {
+        Ok(mut task_plan) => {
             log::info!("專家成功生成任務計劃: {:?} (包含 {} 個子任務)",
                       task_plan.main_task.title, task_plan.subtasks.len());
+            crate::ai_service::sanitize_ai_task(&mut task_plan.main_task);
+            for subtask in task_plan.subtasks.iter_mut() {
+                crate::ai_service::sanitize_ai_task(subtask);
+            }
             task_plan
         }
         Err(e) => {
-            log::error!("專家生成任務計劃失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("專家生成任務計劃失敗: {}", e),
-            }));
+            return Ok(ai_failure_response("專家生成任務計劃失敗", &e));
         }
     };
 
@@ -1695,12 +1922,10 @@ pub async fn generate_task_with_expert(
 // API: 只匹配專家（不生成任務）
 pub async fn match_expert_only(
     req: web::Json<MatchExpertRequest>,
+    ai: web::Data<SharedAIService>,
 ) -> Result<HttpResponse> {
-    // 載入配置
-    let config = crate::config::Config::from_env();
-    
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得共享的 AI 服務
+    let ai_service = match ai.get() {
         Ok(service) => service,
         Err(e) => {
             log::error!("AI 服務初始化失敗: {}", e);
@@ -1721,12 +1946,7 @@ pub async fn match_expert_only(
             match_result
         }
         Err(e) => {
-            log::error!("專家匹配失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("專家匹配失敗: {}", e),
-            }));
+            return Ok(ai_failure_response("專家匹配失敗", &e));
         }
     };
     
@@ -1760,6 +1980,7 @@ pub struct GenerateSubtasksResponse {
 pub async fn generate_subtasks_for_task(
     rb: web::Data<RBatis>,
     req: web::Json<GenerateSubtasksRequest>,
+    ai: web::Data<SharedAIService>,
 ) -> Result<HttpResponse> {
     log::info!(
         "[generate_subtasks_for_task] 開始為任務 {} 生成子任務",
@@ -1843,15 +2064,13 @@ pub async fn generate_subtasks_for_task(
             log::info!("[generate_subtasks_for_task] 已標記任務 {} 為子任務生成中", req.parent_task_id);
         }
 
-        // 啟動異步任務處理
-        tokio::spawn(async move {
+        // 啟動異步任務處理（沿用請求的語言）
+        let shared_ai_service = ai.get();
+        tokio::spawn(crate::language::in_current(async move {
             log::info!("[異步任務] 開始生成子任務 for task {}", parent_task_id_clone);
 
-            // 載入配置
-            let config = crate::config::Config::from_env();
-
-            // 創建 AI 服務
-            let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+            // 取得共享的 AI 服務
+            let ai_service = match shared_ai_service {
                 Ok(service) => service,
                 Err(e) => {
                     log::error!("[異步任務] AI 服務初始化失敗: {}", e);
@@ -1877,8 +2096,9 @@ pub async fn generate_subtasks_for_task(
             let mut task_order = 1;
             let mut created_count = 0;
 
-            for (index, ai_subtask) in subtasks.into_iter().enumerate() {
+            for (index, mut ai_subtask) in subtasks.into_iter().enumerate() {
                 log::info!("[異步任務] 處理第 {} 個子任務: {:?}", index + 1, ai_subtask.title);
+                crate::ai_service::sanitize_ai_task(&mut ai_subtask);
 
                 let subtask_id = uuid::Uuid::new_v4().to_string();
 
@@ -1911,6 +2131,13 @@ pub async fn generate_subtasks_for_task(
                     cancel_count: Some(0),
                     last_cancelled_at: None,
                     attributes: None,
+                    completion_mode: None,
+                    version: Some(0),
+                    require_proof: Some(0),
+                    stake: None,
+                    retro_completed: Some(0),
+                    requires_confirmation: Some(0),
+                    challenge_fail_penalty: None,
                 };
 
                 if let Err(e) = Task::insert(&rb_clone, &subtask).await {
@@ -1944,7 +2171,7 @@ pub async fn generate_subtasks_for_task(
                     log::warn!("[異步任務] 清除生成中標記失敗: {}", e);
                 }
             }
-        });
+        }));
 
         // 立即返回成功響應
         return Ok(HttpResponse::Ok().json(ApiResponse {
@@ -1967,8 +2194,9 @@ pub async fn generate_subtasks_for_task(
 
     log::info!("[generate_subtasks_for_task] 準備創建 {} 個子任務", subtasks_to_create.len());
 
-    for (index, ai_subtask) in subtasks_to_create.into_iter().enumerate() {
+    for (index, mut ai_subtask) in subtasks_to_create.into_iter().enumerate() {
         log::info!("[generate_subtasks_for_task] 處理第 {} 個子任務: {:?}", index + 1, ai_subtask.title);
+        crate::ai_service::sanitize_ai_task(&mut ai_subtask);
 
         let subtask_id = uuid::Uuid::new_v4().to_string();
 
@@ -2001,6 +2229,13 @@ pub async fn generate_subtasks_for_task(
             cancel_count: Some(0),
             last_cancelled_at: None,
             attributes: None,
+            completion_mode: None,
+            version: Some(0),
+            require_proof: Some(0),
+            stake: None,
+            retro_completed: Some(0),
+            requires_confirmation: Some(0),
+            challenge_fail_penalty: None,
         };
 
         // 插入子任務到資料庫
@@ -2050,21 +2285,14 @@ pub async fn generate_subtasks_for_task(
 // API: 專家分析
 pub async fn expert_analysis(
     req: web::Json<ExpertAnalysisRequest>,
+    ai: web::Data<SharedAIService>,
+    http_req: HttpRequest,
+    query: web::Query<AICallOptions>,
 ) -> Result<HttpResponse> {
-    // 載入配置
-    let config = crate::config::Config::from_env();
-    
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得 AI 服務（可依請求指定模型）
+    let ai_service = match resolve_ai_service(&ai, &http_req, req.ai_options.clone().or(query.into_inner())) {
         Ok(service) => service,
-        Err(e) => {
-            log::error!("AI 服務初始化失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("AI 服務初始化失敗: {}", e),
-            }));
-        }
+        Err(response) => return Ok(response),
     };
     
     // 創建一個臨時的專家對象，使用AI返回的信息
@@ -2083,12 +2311,7 @@ pub async fn expert_analysis(
             result
         }
         Err(e) => {
-            log::error!("專家分析失敗: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("專家分析失敗: {}", e),
-            }));
+            return Ok(ai_failure_response("專家分析失敗", &e));
         }
     };
     
@@ -2210,12 +2433,10 @@ pub struct ClassifyIntentResponse {
 /// 判斷用戶輸入是「詳細任務描述」還是「模糊目標」
 pub async fn classify_user_intent(
     req: web::Json<ClassifyIntentRequest>,
+    ai: web::Data<SharedAIService>,
 ) -> Result<HttpResponse> {
-    // 載入配置
-    let config = crate::config::Config::from_env();
-
-    // 創建 AI 服務
-    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+    // 取得共享的 AI 服務
+    let ai_service = match ai.get() {
         Ok(service) => service,
         Err(e) => {
             log::error!("AI 服務初始化失敗: {}", e);
@@ -2239,12 +2460,280 @@ pub async fn classify_user_intent(
             }))
         }
         Err(e) => {
-            log::error!("意圖分類失敗: {}", e);
-            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                message: format!("意圖分類失敗: {}", e),
-            }))
+            Ok(ai_failure_response("意圖分類失敗", &e))
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::json;
+
+    use crate::ai_service::SharedAIService;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+
+    #[actix_web::test]
+    async fn test_ai_task_routes_with_mock_service() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(MockAIService::default())).await;
+        let user = test_utils::create_user(&app, "dreamer").await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/generate-json")
+            .insert_header(user.auth())
+            .set_json(json!({"description": "我想養成閱讀習慣"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["title"], "每天閱讀 30 分鐘");
+        assert_eq!(body["data"]["recurrence_pattern"], "daily");
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/match-expert")
+            .insert_header(user.auth())
+            .set_json(json!({"description": "我想一個月讀完三本書", "user_id": user.id}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["expert_match"]["expert"]["name"], "閱讀教練");
+    }
+
+    // 父任務 p1 屬於 u1，依 (距今天數, 狀態, 難度) 建立子任務
+    async fn seed_history(rb: &rbatis::RBatis, subtasks: &[(i64, i32, i32)]) {
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, difficulty, is_parent_task, is_recurring) VALUES ('p1', 'u1', '每日背單字', 0, 2, 1, 1)",
+            vec![],
+        )
+        .await
+        .unwrap();
+        let today = crate::local_date::local_today();
+        for (i, (days_ago, status, difficulty)) in subtasks.iter().enumerate() {
+            let date = (today - chrono::Duration::days(*days_ago)).format("%Y-%m-%d").to_string();
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, status, difficulty, parent_task_id, task_date) VALUES (?, 'u1', '背 10 個單字', ?, ?, 'p1', ?)",
+                vec![
+                    rbs::Value::String(format!("s{}", i)),
+                    rbs::Value::I32(*status),
+                    rbs::Value::I32(*difficulty),
+                    rbs::Value::String(date),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_completion_history_summary() {
+        let rb = test_utils::setup_db().await;
+        // 今天未完成、昨天與前天完成、3 天前只完成一半、4 天前完成、20 天前（窗口外）完成
+        seed_history(&rb, &[(0, 0, 2), (1, 6, 2), (2, 6, 3), (3, 6, 2), (3, 0, 2), (4, 2, 3), (20, 6, 5)]).await;
+        rb.exec(
+            "INSERT INTO recurring_task_template (id, parent_task_id, title, difficulty) VALUES ('t1', 'p1', '背單字', 2), ('t2', 'p1', '聽力', 3)",
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        let history = super::collect_completion_history(&rb, "p1", crate::local_date::default_timezone(), 14).await.unwrap();
+        assert_eq!(history.window_days, 14);
+        assert_eq!(history.total_tasks, 6);
+        assert_eq!(history.completed_tasks, 4);
+        assert!((history.completion_ratio - 4.0 / 6.0).abs() < 1e-9);
+        assert_eq!(history.avg_completed_difficulty, Some(2.5));
+        // 模板平均難度 2.5 四捨五入
+        assert_eq!(history.template_difficulty, Some(3));
+        // 今天尚未完成時從昨天起算，3 天前未全部完成即中斷
+        assert_eq!(history.current_streak, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_completion_history_streak_counts_today_and_falls_back_to_parent_difficulty() {
+        let rb = test_utils::setup_db().await;
+        seed_history(&rb, &[(0, 6, 1), (1, 6, 1), (2, 6, 1), (4, 6, 1)]).await;
+
+        let history = super::collect_completion_history(&rb, "p1", crate::local_date::default_timezone(), 14).await.unwrap();
+        assert_eq!(history.current_streak, 3);
+        assert_eq!(history.completion_ratio, 1.0);
+        assert_eq!(history.template_difficulty, Some(2));
+
+        // 沒有任何子任務時連續天數為 0，不會除以零
+        let history = super::collect_completion_history(&rb, "missing", crate::local_date::default_timezone(), 14).await.unwrap();
+        assert_eq!((history.total_tasks, history.current_streak), (0, 0));
+        assert_eq!(history.completion_ratio, 0.0);
+        assert_eq!(history.avg_completed_difficulty, None);
+    }
+
+    #[actix_web::test]
+    async fn test_daily_task_json_rejects_other_users_parent_task() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(MockAIService::default())).await;
+        let owner = test_utils::create_user(&app, "habit_owner").await;
+        let intruder = test_utils::create_user(&app, "habit_intruder").await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(owner.auth())
+            .set_json(json!({"user_id": owner.id, "title": "每日背單字", "task_type": "daily", "is_recurring": 1, "recurrence_pattern": "daily", "experience": 10}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let parent_task_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        let generate = |user: &test_utils::TestUser| {
+            test::TestRequest::post()
+                .uri("/api/tasks/generate-daily-task-json")
+                .insert_header(user.auth())
+                .set_json(json!({"description": "每天背單字", "parent_task_id": parent_task_id}))
+                .to_request()
+        };
+        let (status, body) = call_json(&app, generate(&intruder)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", body);
+        assert!(body["data"].is_null());
+
+        let (status, body) = call_json(&app, generate(&owner)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["completion_history"]["window_days"], 14);
+    }
+
+    #[actix_web::test]
+    async fn test_expert_routes_send_expert_description_to_ai() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["建議先從每天 10 頁開始"]);
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "reader").await;
+
+        let expert_match = json!({
+            "expert": {
+                "name": "速讀教練",
+                "description": "專精於速讀與記憶技巧",
+                "expertise_areas": ["閱讀"],
+                "emoji": "📖"
+            },
+            "ai_expert_name": "速讀教練",
+            "ai_expert_description": "專精於速讀與記憶技巧"
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/generate-with-expert")
+            .insert_header(user.auth())
+            .set_json(json!({
+                "description": "我想一個月讀完三本書",
+                "user_id": user.id,
+                "expert_name": "速讀教練",
+                "expert_description": "專精於速讀與記憶技巧",
+                "expert_match": expert_match,
+            }))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let prompts = mock.prompts("generate_task_with_expert");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("專精於速讀與記憶技巧"), "{}", prompts[0]);
+        assert!(prompts[0].contains("我想一個月讀完三本書"), "{}", prompts[0]);
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/expert-analysis")
+            .insert_header(user.auth())
+            .set_json(json!({
+                "description": "我想一個月讀完三本書",
+                "expert_name": "速讀教練",
+                "expert_description": "專精於速讀與記憶技巧",
+                "analysis_type": "analyze",
+                "user_id": user.id,
+            }))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let prompts = mock.prompts("analyze_with_expert");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].starts_with("[analyze] 速讀教練：專精於速讀與記憶技巧"), "{}", prompts[0]);
+    }
+
+    #[actix_web::test]
+    async fn test_model_override_is_validated_echoed_and_logged() {
+        let rb = test_utils::setup_db().await;
+        let ai = SharedAIService::new(Arc::new(MockAIService::default())).with_model_allowlist(&["deepseek/deepseek-chat"]);
+        let app = test_utils::init_app_with_shared_ai(&rb, ai).await;
+        let user = test_utils::create_user(&app, "tinkerer").await;
+
+        let generate = |uri: &str, body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(uri)
+                .insert_header(user.auth())
+                .set_json(body)
+                .to_request()
+        };
+
+        // 允許清單外的模型與非管理員指定服務提供者都回傳 400
+        let req = generate("/api/tasks/generate-json?model=openai/gpt-4o", json!({"description": "我想養成閱讀習慣"}));
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(body["message"], "不允許使用的模型: openai/gpt-4o");
+        let req = generate(
+            "/api/tasks/generate-json",
+            json!({"description": "我想養成閱讀習慣", "model_override": "deepseek/deepseek-chat", "provider_override": "OpenAI"}),
+        );
+        assert_eq!(call_json(&app, req).await.0, StatusCode::BAD_REQUEST);
+
+        let req = generate("/api/tasks/generate-json?model=deepseek/deepseek-chat", json!({"description": "我想養成閱讀習慣"}));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(crate::ai_quota::MODEL_HEADER).unwrap(), "deepseek/deepseek-chat");
+
+        // 未指定時使用設定的模型，紀錄中標記為非覆寫
+        let req = generate("/api/tasks/generate-json", json!({"description": "我想養成閱讀習慣"}));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(crate::ai_quota::MODEL_HEADER).is_some());
+
+        let logs: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT model, is_override, response_status FROM ai_usage_log WHERE user_id = ? AND is_override = 1",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0]["model"], "deepseek/deepseek-chat");
+        assert_eq!(logs[0]["response_status"], 200);
+    }
+
+    #[actix_web::test]
+    async fn test_content_filtered_error_returns_422_and_refunds_quota() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(MockAIService::content_filtered())).await;
+        let user = test_utils::create_user(&app, "filtered").await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/generate-json")
+            .insert_header(user.auth())
+            .set_json(json!({"description": "不當內容"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        assert_eq!(body["success"], false);
+        assert!(body["message"].as_str().unwrap().contains("安全機制過濾"), "{}", body);
+
+        // 被過濾的請求不計入額度
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users/{}/ai-quota", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let tasks = body["data"]["quotas"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|q| q["category"] == "task_generation")
+            .unwrap();
+        assert_eq!(tasks["used"], 0);
+    }
+}
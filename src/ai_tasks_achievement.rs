@@ -1,8 +1,16 @@
 // AI 自動成就生成 - 根據任務生成對應成就
 
 use rbatis::RBatis;
-use crate::models::{Task, Achievement};
-use crate::ai_service::convert_to_achievement_model;
+use rbs::value;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use crate::models::{Task, Achievement, CareerMainlines};
+use crate::ai_service::{convert_to_achievement_model, SharedAIService};
+
+// 每條職業主線批次生成的成就數量上限
+const MAINLINE_ACHIEVEMENTS_MAX: usize = 8;
+// 少於此數量時記錄警告（仍會保存）
+const MAINLINE_ACHIEVEMENTS_MIN: usize = 5;
 
 /// 根據任務內容生成對應的成就
 /// 此函數會分析任務的標題、描述、類型等信息，使用 AI 生成一個與任務完成相關的成就
@@ -15,6 +23,12 @@ pub async fn generate_achievement_for_task(
     let task_type = task.task_type.as_deref().unwrap_or("daily");
     let difficulty = task.difficulty.unwrap_or(1);
 
+    // 職業主線任務由 generate_achievements_for_mainline 批次處理
+    if task.career_mainline_id.is_some() {
+        log::debug!("任務「{}」屬於職業主線，跳過單一任務成就生成", task_title);
+        return Ok(None);
+    }
+
     log::info!("為任務「{}」生成對應成就", task_title);
 
     // 構建 AI 提示詞
@@ -82,7 +96,7 @@ pub async fn generate_achievement_for_task(
             log::info!("✨ 為任務「{}」生成成就：「{}」", task_title, ai_achievement.name);
 
             // 轉換為數據庫模型
-            let mut achievement_model = convert_to_achievement_model(ai_achievement);
+            let (mut achievement_model, _) = convert_to_achievement_model(ai_achievement);
 
             // 設置 related_task_id，標記這個成就與特定任務相關
             achievement_model.related_task_id = task.id.clone();
@@ -106,11 +120,304 @@ pub async fn generate_achievement_for_task(
     }
 }
 
-/// 異步生成任務對應的成就（不阻塞主流程）
-pub fn spawn_generate_achievement_for_task(rb: RBatis, task: Task) {
-    tokio::spawn(async move {
+/// 經由背景工作佇列生成任務對應的成就（不阻塞主流程）；佇列已滿時回傳 false
+pub fn spawn_generate_achievement_for_task(rb: RBatis, task: Task) -> bool {
+    crate::background_jobs::submit("generate_achievement_for_task", async move {
         if let Err(e) = generate_achievement_for_task(&rb, &task).await {
             log::error!("異步生成成就失敗: {}", e);
         }
-    });
+    })
+}
+
+// AI 為職業主線提出的里程碑成就
+#[derive(Debug, Deserialize)]
+struct MainlineAchievementProposal {
+    name: String,
+    description: Option<String>,
+    icon: Option<String>,
+    related_task_title: Option<String>,
+    experience_reward: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MainlineAchievementsResponse {
+    #[serde(default)]
+    achievements: Vec<MainlineAchievementProposal>,
+}
+
+/// 為整條職業主線批次生成 5-8 個里程碑成就（單次 AI 呼叫）
+/// 已存在於同一主線的同名成就會被略過
+pub async fn generate_achievements_for_mainline(
+    rb: &RBatis,
+    ai: &SharedAIService,
+    mainline_id: &str,
+) -> Result<Vec<Achievement>, anyhow::Error> {
+    let mainline = CareerMainlines::select_by_map(rb, value!{"id": mainline_id})
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("找不到職業主線: {}", mainline_id))?;
+    let career = mainline.selected_career.clone().unwrap_or_else(|| "職業主線".to_string());
+
+    let tasks: Vec<Task> = rb
+        .query_decode(
+            "SELECT * FROM task WHERE career_mainline_id = ? AND parent_task_id IS NOT NULL ORDER BY task_order ASC",
+            vec![rbs::Value::String(mainline_id.to_string())],
+        )
+        .await?;
+
+    if tasks.is_empty() {
+        log::info!("職業主線 {} 沒有子任務，跳過成就生成", mainline_id);
+        return Ok(Vec::new());
+    }
+
+    // 依 task_category 分組列出任務
+    let mut grouped: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for task in &tasks {
+        let category = task.task_category.clone().unwrap_or_else(|| "未分類".to_string());
+        grouped.entry(category).or_default().push(task.title.clone().unwrap_or_default());
+    }
+    let tasks_list = grouped
+        .iter()
+        .map(|(category, titles)| {
+            let lines = titles.iter().map(|t| format!("  - {}", t)).collect::<Vec<_>>().join("\n");
+            format!("【{}】\n{}", category, lines)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let xp_range = crate::reward_config::current().career_achievement_xp;
+    let ai_prompt = format!(
+        r#"你是專業的遊戲化成就設計師。請為「{}」職業主線生成 {}-{} 個里程碑成就，涵蓋整條學習路線。
+
+## 任務列表（依分類）
+{}
+
+## 設計原則
+1. 每個成就對應一個有意義的里程碑，並關聯到上面列表中的一個任務
+2. 成就之間不可重複或過於相似，名稱要有趣、簡潔
+3. 由入門到進階分布，涵蓋不同分類
+4. experience_reward 根據里程碑難度設定為 {}-{}
+5. 成就名稱與描述的語言：{}
+
+## 輸出格式（只回傳 JSON）
+{{
+  "achievements": [
+    {{
+      "name": "成就名稱",
+      "description": "成就描述（如何達成）",
+      "icon": "emoji 圖標",
+      "related_task_title": "關聯的任務標題（必須與列表完全一致）",
+      "experience_reward": 50
+    }}
+  ]
+}}"#,
+        career,
+        MAINLINE_ACHIEVEMENTS_MIN,
+        MAINLINE_ACHIEVEMENTS_MAX,
+        tasks_list,
+        xp_range.min,
+        xp_range.max,
+        crate::language::directive()
+    );
+
+    let ai_service = ai.get()?;
+    let ai_response = ai_service.generate_with_model(ai.normal_model(), &ai_prompt).await?;
+
+    let cleaned = ai_response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let parsed: MainlineAchievementsResponse = serde_json::from_str(cleaned)
+        .map_err(|e| anyhow::anyhow!("解析主線成就 JSON 失敗: {}", e))?;
+
+    if parsed.achievements.len() < MAINLINE_ACHIEVEMENTS_MIN {
+        log::warn!("職業主線 {} 只生成了 {} 個成就", mainline_id, parsed.achievements.len());
+    }
+
+    // 同一主線已有的成就名稱（忽略大小寫與前後空白）
+    let existing = Achievement::select_by_map(rb, value!{"career_mainline_id": mainline_id}).await?;
+    let mut seen_names: HashSet<String> = existing
+        .iter()
+        .filter_map(|a| a.name.as_deref())
+        .map(normalize_achievement_name)
+        .collect();
+
+    let mut saved = Vec::new();
+    for proposal in parsed.achievements.into_iter().take(MAINLINE_ACHIEVEMENTS_MAX) {
+        let name = proposal.name.trim().to_string();
+        let name_len = name.chars().count();
+        if !(2..=50).contains(&name_len) {
+            log::warn!("成就名稱長度不符，略過: {}", name);
+            continue;
+        }
+        if !seen_names.insert(normalize_achievement_name(&name)) {
+            log::info!("職業主線 {} 已有成就「{}」，略過", mainline_id, name);
+            continue;
+        }
+
+        let related_task_id = proposal.related_task_title.as_deref().and_then(|title| {
+            tasks
+                .iter()
+                .find(|t| t.title.as_deref().map(str::trim) == Some(title.trim()))
+                .and_then(|t| t.id.clone())
+        });
+
+        let achievement = Achievement {
+            id: Some(uuid::Uuid::new_v4().to_string()),
+            name: Some(name.clone()),
+            description: proposal.description,
+            icon: proposal.icon.or(Some("🏆".to_string())),
+            category: Some("career_specific".to_string()),
+            requirement_type: None, // 職業專屬成就不使用傳統的需求類型
+            requirement_value: None,
+            experience_reward: Some(crate::reward_config::career_achievement_xp(proposal.experience_reward)),
+            career_mainline_id: Some(mainline_id.to_string()),
+            related_task_id,
+            created_at: Some(chrono::Utc::now()),
+        };
+
+        match Achievement::insert(rb, &achievement).await {
+            Ok(_) => {
+                log::info!("✅ 保存主線成就: {}", name);
+                saved.push(achievement);
+            }
+            Err(e) => log::error!("❌ 保存主線成就失敗: {} - {}", name, e),
+        }
+    }
+
+    log::info!("🏆 職業主線 {} 批次生成 {} 個成就", mainline_id, saved.len());
+    Ok(saved)
+}
+
+/// 異步批次生成職業主線成就（不阻塞主流程）
+pub fn spawn_generate_achievements_for_mainline(rb: RBatis, ai: SharedAIService, mainline_id: String) {
+    tokio::spawn(crate::language::in_current(async move {
+        if let Err(e) = generate_achievements_for_mainline(&rb, &ai, &mainline_id).await {
+            log::error!("異步生成職業主線成就失敗: {}", e);
+        }
+    }));
+}
+
+fn normalize_achievement_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::test;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+
+    // 主線 m1 屬於 owner，包含兩個分類的子任務
+    async fn seed_mainline(rb: &RBatis, owner_id: &str) {
+        rb.exec(
+            "INSERT INTO quiz_results (id, user_id, values_results, interests_results, talents_results, workstyle_results, completed_at)
+             VALUES ('q1', ?, '{}', '{}', '{}', '{}', '2026-01-01T00:00:00+00:00')",
+            vec![rbs::Value::String(owner_id.to_string())],
+        )
+        .await
+        .unwrap();
+        rb.exec(
+            "INSERT INTO career_mainlines (id, user_id, quiz_result_id, selected_career) VALUES ('m1', ?, 'q1', '資料科學家')",
+            vec![rbs::Value::String(owner_id.to_string())],
+        )
+        .await
+        .unwrap();
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, career_mainline_id, parent_task_id, task_category, task_order) VALUES
+             ('p1', ?1, '資料科學家主線', 0, 'm1', NULL, NULL, 0),
+             ('t1', ?1, '學會 Python 基礎', 0, 'm1', 'p1', '基礎', 1),
+             ('t2', ?1, '完成第一個機器學習專案', 0, 'm1', 'p1', '專案', 2)",
+            vec![rbs::Value::String(owner_id.to_string())],
+        )
+        .await
+        .unwrap();
+    }
+
+    fn proposals(names: &[&str]) -> String {
+        let achievements: Vec<serde_json::Value> = names
+            .iter()
+            .map(|name| json!({"name": name, "description": "里程碑", "icon": "🏅", "related_task_title": "學會 Python 基礎", "experience_reward": 80}))
+            .collect();
+        json!({ "achievements": achievements }).to_string()
+    }
+
+    #[actix_web::test]
+    async fn test_mainline_achievements_are_batched_and_deduplicated() {
+        let rb = test_utils::setup_db().await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        seed_mainline(&rb, "u1").await;
+
+        // 10 個提案：批次內重名 1 個、名稱過短 1 個，且最多只取前 8 個
+        let first = proposals(&["初入數據", "初入數據 ", "X", "Python 入門", "模型訓練師", "特徵工程", "資料清理", "視覺化達人", "專案完成", "第十個成就"]);
+        let second = proposals(&["初入數據", "進階建模"]);
+        let mock = MockAIService::with_replies(&[&first, &second]);
+        let ai = SharedAIService::new(Arc::new(mock.clone()));
+
+        let saved = generate_achievements_for_mainline(&rb, &ai, "m1").await.unwrap();
+        let names: Vec<&str> = saved.iter().filter_map(|a| a.name.as_deref()).collect();
+        assert_eq!(names, vec!["初入數據", "Python 入門", "模型訓練師", "特徵工程", "資料清理", "視覺化達人"]);
+        assert!(saved.iter().all(|a| a.related_task_id.as_deref() == Some("t1")));
+        assert!(saved.iter().all(|a| a.career_mainline_id.as_deref() == Some("m1")));
+
+        // 整條主線只呼叫一次 AI，提示詞依分類列出子任務
+        let prompts = mock.prompts("generate_with_model");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("【基礎】") && prompts[0].contains("【專案】"), "{}", prompts[0]);
+        assert!(!prompts[0].contains("資料科學家主線"), "{}", prompts[0]);
+
+        // 重新生成時略過主線上已有的同名成就
+        let saved = generate_achievements_for_mainline(&rb, &ai, "m1").await.unwrap();
+        let names: Vec<&str> = saved.iter().filter_map(|a| a.name.as_deref()).collect();
+        assert_eq!(names, vec!["進階建模"]);
+        let total = Achievement::select_by_map(&rb, value!{"career_mainline_id": "m1"}).await.unwrap();
+        assert_eq!(total.len(), 7);
+    }
+
+    #[actix_web::test]
+    async fn test_mainline_tasks_skip_per_task_generation() {
+        let rb = test_utils::setup_db().await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        seed_mainline(&rb, "u1").await;
+
+        let task = Task::select_by_map(&rb, value!{"id": "t1"}).await.unwrap().remove(0);
+        assert!(generate_achievement_for_task(&rb, &task).await.unwrap().is_none());
+        assert!(Achievement::select_by_map(&rb, value!{"related_task_id": "t1"}).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_generate_route_requires_mainline_owner() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&[&proposals(&["初入數據"])]);
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let owner = test_utils::create_user(&app, "mainline_owner").await;
+        let intruder = test_utils::create_user(&app, "mainline_intruder").await;
+        seed_mainline(&rb, &owner.id).await;
+
+        let generate = |user: &test_utils::TestUser| {
+            test::TestRequest::post()
+                .uri("/api/career/mainlines/m1/generate-achievements")
+                .insert_header(user.auth())
+                .to_request()
+        };
+        let (status, body) = call_json(&app, generate(&intruder)).await;
+        assert_eq!(status.as_u16(), 403, "{}", body);
+        assert!(mock.prompts("generate_with_model").is_empty());
+
+        let (status, body) = call_json(&app, generate(&owner)).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+        assert_eq!(body["data"]["achievements_created"], 1);
+        assert_eq!(mock.prompts("generate_with_model").len(), 1);
+    }
 }
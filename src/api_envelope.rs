@@ -0,0 +1,290 @@
+// 統一回應格式：所有錯誤回應都是 ApiResponse（success=false）並附上錯誤代碼 code
+//
+// 處理函數維持回傳 ApiResponse；中間件依 HTTP 狀態碼補上 code，並把 actix 內建的
+// 純文字錯誤（JSON 解析失敗、路徑參數錯誤等）包成 ApiResponse。
+// message 依使用者語言（未登入時依 Accept-Language）換成在地化訊息；繁體中文的成功回應不讀取 body。
+// 舊版聊天 API 曾把欄位放在最外層（例如 text），過渡期間可由設定同時輸出（僅 v1，見 api_version）。
+
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder};
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+
+use crate::api_version::ApiVersion;
+use crate::language::Language;
+use crate::services::ApiResponse;
+
+/// 回應標頭：回應仍包含已淘汰的最外層欄位
+pub const DEPRECATION_HEADER: &str = "deprecation";
+
+/// 錯誤代碼（依 HTTP 狀態碼分類，前端可據此判斷而不必解析訊息文字）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    Conflict,
+    Gone,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    ValidationFailed,
+    RateLimited,
+    InternalError,
+    UpstreamError,
+    ServiceUnavailable,
+}
+
+impl ErrorCode {
+    pub fn from_status(status: StatusCode) -> ErrorCode {
+        match status {
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::METHOD_NOT_ALLOWED => ErrorCode::MethodNotAllowed,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::GONE => ErrorCode::Gone,
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorCode::UnsupportedMediaType,
+            StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::ValidationFailed,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => ErrorCode::UpstreamError,
+            StatusCode::SERVICE_UNAVAILABLE => ErrorCode::ServiceUnavailable,
+            s if s.is_server_error() => ErrorCode::InternalError,
+            _ => ErrorCode::BadRequest,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::MethodNotAllowed => "METHOD_NOT_ALLOWED",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::Gone => "GONE",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::UnsupportedMediaType => "UNSUPPORTED_MEDIA_TYPE",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::UpstreamError => "UPSTREAM_ERROR",
+            ErrorCode::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+        }
+    }
+}
+
+static LEGACY_FIELDS: OnceLock<bool> = OnceLock::new();
+static LEGACY_EXPERT_PREFIX: OnceLock<bool> = OnceLock::new();
+
+/// 啟動時套用設定：是否同時輸出舊版最外層欄位、聊天回覆是否保留專家 emoji 前綴
+pub fn init(legacy_fields: bool, legacy_expert_prefix: bool) {
+    if legacy_fields {
+        log::info!("回應格式: 保留舊版最外層欄位（已淘汰，前端改用 data 後請關閉 API_LEGACY_RESPONSE_FIELDS）");
+    }
+    if legacy_expert_prefix {
+        log::info!("回應格式: 聊天回覆保留專家 emoji 前綴（已淘汰，前端改用 expert_emoji 後請關閉 CHAT_LEGACY_EXPERT_PREFIX）");
+    }
+    let fields_set = LEGACY_FIELDS.set(legacy_fields).is_ok();
+    let prefix_set = LEGACY_EXPERT_PREFIX.set(legacy_expert_prefix).is_ok();
+    if !(fields_set && prefix_set) {
+        log::warn!("回應格式設定已初始化，忽略重複設定");
+    }
+}
+
+// v2 起不再輸出相容格式
+fn legacy_fields_enabled() -> bool {
+    *LEGACY_FIELDS.get_or_init(|| true) && crate::api_version::current() == ApiVersion::V1
+}
+
+/// 聊天回覆的 text 是否仍以「[emoji] 」開頭（僅 v1）
+pub fn legacy_expert_prefix_enabled() -> bool {
+    *LEGACY_EXPERT_PREFIX.get_or_init(|| true) && crate::api_version::current() == ApiVersion::V1
+}
+
+/// 以 ApiResponse 回應；啟用相容模式時把 data 的欄位複製到最外層並加上 Deprecation 標頭
+pub fn json_with_legacy_fields<T: Serialize>(mut builder: HttpResponseBuilder, response: ApiResponse<T>) -> HttpResponse {
+    let mut body = serde_json::to_value(&response).unwrap_or_else(|_| serde_json::json!({}));
+    if legacy_fields_enabled() {
+        if let Some(data) = body.get("data").and_then(|d| d.as_object()).cloned() {
+            if let Some(envelope) = body.as_object_mut() {
+                for (key, value) in data {
+                    envelope.entry(key).or_insert(value);
+                }
+            }
+        }
+        builder.insert_header((HeaderName::from_static(DEPRECATION_HEADER), HeaderValue::from_static("true")));
+    }
+    builder.json(body)
+}
+
+/// 將錯誤回應的 body 轉成含 code 的 ApiResponse；已有 code 的回應保持原樣
+fn normalize_error_body(status: StatusCode, body: &[u8]) -> serde_json::Value {
+    let code = ErrorCode::from_status(status).as_str();
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(mut map)) if map.contains_key("success") => {
+            map.insert("success".to_string(), serde_json::Value::Bool(false));
+            map.entry("data").or_insert(serde_json::Value::Null);
+            map.entry("message").or_insert_with(|| default_message(status).into());
+            map.entry("code").or_insert_with(|| code.into());
+            serde_json::Value::Object(map)
+        }
+        _ => {
+            let text = String::from_utf8_lossy(body).trim().to_string();
+            serde_json::json!({
+                "success": false,
+                "data": null,
+                "message": if text.is_empty() { default_message(status) } else { text },
+                "code": code,
+            })
+        }
+    }
+}
+
+fn default_message(status: StatusCode) -> String {
+    status.canonical_reason().unwrap_or("Error").to_string()
+}
+
+// 回應語言：JwtAuth 寫入的使用者語言，未登入時依 Accept-Language
+fn response_language(req: &HttpRequest) -> Language {
+    if let Some(language) = req.extensions().get::<Language>() {
+        return *language;
+    }
+    req.headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Language::from_accept_language)
+        .unwrap_or_default()
+}
+
+/// 把 message 換成在地化訊息；有替換時回傳 true
+fn localize_body(body: &mut serde_json::Value, language: Language) -> bool {
+    let Some(message) = body.get_mut("message") else {
+        return false;
+    };
+    match message.as_str().and_then(|m| crate::language::localize_message(m, language)) {
+        Some(localized) => {
+            *message = localized.into();
+            true
+        }
+        None => false,
+    }
+}
+
+fn is_json(res: &ServiceResponse<BoxBody>) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+// 回應格式中間件：放在 App 最外層，涵蓋 JwtAuth 與 extractor 產生的錯誤
+pub struct ApiEnvelope;
+
+impl<S, B> Transform<S, ServiceRequest> for ApiEnvelope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiEnvelopeMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiEnvelopeMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct ApiEnvelopeMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiEnvelopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            // 內層回傳 Err 時由 actix 轉成回應（本專案的中間件與處理函數都回傳 Ok）
+            let res = service.call(req).await?.map_into_boxed_body();
+            let status = res.status();
+            let language = response_language(res.request());
+            let is_error = status.is_client_error() || status.is_server_error();
+            // 成功回應只在需要翻譯訊息時讀取 JSON body（SSE 等串流回應不處理）
+            if !is_error && (language == Language::ZhTw || !is_json(&res)) {
+                return Ok(res);
+            }
+
+            let (request, response) = res.into_parts();
+            let (mut head, body) = response.into_parts();
+            let bytes = actix_web::body::to_bytes(body).await.unwrap_or_default();
+            let normalized = if is_error {
+                let mut normalized = normalize_error_body(status, &bytes);
+                localize_body(&mut normalized, language);
+                normalized
+            } else {
+                let mut value = serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default();
+                if !localize_body(&mut value, language) {
+                    return Ok(ServiceResponse::new(request, head.set_body(BoxBody::new(bytes))));
+                }
+                value
+            };
+            let body = serde_json::to_vec(&normalized).unwrap_or_default();
+            head.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            head.headers_mut().remove(actix_web::http::header::CONTENT_LENGTH);
+            Ok(ServiceResponse::new(request, head.set_body(BoxBody::new(body))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_error_body() {
+        // 既有 ApiResponse：補上 code
+        let body = r#"{"success":false,"data":null,"message":"任務不存在"}"#.as_bytes();
+        let value = normalize_error_body(StatusCode::NOT_FOUND, body);
+        assert_eq!(value["code"], "NOT_FOUND");
+        assert_eq!(value["message"], "任務不存在");
+
+        // 已指定 code 的回應不覆寫
+        let body = r#"{"success":false,"message":"額度不足","code":"QUOTA_EXCEEDED"}"#.as_bytes();
+        let value = normalize_error_body(StatusCode::TOO_MANY_REQUESTS, body);
+        assert_eq!(value["code"], "QUOTA_EXCEEDED");
+        assert!(value["data"].is_null());
+
+        // actix 內建的純文字錯誤
+        let value = normalize_error_body(StatusCode::BAD_REQUEST, b"Json deserialize error: EOF");
+        assert_eq!(value["success"], false);
+        assert_eq!(value["code"], "BAD_REQUEST");
+        assert_eq!(value["message"], "Json deserialize error: EOF");
+
+        let value = normalize_error_body(StatusCode::METHOD_NOT_ALLOWED, b"");
+        assert_eq!(value["message"], "Method Not Allowed");
+        assert_eq!(value["code"], "METHOD_NOT_ALLOWED");
+    }
+}
@@ -0,0 +1,427 @@
+// 個人 API token：給腳本與第三方整合使用，免去帳號密碼與 JWT 到期的問題
+//
+// token 只在建立時顯示一次，資料庫僅保存 SHA-256 雜湊。JwtAuth 依前綴辨識 token，
+// 驗證後與 JWT 一樣在請求擴展放入 user_id 與 Claims；唯讀 token 只能呼叫 GET / HEAD / OPTIONS。
+// last_used_at 每分鐘最多寫入一次。
+
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ai_tasks::ApiResponse;
+use crate::auth::Claims;
+
+/// token 前綴，用來與 JWT 區分
+pub const TOKEN_PREFIX: &str = "lu_pat_";
+/// 每位使用者同時有效的 token 上限
+pub const MAX_ACTIVE_TOKENS: i64 = 20;
+const NAME_MAX_CHARS: usize = 50;
+// last_used_at 的最短更新間隔
+const LAST_USED_THROTTLE_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::ReadOnly => "read_only",
+            TokenScope::ReadWrite => "read_write",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        if value == "read_only" {
+            TokenScope::ReadOnly
+        } else {
+            TokenScope::ReadWrite
+        }
+    }
+
+    /// 唯讀 token 只允許不會修改資料的請求
+    pub fn allows(&self, method: &Method) -> bool {
+        match self {
+            TokenScope::ReadOnly => matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS),
+            TokenScope::ReadWrite => true,
+        }
+    }
+}
+
+/// 以 API token 驗證的請求會在請求擴展中帶有此資訊
+#[derive(Debug, Clone)]
+pub struct ApiTokenAuth {
+    pub token_id: String,
+    pub scope: TokenScope,
+}
+
+/// 產生新的 token（前綴 + 32 bytes 隨機值的 hex）
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", TOKEN_PREFIX, hex::encode(bytes))
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenAuthRow {
+    id: String,
+    user_id: String,
+    scope: Option<String>,
+    email: Option<String>,
+    token_version: Option<i32>,
+}
+
+/// 驗證 API token（供 JwtAuth 中間件使用）；無效或已撤銷時回傳 None
+pub async fn authenticate(rb: &RBatis, token: &str) -> std::result::Result<Option<(Claims, ApiTokenAuth)>, rbatis::Error> {
+    let row: Option<TokenAuthRow> = rb
+        .query_decode(
+            "SELECT t.id, t.user_id, t.scope, u.email, COALESCE(u.token_version, 0) AS token_version
+             FROM api_token t JOIN user u ON u.id = t.user_id
+             WHERE t.token_hash = ? AND t.revoked_at IS NULL",
+            vec![rbs::Value::String(hash_token(token))],
+        )
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let now = Utc::now();
+    rb.exec(
+        "UPDATE api_token SET last_used_at = ? WHERE id = ? AND (last_used_at IS NULL OR last_used_at < ?)",
+        vec![
+            rbs::Value::String(now.to_rfc3339()),
+            rbs::Value::String(row.id.clone()),
+            rbs::Value::String((now - Duration::seconds(LAST_USED_THROTTLE_SECS)).to_rfc3339()),
+        ],
+    )
+    .await?;
+
+    // 與 JWT 相同的使用者上下文；token 沒有到期時間與登入裝置
+    let claims = Claims {
+        sub: row.user_id,
+        email: row.email.unwrap_or_default(),
+        exp: 0,
+        iat: 0,
+        sid: None,
+        ver: row.token_version.unwrap_or(0),
+        act: None,
+        read_only: false,
+    };
+    let auth = ApiTokenAuth {
+        token_id: row.id,
+        scope: TokenScope::from_str(row.scope.as_deref().unwrap_or_default()),
+    };
+    Ok(Some((claims, auth)))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub scope: String,
+    // token 開頭幾個字元，方便辨識是哪一把
+    pub token_prefix: String,
+    pub created_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub info: ApiTokenInfo,
+    // 完整 token，只在建立時回傳一次
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scope: TokenScope,
+}
+
+fn error(status: StatusCode, message: String) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message,
+    })
+}
+
+// 只能管理自己的 token，且必須以登入帳號（JWT）操作，避免 token 自行簽發新 token
+fn check_access(http_req: &HttpRequest, user_id: &str) -> Option<HttpResponse> {
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(http_req).as_deref(), user_id) {
+        return Some(e.into_response());
+    }
+    if http_req.extensions().get::<ApiTokenAuth>().is_some() {
+        return Some(error(
+            StatusCode::FORBIDDEN,
+            "請以登入帳號管理 API token".to_string(),
+        ));
+    }
+    None
+}
+
+/// 建立 API token（完整 token 只會顯示這一次）
+pub async fn create_token(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<CreateApiTokenRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Some(response) = check_access(&http_req, &user_id) {
+        return Ok(response);
+    }
+    let name = body.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > NAME_MAX_CHARS {
+        return Ok(error(
+            StatusCode::BAD_REQUEST,
+            format!("token 名稱需為 1～{} 個字", NAME_MAX_CHARS),
+        ));
+    }
+
+    let active: std::result::Result<i64, _> = rb
+        .query_decode(
+            "SELECT COUNT(*) AS count FROM api_token WHERE user_id = ? AND revoked_at IS NULL",
+            vec![rbs::Value::String(user_id.clone())],
+        )
+        .await;
+    match active {
+        Ok(count) if count >= MAX_ACTIVE_TOKENS => {
+            return Ok(error(
+                StatusCode::CONFLICT,
+                format!("最多只能有 {} 個有效的 API token，請先撤銷不用的 token", MAX_ACTIVE_TOKENS),
+            ))
+        }
+        Ok(_) => {}
+        Err(e) => {
+            return Ok(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("建立 API token 失敗: {}", e),
+            ))
+        }
+    }
+
+    let token = generate_token();
+    let info = ApiTokenInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        scope: body.scope.as_str().to_string(),
+        token_prefix: token.chars().take(TOKEN_PREFIX.len() + 6).collect(),
+        created_at: Some(Utc::now().to_rfc3339()),
+        last_used_at: None,
+    };
+    let result = rb
+        .exec(
+            "INSERT INTO api_token (id, user_id, name, token_hash, token_prefix, scope, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            vec![
+                rbs::Value::String(info.id.clone()),
+                rbs::Value::String(user_id.clone()),
+                rbs::Value::String(info.name.clone()),
+                rbs::Value::String(hash_token(&token)),
+                rbs::Value::String(info.token_prefix.clone()),
+                rbs::Value::String(info.scope.clone()),
+                rbs::Value::String(info.created_at.clone().unwrap_or_default()),
+            ],
+        )
+        .await;
+    if let Err(e) = result {
+        return Ok(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("建立 API token 失敗: {}", e),
+        ));
+    }
+
+    let ip = http_req.connection_info().realip_remote_addr().map(str::to_string);
+    crate::audit_log::record(
+        rb.get_ref(),
+        crate::audit_log::ACTION_API_TOKEN_CREATED,
+        Some(&user_id),
+        ip.as_deref(),
+        serde_json::json!({ "token_id": info.id, "name": info.name, "scope": info.scope }),
+    )
+    .await;
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(CreatedApiToken { info, token }),
+        message: "API token 已建立，請立即保存（之後無法再次查看）".to_string(),
+    }))
+}
+
+/// 列出有效的 API token（不含 token 本身）
+pub async fn list_tokens(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Some(response) = check_access(&http_req, &user_id) {
+        return Ok(response);
+    }
+    let tokens: std::result::Result<Vec<ApiTokenInfo>, _> = rb
+        .query_decode(
+            "SELECT id, name, scope, token_prefix, created_at, last_used_at
+             FROM api_token WHERE user_id = ? AND revoked_at IS NULL ORDER BY created_at DESC",
+            vec![rbs::Value::String(user_id)],
+        )
+        .await;
+    match tokens {
+        Ok(tokens) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(tokens),
+            message: "獲取 API token 列表成功".to_string(),
+        })),
+        Err(e) => Ok(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("獲取 API token 列表失敗: {}", e),
+        )),
+    }
+}
+
+/// 撤銷 API token，之後使用該 token 的請求一律回傳 401
+pub async fn revoke_token(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, token_id) = path.into_inner();
+    if let Some(response) = check_access(&http_req, &user_id) {
+        return Ok(response);
+    }
+    let result = rb
+        .exec(
+            "UPDATE api_token SET revoked_at = ? WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+            vec![
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(token_id.clone()),
+                rbs::Value::String(user_id.clone()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(result) if result.rows_affected > 0 => {
+            let ip = http_req.connection_info().realip_remote_addr().map(str::to_string);
+            crate::audit_log::record(
+                rb.get_ref(),
+                crate::audit_log::ACTION_API_TOKEN_REVOKED,
+                Some(&user_id),
+                ip.as_deref(),
+                serde_json::json!({ "token_id": token_id }),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+                success: true,
+                data: None,
+                message: "API token 已撤銷".to_string(),
+            }))
+        }
+        Ok(_) => Ok(error(StatusCode::NOT_FOUND, "找不到該 API token".to_string())),
+        Err(e) => Ok(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("撤銷 API token 失敗: {}", e),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use actix_web::test;
+    use serde_json::json;
+
+    fn bearer(token: &str) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", token))
+    }
+
+    #[actix_web::test]
+    async fn test_api_tokens_authenticate_and_revoked_tokens_are_rejected() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "scripter").await;
+        let tokens_uri = format!("/api/users/{}/tokens", user.id);
+        let settings_uri = format!("/api/users/{}/settings", user.id);
+
+        let create = |scope: &str| {
+            test::TestRequest::post()
+                .uri(&tokens_uri)
+                .insert_header(user.auth())
+                .set_json(json!({"name": format!("{} 腳本", scope), "scope": scope}))
+                .to_request()
+        };
+        let (status, body) = call_json(&app, create("read_write")).await;
+        assert_eq!(status, 201);
+        let token = body["data"]["token"].as_str().unwrap().to_string();
+        let token_id = body["data"]["id"].as_str().unwrap().to_string();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        let (_, body) = call_json(&app, create("read_only")).await;
+        let read_only = body["data"]["token"].as_str().unwrap().to_string();
+
+        // 資料庫只保存雜湊
+        let stored: i64 = rb
+            .query_decode("SELECT COUNT(*) AS count FROM api_token WHERE token_hash = ?", vec![rbs::Value::String(token.clone())])
+            .await
+            .unwrap();
+        assert_eq!(stored, 0);
+
+        // token 與 JWT 得到相同的使用者上下文
+        let req = test::TestRequest::get().uri(&settings_uri).insert_header(bearer(&token)).to_request();
+        assert_eq!(call_json(&app, req).await.0, 200);
+        let req = test::TestRequest::patch()
+            .uri(&settings_uri)
+            .insert_header(bearer(&token))
+            .set_json(json!({"locale": "en"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 200);
+
+        // 唯讀 token 可讀不可寫
+        let req = test::TestRequest::get().uri(&settings_uri).insert_header(bearer(&read_only)).to_request();
+        assert_eq!(call_json(&app, req).await.0, 200);
+        let req = test::TestRequest::patch()
+            .uri(&settings_uri)
+            .insert_header(bearer(&read_only))
+            .set_json(json!({"locale": "zh-TW"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        // token 不能管理 token
+        let req = test::TestRequest::get().uri(&tokens_uri).insert_header(bearer(&token)).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        let req = test::TestRequest::get().uri(&tokens_uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let listed = body["data"].as_array().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|t| t.get("token").is_none() && !t["last_used_at"].is_null()));
+
+        // 撤銷後一律拒絕
+        let req = test::TestRequest::delete()
+            .uri(&format!("{}/{}", tokens_uri, token_id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 200);
+        let req = test::TestRequest::get().uri(&settings_uri).insert_header(bearer(&token)).to_request();
+        assert_eq!(call_json(&app, req).await.0, 401);
+        let req = test::TestRequest::get()
+            .uri(&settings_uri)
+            .insert_header(bearer(&format!("{}not-a-real-token", TOKEN_PREFIX)))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 401);
+    }
+}
@@ -0,0 +1,259 @@
+// API 版本：/api/v1 與 /api/v2 掛載同一組路由（routes::configure_api），行為差異由 api_version::current() 判斷
+//
+// v1 維持既有行為；v2 不再輸出已淘汰的相容格式（最外層欄位、聊天回覆的專家 emoji 前綴），
+// 之後的破壞性變更也只在 v2 生效。不帶版本的 /api 是 v1 的別名，回應附上 Deprecation、Sunset
+// 與指向 /api/v1 的 Link 標頭。自架者可在前端更新後以 API_V1_ENABLED=false 停用 v1（回應 410）。
+
+use std::borrow::Cow;
+use std::future::{ready, Future, Ready};
+use std::rc::Rc;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpResponse};
+use chrono::NaiveDate;
+use futures::future::LocalBoxFuture;
+
+use crate::services::ApiResponse;
+
+/// 回應標頭：別名路徑的停止服務日期（HTTP-date）
+pub const SUNSET_HEADER: &str = "sunset";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/api/v1",
+            ApiVersion::V2 => "/api/v2",
+        }
+    }
+}
+
+tokio::task_local! {
+    static API_VERSION: ApiVersion;
+}
+
+/// 目前請求的 API 版本；不在請求範圍內（背景工作、單元測試）時視為 v1
+pub fn current() -> ApiVersion {
+    API_VERSION.try_with(|version| *version).unwrap_or_default()
+}
+
+/// 在指定版本的範圍內執行
+pub fn scope<F: Future>(version: ApiVersion, future: F) -> impl Future<Output = F::Output> {
+    API_VERSION.scope(version, future)
+}
+
+/// 去掉路徑中的版本（/api/v2/tasks → /api/tasks），供依路徑分類的中間件使用
+pub fn unversioned_path(path: &str) -> Cow<'_, str> {
+    for version in [ApiVersion::V1, ApiVersion::V2] {
+        if let Some(rest) = path.strip_prefix(version.prefix()) {
+            if rest.is_empty() || rest.starts_with('/') {
+                return Cow::Owned(format!("/api{}", rest));
+            }
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// 設定的停止服務日期（YYYY-MM-DD）轉成 Sunset 標頭使用的 HTTP-date
+pub fn sunset_http_date(date: &str) -> Option<String> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date.format("%a, %d %b %Y 00:00:00 GMT").to_string())
+}
+
+/// v1 停用後，/api/v1 與 /api 的所有請求回應 410
+pub async fn v1_disabled() -> HttpResponse {
+    HttpResponse::Gone().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "API v1 已停用，請改用 /api/v2".to_string(),
+    })
+}
+
+// 版本中間件：包在版本 scope 外層，記錄版本；不帶版本的別名另外附上淘汰標頭
+#[derive(Clone)]
+pub struct ApiVersioning {
+    version: ApiVersion,
+    // 不帶版本的別名路徑
+    alias: bool,
+    sunset: Option<Rc<str>>,
+}
+
+impl ApiVersioning {
+    pub fn new(version: ApiVersion) -> Self {
+        ApiVersioning { version, alias: false, sunset: None }
+    }
+
+    /// 不帶版本的 /api：行為同 v1，回應標示為已淘汰（sunset 為設定的 YYYY-MM-DD）
+    pub fn legacy_alias(sunset: &str) -> Self {
+        ApiVersioning {
+            version: ApiVersion::V1,
+            alias: true,
+            sunset: sunset_http_date(sunset).map(Rc::from),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiVersioning
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiVersioningMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiVersioningMiddleware { service: Rc::new(service), versioning: self.clone() }))
+    }
+}
+
+pub struct ApiVersioningMiddleware<S> {
+    service: Rc<S>,
+    versioning: ApiVersioning,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiVersioningMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let version = self.versioning.version;
+        let sunset = self.versioning.sunset.clone();
+        // 別名路徑對應的正式路徑（/api/tasks → /api/v1/tasks）
+        let successor = self.versioning.alias.then(|| {
+            let rest = req.path().strip_prefix("/api").unwrap_or_default();
+            format!("<{}{}>; rel=\"successor-version\"", version.prefix(), rest)
+        });
+
+        Box::pin(async move {
+            let mut res = scope(version, service.call(req)).await?;
+            if let Some(successor) = successor {
+                let headers = res.headers_mut();
+                headers.insert(
+                    HeaderName::from_static(crate::api_envelope::DEPRECATION_HEADER),
+                    HeaderValue::from_static("true"),
+                );
+                if let Some(value) = sunset.and_then(|sunset| HeaderValue::from_str(&sunset).ok()) {
+                    headers.insert(HeaderName::from_static(SUNSET_HEADER), value);
+                }
+                if let Ok(value) = HeaderValue::from_str(&successor) {
+                    headers.append(actix_web::http::header::LINK, value);
+                }
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_versioned_paths_and_deprecated_alias() {
+        use actix_web::test;
+
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "versioned").await;
+
+        // 三種路徑存取同一個資源
+        for prefix in ["/api/v1", "/api/v2", "/api"] {
+            let req = test::TestRequest::get().uri(&format!("{}/users/{}", prefix, user.id)).insert_header(user.auth()).to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status.as_u16(), 200, "{}: {}", prefix, body);
+            assert_eq!(body["data"]["id"], json!(user.id));
+        }
+
+        // 只有不帶版本的別名附上淘汰標頭
+        let req = test::TestRequest::get().uri("/api/chat/test").insert_header(user.auth()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(crate::api_envelope::DEPRECATION_HEADER).unwrap(), "true");
+        assert!(resp.headers().get(SUNSET_HEADER).unwrap().to_str().unwrap().ends_with("GMT"));
+        assert_eq!(
+            resp.headers().get(actix_web::http::header::LINK).unwrap(),
+            "</api/v1/chat/test>; rel=\"successor-version\""
+        );
+
+        let req = test::TestRequest::get().uri("/api/v1/chat/test").insert_header(user.auth()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get(SUNSET_HEADER).is_none());
+        // v1 仍保留舊版最外層欄位
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["timestamp"], body["data"]["timestamp"]);
+
+        // v2 不輸出相容欄位與淘汰標頭
+        let req = test::TestRequest::get().uri("/api/v2/chat/test").insert_header(user.auth()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get(crate::api_envelope::DEPRECATION_HEADER).is_none());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["data"]["timestamp"].is_string());
+        assert!(body.get("timestamp").is_none(), "{}", body);
+
+        // 公開路由同樣可經由版本路徑登入
+        let req = test::TestRequest::post()
+            .uri("/api/v2/auth/login")
+            .set_json(json!({"email": "versioned@lifeup.test", "password": test_utils::TEST_PASSWORD}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn test_v1_can_be_disabled() {
+        use actix_web::test;
+
+        let rb = test_utils::setup_db().await;
+        let mut config = crate::config::Config::from_env();
+        config.app.api_versioning.v1_enabled = false;
+        let app = test_utils::init_app_with_config(&rb, config).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/users")
+            .set_json(json!({"name": "v2only", "email": "v2only@lifeup.test", "password": test_utils::TEST_PASSWORD}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 201, "{}", body);
+
+        for uri in ["/api/v1/auth/login", "/api/auth/login"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_json(json!({"email": "v2only@lifeup.test", "password": test_utils::TEST_PASSWORD}))
+                .to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status.as_u16(), 410, "{}: {}", uri, body);
+            assert_eq!(body["code"], "GONE");
+        }
+    }
+
+    #[test]
+    fn test_unversioned_path_and_sunset_date() {
+        assert_eq!(unversioned_path("/api/v2/tasks/generate-json"), "/api/tasks/generate-json");
+        assert_eq!(unversioned_path("/api/v1/chat/chatgpt"), "/api/chat/chatgpt");
+        assert_eq!(unversioned_path("/api/v10/tasks"), "/api/v10/tasks");
+        assert_eq!(unversioned_path("/api/tasks"), "/api/tasks");
+        assert_eq!(sunset_http_date("2027-06-30").as_deref(), Some("Wed, 30 Jun 2027 00:00:00 GMT"));
+        assert_eq!(sunset_http_date("soon"), None);
+    }
+}
@@ -0,0 +1,359 @@
+// 週屬性比較：本週與 N 週前的屬性、各屬性差值，以及期間內屬性變化的主要來源
+//
+// 某一週沒有快照時改用目前屬性，並以 source = "current"、is_fallback = true 標明。
+// 帳號建立的時間比要求的週數短時，改為與帳號建立的那一週比較（clamped = true）。
+// 回應欄位固定（屬性一律六項、沒有來源時為空陣列），前端可直接畫圖。
+
+use std::collections::BTreeMap;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use rbatis::RBatis;
+use rbs::{value, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{User, UserAttributes, WeeklyAttributeSnapshot};
+use crate::week_start::{format_date, WeekStart};
+
+const ATTRIBUTES: [&str; 6] = ["intelligence", "endurance", "creativity", "social", "focus", "adaptability"];
+const DEFAULT_WEEKS_AGO: i64 = 4;
+const MAX_WEEKS_AGO: i64 = 52;
+const MAX_SOURCES: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct AttributeCompareQuery {
+    pub weeks_ago: Option<i64>,
+}
+
+/// 一週的屬性
+#[derive(Debug, Serialize)]
+pub struct WeekAttributes {
+    pub week_start_date: String,
+    pub week_end_date: String,
+    // snapshot：該週的快照；current：該週沒有快照，使用目前屬性
+    pub source: &'static str,
+    pub is_fallback: bool,
+    pub attributes: BTreeMap<&'static str, i32>,
+}
+
+/// 期間內某個來源（task / focus / skill_level_up / manual）造成的屬性變化
+#[derive(Debug, Serialize)]
+pub struct SourceContribution {
+    pub source: String,
+    pub delta: i64,
+    pub changes: i64,
+    pub attributes: BTreeMap<String, i64>,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+fn attribute_value(attrs: &UserAttributes, name: &str) -> Option<i32> {
+    match name {
+        "intelligence" => attrs.intelligence,
+        "endurance" => attrs.endurance,
+        "creativity" => attrs.creativity,
+        "social" => attrs.social,
+        "focus" => attrs.focus,
+        "adaptability" => attrs.adaptability,
+        _ => None,
+    }
+}
+
+fn snapshot_value(snapshot: &WeeklyAttributeSnapshot, name: &str) -> Option<i32> {
+    match name {
+        "intelligence" => snapshot.intelligence,
+        "endurance" => snapshot.endurance,
+        "creativity" => snapshot.creativity,
+        "social" => snapshot.social,
+        "focus" => snapshot.focus,
+        "adaptability" => snapshot.adaptability,
+        _ => None,
+    }
+}
+
+/// 實際比較的週數：不超過帳號建立以來的完整週數
+pub fn clamp_weeks_ago(requested: i64, current_week_start: NaiveDate, account_week_start: Option<NaiveDate>) -> i64 {
+    match account_week_start {
+        Some(account_week_start) => requested.min(((current_week_start - account_week_start).num_days() / 7).max(0)),
+        None => requested,
+    }
+}
+
+async fn week_attributes(
+    rb: &RBatis,
+    user_id: &str,
+    week_start: WeekStart,
+    date: NaiveDate,
+    current: &UserAttributes,
+) -> std::result::Result<WeekAttributes, rbatis::Error> {
+    let (start, end) = week_start.week_bounds(date);
+    let snapshot = crate::week_start::find_snapshot(rb, user_id, start).await?;
+    Ok(match snapshot {
+        Some(snapshot) => {
+            // 偏好變更前的快照起始日可能差一天，標明快照實際涵蓋的日期
+            let snapshot_start = snapshot
+                .week_start_date
+                .as_deref()
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .unwrap_or(start);
+            WeekAttributes {
+                week_start_date: format_date(snapshot_start),
+                week_end_date: format_date(snapshot_start + Duration::days(6)),
+                source: "snapshot",
+                is_fallback: false,
+                attributes: ATTRIBUTES.iter().map(|name| (*name, snapshot_value(&snapshot, name).unwrap_or(50))).collect(),
+            }
+        }
+        None => WeekAttributes {
+            week_start_date: format_date(start),
+            week_end_date: format_date(end),
+            source: "current",
+            is_fallback: true,
+            attributes: ATTRIBUTES.iter().map(|name| (*name, attribute_value(current, name).unwrap_or(50))).collect(),
+        },
+    })
+}
+
+/// 期間內屬性變化最多的來源（依變化量絕對值排序）
+async fn top_sources(
+    rb: &RBatis,
+    user_id: &str,
+    since: chrono::DateTime<Utc>,
+) -> std::result::Result<Vec<SourceContribution>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT COALESCE(source, 'unknown') AS source, attribute, SUM(delta) AS delta, COUNT(*) AS changes
+             FROM attribute_history
+             WHERE user_id = ? AND julianday(created_at) >= julianday(?)
+             GROUP BY COALESCE(source, 'unknown'), attribute",
+            vec![Value::String(user_id.to_string()), Value::String(since.to_rfc3339())],
+        )
+        .await?;
+
+    let mut by_source: BTreeMap<String, SourceContribution> = BTreeMap::new();
+    for row in &rows {
+        let source = row["source"].as_str().unwrap_or("unknown").to_string();
+        let delta = row["delta"].as_i64().unwrap_or(0);
+        let contribution = by_source.entry(source.clone()).or_insert_with(|| SourceContribution {
+            source,
+            delta: 0,
+            changes: 0,
+            attributes: BTreeMap::new(),
+        });
+        contribution.delta += delta;
+        contribution.changes += row["changes"].as_i64().unwrap_or(0);
+        if let Some(attribute) = row["attribute"].as_str() {
+            *contribution.attributes.entry(attribute.to_string()).or_default() += delta;
+        }
+    }
+    let mut sources: Vec<SourceContribution> = by_source.into_values().collect();
+    sources.sort_by(|a, b| b.delta.abs().cmp(&a.delta.abs()).then_with(|| b.changes.cmp(&a.changes)));
+    sources.truncate(MAX_SOURCES);
+    Ok(sources)
+}
+
+/// GET /api/users/{id}/attributes/compare?weeks_ago=4：本週與 N 週前的屬性比較
+pub async fn compare_weekly_attributes(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<AttributeCompareQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !crate::auth::is_admin_request(&http_req) {
+        return Ok(error(StatusCode::FORBIDDEN, "無權限查看此使用者的屬性"));
+    }
+    let requested = query.weeks_ago.unwrap_or(DEFAULT_WEEKS_AGO);
+    if !(1..=MAX_WEEKS_AGO).contains(&requested) {
+        return Ok(error(StatusCode::BAD_REQUEST, format!("weeks_ago 需介於 1 到 {}", MAX_WEEKS_AGO)));
+    }
+
+    let user = match User::select_by_map(rb.get_ref(), value!{"id": &user_id}).await {
+        Ok(users) => match users.into_iter().next() {
+            Some(user) => user,
+            None => return Ok(error(StatusCode::NOT_FOUND, "用戶不存在")),
+        },
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢用戶失敗: {}", e))),
+    };
+    let current = match UserAttributes::select_by_map(rb.get_ref(), value!{"user_id": &user_id}).await {
+        Ok(attrs) => match attrs.into_iter().next() {
+            Some(attrs) => attrs,
+            None => return Ok(error(StatusCode::NOT_FOUND, "用戶屬性不存在")),
+        },
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("獲取用戶屬性失敗: {}", e))),
+    };
+    let week_start = crate::week_start::load(rb.get_ref(), &user_id).await.unwrap_or_else(|e| {
+        log::warn!("讀取用戶 {} 的週起始日失敗，改用週一: {}", user_id, e);
+        WeekStart::default()
+    });
+
+    let tz = crate::user_settings::user_timezone(rb.get_ref(), &user_id).await;
+    let today = crate::local_date::local_today_in(tz);
+    let account_week_start = user
+        .created_at
+        .map(|created_at| week_start.week_start_of(crate::local_date::local_date_in(created_at, tz)));
+    let weeks_ago = clamp_weeks_ago(requested, week_start.week_start_of(today), account_week_start);
+    let clamped = weeks_ago < requested;
+    let previous_date = today - Duration::weeks(weeks_ago);
+
+    let weeks = async {
+        let current_week = week_attributes(rb.get_ref(), &user_id, week_start, today, &current).await?;
+        let previous_week = week_attributes(rb.get_ref(), &user_id, week_start, previous_date, &current).await?;
+        Ok::<_, rbatis::Error>((current_week, previous_week))
+    };
+    let (current_week, previous_week) = match weeks.await {
+        Ok(weeks) => weeks,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("獲取週屬性快照失敗: {}", e))),
+    };
+    let deltas: BTreeMap<&'static str, i32> = ATTRIBUTES
+        .iter()
+        .map(|name| (*name, current_week.attributes[name] - previous_week.attributes[name]))
+        .collect();
+
+    // 來源統計從 N 週前那一週的第一天（使用者時區午夜）到現在
+    let interval_start = week_start.week_start_of(previous_date);
+    let since = crate::local_date::default_timezone()
+        .from_local_datetime(&interval_start.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_default();
+    let sources = match top_sources(rb.get_ref(), &user_id, since).await {
+        Ok(sources) => sources,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢屬性變化紀錄失敗: {}", e))),
+    };
+
+    let message = if clamped {
+        format!("帳號建立未滿 {} 週，改為與 {} 週前比較", requested, weeks_ago)
+    } else {
+        format!("本週與 {} 週前的屬性比較", weeks_ago)
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message,
+        data: Some(serde_json::json!({
+            "requested_weeks_ago": requested,
+            "weeks_ago": weeks_ago,
+            "clamped": clamped,
+            "week_start": week_start,
+            "current": current_week,
+            "previous": previous_week,
+            "deltas": deltas,
+            "interval": {
+                "from": format_date(interval_start),
+                "to": format_date(today),
+            },
+            "top_sources": sources,
+        })),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[test]
+    fn test_clamp_weeks_ago_to_account_age() {
+        let current = NaiveDate::from_ymd_opt(2026, 3, 16).unwrap();
+        assert_eq!(clamp_weeks_ago(4, current, Some(current - Duration::weeks(10))), 4);
+        assert_eq!(clamp_weeks_ago(4, current, Some(current - Duration::weeks(2))), 2);
+        assert_eq!(clamp_weeks_ago(4, current, Some(current)), 0);
+        assert_eq!(clamp_weeks_ago(4, current, None), 4);
+    }
+
+    #[actix_web::test]
+    async fn test_compare_snapshot_with_current_attributes() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "attribute-compare").await;
+        let other = test_utils::create_user(&app, "attribute-peeker").await;
+        let today = crate::local_date::local_today();
+
+        rb.exec(
+            "UPDATE user SET created_at = ? WHERE id = ?",
+            vec![
+                Value::String((Utc::now() - Duration::weeks(10)).to_rfc3339()),
+                Value::String(user.id.clone()),
+            ],
+        )
+        .await
+        .unwrap();
+        let start = WeekStart::Monday.week_start_of(today - Duration::weeks(4));
+        let (year, week_number) = crate::week_start::iso_key(start);
+        rb.exec(
+            "INSERT INTO weekly_attribute_snapshot (id, user_id, week_start_date, year, week_number, intelligence, focus)
+             VALUES ('four-weeks-ago', ?, ?, ?, ?, 40, 50)",
+            vec![
+                Value::String(user.id.clone()),
+                Value::String(format_date(start)),
+                Value::I32(year),
+                Value::I32(week_number),
+            ],
+        )
+        .await
+        .unwrap();
+        crate::attribute_rewards::apply_attribute_deltas(&rb, &user.id, &[("intelligence".to_string(), 5)], "task", Some("t1"))
+            .await
+            .unwrap();
+        crate::attribute_rewards::apply_attribute_deltas(&rb, &user.id, &[("intelligence".to_string(), 5)], "task", Some("t2"))
+            .await
+            .unwrap();
+        crate::attribute_rewards::apply_attribute_deltas(&rb, &user.id, &[("focus".to_string(), 3)], "focus", None)
+            .await
+            .unwrap();
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/attributes/compare?weeks_ago=4", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let data = &body["data"];
+        assert_eq!(data["weeks_ago"], 4);
+        assert_eq!(data["clamped"], false);
+        assert_eq!(data["previous"]["source"], "snapshot");
+        assert_eq!(data["previous"]["attributes"]["intelligence"], 40);
+        assert_eq!(data["current"]["source"], "current");
+        assert_eq!(data["current"]["is_fallback"], true);
+        assert_eq!(data["current"]["attributes"]["intelligence"], 60);
+        assert_eq!(data["deltas"]["intelligence"], 20);
+        assert_eq!(data["deltas"]["focus"], 3);
+        assert_eq!(data["deltas"].as_object().unwrap().len(), 6);
+        assert_eq!(data["top_sources"][0]["source"], "task");
+        assert_eq!(data["top_sources"][0]["delta"], 10);
+        assert_eq!(data["top_sources"][0]["changes"], 2);
+        assert_eq!(data["top_sources"][1]["attributes"]["focus"], 3);
+
+        // 新帳號：改為與帳號建立的那一週比較
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/attributes/compare", other.id))
+            .insert_header(other.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["requested_weeks_ago"], 4);
+        assert_eq!(body["data"]["clamped"], true);
+        assert_eq!(body["data"]["weeks_ago"], 0);
+        assert_eq!(body["data"]["top_sources"], serde_json::json!([]));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/attributes/compare", user.id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/attributes/compare?weeks_ago=0", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 400);
+    }
+}
@@ -0,0 +1,448 @@
+// 屬性補強建議：找出使用者最弱的兩項屬性，請 AI 依現有技能與近期任務提出具體任務
+//
+// 每項弱屬性最多 3 個建議，直接回傳可送往 POST /api/tasks 的 CreateTaskRequest；
+// 結果依使用者快取 24 小時（記憶體內），避免每次開啟頁面都呼叫 AI。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::ai_service::SharedAIService;
+use crate::models::{CreateTaskRequest, Skill, UserAttributes};
+use crate::services::ApiResponse;
+use crate::task_types::TaskType;
+
+const WEAK_ATTRIBUTE_COUNT: usize = 2;
+const SUGGESTIONS_PER_ATTRIBUTE: usize = 3;
+const RECENT_TASK_LIMIT: i64 = 20;
+const CACHE_TTL_HOURS: i64 = 24;
+
+/// 六大屬性：(欄位名稱, 中文名稱, 定義)，順序同時決定同分時的先後
+pub const ATTRIBUTES: [(&str, &str, &str); 6] = [
+    ("intelligence", "智力", "學習、分析、邏輯思考、程式設計、研究等"),
+    ("endurance", "毅力", "堅持、健身、長期目標、自律、耐力等"),
+    ("creativity", "創造力", "藝術、設計、創意思考、寫作、音樂等"),
+    ("social", "社交力", "溝通、團隊合作、人際關係、演講、領導等"),
+    ("focus", "專注力", "專注、效率、時間管理、任務執行、細節處理等"),
+    ("adaptability", "適應力", "學習新事物、解決問題、應變能力、多任務處理等"),
+];
+
+/// 弱屬性與目前數值
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WeakAttribute {
+    pub attribute: String,
+    pub name: String,
+    pub value: i32,
+}
+
+/// 單一建議：可直接建立的任務與建議理由
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeSuggestion {
+    pub attribute: String,
+    pub reasoning: String,
+    pub task: CreateTaskRequest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeRecommendations {
+    pub weak_attributes: Vec<WeakAttribute>,
+    pub suggestions: Vec<AttributeSuggestion>,
+    #[serde(with = "crate::datetime_format::rfc3339")]
+    pub generated_at: DateTime<Utc>,
+    pub cached: bool,
+}
+
+// AI 回傳的單一建議（欄位皆可缺，轉換時再驗證）
+#[derive(Debug, Deserialize)]
+struct RawSuggestion {
+    attribute: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    task_type: Option<String>,
+    difficulty: Option<i32>,
+    experience: Option<i32>,
+    skill_tags: Option<Vec<String>>,
+    reasoning: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSuggestions {
+    suggestions: Vec<RawSuggestion>,
+}
+
+// 使用者 ID -> 上次產生的建議
+static CACHE: OnceLock<Mutex<HashMap<String, AttributeRecommendations>>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<HashMap<String, AttributeRecommendations>> {
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn cached_for(user_id: &str, now: DateTime<Utc>) -> Option<AttributeRecommendations> {
+    let cache = cache().lock().ok()?;
+    cache
+        .get(user_id)
+        .filter(|entry| now - entry.generated_at < Duration::hours(CACHE_TTL_HOURS))
+        .cloned()
+}
+
+fn attribute_name(attribute: &str) -> Option<&'static str> {
+    ATTRIBUTES.iter().find(|(key, _, _)| *key == attribute).map(|(_, name, _)| *name)
+}
+
+fn attribute_value(attrs: &UserAttributes, attribute: &str) -> i32 {
+    match attribute {
+        "intelligence" => attrs.intelligence,
+        "endurance" => attrs.endurance,
+        "creativity" => attrs.creativity,
+        "social" => attrs.social,
+        "focus" => attrs.focus,
+        "adaptability" => attrs.adaptability,
+        _ => None,
+    }
+    .unwrap_or(50)
+}
+
+/// 數值最低的 count 項屬性（同分時依 ATTRIBUTES 順序）
+pub fn weakest_attributes(attrs: &UserAttributes, count: usize) -> Vec<WeakAttribute> {
+    let mut all: Vec<WeakAttribute> = ATTRIBUTES
+        .iter()
+        .map(|(key, name, _)| WeakAttribute {
+            attribute: key.to_string(),
+            name: name.to_string(),
+            value: attribute_value(attrs, key),
+        })
+        .collect();
+    // sort_by_key 為穩定排序，同分時保留原本順序
+    all.sort_by_key(|a| a.value);
+    all.truncate(count);
+    all
+}
+
+fn build_prompt(weak: &[WeakAttribute], skills: &[String], recent_titles: &[String]) -> String {
+    let definitions = ATTRIBUTES
+        .iter()
+        .map(|(key, name, definition)| format!("- {} ({}): {}", key, name, definition))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let targets = weak
+        .iter()
+        .map(|w| format!("- {} ({})：目前 {} / 100", w.attribute, w.name, w.value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let skills = if skills.is_empty() { "（尚無技能）".to_string() } else { skills.join("、") };
+    let recent = if recent_titles.is_empty() {
+        "（尚無任務）".to_string()
+    } else {
+        recent_titles.iter().map(|t| format!("- {}", t)).collect::<Vec<_>>().join("\n")
+    };
+
+    format!(
+        "你是遊戲化成長教練。使用者以下屬性偏低，請為每項屬性各提出 {} 個具體、今天就能開始的任務。\n\n\
+         **需要補強的屬性：**\n{}\n\n\
+         **六大屬性定義：**\n{}\n\n\
+         **使用者現有技能：**\n{}\n\n\
+         **使用者近期任務：**\n{}\n\n\
+         **規則：**\n\
+         1. 任務要結合使用者現有的技能與近期任務，不要給籠統的建議\n\
+         2. 不要與近期任務重複\n\
+         3. skill_tags 優先沿用現有技能名稱，最多 3 個\n\
+         4. difficulty 為 1-5，experience 為 10-200，task_type 為 main / side / challenge 其中之一\n\
+         5. reasoning 用一句話說明這個任務如何補強該屬性\n\
+         6. title、description、reasoning 的語言：{}\n\n\
+         只回傳 JSON，格式如下：\n\
+         {{\"suggestions\": [{{\"attribute\": \"屬性欄位名稱\", \"title\": \"任務標題\", \"description\": \"任務描述\", \
+         \"task_type\": \"side\", \"difficulty\": 2, \"experience\": 50, \"skill_tags\": [\"技能\"], \"reasoning\": \"建議理由\"}}]}}",
+        SUGGESTIONS_PER_ATTRIBUTE, targets, definitions, skills, recent, crate::language::directive()
+    )
+}
+
+fn to_suggestion(raw: RawSuggestion, user_id: &str) -> Option<AttributeSuggestion> {
+    let attribute = raw.attribute?.trim().to_string();
+    attribute_name(&attribute)?;
+    let title = raw.title?.trim().to_string();
+    let task_type = raw
+        .task_type
+        .and_then(|t| t.parse::<TaskType>().ok())
+        .filter(|t| matches!(t, TaskType::Main | TaskType::Side | TaskType::Challenge))
+        .unwrap_or(TaskType::Side);
+    let difficulty = raw.difficulty.map(|d| d.clamp(1, 5));
+    let task = CreateTaskRequest {
+        user_id: Some(user_id.to_string()),
+        title,
+        description: raw.description,
+        priority: None,
+        task_type: Some(task_type.to_string()),
+        difficulty,
+        experience: raw.experience.map(|e| e.clamp(0, 10000)),
+        parent_task_id: None,
+        task_order: None,
+        due_date: None,
+        task_date: None,
+        is_recurring: None,
+        recurrence_pattern: None,
+        start_date: None,
+        end_date: None,
+        completion_target: None,
+        skill_tags: raw.skill_tags.map(|tags| tags.into_iter().take(3).collect()),
+        // 完成時獎勵對應的弱屬性，點數與難度相同
+        attributes: Some(serde_json::json!({ attribute.as_str(): difficulty.unwrap_or(1) })),
+        completion_mode: None,
+        require_proof: None,
+        stake: None,
+        requires_confirmation: None,
+        client_request_id: None,
+        source: None,
+    };
+    task.validate().ok()?;
+    Some(AttributeSuggestion {
+        attribute,
+        reasoning: raw.reasoning.map(|r| r.trim().to_string()).unwrap_or_default(),
+        task,
+    })
+}
+
+/// 解析 AI 回應：只保留弱屬性的有效建議，每項屬性最多 3 個
+fn parse_suggestions(reply: &str, weak: &[WeakAttribute], user_id: &str) -> Result<Vec<AttributeSuggestion>, String> {
+    let cleaned = reply
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let parsed: RawSuggestions = serde_json::from_str(cleaned).map_err(|e| format!("AI 回應格式錯誤: {}", e))?;
+
+    let mut per_attribute: HashMap<String, usize> = HashMap::new();
+    Ok(parsed
+        .suggestions
+        .into_iter()
+        .filter_map(|raw| to_suggestion(raw, user_id))
+        .filter(|s| weak.iter().any(|w| w.attribute == s.attribute))
+        .filter(|s| {
+            let count = per_attribute.entry(s.attribute.clone()).or_insert(0);
+            *count += 1;
+            *count <= SUGGESTIONS_PER_ATTRIBUTE
+        })
+        .collect())
+}
+
+// 尚無屬性記錄時視為新帳號的初始屬性
+fn neutral_attributes(user_id: &str) -> UserAttributes {
+    let defaults = crate::new_user_defaults::current();
+    UserAttributes {
+        id: None,
+        user_id: Some(user_id.to_string()),
+        intelligence: Some(defaults.attribute("intelligence")),
+        endurance: Some(defaults.attribute("endurance")),
+        creativity: Some(defaults.attribute("creativity")),
+        social: Some(defaults.attribute("social")),
+        focus: Some(defaults.attribute("focus")),
+        adaptability: Some(defaults.attribute("adaptability")),
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+async fn load_context(rb: &RBatis, user_id: &str) -> std::result::Result<(UserAttributes, Vec<String>, Vec<String>), rbatis::Error> {
+    let attributes = UserAttributes::select_by_map(rb, value!{"user_id": user_id})
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| neutral_attributes(user_id));
+    let skills = Skill::select_by_map(rb, value!{"user_id": user_id})
+        .await?
+        .into_iter()
+        .filter_map(|s| s.name)
+        .collect();
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT title FROM task WHERE user_id = ? AND parent_task_id IS NULL ORDER BY created_at DESC LIMIT ?",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::I64(RECENT_TASK_LIMIT)],
+        )
+        .await?;
+    let titles = rows.iter().filter_map(|row| row["title"].as_str().map(str::to_string)).collect();
+    Ok((attributes, skills, titles))
+}
+
+/// 取得屬性補強建議（24 小時內重複呼叫回傳快取結果）
+/// GET /api/users/{id}/recommendations/attributes
+pub async fn get_attribute_recommendations(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<SharedAIService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let now = Utc::now();
+    if let Some(mut cached) = cached_for(&user_id, now) {
+        cached.cached = true;
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(cached),
+            message: "獲取屬性建議成功".to_string(),
+        }));
+    }
+
+    let (attributes, skills, recent_titles) = match load_context(rb.get_ref(), &user_id).await {
+        Ok(context) => context,
+        Err(e) => {
+            log::error!("讀取屬性建議資料失敗: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("讀取屬性建議資料失敗: {}", e),
+            }));
+        }
+    };
+    let weak = weakest_attributes(&attributes, WEAK_ATTRIBUTE_COUNT);
+    let prompt = build_prompt(&weak, &skills, &recent_titles);
+
+    let reply = match ai.get() {
+        Ok(service) => service.generate_with_model(ai.small_model(), &prompt).await,
+        Err(e) => Err(e),
+    };
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(e) => return Ok(crate::ai_tasks::ai_failure_response("AI 生成屬性建議失敗", &e)),
+    };
+    let suggestions = match parse_suggestions(&reply, &weak, &user_id) {
+        Ok(suggestions) => suggestions,
+        Err(message) => {
+            log::warn!("{}: {}", message, reply);
+            return Ok(HttpResponse::BadGateway().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message,
+            }));
+        }
+    };
+
+    let recommendations = AttributeRecommendations {
+        weak_attributes: weak,
+        suggestions,
+        generated_at: now,
+        cached: false,
+    };
+    // 沒有任何有效建議時不快取，讓使用者可以馬上重試
+    if !recommendations.suggestions.is_empty() {
+        if let Ok(mut cache) = cache().lock() {
+            cache.insert(user_id, recommendations.clone());
+        }
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(recommendations),
+        message: "獲取屬性建議成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_ai::MockAIService;
+    use crate::test_utils::{self, call_json};
+    use std::sync::Arc;
+
+    fn attrs(values: [i32; 6]) -> UserAttributes {
+        UserAttributes {
+            intelligence: Some(values[0]),
+            endurance: Some(values[1]),
+            creativity: Some(values[2]),
+            social: Some(values[3]),
+            focus: Some(values[4]),
+            adaptability: Some(values[5]),
+            ..neutral_attributes("u1")
+        }
+    }
+
+    #[test]
+    fn test_weakest_attributes_prefers_declared_order_on_ties() {
+        let weak = weakest_attributes(&attrs([60, 40, 70, 40, 55, 40]), 2);
+        let keys: Vec<&str> = weak.iter().map(|w| w.attribute.as_str()).collect();
+        assert_eq!(keys, vec!["endurance", "social"]);
+        assert_eq!(weak[1].name, "社交力");
+    }
+
+    #[test]
+    fn test_parse_suggestions_filters_and_caps() {
+        let weak = weakest_attributes(&attrs([60, 60, 60, 10, 20, 60]), 2);
+        let mut items: Vec<serde_json::Value> = (0..4)
+            .map(|i| serde_json::json!({"attribute": "social", "title": format!("社交任務 {}", i), "difficulty": 9, "reasoning": "多和人互動"}))
+            .collect();
+        items.push(serde_json::json!({"attribute": "creativity", "title": "畫一張圖"}));
+        items.push(serde_json::json!({"attribute": "focus", "title": ""}));
+        items.push(serde_json::json!({"attribute": "focus", "title": "番茄鐘專注 25 分鐘", "task_type": "unknown"}));
+        let reply = format!("```json\n{}\n```", serde_json::json!({ "suggestions": items }));
+
+        let suggestions = parse_suggestions(&reply, &weak, "u1").unwrap();
+        assert_eq!(suggestions.iter().filter(|s| s.attribute == "social").count(), 3);
+        let focus: Vec<_> = suggestions.iter().filter(|s| s.attribute == "focus").collect();
+        assert_eq!(focus.len(), 1);
+        assert_eq!(focus[0].task.task_type.as_deref(), Some("side"));
+        assert_eq!(suggestions[0].task.difficulty, Some(5));
+        assert_eq!(suggestions[0].task.attributes, Some(serde_json::json!({"social": 5})));
+        assert!(parse_suggestions("不是 JSON", &weak, "u1").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_attribute_recommendations_are_cached() {
+        let reply = serde_json::json!({"suggestions": [
+            {"attribute": "social", "title": "和同事分享 Rust 心得", "description": "午餐時間分享 10 分鐘", "task_type": "side",
+             "difficulty": 2, "experience": 40, "skill_tags": ["Rust"], "reasoning": "用熟悉的主題練習表達"},
+            {"attribute": "focus", "title": "關閉通知寫程式 45 分鐘", "difficulty": 3, "experience": 60, "reasoning": "建立深度工作習慣"}
+        ]})
+        .to_string();
+        let mock = MockAIService::with_replies(&[&reply]);
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "weak_spots").await;
+        let other = test_utils::create_user(&app, "weak_spots_other").await;
+
+        rb.exec(
+            "UPDATE user_attributes SET social = 20, focus = 30 WHERE user_id = ?",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/skills")
+            .insert_header(user.auth())
+            .set_json(serde_json::json!({"user_id": user.id, "name": "Rust", "category": "technical", "attribute": "intelligence"}))
+            .to_request();
+        call_json(&app, req).await;
+
+        let uri = format!("/api/users/{}/recommendations/attributes", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let data = &body["data"];
+        assert_eq!(data["weak_attributes"][0]["attribute"], "social");
+        assert_eq!(data["weak_attributes"][1]["attribute"], "focus");
+        assert_eq!(data["suggestions"].as_array().unwrap().len(), 2);
+        assert_eq!(data["suggestions"][0]["task"]["user_id"], user.id.as_str());
+        assert_eq!(data["suggestions"][0]["task"]["attributes"]["social"], 2);
+        assert_eq!(data["cached"], false);
+
+        let prompts = mock.prompts("generate_with_model");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("social (社交力)：目前 20 / 100"));
+        assert!(prompts[0].contains("Rust"));
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["cached"], true);
+        assert_eq!(mock.prompts("generate_with_model").len(), 1);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+}
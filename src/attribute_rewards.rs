@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+
+use crate::config::SkillAttributeConfig;
+use crate::models::{AttributeHistory, UserAttributes};
+
+pub const SOURCE_TASK: &str = "task";
+pub const SOURCE_MANUAL: &str = "manual";
+pub const SOURCE_FOCUS: &str = "focus";
+pub const SOURCE_SKILL_LEVEL_UP: &str = "skill_level_up";
+
+static SKILL_ATTRIBUTE: OnceLock<SkillAttributeConfig> = OnceLock::new();
+
+/// 啟動時套用技能升級的屬性成長設定
+pub fn init_skill_attribute(config: SkillAttributeConfig) {
+    log::info!(
+        "技能升級屬性成長: 每級 +{}，依類別覆寫 {:?}",
+        config.gain_per_level,
+        config.category_gains
+    );
+    if SKILL_ATTRIBUTE.set(config).is_err() {
+        log::warn!("技能升級屬性成長設定已初始化，忽略重複設定");
+    }
+}
+
+/// 技能升了 levels 級時，對應屬性應增加的點數
+pub fn skill_level_up_gain(category: Option<&str>, levels: i32) -> i32 {
+    SKILL_ATTRIBUTE.get_or_init(SkillAttributeConfig::default).gain_for(category) * levels.max(0)
+}
+
+/// 屬性變化結果：更新後的屬性與各屬性的 (舊值, 新值)
+#[derive(Debug, Clone)]
+pub struct AttributeChanges {
+    pub attributes: UserAttributes,
+    pub changes: HashMap<String, (i32, i32)>,
+}
+
+impl AttributeChanges {
+    /// 實際套用的增減量（已扣除 0-100 上下限截斷的部分）
+    pub fn applied_gains(&self) -> HashMap<String, i32> {
+        self.changes
+            .iter()
+            .map(|(name, (old_val, new_val))| (name.clone(), new_val - old_val))
+            .collect()
+    }
+}
+
+fn default_attributes(user_id: &str) -> UserAttributes {
+    // 新的屬性記錄使用新帳號的初始屬性設定
+    let defaults = crate::new_user_defaults::current();
+    UserAttributes {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id.to_string()),
+        intelligence: Some(defaults.attribute("intelligence")),
+        endurance: Some(defaults.attribute("endurance")),
+        creativity: Some(defaults.attribute("creativity")),
+        social: Some(defaults.attribute("social")),
+        focus: Some(defaults.attribute("focus")),
+        adaptability: Some(defaults.attribute("adaptability")),
+        created_at: Some(Utc::now()),
+        updated_at: Some(Utc::now()),
+    }
+}
+
+/// 套用單一屬性變化（限制在 0-100 之間）；未知的屬性名稱回傳 None
+pub fn apply_attribute_change(attrs: &mut UserAttributes, name: &str, change: i32) -> Option<(i32, i32)> {
+    let field = match name {
+        "intelligence" => &mut attrs.intelligence,
+        "endurance" => &mut attrs.endurance,
+        "creativity" => &mut attrs.creativity,
+        "social" => &mut attrs.social,
+        "focus" => &mut attrs.focus,
+        "adaptability" => &mut attrs.adaptability,
+        _ => return None,
+    };
+    let old_val = field.unwrap_or(50);
+    let new_val = (old_val + change).clamp(0, 100);
+    *field = Some(new_val);
+    Some((old_val, new_val))
+}
+
+/// 解析任務的 attributes JSON（如 {"intelligence": 2, "focus": 1}），略過非整數的值
+pub fn parse_task_attributes(attributes: &serde_json::Value) -> Vec<(String, i32)> {
+    let Some(obj) = attributes.as_object() else {
+        log::warn!("任務屬性獎勵格式錯誤: {}", attributes);
+        return Vec::new();
+    };
+    obj.iter()
+        .filter_map(|(name, v)| match v.as_i64() {
+            Some(delta) => Some((name.clone(), delta as i32)),
+            None => {
+                log::warn!("任務屬性 {} 的獎勵值不是整數: {}", name, v);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 為使用者套用屬性增減，寫入屬性變化紀錄並累計到今日進度
+///
+/// 未知的屬性名稱只記錄警告，不影響其他屬性
+pub async fn apply_attribute_deltas(
+    rb: &RBatis,
+    user_id: &str,
+    deltas: &[(String, i32)],
+    source: &str,
+    task_id: Option<&str>,
+) -> Result<AttributeChanges, rbatis::Error> {
+    let existing = UserAttributes::select_by_map(rb, value!{"user_id": user_id}).await?.into_iter().next();
+    let is_new = existing.is_none();
+    let mut attrs = existing.unwrap_or_else(|| default_attributes(user_id));
+
+    let mut changes = HashMap::new();
+    for (name, change) in deltas {
+        match apply_attribute_change(&mut attrs, name, *change) {
+            Some(change) => {
+                changes.insert(name.clone(), change);
+            }
+            None => log::warn!("未知的屬性名稱: {}", name),
+        }
+    }
+    attrs.updated_at = Some(Utc::now());
+
+    if is_new {
+        UserAttributes::insert(rb, &attrs).await?;
+    } else {
+        UserAttributes::update_by_map(rb, &attrs, value!{"user_id": user_id}).await?;
+    }
+    crate::gamified_cache::invalidate(user_id);
+
+    let result = AttributeChanges { attributes: attrs, changes };
+    let gains: HashMap<String, i32> = result.applied_gains().into_iter().filter(|(_, delta)| *delta != 0).collect();
+    if gains.is_empty() {
+        return Ok(result);
+    }
+
+    for (name, (old_val, new_val)) in &result.changes {
+        if old_val == new_val {
+            continue;
+        }
+        let history = AttributeHistory {
+            id: Some(uuid::Uuid::new_v4().to_string()),
+            user_id: Some(user_id.to_string()),
+            attribute: Some(name.clone()),
+            old_value: Some(*old_val),
+            new_value: Some(*new_val),
+            delta: Some(new_val - old_val),
+            source: Some(source.to_string()),
+            task_id: task_id.map(|s| s.to_string()),
+            created_at: Some(Utc::now()),
+        };
+        if let Err(e) = AttributeHistory::insert(rb, &history).await {
+            log::warn!("記錄屬性變化失敗: {}", e);
+        }
+    }
+
+    if let Err(e) = record_daily_attribute_gains(rb, user_id, &gains).await {
+        log::warn!("累計今日屬性成長失敗: {}", e);
+    }
+    Ok(result)
+}
+
+/// 將屬性成長合併到今日（使用者時區）的 daily_progress.attributes_gained
+///
+/// 在 UPSERT 內以 json_set 逐項累加，同時的屬性更新不會互相覆蓋
+async fn record_daily_attribute_gains(rb: &RBatis, user_id: &str, gains: &HashMap<String, i32>) -> Result<(), rbatis::Error> {
+    if gains.is_empty() {
+        return Ok(());
+    }
+    let today = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb, user_id).await);
+    let now = Utc::now().to_rfc3339();
+
+    let initial = merge_attribute_gains(None, gains);
+    // 既有值不是 JSON 物件時視為空物件
+    let current = "CASE WHEN json_valid(attributes_gained) AND json_type(attributes_gained) = 'object' THEN attributes_gained ELSE '{}' END";
+    let mut gains: Vec<(&String, &i32)> = gains.iter().collect();
+    gains.sort();
+    let assignments = gains
+        .iter()
+        .map(|_| format!("'$.' || ?, COALESCE(json_extract({current}, '$.' || ?), 0) + ?"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "INSERT INTO daily_progress (id, user_id, date, completed_tasks, total_tasks, experience_gained, attributes_gained, created_at, updated_at)
+         VALUES (?, ?, ?, 0, 0, 0, ?, ?, ?)
+         ON CONFLICT(user_id, date) DO UPDATE SET
+             attributes_gained = json_set({current}, {assignments}),
+             updated_at = excluded.updated_at"
+    );
+
+    let mut args = vec![
+        rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+        rbs::Value::String(user_id.to_string()),
+        rbs::Value::String(today),
+        rbs::Value::String(initial.to_string()),
+        rbs::Value::String(now.clone()),
+        rbs::Value::String(now),
+    ];
+    for (name, delta) in gains {
+        args.push(rbs::Value::String(name.clone()));
+        args.push(rbs::Value::String(name.clone()));
+        args.push(rbs::Value::I32(*delta));
+    }
+    rb.exec(&sql, args).await?;
+    crate::gamified_cache::invalidate(user_id);
+    Ok(())
+}
+
+/// 合併既有的屬性成長（資料庫中可能是 JSON 字串或物件）
+pub fn merge_attribute_gains(current: Option<&serde_json::Value>, gains: &HashMap<String, i32>) -> serde_json::Value {
+    let mut merged = match current {
+        Some(serde_json::Value::String(text)) => serde_json::from_str::<serde_json::Value>(text).unwrap_or_default(),
+        Some(value) => value.clone(),
+        None => serde_json::Value::Null,
+    };
+    if !merged.is_object() {
+        merged = serde_json::json!({});
+    }
+    if let Some(obj) = merged.as_object_mut() {
+        for (name, delta) in gains {
+            let total = obj.get(name).and_then(|v| v.as_i64()).unwrap_or(0) + *delta as i64;
+            obj.insert(name.clone(), serde_json::json!(total));
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_is_clamped_and_unknown_name_ignored() {
+        let mut attrs = default_attributes("u1");
+        attrs.focus = Some(98);
+        assert_eq!(apply_attribute_change(&mut attrs, "focus", 5), Some((98, 100)));
+        assert_eq!(apply_attribute_change(&mut attrs, "social", -70), Some((50, 0)));
+        assert_eq!(apply_attribute_change(&mut attrs, "charisma", 3), None);
+    }
+
+    #[test]
+    fn test_parse_task_attributes_skips_invalid_values() {
+        let parsed = parse_task_attributes(&serde_json::json!({"intelligence": 2, "focus": "high"}));
+        assert_eq!(parsed, vec![("intelligence".to_string(), 2)]);
+        assert!(parse_task_attributes(&serde_json::json!(["intelligence"])).is_empty());
+    }
+
+    #[test]
+    fn test_merge_attribute_gains_accumulates() {
+        let gains = HashMap::from([("intelligence".to_string(), 2), ("focus".to_string(), 1)]);
+        let current = serde_json::Value::String(r#"{"intelligence": 3, "endurance": 1}"#.to_string());
+        let merged = merge_attribute_gains(Some(&current), &gains);
+        assert_eq!(merged, serde_json::json!({"intelligence": 5, "endurance": 1, "focus": 1}));
+        assert_eq!(merge_attribute_gains(None, &gains), serde_json::json!({"intelligence": 2, "focus": 1}));
+    }
+
+    #[tokio::test]
+    async fn test_task_rewards_are_recorded() {
+        let path = std::env::temp_dir().join(format!("lifeup_attribute_rewards_{}.db", uuid::Uuid::new_v4()));
+        let rb = RBatis::new();
+        rb.init(rbdc_sqlite::driver::SqliteDriver {}, &format!("sqlite://{}", path.display())).unwrap();
+        crate::create_tables(&rb).await;
+        crate::migrate_database(&rb).await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+
+        let deltas = parse_task_attributes(&serde_json::json!({"intelligence": 2, "charisma": 5}));
+        let result = apply_attribute_deltas(&rb, "u1", &deltas, SOURCE_TASK, Some("t1")).await.unwrap();
+        assert_eq!(result.attributes.intelligence, Some(52));
+        assert_eq!(result.applied_gains(), HashMap::from([("intelligence".to_string(), 2)]));
+
+        apply_attribute_deltas(&rb, "u1", &deltas, SOURCE_TASK, Some("t2")).await.unwrap();
+        let history = AttributeHistory::select_by_map(&rb, value!{"user_id": "u1"}).await.unwrap();
+        assert_eq!(history.len(), 2);
+
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT attributes_gained FROM daily_progress WHERE user_id = 'u1'", vec![])
+            .await
+            .unwrap();
+        let gained = merge_attribute_gains(rows.first().and_then(|r| r.get("attributes_gained")), &HashMap::new());
+        assert_eq!(gained, serde_json::json!({"intelligence": 4}));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
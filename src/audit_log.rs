@@ -0,0 +1,37 @@
+use chrono::Utc;
+use rbatis::RBatis;
+
+use crate::models::AuditLog;
+
+pub const ACTION_LOGIN_LOCKOUT: &str = "login_lockout";
+pub const ACTION_LOGIN_IP_THROTTLED: &str = "login_ip_throttled";
+pub const ACTION_AI_QUOTA_UPDATED: &str = "ai_quota_updated";
+pub const ACTION_DATA_PRUNED: &str = "data_pruned";
+pub const ACTION_API_TOKEN_CREATED: &str = "api_token_created";
+pub const ACTION_API_TOKEN_REVOKED: &str = "api_token_revoked";
+pub const ACTION_IMPERSONATION_STARTED: &str = "impersonation_started";
+pub const ACTION_IMPERSONATED_REQUEST: &str = "impersonated_request";
+#[cfg_attr(not(feature = "push-notifications"), allow(dead_code))]
+pub const ACTION_VAPID_KEYS_ROTATED: &str = "vapid_keys_rotated";
+
+/// 寫入一筆稽核日誌；寫入失敗只記錄警告，不影響主要流程
+pub async fn record(
+    rb: &RBatis,
+    action: &str,
+    user_id: Option<&str>,
+    ip_address: Option<&str>,
+    detail: serde_json::Value,
+) {
+    let entry = AuditLog {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: user_id.map(|s| s.to_string()),
+        action: Some(action.to_string()),
+        ip_address: ip_address.map(|s| s.to_string()),
+        detail: Some(detail.to_string()),
+        created_at: Some(Utc::now()),
+    };
+    log::info!("稽核日誌: action={} user={:?} ip={:?} detail={}", action, user_id, ip_address, detail);
+    if let Err(e) = AuditLog::insert(rb, &entry).await {
+        log::warn!("寫入稽核日誌失敗: {}", e);
+    }
+}
@@ -1,4 +1,4 @@
-use actix_web::{dev::ServiceRequest, Error, HttpMessage, HttpResponse};
+use actix_web::{dev::ServiceRequest, web, Error, HttpMessage, HttpRequest, HttpResponse};
 use actix_web::error::ErrorUnauthorized;
 use actix_web::dev::{forward_ready, Service, ServiceResponse, Transform};
 use actix_web::body::EitherBody;
@@ -7,9 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use chrono::{Duration, Utc};
 use std::future::{ready, Ready};
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::rc::Rc;
 use futures::future::LocalBoxFuture;
+use rbatis::RBatis;
 
 // JWT Claims 結構
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,10 +18,26 @@ pub struct Claims {
     pub email: String,    // User email
     pub exp: usize,       // Expiration time (timestamp)
     pub iat: usize,       // Issued at (timestamp)
+    #[serde(default)]
+    pub sid: Option<String>,  // 登入裝置（user_session.id），舊 token 沒有此欄位
+    #[serde(default)]
+    pub ver: i32,             // 簽發時的 user.token_version，登出所有裝置後舊 token 失效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<ImpersonationActor>,  // 代理檢視：簽發此 token 的管理員（見 impersonation.rs）
+    #[serde(default)]
+    pub read_only: bool,      // 只能發出讀取請求（代理檢視 token 一律為 true）
+}
+
+/// 代理檢視時實際操作的管理員
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImpersonationActor {
+    pub sub: String,
+    pub email: String,
 }
 
 // JWT 配置常量
-const JWT_EXPIRATION_HOURS: i64 = 24; // Token 有效期 24 小時
+pub const JWT_EXPIRATION_HOURS: i64 = 24; // Token 有效期 24 小時
+pub const IMPERSONATION_EXPIRATION_MINUTES: i64 = 15; // 代理檢視 token 有效期 15 分鐘，不可延長
 
 /// 獲取 JWT 密鑰
 fn get_jwt_secret() -> String {
@@ -31,8 +47,8 @@ fn get_jwt_secret() -> String {
     })
 }
 
-/// 生成 JWT token
-pub fn generate_jwt(user_id: &str, email: &str) -> Result<String, jsonwebtoken::errors::Error> {
+/// 生成 JWT token（綁定登入裝置與使用者目前的 token 版本）
+pub fn generate_jwt(user_id: &str, email: &str, session_id: &str, token_version: i32) -> Result<String, jsonwebtoken::errors::Error> {
     let now = Utc::now();
     let exp = (now + Duration::hours(JWT_EXPIRATION_HOURS)).timestamp() as usize;
     let iat = now.timestamp() as usize;
@@ -42,6 +58,10 @@ pub fn generate_jwt(user_id: &str, email: &str) -> Result<String, jsonwebtoken::
         email: email.to_string(),
         exp,
         iat,
+        sid: Some(session_id.to_string()),
+        ver: token_version,
+        act: None,
+        read_only: false,
     };
 
     let secret = get_jwt_secret();
@@ -52,6 +72,33 @@ pub fn generate_jwt(user_id: &str, email: &str) -> Result<String, jsonwebtoken::
     )
 }
 
+/// 生成代理檢視 token：主體為被檢視的使用者，act 記錄管理員，唯讀且不綁定登入裝置；回傳 token 與到期時間
+pub fn generate_impersonation_jwt(
+    user_id: &str,
+    email: &str,
+    token_version: i32,
+    actor: ImpersonationActor,
+) -> Result<(String, chrono::DateTime<Utc>), jsonwebtoken::errors::Error> {
+    let now = Utc::now();
+    let expires_at = now + Duration::minutes(IMPERSONATION_EXPIRATION_MINUTES);
+    let claims = Claims {
+        sub: user_id.to_string(),
+        email: email.to_string(),
+        exp: expires_at.timestamp() as usize,
+        iat: now.timestamp() as usize,
+        sid: None,
+        ver: token_version,
+        act: Some(actor),
+        read_only: true,
+    };
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(get_jwt_secret().as_bytes()),
+    )?;
+    Ok((token, expires_at))
+}
+
 /// 驗證 JWT token
 pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     let secret = get_jwt_secret();
@@ -104,17 +151,40 @@ pub fn get_user_id_from_request(req: &ServiceRequest) -> Result<String, Error> {
     Err(ErrorUnauthorized("需要 JWT 認證"))
 }
 
+/// 檢查請求者是否為管理員（email 列於 ADMIN_EMAILS，以逗號分隔）；代理檢視中一律不具管理員權限
+pub fn is_admin_request(req: &HttpRequest) -> bool {
+    let admin_emails = env::var("ADMIN_EMAILS").unwrap_or_default();
+    let claims = req.extensions().get::<Claims>().cloned();
+    match claims {
+        Some(claims) if claims.act.is_some() => false,
+        Some(claims) => admin_emails
+            .split(',')
+            .map(|e| e.trim())
+            .any(|e| !e.is_empty() && e.eq_ignore_ascii_case(&claims.email)),
+        None => false,
+    }
+}
+
+/// 從 HttpRequest 取得已通過 JWT 驗證的 user_id
+pub fn current_user_id(req: &HttpRequest) -> Option<String> {
+    req.extensions().get::<String>().cloned()
+}
+
 /// 從請求擴展中獲取 user_id（由中間件設置）
 pub fn get_user_id_from_extensions(req: &ServiceRequest) -> Option<String> {
     req.extensions().get::<String>().cloned()
 }
 
-// JWT 認證中間件
+// JWT 認證中間件（也接受個人 API token，見 api_tokens.rs）
+//
+// 除了驗證簽章與有效期，每個請求還會查詢一次資料庫（user.token_version 與 user_session），
+// 確保已撤銷的裝置或「登出所有裝置」前簽發的 token 在有效期內也會被拒絕。
+// 查詢以主鍵比對單筆資料，成本很低；last_seen_at 每 5 分鐘最多寫入一次。
 pub struct JwtAuth;
 
 impl<S, B> Transform<S, ServiceRequest> for JwtAuth
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -125,17 +195,56 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(JwtAuthMiddleware { service }))
+        ready(Ok(JwtAuthMiddleware { service: Rc::new(service) }))
     }
 }
 
 pub struct JwtAuthMiddleware<S> {
-    service: S,
+    service: Rc<S>,
+}
+
+/// 建立 401 回應（附上 CORS 頭部，讓前端能讀到錯誤訊息）
+fn unauthorized_response<B>(req: ServiceRequest, message: String) -> ServiceResponse<EitherBody<B>> {
+    error_response(req, actix_web::http::StatusCode::UNAUTHORIZED, message)
+}
+
+fn error_response<B>(
+    req: ServiceRequest,
+    status: actix_web::http::StatusCode,
+    message: String,
+) -> ServiceResponse<EitherBody<B>> {
+    // 獲取請求的 Origin 頭部
+    let origin = req.headers()
+        .get("origin")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    let mut response = HttpResponse::build(status)
+        .content_type("application/json")
+        .json(serde_json::json!({
+            "success": false,
+            "data": serde_json::Value::Null,
+            "message": message
+        }));
+
+    // 添加 CORS 頭部
+    if let Some(origin_value) = origin.and_then(|o| actix_web::http::header::HeaderValue::from_str(&o).ok()) {
+        response.headers_mut().insert(
+            actix_web::http::header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            origin_value
+        );
+        response.headers_mut().insert(
+            actix_web::http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            actix_web::http::header::HeaderValue::from_static("true")
+        );
+    }
+
+    req.into_response(response).map_into_boxed_body().map_into_right_body()
 }
 
 impl<S, B> Service<ServiceRequest> for JwtAuthMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -146,92 +255,115 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // 提取並驗證 JWT token
-        let token_result = extract_token_from_header(&req);
-
-        match token_result {
-            Ok(token) => {
-                match verify_jwt(&token) {
-                    Ok(claims) => {
-                        // 將 user_id 存入請求擴展
-                        req.extensions_mut().insert(claims.sub.clone());
-                        req.extensions_mut().insert(claims.clone());
-
-                        let fut = self.service.call(req);
-                        Box::pin(async move {
-                            let res = fut.await?;
-                            Ok(res.map_into_left_body())
-                        })
-                    }
+        let token = match extract_token_from_header(&req) {
+            Ok(token) => token,
+            Err(e) => {
+                let error_msg = e.to_string();
+                return Box::pin(async move { Ok(unauthorized_response(req, error_msg)) });
+            }
+        };
+
+        // 個人 API token：查詢資料庫驗證，並檢查權限範圍
+        if token.starts_with(crate::api_tokens::TOKEN_PREFIX) {
+            let rb = req.app_data::<web::Data<RBatis>>().cloned();
+            let service = self.service.clone();
+            return Box::pin(async move {
+                let Some(rb) = rb else {
+                    log::error!("JwtAuth 找不到資料庫連線，無法驗證 API token");
+                    return Ok(unauthorized_response(req, "無法驗證登入狀態".to_string()));
+                };
+                let (claims, token_auth) = match crate::api_tokens::authenticate(rb.get_ref(), &token).await {
+                    Ok(Some(resolved)) => resolved,
+                    Ok(None) => return Ok(unauthorized_response(req, "API token 無效或已撤銷".to_string())),
                     Err(e) => {
-                        log::warn!("JWT 驗證失敗: {}", e);
-
-                        // 獲取請求的 Origin 頭部
-                        let origin = req.headers()
-                            .get("origin")
-                            .and_then(|v| v.to_str().ok())
-                            .map(|s| s.to_string());
-
-                        Box::pin(async move {
-                            let mut response = HttpResponse::Unauthorized()
-                                .content_type("application/json")
-                                .json(serde_json::json!({
-                                    "success": false,
-                                    "data": serde_json::Value::Null,
-                                    "message": format!("無效的 JWT: {}", e)
-                                }));
-
-                            // 添加 CORS 頭部
-                            if let Some(origin_value) = origin {
-                                response.headers_mut().insert(
-                                    actix_web::http::header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                                    actix_web::http::header::HeaderValue::from_str(&origin_value).unwrap()
-                                );
-                                response.headers_mut().insert(
-                                    actix_web::http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
-                                    actix_web::http::header::HeaderValue::from_static("true")
-                                );
-                            }
-
-                            Ok(req.into_response(response).map_into_boxed_body().map_into_right_body())
-                        })
+                        log::error!("驗證 API token 失敗: {}", e);
+                        return Ok(unauthorized_response(req, "無法驗證登入狀態".to_string()));
                     }
+                };
+                if !token_auth.scope.allows(req.method()) {
+                    log::info!("拒絕唯讀 API token {} 的請求: {} {}", token_auth.token_id, req.method(), req.path());
+                    return Ok(error_response(
+                        req,
+                        actix_web::http::StatusCode::FORBIDDEN,
+                        "此 API token 僅能讀取資料".to_string(),
+                    ));
                 }
-            }
+
+                // 使用者語言：AI 提示詞與回應訊息的語系（ApiEnvelope 由 extensions 讀取）
+                let language = crate::language::user_language(rb.get_ref(), &claims.sub).await;
+
+                crate::request_context::set_user_id(&claims.sub);
+                req.extensions_mut().insert(claims.sub.clone());
+                req.extensions_mut().insert(claims);
+                req.extensions_mut().insert(token_auth);
+                req.extensions_mut().insert(language);
+
+                let res = crate::language::scope(language, service.call(req)).await?;
+                Ok(res.map_into_left_body())
+            });
+        }
+
+        // 驗證 JWT 簽章與有效期
+        let claims = match verify_jwt(&token) {
+            Ok(claims) => claims,
             Err(e) => {
-                // 獲取請求的 Origin 頭部
-                let origin = req.headers()
-                    .get("origin")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|s| s.to_string());
+                log::warn!("JWT 驗證失敗: {}", e);
+                return Box::pin(async move { Ok(unauthorized_response(req, format!("無效的 JWT: {}", e))) });
+            }
+        };
+
+        let rb = req.app_data::<web::Data<RBatis>>().cloned();
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // 檢查 token 是否已被撤銷（裝置登出或登出所有裝置）
+            let Some(rb) = rb else {
+                log::error!("JwtAuth 找不到資料庫連線，無法檢查登入狀態");
+                return Ok(unauthorized_response(req, "無法驗證登入狀態".to_string()));
+            };
+            match crate::sessions::validate_session(rb.get_ref(), &claims).await {
+                Ok(crate::sessions::SessionCheck::Valid) => {}
+                Ok(check) => {
+                    log::info!("拒絕已失效的 token (user_id: {}): {:?}", claims.sub, check);
+                    return Ok(unauthorized_response(req, check.message().to_string()));
+                }
+                Err(e) => {
+                    log::error!("檢查登入狀態失敗: {}", e);
+                    return Ok(unauthorized_response(req, "無法驗證登入狀態".to_string()));
+                }
+            }
 
-                let error_msg = e.to_string();
+            // 代理檢視：只允許讀取，每個請求都寫入稽核日誌（同時記錄管理員與被檢視的使用者）
+            let impersonation = claims.act.clone().map(|actor| {
+                let request = crate::impersonation::ImpersonatedRequest::new(&req, &claims.sub, actor);
+                (request, claims.read_only)
+            });
+            if let Some((request, read_only)) = &impersonation {
+                if *read_only && !crate::impersonation::is_read_method(req.method()) {
+                    request.record(rb.get_ref(), actix_web::http::StatusCode::FORBIDDEN, true).await;
+                    return Ok(error_response(
+                        req,
+                        actix_web::http::StatusCode::FORBIDDEN,
+                        "代理檢視模式僅能讀取資料".to_string(),
+                    ));
+                }
+            }
 
-                Box::pin(async move {
-                    let mut response = HttpResponse::Unauthorized()
-                        .content_type("application/json")
-                        .json(serde_json::json!({
-                            "success": false,
-                            "data": serde_json::Value::Null,
-                            "message": error_msg
-                        }));
-
-                    // 添加 CORS 頭部
-                    if let Some(origin_value) = origin {
-                        response.headers_mut().insert(
-                            actix_web::http::header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                            actix_web::http::header::HeaderValue::from_str(&origin_value).unwrap()
-                        );
-                        response.headers_mut().insert(
-                            actix_web::http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
-                            actix_web::http::header::HeaderValue::from_static("true")
-                        );
-                    }
+            // 將 user_id 存入請求擴展，並帶入日誌上下文
+            // 使用者語言：AI 提示詞與回應訊息的語系（ApiEnvelope 由 extensions 讀取）
+            let language = crate::language::user_language(rb.get_ref(), &claims.sub).await;
 
-                    Ok(req.into_response(response).map_into_boxed_body().map_into_right_body())
-                })
+            crate::request_context::set_user_id(&claims.sub);
+            req.extensions_mut().insert(claims.sub.clone());
+            req.extensions_mut().insert(claims);
+            req.extensions_mut().insert(language);
+
+            let res = crate::language::scope(language, service.call(req)).await?;
+            if let Some((request, _)) = &impersonation {
+                request.record(rb.get_ref(), res.status(), false).await;
             }
-        }
+            Ok(res.map_into_left_body())
+        })
     }
 }
 
@@ -245,13 +377,15 @@ mod tests {
         let email = "test@example.com";
 
         // 生成 token
-        let token = generate_jwt(user_id, email).unwrap();
+        let token = generate_jwt(user_id, email, "session-1", 2).unwrap();
         assert!(!token.is_empty());
 
         // 驗證 token
         let claims = verify_jwt(&token).unwrap();
         assert_eq!(claims.sub, user_id);
         assert_eq!(claims.email, email);
+        assert_eq!(claims.sid.as_deref(), Some("session-1"));
+        assert_eq!(claims.ver, 2);
     }
 
     #[test]
@@ -0,0 +1,92 @@
+// 有上限的背景工作佇列：取代直接 tokio::spawn，避免大量請求同時觸發外部呼叫
+//
+// capacity 限制排隊中與執行中的工作總數，佇列已滿時略過並記錄日誌；
+// workers 限制同時執行的工作數，其餘工作排隊等待。
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::config::BackgroundJobConfig;
+
+static BACKGROUND_JOB_CONFIG: OnceLock<BackgroundJobConfig> = OnceLock::new();
+static SLOTS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+static WORKERS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// 啟動時套用設定
+pub fn init(config: BackgroundJobConfig) {
+    log::info!("背景工作佇列: 上限 {} 個工作，同時執行 {} 個", config.capacity, config.workers);
+    if BACKGROUND_JOB_CONFIG.set(config).is_err() {
+        log::warn!("背景工作佇列設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static BackgroundJobConfig {
+    BACKGROUND_JOB_CONFIG.get_or_init(BackgroundJobConfig::default)
+}
+
+fn slots() -> &'static Arc<Semaphore> {
+    SLOTS.get_or_init(|| Arc::new(Semaphore::new(config().capacity)))
+}
+
+fn workers() -> &'static Arc<Semaphore> {
+    WORKERS.get_or_init(|| Arc::new(Semaphore::new(config().workers)))
+}
+
+/// 送出背景工作；佇列已滿時回傳 false
+pub fn submit<F>(name: &str, job: F) -> bool
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    submit_after(name, Duration::ZERO, job)
+}
+
+/// 延遲 delay 後執行；等待期間佔用佇列名額，但不佔用執行名額
+pub fn submit_after<F>(name: &str, delay: Duration, job: F) -> bool
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let slot = match slots().clone().try_acquire_owned() {
+        Ok(slot) => slot,
+        Err(_) => {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            log::warn!("背景工作佇列已滿（上限 {}），略過工作: {}", config().capacity, name);
+            return false;
+        }
+    };
+    // 由請求觸發的工作沿用該請求的語言（AI 生成內容的語言）
+    let job = crate::language::in_current(job);
+    tokio::spawn(async move {
+        let _slot = slot;
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        if let Ok(_worker) = workers().clone().acquire_owned().await {
+            job.await;
+        }
+    });
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackgroundJobMetrics {
+    pub capacity: usize,
+    // 排隊中與執行中的工作數
+    pub pending: usize,
+    // 程序啟動後因佇列已滿而略過的工作數
+    pub dropped: u64,
+}
+
+pub fn metrics_snapshot() -> BackgroundJobMetrics {
+    let capacity = config().capacity;
+    BackgroundJobMetrics {
+        capacity,
+        pending: capacity.saturating_sub(slots().available_permits()),
+        dropped: DROPPED.load(Ordering::Relaxed),
+    }
+}
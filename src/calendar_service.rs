@@ -1,153 +1,336 @@
-use chrono::{NaiveDate, Datelike};
-use std::collections::HashSet;
-use std::fs;
-use std::sync::{Arc, RwLock};
-
-/// 假日服務，用於判斷特定日期是否為假日
-#[derive(Clone)]
-pub struct CalendarService {
-    holidays: Arc<RwLock<HashSet<NaiveDate>>>,
-}
-
-impl CalendarService {
-    /// 創建新的日曆服務並載入假日資料
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let mut holidays = HashSet::new();
-
-        // 載入所有年度的假日 CSV 文件
-        let calendar_dir = "calendar";
-
-        if let Ok(entries) = fs::read_dir(calendar_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("csv") {
-                    log::info!("載入假日文件: {:?}", path);
-                    match Self::load_holidays_from_csv(&path, &mut holidays) {
-                        Ok(count) => log::info!("✅ 成功載入 {} 個假日", count),
-                        Err(e) => log::error!("❌ 載入假日文件失敗: {}", e),
-                    }
-                }
-            }
-        } else {
-            log::warn!("警告: calendar 目錄不存在或無法讀取");
-        }
-
-        log::info!("假日服務初始化完成，共載入 {} 個假日", holidays.len());
-
-        Ok(Self {
-            holidays: Arc::new(RwLock::new(holidays)),
-        })
-    }
-
-    /// 從 CSV 文件載入假日資料
-    fn load_holidays_from_csv(
-        path: &std::path::Path,
-        holidays: &mut HashSet<NaiveDate>,
-    ) -> Result<usize, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(path)?;
-        let mut count = 0;
-
-        for line in content.lines().skip(1) {
-            // 跳過標題行
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            // CSV 格式: Subject,Start Date,Start Time,End Date,End Time,All Day Event,Description,Location
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 2 {
-                continue;
-            }
-
-            let subject = parts[0].trim();
-            let date_str = parts[1].trim();
-
-            // 跳過空行或沒有日期的行
-            if subject.is_empty() || date_str.is_empty() {
-                continue;
-            }
-
-            // 解析日期 (格式: YYYY/M/D 或 YYYY/MM/DD)
-            if let Ok(date) = Self::parse_date(date_str) {
-                holidays.insert(date);
-                count += 1;
-            }
-        }
-
-        Ok(count)
-    }
-
-    /// 解析日期字串 (支援 YYYY/M/D 和 YYYY/MM/DD 格式)
-    fn parse_date(date_str: &str) -> Result<NaiveDate, Box<dyn std::error::Error>> {
-        let parts: Vec<&str> = date_str.split('/').collect();
-        if parts.len() != 3 {
-            return Err("無效的日期格式".into());
-        }
-
-        let year: i32 = parts[0].parse()?;
-        let month: u32 = parts[1].parse()?;
-        let day: u32 = parts[2].parse()?;
-
-        Ok(NaiveDate::from_ymd_opt(year, month, day)
-            .ok_or("無效的日期")?)
-    }
-
-    /// 檢查指定日期是否為假日（包含週末和國定假日）
-    pub fn is_holiday(&self, date: NaiveDate) -> bool {
-        if let Ok(holidays) = self.holidays.read() {
-            holidays.contains(&date)
-        } else {
-            log::error!("無法讀取假日資料");
-            false
-        }
-    }
-
-    /// 檢查指定日期是否為週末（週六或週日）
-    pub fn is_weekend(&self, date: NaiveDate) -> bool {
-        let weekday = date.weekday();
-        matches!(weekday, chrono::Weekday::Sat | chrono::Weekday::Sun)
-    }
-
-    /// 檢查指定日期是否為工作日（非週末且非假日）
-    pub fn is_workday(&self, date: NaiveDate) -> bool {
-        !self.is_weekend(date) && !self.is_holiday(date)
-    }
-
-    /// 獲取假日總數
-    pub fn get_holiday_count(&self) -> usize {
-        if let Ok(holidays) = self.holidays.read() {
-            holidays.len()
-        } else {
-            0
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_date() {
-        assert!(CalendarService::parse_date("2025/1/1").is_ok());
-        assert!(CalendarService::parse_date("2025/12/25").is_ok());
-        assert!(CalendarService::parse_date("invalid").is_err());
-    }
-
-    #[test]
-    fn test_is_weekend() {
-        let service = CalendarService::new().unwrap();
-
-        // 2025/11/9 是週六
-        let saturday = NaiveDate::from_ymd_opt(2025, 11, 9).unwrap();
-        assert!(service.is_weekend(saturday));
-
-        // 2025/11/10 是週日
-        let sunday = NaiveDate::from_ymd_opt(2025, 11, 10).unwrap();
-        assert!(service.is_weekend(sunday));
-
-        // 2025/11/11 是週一
-        let monday = NaiveDate::from_ymd_opt(2025, 11, 11).unwrap();
-        assert!(!service.is_weekend(monday));
-    }
-}
+use chrono::{NaiveDate, Datelike};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+// 假日 CSV 所在目錄（相對於工作目錄）
+const CALENDAR_DIR: &str = "calendar";
+
+/// 假日服務，用於判斷特定日期是否為假日
+///
+/// 假日資料缺少或無法讀取時以「只判斷週末」的降級模式運作並記錄原因，
+/// 不影響啟動；修正資料後可經由管理員端點重新載入。複製出的實例共用同一份資料。
+#[derive(Clone)]
+pub struct CalendarService {
+    dir: PathBuf,
+    state: Arc<RwLock<CalendarState>>,
+}
+
+#[derive(Default)]
+struct CalendarState {
+    holidays: HashSet<NaiveDate>,
+    degraded_reason: Option<String>,
+}
+
+/// 一次讀取假日目錄的結果
+struct LoadedHolidays {
+    holidays: HashSet<NaiveDate>,
+    files: usize,
+    errors: Vec<String>,
+}
+
+impl LoadedHolidays {
+    /// 沒有任何假日檔案成功載入時的降級原因
+    fn degraded_reason(&self, dir: &Path) -> Option<String> {
+        if self.files > 0 {
+            return None;
+        }
+        Some(if self.errors.is_empty() {
+            format!("{} 目錄中沒有假日 CSV 檔案", dir.display())
+        } else {
+            self.errors.join("；")
+        })
+    }
+}
+
+/// 假日資料狀態（深度健康檢查與管理員端點使用）
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarStatus {
+    // full：使用國定假日資料；weekend_only：假日資料缺少，只判斷週末
+    pub mode: &'static str,
+    pub holiday_count: usize,
+    pub degraded_reason: Option<String>,
+    pub data_dir: String,
+}
+
+static SHARED_CALENDAR: OnceLock<CalendarService> = OnceLock::new();
+
+/// 全域共用的日曆服務（第一次使用時載入假日資料）
+pub fn shared() -> &'static CalendarService {
+    SHARED_CALENDAR.get_or_init(|| CalendarService::load(CALENDAR_DIR))
+}
+
+impl CalendarService {
+    /// 從指定目錄載入假日資料；資料缺少時進入只判斷週末的降級模式，不會失敗
+    pub fn load(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let loaded = Self::read_holidays(&dir);
+        let degraded_reason = loaded.degraded_reason(&dir);
+        match &degraded_reason {
+            Some(reason) => log::warn!("⚠️ 假日資料無法載入，改為只判斷週末: {}", reason),
+            None => log::info!("假日服務初始化完成，共載入 {} 個假日", loaded.holidays.len()),
+        }
+        Self {
+            dir,
+            state: Arc::new(RwLock::new(CalendarState {
+                holidays: loaded.holidays,
+                degraded_reason,
+            })),
+        }
+    }
+
+    /// 重新從磁碟載入假日資料；仍無法載入時保留目前的資料並回傳原因
+    pub fn reload(&self) -> Result<CalendarStatus, String> {
+        let loaded = Self::read_holidays(&self.dir);
+        if let Some(reason) = loaded.degraded_reason(&self.dir) {
+            log::warn!("重新載入假日資料失敗: {}", reason);
+            return Err(reason);
+        }
+        let count = loaded.holidays.len();
+        match self.state.write() {
+            Ok(mut state) => {
+                state.holidays = loaded.holidays;
+                state.degraded_reason = None;
+            }
+            Err(_) => return Err("無法更新假日資料".to_string()),
+        }
+        log::info!("已重新載入假日資料，共 {} 個假日", count);
+        Ok(self.status())
+    }
+
+    /// 目前的假日資料狀態
+    pub fn status(&self) -> CalendarStatus {
+        let (holiday_count, degraded_reason) = match self.state.read() {
+            Ok(state) => (state.holidays.len(), state.degraded_reason.clone()),
+            Err(_) => (0, Some("無法讀取假日資料".to_string())),
+        };
+        CalendarStatus {
+            mode: if degraded_reason.is_some() { "weekend_only" } else { "full" },
+            holiday_count,
+            degraded_reason,
+            data_dir: self.dir.display().to_string(),
+        }
+    }
+
+    /// 讀取目錄中所有年度的假日 CSV 文件
+    fn read_holidays(dir: &Path) -> LoadedHolidays {
+        let mut loaded = LoadedHolidays { holidays: HashSet::new(), files: 0, errors: Vec::new() };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                loaded.errors.push(format!("無法讀取 {} 目錄: {}", dir.display(), e));
+                return loaded;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("csv") {
+                log::info!("載入假日文件: {:?}", path);
+                match Self::load_holidays_from_csv(&path, &mut loaded.holidays) {
+                    Ok(count) => {
+                        log::info!("✅ 成功載入 {} 個假日", count);
+                        loaded.files += 1;
+                    }
+                    Err(e) => {
+                        log::error!("❌ 載入假日文件失敗: {}", e);
+                        loaded.errors.push(format!("{}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+        loaded
+    }
+
+    /// 從 CSV 文件載入假日資料
+    fn load_holidays_from_csv(
+        path: &std::path::Path,
+        holidays: &mut HashSet<NaiveDate>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let mut count = 0;
+
+        for line in content.lines().skip(1) {
+            // 跳過標題行
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // CSV 格式: Subject,Start Date,Start Time,End Date,End Time,All Day Event,Description,Location
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() < 2 {
+                continue;
+            }
+
+            let subject = parts[0].trim();
+            let date_str = parts[1].trim();
+
+            // 跳過空行或沒有日期的行
+            if subject.is_empty() || date_str.is_empty() {
+                continue;
+            }
+
+            // 解析日期 (格式: YYYY/M/D 或 YYYY/MM/DD)
+            if let Ok(date) = Self::parse_date(date_str) {
+                holidays.insert(date);
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// 解析日期字串 (支援 YYYY/M/D 和 YYYY/MM/DD 格式)
+    fn parse_date(date_str: &str) -> Result<NaiveDate, Box<dyn std::error::Error>> {
+        let parts: Vec<&str> = date_str.split('/').collect();
+        if parts.len() != 3 {
+            return Err("無效的日期格式".into());
+        }
+
+        let year: i32 = parts[0].parse()?;
+        let month: u32 = parts[1].parse()?;
+        let day: u32 = parts[2].parse()?;
+
+        Ok(NaiveDate::from_ymd_opt(year, month, day)
+            .ok_or("無效的日期")?)
+    }
+
+    /// 檢查指定日期是否為假日（包含週末和國定假日）
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        if let Ok(state) = self.state.read() {
+            state.holidays.contains(&date)
+        } else {
+            log::error!("無法讀取假日資料");
+            false
+        }
+    }
+
+    /// 檢查指定日期是否為週末（週六或週日）
+    pub fn is_weekend(&self, date: NaiveDate) -> bool {
+        let weekday = date.weekday();
+        matches!(weekday, chrono::Weekday::Sat | chrono::Weekday::Sun)
+    }
+
+    /// 檢查指定日期是否為工作日（非週末且非假日）
+    pub fn is_workday(&self, date: NaiveDate) -> bool {
+        !self.is_weekend(date) && !self.is_holiday(date)
+    }
+
+    /// 獲取假日總數
+    pub fn get_holiday_count(&self) -> usize {
+        self.status().holiday_count
+    }
+}
+
+/// POST /api/admin/calendar/reload：修正假日檔案後重新載入，不需重啟服務
+pub async fn reload_calendar(http_req: actix_web::HttpRequest) -> actix_web::Result<actix_web::HttpResponse> {
+    use crate::ai_tasks::ApiResponse;
+    use actix_web::HttpResponse;
+
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+    let calendar = shared();
+    match calendar.reload() {
+        Ok(status) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!("已重新載入 {} 個假日", status.holiday_count),
+            data: Some(status),
+        })),
+        Err(reason) => Ok(HttpResponse::ServiceUnavailable().json(ApiResponse {
+            success: false,
+            data: Some(calendar.status()),
+            message: format!("假日資料仍無法載入，維持目前資料: {}", reason),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[test]
+    fn test_parse_date() {
+        assert!(CalendarService::parse_date("2025/1/1").is_ok());
+        assert!(CalendarService::parse_date("2025/12/25").is_ok());
+        assert!(CalendarService::parse_date("invalid").is_err());
+    }
+
+    #[test]
+    fn test_is_weekend() {
+        let service = CalendarService::load(CALENDAR_DIR);
+
+        // 2025/11/8 是週六
+        let saturday = NaiveDate::from_ymd_opt(2025, 11, 8).unwrap();
+        assert!(service.is_weekend(saturday));
+
+        // 2025/11/9 是週日
+        let sunday = NaiveDate::from_ymd_opt(2025, 11, 9).unwrap();
+        assert!(service.is_weekend(sunday));
+
+        // 2025/11/10 是週一
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 10).unwrap();
+        assert!(!service.is_weekend(monday));
+    }
+
+    #[test]
+    fn test_missing_data_degrades_to_weekend_only_and_reloads() {
+        let dir = std::env::temp_dir().join(format!("lifeup_calendar_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("115.csv");
+        fs::write(&csv, "Subject,Start Date\n中華民國開國紀念日,2026/1/1\n").unwrap();
+        let healthy = CalendarService::load(&dir);
+        assert_eq!(healthy.status().mode, "full");
+
+        // 假日檔案被刪除後啟動：不 panic，只判斷週末並記錄原因
+        fs::remove_file(&csv).unwrap();
+        let service = CalendarService::load(&dir);
+        let status = service.status();
+        assert_eq!(status.mode, "weekend_only");
+        assert_eq!(status.holiday_count, 0);
+        assert!(status.degraded_reason.unwrap().contains("沒有假日 CSV 檔案"));
+        let new_year = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert!(service.is_workday(new_year));
+        assert!(!service.is_workday(NaiveDate::from_ymd_opt(2026, 1, 3).unwrap()));
+
+        // 目錄整個不存在也一樣
+        let missing = CalendarService::load(dir.join("missing"));
+        assert!(missing.status().degraded_reason.unwrap().contains("無法讀取"));
+
+        // 檔案仍缺少時重新載入失敗，狀態不變；修正後重新載入即恢復（複製出的實例共用資料）
+        let cloned = service.clone();
+        assert!(service.reload().is_err());
+        fs::write(&csv, "Subject,Start Date\n中華民國開國紀念日,2026/1/1\n").unwrap();
+        let status = service.reload().unwrap();
+        assert_eq!(status.mode, "full");
+        assert_eq!(status.holiday_count, 1);
+        assert!(!cloned.is_workday(new_year));
+        assert!(cloned.status().degraded_reason.is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_reload_calendar_requires_admin() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let admin = test_utils::create_user(&app, "calendar-admin").await;
+        let user = test_utils::create_user(&app, "calendar-user").await;
+        test_utils::grant_admin("calendar-admin");
+
+        let reload = |auth: (&'static str, String)| {
+            actix_web::test::TestRequest::post().uri("/api/admin/calendar/reload").insert_header(auth).to_request()
+        };
+        assert_eq!(call_json(&app, reload(user.auth())).await.0, 403);
+        let (status, body) = call_json(&app, reload(admin.auth())).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["mode"], "full");
+        assert!(body["data"]["holiday_count"].as_u64().unwrap() > 0);
+    }
+}
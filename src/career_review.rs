@@ -0,0 +1,689 @@
+// 職業規劃審核佇列：AI 一次產生 20–40 個任務，總有幾個偏離目標
+//
+// 接受職業規劃時（未指定 skip_review）任務先暫存在 career_review_item，主線狀態為 reviewing。
+// 使用者可逐項修改標題、難度與經驗值，並接受或拒絕；提交時在同一個交易中建立父任務與
+// 接受的子任務（未決定的項目視為接受）。拒絕的項目不建立任務，保留拒絕原因供改善提示詞分析。
+// 重複提交回傳第一次提交的結果，不會再建立任務。
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ai_tasks::ApiResponse;
+use crate::career_routes::{CareerPlan, CareerPlanItem};
+use crate::models::GeneratedTask;
+
+/// 審核中的職業主線狀態（提交後改為 active）
+pub const MAINLINE_REVIEWING: &str = "reviewing";
+
+const REVIEW_PENDING: &str = "pending";
+const REVIEW_COMMITTED: &str = "committed";
+
+const ITEM_PENDING: &str = "pending";
+const ITEM_ACCEPTED: &str = "accepted";
+const ITEM_REJECTED: &str = "rejected";
+
+const TITLE_MAX_CHARS: usize = 100;
+const EXPERIENCE_MAX: i32 = 1000;
+
+#[derive(Debug, Deserialize)]
+struct CareerReview {
+    mainline_id: String,
+    user_id: String,
+    selected_career: String,
+    learning_summary: Option<String>,
+    estimated_months: Option<i32>,
+    #[serde(default)]
+    achievements: serde_json::Value,
+    status: String,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+impl CareerReview {
+    fn committed_result(&self) -> serde_json::Value {
+        json_column(&self.result)
+    }
+}
+
+// JSON 欄位讀出時可能是字串或已解析的結構（rbatis 會自動解析看起來像 JSON 的字串）
+fn json_column(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(text) => serde_json::from_str(text).unwrap_or_else(|_| value.clone()),
+        _ => value.clone(),
+    }
+}
+
+/// 審核佇列中的一個任務
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CareerReviewItem {
+    pub id: String,
+    // main / daily / project
+    pub task_group: String,
+    pub item_order: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub difficulty: i32,
+    pub experience: i32,
+    pub status: String,
+    pub rejection_reason: Option<String>,
+    // 提交後建立的任務
+    pub task_id: Option<String>,
+    // AI 產生的完整任務（技能標籤、資源等），提交時套用修改後建立任務
+    #[serde(skip_serializing)]
+    pub payload: serde_json::Value,
+}
+
+impl CareerReviewItem {
+    fn into_plan_item(self) -> std::result::Result<CareerPlanItem, serde_json::Error> {
+        let mut task: GeneratedTask = serde_json::from_value(json_column(&self.payload))?;
+        task.title = self.title;
+        task.difficulty = self.difficulty;
+        let group = match self.task_group.as_str() {
+            "daily" => "daily",
+            "project" => "project",
+            _ => "main",
+        };
+        Ok(CareerPlanItem {
+            task,
+            group,
+            experience: self.experience,
+            milestone: group != "daily",
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateReviewItemRequest {
+    pub title: Option<String>,
+    pub difficulty: Option<i32>,
+    pub experience: Option<i32>,
+    // accepted / rejected / pending
+    pub decision: Option<String>,
+    pub rejection_reason: Option<String>,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// 將產生的任務放進審核佇列（主線已建立，狀態為 reviewing）
+pub async fn stage_review(rb: &RBatis, plan: &CareerPlan, items: &[CareerPlanItem]) -> Result<HttpResponse> {
+    let now = Utc::now().to_rfc3339();
+    let result: std::result::Result<(), Box<dyn std::error::Error>> = async {
+        let tx = rb.acquire_begin().await?;
+        let staged: std::result::Result<(), Box<dyn std::error::Error>> = async {
+            tx.exec(
+                "INSERT INTO career_review (mainline_id, user_id, selected_career, learning_summary, estimated_months, achievements, status, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                vec![
+                    Value::String(plan.mainline_id.clone()),
+                    Value::String(plan.user_id.clone()),
+                    Value::String(plan.selected_career.clone()),
+                    Value::String(plan.learning_summary.clone()),
+                    Value::I32(plan.estimated_months),
+                    Value::String(serde_json::to_string(&plan.achievements)?),
+                    Value::String(REVIEW_PENDING.to_string()),
+                    Value::String(now.clone()),
+                ],
+            )
+            .await?;
+            for (index, item) in items.iter().enumerate() {
+                tx.exec(
+                    "INSERT INTO career_review_item
+                     (id, mainline_id, user_id, task_group, item_order, title, description, difficulty, experience, status, payload, created_at, updated_at)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    vec![
+                        Value::String(Uuid::new_v4().to_string()),
+                        Value::String(plan.mainline_id.clone()),
+                        Value::String(plan.user_id.clone()),
+                        Value::String(item.group.to_string()),
+                        Value::I32(index as i32 + 1),
+                        Value::String(item.task.title.clone()),
+                        Value::String(item.task.description.clone()),
+                        Value::I32(item.task.difficulty),
+                        Value::I32(item.experience),
+                        Value::String(ITEM_PENDING.to_string()),
+                        Value::String(serde_json::to_string(&item.task)?),
+                        Value::String(now.clone()),
+                        Value::String(now.clone()),
+                    ],
+                )
+                .await?;
+            }
+            Ok(())
+        }
+        .await;
+        match staged {
+            Ok(()) => Ok(tx.commit().await?),
+            Err(e) => {
+                let _ = tx.rollback().await;
+                Err(e)
+            }
+        }
+    }
+    .await;
+
+    if let Err(e) = result {
+        log::error!("建立職業規劃審核佇列失敗: {}", e);
+        let _ = rb
+            .exec("DELETE FROM career_mainlines WHERE id = ?", vec![Value::String(plan.mainline_id.clone())])
+            .await;
+        return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, "創建學習主線失敗"));
+    }
+
+    let items = match load_items(rb, &plan.mainline_id).await {
+        Ok(items) => items,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢審核佇列失敗: {}", e))),
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: format!("已產生 {} 個任務，請審核後提交", items.len()),
+        data: Some(serde_json::json!({
+            "mainline_id": plan.mainline_id,
+            "status": MAINLINE_REVIEWING,
+            "items": items,
+        })),
+    }))
+}
+
+/// 刪除主線的審核佇列（重新接受同一份職業規劃時）
+pub async fn discard_review(rb: &RBatis, mainline_id: &str) {
+    for sql in ["DELETE FROM career_review_item WHERE mainline_id = ?", "DELETE FROM career_review WHERE mainline_id = ?"] {
+        if let Err(e) = rb.exec(sql, vec![Value::String(mainline_id.to_string())]).await {
+            log::warn!("刪除職業規劃審核佇列失敗 (mainline_id: {}): {}", mainline_id, e);
+        }
+    }
+}
+
+async fn load_items(rb: &RBatis, mainline_id: &str) -> std::result::Result<Vec<CareerReviewItem>, rbatis::Error> {
+    rb.query_decode(
+        "SELECT id, task_group, item_order, title, description, difficulty, experience, status, rejection_reason, task_id, payload
+         FROM career_review_item WHERE mainline_id = ? ORDER BY item_order",
+        vec![Value::String(mainline_id.to_string())],
+    )
+    .await
+}
+
+// 只有主線擁有者可以審核（擁有權檢查見 ownership::assert_mainline_owner）
+async fn review_queue(
+    http_req: &HttpRequest,
+    rb: &RBatis,
+    mainline_id: &str,
+) -> std::result::Result<CareerReview, HttpResponse> {
+    crate::ownership::assert_mainline_owner(rb, mainline_id, crate::auth::current_user_id(http_req).as_deref())
+        .await
+        .map_err(|e| e.into_response())?;
+    let reviews: Vec<CareerReview> = rb
+        .query_decode(
+            "SELECT mainline_id, user_id, selected_career, learning_summary, estimated_months, achievements, status, result
+             FROM career_review WHERE mainline_id = ?",
+            vec![Value::String(mainline_id.to_string())],
+        )
+        .await
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢審核佇列失敗: {}", e)))?;
+    reviews.into_iter().next().ok_or_else(|| error(StatusCode::NOT_FOUND, "此主線沒有審核佇列"))
+}
+
+/// GET /api/career/mainlines/{id}/review：審核佇列中的任務
+pub async fn get_review(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let review = match review_queue(&http_req, rb.get_ref(), &path.into_inner()).await {
+        Ok(review) => review,
+        Err(response) => return Ok(response),
+    };
+    let items = match load_items(rb.get_ref(), &review.mainline_id).await {
+        Ok(items) => items,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢審核佇列失敗: {}", e))),
+    };
+    let count = |status: &str| items.iter().filter(|item| item.status == status).count();
+    let summary = serde_json::json!({
+        "pending": count(ITEM_PENDING),
+        "accepted": count(ITEM_ACCEPTED),
+        "rejected": count(ITEM_REJECTED),
+    });
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: format!("共 {} 個任務", items.len()),
+        data: Some(serde_json::json!({
+            "mainline_id": review.mainline_id,
+            "selected_career": review.selected_career,
+            "status": review.status,
+            "summary": summary,
+            "items": items,
+            "result": review.committed_result(),
+        })),
+    }))
+}
+
+/// PATCH /api/career/mainlines/{id}/review/{item_id}：修改任務內容或接受 / 拒絕
+///
+/// 只修改難度時經驗值依新難度重新計算；同時指定經驗值時以指定值為準。
+pub async fn update_review_item(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+    body: web::Json<UpdateReviewItemRequest>,
+) -> Result<HttpResponse> {
+    let (mainline_id, item_id) = path.into_inner();
+    let review = match review_queue(&http_req, rb.get_ref(), &mainline_id).await {
+        Ok(review) => review,
+        Err(response) => return Ok(response),
+    };
+    if review.status == REVIEW_COMMITTED {
+        return Ok(error(StatusCode::CONFLICT, "審核已提交，無法再修改"));
+    }
+    let items = match load_items(rb.get_ref(), &mainline_id).await {
+        Ok(items) => items,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢審核佇列失敗: {}", e))),
+    };
+    let Some(mut item) = items.into_iter().find(|item| item.id == item_id) else {
+        return Ok(error(StatusCode::NOT_FOUND, "審核項目不存在"));
+    };
+
+    let UpdateReviewItemRequest { title, difficulty, experience, decision, rejection_reason } = body.into_inner();
+    if let Some(title) = title {
+        let title = title.trim();
+        if title.is_empty() || title.chars().count() > TITLE_MAX_CHARS {
+            return Ok(error(StatusCode::BAD_REQUEST, format!("標題需為 1 到 {} 個字", TITLE_MAX_CHARS)));
+        }
+        item.title = title.to_string();
+    }
+    if let Some(difficulty) = difficulty {
+        if !(1..=5).contains(&difficulty) {
+            return Ok(error(StatusCode::BAD_REQUEST, "難度需介於 1 到 5"));
+        }
+        item.difficulty = difficulty;
+        item.experience = crate::career_routes::career_task_experience(difficulty);
+    }
+    if let Some(experience) = experience {
+        if !(0..=EXPERIENCE_MAX).contains(&experience) {
+            return Ok(error(StatusCode::BAD_REQUEST, format!("經驗值需介於 0 到 {}", EXPERIENCE_MAX)));
+        }
+        item.experience = experience;
+    }
+    if let Some(decision) = decision {
+        if ![ITEM_PENDING, ITEM_ACCEPTED, ITEM_REJECTED].contains(&decision.as_str()) {
+            return Ok(error(StatusCode::BAD_REQUEST, "decision 只能是 accepted、rejected 或 pending"));
+        }
+        item.status = decision;
+    }
+    // 拒絕原因只保留在拒絕的項目上
+    item.rejection_reason = if item.status == ITEM_REJECTED {
+        rejection_reason
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty())
+            .or(item.rejection_reason)
+    } else {
+        None
+    };
+
+    let updated = rb
+        .exec(
+            "UPDATE career_review_item SET title = ?, difficulty = ?, experience = ?, status = ?, rejection_reason = ?, updated_at = ?
+             WHERE id = ? AND mainline_id = ?",
+            vec![
+                Value::String(item.title.clone()),
+                Value::I32(item.difficulty),
+                Value::I32(item.experience),
+                Value::String(item.status.clone()),
+                item.rejection_reason.clone().map(Value::String).unwrap_or(Value::Null),
+                Value::String(Utc::now().to_rfc3339()),
+                Value::String(item.id.clone()),
+                Value::String(mainline_id),
+            ],
+        )
+        .await;
+    if let Err(e) = updated {
+        return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新審核項目失敗: {}", e)));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: "審核項目已更新".to_string(),
+        data: Some(item),
+    }))
+}
+
+/// POST /api/career/mainlines/{id}/review/commit：建立接受的任務並結束審核
+pub async fn commit_review(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<crate::ai_service::SharedAIService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let review = match review_queue(&http_req, rb.get_ref(), &path.into_inner()).await {
+        Ok(review) => review,
+        Err(response) => return Ok(response),
+    };
+    if review.status == REVIEW_COMMITTED {
+        return Ok(already_committed(&review));
+    }
+    let items = match load_items(rb.get_ref(), &review.mainline_id).await {
+        Ok(items) => items,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢審核佇列失敗: {}", e))),
+    };
+    let rejected = items.iter().filter(|item| item.status == ITEM_REJECTED).count();
+    let (accepted_ids, plan_items): (Vec<String>, Vec<CareerPlanItem>) = match items
+        .into_iter()
+        .filter(|item| item.status != ITEM_REJECTED)
+        .map(|item| {
+            let id = item.id.clone();
+            item.into_plan_item().map(|plan_item| (id, plan_item))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()
+    {
+        Ok(accepted) => accepted.into_iter().unzip(),
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("審核項目資料損毀: {}", e))),
+    };
+    if plan_items.is_empty() {
+        return Ok(error(StatusCode::BAD_REQUEST, "所有任務都已拒絕，請至少接受一個任務"));
+    }
+
+    let plan = CareerPlan {
+        user_id: review.user_id.clone(),
+        mainline_id: review.mainline_id.clone(),
+        selected_career: review.selected_career.clone(),
+        learning_summary: review.learning_summary.clone().unwrap_or_default(),
+        estimated_months: review.estimated_months.unwrap_or(6),
+        achievements: match json_column(&review.achievements) {
+            serde_json::Value::Array(achievements) => achievements,
+            _ => Vec::new(),
+        },
+    };
+
+    let tx = match rb.acquire_begin().await {
+        Ok(tx) => tx,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("提交審核失敗: {}", e))),
+    };
+    let result: std::result::Result<Option<serde_json::Value>, Box<dyn std::error::Error>> = async {
+        let now = Utc::now().to_rfc3339();
+        // 以狀態條件搶下提交，同時送出的重複提交只有一個會建立任務
+        let claimed = tx
+            .exec(
+                "UPDATE career_review SET status = ?, committed_at = ? WHERE mainline_id = ? AND status = ?",
+                vec![
+                    Value::String(REVIEW_COMMITTED.to_string()),
+                    Value::String(now.clone()),
+                    Value::String(plan.mainline_id.clone()),
+                    Value::String(REVIEW_PENDING.to_string()),
+                ],
+            )
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Ok(None);
+        }
+
+        let materialized = crate::career_routes::materialize_career_plan(&tx, &plan, &plan_items).await?;
+        for (item_id, task) in accepted_ids.iter().zip(&materialized.created_tasks) {
+            tx.exec(
+                "UPDATE career_review_item SET status = ?, task_id = ?, updated_at = ? WHERE id = ?",
+                vec![
+                    Value::String(ITEM_ACCEPTED.to_string()),
+                    task.id.clone().map(Value::String).unwrap_or(Value::Null),
+                    Value::String(now.clone()),
+                    Value::String(item_id.clone()),
+                ],
+            )
+            .await?;
+        }
+        tx.exec(
+            "UPDATE career_mainlines SET status = 'active', total_tasks_generated = ?, updated_at = ? WHERE id = ?",
+            vec![
+                Value::I32(materialized.created_tasks.len() as i32),
+                Value::String(now),
+                Value::String(plan.mainline_id.clone()),
+            ],
+        )
+        .await?;
+
+        let mut data = materialized.response_data(&plan);
+        data["rejected"] = serde_json::json!(rejected);
+        tx.exec(
+            "UPDATE career_review SET result = ? WHERE mainline_id = ?",
+            vec![Value::String(data.to_string()), Value::String(plan.mainline_id.clone())],
+        )
+        .await?;
+        Ok(Some(data))
+    }
+    .await;
+
+    match result {
+        Ok(Some(data)) => {
+            if let Err(e) = tx.commit().await {
+                return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("提交審核失敗: {}", e)));
+            }
+            crate::career_routes::after_career_plan_materialized(rb.get_ref(), ai.get_ref(), &plan).await;
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: format!(
+                    "🎉 成功創建職業主線「{}」，包含 {} 個子任務（拒絕 {} 個）",
+                    plan.selected_career, plan_items.len(), rejected
+                ),
+                data: Some(data),
+            }))
+        }
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            match review_queue(&http_req, rb.get_ref(), &plan.mainline_id).await {
+                Ok(review) => Ok(already_committed(&review)),
+                Err(response) => Ok(response),
+            }
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            log::error!("提交職業規劃審核失敗 (mainline_id: {}): {}", plan.mainline_id, e);
+            Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("提交審核失敗: {}", e)))
+        }
+    }
+}
+
+// 重複提交：回傳第一次提交的結果
+fn already_committed(review: &CareerReview) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: "審核已提交".to_string(),
+        data: Some(review.committed_result()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    async fn insert_quiz_result(rb: &rbatis::RBatis, id: &str, user_id: &str) {
+        rb.exec(
+            "INSERT INTO quiz_results (id, user_id, values_results, interests_results, talents_results, workstyle_results, completed_at)
+             VALUES (?, ?, '{}', '{}', '{}', '{}', datetime('now'))",
+            vec![rbs::Value::String(id.to_string()), rbs::Value::String(user_id.to_string())],
+        )
+        .await
+        .unwrap();
+    }
+
+    fn generated_task(title: &str, difficulty: i32) -> serde_json::Value {
+        json!({
+            "title": title,
+            "description": format!("{}的說明", title),
+            "difficulty": difficulty,
+            "estimated_hours": 2,
+            "skill_tags": [{"name": "Rust", "category": "technical"}],
+            "resources": [],
+            "personality_match": null,
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_review_queue_edits_and_commits_once() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "career-reviewer").await;
+        let other = test_utils::create_user(&app, "career-stranger").await;
+        insert_quiz_result(&rb, "quiz-1", &user.id).await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/career/accept-tasks")
+            .insert_header(user.auth())
+            .set_json(json!({
+                "quiz_result_id": "quiz-1",
+                "selected_career": "後端工程師",
+                "user_id": user.id,
+                "learning_summary": "從基礎開始",
+                "estimated_months": 3,
+                "main_tasks": [generated_task("學習所有權", 3), generated_task("寫一個區塊鏈", 5)],
+                "daily_tasks": [generated_task("每日刷題", 1)],
+                "project_tasks": [],
+                "achievements": {"achievements": []},
+            }))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["status"], "reviewing");
+        let mainline_id = body["data"]["mainline_id"].as_str().unwrap().to_string();
+        let items = body["data"]["items"].as_array().unwrap().clone();
+        assert_eq!(items.len(), 3);
+        assert!(items[0].get("payload").is_none());
+
+        let task_count = |rb: rbatis::RBatis, mainline_id: String| async move {
+            let rows: Vec<serde_json::Value> = rb
+                .query_decode(
+                    "SELECT id FROM task WHERE career_mainline_id = ?",
+                    vec![rbs::Value::String(mainline_id)],
+                )
+                .await
+                .unwrap();
+            rows.len()
+        };
+        assert_eq!(task_count(rb.clone(), mainline_id.clone()).await, 0);
+
+        // 其他使用者不能查看審核佇列
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/career/mainlines/{}/review", mainline_id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        // 修改第一項、拒絕第二項，第三項未決定
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/career/mainlines/{}/review/{}", mainline_id, items[0]["id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .set_json(json!({"title": "學習所有權與借用", "difficulty": 4, "decision": "accepted"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["experience"], 50);
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/career/mainlines/{}/review/{}", mainline_id, items[1]["id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .set_json(json!({"decision": "rejected", "rejection_reason": "與目標無關"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 200);
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/career/mainlines/{}/review/{}", mainline_id, items[2]["id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .set_json(json!({"difficulty": 9}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 400);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/career/mainlines/{}/review", mainline_id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["summary"], json!({"pending": 1, "accepted": 1, "rejected": 1}));
+
+        let commit = || {
+            actix_web::test::TestRequest::post()
+                .uri(&format!("/api/career/mainlines/{}/review/commit", mainline_id))
+                .insert_header(user.auth())
+                .to_request()
+        };
+        let (status, first) = call_json(&app, commit()).await;
+        assert_eq!(status, 200, "{}", first);
+        assert_eq!(first["data"]["subtasks_created"], 2);
+        assert_eq!(first["data"]["rejected"], 1);
+        let titles: Vec<&str> = first["data"]["subtasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|task| task["title"].as_str())
+            .collect();
+        assert_eq!(titles, vec!["學習所有權與借用", "每日刷題"]);
+        assert_eq!(first["data"]["subtasks"][0]["experience"], 50);
+        // 父任務 + 兩個子任務
+        assert_eq!(task_count(rb.clone(), mainline_id.clone()).await, 3);
+
+        // 重複提交回傳同樣結果，不再建立任務
+        let (status, second) = call_json(&app, commit()).await;
+        assert_eq!(status, 200, "{}", second);
+        assert_eq!(second["data"]["parent_task_id"], first["data"]["parent_task_id"]);
+        assert_eq!(task_count(rb.clone(), mainline_id.clone()).await, 3);
+
+        // 拒絕原因保留供分析，主線改為進行中
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT rejection_reason FROM career_review_item WHERE mainline_id = ? AND status = 'rejected'",
+                vec![rbs::Value::String(mainline_id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["rejection_reason"], "與目標無關");
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT status FROM career_mainlines WHERE id = ?", vec![rbs::Value::String(mainline_id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["status"], "active");
+
+        // 提交後不能再修改
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/career/mainlines/{}/review/{}", mainline_id, items[2]["id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .set_json(json!({"decision": "rejected"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 409);
+    }
+
+    #[actix_web::test]
+    async fn test_skip_review_creates_tasks_immediately() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "career-skipper").await;
+        insert_quiz_result(&rb, "quiz-2", &user.id).await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/career/accept-tasks")
+            .insert_header(user.auth())
+            .set_json(json!({
+                "quiz_result_id": "quiz-2",
+                "selected_career": "資料分析師",
+                "user_id": user.id,
+                "skip_review": true,
+                "main_tasks": [generated_task("學習 SQL", 2)],
+                "daily_tasks": [generated_task("每日閱讀", 1)],
+                "achievements": {"achievements": [{"name": "初心者", "description": "完成第一步", "icon": "🌱"}]},
+            }))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["subtasks_created"], 2);
+        assert_eq!(body["data"]["achievements_created"], 1);
+        assert_eq!(body["data"]["subtasks"][0]["requires_confirmation"], 1);
+        assert_eq!(body["data"]["subtasks"][1]["requires_confirmation"], 0);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/career/mainlines/{}/review", body["data"]["mainline_id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 404);
+    }
+}
@@ -1,4 +1,5 @@
 use actix_web::{web, HttpResponse, Result};
+use rbatis::executor::Executor;
 use rbatis::RBatis;
 use uuid::Uuid;
 use chrono::Utc;
@@ -318,6 +319,7 @@ pub async fn generate_career_tasks(
 // 新增：接受並保存職業任務的 API
 pub async fn accept_career_tasks(
     rb: web::Data<RBatis>,
+    ai: web::Data<crate::ai_service::SharedAIService>,
     request: web::Json<serde_json::Value>
 ) -> Result<HttpResponse> {
     log::info!("用戶接受職業任務，開始保存到資料庫");
@@ -332,6 +334,8 @@ pub async fn accept_career_tasks(
 
     let learning_summary = request["learning_summary"].as_str().unwrap_or_default().to_string();
     let estimated_months = request["estimated_months"].as_i64().unwrap_or(6) as i32;
+    // 預設先進入審核佇列；skip_review 時沿用直接建立所有任務的流程
+    let skip_review = request["skip_review"].as_bool().unwrap_or(false);
 
     let main_tasks: Vec<GeneratedTask> = serde_json::from_value(request["main_tasks"].clone()).unwrap_or_default();
     let daily_tasks: Vec<GeneratedTask> = serde_json::from_value(request["daily_tasks"].clone()).unwrap_or_default();
@@ -346,7 +350,19 @@ pub async fn accept_career_tasks(
 
     log::info!("📊 解析到 {} 個AI生成的成就", achievements_data.len());
 
-    let total_tasks = main_tasks.len() + daily_tasks.len() + project_tasks.len();
+    // 統一創建所有子任務為同一類型，確保循序漸進的學習體驗；主要任務與項目任務為階段任務
+    let items: Vec<CareerPlanItem> = [(main_tasks, "main", true), (daily_tasks, "daily", false), (project_tasks, "project", true)]
+        .into_iter()
+        .flat_map(|(tasks, group, milestone)| {
+            tasks.into_iter().map(move |task| CareerPlanItem {
+                experience: career_task_experience(task.difficulty),
+                task,
+                group,
+                milestone,
+            })
+        })
+        .collect();
+    let total_tasks = items.len();
 
     // 檢查是否已經為此測驗結果和職業生成過任務 - 如果有則先刪除
     let existing_check = rb.query_decode::<Vec<CareerMainlines>>(
@@ -363,6 +379,8 @@ pub async fn accept_career_tasks(
                 log::info!("刪除舊的職業主線任務: {}", old_id);
                 // 刪除關聯的任務
                 let _ = rb.exec("DELETE FROM task WHERE career_mainline_id = ?", vec![rbs::to_value!(old_id.clone())]).await;
+                // 刪除尚未完成的審核佇列
+                crate::career_review::discard_review(rb.get_ref(), old_id).await;
                 // 刪除職業主線記錄
                 let _ = rb.exec("DELETE FROM career_mainlines WHERE id = ?", vec![rbs::to_value!(old_id.clone())]).await;
             }
@@ -380,7 +398,7 @@ pub async fn accept_career_tasks(
         survey_answers: Some(serde_json::to_string(&survey_answers)?),
         total_tasks_generated: Some(total_tasks as i32),
         estimated_completion_months: Some(estimated_months),
-        status: Some("active".to_string()),
+        status: Some(if skip_review { "active" } else { crate::career_review::MAINLINE_REVIEWING }.to_string()),
         progress_percentage: Some(0.0),
         created_at: Some(Utc::now()),
         updated_at: Some(Utc::now()),
@@ -395,6 +413,124 @@ pub async fn accept_career_tasks(
         }));
     }
 
+    let plan = CareerPlan {
+        user_id,
+        mainline_id,
+        selected_career,
+        learning_summary,
+        estimated_months,
+        achievements: achievements_data,
+    };
+
+    if !skip_review {
+        return crate::career_review::stage_review(rb.get_ref(), &plan, &items).await;
+    }
+
+    // 6-8. 在同一個交易中建立父任務、子任務、成就與聊天記錄
+    let result = async {
+        let tx = rb.acquire_begin().await?;
+        match materialize_career_plan(&tx, &plan, &items).await {
+            Ok(materialized) => {
+                tx.commit().await?;
+                Ok(materialized)
+            }
+            Err(e) => {
+                let _ = tx.rollback().await;
+                Err(e)
+            }
+        }
+    }
+    .await;
+    let materialized = match result {
+        Ok(materialized) => materialized,
+        Err(e) => {
+            log::error!("創建職業主線任務失敗: {}", e);
+            let _ = rb.exec("DELETE FROM career_mainlines WHERE id = ?", vec![Value::String(plan.mainline_id.clone())]).await;
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "創建職業主線失敗".to_string(),
+            }));
+        }
+    };
+    after_career_plan_materialized(rb.get_ref(), ai.get_ref(), &plan).await;
+
+    // 9. 返回成功回應
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: format!("🎉 成功創建職業主線「{}」，包含 {} 個子任務！", plan.selected_career, materialized.created_tasks.len()),
+        data: Some(materialized.response_data(&plan)),
+    }))
+}
+
+/// 已接受的職業規劃：主線資料與 AI 生成的成就
+pub struct CareerPlan {
+    pub user_id: String,
+    pub mainline_id: String,
+    pub selected_career: String,
+    pub learning_summary: String,
+    pub estimated_months: i32,
+    pub achievements: Vec<serde_json::Value>,
+}
+
+/// 要建立為子任務的職業規劃項目
+pub struct CareerPlanItem {
+    pub task: GeneratedTask,
+    // main / daily / project
+    pub group: &'static str,
+    pub experience: i32,
+    // 階段任務（主要任務、項目任務）完成時需要二次確認
+    pub milestone: bool,
+}
+
+/// 建立完成的父任務、子任務與成就數
+pub struct MaterializedCareerPlan {
+    pub parent_task_id: String,
+    pub created_tasks: Vec<Task>,
+    pub saved_achievements: usize,
+}
+
+impl MaterializedCareerPlan {
+    pub fn response_data(&self, plan: &CareerPlan) -> serde_json::Value {
+        serde_json::json!({
+            "mainline_id": plan.mainline_id,
+            "parent_task_id": self.parent_task_id,
+            "parent_task": {
+                "id": self.parent_task_id,
+                "title": format!("職業主線：{}", plan.selected_career),
+                "description": format!("{}\n\n📋 包含 {} 個子任務，完成後將掌握相關職業技能。",
+                                     plan.learning_summary, self.created_tasks.len()),
+                "subtasks_count": self.created_tasks.len()
+            },
+            "subtasks_created": self.created_tasks.len(),
+            "achievements_created": self.saved_achievements,
+            "learning_summary": plan.learning_summary,
+            "estimated_months": plan.estimated_months,
+            "subtasks": self.created_tasks
+        })
+    }
+}
+
+/// 依難度計算職業子任務的預設經驗值
+pub fn career_task_experience(difficulty: i32) -> i32 {
+    match difficulty {
+        1 => 15,
+        2 => 25,
+        3 => 35,
+        4 => 50,
+        5 => 75,
+        _ => 25,
+    }
+}
+
+/// 建立職業主線的父任務、子任務、成就與聊天記錄；任一筆失敗即回傳錯誤，由呼叫端回滾交易
+pub async fn materialize_career_plan(
+    executor: &dyn Executor,
+    plan: &CareerPlan,
+    items: &[CareerPlanItem],
+) -> Result<MaterializedCareerPlan, Box<dyn std::error::Error>> {
+    let CareerPlan { user_id, mainline_id, selected_career, learning_summary, estimated_months, .. } = plan;
+
     // 6. 建立職業主線父任務
     let parent_task_id = Uuid::new_v4().to_string();
     let now = Utc::now();
@@ -405,7 +541,7 @@ pub async fn accept_career_tasks(
         title: Some(format!("職業主線：{}", selected_career)),
         description: Some(format!("{}\n\n📋 包含 {} 個子任務，完成後將掌握相關職業技能。\n\n🎯 預計學習時程：{} 個月",
                                 learning_summary,
-                                total_tasks,
+                                items.len(),
                                 estimated_months)),
         status: Some(0), // pending
         priority: Some(2), // 高優先級
@@ -432,22 +568,10 @@ pub async fn accept_career_tasks(
         last_cancelled_at: None,
         skill_tags: {
             // 聚合所有子任務的技能標籤（只取名稱）
-            let mut all_skills: std::collections::HashSet<String> = std::collections::HashSet::new();
-            for task in &main_tasks {
-                for skill in &task.skill_tags {
-                    all_skills.insert(skill.name.clone());
-                }
-            }
-            for task in &daily_tasks {
-                for skill in &task.skill_tags {
-                    all_skills.insert(skill.name.clone());
-                }
-            }
-            for task in &project_tasks {
-                for skill in &task.skill_tags {
-                    all_skills.insert(skill.name.clone());
-                }
-            }
+            let all_skills: std::collections::HashSet<String> = items
+                .iter()
+                .flat_map(|item| item.task.skill_tags.iter().map(|skill| skill.name.clone()))
+                .collect();
             if all_skills.is_empty() {
                 None
             } else {
@@ -455,77 +579,55 @@ pub async fn accept_career_tasks(
             }
         },
         attributes: None,
+        completion_mode: None,
+        version: Some(0),
+        require_proof: Some(0),
+        stake: None,
+        retro_completed: Some(0),
+        requires_confirmation: Some(0),
+        challenge_fail_penalty: None,
     };
 
     // 保存父任務
-    if let Err(e) = Task::insert(rb.get_ref(), &parent_task).await {
-        log::error!("創建職業主線父任務失敗: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            message: "創建職業主線失敗".to_string(),
-        }));
-    }
-
+    Task::insert(executor, &parent_task).await?;
     log::info!("✅ 創建職業主線父任務: {}", parent_task_id);
 
     // 7. 將生成的任務插入資料庫作為子任務
     let mut created_tasks = Vec::new();
-    let mut task_order = 1;
-
-    // 統一創建所有子任務為同一類型，確保循序漸進的學習體驗
-
-    // 創建主要任務（作為子任務）
-    for ai_task in &main_tasks {
-        match create_subtask_from_ai_data(&rb, &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order).await {
-            Ok(task) => {
-                created_tasks.push(task);
-                task_order += 1;
-            }
-            Err(e) => log::error!("創建學習子任務失敗: {}", e),
-        }
-    }
-
-    // 創建每日任務（作為子任務）
-    for ai_task in &daily_tasks {
-        match create_subtask_from_ai_data(&rb, &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order).await {
-            Ok(task) => {
-                created_tasks.push(task);
-                task_order += 1;
-            }
-            Err(e) => log::error!("創建學習子任務失敗: {}", e),
-        }
-    }
-
-    // 創建項目任務（作為子任務）
-    for ai_task in &project_tasks {
-        match create_subtask_from_ai_data(&rb, &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order).await {
-            Ok(task) => {
-                created_tasks.push(task);
-                task_order += 1;
-            }
-            Err(e) => log::error!("創建學習子任務失敗: {}", e),
-        }
+    for (index, item) in items.iter().enumerate() {
+        let task = create_subtask_from_ai_data(
+            executor,
+            user_id,
+            mainline_id,
+            &parent_task_id,
+            &item.task,
+            "career_subtask",
+            index as i32 + 1,
+            item.experience,
+            item.milestone,
+        )
+        .await?;
+        created_tasks.push(task);
     }
 
     log::info!("✅ 成功創建 {} 個任務", created_tasks.len());
 
     // 更新父任務的經驗值為所有子任務經驗值總和
-    if let Err(e) = crate::routes::update_parent_task_experience(rb.get_ref(), &parent_task_id).await {
+    if let Err(e) = crate::services::task_hierarchy::update_parent_task_experience(executor, &parent_task_id).await {
         log::warn!("更新父任務經驗值時發生錯誤: {}", e);
     }
 
     // 7. 保存AI生成的成就到資料庫
     let mut saved_achievements = 0;
-    for ach_data in &achievements_data {
+    for ach_data in &plan.achievements {
         if let (Some(name), Some(description), Some(icon)) = (
             ach_data.get("name").and_then(|v| v.as_str()),
             ach_data.get("description").and_then(|v| v.as_str()),
             ach_data.get("icon").and_then(|v| v.as_str()),
         ) {
-            let experience_reward = ach_data.get("experience_reward")
-                .and_then(|v| v.as_i64())
-                .unwrap_or(50) as i32;
+            let experience_reward = crate::reward_config::career_achievement_xp(
+                ach_data.get("experience_reward").and_then(|v| v.as_i64()).map(|xp| xp as i32),
+            );
 
             let category = ach_data.get("category")
                 .and_then(|v| v.as_str())
@@ -557,13 +659,9 @@ pub async fn accept_career_tasks(
                 created_at: Some(Utc::now()),
             };
 
-            match crate::models::Achievement::insert(rb.get_ref(), &achievement).await {
-                Ok(_) => {
-                    saved_achievements += 1;
-                    log::info!("✅ 保存成就: {}", name);
-                }
-                Err(e) => log::error!("❌ 保存成就失敗: {} - {}", name, e),
-            }
+            crate::models::Achievement::insert(executor, &achievement).await?;
+            saved_achievements += 1;
+            log::info!("✅ 保存成就: {}", name);
         }
     }
 
@@ -578,34 +676,21 @@ pub async fn accept_career_tasks(
                              selected_career,
                              created_tasks.len(),
                              learning_summary)),
+        source: Some(crate::models::CHAT_SOURCE_BACKEND.to_string()),
+        expert_name: None,
+        attachment_id: None,
         created_at: Some(Utc::now()),
     };
+    ChatMessage::insert(executor, &chat_message).await?;
 
-    if let Err(e) = ChatMessage::insert(rb.get_ref(), &chat_message).await {
-        log::warn!("保存聊天記錄失敗: {}", e);
-    }
+    Ok(MaterializedCareerPlan { parent_task_id, created_tasks, saved_achievements })
+}
 
-    // 9. 返回成功回應
-    Ok(HttpResponse::Ok().json(ApiResponse {
-        success: true,
-        data: Some(serde_json::json!({
-            "mainline_id": mainline_id,
-            "parent_task_id": parent_task_id,
-            "parent_task": {
-                "id": parent_task_id,
-                "title": format!("職業主線：{}", selected_career),
-                "description": format!("{}\n\n📋 包含 {} 個子任務，完成後將掌握相關職業技能。",
-                                     learning_summary, total_tasks),
-                "subtasks_count": created_tasks.len()
-            },
-            "subtasks_created": created_tasks.len(),
-            "achievements_created": saved_achievements,
-            "learning_summary": learning_summary,
-            "estimated_months": estimated_months,
-            "subtasks": created_tasks
-        })),
-        message: format!("🎉 成功創建職業主線「{}」，包含 {} 個子任務！", selected_career, created_tasks.len()),
-    }))
+/// 交易提交後的後續處理：前端未附帶成就時，為整條主線批次生成（單次 AI 呼叫）
+pub async fn after_career_plan_materialized(rb: &RBatis, ai: &crate::ai_service::SharedAIService, plan: &CareerPlan) {
+    if plan.achievements.is_empty() {
+        crate::ai_tasks_achievement::spawn_generate_achievements_for_mainline(rb.clone(), ai.clone(), plan.mainline_id.clone());
+    }
 }
 
 // ============= 輔助函數 =============
@@ -859,8 +944,8 @@ pub fn build_career_task_prompt(
 - **每個任務都必須有 estimated_hours 欄位**，用於計算經驗值
 
 **語言要求：**
-- **必須使用繁體中文**，絕對不可以出現任何簡體字
-- 所有內容包括：title、description、skill_tags、resources 等都必須是繁體中文
+- **{language_directive}**
+- 所有內容包括：title、description、skill_tags、resources 等都必須是{language}
 - JSON 結構必須完全符合格式要求**
 "#, 
         career = selected_career,
@@ -872,7 +957,9 @@ pub fn build_career_task_prompt(
         available_time = survey_answers.available_time,
         learning_styles = survey_answers.learning_styles.join("、"),
         timeline = survey_answers.timeline,
-        motivation = survey_answers.motivation.as_ref().unwrap_or(&"提升個人能力".to_string())
+        motivation = survey_answers.motivation.as_ref().unwrap_or(&"提升個人能力".to_string()),
+        language = crate::language::current().name(),
+        language_directive = crate::language::directive(),
     )
 }
 
@@ -1088,27 +1175,21 @@ pub fn parse_ai_tasks_response(ai_response: &str) -> Result<GeneratedTasksRespon
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn create_subtask_from_ai_data(
-    rb: &RBatis,
+    rb: &dyn Executor,
     user_id: &str,
     mainline_id: &str,
     parent_task_id: &str,
     ai_task: &GeneratedTask,
     task_category: &str,
     task_order: i32,
+    experience: i32,
+    // 階段任務（主要任務、項目任務）完成時需要二次確認
+    milestone: bool,
 ) -> Result<Task, Box<dyn std::error::Error>> {
     let task_id = Uuid::new_v4().to_string();
     let now = Utc::now();
-    
-    // 計算經驗值
-    let experience = match ai_task.difficulty {
-        1 => 15,
-        2 => 25,
-        3 => 35,
-        4 => 50,
-        5 => 75,
-        _ => 25,
-    };
 
     let task = Task {
         id: Some(task_id),
@@ -1144,6 +1225,13 @@ async fn create_subtask_from_ai_data(
         cancel_count: Some(0),
         last_cancelled_at: None,
         attributes: ai_task.attributes.clone(),
+        completion_mode: None,
+        version: Some(0),
+        require_proof: Some(0),
+        stake: None,
+        retro_completed: Some(0),
+        requires_confirmation: Some(milestone as i32),
+        challenge_fail_penalty: None,
     };
 
     // 在保存任務之前，先確保所有技能標籤都存在於技能表中
@@ -1180,6 +1268,7 @@ pub struct ImportCareerTasksRequest {
 
 pub async fn import_career_tasks(
     rb: web::Data<RBatis>,
+    ai: web::Data<crate::ai_service::SharedAIService>,
     req: web::Json<ImportCareerTasksRequest>
 ) -> Result<HttpResponse> {
     // 1) 解析 JSON
@@ -1314,6 +1403,13 @@ pub async fn import_career_tasks(
             if all.is_empty() { None } else { Some(all.into_iter().collect()) }
         },
         attributes: None,
+        completion_mode: None,
+        version: Some(0),
+        require_proof: Some(0),
+        stake: None,
+        retro_completed: Some(0),
+        requires_confirmation: Some(0),
+        challenge_fail_penalty: None,
     };
     if let Err(e) = Task::insert(rb.get_ref(), &parent_task).await {
         log::error!("創建父任務失敗: {}", e);
@@ -1328,26 +1424,29 @@ pub async fn import_career_tasks(
     let mut created_tasks = Vec::new();
     let mut task_order = 1;
     for ai_task in &generated_tasks.main_tasks {
-        if let Ok(task) = create_subtask_from_ai_data(&rb, &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order).await {
+        if let Ok(task) = create_subtask_from_ai_data(rb.get_ref(), &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order, career_task_experience(ai_task.difficulty), true).await {
             created_tasks.push(task);
             task_order += 1;
         }
     }
     for ai_task in &generated_tasks.daily_tasks {
-        if let Ok(task) = create_subtask_from_ai_data(&rb, &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order).await {
+        if let Ok(task) = create_subtask_from_ai_data(rb.get_ref(), &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order, career_task_experience(ai_task.difficulty), false).await {
             created_tasks.push(task);
             task_order += 1;
         }
     }
     for ai_task in &generated_tasks.project_tasks {
-        if let Ok(task) = create_subtask_from_ai_data(&rb, &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order).await {
+        if let Ok(task) = create_subtask_from_ai_data(rb.get_ref(), &user_id, &mainline_id, &parent_task_id, ai_task, "career_subtask", task_order, career_task_experience(ai_task.difficulty), true).await {
             created_tasks.push(task);
             task_order += 1;
         }
     }
 
     // 7) 更新父任務經驗值
-    let _ = crate::routes::update_parent_task_experience(rb.get_ref(), &parent_task_id).await;
+    let _ = crate::services::task_hierarchy::update_parent_task_experience(rb.get_ref(), &parent_task_id).await;
+
+    // 8) 為整條主線批次生成成就（不阻塞回應）
+    crate::ai_tasks_achievement::spawn_generate_achievements_for_mainline(rb.get_ref().clone(), ai.get_ref().clone(), mainline_id.clone());
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -1361,8 +1460,45 @@ pub async fn import_career_tasks(
     }))
 }
 
+// ============= 職業主線成就 =============
+
+// 手動重新為職業主線批次生成成就（同名成就會被略過）
+pub async fn generate_mainline_achievements(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<crate::ai_service::SharedAIService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let mainline_id = path.into_inner();
+
+    // 生成會呼叫付費的 AI 服務，只有主線擁有者可以觸發
+    if let Err(e) = crate::ownership::assert_mainline_owner(rb.get_ref(), &mainline_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        return Ok(e.into_response());
+    }
+
+    match crate::ai_tasks_achievement::generate_achievements_for_mainline(rb.get_ref(), ai.get_ref(), &mainline_id).await {
+        Ok(achievements) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: format!("成功生成 {} 個主線成就", achievements.len()),
+            data: Some(serde_json::json!({
+                "mainline_id": mainline_id,
+                "achievements_created": achievements.len(),
+                "achievements": achievements,
+            })),
+        })),
+        Err(e) => {
+            log::error!("生成職業主線成就失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("生成職業主線成就失敗: {}", e),
+            }))
+        }
+    }
+}
+
 // 輔助函數：確保技能存在於技能表中
-async fn ensure_skills_exist(rb: &RBatis, user_id: &str, skill_tags: &[SkillTag]) -> Result<(), Box<dyn std::error::Error>> {
+async fn ensure_skills_exist(rb: &dyn Executor, user_id: &str, skill_tags: &[SkillTag]) -> Result<(), Box<dyn std::error::Error>> {
     use crate::models::Skill;
 
     log::info!("📊 ensure_skills_exist 被調用，user_id: {}, 技能標籤數: {}", user_id, skill_tags.len());
@@ -1381,7 +1517,8 @@ async fn ensure_skills_exist(rb: &RBatis, user_id: &str, skill_tags: &[SkillTag]
 
         if existing_skills.is_empty() {
             // 技能不存在，創建新技能
-            let skill_category = &skill_tag.category;  // 使用AI提供的分類
+            // 使用AI提供的分類，對應到固定分類
+            let skill_category = crate::skill_categories::SkillCategory::from_legacy(Some(&skill_tag.category)).to_string();
             log::info!("  🆕 技能不存在，準備創建: {} (類型: {})", skill_name, skill_category);
 
             let new_skill = Skill {
@@ -1423,6 +1560,67 @@ async fn ensure_skills_exist(rb: &RBatis, user_id: &str, skill_tags: &[SkillTag]
 }
 
 
+/// 計算職業主線進度百分比：已完成子任務數 / 子任務總數（不含父任務）
+pub async fn compute_mainline_progress(rb: &RBatis, mainline_id: &str) -> Result<f64, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT COUNT(*) as total, SUM(CASE WHEN status IN (?, ?) THEN 1 ELSE 0 END) as completed
+             FROM task WHERE career_mainline_id = ? AND COALESCE(is_parent_task, 0) = 0",
+            vec![
+                Value::I32(crate::models::TaskStatus::Completed.to_i32()),
+                Value::I32(crate::models::TaskStatus::DailyCompleted.to_i32()),
+                Value::String(mainline_id.to_string()),
+            ],
+        )
+        .await?;
+    let (total, completed) = rows
+        .first()
+        .map(|row| {
+            (
+                row.get("total").and_then(|v| v.as_i64()).unwrap_or(0),
+                row.get("completed").and_then(|v| v.as_i64()).unwrap_or(0),
+            )
+        })
+        .unwrap_or((0, 0));
+
+    if total == 0 {
+        return Ok(0.0);
+    }
+    // 保留一位小數
+    Ok((completed as f64 / total as f64 * 1000.0).round() / 10.0)
+}
+
+/// 重新計算並寫回職業主線進度
+pub async fn refresh_mainline_progress(rb: &RBatis, mainline_id: &str) -> Result<f64, rbatis::Error> {
+    let progress = compute_mainline_progress(rb, mainline_id).await?;
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT user_id, selected_career, progress_percentage FROM career_mainlines WHERE id = ?",
+            vec![Value::String(mainline_id.to_string())],
+        )
+        .await?;
+    rb.exec(
+        "UPDATE career_mainlines SET progress_percentage = ?, updated_at = ? WHERE id = ?",
+        vec![
+            Value::F64(progress),
+            Value::String(Utc::now().to_rfc3339()),
+            Value::String(mainline_id.to_string()),
+        ],
+    )
+    .await?;
+
+    // 進度第一次達到 100% 時通知使用者
+    if let Some(row) = rows.first() {
+        let previous = row.get("progress_percentage").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let user_id = row.get("user_id").and_then(|v| v.as_str());
+        let career = row.get("selected_career").and_then(|v| v.as_str()).unwrap_or("職業");
+        if let (Some(user_id), true) = (user_id, previous < 100.0 && progress >= 100.0) {
+            crate::event_notifier::notify_mainline_completed(rb, user_id, mainline_id, career).await;
+        }
+    }
+    Ok(progress)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
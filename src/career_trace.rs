@@ -0,0 +1,256 @@
+// 職業任務生成追蹤：漸進式生成（大綱 → 細節 → 資源 → 成就）每個 AI 步驟各記一筆
+//
+// 記錄 prompt 雜湊、模型、耗時、估算的 token 數與截斷後的輸出（或錯誤），
+// 產出異常時可還原每個步驟收到與產生的內容。AIService 不回傳供應商的實際用量，
+// token 數以字元估算（CJK 一字約一 token，其餘約四字元一 token）。
+// 完整 prompt 含個人資料，只在 CAREER_TRACE_STORE_PROMPTS 開啟時保存。
+
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, Utc};
+use rbatis::RBatis;
+use rbs::Value;
+use sha2::{Digest, Sha256};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::CareerTraceConfig;
+
+static CAREER_TRACE_CONFIG: OnceLock<CareerTraceConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: CareerTraceConfig) {
+    log::info!(
+        "職業任務生成追蹤: 保留 {} 天，輸出上限 {} 字元，{}保存完整 prompt",
+        config.retention_days,
+        config.max_output_chars,
+        if config.store_prompts { "" } else { "不" }
+    );
+    if CAREER_TRACE_CONFIG.set(config).is_err() {
+        log::warn!("職業任務生成追蹤設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static CareerTraceConfig {
+    CAREER_TRACE_CONFIG.get_or_init(CareerTraceConfig::default)
+}
+
+pub fn hash_prompt(prompt: &str) -> String {
+    hex::encode(Sha256::digest(prompt.as_bytes()))
+}
+
+/// 以字元估算 token 數
+pub fn estimate_tokens(text: &str) -> i64 {
+    let (ascii, other) = text.chars().fold((0i64, 0i64), |(ascii, other), c| {
+        if c.is_ascii() { (ascii + 1, other) } else { (ascii, other + 1) }
+    });
+    other + (ascii + 3) / 4
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => format!("{}…（已截斷，共 {} 字元）", &text[..index], text.chars().count()),
+        None => text.to_string(),
+    }
+}
+
+/// 一次生成工作的追蹤；job_id 即為追蹤 ID，會附在錯誤回應中
+pub struct CareerTrace {
+    rb: RBatis,
+    job_id: String,
+    user_id: Option<String>,
+    step_order: i32,
+}
+
+impl CareerTrace {
+    pub fn new(rb: &RBatis, job_id: &str, user_id: Option<&str>) -> Self {
+        CareerTrace {
+            rb: rb.clone(),
+            job_id: job_id.to_string(),
+            user_id: user_id.map(str::to_string),
+            step_order: 0,
+        }
+    }
+
+    /// 執行一個 AI 步驟並記錄結果；記錄失敗只寫日誌，不影響生成流程
+    pub async fn step<F>(&mut self, step: &str, model: &str, prompt: &str, call: F) -> anyhow::Result<String>
+    where
+        F: Future<Output = anyhow::Result<String>>,
+    {
+        let started = Instant::now();
+        let result = call.await;
+        let duration_ms = started.elapsed().as_millis() as i64;
+        self.step_order += 1;
+
+        let (output, error) = match &result {
+            Ok(output) => (Some(output.as_str()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        if let Err(e) = self.insert(step, model, prompt, duration_ms, output, error.as_deref()).await {
+            log::warn!("記錄職業任務生成步驟失敗（{} / {}）: {}", self.job_id, step, e);
+        }
+        result
+    }
+
+    /// 步驟的輸出無法解析時，把錯誤補記在最後一個步驟上
+    pub async fn mark_failed(&self, error: &str) {
+        let result = self
+            .rb
+            .exec(
+                "UPDATE career_generation_trace SET error = ? WHERE job_id = ? AND step_order = ?",
+                vec![
+                    Value::String(error.to_string()),
+                    Value::String(self.job_id.clone()),
+                    Value::I32(self.step_order),
+                ],
+            )
+            .await;
+        if let Err(e) = result {
+            log::warn!("記錄職業任務生成錯誤失敗（{}）: {}", self.job_id, e);
+        }
+    }
+
+    async fn insert(
+        &self,
+        step: &str,
+        model: &str,
+        prompt: &str,
+        duration_ms: i64,
+        output: Option<&str>,
+        error: Option<&str>,
+    ) -> std::result::Result<(), rbatis::Error> {
+        let optional = |text: Option<String>| text.map(Value::String).unwrap_or(Value::Null);
+        self.rb
+            .exec(
+                "INSERT INTO career_generation_trace
+                     (id, job_id, user_id, step, step_order, model, prompt_hash, prompt, prompt_tokens,
+                      output_tokens, duration_ms, output, error, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                vec![
+                    Value::String(uuid::Uuid::new_v4().to_string()),
+                    Value::String(self.job_id.clone()),
+                    optional(self.user_id.clone()),
+                    Value::String(step.to_string()),
+                    Value::I32(self.step_order),
+                    Value::String(model.to_string()),
+                    Value::String(hash_prompt(prompt)),
+                    optional(config().store_prompts.then(|| prompt.to_string())),
+                    Value::I64(estimate_tokens(prompt)),
+                    output.map(|o| Value::I64(estimate_tokens(o))).unwrap_or(Value::Null),
+                    Value::I64(duration_ms),
+                    optional(output.map(|o| truncate_chars(o, config().max_output_chars))),
+                    optional(error.map(str::to_string)),
+                    Value::String(Utc::now().to_rfc3339()),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// 刪除超過保留天數的追蹤紀錄（每晚彙整呼叫），回傳刪除筆數
+pub async fn prune(rb: &RBatis, now: DateTime<Utc>) -> std::result::Result<u64, rbatis::Error> {
+    let cutoff = now - Duration::days(config().retention_days);
+    let result = rb
+        .exec(
+            "DELETE FROM career_generation_trace WHERE created_at < ?",
+            vec![Value::String(cutoff.to_rfc3339())],
+        )
+        .await?;
+    Ok(result.rows_affected)
+}
+
+/// 管理員查看一次生成工作的所有步驟
+pub async fn get_career_trace(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+    let job_id = path.into_inner();
+    let steps: std::result::Result<Vec<serde_json::Value>, _> = rb
+        .query_decode(
+            "SELECT step, step_order, model, prompt_hash, prompt, prompt_tokens, output_tokens, duration_ms,
+                    output, error, user_id, created_at
+             FROM career_generation_trace WHERE job_id = ? ORDER BY step_order",
+            vec![Value::String(job_id.clone())],
+        )
+        .await;
+    match steps {
+        Ok(steps) if steps.is_empty() => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "找不到此生成工作的追蹤紀錄".to_string(),
+        })),
+        Ok(steps) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "job_id": job_id, "steps": steps })),
+            message: "獲取職業任務生成追蹤成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("查詢職業任務生成追蹤失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_steps_are_recorded_and_pruned() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "trace").await;
+
+        let mut trace = CareerTrace::new(&rb, "job-1", Some(&user.id));
+        let output = trace
+            .step("outline", "outline-model", "請為後端工程師規劃", async { Ok("x".repeat(5000)) })
+            .await
+            .unwrap();
+        assert_eq!(output.len(), 5000);
+        trace.mark_failed("大綱 JSON 解析失敗").await;
+        let failed = trace
+            .step("details", "detail-model", "展開細節", async { Err(anyhow::anyhow!("逾時")) })
+            .await;
+        assert!(failed.is_err());
+
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT * FROM career_generation_trace WHERE job_id = 'job-1' ORDER BY step_order",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["prompt_hash"], hash_prompt("請為後端工程師規劃"));
+        // 預設不保存完整 prompt，輸出截斷
+        assert!(rows[0]["prompt"].is_null());
+        assert!(rows[0]["output"].as_str().unwrap().chars().count() < 4100);
+        assert_eq!(rows[0]["output_tokens"], 1250);
+        assert_eq!(rows[0]["error"], "大綱 JSON 解析失敗");
+        assert_eq!(rows[1]["error"], "逾時");
+        assert!(rows[1]["output"].is_null());
+
+        // 一般使用者不能查看
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/admin/career/traces/job-1")
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        assert_eq!(prune(&rb, Utc::now()).await.unwrap(), 0);
+        assert_eq!(prune(&rb, Utc::now() + Duration::days(31)).await.unwrap(), 2);
+    }
+}
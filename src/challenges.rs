@@ -0,0 +1,339 @@
+// 挑戰任務：有結束日期與賭注的限時任務（例如「30 天不喝手搖」）
+//
+// 結束日期過後自動結算：重複性挑戰以每日完成率、一般挑戰以子任務完成比例對照 completion_target，
+// 達標標記為已完成，否則標記為挑戰失敗（Failed）。結果寫入通知中心並推送，
+// 失敗時扣除建立挑戰時記錄的經驗值（經由經驗值流水）。重複性挑戰由 recurring_progress 的期滿結算轉交處理。
+
+use std::time::Duration as StdDuration;
+
+use chrono::{FixedOffset, Utc};
+use rbatis::RBatis;
+use serde::Serialize;
+
+use crate::models::{Task, TaskStatus};
+use crate::task_types::TaskType;
+
+// 與重複性任務相同的預設目標完成率
+const DEFAULT_TARGET_RATE: f64 = 0.8;
+const SWEEP_INTERVAL_SECS: u64 = 3600;
+// 已結算（或不再結算）的狀態
+const SETTLED_STATUSES: [TaskStatus; 4] = [
+    TaskStatus::Completed,
+    TaskStatus::Cancelled,
+    TaskStatus::DailyNotCompleted,
+    TaskStatus::Failed,
+];
+
+/// 挑戰失敗扣除的經驗值：使用建立時記錄的值，沒有記錄的舊挑戰採用目前設定
+pub fn fail_penalty(task: &Task) -> i32 {
+    task.challenge_fail_penalty
+        .unwrap_or_else(|| crate::reward_config::current().challenge_fail_penalty)
+}
+
+/// 是否為挑戰任務本體（挑戰底下的子任務不算）
+pub fn is_challenge(task: &Task) -> bool {
+    TaskType::from_stored(task.task_type.as_deref()) == Some(TaskType::Challenge) && task.parent_task_id.is_none()
+}
+
+fn is_settled(status: Option<i32>) -> bool {
+    SETTLED_STATUSES.iter().any(|s| status == Some(s.to_i32()))
+}
+
+/// 挑戰目前的進度
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeStanding {
+    // secured（已達標）/ in_progress / unreachable（剩餘天數已不可能達標）/ succeeded / failed
+    pub state: &'static str,
+    pub progress_rate: f64,
+    pub target_rate: f64,
+    pub completed: i64,
+    pub total: i64,
+}
+
+/// 結算結果（通知內容）
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeOutcome {
+    pub task_id: String,
+    pub title: String,
+    pub succeeded: bool,
+    pub progress_rate: f64,
+    pub target_rate: f64,
+    pub xp_penalty: i32,
+}
+
+/// 距離結束日期的天數（使用者時區，已過期為 0）
+pub fn days_remaining(task: &Task, tz: FixedOffset) -> Option<i64> {
+    let end = crate::local_date::local_date_in(task.end_date?, tz);
+    Some((end - crate::local_date::local_today_in(tz)).num_days().max(0))
+}
+
+/// 計算挑戰進度：重複性挑戰依每日完成天數，其餘依子任務完成數（沒有子任務時看本身是否完成）
+pub async fn compute_standing(rb: &RBatis, task: &Task) -> Result<ChallengeStanding, rbatis::Error> {
+    let target_rate = task.completion_target.unwrap_or(DEFAULT_TARGET_RATE);
+    let (completed, total, reachable) = if task.is_recurring == Some(1) {
+        let progress = crate::recurring_progress::compute_recurring_progress(rb, task).await?;
+        let best_case = (progress.completed_days + progress.remaining_days) as f64;
+        let reachable = progress.total_days == 0 || best_case / progress.total_days as f64 >= target_rate;
+        (progress.completed_days as i64, progress.total_days as i64, reachable)
+    } else {
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT COUNT(*) AS total, SUM(CASE WHEN status IN (?, ?) THEN 1 ELSE 0 END) AS completed
+                 FROM task WHERE parent_task_id = ?",
+                vec![
+                    rbs::Value::I32(TaskStatus::Completed.to_i32()),
+                    rbs::Value::I32(TaskStatus::DailyCompleted.to_i32()),
+                    rbs::Value::String(task.id.clone().unwrap_or_default()),
+                ],
+            )
+            .await?;
+        let total = rows.first().and_then(|r| r["total"].as_i64()).unwrap_or(0);
+        let completed = rows.first().and_then(|r| r["completed"].as_i64()).unwrap_or(0);
+        if total == 0 {
+            (crate::shared_tasks::is_completed_status(task.status) as i64, 1, true)
+        } else {
+            (completed, total, true)
+        }
+    };
+
+    let progress_rate = if total > 0 { completed as f64 / total as f64 } else { 0.0 };
+    let state = if task.status == Some(TaskStatus::Failed.to_i32()) {
+        "failed"
+    } else if task.status == Some(TaskStatus::Completed.to_i32()) {
+        "succeeded"
+    } else if progress_rate >= target_rate {
+        "secured"
+    } else if !reachable {
+        "unreachable"
+    } else {
+        "in_progress"
+    };
+    Ok(ChallengeStanding { state, progress_rate, target_rate, completed, total })
+}
+
+/// 結算挑戰：以條件更新避免重複結算，失敗時扣除 xp_penalty；回傳是否由這次呼叫完成結算
+pub async fn settle(
+    rb: &RBatis,
+    task: &Task,
+    succeeded: bool,
+    progress_rate: f64,
+    target_rate: f64,
+    xp_penalty: i32,
+) -> Result<bool, rbatis::Error> {
+    let new_status = if succeeded { TaskStatus::Completed } else { TaskStatus::Failed };
+    let mut args = vec![
+        rbs::Value::I32(new_status.to_i32()),
+        rbs::Value::String(Utc::now().to_rfc3339()),
+        rbs::Value::String(task.id.clone().unwrap_or_default()),
+    ];
+    args.extend(SETTLED_STATUSES.iter().map(|s| rbs::Value::I32(s.to_i32())));
+    let result = rb
+        .exec(
+            "UPDATE task SET status = ?, updated_at = ? WHERE id = ? AND status NOT IN (?, ?, ?, ?)",
+            args,
+        )
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(false);
+    }
+
+    log::info!(
+        "挑戰任務 {} 結算: {} ({:.1}% / 目標 {:.1}%)",
+        task.id.as_deref().unwrap_or_default(),
+        if succeeded { "成功" } else { "失敗" },
+        progress_rate * 100.0,
+        target_rate * 100.0
+    );
+
+    let Some(user_id) = task.user_id.clone() else {
+        return Ok(true);
+    };
+    let xp_penalty = if succeeded { 0 } else { xp_penalty.max(0) };
+    if xp_penalty > 0 {
+        if let Err(e) = crate::services::experience::apply_experience_gain(rb, &user_id, -xp_penalty).await {
+            log::error!("扣除挑戰失敗經驗值失敗 ({}): {}", user_id, e);
+        }
+    }
+
+    let outcome = ChallengeOutcome {
+        task_id: task.id.clone().unwrap_or_default(),
+        title: task.title.clone().unwrap_or_default(),
+        succeeded,
+        progress_rate,
+        target_rate,
+        xp_penalty,
+    };
+    crate::event_notifier::notify_challenge_finished(rb, &user_id, &outcome).await;
+
+    if succeeded {
+        crate::achievement_check::schedule(rb, &user_id);
+    }
+    Ok(true)
+}
+
+/// 已過結束日期的挑戰依進度結算；回傳是否完成結算
+pub async fn evaluate(rb: &RBatis, task: &Task) -> Result<bool, rbatis::Error> {
+    if !is_challenge(task) || is_settled(task.status) {
+        return Ok(false);
+    }
+    if !crate::recurring_progress::has_ended(task, crate::recurring_progress::owner_timezone(rb, task).await) {
+        return Ok(false);
+    }
+    let standing = compute_standing(rb, task).await?;
+    let succeeded = standing.progress_rate >= standing.target_rate;
+    settle(rb, task, succeeded, standing.progress_rate, standing.target_rate, fail_penalty(task)).await
+}
+
+/// 重複性挑戰期滿時由 recurring_progress 呼叫
+pub async fn settle_recurring(
+    rb: &RBatis,
+    task: &Task,
+    completion_rate: f64,
+    target_rate: f64,
+) -> Result<bool, rbatis::Error> {
+    settle(rb, task, completion_rate >= target_rate, completion_rate, target_rate, fail_penalty(task)).await
+}
+
+/// 任務列表附上剩餘天數與目前進度
+pub async fn with_standing(rb: &RBatis, tasks: Vec<Task>) -> Vec<serde_json::Value> {
+    let mut items = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let mut item = serde_json::to_value(&task).unwrap_or_default();
+        let standing = match compute_standing(rb, &task).await {
+            Ok(standing) => Some(standing),
+            Err(e) => {
+                log::warn!("計算挑戰進度失敗 ({}): {}", task.id.as_deref().unwrap_or_default(), e);
+                None
+            }
+        };
+        if let Some(obj) = item.as_object_mut() {
+            let tz = crate::recurring_progress::owner_timezone(rb, &task).await;
+            obj.insert("days_remaining".to_string(), serde_json::json!(days_remaining(&task, tz)));
+            obj.insert("standing".to_string(), serde_json::json!(standing));
+        }
+        items.push(item);
+    }
+    items
+}
+
+/// 定期結算已過結束日期的非重複性挑戰（重複性挑戰由重複性任務的期滿結算處理）
+pub fn spawn_sweeper(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(SWEEP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let mut args = vec![rbs::Value::String(TaskType::Challenge.to_string())];
+            args.extend(SETTLED_STATUSES.iter().map(|s| rbs::Value::I32(s.to_i32())));
+            let candidates: Result<Vec<Task>, _> = rb
+                .query_decode(
+                    "SELECT * FROM task WHERE task_type = ? AND parent_task_id IS NULL AND COALESCE(is_recurring, 0) = 0
+                     AND end_date IS NOT NULL AND status NOT IN (?, ?, ?, ?)",
+                    args,
+                )
+                .await;
+            let candidates = match candidates {
+                Ok(tasks) => tasks,
+                Err(e) => {
+                    log::error!("查詢待結算挑戰任務失敗: {}", e);
+                    continue;
+                }
+            };
+            for task in &candidates {
+                if let Err(e) = evaluate(&rb, task).await {
+                    log::error!("結算挑戰任務 {} 失敗: {}", task.id.as_deref().unwrap_or_default(), e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_challenge_requires_end_date_and_settles_as_failed() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "challenger").await;
+        let create = |body: serde_json::Value| {
+            actix_web::test::TestRequest::post().uri("/api/tasks").insert_header(user.auth()).set_json(body).to_request()
+        };
+
+        let (status, _) = call_json(&app, create(json!({"user_id": user.id, "title": "30 天不喝手搖", "task_type": "challenge"}))).await;
+        assert_eq!(status, 400);
+
+        let end_date = (Utc::now() - chrono::Duration::days(2)).to_rfc3339();
+        let (status, body) = call_json(
+            &app,
+            create(json!({
+                "user_id": user.id, "title": "30 天不喝手搖", "task_type": "challenge",
+                "end_date": end_date, "completion_target": 0.8, "stake": "失敗請全組喝飲料"
+            })),
+        )
+        .await;
+        assert_eq!(status, 201);
+        assert_eq!(body["data"]["stake"], "失敗請全組喝飲料");
+        let challenge_id = body["data"]["id"].as_str().unwrap().to_string();
+        for (title, status) in [("第一週", 2), ("第二週", 0)] {
+            let (_, body) = call_json(
+                &app,
+                create(json!({"user_id": user.id, "title": title, "task_type": "challenge", "parent_task_id": challenge_id})),
+            )
+            .await;
+            rb.exec(
+                "UPDATE task SET status = ? WHERE id = ?",
+                vec![rbs::Value::I32(status), rbs::Value::String(body["data"]["id"].as_str().unwrap().to_string())],
+            )
+            .await
+            .unwrap();
+        }
+        rb.exec(
+            "UPDATE user_profile SET experience = 50 WHERE user_id = ?",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        let task: Task = rb
+            .query_decode("SELECT * FROM task WHERE id = ?", vec![rbs::Value::String(challenge_id.clone())])
+            .await
+            .unwrap();
+        let standing = compute_standing(&rb, &task).await.unwrap();
+        assert_eq!((standing.completed, standing.total, standing.state), (1, 2, "in_progress"));
+        assert!(settle(&rb, &task, false, standing.progress_rate, standing.target_rate, 30).await.unwrap());
+        // 已結算的挑戰不會重複結算
+        assert!(!settle(&rb, &task, false, standing.progress_rate, standing.target_rate, 30).await.unwrap());
+
+        let experience: i64 = rb
+            .query_decode(
+                "SELECT experience FROM user_profile WHERE user_id = ?",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(experience, 20);
+        let notifications: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT title FROM notification_history WHERE user_id = ? AND event_type = 'challenge_finished'",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks/type/challenge?user_id={}", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let listed = &body["data"][0];
+        assert_eq!(listed["status"], TaskStatus::Failed.to_i32());
+        assert_eq!(listed["days_remaining"], 0);
+        assert_eq!(listed["standing"]["state"], "failed");
+        assert_eq!(listed["standing"]["progress_rate"], 0.5);
+    }
+}
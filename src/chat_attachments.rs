@@ -0,0 +1,361 @@
+// 聊天附圖：POST /api/chat/personality 附帶的圖片，檔案存放於 {storage_dir}/chat/{user_id}/
+//
+// 看圖模型檢查與大小、類型驗證都在寫入檔案之前完成；使用者訊息的 chat_message.attachment_id
+// 指向這裡的資料列，歷史訊息以 GET /api/chat/attachments/{id} 取得圖片（僅限本人）。
+
+use std::path::{Path, PathBuf};
+
+use actix_web::http::header::ContentDisposition;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use base64::Engine;
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+
+use crate::ai_service::ChatImage;
+use crate::ai_tasks::ApiResponse;
+use crate::models::{ChatAttachment, ChatAttachmentInput, ChatWithPersonalityRequest};
+use crate::task_attachments;
+
+// multipart 標頭、邊界與訊息文字的額外空間
+const REQUEST_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// 請求中尚未驗證的圖片
+#[derive(Debug)]
+pub struct IncomingImage {
+    pub mime_type: String,
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+fn chat_dir(user_id: &str) -> Option<PathBuf> {
+    let base = Path::new(&task_attachments::config().storage_dir).join("chat");
+    task_attachments::user_dir_in(&base, user_id)
+}
+
+fn file_path(attachment: &ChatAttachment) -> Option<PathBuf> {
+    task_attachments::stored_file_in(chat_dir(attachment.user_id.as_deref()?)?, attachment.stored_name.as_deref()?)
+}
+
+pub fn download_url(id: &str) -> String {
+    format!("/api/chat/attachments/{}", id)
+}
+
+/// 聊天請求的 body 上限：base64 會讓圖片增加約 1/3
+pub fn max_request_bytes() -> usize {
+    task_attachments::config().chat_image_max_bytes.div_ceil(3) * 4 + REQUEST_OVERHEAD_BYTES
+}
+
+fn decode_input(input: ChatAttachmentInput) -> std::result::Result<IncomingImage, String> {
+    // data:image/png;base64,... 的 MIME 類型只在未另外指定時使用
+    let (url_mime, encoded) = match input.data.trim().strip_prefix("data:").and_then(|rest| rest.split_once(',')) {
+        Some((meta, encoded)) => (meta.strip_suffix(";base64").map(str::to_string), encoded),
+        None => (None, input.data.trim()),
+    };
+    let mime_type = input
+        .mime_type
+        .filter(|m| !m.trim().is_empty())
+        .or(url_mime)
+        .ok_or("缺少圖片的 mime_type")?;
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| "圖片不是有效的 base64 內容".to_string())?;
+    Ok(IncomingImage {
+        mime_type,
+        filename: input.filename.filter(|f| !f.trim().is_empty()).unwrap_or_else(|| "image".to_string()),
+        data,
+    })
+}
+
+fn parse_multipart_request(
+    content_type: &str,
+    body: &[u8],
+) -> std::result::Result<(ChatWithPersonalityRequest, Option<IncomingImage>), String> {
+    let mut message = None;
+    let mut user_id = None;
+    let mut image = None;
+    for part in task_attachments::parse_multipart_parts(content_type, body)? {
+        match (part.filename, part.name.as_deref()) {
+            (Some(filename), _) if image.is_none() => {
+                image = Some(IncomingImage { mime_type: part.content_type, filename, data: part.data });
+            }
+            (None, Some("message")) => message = Some(String::from_utf8_lossy(&part.data).to_string()),
+            (None, Some("user_id")) => user_id = Some(String::from_utf8_lossy(&part.data).to_string()),
+            _ => {}
+        }
+    }
+    let message = message.ok_or("缺少 message 欄位")?;
+    Ok((ChatWithPersonalityRequest { message, user_id, attachment: None }, image))
+}
+
+/// 解析聊天請求：JSON（圖片以 base64 放在 attachment）或 multipart/form-data（message、user_id 與一個檔案欄位）
+pub fn parse_request(
+    content_type: &str,
+    body: &[u8],
+) -> std::result::Result<(ChatWithPersonalityRequest, Option<IncomingImage>), String> {
+    if content_type.to_lowercase().starts_with("multipart/form-data") {
+        return parse_multipart_request(content_type, body);
+    }
+    let mut req: ChatWithPersonalityRequest =
+        serde_json::from_slice(body).map_err(|e| format!("JSON 解析錯誤: {}", e))?;
+    let image = req.attachment.take().map(decode_input).transpose()?;
+    Ok((req, image))
+}
+
+/// 檢查圖片大小與類型
+pub fn validate(image: IncomingImage) -> std::result::Result<(ChatImage, String), (StatusCode, String)> {
+    let config = task_attachments::config();
+    if image.data.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "圖片內容為空".to_string()));
+    }
+    if image.data.len() > config.chat_image_max_bytes {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("圖片超過大小上限 {} bytes", config.chat_image_max_bytes),
+        ));
+    }
+    let mime_type = image.mime_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+    if !config.chat_image_mime_types.iter().any(|m| m == &mime_type) {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("不支援的圖片類型: {}（允許: {}）", mime_type, config.chat_image_mime_types.join(", ")),
+        ));
+    }
+    Ok((ChatImage { mime_type, data: image.data }, image.filename))
+}
+
+/// 寫入圖片檔案與資料列
+pub async fn save(rb: &RBatis, user_id: &str, image: &ChatImage, filename: &str) -> std::result::Result<ChatAttachment, String> {
+    let dir = chat_dir(user_id).ok_or("無效的使用者 ID")?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let (stored_name, sha256) = task_attachments::write_stored_file(&dir, &id, &image.mime_type, filename, &image.data)
+        .map_err(|e| format!("寫入聊天圖片失敗: {}", e))?;
+    let attachment = ChatAttachment {
+        id: Some(id),
+        user_id: Some(user_id.to_string()),
+        original_name: Some(filename.to_string()),
+        stored_name: Some(stored_name),
+        mime_type: Some(image.mime_type.clone()),
+        size_bytes: Some(image.data.len() as i64),
+        sha256: Some(sha256),
+        created_at: Some(Utc::now()),
+    };
+    if let Err(e) = ChatAttachment::insert(rb, &attachment).await {
+        if let Some(path) = file_path(&attachment) {
+            task_attachments::remove_path(&path);
+        }
+        return Err(format!("儲存聊天圖片紀錄失敗: {}", e));
+    }
+    Ok(attachment)
+}
+
+/// 重置聊天紀錄時刪除使用者的所有聊天圖片
+pub async fn purge_user_chat_attachments(rb: &RBatis, user_id: &str) -> std::result::Result<i32, rbatis::Error> {
+    let result = ChatAttachment::delete_by_map(rb, value!{"user_id": user_id}).await?;
+    if let Some(dir) = chat_dir(user_id) {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("刪除使用者聊天圖片目錄失敗 {:?}: {}", dir, e);
+            }
+        }
+    }
+    Ok(result.rows_affected as i32)
+}
+
+/// 刪除已沒有訊息引用的聊天圖片（資料保留策略清除舊訊息後呼叫）
+pub async fn purge_unreferenced(rb: &RBatis, user_id: &str) -> std::result::Result<u64, rbatis::Error> {
+    let rows: Vec<ChatAttachment> = rb
+        .query_decode(
+            "SELECT * FROM chat_attachment WHERE user_id = ?
+               AND id NOT IN (SELECT attachment_id FROM chat_message WHERE attachment_id IS NOT NULL)",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let mut removed = 0;
+    for attachment in &rows {
+        if let Some(path) = file_path(attachment) {
+            task_attachments::remove_path(&path);
+        }
+        if let Some(id) = attachment.id.as_deref() {
+            removed += ChatAttachment::delete_by_map(rb, value!{"id": id}).await?.rows_affected;
+        }
+    }
+    Ok(removed)
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// GET /api/chat/attachments/{id}：下載聊天圖片（僅限上傳者本人）
+pub async fn download_chat_attachment(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let Some(caller) = crate::auth::current_user_id(&http_req) else {
+        return Ok(error_response(StatusCode::UNAUTHORIZED, "請先登入"));
+    };
+    let attachment = match ChatAttachment::select_by_map(rb.get_ref(), value!{"id": path.into_inner()}).await {
+        Ok(rows) => rows.into_iter().next(),
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢聊天圖片失敗: {}", e))),
+    };
+    let Some(attachment) = attachment else {
+        return Ok(error_response(StatusCode::NOT_FOUND, "圖片不存在"));
+    };
+    if attachment.user_id.as_deref() != Some(caller.as_str()) {
+        return Ok(error_response(StatusCode::FORBIDDEN, "無權存取此圖片"));
+    }
+    let Some(data) = file_path(&attachment).and_then(|p| std::fs::read(p).ok()) else {
+        return Ok(error_response(StatusCode::NOT_FOUND, "圖片檔案不存在"));
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.mime_type.clone().unwrap_or_else(|| "application/octet-stream".to_string()))
+        .insert_header(ContentDisposition::attachment(attachment.original_name.clone().unwrap_or_default()))
+        .body(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::header::CONTENT_TYPE;
+    use serde_json::json;
+
+    use super::*;
+    use crate::ai_service::SharedAIService;
+    use crate::test_utils::mock_ai::MockAIService;
+    use crate::test_utils::{self, call_json};
+
+    const BOUNDARY: &str = "lifeupchatboundary";
+
+    fn chat_request(user: &test_utils::TestUser, body: serde_json::Value) -> actix_http::Request {
+        actix_web::test::TestRequest::post()
+            .uri("/api/chat/personality")
+            .insert_header(user.auth())
+            .set_json(body)
+            .to_request()
+    }
+
+    async fn count(rb: &RBatis, sql: &str, user_id: &str) -> i64 {
+        rb.query_decode::<i64>(sql, vec![rbs::Value::String(user_id.to_string())]).await.unwrap()
+    }
+
+    #[test]
+    fn test_parse_request_accepts_data_url_and_multipart() {
+        let body = json!({"message": "看看這個", "attachment": {"data": "data:image/png;base64,AQID"}});
+        let (req, image) = parse_request("application/json", body.to_string().as_bytes()).unwrap();
+        assert_eq!(req.message, "看看這個");
+        let image = image.unwrap();
+        assert_eq!((image.mime_type.as_str(), image.data.as_slice()), ("image/png", &[1u8, 2, 3][..]));
+
+        let body = json!({"message": "x", "attachment": {"data": "not base64!", "mime_type": "image/png"}});
+        assert!(parse_request("application/json", body.to_string().as_bytes()).is_err());
+        let body = json!({"message": "x", "attachment": {"data": "AQID"}});
+        assert!(parse_request("application/json", body.to_string().as_bytes()).is_err());
+
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"message\"\r\n\r\n早餐健康嗎\r\n--{b}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"meal.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n",
+            b = BOUNDARY
+        )
+        .into_bytes();
+        body.extend_from_slice(b"\xff\xd8jpeg");
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        let (req, image) = parse_request(&format!("multipart/form-data; boundary={}", BOUNDARY), &body).unwrap();
+        assert_eq!(req.message, "早餐健康嗎");
+        let image = image.unwrap();
+        assert_eq!((image.filename.as_str(), image.mime_type.as_str()), ("meal.jpg", "image/jpeg"));
+        assert_eq!(image.data, b"\xff\xd8jpeg");
+    }
+
+    #[actix_web::test]
+    async fn test_image_rejected_when_model_lacks_vision() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::default();
+        let app = test_utils::init_app_with_shared_ai(&rb, SharedAIService::new(Arc::new(mock.clone()))).await;
+        let user = test_utils::create_user(&app, "no-vision").await;
+
+        let body = json!({"message": "這是什麼", "user_id": user.id, "attachment": {"data": "AQID", "mime_type": "image/png"}});
+        let (status, body) = call_json(&app, chat_request(&user, body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(body["message"], "此模型不支援圖片");
+
+        // 模型設定了但實作不支援圖片（例如 Ollama）
+        let mock = MockAIService::default().without_vision();
+        let ai = SharedAIService::new(Arc::new(mock.clone())).with_vision_model("llama3.1");
+        let app = test_utils::init_app_with_shared_ai(&rb, ai).await;
+        let body = json!({"message": "這是什麼", "user_id": user.id, "attachment": {"data": "AQID", "mime_type": "image/png"}});
+        let (status, _) = call_json(&app, chat_request(&user, body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        assert!(mock.calls().is_empty());
+        assert_eq!(count(&rb, "SELECT COUNT(*) FROM chat_attachment WHERE user_id = ?", &user.id).await, 0);
+        assert_eq!(count(&rb, "SELECT COUNT(*) FROM chat_message WHERE user_id = ?", &user.id).await, 0);
+        assert!(!chat_dir(&user.id).is_some_and(|dir| dir.exists()));
+    }
+
+    #[actix_web::test]
+    async fn test_image_chat_stores_attachment_and_calls_vision_model() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["看起來是均衡的早餐"]);
+        let ai = SharedAIService::new(Arc::new(mock.clone())).with_vision_model("openai/gpt-4o-mini");
+        let app = test_utils::init_app_with_shared_ai(&rb, ai).await;
+        let user = test_utils::create_user(&app, "vision-user").await;
+        let other = test_utils::create_user(&app, "vision-other").await;
+
+        // 類型與大小限制
+        let body = json!({"message": "x", "user_id": user.id, "attachment": {"data": "AQID", "mime_type": "application/pdf"}});
+        assert_eq!(call_json(&app, chat_request(&user, body)).await.0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let too_large = base64::engine::general_purpose::STANDARD.encode(vec![0u8; task_attachments::config().chat_image_max_bytes + 1]);
+        let body = json!({"message": "x", "user_id": user.id, "attachment": {"data": too_large, "mime_type": "image/png"}});
+        assert_eq!(call_json(&app, chat_request(&user, body)).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(count(&rb, "SELECT COUNT(*) FROM chat_attachment WHERE user_id = ?", &user.id).await, 0);
+
+        let body = json!({
+            "message": "這份早餐健康嗎",
+            "user_id": user.id,
+            "attachment": {"data": "data:image/jpeg;base64,/9j/AQID", "filename": "breakfast.jpg"}
+        });
+        let (status, body) = call_json(&app, chat_request(&user, body)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(body["data"]["text"].as_str().unwrap().ends_with("看起來是均衡的早餐"), "{}", body);
+        assert_eq!(body["data"]["mode"], "full");
+        let attachment_id = body["data"]["attachment"]["id"].as_str().unwrap().to_string();
+        let prompts = mock.prompts("generate_with_image");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].starts_with("[openai/gpt-4o-mini] [image/jpeg 6 bytes]"), "{}", prompts[0]);
+        assert!(prompts[0].ends_with("這份早餐健康嗎"), "{}", prompts[0]);
+
+        // 歷史訊息帶有圖片引用
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/chat/messages?user_id={}", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let messages = body["data"].as_array().unwrap();
+        assert!(messages.iter().any(|m| m["role"] == "user" && m["attachment_id"] == attachment_id.as_str()), "{}", body);
+
+        let download = |auth: (&'static str, String)| {
+            actix_web::test::TestRequest::get()
+                .uri(&download_url(&attachment_id))
+                .insert_header(auth)
+                .to_request()
+        };
+        assert_eq!(call_json(&app, download(other.auth())).await.0, StatusCode::FORBIDDEN);
+        let resp = actix_web::test::call_service(&app, download(user.auth())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "image/jpeg");
+        assert_eq!(actix_web::test::read_body(resp).await.as_ref(), b"\xff\xd8\xff\x01\x02\x03");
+
+        // 重置聊天紀錄時一併刪除圖片
+        assert_eq!(purge_user_chat_attachments(&rb, &user.id).await.unwrap(), 1);
+        assert!(!chat_dir(&user.id).unwrap().exists());
+    }
+}
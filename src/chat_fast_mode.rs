@@ -0,0 +1,247 @@
+// 聊天快速模式：個性化聊天先以 fast 等級模型立即回答，短訊息略過專家匹配
+//
+// 使用者需自行開啟（預設關閉）。開啟「更完整的回答」時，另在背景以一般流程（專家匹配 +
+// 預設模型）重新回答，完成後寫入聊天紀錄並透過 SSE 推送 chat_followup 事件。
+// 兩種路徑的回應時間都會記錄，定期輸出 P50 / P95 供比較。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::ChatFastModeConfig;
+
+pub const FOLLOWUP_EVENT_TYPE: &str = "chat_followup";
+
+static CHAT_FAST_MODE_CONFIG: OnceLock<ChatFastModeConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: ChatFastModeConfig) {
+    log::info!(
+        "聊天快速模式: {} 字以內略過專家匹配，延遲統計保留最近 {} 筆",
+        config.skip_expert_max_chars,
+        config.latency_window
+    );
+    if CHAT_FAST_MODE_CONFIG.set(config).is_err() {
+        log::warn!("聊天快速模式設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static ChatFastModeConfig {
+    CHAT_FAST_MODE_CONFIG.get_or_init(ChatFastModeConfig::default)
+}
+
+/// 短訊息略過專家匹配（以字元數計算，中文一字算一個）
+pub fn should_skip_expert(message: &str) -> bool {
+    message.trim().chars().count() <= config().skip_expert_max_chars
+}
+
+/// 聊天回應的路徑（分別統計延遲）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatPath {
+    // 快速模式的即時回答
+    Fast,
+    // 一般流程（未開啟快速模式）
+    Full,
+    // 快速模式在背景產生的完整回答
+    Followup,
+}
+
+impl ChatPath {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatPath::Fast => "fast",
+            ChatPath::Full => "full",
+            ChatPath::Followup => "followup",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+// 路徑 -> 最近的回應時間（毫秒）
+static LATENCIES: OnceLock<Mutex<HashMap<&'static str, VecDeque<u64>>>> = OnceLock::new();
+
+fn latencies() -> &'static Mutex<HashMap<&'static str, VecDeque<u64>>> {
+    LATENCIES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 最近鄰排名法的百分位數（samples 不需排序）
+pub fn summarize(samples: &VecDeque<u64>) -> LatencySummary {
+    let mut sorted: Vec<u64> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    let percentile = |p: f64| -> u64 {
+        if sorted.is_empty() {
+            return 0;
+        }
+        let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    };
+    LatencySummary {
+        samples: sorted.len(),
+        p50_ms: percentile(50.0),
+        p95_ms: percentile(95.0),
+    }
+}
+
+/// 記錄一次回應時間，並輸出該路徑目前的 P50 / P95
+pub fn record_latency(path: ChatPath, elapsed: Duration) {
+    let ms = elapsed.as_millis() as u64;
+    let Ok(mut all) = latencies().lock() else {
+        return;
+    };
+    let samples = all.entry(path.as_str()).or_default();
+    samples.push_back(ms);
+    while samples.len() > config().latency_window {
+        samples.pop_front();
+    }
+    let summary = summarize(samples);
+    log::info!(
+        "聊天延遲 [{}] {}ms（最近 {} 筆 P50 {}ms / P95 {}ms）",
+        path.as_str(),
+        ms,
+        summary.samples,
+        summary.p50_ms,
+        summary.p95_ms
+    );
+}
+
+/// 各路徑目前的延遲統計（管理員執行期統計使用）
+pub fn metrics_snapshot() -> BTreeMap<String, LatencySummary> {
+    latencies()
+        .lock()
+        .map(|all| all.iter().map(|(path, samples)| (path.to_string(), summarize(samples))).collect())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FastModeSettings {
+    pub enabled: bool,
+    // 快速回答後是否在背景產生更完整的回答
+    pub followup: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFastModeSettingsRequest {
+    pub enabled: Option<bool>,
+    pub followup: Option<bool>,
+}
+
+/// 讀取使用者設定；未建立時為關閉、開啟後預設產生完整回答
+pub async fn load_settings(rb: &RBatis, user_id: &str) -> std::result::Result<FastModeSettings, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT chat_fast_mode, chat_fast_followup FROM user_settings WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let row = rows.first();
+    Ok(FastModeSettings {
+        enabled: row.and_then(|r| r["chat_fast_mode"].as_i64()).unwrap_or(0) == 1,
+        followup: row.and_then(|r| r["chat_fast_followup"].as_i64()).unwrap_or(1) == 1,
+    })
+}
+
+fn internal_error(e: rbatis::Error) -> HttpResponse {
+    log::error!("聊天快速模式設定存取失敗: {}", e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("聊天快速模式設定存取失敗: {}", e),
+    })
+}
+
+/// 取得聊天快速模式設定
+pub async fn get_fast_mode_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_settings(rb.get_ref(), &user_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            message: "獲取聊天快速模式設定成功".to_string(),
+        })),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+/// 更新聊天快速模式設定（未提供的欄位維持原值）
+pub async fn update_fast_mode_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<UpdateFastModeSettingsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let flag = |value: Option<bool>| value.map(|v| rbs::Value::I32(v as i32)).unwrap_or(rbs::Value::Null);
+    let rb = rb.get_ref();
+    let result = rb
+        .exec(
+            "INSERT INTO user_settings (user_id, chat_fast_mode, chat_fast_followup, updated_at)
+             VALUES (?, COALESCE(?, 0), COALESCE(?, 1), ?)
+             ON CONFLICT(user_id) DO UPDATE SET
+                 chat_fast_mode = COALESCE(?, chat_fast_mode),
+                 chat_fast_followup = COALESCE(?, chat_fast_followup),
+                 updated_at = excluded.updated_at",
+            vec![
+                rbs::Value::String(user_id.clone()),
+                flag(body.enabled),
+                flag(body.followup),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                flag(body.enabled),
+                flag(body.followup),
+            ],
+        )
+        .await;
+    if let Err(e) = result {
+        return Ok(internal_error(e));
+    }
+    match load_settings(rb, &user_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            message: "聊天快速模式設定已更新".to_string(),
+        })),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let samples: VecDeque<u64> = (1..=20).rev().map(|i| i * 100).collect();
+        let summary = summarize(&samples);
+        assert_eq!(summary, LatencySummary { samples: 20, p50_ms: 1000, p95_ms: 1900 });
+        assert_eq!(summarize(&VecDeque::from(vec![42])).p95_ms, 42);
+        assert_eq!(summarize(&VecDeque::new()).samples, 0);
+    }
+
+    #[test]
+    fn test_short_messages_skip_expert_matching() {
+        assert!(should_skip_expert("今天好累"));
+        assert!(!should_skip_expert(&"我想規劃一個長期的學習計畫".repeat(3)));
+    }
+}
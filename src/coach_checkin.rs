@@ -0,0 +1,538 @@
+// 教練主動關心：活躍度下滑時，由教練在聊天中主動開啟話題並寫入通知中心
+//
+// 使用者需自行開啟（預設關閉）。每天於設定時間檢查，只有在有具體資料時才產生訊息：
+// 前一週任務數達門檻且本週完成率明顯下滑，或過去一週有連續紀錄中斷。
+// 提示詞只帶入這些事實，避免 AI 憑空推測使用者的狀況。
+
+use std::sync::OnceLock;
+use std::time::Duration as StdDuration;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::ai_service::SharedAIService;
+use crate::ai_tasks::ApiResponse;
+use crate::config::CoachCheckinConfig;
+use crate::models::{ChatMessage, Task, CHAT_SOURCE_BACKEND};
+
+pub const EVENT_TYPE: &str = "coach_checkin";
+// 使用者可設定的間隔天數範圍
+const MIN_FREQUENCY_DAYS: i64 = 1;
+const MAX_FREQUENCY_DAYS: i64 = 30;
+// 提示詞中最多列出的中斷紀錄
+const MAX_PROMPT_STREAKS: usize = 3;
+
+static COACH_CHECKIN_CONFIG: OnceLock<CoachCheckinConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: CoachCheckinConfig) {
+    log::info!(
+        "教練主動關心: 每天 {} 檢查，預設每 {} 天最多一則",
+        config.run_time,
+        config.default_frequency_days
+    );
+    if COACH_CHECKIN_CONFIG.set(config).is_err() {
+        log::warn!("教練主動關心設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static CoachCheckinConfig {
+    COACH_CHECKIN_CONFIG.get_or_init(CoachCheckinConfig::default)
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckinSettings {
+    pub enabled: bool,
+    pub frequency_days: i64,
+    pub last_checkin_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCheckinSettingsRequest {
+    pub enabled: Option<bool>,
+    pub frequency_days: Option<i64>,
+}
+
+/// 一週（7 天）的任務完成數
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct WeekCompletion {
+    pub completed: i32,
+    pub total: i32,
+}
+
+impl WeekCompletion {
+    pub fn rate(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.completed as f64 / self.total as f64
+        }
+    }
+}
+
+/// 過去一週中斷的連續紀錄
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrokenStreak {
+    pub task_id: String,
+    pub title: String,
+    pub streak: i32,
+    pub missed_date: String,
+}
+
+/// 活躍度下滑的依據
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ActivitySignals {
+    pub this_week: WeekCompletion,
+    pub last_week: WeekCompletion,
+    pub completion_dropped: bool,
+    pub broken_streaks: Vec<BrokenStreak>,
+}
+
+impl ActivitySignals {
+    /// 有任一下滑訊號時才值得關心
+    pub fn has_drop(&self) -> bool {
+        self.completion_dropped || !self.broken_streaks.is_empty()
+    }
+}
+
+/// 以 end 為最後一天、往前共 7 天的任務完成數
+pub fn week_completion(tasks: &[Task], end: NaiveDate) -> WeekCompletion {
+    (0..7).fold(WeekCompletion::default(), |mut week, offset| {
+        let snapshot = crate::recompute::daily_snapshot(tasks, end - Duration::days(offset));
+        week.completed += snapshot.completed_tasks;
+        week.total += snapshot.total_tasks;
+        week
+    })
+}
+
+/// 前一週任務數達門檻、且本週完成率下降達設定幅度
+pub fn completion_dropped(this_week: WeekCompletion, last_week: WeekCompletion, min_tasks: i32, drop: f64) -> bool {
+    last_week.total >= min_tasks && last_week.rate() - this_week.rate() >= drop
+}
+
+/// 從習慣日曆找出過去 7 天內（不含今天）最近一次中斷：回傳 (中斷前的連續天數, 中斷日期)
+pub fn broken_streak(calendar: &[crate::habit_stats::HabitDay], min_streak: i32) -> Option<(i32, String)> {
+    // 日曆最後一天是今天
+    let past = calendar.len().checked_sub(1)?;
+    let window_start = past.saturating_sub(7);
+    let missed = (window_start..past).rev().find(|&i| calendar[i].scheduled && !calendar[i].completed)?;
+    let streak = calendar[..missed]
+        .iter()
+        .rev()
+        .filter(|day| day.scheduled)
+        .take_while(|day| day.completed)
+        .count() as i32;
+    (streak >= min_streak).then(|| (streak, calendar[missed].date.clone()))
+}
+
+/// 彙整使用者的活躍度訊號（today 為使用者時區的今天，今天尚未結束不計入）
+pub async fn collect_signals(rb: &RBatis, user_id: &str, today: NaiveDate) -> std::result::Result<ActivitySignals, rbatis::Error> {
+    let tasks = Task::select_by_map(rb, value!{"user_id": user_id}).await?;
+    let yesterday = today - Duration::days(1);
+    let this_week = week_completion(&tasks, yesterday);
+    let last_week = week_completion(&tasks, yesterday - Duration::days(7));
+    let settings = config();
+
+    let mut broken_streaks = Vec::new();
+    for task in tasks.iter().filter(|t| t.is_recurring == Some(1) && t.parent_task_id.is_none()) {
+        let stats = crate::habit_stats::load_habit_stats(rb, task).await?;
+        if let Some((streak, missed_date)) = broken_streak(&stats.calendar, settings.min_broken_streak) {
+            broken_streaks.push(BrokenStreak {
+                task_id: stats.task_id,
+                title: task.title.clone().unwrap_or_default(),
+                streak,
+                missed_date,
+            });
+        }
+    }
+    broken_streaks.sort_by_key(|b| std::cmp::Reverse(b.streak));
+
+    Ok(ActivitySignals {
+        completion_dropped: completion_dropped(this_week, last_week, settings.min_weekly_tasks, settings.completion_drop),
+        this_week,
+        last_week,
+        broken_streaks,
+    })
+}
+
+/// 組出關心訊息的提示詞：只列出已知事實
+pub fn build_checkin_prompt(system_prompt: &str, signals: &ActivitySignals, language: crate::language::Language) -> String {
+    let mut facts = Vec::new();
+    if signals.completion_dropped {
+        facts.push(format!(
+            "- 上上週完成 {}/{} 個任務（{:.0}%），上週完成 {}/{} 個（{:.0}%）",
+            signals.last_week.completed,
+            signals.last_week.total,
+            signals.last_week.rate() * 100.0,
+            signals.this_week.completed,
+            signals.this_week.total,
+            signals.this_week.rate() * 100.0
+        ));
+    }
+    for broken in signals.broken_streaks.iter().take(MAX_PROMPT_STREAKS) {
+        facts.push(format!(
+            "- 習慣「{}」原本連續 {} 天，在 {} 中斷了",
+            broken.title, broken.streak, broken.missed_date
+        ));
+    }
+
+    format!(
+        "{}\n\n你要主動傳訊息關心使用者，開啟一段對話。以下是目前已知的全部事實：\n{}\n\n\
+         請寫一則 1～2 句的簡短訊息：\n\
+         - {}\n\
+         - 只根據上面的事實，不要猜測原因，也不要提到其他任務或數字\n\
+         - 不要責備或讓使用者有罪惡感，以關心的問句結尾，邀請使用者聊聊發生了什麼事\n\
+         - 只輸出訊息本身",
+        system_prompt,
+        facts.join("\n"),
+        language.directive()
+    )
+}
+
+/// 間隔天數：使用者設定優先，並限制在合理範圍
+fn effective_frequency(frequency_days: Option<i64>) -> i64 {
+    frequency_days
+        .unwrap_or(config().default_frequency_days)
+        .clamp(MIN_FREQUENCY_DAYS, MAX_FREQUENCY_DAYS)
+}
+
+/// 已開啟且距離上次關心已超過間隔天數的使用者
+async fn due_users(rb: &RBatis, now: DateTime<Utc>) -> std::result::Result<Vec<String>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT user_id, frequency_days, last_checkin_at FROM coach_checkin_setting WHERE enabled = 1",
+            vec![],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .filter(|row| {
+            let last = row["last_checkin_at"]
+                .as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok());
+            let frequency = effective_frequency(row["frequency_days"].as_i64());
+            // 以天為單位的間隔，保留一小時緩衝讓每天同一時間的排程不會因秒數誤差延後一天
+            last.is_none_or(|last| now - last.with_timezone(&Utc) >= Duration::days(frequency) - Duration::hours(1))
+        })
+        .filter_map(|row| row["user_id"].as_str().map(str::to_string))
+        .collect())
+}
+
+/// 為單一使用者檢查並產生關心訊息；回傳是否已發送
+pub async fn checkin_user(
+    rb: &RBatis,
+    ai: &SharedAIService,
+    user_id: &str,
+    today: NaiveDate,
+) -> std::result::Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let signals = collect_signals(rb, user_id, today).await?;
+    // 沒有具體資料時不產生訊息，避免 AI 編造內容
+    if !signals.has_drop() {
+        return Ok(false);
+    }
+
+    let personality = crate::routes::coach::get_user_personality_type(rb, Some(user_id.to_string()))
+        .await
+        .unwrap_or(crate::models::CoachPersonalityType::EmotionalSupport);
+    // 排程工作不在請求範圍內，依使用者設定的語言撰寫
+    let language = crate::language::user_language(rb, user_id).await;
+    let prompt = build_checkin_prompt(personality.system_prompt(), &signals, language);
+    let message = ai.get()?.generate_with_model(ai.background_model(), &prompt).await?;
+    let message = message.trim().to_string();
+    if message.is_empty() {
+        return Ok(false);
+    }
+
+    let now = Utc::now();
+    let chat_message = ChatMessage {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id.to_string()),
+        role: Some("assistant".to_string()),
+        content: Some(message.clone()),
+        source: Some(CHAT_SOURCE_BACKEND.to_string()),
+        expert_name: None,
+        attachment_id: None,
+        created_at: Some(now),
+    };
+    ChatMessage::insert(rb, &chat_message).await?;
+    rb.exec(
+        "UPDATE coach_checkin_setting SET last_checkin_at = ?, updated_at = ? WHERE user_id = ?",
+        vec![
+            rbs::Value::String(now.to_rfc3339()),
+            rbs::Value::String(now.to_rfc3339()),
+            rbs::Value::String(user_id.to_string()),
+        ],
+    )
+    .await?;
+
+    let notification = json!({
+        "title": format!("{}想和你聊聊", personality.display_name()),
+        "body": message,
+        "icon": "/icon.svg",
+        "badge": "/icon.svg",
+        "tag": "coach-checkin-notification",
+        "data": {
+            "url": "/chat",
+            "type": EVENT_TYPE,
+            "chat_message_id": chat_message.id,
+            "signals": signals,
+        }
+    });
+    crate::event_notifier::notify_scheduled(rb, user_id, EVENT_TYPE, &notification).await;
+    Ok(true)
+}
+
+/// 對所有到期的使用者執行一輪關心；回傳發送數
+pub async fn run_checkins(rb: &RBatis, ai: &SharedAIService, today: NaiveDate) -> usize {
+    let users = match due_users(rb, Utc::now()).await {
+        Ok(users) => users,
+        Err(e) => {
+            log::error!("查詢教練主動關心設定失敗: {}", e);
+            return 0;
+        }
+    };
+    let mut sent = 0;
+    for user_id in users {
+        match checkin_user(rb, ai, &user_id, today).await {
+            Ok(true) => sent += 1,
+            Ok(false) => {}
+            Err(e) => log::error!("教練主動關心失敗 (user_id: {}): {}", user_id, e),
+        }
+    }
+    sent
+}
+
+/// 每分鐘檢查一次，到設定時間時執行一輪
+pub fn spawn_scheduler(rb: RBatis, ai: SharedAIService) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let now = Utc::now().with_timezone(&crate::local_date::default_timezone());
+            if format!("{:02}:{:02}", now.hour(), now.minute()) != config().run_time {
+                continue;
+            }
+            let sent = run_checkins(&rb, &ai, now.date_naive()).await;
+            if sent > 0 {
+                log::info!("教練主動關心：共發送 {} 則", sent);
+            }
+        }
+    });
+}
+
+async fn load_settings(rb: &RBatis, user_id: &str) -> std::result::Result<CheckinSettings, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT enabled, frequency_days, last_checkin_at FROM coach_checkin_setting WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let row = rows.first();
+    Ok(CheckinSettings {
+        enabled: row.and_then(|r| r["enabled"].as_i64()).unwrap_or(0) == 1,
+        frequency_days: effective_frequency(row.and_then(|r| r["frequency_days"].as_i64())),
+        last_checkin_at: row.and_then(|r| r["last_checkin_at"].as_str()).map(str::to_string),
+    })
+}
+
+fn internal_error(e: rbatis::Error) -> HttpResponse {
+    log::error!("教練主動關心設定存取失敗: {}", e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("教練主動關心設定存取失敗: {}", e),
+    })
+}
+
+/// 取得教練主動關心設定
+pub async fn get_checkin_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_settings(rb.get_ref(), &user_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            message: "獲取教練主動關心設定成功".to_string(),
+        })),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+/// 更新教練主動關心設定（未提供的欄位維持原值）
+pub async fn update_checkin_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<UpdateCheckinSettingsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    if let Some(days) = body.frequency_days {
+        if !(MIN_FREQUENCY_DAYS..=MAX_FREQUENCY_DAYS).contains(&days) {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("frequency_days 必須介於 {} 到 {} 之間", MIN_FREQUENCY_DAYS, MAX_FREQUENCY_DAYS),
+            }));
+        }
+    }
+
+    let rb = rb.get_ref();
+    let result = rb
+        .exec(
+            "INSERT INTO coach_checkin_setting (user_id, enabled, frequency_days, updated_at)
+             VALUES (?, COALESCE(?, 0), ?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET
+                 enabled = COALESCE(?, enabled),
+                 frequency_days = COALESCE(?, frequency_days),
+                 updated_at = excluded.updated_at",
+            vec![
+                rbs::Value::String(user_id.clone()),
+                body.enabled.map(|e| rbs::Value::I32(e as i32)).unwrap_or(rbs::Value::Null),
+                body.frequency_days.map(rbs::Value::I64).unwrap_or(rbs::Value::Null),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                body.enabled.map(|e| rbs::Value::I32(e as i32)).unwrap_or(rbs::Value::Null),
+                body.frequency_days.map(rbs::Value::I64).unwrap_or(rbs::Value::Null),
+            ],
+        )
+        .await;
+    if let Err(e) = result {
+        return Ok(internal_error(e));
+    }
+    match load_settings(rb, &user_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            message: "教練主動關心設定已更新".to_string(),
+        })),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::habit_stats::HabitDay;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+    use std::sync::Arc;
+
+    fn day(date: &str, scheduled: bool, completed: bool) -> HabitDay {
+        HabitDay { date: date.to_string(), weekday: 0, scheduled, completed }
+    }
+
+    #[test]
+    fn test_signal_rules() {
+        let last = WeekCompletion { completed: 6, total: 7 };
+        assert!(completion_dropped(WeekCompletion { completed: 2, total: 7 }, last, 3, 0.2));
+        assert!(!completion_dropped(WeekCompletion { completed: 5, total: 7 }, last, 3, 0.2));
+        // 前一週資料太少不比較
+        assert!(!completion_dropped(WeekCompletion::default(), WeekCompletion { completed: 2, total: 2 }, 3, 0.2));
+
+        // 連續 3 天後在 3/5 中斷，今天 3/7 尚未完成不算中斷
+        let calendar = vec![
+            day("2026-03-01", true, true),
+            day("2026-03-02", false, false),
+            day("2026-03-03", true, true),
+            day("2026-03-04", true, true),
+            day("2026-03-05", true, false),
+            day("2026-03-06", true, true),
+            day("2026-03-07", true, false),
+        ];
+        assert_eq!(broken_streak(&calendar, 3), Some((3, "2026-03-05".to_string())));
+        assert_eq!(broken_streak(&calendar, 4), None);
+        assert_eq!(broken_streak(&calendar[..4], 1), None);
+    }
+
+    #[actix_web::test]
+    async fn test_checkin_requires_opt_in_and_data() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["上週好像比較忙？想聊聊發生了什麼事嗎？"]);
+        let ai = SharedAIService::new(Arc::new(mock.clone()));
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "checkin_user").await;
+        let idle = test_utils::create_user(&app, "checkin_idle").await;
+        let today = NaiveDate::from_ymd_opt(2026, 3, 16).unwrap();
+
+        // 上上週每天完成，上週幾乎沒完成
+        for offset in 1..=14 {
+            let date = today - Duration::days(offset);
+            let status = if offset > 7 || offset == 1 { 6 } else { 7 };
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, task_type, status, task_date, created_at) VALUES (?, ?, '晨跑', 'daily', ?, ?, ?)",
+                vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::I32(status),
+                    rbs::Value::String(date.format("%Y-%m-%d").to_string()),
+                    rbs::Value::String(Utc::now().to_rfc3339()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        // 未開啟時不檢查
+        assert_eq!(run_checkins(&rb, &ai, today).await, 0);
+
+        for target in [&user, &idle] {
+            let req = actix_web::test::TestRequest::put()
+                .uri(&format!("/api/users/{}/coach/checkin-settings", target.id))
+                .insert_header(target.auth())
+                .set_json(json!({"enabled": true}))
+                .to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, 200);
+            assert_eq!(body["data"]["enabled"], true);
+            assert_eq!(body["data"]["frequency_days"], 7);
+        }
+
+        // 只有活躍度下滑的使用者收到；沒有資料的使用者不呼叫 AI
+        assert_eq!(run_checkins(&rb, &ai, today).await, 1);
+        let prompts = mock.prompts("generate_with_model");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].starts_with(&format!("[{}]", ai.background_model())));
+        assert!(prompts[0].contains("上上週完成 7/7 個任務（100%），上週完成 1/7 個（14%）"));
+
+        let messages: Vec<ChatMessage> = ChatMessage::select_by_map(&rb, value!{"user_id": &user.id}).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role.as_deref(), Some("assistant"));
+        assert_eq!(messages[0].content.as_deref(), Some("上週好像比較忙？想聊聊發生了什麼事嗎？"));
+        let count: i64 = rb
+            .query_decode(
+                "SELECT COUNT(*) AS count FROM notification_history WHERE user_id = ? AND event_type = ?",
+                vec![rbs::Value::String(user.id.clone()), rbs::Value::String(EVENT_TYPE.to_string())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // 間隔天數內不重複發送
+        assert_eq!(run_checkins(&rb, &ai, today).await, 0);
+
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("/api/users/{}/coach/checkin-settings", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"frequency_days": 60}))
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 400);
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/coach/checkin-settings", user.id))
+            .insert_header(idle.auth())
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 403);
+    }
+}
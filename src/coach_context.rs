@@ -0,0 +1,277 @@
+// 教練對話的使用者狀況：每則訊息重新整理等級、連續登入、前幾名技能、今日待辦與目前職涯主線，
+//
+// 以精簡文字放在系統提示詞最前面，讓建議貼近使用者的實際狀況。內容只包含上述欄位，
+// 並以字元數上限控制長度（中文約一字一 token）。使用者可在設定中關閉（share_with_coach）。
+
+use chrono::{Duration, TimeZone, Utc};
+use rbatis::RBatis;
+use rbs::Value;
+
+use crate::models::TaskStatus;
+
+/// 整段上下文的字元上限（約略等於 token 上限）
+pub const MAX_CONTEXT_CHARS: usize = 400;
+const MAX_SKILLS: usize = 3;
+const MAX_TASKS: usize = 8;
+// 單一任務標題最多顯示的字元數
+const MAX_TITLE_CHARS: usize = 24;
+
+/// 組成上下文的資料（只放要給教練看的欄位）
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CoachContext {
+    pub level: Option<i32>,
+    pub experience: Option<i32>,
+    pub max_experience: Option<i32>,
+    pub login_streak: i32,
+    // (技能名稱, 等級)
+    pub top_skills: Vec<(String, i32)>,
+    pub pending_tasks: Vec<String>,
+    // (職業名稱, 進度百分比)
+    pub mainline: Option<(String, f64)>,
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// 使用者是否同意把資料提供給教練（未設定時預設開啟）
+pub async fn sharing_enabled(rb: &RBatis, user_id: &str) -> Result<bool, rbatis::Error> {
+    let value: Option<i64> = rb
+        .query_decode(
+            "SELECT share_with_coach FROM user_settings WHERE user_id = ?",
+            vec![Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(value != Some(0))
+}
+
+/// 讀取組成上下文所需的資料；沒有資料的項目保持空值
+pub async fn load(rb: &RBatis, user_id: &str) -> Result<CoachContext, rbatis::Error> {
+    let uid = || Value::String(user_id.to_string());
+    let mut context = CoachContext::default();
+
+    let profiles: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT level, experience, max_experience, consecutive_login_days FROM user_profile WHERE user_id = ? LIMIT 1",
+            vec![uid()],
+        )
+        .await?;
+    if let Some(profile) = profiles.first() {
+        let number = |key: &str| profile[key].as_i64().map(|v| v as i32);
+        context.level = number("level");
+        context.experience = number("experience");
+        context.max_experience = number("max_experience");
+        context.login_streak = number("consecutive_login_days").unwrap_or(0);
+    }
+
+    let skills: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT name, level FROM skill WHERE user_id = ? AND name IS NOT NULL
+             ORDER BY COALESCE(level, 0) DESC, COALESCE(experience, 0) DESC LIMIT ?",
+            vec![uid(), Value::I64(MAX_SKILLS as i64)],
+        )
+        .await?;
+    context.top_skills = skills
+        .iter()
+        .filter_map(|s| Some((s["name"].as_str()?.to_string(), s["level"].as_i64().unwrap_or(1) as i32)))
+        .collect();
+
+    // 今日待辦：今天的每日子任務、今天（使用者時區）以前到期或進行中的任務；不含重複性任務的模板
+    let tz = crate::user_settings::user_timezone(rb, user_id).await;
+    let today = crate::local_date::local_today_in(tz);
+    let tomorrow = today + Duration::days(1);
+    let tomorrow_start = tz
+        .from_local_datetime(&tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let tasks: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT title FROM task
+             WHERE user_id = ? AND status IN (?, ?, ?) AND title IS NOT NULL
+               AND NOT (COALESCE(is_recurring, 0) = 1 AND parent_task_id IS NULL)
+               AND (task_date = ? OR status = ? OR julianday(due_date) < julianday(?))
+             ORDER BY COALESCE(priority, 0) DESC, due_date IS NULL, due_date
+             LIMIT ?",
+            vec![
+                uid(),
+                Value::I32(TaskStatus::Pending.to_i32()),
+                Value::I32(TaskStatus::InProgress.to_i32()),
+                Value::I32(TaskStatus::DailyInProgress.to_i32()),
+                Value::String(today.format("%Y-%m-%d").to_string()),
+                Value::I32(TaskStatus::InProgress.to_i32()),
+                Value::String(tomorrow_start.to_rfc3339()),
+                Value::I64(MAX_TASKS as i64),
+            ],
+        )
+        .await?;
+    context.pending_tasks = tasks
+        .iter()
+        .filter_map(|t| t["title"].as_str())
+        .map(|title| truncate_chars(title.trim(), MAX_TITLE_CHARS))
+        .filter(|title| !title.is_empty())
+        .collect();
+
+    let mainlines: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT selected_career, progress_percentage FROM career_mainlines
+             WHERE user_id = ? AND status = 'active' ORDER BY created_at DESC LIMIT 1",
+            vec![uid()],
+        )
+        .await?;
+    context.mainline = mainlines.first().and_then(|m| {
+        let career = m["selected_career"].as_str()?.to_string();
+        Some((career, m["progress_percentage"].as_f64().unwrap_or(0.0)))
+    });
+
+    Ok(context)
+}
+
+/// 組成上下文文字；超過字元上限時先減少待辦項目，最後整段截斷
+pub fn render(context: &CoachContext) -> String {
+    let mut lines = vec!["【使用者目前狀況】（回答時自然參考，不必逐條複述）".to_string()];
+    lines.push(match (context.level, context.experience, context.max_experience) {
+        (Some(level), Some(exp), Some(max)) => format!("等級：Lv.{}（經驗值 {}/{}）", level, exp, max),
+        (Some(level), _, _) => format!("等級：Lv.{}", level),
+        _ => "等級：尚無資料".to_string(),
+    });
+    lines.push(format!("連續登入：{} 天", context.login_streak));
+    lines.push(if context.top_skills.is_empty() {
+        "擅長技能：尚無".to_string()
+    } else {
+        let skills: Vec<String> = context
+            .top_skills
+            .iter()
+            .map(|(name, level)| format!("{} Lv.{}", truncate_chars(name, MAX_TITLE_CHARS), level))
+            .collect();
+        format!("擅長技能：{}", skills.join("、"))
+    });
+    lines.push(match &context.mainline {
+        Some((career, progress)) => format!("職涯主線：{}（進度 {:.0}%）", truncate_chars(career, MAX_TITLE_CHARS), progress),
+        None => "職涯主線：尚未選擇".to_string(),
+    });
+
+    let fixed = lines.join("\n");
+    let task_line = |count: usize| -> String {
+        if context.pending_tasks.is_empty() {
+            return "今日待辦：無".to_string();
+        }
+        let shown = &context.pending_tasks[..count];
+        let hidden = context.pending_tasks.len() - count;
+        match (count, hidden) {
+            (0, _) => format!("今日待辦：{} 項", hidden),
+            (_, 0) => format!("今日待辦：{}", shown.join("、")),
+            _ => format!("今日待辦：{} 等 {} 項", shown.join("、"), context.pending_tasks.len()),
+        }
+    };
+    let mut count = context.pending_tasks.len();
+    let mut block = format!("{}\n{}", fixed, task_line(count));
+    while block.chars().count() > MAX_CONTEXT_CHARS && count > 0 {
+        count -= 1;
+        block = format!("{}\n{}", fixed, task_line(count));
+    }
+    truncate_chars(&block, MAX_CONTEXT_CHARS)
+}
+
+/// 教練提示詞使用的上下文；使用者關閉分享或查詢失敗時回傳 None
+pub async fn prompt_block(rb: &RBatis, user_id: &str) -> Option<String> {
+    match sharing_enabled(rb, user_id).await {
+        Ok(true) => {}
+        Ok(false) => return None,
+        Err(e) => {
+            log::warn!("讀取教練資料分享設定失敗: {}", e);
+            return None;
+        }
+    }
+    match load(rb, user_id).await {
+        Ok(context) => {
+            let block = render(&context);
+            log::info!("教練上下文: 用戶 {}，{} 字元", user_id, block.chars().count());
+            Some(block)
+        }
+        Err(e) => {
+            log::warn!("整理教練上下文失敗: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[test]
+    fn test_render_empty_context() {
+        let block = render(&CoachContext::default());
+        assert!(block.contains("等級：尚無資料"));
+        assert!(block.contains("連續登入：0 天"));
+        assert!(block.contains("擅長技能：尚無"));
+        assert!(block.contains("今日待辦：無"));
+        assert!(block.contains("職涯主線：尚未選擇"));
+        assert!(block.chars().count() <= MAX_CONTEXT_CHARS);
+    }
+
+    #[test]
+    fn test_render_respects_budget() {
+        let context = CoachContext {
+            level: Some(7),
+            experience: Some(120),
+            max_experience: Some(300),
+            login_streak: 12,
+            top_skills: vec![("Rust".to_string(), 4), ("英文寫作".to_string(), 2)],
+            pending_tasks: (0..MAX_TASKS).map(|i| format!("{}：{}", i, "非常長的任務標題".repeat(5))).collect(),
+            mainline: Some(("後端工程師".to_string(), 42.4)),
+        };
+        let block = render(&context);
+        assert!(block.chars().count() <= MAX_CONTEXT_CHARS);
+        assert!(block.contains("Lv.7（經驗值 120/300）"));
+        assert!(block.contains("Rust Lv.4、英文寫作 Lv.2"));
+        assert!(block.contains("後端工程師（進度 42%）"));
+        assert!(block.contains(&format!("等 {} 項", MAX_TASKS)));
+    }
+
+    #[actix_web::test]
+    async fn test_prompt_block_for_new_user_and_opt_out() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "coach-context").await;
+
+        // 沒有技能、任務與主線的新使用者
+        let block = prompt_block(&rb, &user.id).await.unwrap();
+        assert!(block.contains("擅長技能：尚無"));
+        assert!(block.contains("今日待辦：無"));
+        assert!(block.contains("職涯主線：尚未選擇"));
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "讀完第三章", "task_type": "side"}))
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", body["data"]["id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .set_json(json!({"status": TaskStatus::InProgress.to_i32(), "version": body["data"]["version"]}))
+            .to_request();
+        call_json(&app, req).await;
+        let block = prompt_block(&rb, &user.id).await.unwrap();
+        assert!(block.contains("今日待辦：讀完第三章"));
+
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/users/{}/settings", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"share_with_coach": false}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["share_with_coach"], false);
+        assert!(prompt_block(&rb, &user.id).await.is_none());
+    }
+}
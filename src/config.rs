@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::env;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -18,13 +18,672 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub allowed_origins: Vec<String>,
+    // 開發用：允許任何來源（生產環境會被忽略）
+    pub allow_any_origin: bool,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub environment: String,
     pub log_level: String,
+    // 日誌格式："text"（預設）或 "json"（每行一個 JSON 物件，供 Loki/ELK 收集）
+    pub log_format: String,
+    pub log_file: LogFileConfig,
     pub ai: AIConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub registration_guard: RegistrationGuardConfig,
+    pub ai_quota: AiQuotaConfig,
+    pub mail: MailConfig,
+    pub attachments: AttachmentConfig,
+    pub focus: FocusConfig,
+    pub daily_quests: DailyQuestConfig,
+    pub slow_log: SlowLogConfig,
+    pub streak_reminder: StreakReminderConfig,
+    pub notification_center: NotificationCenterConfig,
+    pub gamified_cache: GamifiedCacheConfig,
+    pub coach_checkin: CoachCheckinConfig,
+    pub data_retention: DataRetentionConfig,
+    pub reward: RewardConfig,
+    pub recurring: RecurringConfig,
+    pub chat_fast_mode: ChatFastModeConfig,
+    pub ai_sanitize: AiSanitizeConfig,
+    pub nightly_digest: NightlyDigestConfig,
+    pub daily_compaction: DailyCompactionConfig,
+    pub background_jobs: BackgroundJobConfig,
+    pub achievement_autogen: AchievementAutogenConfig,
+    pub achievement_check: AchievementCheckConfig,
+    pub task_confirmation: TaskConfirmationConfig,
+    pub task_cancellation: TaskCancellationConfig,
+    pub career_trace: CareerTraceConfig,
+    pub skill_attribute: SkillAttributeConfig,
+    pub new_user_defaults: NewUserDefaultsConfig,
+    pub task_types: TaskTypeConfig,
+    // 過渡期：聊天 API 同時在最外層輸出 data 內的欄位（例如 text）
+    pub legacy_response_fields: bool,
+    // 過渡期：聊天回覆的 text 前面加上專家 emoji（前端改讀 expert_emoji 後關閉）
+    pub legacy_expert_prefix: bool,
+    pub api_versioning: ApiVersioningConfig,
+    // 未個別設定時區的使用者所用的 UTC 偏移（±HH:MM）
+    pub default_timezone: String,
+}
+
+/// 郵件發送設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct MailConfig {
+    // smtp 或 log（開發用，只寫入日誌）
+    pub provider: String,
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_starttls: bool,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub from_address: String,
+    // 背景發送失敗時的最多嘗試次數
+    pub max_attempts: u32,
+}
+
+/// 登入失敗節流設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoginThrottleConfig {
+    // 同一 email 在時間窗口內失敗幾次後鎖定
+    pub max_failures_per_email: u32,
+    // 同一 IP 在時間窗口內失敗幾次後回傳 429
+    pub max_failures_per_ip: u32,
+    pub window_secs: i64,
+    // 第一次鎖定的時間，之後每次鎖定加倍（上限 24 小時）
+    pub lockout_secs: i64,
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        LoginThrottleConfig {
+            max_failures_per_email: 5,
+            max_failures_per_ip: 20,
+            window_secs: 900,
+            lockout_secs: 900,
+        }
+    }
+}
+
+/// 註冊防濫用設定（各項可分別關閉）
+#[derive(Debug, Deserialize, Clone)]
+pub struct RegistrationGuardConfig {
+    // 拒絕拋棄式信箱網域
+    pub block_disposable_email: bool,
+    // 自訂網域清單檔案（每行一個網域，# 開頭為註解）；未設定時使用內建清單
+    pub disposable_domains_file: Option<String>,
+    // 人機驗證：none 或 hcaptcha
+    pub captcha_provider: String,
+    pub hcaptcha_secret: Option<String>,
+    pub hcaptcha_verify_url: String,
+    // 同一 IP 每天（使用者時區）最多成功註冊幾個帳號，0 表示不限制
+    pub max_registrations_per_ip_per_day: u32,
+}
+
+impl Default for RegistrationGuardConfig {
+    fn default() -> Self {
+        RegistrationGuardConfig {
+            block_disposable_email: true,
+            disposable_domains_file: None,
+            captcha_provider: "none".to_string(),
+            hcaptcha_secret: None,
+            hcaptcha_verify_url: "https://api.hcaptcha.com/siteverify".to_string(),
+            max_registrations_per_ip_per_day: 5,
+        }
+    }
+}
+
+/// 每位使用者的 AI 每日使用上限（依類別計算；-1 表示不限制）
+#[derive(Debug, Deserialize, Clone)]
+pub struct AiQuotaConfig {
+    pub task_generation: i32,
+    pub career_generation: i32,
+    pub achievement_generation: i32,
+    pub chat: i32,
+    pub personality_compare: i32,
+}
+
+impl Default for AiQuotaConfig {
+    fn default() -> Self {
+        AiQuotaConfig {
+            task_generation: 50,
+            career_generation: 5,
+            achievement_generation: 20,
+            chat: 200,
+            personality_compare: 10,
+        }
+    }
+}
+
+/// 任務附件（完成證明）與聊天圖片的儲存位置與上傳限制
+#[derive(Debug, Deserialize, Clone)]
+pub struct AttachmentConfig {
+    pub storage_dir: String,
+    pub max_size_bytes: usize,
+    pub allowed_mime_types: Vec<String>,
+    // 聊天附圖（存放於 storage_dir/chat）
+    pub chat_image_max_bytes: usize,
+    pub chat_image_mime_types: Vec<String>,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        AttachmentConfig {
+            storage_dir: "uploads/attachments".to_string(),
+            max_size_bytes: 10 * 1024 * 1024,
+            allowed_mime_types: ["image/jpeg", "image/png", "image/webp", "image/heic", "application/pdf"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            chat_image_max_bytes: 5 * 1024 * 1024,
+            chat_image_mime_types: ["image/jpeg", "image/png", "image/webp", "image/gif"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// 專注（番茄鐘）時段的獎勵與長度限制
+#[derive(Debug, Deserialize, Clone)]
+pub struct FocusConfig {
+    pub experience_per_session: i32,
+    pub default_duration_minutes: i32,
+    pub max_duration_minutes: i32,
+}
+
+impl Default for FocusConfig {
+    fn default() -> Self {
+        FocusConfig {
+            experience_per_session: 5,
+            default_duration_minutes: 25,
+            max_duration_minutes: 180,
+        }
+    }
+}
+
+/// 每日三任務的重抽次數與全數完成的額外獎勵
+#[derive(Debug, Deserialize, Clone)]
+pub struct DailyQuestConfig {
+    pub bonus_experience: i32,
+    pub max_rerolls: i32,
+}
+
+impl Default for DailyQuestConfig {
+    fn default() -> Self {
+        DailyQuestConfig {
+            bonus_experience: 30,
+            max_rerolls: 2,
+        }
+    }
+}
+
+/// 日誌檔案設定：依大小或時間輪替，保留固定數量的封存檔
+#[derive(Debug, Deserialize, Clone)]
+pub struct LogFileConfig {
+    pub dir: String,
+    pub max_size_mb: u64,
+    // 依時間輪替的間隔（小時），0 表示只依大小輪替
+    pub rotate_interval_hours: i64,
+    pub keep_archives: u32,
+    pub gzip: bool,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        LogFileConfig {
+            dir: "logs".to_string(),
+            max_size_mb: 50,
+            rotate_interval_hours: 24,
+            keep_archives: 14,
+            gzip: true,
+        }
+    }
+}
+
+/// 慢查詢與慢請求的日誌門檻（毫秒）
+#[derive(Debug, Deserialize, Clone)]
+pub struct SlowLogConfig {
+    pub sql_threshold_ms: u64,
+    pub request_threshold_ms: u64,
+}
+
+impl Default for SlowLogConfig {
+    fn default() -> Self {
+        SlowLogConfig {
+            sql_threshold_ms: 200,
+            request_threshold_ms: 1000,
+        }
+    }
+}
+
+/// 連續紀錄中斷提醒：晚間總結前檢查仍未完成的重複性任務
+#[derive(Debug, Deserialize, Clone)]
+pub struct StreakReminderConfig {
+    // 連續天數達到門檻才提醒
+    pub min_streak: i32,
+    // 在晚間總結前幾分鐘發送（晚間總結 22:00、30 分鐘 → 21:30）
+    pub minutes_before_evening: i64,
+}
+
+impl Default for StreakReminderConfig {
+    fn default() -> Self {
+        StreakReminderConfig {
+            min_streak: 3,
+            minutes_before_evening: 30,
+        }
+    }
+}
+
+/// 教練主動關心：活躍度下滑時由教練在聊天中主動開啟話題
+#[derive(Debug, Deserialize, Clone)]
+pub struct CoachCheckinConfig {
+    // 每天檢查的時間（UTC+8，HH:MM）
+    pub run_time: String,
+    // 使用者未自行設定時，兩次關心訊息的最短間隔天數
+    pub default_frequency_days: i64,
+    // 前一週的任務數達門檻才比較完成率（資料太少不產生訊息）
+    pub min_weekly_tasks: i32,
+    // 完成率下滑幅度（0.2 = 下降 20 個百分點）
+    pub completion_drop: f64,
+    // 中斷前的連續天數達門檻才視為連續紀錄中斷
+    pub min_broken_streak: i32,
+}
+
+impl Default for CoachCheckinConfig {
+    fn default() -> Self {
+        CoachCheckinConfig {
+            run_time: "20:00".to_string(),
+            default_frequency_days: 7,
+            min_weekly_tasks: 3,
+            completion_drop: 0.2,
+            min_broken_streak: 3,
+        }
+    }
+}
+
+/// 使用者資料保留策略的清除工作設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct DataRetentionConfig {
+    // 每天執行清除的時間（UTC+8，HH:MM）
+    pub run_time: String,
+    // 每次 DELETE 的最多筆數（分批刪除，避免長時間鎖住 SQLite）
+    pub batch_size: i64,
+}
+
+impl Default for DataRetentionConfig {
+    fn default() -> Self {
+        DataRetentionConfig {
+            run_time: "03:30".to_string(),
+            batch_size: 500,
+        }
+    }
+}
+
+/// 每晚彙整前一天資料的背景工作設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct NightlyDigestConfig {
+    // 每天執行的時間（各時區組的當地時間，HH:MM；應在午夜之後）
+    pub run_time: String,
+    // 每批處理的使用者數（批次之間讓出執行緒，避免長時間鎖住 SQLite）
+    pub batch_size: i64,
+}
+
+impl Default for NightlyDigestConfig {
+    fn default() -> Self {
+        NightlyDigestConfig {
+            run_time: "00:10".to_string(),
+            batch_size: 50,
+        }
+    }
+}
+
+/// 每日子任務壓縮設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct DailyCompactionConfig {
+    // 每晚彙整時一併壓縮超過期限的每日子任務
+    pub nightly: bool,
+    // task_date 早於此天數的重複性子任務彙總為每月一列後刪除
+    pub cutoff_days: i64,
+}
+
+impl Default for DailyCompactionConfig {
+    fn default() -> Self {
+        DailyCompactionConfig {
+            nightly: false,
+            cutoff_days: 180,
+        }
+    }
+}
+
+/// 背景工作佇列設定（排隊中 + 執行中的工作數上限，超過時直接略過）
+#[derive(Debug, Deserialize, Clone)]
+pub struct BackgroundJobConfig {
+    pub capacity: usize,
+    // 同時執行的工作數
+    pub workers: usize,
+}
+
+impl Default for BackgroundJobConfig {
+    fn default() -> Self {
+        BackgroundJobConfig { capacity: 64, workers: 2 }
+    }
+}
+
+/// 建立任務時自動生成對應成就的設定（使用者另需自行開啟）
+#[derive(Debug, Deserialize, Clone)]
+pub struct AchievementAutogenConfig {
+    // 全站開關
+    pub enabled: bool,
+    // 每位使用者每 N 分鐘最多生成一次，期間只保留最新的任務
+    pub debounce_minutes: i64,
+}
+
+impl Default for AchievementAutogenConfig {
+    fn default() -> Self {
+        AchievementAutogenConfig {
+            enabled: true,
+            debounce_minutes: 10,
+        }
+    }
+}
+
+/// API 版本設定：/api/v2 一律提供，v1（含不帶版本的 /api 別名）可在前端更新後停用
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiVersioningConfig {
+    pub v1_enabled: bool,
+    // 不帶版本的 /api 別名預計停止服務的日期（YYYY-MM-DD），寫入 Sunset 標頭
+    pub v1_sunset: String,
+}
+
+impl Default for ApiVersioningConfig {
+    fn default() -> Self {
+        ApiVersioningConfig {
+            v1_enabled: true,
+            v1_sunset: "2027-06-30".to_string(),
+        }
+    }
+}
+
+/// 任務完成後背景成就檢查的去抖動設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct AchievementCheckConfig {
+    // 使用者最後一次觸發後靜止多少毫秒才執行檢查；連續觸發最多延後 10 倍
+    pub debounce_ms: u64,
+}
+
+impl Default for AchievementCheckConfig {
+    fn default() -> Self {
+        AchievementCheckConfig { debounce_ms: 300 }
+    }
+}
+
+/// 經驗值數值表（只在建立任務、子任務、成就與挑戰時寫入，調整後既有資料不受影響）
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RewardConfig {
+    // 未指定經驗值的每日任務
+    pub daily_task_xp: i32,
+    // 通用子任務模板依序的經驗值（準備、學習基礎、實踐、深入、完成項目、總結）
+    pub subtask_template_xp: Vec<i32>,
+    // AI 未提供經驗值時的成就獎勵
+    pub achievement_default_xp: i32,
+    // AI 生成成就的經驗值必須落在此範圍，否則視為無效
+    pub ai_achievement_xp: XpRange,
+    // 職業主線成就的經驗值限制在此範圍
+    pub career_achievement_xp: XpRange,
+    // 挑戰失敗時扣除的經驗值（0 表示不扣）
+    pub challenge_fail_penalty: i32,
+}
+
+/// 經驗值範圍（含上下限）
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct XpRange {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl XpRange {
+    pub fn contains(&self, xp: i32) -> bool {
+        (self.min..=self.max).contains(&xp)
+    }
+
+    pub fn clamp(&self, xp: i32) -> i32 {
+        xp.clamp(self.min, self.max)
+    }
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        RewardConfig {
+            daily_task_xp: 10,
+            subtask_template_xp: vec![20, 30, 50, 60, 80, 30],
+            achievement_default_xp: 50,
+            ai_achievement_xp: XpRange { min: 50, max: 500 },
+            career_achievement_xp: XpRange { min: 30, max: 100 },
+            challenge_fail_penalty: 0,
+        }
+    }
+}
+
+/// 重複性任務設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct RecurringConfig {
+    // 補記過去日期完成時，最多可往前補記的天數
+    pub catch_up_max_days: i64,
+}
+
+impl Default for RecurringConfig {
+    fn default() -> Self {
+        RecurringConfig { catch_up_max_days: 7 }
+    }
+}
+
+/// 任務完成二次確認設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct TaskConfirmationConfig {
+    // 待確認超過此分鐘數未確認時，自動還原為標記完成前的狀態
+    pub window_minutes: i64,
+}
+
+impl Default for TaskConfirmationConfig {
+    fn default() -> Self {
+        TaskConfirmationConfig { window_minutes: 1440 }
+    }
+}
+
+/// 任務取消設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct TaskCancellationConfig {
+    // 同一任務取消超過此次數後，再取消必須填寫原因（並請教練回顧一次）
+    pub soft_limit: i32,
+}
+
+impl Default for TaskCancellationConfig {
+    fn default() -> Self {
+        TaskCancellationConfig { soft_limit: 2 }
+    }
+}
+
+/// 職業任務生成追蹤設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct CareerTraceConfig {
+    // 保存完整 prompt（含個人資料，僅供除錯；預設只保存雜湊）
+    pub store_prompts: bool,
+    // 每個步驟保存的輸出字元上限
+    pub max_output_chars: usize,
+    // 追蹤紀錄保留天數（由每晚彙整清除）
+    pub retention_days: i64,
+}
+
+impl Default for CareerTraceConfig {
+    fn default() -> Self {
+        CareerTraceConfig {
+            store_prompts: false,
+            max_output_chars: 4000,
+            retention_days: 30,
+        }
+    }
+}
+
+/// 技能升級時對應屬性的成長設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct SkillAttributeConfig {
+    // 每升一級，技能對應的屬性增加幾點（0 表示停用）
+    pub gain_per_level: i32,
+    // 依技能類別覆寫每級的成長點數（例如 technical → 1、soft → 2）
+    pub category_gains: std::collections::HashMap<String, i32>,
+}
+
+impl Default for SkillAttributeConfig {
+    fn default() -> Self {
+        SkillAttributeConfig {
+            gain_per_level: 1,
+            category_gains: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl SkillAttributeConfig {
+    /// 該類別技能每升一級的屬性成長
+    pub fn gain_for(&self, category: Option<&str>) -> i32 {
+        category
+            .and_then(|c| self.category_gains.get(&c.trim().to_lowercase()))
+            .copied()
+            .unwrap_or(self.gain_per_level)
+    }
+}
+
+/// 新帳號的初始遊戲化資料（只在建立 user_profile / user_attributes 時套用，既有使用者不受影響）
+#[derive(Debug, Deserialize, Clone)]
+pub struct NewUserDefaultsConfig {
+    // 六項屬性的初始值
+    pub attribute_baseline: i32,
+    // 依屬性名稱覆寫初始值（例如 intelligence:60,social:40）
+    pub attribute_overrides: std::collections::HashMap<String, i32>,
+    pub level: i32,
+    // 第一級升級所需經驗值
+    pub max_experience: i32,
+    pub title: String,
+    // 註冊時建立的入門任務包名稱（None 表示不建立）
+    pub starter_pack: Option<String>,
+}
+
+impl Default for NewUserDefaultsConfig {
+    fn default() -> Self {
+        NewUserDefaultsConfig {
+            attribute_baseline: 50,
+            attribute_overrides: std::collections::HashMap::new(),
+            level: 1,
+            max_experience: 100,
+            title: "新手冒險者".to_string(),
+            starter_pack: None,
+        }
+    }
+}
+
+impl NewUserDefaultsConfig {
+    /// 指定屬性的初始值（限制在 0–100）
+    pub fn attribute(&self, name: &str) -> i32 {
+        self.attribute_overrides
+            .get(name)
+            .copied()
+            .unwrap_or(self.attribute_baseline)
+            .clamp(0, 100)
+    }
+}
+
+/// 任務類型設定
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TaskTypeConfig {
+    // 內建類型以外允許的自訂類型（小寫英數與底線），未列入的類型在 API 會被拒絕
+    pub custom_types: Vec<String>,
+}
+
+/// 聊天快速模式設定（使用者自行開啟）
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChatFastModeConfig {
+    // 訊息字數不超過此值時略過專家匹配，直接由通用生活教練回答
+    pub skip_expert_max_chars: usize,
+    // 計算 P50 / P95 延遲時保留的最近樣本數（每種路徑各自計算）
+    pub latency_window: usize,
+}
+
+impl Default for ChatFastModeConfig {
+    fn default() -> Self {
+        ChatFastModeConfig {
+            skip_expert_max_chars: 30,
+            latency_window: 200,
+        }
+    }
+}
+
+/// AI 生成內容寫入資料庫前的清理設定
+#[derive(Debug, Deserialize, Clone)]
+pub struct AiSanitizeConfig {
+    // 任務標題 / 成就名稱的字數上限
+    pub title_max_chars: usize,
+    // 描述的字數上限
+    pub description_max_chars: usize,
+    // 要移除的 AI 套話（不分大小寫）
+    pub blocklist: Vec<String>,
+}
+
+impl Default for AiSanitizeConfig {
+    fn default() -> Self {
+        AiSanitizeConfig {
+            title_max_chars: 60,
+            description_max_chars: 500,
+            blocklist: [
+                "As an AI language model",
+                "As an AI assistant",
+                "I'm just an AI",
+                "作為一個AI語言模型",
+                "作為一個 AI 語言模型",
+                "身為AI語言模型",
+                "作為AI助手",
+                "作為一個AI助手",
+                "Here is the JSON",
+                "Here's the JSON",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// 通知中心保留策略：超過保留天數或每位使用者超過上限的通知會被清除
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationCenterConfig {
+    pub retention_days: i64,
+    pub max_per_user: i64,
+}
+
+impl Default for NotificationCenterConfig {
+    fn default() -> Self {
+        NotificationCenterConfig {
+            retention_days: 90,
+            max_per_user: 200,
+        }
+    }
+}
+
+/// 遊戲化用戶資料快取：依使用者保存組合好的結果，超過數量上限時淘汰最久未使用的項目
+#[derive(Debug, Deserialize, Clone)]
+pub struct GamifiedCacheConfig {
+    pub enabled: bool,
+    pub ttl_seconds: u64,
+    pub capacity: usize,
+}
+
+impl Default for GamifiedCacheConfig {
+    fn default() -> Self {
+        GamifiedCacheConfig {
+            enabled: true,
+            ttl_seconds: 30,
+            capacity: 1000,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -34,6 +693,10 @@ pub struct AIConfig {
     pub openai_model: String,
     pub openrouter_api_key: Option<String>,
     pub openrouter_model: String,
+    pub gemini_api_key: Option<String>,
+    pub gemini_model: String,
+    pub ollama_base_url: String,
+    pub ollama_model: String,
 
     // 多步驟任務生成模型配置
     pub outline_model: String,        // 大綱生成模型（輕量快速）
@@ -46,6 +709,17 @@ pub struct AIConfig {
     pub model_normal: String,         // 標準推理模型（任務生成、成就生成）
     pub model_think: String,          // 深度推理模型（複雜規劃、專家分析）
     pub model_background: String,     // 背景處理模型（大量數據分析、批次處理）
+    pub model_vision: Option<String>, // 看圖模型（聊天附圖；未設定時不接受圖片）
+
+    // 單次請求可覆寫的模型（非管理員只能選擇清單中的模型）
+    pub model_allowlist: Vec<String>,
+
+    // 混合路由時各服務提供者的斷路器（連續失敗次數門檻，0 表示停用；開啟後的冷卻秒數）
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown_secs: u64,
+
+    // 同時進行的 AI 呼叫上限（比較教練個性等並行呼叫時使用）
+    pub max_concurrent_calls: usize,
 
     // Token 预算控制
     pub max_prompt_tokens: usize,
@@ -75,12 +749,14 @@ impl Config {
             .unwrap_or(8080);
 
         // CORS 配置 - 讀取允許的來源列表
-        let allowed_origins = env::var("ALLOWED_ORIGINS")
-            .unwrap_or_else(|_| "http://localhost:5173".to_string())
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<String>>();
+        // 支援完全比對與子網域萬用字元，例如 https://*.lifeup.pages.dev
+        let allowed_origins = parse_origin_list(
+            &env::var("ALLOWED_ORIGINS").unwrap_or_else(|_| "http://localhost:5173".to_string()),
+        );
+        let allow_any_origin = env::var("ALLOW_ANY_ORIGIN")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false);
 
         let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string());
         let log_level = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
@@ -91,9 +767,11 @@ impl Config {
         let api_option = match api_option_normalized.as_str() {
             "openai" => "OpenAI".to_string(),
             "openrouter" => "OpenRouter".to_string(),
+            "gemini" => "Gemini".to_string(),
+            "ollama" => "Ollama".to_string(),
             other => {
                 log::warn!(
-                    "未識別的 API_OPTION 值: '{}', 將維持原值。可用選項: OpenAI, OpenRouter",
+                    "未識別的 API_OPTION 值: '{}', 將維持原值。可用選項: OpenAI, OpenRouter, Gemini, Ollama",
                     other
                 );
                 raw_api_option.trim().to_string()
@@ -105,6 +783,13 @@ impl Config {
         let openrouter_api_key = env::var("OPENROUTER_API_KEY").ok();
         let openrouter_model = env::var("OPENROUTER_MODEL")
             .unwrap_or_else(|_| "openrouter.ai/google/gemma-3n-e4b-it".to_string());
+        let gemini_api_key = env::var("GEMINI_API_KEY").ok();
+        let gemini_model = env::var("GEMINI_MODEL")
+            .unwrap_or_else(|_| "gemini-2.0-flash".to_string());
+        let ollama_base_url = env::var("OLLAMA_BASE_URL")
+            .unwrap_or_else(|_| "http://localhost:11434/v1".to_string());
+        let ollama_model = env::var("OLLAMA_MODEL")
+            .unwrap_or_else(|_| "llama3.1".to_string());
 
         // 多步驟任務生成模型配置
         let outline_model = env::var("OUTLINE_MODEL")
@@ -146,6 +831,41 @@ impl Config {
                     .or_else(|_| env::var("OPENROUTER_MODEL"))
                     .unwrap_or_else(|_| "google/gemma-3n-e4b-it".to_string())
             });
+        // Gemini 的模型名稱與其他供應商不通用，未設定 AI_MODEL_* 時改用 GEMINI_MODEL_<等級>，再降級為 GEMINI_MODEL
+        let (model_small, model_fast, model_normal, model_think, model_background) = if api_option == "Gemini" {
+            (
+                gemini_tier_model("SMALL", &gemini_model),
+                gemini_tier_model("FAST", &gemini_model),
+                gemini_tier_model("NORMAL", &gemini_model),
+                gemini_tier_model("THINK", &gemini_model),
+                gemini_tier_model("BACKGROUND", &gemini_model),
+            )
+        } else {
+            (model_small, model_fast, model_normal, model_think, model_background)
+        };
+        let model_vision = env::var("AI_MODEL_VISION")
+            .ok()
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty());
+        let model_allowlist = env::var("AI_MODEL_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect();
+        let circuit_breaker_threshold = env::var("AI_CIRCUIT_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5);
+        let circuit_breaker_cooldown_secs = env::var("AI_CIRCUIT_BREAKER_COOLDOWN_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+        let max_concurrent_calls = env::var("AI_MAX_CONCURRENT_CALLS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(4);
 
         // Token 预算控制
         let max_prompt_tokens = env::var("AI_MAX_PROMPT_TOKENS")
@@ -191,6 +911,541 @@ impl Config {
             .and_then(|v| v.parse().ok())
             .unwrap_or(true);
 
+        // 登入失敗節流配置
+        let throttle_defaults = LoginThrottleConfig::default();
+        let login_throttle = LoginThrottleConfig {
+            max_failures_per_email: env::var("LOGIN_MAX_FAILURES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(throttle_defaults.max_failures_per_email),
+            max_failures_per_ip: env::var("LOGIN_MAX_FAILURES_PER_IP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(throttle_defaults.max_failures_per_ip),
+            window_secs: env::var("LOGIN_FAILURE_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(throttle_defaults.window_secs),
+            lockout_secs: env::var("LOGIN_LOCKOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(throttle_defaults.lockout_secs),
+        };
+
+        // 註冊防濫用配置
+        let registration_defaults = RegistrationGuardConfig::default();
+        let registration_guard = RegistrationGuardConfig {
+            block_disposable_email: env::var("REGISTRATION_BLOCK_DISPOSABLE_EMAIL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(registration_defaults.block_disposable_email),
+            disposable_domains_file: env::var("REGISTRATION_DISPOSABLE_DOMAINS_FILE")
+                .ok()
+                .filter(|path| !path.trim().is_empty()),
+            captcha_provider: env::var("REGISTRATION_CAPTCHA_PROVIDER")
+                .ok()
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .unwrap_or(registration_defaults.captcha_provider),
+            hcaptcha_secret: env::var("HCAPTCHA_SECRET").ok().filter(|s| !s.is_empty()),
+            hcaptcha_verify_url: env::var("HCAPTCHA_VERIFY_URL")
+                .ok()
+                .filter(|url| !url.is_empty())
+                .unwrap_or(registration_defaults.hcaptcha_verify_url),
+            max_registrations_per_ip_per_day: env::var("REGISTRATION_MAX_PER_IP_PER_DAY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(registration_defaults.max_registrations_per_ip_per_day),
+        };
+
+        // AI 每日額度配置
+        let quota_defaults = AiQuotaConfig::default();
+        let ai_quota = AiQuotaConfig {
+            task_generation: env::var("AI_QUOTA_TASK_GENERATION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(quota_defaults.task_generation),
+            career_generation: env::var("AI_QUOTA_CAREER_GENERATION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(quota_defaults.career_generation),
+            achievement_generation: env::var("AI_QUOTA_ACHIEVEMENT_GENERATION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(quota_defaults.achievement_generation),
+            chat: env::var("AI_QUOTA_CHAT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(quota_defaults.chat),
+            personality_compare: env::var("AI_QUOTA_PERSONALITY_COMPARE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(quota_defaults.personality_compare),
+        };
+
+        // 任務附件配置
+        let attachment_defaults = AttachmentConfig::default();
+        let attachments = AttachmentConfig {
+            storage_dir: env::var("ATTACHMENT_STORAGE_DIR").unwrap_or(attachment_defaults.storage_dir),
+            max_size_bytes: env::var("ATTACHMENT_MAX_SIZE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(attachment_defaults.max_size_bytes),
+            allowed_mime_types: env::var("ATTACHMENT_ALLOWED_MIME_TYPES")
+                .map(|v| {
+                    v.split(',')
+                        .map(|m| m.trim().to_lowercase())
+                        .filter(|m| !m.is_empty())
+                        .collect()
+                })
+                .unwrap_or(attachment_defaults.allowed_mime_types),
+            chat_image_max_bytes: env::var("CHAT_IMAGE_MAX_SIZE_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(attachment_defaults.chat_image_max_bytes),
+            chat_image_mime_types: env::var("CHAT_IMAGE_ALLOWED_MIME_TYPES")
+                .map(|v| {
+                    v.split(',')
+                        .map(|m| m.trim().to_lowercase())
+                        .filter(|m| !m.is_empty())
+                        .collect()
+                })
+                .unwrap_or(attachment_defaults.chat_image_mime_types),
+        };
+
+        // 專注時段配置
+        let focus_defaults = FocusConfig::default();
+        let focus = FocusConfig {
+            experience_per_session: env::var("FOCUS_SESSION_EXPERIENCE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(focus_defaults.experience_per_session),
+            default_duration_minutes: env::var("FOCUS_DEFAULT_DURATION_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(focus_defaults.default_duration_minutes),
+            max_duration_minutes: env::var("FOCUS_MAX_DURATION_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(focus_defaults.max_duration_minutes),
+        };
+
+        // 每日三任務配置
+        let daily_quest_defaults = DailyQuestConfig::default();
+        let daily_quests = DailyQuestConfig {
+            bonus_experience: env::var("DAILY_QUEST_BONUS_EXPERIENCE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(daily_quest_defaults.bonus_experience),
+            max_rerolls: env::var("DAILY_QUEST_MAX_REROLLS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(daily_quest_defaults.max_rerolls),
+        };
+
+        // 慢查詢日誌配置
+        let slow_log_defaults = SlowLogConfig::default();
+        let slow_log = SlowLogConfig {
+            sql_threshold_ms: env::var("SLOW_SQL_THRESHOLD_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(slow_log_defaults.sql_threshold_ms),
+            request_threshold_ms: env::var("SLOW_REQUEST_THRESHOLD_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(slow_log_defaults.request_threshold_ms),
+        };
+
+        // 連續紀錄中斷提醒配置
+        let streak_reminder_defaults = StreakReminderConfig::default();
+        let streak_reminder = StreakReminderConfig {
+            min_streak: env::var("STREAK_REMINDER_MIN_STREAK")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(streak_reminder_defaults.min_streak),
+            minutes_before_evening: env::var("STREAK_REMINDER_MINUTES_BEFORE_EVENING")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(streak_reminder_defaults.minutes_before_evening),
+        };
+
+        // 通知中心保留策略配置
+        let notification_center_defaults = NotificationCenterConfig::default();
+        let notification_center = NotificationCenterConfig {
+            retention_days: env::var("NOTIFICATION_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(notification_center_defaults.retention_days),
+            max_per_user: env::var("NOTIFICATION_MAX_PER_USER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(notification_center_defaults.max_per_user),
+        };
+
+        // 遊戲化用戶資料快取配置
+        let gamified_cache_defaults = GamifiedCacheConfig::default();
+        let gamified_cache = GamifiedCacheConfig {
+            enabled: env::var("GAMIFIED_CACHE_ENABLED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(gamified_cache_defaults.enabled),
+            ttl_seconds: env::var("GAMIFIED_CACHE_TTL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(gamified_cache_defaults.ttl_seconds),
+            capacity: env::var("GAMIFIED_CACHE_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(gamified_cache_defaults.capacity),
+        };
+
+        // 教練主動關心配置
+        let coach_checkin_defaults = CoachCheckinConfig::default();
+        let coach_checkin = CoachCheckinConfig {
+            run_time: env::var("COACH_CHECKIN_TIME")
+                .ok()
+                .filter(|v| chrono::NaiveTime::parse_from_str(v.trim(), "%H:%M").is_ok())
+                .map(|v| v.trim().to_string())
+                .unwrap_or(coach_checkin_defaults.run_time),
+            default_frequency_days: env::var("COACH_CHECKIN_FREQUENCY_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|days: &i64| *days > 0)
+                .unwrap_or(coach_checkin_defaults.default_frequency_days),
+            min_weekly_tasks: env::var("COACH_CHECKIN_MIN_WEEKLY_TASKS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(coach_checkin_defaults.min_weekly_tasks),
+            completion_drop: env::var("COACH_CHECKIN_COMPLETION_DROP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(coach_checkin_defaults.completion_drop),
+            min_broken_streak: env::var("COACH_CHECKIN_MIN_BROKEN_STREAK")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(coach_checkin_defaults.min_broken_streak),
+        };
+
+        // 資料保留清除配置
+        let data_retention_defaults = DataRetentionConfig::default();
+        let data_retention = DataRetentionConfig {
+            run_time: env::var("DATA_RETENTION_RUN_TIME")
+                .ok()
+                .filter(|v| chrono::NaiveTime::parse_from_str(v.trim(), "%H:%M").is_ok())
+                .map(|v| v.trim().to_string())
+                .unwrap_or(data_retention_defaults.run_time),
+            batch_size: env::var("DATA_RETENTION_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|size: &i64| *size > 0)
+                .unwrap_or(data_retention_defaults.batch_size),
+        };
+
+        // 每晚彙整配置
+        let nightly_digest_defaults = NightlyDigestConfig::default();
+        let nightly_digest = NightlyDigestConfig {
+            run_time: env::var("NIGHTLY_DIGEST_RUN_TIME")
+                .ok()
+                .filter(|v| chrono::NaiveTime::parse_from_str(v.trim(), "%H:%M").is_ok())
+                .map(|v| v.trim().to_string())
+                .unwrap_or(nightly_digest_defaults.run_time),
+            batch_size: env::var("NIGHTLY_DIGEST_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|size: &i64| *size > 0)
+                .unwrap_or(nightly_digest_defaults.batch_size),
+        };
+
+        // 每日子任務壓縮配置
+        let daily_compaction_defaults = DailyCompactionConfig::default();
+        let daily_compaction = DailyCompactionConfig {
+            nightly: env::var("DAILY_COMPACTION_NIGHTLY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(daily_compaction_defaults.nightly),
+            cutoff_days: env::var("DAILY_COMPACTION_CUTOFF_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|days: &i64| *days > 0)
+                .unwrap_or(daily_compaction_defaults.cutoff_days),
+        };
+
+        // 背景工作佇列配置
+        let background_job_defaults = BackgroundJobConfig::default();
+        let background_jobs = BackgroundJobConfig {
+            capacity: env::var("BACKGROUND_JOB_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n: &usize| *n > 0)
+                .unwrap_or(background_job_defaults.capacity),
+            workers: env::var("BACKGROUND_JOB_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n: &usize| *n > 0)
+                .unwrap_or(background_job_defaults.workers),
+        };
+
+        // 自動生成任務成就配置
+        let achievement_autogen_defaults = AchievementAutogenConfig::default();
+        let achievement_autogen = AchievementAutogenConfig {
+            enabled: env::var("ACHIEVEMENT_AUTOGEN_ENABLED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(achievement_autogen_defaults.enabled),
+            debounce_minutes: env::var("ACHIEVEMENT_AUTOGEN_DEBOUNCE_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|minutes: &i64| *minutes >= 0)
+                .unwrap_or(achievement_autogen_defaults.debounce_minutes),
+        };
+
+        // 背景成就檢查去抖動配置
+        let achievement_check = AchievementCheckConfig {
+            debounce_ms: env::var("ACHIEVEMENT_CHECK_DEBOUNCE_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(AchievementCheckConfig::default().debounce_ms),
+        };
+
+        // 經驗值數值表（挑戰失敗扣除沿用舊的 CHALLENGE_FAILURE_XP_PENALTY 作為備援）
+        let reward_defaults = RewardConfig::default();
+        let reward = RewardConfig {
+            daily_task_xp: env::var("REWARD_DAILY_TASK_XP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|xp: &i32| *xp >= 0)
+                .unwrap_or(reward_defaults.daily_task_xp),
+            subtask_template_xp: env::var("REWARD_SUBTASK_TEMPLATE_XP")
+                .ok()
+                .and_then(|v| parse_xp_list(&v))
+                .filter(|list| list.len() == reward_defaults.subtask_template_xp.len())
+                .unwrap_or(reward_defaults.subtask_template_xp),
+            achievement_default_xp: env::var("REWARD_ACHIEVEMENT_DEFAULT_XP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|xp: &i32| *xp >= 0)
+                .unwrap_or(reward_defaults.achievement_default_xp),
+            ai_achievement_xp: env::var("REWARD_AI_ACHIEVEMENT_XP_RANGE")
+                .ok()
+                .and_then(|v| parse_xp_range(&v))
+                .unwrap_or(reward_defaults.ai_achievement_xp),
+            career_achievement_xp: env::var("REWARD_CAREER_ACHIEVEMENT_XP_RANGE")
+                .ok()
+                .and_then(|v| parse_xp_range(&v))
+                .unwrap_or(reward_defaults.career_achievement_xp),
+            challenge_fail_penalty: env::var("REWARD_CHALLENGE_FAIL_PENALTY")
+                .or_else(|_| env::var("CHALLENGE_FAILURE_XP_PENALTY"))
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|penalty: &i32| *penalty >= 0)
+                .unwrap_or(reward_defaults.challenge_fail_penalty),
+        };
+
+        // 重複性任務配置
+        let recurring = RecurringConfig {
+            catch_up_max_days: env::var("RECURRING_CATCH_UP_MAX_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|days: &i64| *days > 0)
+                .unwrap_or(RecurringConfig::default().catch_up_max_days),
+        };
+
+        // 任務完成二次確認配置
+        let task_confirmation = TaskConfirmationConfig {
+            window_minutes: env::var("TASK_CONFIRMATION_WINDOW_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|minutes: &i64| *minutes > 0)
+                .unwrap_or(TaskConfirmationConfig::default().window_minutes),
+        };
+
+        // 任務取消配置
+        let task_cancellation = TaskCancellationConfig {
+            soft_limit: env::var("TASK_CANCEL_SOFT_LIMIT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|limit: &i32| *limit >= 0)
+                .unwrap_or(TaskCancellationConfig::default().soft_limit),
+        };
+
+        // 技能升級屬性成長配置
+        let skill_attribute_defaults = SkillAttributeConfig::default();
+        let skill_attribute = SkillAttributeConfig {
+            gain_per_level: env::var("SKILL_ATTRIBUTE_GAIN_PER_LEVEL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(skill_attribute_defaults.gain_per_level),
+            category_gains: env::var("SKILL_ATTRIBUTE_GAIN_BY_CATEGORY")
+                .map(|v| parse_category_gains(&v))
+                .unwrap_or(skill_attribute_defaults.category_gains),
+        };
+
+        // 新帳號初始資料配置
+        let new_user_defaults_base = NewUserDefaultsConfig::default();
+        let new_user_defaults = NewUserDefaultsConfig {
+            attribute_baseline: env::var("NEW_USER_ATTRIBUTE_BASELINE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(new_user_defaults_base.attribute_baseline),
+            attribute_overrides: env::var("NEW_USER_ATTRIBUTES")
+                .map(|v| parse_category_gains(&v))
+                .unwrap_or(new_user_defaults_base.attribute_overrides),
+            level: env::var("NEW_USER_LEVEL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|level: &i32| *level >= 1)
+                .unwrap_or(new_user_defaults_base.level),
+            max_experience: env::var("NEW_USER_MAX_EXPERIENCE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|xp: &i32| *xp > 0)
+                .unwrap_or(new_user_defaults_base.max_experience),
+            title: env::var("NEW_USER_TITLE")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .unwrap_or(new_user_defaults_base.title),
+            starter_pack: env::var("NEW_USER_STARTER_PACK")
+                .ok()
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty()),
+        };
+
+        // 任務類型配置
+        let task_types = TaskTypeConfig {
+            custom_types: env::var("CUSTOM_TASK_TYPES")
+                .map(|v| parse_origin_list(&v).into_iter().map(|t| t.to_lowercase()).collect())
+                .unwrap_or_default(),
+        };
+
+        // 職業任務生成追蹤配置
+        let career_trace_defaults = CareerTraceConfig::default();
+        let career_trace = CareerTraceConfig {
+            store_prompts: env::var("CAREER_TRACE_STORE_PROMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(career_trace_defaults.store_prompts),
+            max_output_chars: env::var("CAREER_TRACE_MAX_OUTPUT_CHARS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|chars: &usize| *chars > 0)
+                .unwrap_or(career_trace_defaults.max_output_chars),
+            retention_days: env::var("CAREER_TRACE_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|days: &i64| *days > 0)
+                .unwrap_or(career_trace_defaults.retention_days),
+        };
+
+        // 聊天快速模式配置
+        let chat_fast_mode_defaults = ChatFastModeConfig::default();
+        let chat_fast_mode = ChatFastModeConfig {
+            skip_expert_max_chars: env::var("CHAT_FAST_SKIP_EXPERT_MAX_CHARS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(chat_fast_mode_defaults.skip_expert_max_chars),
+            latency_window: env::var("CHAT_LATENCY_WINDOW")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n: &usize| *n > 0)
+                .unwrap_or(chat_fast_mode_defaults.latency_window),
+        };
+
+        // AI 生成內容清理配置
+        let ai_sanitize_defaults = AiSanitizeConfig::default();
+        let ai_sanitize = AiSanitizeConfig {
+            title_max_chars: env::var("AI_TITLE_MAX_CHARS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n: &usize| *n > 0)
+                .unwrap_or(ai_sanitize_defaults.title_max_chars),
+            description_max_chars: env::var("AI_DESCRIPTION_MAX_CHARS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n: &usize| *n > 0)
+                .unwrap_or(ai_sanitize_defaults.description_max_chars),
+            blocklist: env::var("AI_BOILERPLATE_BLOCKLIST")
+                .ok()
+                .map(|raw| raw.split('|').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or(ai_sanitize_defaults.blocklist),
+        };
+
+        let log_format = env::var("LOG_FORMAT")
+            .ok()
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| v == "json" || v == "text")
+            .unwrap_or_else(|| "text".to_string());
+
+        // 日誌檔案配置
+        let log_file_defaults = LogFileConfig::default();
+        let log_file = LogFileConfig {
+            dir: env::var("LOG_DIR")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or(log_file_defaults.dir),
+            max_size_mb: env::var("LOG_MAX_SIZE_MB")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(log_file_defaults.max_size_mb),
+            rotate_interval_hours: env::var("LOG_ROTATE_INTERVAL_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(log_file_defaults.rotate_interval_hours),
+            keep_archives: env::var("LOG_KEEP_ARCHIVES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(log_file_defaults.keep_archives),
+            gzip: env::var("LOG_GZIP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(log_file_defaults.gzip),
+        };
+
+        // 舊版回應欄位相容（前端全面改用 data 後關閉）
+        let legacy_response_fields = env::var("API_LEGACY_RESPONSE_FIELDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true);
+        let legacy_expert_prefix = env::var("CHAT_LEGACY_EXPERT_PREFIX")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(true);
+
+        // 使用者預設時區（task_date 與每日統計的日期界線）
+        let default_timezone = env::var("DEFAULT_USER_TIMEZONE").unwrap_or_else(|_| "+08:00".to_string());
+
+        // API 版本配置
+        let api_versioning_defaults = ApiVersioningConfig::default();
+        let api_versioning = ApiVersioningConfig {
+            v1_enabled: env::var("API_V1_ENABLED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(api_versioning_defaults.v1_enabled),
+            v1_sunset: env::var("API_V1_SUNSET")
+                .ok()
+                .filter(|v| chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").is_ok())
+                .unwrap_or(api_versioning_defaults.v1_sunset),
+        };
+        let mail = MailConfig {
+            provider: env::var("MAIL_PROVIDER").unwrap_or_else(|_| "log".to_string()),
+            smtp_host: env::var("SMTP_HOST").ok().filter(|v| !v.is_empty()),
+            smtp_port: env::var("SMTP_PORT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(587),
+            smtp_starttls: env::var("SMTP_STARTTLS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+            smtp_username: env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty()),
+            smtp_password: env::var("SMTP_PASSWORD").ok().filter(|v| !v.is_empty()),
+            from_address: env::var("MAIL_FROM").unwrap_or_else(|_| "LifeUp <noreply@lifeup.app>".to_string()),
+            max_attempts: env::var("MAIL_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+        };
+
         // 調試日誌 - 注意：此時日誌系統可能還未初始化
         // 這些日誌會在 main.rs 中重新顯示
 
@@ -200,16 +1455,23 @@ impl Config {
                 host: server_host,
                 port: server_port,
                 allowed_origins,
+                allow_any_origin,
             },
             app: AppConfig {
                 environment,
                 log_level,
+                log_format,
+                log_file,
                 ai: AIConfig {
                     api_option,
                     openai_api_key,
                     openai_model,
                     openrouter_api_key,
                     openrouter_model,
+                    gemini_api_key,
+                    gemini_model,
+                    ollama_base_url,
+                    ollama_model,
                     outline_model,
                     detail_model,
                     resource_model,
@@ -218,6 +1480,11 @@ impl Config {
                     model_normal,
                     model_think,
                     model_background,
+                    model_vision,
+                    model_allowlist,
+                    circuit_breaker_threshold,
+                    circuit_breaker_cooldown_secs,
+                    max_concurrent_calls,
                     max_prompt_tokens,
                     max_completion_tokens,
                     recent_tasks_sample_size,
@@ -228,6 +1495,38 @@ impl Config {
                     enable_milestone_detection,
                     enable_streak_analysis,
                 },
+                login_throttle,
+                registration_guard,
+                ai_quota,
+                mail,
+                attachments,
+                focus,
+                daily_quests,
+                slow_log,
+                streak_reminder,
+                notification_center,
+                gamified_cache,
+                coach_checkin,
+                data_retention,
+                reward,
+                recurring,
+                chat_fast_mode,
+                ai_sanitize,
+                nightly_digest,
+                daily_compaction,
+                background_jobs,
+                achievement_autogen,
+                achievement_check,
+                task_confirmation,
+                task_cancellation,
+                career_trace,
+                skill_attribute,
+                new_user_defaults,
+                task_types,
+                legacy_response_fields,
+                legacy_expert_prefix,
+                api_versioning,
+                default_timezone,
             },
         }
     }
@@ -235,4 +1534,45 @@ impl Config {
     pub fn server_addr(&self) -> String {
         format!("{}:{}", self.server.host, self.server.port)
     }
-} 
\ No newline at end of file
+} 
+
+/// Gemini 等級模型：AI_MODEL_<等級> -> GEMINI_MODEL_<等級> -> GEMINI_MODEL
+fn gemini_tier_model(tier: &str, gemini_model: &str) -> String {
+    env::var(format!("AI_MODEL_{}", tier))
+        .or_else(|_| env::var(format!("GEMINI_MODEL_{}", tier)))
+        .unwrap_or_else(|_| gemini_model.to_string())
+}
+
+/// 解析「類別:點數」的逗號分隔列表（例如 technical:1,soft:2），略過格式錯誤的項目
+pub fn parse_category_gains(raw: &str) -> std::collections::HashMap<String, i32> {
+    raw.split(',')
+        .filter_map(|item| {
+            let (category, gain) = item.split_once(':')?;
+            let category = category.trim().to_lowercase();
+            let gain = gain.trim().parse().ok()?;
+            (!category.is_empty()).then_some((category, gain))
+        })
+        .collect()
+}
+
+/// 解析以逗號分隔的經驗值列表（例如 20,30,50），任一項不是非負整數時回傳 None
+pub fn parse_xp_list(raw: &str) -> Option<Vec<i32>> {
+    raw.split(',')
+        .map(|item| item.trim().parse().ok().filter(|xp: &i32| *xp >= 0))
+        .collect()
+}
+
+/// 解析「下限-上限」的經驗值範圍（例如 50-500），下限大於上限時回傳 None
+pub fn parse_xp_range(raw: &str) -> Option<XpRange> {
+    let (min, max) = raw.split_once('-')?;
+    let range = XpRange { min: min.trim().parse().ok()?, max: max.trim().parse().ok()? };
+    (0 <= range.min && range.min <= range.max).then_some(range)
+}
+
+/// 解析以逗號分隔的 CORS 來源列表
+pub fn parse_origin_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
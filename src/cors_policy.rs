@@ -0,0 +1,303 @@
+use std::sync::{OnceLock, RwLock};
+use actix_cors::Cors;
+use actix_web::{HttpRequest, HttpResponse, Result};
+use serde::Serialize;
+
+use crate::ai_tasks::ApiResponse;
+
+const ALLOWED_ORIGINS_KEY: &str = "ALLOWED_ORIGINS";
+
+/// 允許的來源：完全比對，或 scheme://*.domain 形式的子網域萬用字元
+#[derive(Debug, Clone, PartialEq)]
+pub enum OriginPattern {
+    Exact(String),
+    // scheme 為 "https://"，suffix 為 ".lifeup.pages.dev"（含連接埠時一併比對）
+    Wildcard { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    /// 解析並驗證設定中的來源
+    pub fn parse(raw: &str) -> std::result::Result<Self, String> {
+        let origin = raw.trim().to_ascii_lowercase();
+        if origin == "*" {
+            return Err("不支援單獨的 *，開發環境請改用 ALLOW_ANY_ORIGIN=true".to_string());
+        }
+        let Some(scheme_end) = origin.find("://") else {
+            return Err(format!("來源缺少 scheme: {}", raw));
+        };
+        let (scheme, host) = origin.split_at(scheme_end + 3);
+        if scheme != "http://" && scheme != "https://" {
+            return Err(format!("來源 scheme 只能是 http 或 https: {}", raw));
+        }
+        if host.is_empty() || host.contains(['/', '?', '#', '@']) {
+            return Err(format!("來源只能包含 scheme、主機與連接埠（不可有路徑或結尾斜線）: {}", raw));
+        }
+
+        if let Some(suffix) = host.strip_prefix('*') {
+            if !suffix.starts_with('.') || suffix.len() < 2 || suffix.contains('*') {
+                return Err(format!("萬用字元只能用於最左側的子網域，例如 https://*.example.com: {}", raw));
+            }
+            return Ok(OriginPattern::Wildcard { scheme: scheme.to_string(), suffix: suffix.to_string() });
+        }
+        if host.contains('*') {
+            return Err(format!("萬用字元只能用於最左側的子網域，例如 https://*.example.com: {}", raw));
+        }
+        Ok(OriginPattern::Exact(origin))
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        match self {
+            OriginPattern::Exact(expected) => origin == *expected,
+            OriginPattern::Wildcard { scheme, suffix } => {
+                let Some(host) = origin.strip_prefix(scheme.as_str()) else {
+                    return false;
+                };
+                let Some(subdomain) = host.strip_suffix(suffix.as_str()) else {
+                    return false;
+                };
+                // 子網域至少一層，且只能包含合法的主機名稱字元
+                !subdomain.is_empty()
+                    && !subdomain.starts_with('.')
+                    && !subdomain.ends_with('.')
+                    && subdomain.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            }
+        }
+    }
+}
+
+/// 目前生效的 CORS 設定
+#[derive(Debug, Clone, Default)]
+pub struct CorsPolicy {
+    pub origins: Vec<String>,
+    pub patterns: Vec<OriginPattern>,
+    // 開發用：允許任何來源（仍回傳請求的 Origin 而非 *，以符合 credentials 規則）
+    pub allow_any: bool,
+}
+
+impl CorsPolicy {
+    /// 由設定的來源列表建立；回傳無效的項目供呼叫端記錄或拒絕
+    pub fn from_origins(origins: &[String], allow_any: bool) -> (Self, Vec<String>) {
+        let mut patterns = Vec::new();
+        let mut valid = Vec::new();
+        let mut errors = Vec::new();
+        for origin in origins {
+            match OriginPattern::parse(origin) {
+                Ok(pattern) => {
+                    patterns.push(pattern);
+                    valid.push(origin.clone());
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        (CorsPolicy { origins: valid, patterns, allow_any }, errors)
+    }
+
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.allow_any || self.patterns.iter().any(|p| p.matches(origin))
+    }
+}
+
+struct CorsState {
+    policy: CorsPolicy,
+    // 重新載入時讀取的 .env 檔案
+    env_file: String,
+}
+
+static CORS_STATE: OnceLock<RwLock<CorsState>> = OnceLock::new();
+
+/// 啟動時初始化 CORS 設定；無效的來源會記錄錯誤並略過
+pub fn init(origins: &[String], allow_any: bool, is_production: bool, env_file: &str) {
+    let allow_any = if allow_any && is_production {
+        log::warn!("生產環境忽略 ALLOW_ANY_ORIGIN，只允許設定的來源");
+        false
+    } else {
+        allow_any
+    };
+
+    let (policy, errors) = CorsPolicy::from_origins(origins, allow_any);
+    for error in &errors {
+        log::error!("CORS 來源設定無效，已略過: {}", error);
+    }
+    if policy.allow_any {
+        log::warn!("ALLOW_ANY_ORIGIN 已啟用：允許任何來源（僅供開發使用）");
+    }
+    log::info!("允許的 CORS 來源: {:?}", policy.origins);
+
+    let state = CorsState { policy, env_file: env_file.to_string() };
+    if let Err(existing) = CORS_STATE.set(RwLock::new(state)) {
+        // 重複初始化時以新的設定覆蓋
+        if let (Some(lock), Ok(state)) = (CORS_STATE.get(), existing.into_inner()) {
+            if let Ok(mut guard) = lock.write() {
+                *guard = state;
+            }
+        }
+    }
+}
+
+/// 檢查來源是否允許；拒絕時以 debug 等級記錄，方便排查設定問題
+pub fn is_origin_allowed(origin: &str) -> bool {
+    let allowed = CORS_STATE
+        .get()
+        .and_then(|lock| lock.read().ok().map(|state| state.policy.is_allowed(origin)))
+        .unwrap_or(false);
+    if !allowed {
+        log::debug!("CORS 拒絕來源: {}", origin);
+    }
+    allowed
+}
+
+/// 建立 CORS 中介層（HTTP 與 HTTPS 伺服器共用）
+pub fn build_cors() -> Cors {
+    Cors::default()
+        .allowed_origin_fn(|origin, _req_head| origin.to_str().map(is_origin_allowed).unwrap_or(false))
+        .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"])
+        .allowed_headers(vec![
+            actix_web::http::header::AUTHORIZATION,
+            actix_web::http::header::ACCEPT,
+            actix_web::http::header::CONTENT_TYPE,
+            actix_web::http::header::HeaderName::from_static("x-requested-with"),
+            actix_web::http::header::HeaderName::from_static("idempotency-key"),
+        ])
+        .expose_headers(vec![actix_web::http::header::CONTENT_TYPE])
+        .supports_credentials()
+        .max_age(3600)
+}
+
+/// 從 .env 檔案讀取 ALLOWED_ORIGINS；檔案中沒有時沿用啟動時的環境變數
+fn read_allowed_origins(env_file: &str) -> Vec<String> {
+    // 啟動時已由 dotenv 寫入環境變數且不會被覆蓋，因此直接讀取檔案內容
+    let from_file = std::fs::read_to_string(env_file).ok().and_then(|content| {
+        content.lines().find_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            (key.trim() == ALLOWED_ORIGINS_KEY).then(|| value.trim().trim_matches('"').to_string())
+        })
+    });
+    let raw = from_file
+        .or_else(|| std::env::var(ALLOWED_ORIGINS_KEY).ok())
+        .unwrap_or_default();
+    crate::config::parse_origin_list(&raw)
+}
+
+#[derive(Serialize)]
+pub struct CorsPolicyResponse {
+    pub origins: Vec<String>,
+    pub allow_any: bool,
+}
+
+fn current_policy_response() -> CorsPolicyResponse {
+    let policy = CORS_STATE
+        .get()
+        .and_then(|lock| lock.read().ok().map(|state| state.policy.clone()))
+        .unwrap_or_default();
+    CorsPolicyResponse { origins: policy.origins, allow_any: policy.allow_any }
+}
+
+fn admin_forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "需要管理員權限".to_string(),
+    })
+}
+
+/// 查看目前的 CORS 設定
+pub async fn get_cors_origins(http_req: HttpRequest) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(current_policy_response()),
+        message: "獲取 CORS 設定成功".to_string(),
+    }))
+}
+
+/// 重新讀取 .env 的 ALLOWED_ORIGINS，不需重啟伺服器；有無效項目時保留原設定
+pub async fn reload_cors_origins(http_req: HttpRequest) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+    let Some(lock) = CORS_STATE.get() else {
+        return Ok(HttpResponse::ServiceUnavailable().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "CORS 設定尚未初始化".to_string(),
+        }));
+    };
+
+    let env_file = lock.read().map(|state| state.env_file.clone()).unwrap_or_default();
+    let origins = read_allowed_origins(&env_file);
+    let allow_any = lock.read().map(|state| state.policy.allow_any).unwrap_or(false);
+    let (policy, errors) = CorsPolicy::from_origins(&origins, allow_any);
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse {
+            success: false,
+            data: Some(errors),
+            message: "CORS 來源設定無效，保留原設定".to_string(),
+        }));
+    }
+
+    match lock.write() {
+        Ok(mut state) => {
+            log::info!("CORS 來源已重新載入: {:?}", policy.origins);
+            state.policy = policy;
+        }
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "更新 CORS 設定失敗".to_string(),
+            }));
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(current_policy_response()),
+        message: "CORS 來源已重新載入".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_origin_still_matches() {
+        let pattern = OriginPattern::parse("http://localhost:5173").unwrap();
+        assert!(pattern.matches("http://localhost:5173"));
+        assert!(!pattern.matches("http://localhost:5174"));
+        assert!(!pattern.matches("https://localhost:5173"));
+    }
+
+    #[test]
+    fn test_wildcard_matches_subdomains_only() {
+        let pattern = OriginPattern::parse("https://*.lifeup.pages.dev").unwrap();
+        assert!(pattern.matches("https://pr-123.lifeup.pages.dev"));
+        assert!(pattern.matches("https://a.b.lifeup.pages.dev"));
+        assert!(!pattern.matches("https://lifeup.pages.dev"));
+        assert!(!pattern.matches("http://pr-123.lifeup.pages.dev"));
+        assert!(!pattern.matches("https://evil.com/.lifeup.pages.dev"));
+        assert!(!pattern.matches("https://pr-123.lifeup.pages.dev.evil.com"));
+    }
+
+    #[test]
+    fn test_invalid_origins_are_rejected() {
+        assert!(OriginPattern::parse("*").is_err());
+        assert!(OriginPattern::parse("lifeup.app").is_err());
+        assert!(OriginPattern::parse("https://lifeup.app/").is_err());
+        assert!(OriginPattern::parse("https://app.*.pages.dev").is_err());
+
+        let origins = vec!["https://lifeup.app".to_string(), "ftp://lifeup.app".to_string()];
+        let (policy, errors) = CorsPolicy::from_origins(&origins, false);
+        assert_eq!(policy.origins, vec!["https://lifeup.app".to_string()]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_allow_any_accepts_every_origin() {
+        let (policy, _) = CorsPolicy::from_origins(&[], true);
+        assert!(policy.is_allowed("http://192.168.0.10:5173"));
+    }
+}
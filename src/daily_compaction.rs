@@ -0,0 +1,523 @@
+// 每日子任務壓縮：重複性任務超過期限的每日子任務彙總為每月一列後刪除原始資料
+//
+// 彙總以位元遮罩保留當月哪幾天有完成，並累計子任務數、完成數與經驗值，
+// 重複性任務進度（completion_rate）、習慣統計的連續紀錄與父任務經驗值在壓縮前後一致。
+// 有附件、留言、待確認完成或下層子任務的子任務保留原始資料。
+// 每個（任務, 月份）在一個交易中寫入彙總並刪除原始資料，批次之間讓出執行緒，避免長時間持有 SQLite 寫入鎖。
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rbatis::executor::Executor;
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::DailyCompactionConfig;
+use crate::models::TaskStatus;
+
+// 最短保留天數（另須超過補記上限，補記的日期一定還有原始資料）
+pub const MIN_CUTOFF_DAYS: i64 = 30;
+
+// 可壓縮的子任務（別名 s）
+const COMPACTABLE: &str = "NOT EXISTS (SELECT 1 FROM task_attachment a WHERE a.task_id = s.id)
+       AND NOT EXISTS (SELECT 1 FROM task_comment c WHERE c.task_id = s.id)
+       AND NOT EXISTS (SELECT 1 FROM task_pending_confirmation pc WHERE pc.task_id = s.id)
+       AND NOT EXISTS (SELECT 1 FROM task child WHERE child.parent_task_id = s.id)";
+
+// 單一（任務, 月份）中早於 cutoff 的子任務；參數依序為 parent_task_id、月份、cutoff
+const MONTH_SUBTASKS: &str = "s.parent_task_id = ? AND substr(s.task_date, 1, 7) = ? AND s.task_date < ?";
+
+static DAILY_COMPACTION_CONFIG: OnceLock<DailyCompactionConfig> = OnceLock::new();
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 啟動時套用設定
+pub fn init(config: DailyCompactionConfig) {
+    let cutoff_days = config.cutoff_days.max(min_cutoff_days());
+    if cutoff_days != config.cutoff_days {
+        log::warn!("每日子任務壓縮天數 {} 過短，改為 {} 天", config.cutoff_days, cutoff_days);
+    }
+    log::info!(
+        "每日子任務壓縮: 保留 {} 天，{}",
+        cutoff_days,
+        if config.nightly { "每晚彙整時執行" } else { "僅管理員手動執行" }
+    );
+    if DAILY_COMPACTION_CONFIG.set(DailyCompactionConfig { cutoff_days, ..config }).is_err() {
+        log::warn!("每日子任務壓縮設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static DailyCompactionConfig {
+    DAILY_COMPACTION_CONFIG.get_or_init(DailyCompactionConfig::default)
+}
+
+fn min_cutoff_days() -> i64 {
+    MIN_CUTOFF_DAYS.max(crate::recurring_progress::catch_up_max_days() + 1)
+}
+
+/// 每晚彙整時是否一併壓縮
+pub fn nightly_enabled() -> bool {
+    config().nightly
+}
+
+pub fn default_cutoff_days() -> i64 {
+    config().cutoff_days
+}
+
+/// 一個月份的彙總
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MonthRollup {
+    pub subtask_count: i64,
+    pub completed_count: i64,
+    // 第 n 天有完成時設定第 n - 1 位元
+    pub completed_day_mask: i64,
+    pub experience_total: i64,
+}
+
+impl MonthRollup {
+    pub fn add(&mut self, date: NaiveDate, completed: bool, experience: i64) {
+        self.subtask_count += 1;
+        self.experience_total += experience;
+        if completed {
+            self.completed_count += 1;
+            self.completed_day_mask |= 1 << date.day0();
+        }
+    }
+}
+
+/// 彙總中有完成的日期
+pub fn mask_dates(month: &str, mask: i64) -> Vec<NaiveDate> {
+    let Ok(first) = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d") else {
+        return Vec::new();
+    };
+    (0..31)
+        .filter(|day| mask & (1 << day) != 0)
+        .filter_map(|day| first.with_day0(day))
+        .collect()
+}
+
+/// 已壓縮的子任務中有完成的日期
+pub async fn archived_completed_dates(rb: &RBatis, parent_task_id: &str) -> std::result::Result<HashSet<NaiveDate>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT month, completed_day_mask FROM daily_task_summary WHERE parent_task_id = ?",
+            vec![Value::String(parent_task_id.to_string())],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| Some((row["month"].as_str()?, row["completed_day_mask"].as_i64()?)))
+        .flat_map(|(month, mask)| mask_dates(month, mask))
+        .collect())
+}
+
+/// 已壓縮的子任務經驗值總和（父任務經驗值為所有子任務的總和）
+pub async fn archived_experience(rb: &dyn Executor, parent_task_id: &str) -> std::result::Result<i32, rbatis::Error> {
+    let rows = rb
+        .query(
+            "SELECT COALESCE(SUM(experience_total), 0) AS total FROM daily_task_summary WHERE parent_task_id = ?",
+            vec![Value::String(parent_task_id.to_string())],
+        )
+        .await?;
+    Ok(rows[0]["total"].as_i64().unwrap_or(0) as i32)
+}
+
+/// 刪除任務時一併刪除它的彙總
+pub async fn delete_summaries(rb: &RBatis, parent_task_id: &str) -> std::result::Result<u64, rbatis::Error> {
+    Ok(rb
+        .exec(
+            "DELETE FROM daily_task_summary WHERE parent_task_id = ?",
+            vec![Value::String(parent_task_id.to_string())],
+        )
+        .await?
+        .rows_affected)
+}
+
+/// 一次壓縮的結果（dry_run 時為預計影響的數量）
+#[derive(Debug, Default, Serialize)]
+pub struct CompactionReport {
+    pub dry_run: bool,
+    pub cutoff_days: i64,
+    // 壓縮 task_date 早於此日期的子任務
+    pub cutoff_date: String,
+    pub parent_tasks: u64,
+    pub months: u64,
+    pub subtasks: u64,
+    // 有附件、留言、待確認完成或下層子任務而保留的子任務
+    pub skipped_subtasks: u64,
+    pub failed_months: u64,
+}
+
+struct PlannedMonth {
+    parent_task_id: String,
+    user_id: Option<String>,
+    month: String,
+    subtasks: u64,
+}
+
+async fn plan(rb: &RBatis, cutoff: &str) -> std::result::Result<(Vec<PlannedMonth>, u64), rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            &format!(
+                "SELECT s.parent_task_id, p.user_id, substr(s.task_date, 1, 7) AS month, COUNT(*) AS subtasks
+                 FROM task s JOIN task p ON p.id = s.parent_task_id
+                 WHERE p.is_recurring = 1 AND s.task_date IS NOT NULL AND s.task_date < ? AND {COMPACTABLE}
+                 GROUP BY s.parent_task_id, month
+                 ORDER BY s.parent_task_id, month"
+            ),
+            vec![Value::String(cutoff.to_string())],
+        )
+        .await?;
+    let months = rows
+        .iter()
+        .filter_map(|row| {
+            Some(PlannedMonth {
+                parent_task_id: row["parent_task_id"].as_str()?.to_string(),
+                user_id: row["user_id"].as_str().map(str::to_string),
+                month: row["month"].as_str()?.to_string(),
+                subtasks: row["subtasks"].as_u64().unwrap_or(0),
+            })
+        })
+        .collect();
+
+    let skipped: Vec<serde_json::Value> = rb
+        .query_decode(
+            &format!(
+                "SELECT COUNT(*) AS count FROM task s JOIN task p ON p.id = s.parent_task_id
+                 WHERE p.is_recurring = 1 AND s.task_date IS NOT NULL AND s.task_date < ? AND NOT ({COMPACTABLE})"
+            ),
+            vec![Value::String(cutoff.to_string())],
+        )
+        .await?;
+    Ok((months, skipped.first().and_then(|row| row["count"].as_u64()).unwrap_or(0)))
+}
+
+/// 在一個交易中把一個（任務, 月份）的子任務寫入彙總並刪除；回傳刪除的子任務數
+async fn compact_month(rb: &RBatis, planned: &PlannedMonth, cutoff: &str) -> std::result::Result<u64, rbatis::Error> {
+    let args = || {
+        vec![
+            Value::String(planned.parent_task_id.clone()),
+            Value::String(planned.month.clone()),
+            Value::String(cutoff.to_string()),
+        ]
+    };
+    let tx = rb.acquire_begin().await?;
+    let result: std::result::Result<u64, rbatis::Error> = async {
+        let rows: Vec<serde_json::Value> = tx
+            .query_decode(
+                &format!(
+                    "SELECT s.task_date, s.status, COALESCE(s.experience, 0) AS experience
+                     FROM task s WHERE {MONTH_SUBTASKS} AND {COMPACTABLE}"
+                ),
+                args(),
+            )
+            .await?;
+        let mut rollup = MonthRollup::default();
+        for row in &rows {
+            let Some(date) = row["task_date"].as_str().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) else {
+                continue;
+            };
+            let completed = row["status"].as_i64() == Some(TaskStatus::DailyCompleted.to_i32() as i64);
+            rollup.add(date, completed, row["experience"].as_i64().unwrap_or(0));
+        }
+        if rollup.subtask_count == 0 {
+            return Ok(0);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        tx.exec(
+            "INSERT INTO daily_task_summary
+             (id, parent_task_id, user_id, month, subtask_count, completed_count, completed_day_mask, experience_total, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(parent_task_id, month) DO UPDATE SET
+                 subtask_count = subtask_count + excluded.subtask_count,
+                 completed_count = completed_count + excluded.completed_count,
+                 completed_day_mask = completed_day_mask | excluded.completed_day_mask,
+                 experience_total = experience_total + excluded.experience_total,
+                 updated_at = excluded.updated_at",
+            vec![
+                Value::String(uuid::Uuid::new_v4().to_string()),
+                Value::String(planned.parent_task_id.clone()),
+                planned.user_id.clone().map(Value::String).unwrap_or(Value::Null),
+                Value::String(planned.month.clone()),
+                Value::I64(rollup.subtask_count),
+                Value::I64(rollup.completed_count),
+                Value::I64(rollup.completed_day_mask),
+                Value::I64(rollup.experience_total),
+                Value::String(now.clone()),
+                Value::String(now),
+            ],
+        )
+        .await?;
+
+        let subtask_ids = format!("SELECT s.id FROM task s WHERE {MONTH_SUBTASKS} AND {COMPACTABLE}");
+        for table in ["task_tag", "task_participant", "task_completion"] {
+            tx.exec(&format!("DELETE FROM {table} WHERE task_id IN ({subtask_ids})"), args()).await?;
+        }
+        let deleted = tx.exec(&format!("DELETE FROM task WHERE id IN ({subtask_ids})"), args()).await?.rows_affected;
+        Ok(deleted)
+    }
+    .await;
+    match result {
+        Ok(deleted) => {
+            tx.commit().await?;
+            Ok(deleted)
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+/// 壓縮 task_date 早於 cutoff_days 天前的子任務；同一時間只允許一輪（已在執行時回傳 None）
+pub async fn run_compaction(
+    rb: &RBatis,
+    cutoff_days: i64,
+    dry_run: bool,
+) -> Option<std::result::Result<CompactionReport, rbatis::Error>> {
+    if RUNNING.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return None;
+    }
+    let result = compact(rb, cutoff_days, dry_run).await;
+    RUNNING.store(false, Ordering::SeqCst);
+    Some(result)
+}
+
+async fn compact(rb: &RBatis, cutoff_days: i64, dry_run: bool) -> std::result::Result<CompactionReport, rbatis::Error> {
+    let cutoff = crate::local_date::local_today() - Duration::days(cutoff_days);
+    let cutoff_date = cutoff.format("%Y-%m-%d").to_string();
+    let (months, skipped_subtasks) = plan(rb, &cutoff_date).await?;
+    let mut report = CompactionReport {
+        dry_run,
+        cutoff_days,
+        cutoff_date,
+        skipped_subtasks,
+        ..Default::default()
+    };
+
+    let mut parents = HashSet::new();
+    for planned in &months {
+        if dry_run {
+            parents.insert(planned.parent_task_id.as_str());
+            report.months += 1;
+            report.subtasks += planned.subtasks;
+            continue;
+        }
+        match compact_month(rb, planned, &report.cutoff_date).await {
+            Ok(deleted) => {
+                parents.insert(planned.parent_task_id.as_str());
+                report.months += 1;
+                report.subtasks += deleted;
+            }
+            Err(e) => {
+                log::warn!("壓縮任務 {} 的 {} 子任務失敗: {}", planned.parent_task_id, planned.month, e);
+                report.failed_months += 1;
+            }
+        }
+        // 讓其他寫入有機會取得鎖
+        tokio::task::yield_now().await;
+    }
+    report.parent_tasks = parents.len() as u64;
+
+    if !dry_run {
+        log::info!(
+            "每日子任務壓縮：{} 個任務、{} 個月份、刪除 {} 個子任務（保留 {} 個，失敗 {} 個月份）",
+            report.parent_tasks,
+            report.months,
+            report.subtasks,
+            report.skipped_subtasks,
+            report.failed_months
+        );
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompactionQuery {
+    pub dry_run: Option<bool>,
+    // 未指定時使用 DAILY_COMPACTION_CUTOFF_DAYS
+    pub cutoff_days: Option<i64>,
+}
+
+/// POST /api/admin/maintenance/compact-daily-tasks：壓縮超過期限的每日子任務（dry_run 只回報影響數量）
+pub async fn compact_daily_tasks(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<CompactionQuery>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+    let cutoff_days = query.cutoff_days.unwrap_or_else(default_cutoff_days);
+    if cutoff_days < min_cutoff_days() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("cutoff_days 至少需要 {} 天", min_cutoff_days()),
+        }));
+    }
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    match run_compaction(rb.get_ref(), cutoff_days, dry_run).await {
+        Some(Ok(report)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: if dry_run {
+                format!("預計壓縮 {} 個子任務", report.subtasks)
+            } else {
+                format!("已壓縮 {} 個子任務", report.subtasks)
+            },
+            data: Some(report),
+        })),
+        Some(Err(e)) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("壓縮每日子任務失敗: {}", e),
+        })),
+        None => Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "每日子任務壓縮正在執行，請稍後再試".to_string(),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Task;
+    use crate::test_utils::{self, call_json};
+    use rbs::value;
+
+    #[test]
+    fn test_month_rollup_mask_round_trip() {
+        let mut rollup = MonthRollup::default();
+        let date = |d: u32| NaiveDate::from_ymd_opt(2026, 1, d).unwrap();
+        rollup.add(date(1), true, 10);
+        rollup.add(date(1), false, 10);
+        rollup.add(date(31), true, 5);
+        assert_eq!(rollup.subtask_count, 3);
+        assert_eq!(rollup.completed_count, 2);
+        assert_eq!(rollup.experience_total, 25);
+        assert_eq!(mask_dates("2026-01", rollup.completed_day_mask), vec![date(1), date(31)]);
+        assert!(mask_dates("bad", 1).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_compaction_preserves_progress_and_streaks() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let admin = test_utils::create_user(&app, "compaction-admin").await;
+        let user = test_utils::create_user(&app, "compaction").await;
+        test_utils::grant_admin("compaction-admin");
+
+        let today = crate::local_date::local_today();
+        let start = today - Duration::days(250);
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, is_recurring, recurrence_pattern, is_parent_task, start_date, experience, created_at, updated_at)
+             VALUES ('habit', ?, '晨跑', 0, 1, 'daily', 1, ?, 0, ?, ?)",
+            vec![
+                Value::String(user.id.clone()),
+                Value::String((Utc::now() - Duration::days(250)).to_rfc3339()),
+                Value::String(Utc::now().to_rfc3339()),
+                Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await
+        .unwrap();
+        // 每三天漏一天；最後 10 天連續完成
+        for offset in 0..=250 {
+            let date = start + Duration::days(offset);
+            let status = if offset > 240 || offset % 3 != 0 { TaskStatus::DailyCompleted } else { TaskStatus::DailyNotCompleted };
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, status, parent_task_id, task_date, experience) VALUES (?, ?, '晨跑', ?, 'habit', ?, 10)",
+                vec![
+                    Value::String(format!("day-{}", offset)),
+                    Value::String(user.id.clone()),
+                    Value::I32(status.to_i32()),
+                    Value::String(date.format("%Y-%m-%d").to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+        rb.exec(
+            "INSERT INTO task_comment (id, task_id, user_id, author, content) VALUES ('c1', 'day-1', ?, 'me', '下雨')",
+            vec![Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        let parent = Task::select_by_map(&rb, value!{"id": "habit"}).await.unwrap().remove(0);
+        let before = crate::recurring_progress::compute_recurring_progress(&rb, &parent).await.unwrap();
+        let habit_before = crate::habit_stats::load_habit_stats(&rb, &parent).await.unwrap();
+
+        let uri = "/api/admin/maintenance/compact-daily-tasks";
+        let req = actix_web::test::TestRequest::post().uri(uri).insert_header(user.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}?cutoff_days=5", uri))
+            .insert_header(admin.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 400);
+
+        // 預覽不刪除資料
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}?dry_run=true&cutoff_days=60", uri))
+            .insert_header(admin.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["subtasks"], 189);
+        assert_eq!(body["data"]["skipped_subtasks"], 1);
+        assert_eq!(body["data"]["parent_tasks"], 1);
+        let count = |rb: RBatis| async move {
+            let rows: Vec<serde_json::Value> = rb
+                .query_decode("SELECT COUNT(*) AS count FROM task WHERE parent_task_id = 'habit'", vec![])
+                .await
+                .unwrap();
+            rows[0]["count"].as_i64().unwrap()
+        };
+        assert_eq!(count(rb.clone()).await, 251);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}?cutoff_days=60", uri))
+            .insert_header(admin.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["subtasks"], 189);
+        assert_eq!(count(rb.clone()).await, 62);
+
+        let after = crate::recurring_progress::compute_recurring_progress(&rb, &parent).await.unwrap();
+        crate::habit_stats::invalidate("habit");
+        let habit_after = crate::habit_stats::load_habit_stats(&rb, &parent).await.unwrap();
+        assert_eq!(after.completed_days, before.completed_days);
+        assert_eq!(after.missed_days, before.missed_days);
+        assert_eq!(habit_after.current_streak, habit_before.current_streak);
+        assert_eq!(habit_after.longest_streak, habit_before.longest_streak);
+        assert_eq!(habit_after.completed_days, habit_before.completed_days);
+
+        // 父任務經驗值仍包含已壓縮的子任務
+        crate::services::task_hierarchy::update_parent_task_experience(&rb, "habit").await.unwrap();
+        let parent = Task::select_by_map(&rb, value!{"id": "habit"}).await.unwrap().remove(0);
+        assert_eq!(parent.experience, Some(2510));
+
+        // 再執行一次沒有可壓縮的資料
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}?cutoff_days=60", uri))
+            .insert_header(admin.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["subtasks"], 0);
+    }
+}
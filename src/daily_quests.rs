@@ -0,0 +1,703 @@
+// 每日三任務（今日三選）：每天從待辦任務中各挑一個主線、支線與每日任務
+//
+// 依到期日、職涯主線/優先級與擱置天數評分，當天的結果快取在 daily_quest 表；
+// 可有限次數重抽，三個任務全部完成時給予額外經驗值並計入成就。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::DailyQuestConfig;
+use crate::models::{DailyQuest, Task, TaskStatus};
+
+/// 每日三任務的三個欄位（對應 task_type）
+pub const SLOTS: [&str; 3] = ["main", "side", "daily"];
+
+static DAILY_QUESTS: OnceLock<DailyQuestConfig> = OnceLock::new();
+
+fn config() -> &'static DailyQuestConfig {
+    DAILY_QUESTS.get_or_init(DailyQuestConfig::default)
+}
+
+/// 啟動時套用設定
+pub fn init(config: DailyQuestConfig) {
+    log::info!(
+        "每日三任務: 每天可重抽 {} 次，全部完成獎勵 {} 經驗值",
+        config.max_rerolls,
+        config.bonus_experience
+    );
+    let _ = DAILY_QUESTS.set(config);
+}
+
+/// 選出的任務（存於 daily_quest.picks）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestPick {
+    pub slot: String,
+    pub task_id: String,
+    pub rationale: String,
+    pub score: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RerollRequest {
+    // 只重抽指定欄位（main / side / daily），未指定時三個都重抽
+    pub slot: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuestPickView {
+    pub slot: String,
+    pub task_id: String,
+    pub title: Option<String>,
+    pub task_type: Option<String>,
+    pub status: Option<i32>,
+    pub completed: bool,
+    pub rationale: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyQuestResponse {
+    pub date: String,
+    pub picks: Vec<QuestPickView>,
+    pub rerolls_used: i32,
+    pub rerolls_remaining: i32,
+    pub all_completed: bool,
+    pub bonus_awarded: bool,
+    pub bonus_experience: i32,
+}
+
+fn slot_label(slot: &str) -> &'static str {
+    match slot {
+        "main" => "主線",
+        "side" => "支線",
+        _ => "每日",
+    }
+}
+
+// 已完成、已取消、已暫停、挑戰失敗或已過期的每日任務都不列入
+fn is_excluded_status(status: Option<i32>) -> bool {
+    matches!(
+        TaskStatus::from_i32(status.unwrap_or(0)),
+        Some(TaskStatus::Completed | TaskStatus::Cancelled | TaskStatus::Paused | TaskStatus::DailyCompleted | TaskStatus::DailyNotCompleted | TaskStatus::Failed)
+    )
+}
+
+/// 依序執行的子任務中，前面還有未完成的子任務時視為受阻
+fn blocked_task_ids(tasks: &[Task]) -> HashSet<String> {
+    let recurring_parents: HashSet<&str> = tasks
+        .iter()
+        .filter(|t| t.is_recurring == Some(1))
+        .filter_map(|t| t.id.as_deref())
+        .collect();
+    let mut siblings: HashMap<&str, Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        if let Some(parent_id) = task.parent_task_id.as_deref() {
+            if !recurring_parents.contains(parent_id) {
+                siblings.entry(parent_id).or_default().push(task);
+            }
+        }
+    }
+
+    let mut blocked = HashSet::new();
+    for group in siblings.values() {
+        for task in group {
+            let order = task.task_order.unwrap_or(0);
+            let waiting = group.iter().any(|other| {
+                other.task_order.unwrap_or(0) < order
+                    && !crate::shared_tasks::is_completed_status(other.status)
+                    && other.status != Some(TaskStatus::Cancelled.to_i32())
+            });
+            if waiting {
+                if let Some(id) = &task.id {
+                    blocked.insert(id.clone());
+                }
+            }
+        }
+    }
+    blocked
+}
+
+/// 任務評分與理由：到期日、職涯主線/優先級、擱置天數
+pub fn score_task(task: &Task, today: NaiveDate, now: DateTime<Utc>) -> (i32, Vec<String>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+
+    if let Some(due) = task.due_date {
+        let days_left = (crate::local_date::local_date(due) - today).num_days();
+        if days_left < 0 {
+            score += 60 + (-days_left).min(10) as i32 * 2;
+            reasons.push(format!("已逾期 {} 天", -days_left));
+        } else if days_left == 0 {
+            score += 50;
+            reasons.push("今天到期".to_string());
+        } else if days_left <= 3 {
+            score += 35;
+            reasons.push(format!("{} 天內到期", days_left));
+        } else if days_left <= 7 {
+            score += 15;
+            reasons.push("一週內到期".to_string());
+        }
+    }
+
+    if task.career_mainline_id.is_some() {
+        score += 20;
+        reasons.push("屬於職涯主線".to_string());
+    }
+    let priority = task.priority.unwrap_or(0).clamp(0, 5);
+    score += priority * 5;
+    if priority >= 3 {
+        reasons.push("高優先級".to_string());
+    }
+
+    if task.task_date.as_deref() == Some(today.format("%Y-%m-%d").to_string().as_str()) {
+        score += 10;
+        reasons.push("今日的每日任務".to_string());
+    }
+
+    if let Some(last_touched) = task.updated_at.or(task.created_at) {
+        let idle_days = (now - last_touched).num_days().max(0);
+        score += (idle_days.min(30) as i32 * 3) / 2;
+        if idle_days >= 3 {
+            reasons.push(format!("已 {} 天未處理", idle_days));
+        }
+    }
+
+    (score, reasons)
+}
+
+/// 從使用者的任務中為每個欄位挑出分數最高的候選（略過 skipped 中的任務）
+pub fn select_picks(
+    tasks: &[Task],
+    slots: &[&str],
+    skipped: &HashSet<String>,
+    today: NaiveDate,
+    now: DateTime<Utc>,
+) -> Vec<QuestPick> {
+    let today_str = today.format("%Y-%m-%d").to_string();
+    let blocked = blocked_task_ids(tasks);
+
+    slots
+        .iter()
+        .filter_map(|slot| {
+            tasks
+                .iter()
+                .filter(|t| t.task_type.as_deref() == Some(*slot))
+                .filter(|t| !is_excluded_status(t.status))
+                // 重複性任務的父任務只是模板，實際可做的是當天產生的子任務
+                .filter(|t| !(t.is_recurring == Some(1) && t.is_parent_task == Some(1)))
+                .filter(|t| t.task_date.as_deref().is_none_or(|d| d == today_str))
+                .filter_map(|t| t.id.as_ref().map(|id| (id, t)))
+                .filter(|(id, _)| !skipped.contains(*id) && !blocked.contains(*id))
+                .map(|(id, t)| {
+                    let (score, reasons) = score_task(t, today, now);
+                    (score, t.created_at, id, reasons)
+                })
+                // 分數高者優先，同分時較早建立的優先
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(b.2.cmp(a.2)))
+                .map(|(score, _, id, reasons)| QuestPick {
+                    slot: slot.to_string(),
+                    task_id: id.clone(),
+                    rationale: if reasons.is_empty() {
+                        format!("目前最值得推進的{}任務", slot_label(slot))
+                    } else {
+                        reasons.join("、")
+                    },
+                    score,
+                })
+        })
+        .collect()
+}
+
+// JSON 欄位讀回時可能是字串或已解析的陣列
+fn parse_list<T: serde::de::DeserializeOwned>(json: Option<&serde_json::Value>) -> Vec<T> {
+    match json {
+        Some(serde_json::Value::String(text)) => serde_json::from_str(text).ok(),
+        Some(value) => serde_json::from_value(value.clone()).ok(),
+        None => None,
+    }
+    .unwrap_or_default()
+}
+
+async fn today_quest(rb: &RBatis, user_id: &str, date: &str) -> Result<Option<DailyQuest>, rbatis::Error> {
+    let rows = DailyQuest::select_by_map(rb, value!{"user_id": user_id, "quest_date": date}).await?;
+    Ok(rows.into_iter().next())
+}
+
+// 取得今天的三任務，尚未產生時選出並寫入
+async fn load_or_create(rb: &RBatis, user_id: &str) -> Result<DailyQuest, rbatis::Error> {
+    let today = crate::local_date::local_today_in(crate::user_settings::user_timezone(rb, user_id).await);
+    let date = today.format("%Y-%m-%d").to_string();
+    if let Some(quest) = today_quest(rb, user_id, &date).await? {
+        return Ok(quest);
+    }
+
+    let tasks = Task::select_by_map(rb, value!{"user_id": user_id}).await?;
+    let picks = select_picks(&tasks, &SLOTS, &HashSet::new(), today, Utc::now());
+    let quest = DailyQuest {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id.to_string()),
+        quest_date: Some(date.clone()),
+        picks: Some(serde_json::Value::String(serde_json::to_string(&picks).unwrap_or_else(|_| "[]".to_string()))),
+        skipped_task_ids: Some(serde_json::Value::String("[]".to_string())),
+        reroll_count: Some(0),
+        bonus_awarded: Some(0),
+        created_at: Some(Utc::now()),
+        updated_at: Some(Utc::now()),
+    };
+    if let Err(e) = DailyQuest::insert(rb, &quest).await {
+        // 同時請求時由另一個請求先寫入（UNIQUE(user_id, quest_date)）
+        if let Some(existing) = today_quest(rb, user_id, &date).await? {
+            return Ok(existing);
+        }
+        return Err(e);
+    }
+    Ok(quest)
+}
+
+async fn picked_tasks(rb: &RBatis, picks: &[QuestPick]) -> Result<HashMap<String, Task>, rbatis::Error> {
+    let mut tasks = HashMap::new();
+    for pick in picks {
+        if let Some(task) = Task::select_by_map(rb, value!{"id": &pick.task_id}).await?.into_iter().next() {
+            tasks.insert(pick.task_id.clone(), task);
+        }
+    }
+    Ok(tasks)
+}
+
+fn all_completed(picks: &[QuestPick], tasks: &HashMap<String, Task>) -> bool {
+    !picks.is_empty()
+        && picks
+            .iter()
+            .all(|p| tasks.get(&p.task_id).is_some_and(|t| crate::shared_tasks::is_completed_status(t.status)))
+}
+
+/// 今天選出的任務全部完成時發放額外經驗值（每天最多一次），回傳發放的經驗值
+///
+/// 成就檢查由呼叫端負責（任務完成時原本就會檢查）
+pub async fn award_bonus_if_complete(rb: &RBatis, user_id: &str) -> Result<Option<i32>, rbatis::Error> {
+    let date = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb, user_id).await);
+    let Some(quest) = today_quest(rb, user_id, &date).await? else {
+        return Ok(None);
+    };
+    if quest.bonus_awarded == Some(1) {
+        return Ok(None);
+    }
+    let picks: Vec<QuestPick> = parse_list(quest.picks.as_ref());
+    if !all_completed(&picks, &picked_tasks(rb, &picks).await?) {
+        return Ok(None);
+    }
+
+    // 以條件更新確保只發放一次
+    let result = rb
+        .exec(
+            "UPDATE daily_quest SET bonus_awarded = 1, updated_at = ? WHERE id = ? AND bonus_awarded = 0",
+            vec![
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(quest.id.clone().unwrap_or_default()),
+            ],
+        )
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(None);
+    }
+
+    let bonus = config().bonus_experience.max(0);
+    if bonus > 0 {
+        crate::services::experience::apply_experience_gain(rb, user_id, bonus).await?;
+    }
+    log::info!("使用者 {} 完成今日三任務，獲得 {} 經驗值", user_id, bonus);
+    Ok(Some(bonus))
+}
+
+async fn build_response(rb: &RBatis, quest: &DailyQuest) -> Result<DailyQuestResponse, rbatis::Error> {
+    let picks: Vec<QuestPick> = parse_list(quest.picks.as_ref());
+    let tasks = picked_tasks(rb, &picks).await?;
+    let rerolls_used = quest.reroll_count.unwrap_or(0);
+
+    Ok(DailyQuestResponse {
+        date: quest.quest_date.clone().unwrap_or_default(),
+        all_completed: all_completed(&picks, &tasks),
+        picks: picks
+            .into_iter()
+            .filter_map(|pick| {
+                // 已刪除的任務不顯示，可透過重抽補上
+                let task = tasks.get(&pick.task_id)?;
+                Some(QuestPickView {
+                    slot: pick.slot,
+                    task_id: pick.task_id,
+                    title: task.title.clone(),
+                    task_type: task.task_type.clone(),
+                    status: task.status,
+                    completed: crate::shared_tasks::is_completed_status(task.status),
+                    rationale: pick.rationale,
+                })
+            })
+            .collect(),
+        rerolls_used,
+        rerolls_remaining: (config().max_rerolls - rerolls_used).max(0),
+        bonus_awarded: quest.bonus_awarded == Some(1),
+        bonus_experience: config().bonus_experience,
+    })
+}
+
+/// 今日三任務（當天第一次呼叫時選出並快取；任務可能從其他途徑完成，查詢時補發獎勵）
+pub async fn load_today(rb: &RBatis, user_id: &str) -> Result<DailyQuestResponse, rbatis::Error> {
+    let mut quest = load_or_create(rb, user_id).await?;
+    match award_bonus_if_complete(rb, user_id).await {
+        Ok(Some(_)) => {
+            quest.bonus_awarded = Some(1);
+            if let Err(e) = crate::achievement_service::AchievementService::check_and_unlock_achievements(rb, user_id).await {
+                log::warn!("檢查成就失敗: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("發放每日任務獎勵失敗: {}", e),
+    }
+    build_response(rb, &quest).await
+}
+
+fn internal_error(context: &str, e: rbatis::Error) -> HttpResponse {
+    log::error!("{}: {}", context, e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("{}: {}", context, e),
+    })
+}
+
+/// 取得今日三任務（當天第一次呼叫時選出並快取）
+pub async fn get_daily_quests(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_admin = crate::auth::is_admin_request(&http_req);
+    if let Err(e) = crate::ownership::assert_self_or_admin(crate::auth::current_user_id(&http_req).as_deref(), is_admin, &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match load_today(rb.get_ref(), &user_id).await {
+        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(data),
+            message: "獲取每日任務成功".to_string(),
+        })),
+        Err(e) => Ok(internal_error("獲取每日任務失敗", e)),
+    }
+}
+
+/// 重抽今日三任務（可指定欄位），每天有次數上限
+pub async fn reroll_daily_quests(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: Option<web::Json<RerollRequest>>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_admin = crate::auth::is_admin_request(&http_req);
+    if let Err(e) = crate::ownership::assert_self_or_admin(crate::auth::current_user_id(&http_req).as_deref(), is_admin, &user_id) {
+        return Ok(e.into_response());
+    }
+    let slot = body.and_then(|b| b.into_inner().slot);
+    if let Some(slot) = &slot {
+        if !SLOTS.contains(&slot.as_str()) {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("無效的欄位: {}（可用: {}）", slot, SLOTS.join(", ")),
+            }));
+        }
+    }
+
+    let quest = match load_or_create(rb.get_ref(), &user_id).await {
+        Ok(quest) => quest,
+        Err(e) => return Ok(internal_error("獲取每日任務失敗", e)),
+    };
+    let rerolls_used = quest.reroll_count.unwrap_or(0);
+    if rerolls_used >= config().max_rerolls {
+        return Ok(HttpResponse::TooManyRequests().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("今天的重抽次數已用完（每天 {} 次）", config().max_rerolls),
+        }));
+    }
+    if quest.bonus_awarded == Some(1) {
+        return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "今日三任務已全部完成".to_string(),
+        }));
+    }
+
+    let old_picks: Vec<QuestPick> = parse_list(quest.picks.as_ref());
+    let rerolled_slots: Vec<&str> = match &slot {
+        Some(slot) => vec![slot.as_str()],
+        None => SLOTS.to_vec(),
+    };
+    // 換掉的任務當天不再出現；已完成的欄位保留不動
+    let tasks = match Task::select_by_map(rb.get_ref(), value!{"user_id": &user_id}).await {
+        Ok(tasks) => tasks,
+        Err(e) => return Ok(internal_error("查詢任務失敗", e)),
+    };
+    let completed_ids: HashSet<&str> = tasks
+        .iter()
+        .filter(|t| crate::shared_tasks::is_completed_status(t.status))
+        .filter_map(|t| t.id.as_deref())
+        .collect();
+    let mut skipped: HashSet<String> = parse_list::<String>(quest.skipped_task_ids.as_ref()).into_iter().collect();
+    let (kept, replaced): (Vec<QuestPick>, Vec<QuestPick>) = old_picks
+        .into_iter()
+        .partition(|p| !rerolled_slots.contains(&p.slot.as_str()) || completed_ids.contains(p.task_id.as_str()));
+    skipped.extend(replaced.iter().map(|p| p.task_id.clone()));
+    let open_slots: Vec<&str> = rerolled_slots
+        .into_iter()
+        .filter(|s| !kept.iter().any(|p| p.slot == *s))
+        .collect();
+    let today = crate::local_date::local_today_in(crate::user_settings::user_timezone(rb.get_ref(), &user_id).await);
+    let new_picks = select_picks(&tasks, &open_slots, &skipped, today, Utc::now());
+    if new_picks.is_empty() {
+        return Ok(HttpResponse::UnprocessableEntity().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "沒有其他可替換的任務".to_string(),
+        }));
+    }
+
+    // 依固定順序（主線、支線、每日）排列；沒有新候選的欄位保留原本的任務
+    let mut picks: Vec<QuestPick> = kept.into_iter().chain(new_picks).collect();
+    for old in replaced {
+        if !picks.iter().any(|p| p.slot == old.slot) {
+            skipped.remove(&old.task_id);
+            picks.push(old);
+        }
+    }
+    picks.sort_by_key(|p| SLOTS.iter().position(|s| *s == p.slot));
+
+    let skipped: Vec<String> = skipped.into_iter().collect();
+    let result = rb
+        .get_ref()
+        .exec(
+            "UPDATE daily_quest SET picks = ?, skipped_task_ids = ?, reroll_count = reroll_count + 1, updated_at = ? WHERE id = ? AND reroll_count = ?",
+            vec![
+                rbs::Value::String(serde_json::to_string(&picks).unwrap_or_else(|_| "[]".to_string())),
+                rbs::Value::String(serde_json::to_string(&skipped).unwrap_or_else(|_| "[]".to_string())),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(quest.id.clone().unwrap_or_default()),
+                rbs::Value::I32(rerolls_used),
+            ],
+        )
+        .await;
+    match result {
+        Ok(r) if r.rows_affected == 0 => {
+            return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "每日任務已被其他請求更新，請重新整理".to_string(),
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => return Ok(internal_error("重抽每日任務失敗", e)),
+    }
+
+    let quest = match today_quest(rb.get_ref(), &user_id, quest.quest_date.as_deref().unwrap_or_default()).await {
+        Ok(Some(quest)) => quest,
+        Ok(None) => return Ok(internal_error("重抽每日任務失敗", rbatis::Error::from("每日任務不存在"))),
+        Err(e) => return Ok(internal_error("重抽每日任務失敗", e)),
+    };
+    match build_response(rb.get_ref(), &quest).await {
+        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(data),
+            message: "已重抽每日任務".to_string(),
+        })),
+        Err(e) => Ok(internal_error("重抽每日任務失敗", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use chrono::Duration;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    fn task(id: &str, task_type: &str, status: TaskStatus, extra: serde_json::Value) -> Task {
+        let mut value = json!({"id": id, "user_id": "u1", "title": id, "task_type": task_type, "status": status.to_i32(), "priority": 1});
+        value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_select_picks_prefers_urgent_and_skips_unavailable_tasks() {
+        let now = Utc::now();
+        let today = crate::local_date::local_date(now);
+        let overdue = (now - Duration::days(2)).to_rfc3339();
+        let tasks = vec![
+            task("main-new", "main", TaskStatus::Pending, json!({})),
+            task("main-overdue", "main", TaskStatus::InProgress, json!({"due_date": overdue})),
+            task("main-done", "main", TaskStatus::Completed, json!({"due_date": overdue})),
+            task("side-paused", "side", TaskStatus::Paused, json!({})),
+            task("side-step1", "side", TaskStatus::Pending, json!({"parent_task_id": "p", "task_order": 1})),
+            task("side-step2", "side", TaskStatus::Pending, json!({"parent_task_id": "p", "task_order": 2, "career_mainline_id": "m"})),
+            task("daily-old", "daily", TaskStatus::DailyInProgress, json!({"task_date": "2000-01-01"})),
+            task("daily-today", "daily", TaskStatus::DailyInProgress, json!({"task_date": today.format("%Y-%m-%d").to_string()})),
+        ];
+
+        let picks = select_picks(&tasks, &SLOTS, &HashSet::new(), today, now);
+        let ids: Vec<&str> = picks.iter().map(|p| p.task_id.as_str()).collect();
+        // step2 雖然屬於主線，但 step1 尚未完成所以受阻
+        assert_eq!(ids, vec!["main-overdue", "side-step1", "daily-today"]);
+        assert!(picks[0].rationale.contains("已逾期 2 天"));
+        assert!(picks[2].rationale.contains("今日的每日任務"));
+
+        let skipped = HashSet::from(["main-overdue".to_string()]);
+        let picks = select_picks(&tasks, &["main"], &skipped, today, now);
+        assert_eq!(picks[0].task_id, "main-new");
+        assert_eq!(picks[0].rationale, "目前最值得推進的主線任務");
+    }
+
+    #[actix_web::test]
+    async fn test_daily_quests_cache_reroll_and_bonus() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "quest_user").await;
+        let other = test_utils::create_user(&app, "quest_other").await;
+
+        let mut task_ids = HashMap::new();
+        for (title, task_type) in [("寫報告", "main"), ("整理房間", "side"), ("讀英文", "side"), ("喝水", "daily")] {
+            let req = actix_web::test::TestRequest::post()
+                .uri("/api/tasks")
+                .insert_header(user.auth())
+                .set_json(json!({"user_id": user.id, "title": title, "task_type": task_type, "difficulty": 1, "experience": 10}))
+                .to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, StatusCode::CREATED);
+            task_ids.insert(title, body["data"]["id"].as_str().unwrap().to_string());
+        }
+
+        let uri = format!("/api/users/{}/daily-quests", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let picks = body["data"]["picks"].as_array().unwrap().clone();
+        assert_eq!(picks.len(), 3);
+        assert_eq!(body["data"]["rerolls_remaining"], config().max_rerolls);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+
+        // 重抽支線會換成另一個支線任務，當天不會再換回來
+        let side_before = picks[1]["task_id"].as_str().unwrap().to_string();
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/reroll", uri))
+            .insert_header(user.auth())
+            .set_json(json!({"slot": "side"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let side_after = body["data"]["picks"][1]["task_id"].as_str().unwrap().to_string();
+        assert_ne!(side_before, side_after);
+        assert_eq!(body["data"]["picks"][0]["task_id"], picks[0]["task_id"]);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/reroll", uri))
+            .insert_header(user.auth())
+            .set_json(json!({"slot": "side"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // 依序完成三個任務，最後一個完成時發放獎勵
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        let mut last_body = json!(null);
+        for pick in body["data"]["picks"].as_array().unwrap() {
+            let task_id = pick["task_id"].as_str().unwrap();
+            let req = actix_web::test::TestRequest::get()
+                .uri(&format!("/api/tasks/{}", task_id))
+                .insert_header(user.auth())
+                .to_request();
+            let (_, task) = call_json(&app, req).await;
+            let req = actix_web::test::TestRequest::put()
+                .uri(&format!("/api/tasks/{}", task_id))
+                .insert_header(user.auth())
+                .set_json(json!({"status": TaskStatus::Completed.to_i32(), "version": task["data"]["version"]}))
+                .to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            last_body = body;
+        }
+        assert_eq!(last_body["data"]["daily_quest_bonus"], config().bonus_experience);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["all_completed"], true);
+        assert_eq!(body["data"]["bonus_awarded"], true);
+        let count: u64 = rb
+            .query_decode("SELECT COUNT(*) FROM daily_quest WHERE bonus_awarded = 1", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_daily_quests_use_user_timezone() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "quest_night_owl").await;
+
+        // 找一個此刻日期與預設時區不同的 UTC 偏移
+        let default_today = crate::local_date::local_today_string();
+        let tz = (-12..=14)
+            .filter_map(|hours| chrono::FixedOffset::east_opt(hours * 3600))
+            .find(|tz| crate::local_date::local_today_string_in(*tz) != default_today)
+            .unwrap();
+        let user_today = crate::local_date::local_today_string_in(tz);
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/users/{}/settings", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"timezone": tz.to_string()}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+
+        // 使用者今天的每日任務會被選入，預設時區今天的不會
+        for (id, date) in [("daily-user-today", &user_today), ("daily-server-today", &default_today)] {
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, task_type, status, task_date) VALUES (?, ?, ?, 'daily', ?, ?)",
+                vec![
+                    rbs::Value::String(id.to_string()),
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::String(id.to_string()),
+                    rbs::Value::I32(TaskStatus::DailyInProgress.to_i32()),
+                    rbs::Value::String(date.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/daily-quests", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["date"], user_today.as_str());
+        let daily: Vec<&str> = body["data"]["picks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|p| p["slot"] == "daily")
+            .map(|p| p["task_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(daily, vec!["daily-user-today"]);
+    }
+}
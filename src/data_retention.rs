@@ -0,0 +1,409 @@
+// 使用者資料保留策略：使用者可為各類紀錄設定保留天數，每天由背景工作刪除過期資料
+//
+// 未設定（null）的類別永久保留；通知另受通知中心的全站保留策略限制。
+// 刪除以小批次進行，每批之間讓出執行緒，避免長時間持有 SQLite 寫入鎖。
+// 每位使用者的刪除筆數寫入稽核日誌，累計數字可在管理員執行期統計查看。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration as StdDuration;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::DataRetentionConfig;
+
+pub const MIN_RETENTION_DAYS: i64 = 7;
+pub const MAX_RETENTION_DAYS: i64 = 3650;
+
+static DATA_RETENTION_CONFIG: OnceLock<DataRetentionConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: DataRetentionConfig) {
+    log::info!("資料保留清除: 每天 {}，每批 {} 筆", config.run_time, config.batch_size);
+    if DATA_RETENTION_CONFIG.set(config).is_err() {
+        log::warn!("資料保留設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static DataRetentionConfig {
+    DATA_RETENTION_CONFIG.get_or_init(DataRetentionConfig::default)
+}
+
+/// 各類別的保留天數（None 代表永久保留）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub chat_days: Option<i64>,
+    #[serde(default)]
+    pub notification_days: Option<i64>,
+    #[serde(default)]
+    pub attribute_history_days: Option<i64>,
+}
+
+impl RetentionPolicy {
+    /// (稽核日誌中的類別名稱, 資料表, 保留天數)
+    fn categories(&self) -> [(&'static str, &'static str, Option<i64>); 3] {
+        [
+            ("chat", "chat_message", self.chat_days),
+            ("notifications", "notification_history", self.notification_days),
+            ("attribute_history", "attribute_history", self.attribute_history_days),
+        ]
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for (category, _, days) in self.categories() {
+            if let Some(days) = days {
+                if !(MIN_RETENTION_DAYS..=MAX_RETENTION_DAYS).contains(&days) {
+                    return Err(format!(
+                        "{} 的保留天數需介於 {}～{} 天",
+                        category, MIN_RETENTION_DAYS, MAX_RETENTION_DAYS
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetentionSettings {
+    #[serde(flatten)]
+    pub policy: RetentionPolicy,
+    pub min_days: i64,
+    pub max_days: i64,
+}
+
+/// 清除統計（程序啟動後累計）
+#[derive(Debug, Serialize)]
+pub struct RetentionMetricsSnapshot {
+    pub runs: u64,
+    pub chat_pruned: u64,
+    pub notifications_pruned: u64,
+    pub attribute_history_pruned: u64,
+}
+
+static RUNS: AtomicU64 = AtomicU64::new(0);
+// 依 RetentionPolicy::categories 的順序
+static PRUNED: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+pub fn metrics_snapshot() -> RetentionMetricsSnapshot {
+    RetentionMetricsSnapshot {
+        runs: RUNS.load(Ordering::Relaxed),
+        chat_pruned: PRUNED[0].load(Ordering::Relaxed),
+        notifications_pruned: PRUNED[1].load(Ordering::Relaxed),
+        attribute_history_pruned: PRUNED[2].load(Ordering::Relaxed),
+    }
+}
+
+async fn load_policy(rb: &RBatis, user_id: &str) -> std::result::Result<RetentionPolicy, rbatis::Error> {
+    let rows: Vec<RetentionPolicy> = rb
+        .query_decode(
+            "SELECT chat_retention_days AS chat_days, notification_retention_days AS notification_days,
+                    attribute_history_retention_days AS attribute_history_days
+             FROM user_settings WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(rows.into_iter().next().unwrap_or_default())
+}
+
+fn optional_days(days: Option<i64>) -> rbs::Value {
+    days.map(rbs::Value::I64).unwrap_or(rbs::Value::Null)
+}
+
+/// 分批刪除單一資料表中使用者早於 cutoff 的資料；回傳刪除筆數
+async fn prune_table(
+    rb: &RBatis,
+    table: &str,
+    user_id: &str,
+    cutoff: DateTime<Utc>,
+    batch_size: i64,
+) -> std::result::Result<u64, rbatis::Error> {
+    let sql = format!(
+        "DELETE FROM {table} WHERE id IN (
+             SELECT id FROM {table} WHERE user_id = ? AND julianday(created_at) < julianday(?) LIMIT ?
+         )"
+    );
+    let mut removed = 0;
+    loop {
+        let affected = rb
+            .exec(
+                &sql,
+                vec![
+                    rbs::Value::String(user_id.to_string()),
+                    rbs::Value::String(cutoff.to_rfc3339()),
+                    rbs::Value::I64(batch_size),
+                ],
+            )
+            .await?
+            .rows_affected;
+        removed += affected;
+        if (affected as i64) < batch_size {
+            return Ok(removed);
+        }
+        // 讓其他寫入有機會取得鎖
+        tokio::task::yield_now().await;
+    }
+}
+
+/// 依使用者的保留策略刪除過期資料；回傳各類別刪除筆數（與 categories 同順序）
+pub async fn prune_user(
+    rb: &RBatis,
+    user_id: &str,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    batch_size: i64,
+) -> std::result::Result<[u64; 3], rbatis::Error> {
+    let mut counts = [0; 3];
+    for (index, (_, table, days)) in policy.categories().into_iter().enumerate() {
+        if let Some(days) = days {
+            counts[index] = prune_table(rb, table, user_id, now - Duration::days(days), batch_size).await?;
+        }
+    }
+    // 聊天訊息刪除後，一併刪除不再被引用的聊天圖片
+    if counts[0] > 0 {
+        crate::chat_attachments::purge_unreferenced(rb, user_id).await?;
+    }
+    Ok(counts)
+}
+
+/// 對所有設定保留策略的使用者執行一輪清除；回傳總刪除筆數
+pub async fn run_pruning(rb: &RBatis, now: DateTime<Utc>, batch_size: i64) -> u64 {
+    let rows: Vec<serde_json::Value> = match rb
+        .query_decode(
+            "SELECT user_id FROM user_settings
+             WHERE chat_retention_days IS NOT NULL OR notification_retention_days IS NOT NULL
+                OR attribute_history_retention_days IS NOT NULL",
+            vec![],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            log::error!("讀取資料保留設定失敗: {}", e);
+            return 0;
+        }
+    };
+
+    RUNS.fetch_add(1, Ordering::Relaxed);
+    let mut total = 0;
+    for user_id in rows.iter().filter_map(|row| row["user_id"].as_str()) {
+        let result = match load_policy(rb, user_id).await {
+            Ok(policy) => prune_user(rb, user_id, &policy, now, batch_size).await.map(|counts| (policy, counts)),
+            Err(e) => Err(e),
+        };
+        let (policy, counts) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("清除使用者過期資料失敗 (user_id: {}): {}", user_id, e);
+                continue;
+            }
+        };
+        let removed: u64 = counts.iter().sum();
+        if removed == 0 {
+            continue;
+        }
+        let mut detail = serde_json::Map::new();
+        for (index, (category, _, _)) in policy.categories().into_iter().enumerate() {
+            PRUNED[index].fetch_add(counts[index], Ordering::Relaxed);
+            detail.insert(category.to_string(), serde_json::json!(counts[index]));
+        }
+        crate::audit_log::record(
+            rb,
+            crate::audit_log::ACTION_DATA_PRUNED,
+            Some(user_id),
+            None,
+            serde_json::Value::Object(detail),
+        )
+        .await;
+        total += removed;
+    }
+    total
+}
+
+/// 每分鐘檢查一次，到設定時間時執行一輪清除
+pub fn spawn_scheduler(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let now = Utc::now();
+            let local = now.with_timezone(&crate::local_date::default_timezone());
+            if format!("{:02}:{:02}", local.hour(), local.minute()) != config().run_time {
+                continue;
+            }
+            let removed = run_pruning(&rb, now, config().batch_size).await;
+            if removed > 0 {
+                log::info!("資料保留清除：共刪除 {} 筆", removed);
+            }
+        }
+    });
+}
+
+/// 取得自己的資料保留設定
+pub async fn get_retention_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_policy(rb.get_ref(), &user_id).await {
+        Ok(policy) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(RetentionSettings {
+                policy,
+                min_days: MIN_RETENTION_DAYS,
+                max_days: MAX_RETENTION_DAYS,
+            }),
+            message: "獲取資料保留設定成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取資料保留設定失敗: {}", e),
+        })),
+    }
+}
+
+/// 更新資料保留設定（整份取代，未帶的類別改為永久保留）
+pub async fn update_retention_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<RetentionPolicy>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let policy = body.into_inner();
+    if let Err(message) = policy.validate() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message,
+        }));
+    }
+
+    let result = rb
+        .exec(
+            "INSERT INTO user_settings (user_id, chat_retention_days, notification_retention_days, attribute_history_retention_days, updated_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET
+                 chat_retention_days = excluded.chat_retention_days,
+                 notification_retention_days = excluded.notification_retention_days,
+                 attribute_history_retention_days = excluded.attribute_history_retention_days,
+                 updated_at = excluded.updated_at",
+            vec![
+                rbs::Value::String(user_id),
+                optional_days(policy.chat_days),
+                optional_days(policy.notification_days),
+                optional_days(policy.attribute_history_days),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(RetentionSettings {
+                policy,
+                min_days: MIN_RETENTION_DAYS,
+                max_days: MAX_RETENTION_DAYS,
+            }),
+            message: "資料保留設定已更新".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("更新資料保留設定失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    async fn insert_chat(rb: &RBatis, user_id: &str, created_at: DateTime<Utc>) {
+        rb.exec(
+            "INSERT INTO chat_message (id, user_id, role, content, created_at) VALUES (?, ?, 'user', '嗨', ?)",
+            vec![
+                rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(created_at.to_rfc3339()),
+            ],
+        )
+        .await
+        .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_policy_bounds_and_batched_pruning() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "retention").await;
+        let uri = format!("/api/users/{}/data-retention", user.id);
+
+        let req = actix_web::test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({"chat_days": 3}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 400);
+
+        let req = actix_web::test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({"chat_days": 90}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["chat_days"], 90);
+        assert!(body["data"]["notification_days"].is_null());
+
+        let now = Utc::now();
+        for _ in 0..5 {
+            insert_chat(&rb, &user.id, now - Duration::days(120)).await;
+        }
+        insert_chat(&rb, &user.id, now - Duration::days(10)).await;
+
+        // 每批 2 筆，需分三批才刪完
+        let removed = run_pruning(&rb, now, 2).await;
+        assert_eq!(removed, 5);
+        let remaining: i64 = rb
+            .query_decode(
+                "SELECT COUNT(*) AS count FROM chat_message WHERE user_id = ?",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(remaining, 1);
+
+        let audits: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT detail FROM audit_log WHERE user_id = ? AND action = ?",
+                vec![
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::String(crate::audit_log::ACTION_DATA_PRUNED.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(audits.len(), 1);
+        assert!(metrics_snapshot().chat_pruned >= 5);
+
+        let other = test_utils::create_user(&app, "retention_other").await;
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+}
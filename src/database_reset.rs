@@ -36,6 +36,7 @@ pub async fn reset_database(rb: &RBatis) -> Result<(), Box<dyn std::error::Error
     // 刪除所有表（按依賴順序）
     let drop_tables = vec![
         // 先刪關聯子表
+        "DROP TABLE IF EXISTS achievement_share",
         "DROP TABLE IF EXISTS user_achievement",
         "DROP TABLE IF EXISTS achievement_stats",
         "DROP TABLE IF EXISTS daily_progress",
@@ -46,6 +47,45 @@ pub async fn reset_database(rb: &RBatis) -> Result<(), Box<dyn std::error::Error
         "DROP TABLE IF EXISTS quiz_results",
         "DROP TABLE IF EXISTS user_coach_preference",
         "DROP TABLE IF EXISTS push_subscription",
+        "DROP TABLE IF EXISTS skill_alias",
+        "DROP TABLE IF EXISTS notification_history",
+        "DROP TABLE IF EXISTS friendship",
+        "DROP TABLE IF EXISTS task_participant",
+        "DROP TABLE IF EXISTS task_completion",
+        "DROP TABLE IF EXISTS idempotency_key",
+        "DROP TABLE IF EXISTS attribute_history",
+        "DROP TABLE IF EXISTS user_session",
+        "DROP TABLE IF EXISTS audit_log",
+        "DROP TABLE IF EXISTS ai_usage_daily",
+        "DROP TABLE IF EXISTS ai_quota_override",
+        "DROP TABLE IF EXISTS ai_usage_log",
+        "DROP TABLE IF EXISTS task_attachment",
+        "DROP TABLE IF EXISTS chat_attachment",
+        "DROP TABLE IF EXISTS focus_session",
+        "DROP TABLE IF EXISTS cancellation_log",
+        "DROP TABLE IF EXISTS daily_quest",
+        "DROP TABLE IF EXISTS integration_secret",
+        "DROP TABLE IF EXISTS activity_mapping",
+        "DROP TABLE IF EXISTS integration_event",
+        "DROP TABLE IF EXISTS task_comment",
+        "DROP TABLE IF EXISTS user_memory",
+        "DROP TABLE IF EXISTS task_snapshot",
+        "DROP TABLE IF EXISTS coach_checkin_setting",
+        "DROP TABLE IF EXISTS difficulty_calibration",
+        "DROP TABLE IF EXISTS user_settings",
+        "DROP TABLE IF EXISTS skill_experience_history",
+        "DROP TABLE IF EXISTS api_token",
+        "DROP TABLE IF EXISTS task_pending_confirmation",
+        "DROP TABLE IF EXISTS career_generation_trace",
+        "DROP TABLE IF EXISTS background_job",
+        "DROP TABLE IF EXISTS task_tag",
+        "DROP TABLE IF EXISTS tag",
+        "DROP TABLE IF EXISTS monthly_report",
+        "DROP TABLE IF EXISTS career_review_item",
+        "DROP TABLE IF EXISTS career_review",
+        "DROP TABLE IF EXISTS daily_task_summary",
+        "DROP TABLE IF EXISTS reward_redemption",
+        "DROP TABLE IF EXISTS reward",
         // 再刪引用 user 的資料表
         "DROP TABLE IF EXISTS task",
         "DROP TABLE IF EXISTS skill",
@@ -63,9 +103,15 @@ pub async fn reset_database(rb: &RBatis) -> Result<(), Box<dyn std::error::Error
         }
     }
 
+    // 附件檔案隨資料表一併清除
+    crate::task_attachments::remove_all_files();
+
     // 刪除可能存在的索引（SQLite 不會隨表自動刪索引）
     let drop_indexes = vec![
         "DROP INDEX IF EXISTS idx_user_email_unique",
+        "DROP INDEX IF EXISTS idx_focus_session_active",
+        "DROP INDEX IF EXISTS idx_user_profile_handle",
+        "DROP INDEX IF EXISTS idx_user_profile_token",
     ];
     for sql in drop_indexes {
         let _ = rb.exec(sql, vec![]).await;
@@ -73,11 +119,25 @@ pub async fn reset_database(rb: &RBatis) -> Result<(), Box<dyn std::error::Error
 
     // 重新建立所有表
     create_all_tables(rb).await?;
+    crate::gamified_cache::clear();
+    crate::language::clear();
     // 重新建立唯一索引
     let _ = rb.exec(
         "CREATE UNIQUE INDEX IF NOT EXISTS idx_user_email_unique ON user(email)",
         vec![]
     ).await;
+    let _ = rb.exec(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_focus_session_active ON focus_session(user_id) WHERE status = 'active'",
+        vec![]
+    ).await;
+    let _ = rb.exec(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_user_profile_handle ON user_profile(profile_handle)",
+        vec![]
+    ).await;
+    let _ = rb.exec(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_user_profile_token ON user_profile(profile_token)",
+        vec![]
+    ).await;
     // 雙保險：確保核心表無殘留資料
     let _ = rb.exec("DELETE FROM user", vec![]).await;
     // 開啟外鍵檢查
@@ -97,6 +157,7 @@ async fn create_all_tables(rb: &RBatis) -> Result<(), Box<dyn std::error::Error>
             name TEXT,
             email TEXT,
             password_hash TEXT,
+            token_version INTEGER DEFAULT 0,
             created_at TEXT,
             updated_at TEXT
         )
@@ -157,6 +218,9 @@ async fn create_all_tables(rb: &RBatis) -> Result<(), Box<dyn std::error::Error>
             user_id TEXT,
             role TEXT,
             content TEXT,
+            source TEXT DEFAULT 'client',
+            expert_name TEXT,
+            attachment_id TEXT,
             created_at TEXT,
             FOREIGN KEY (user_id) REFERENCES user (id)
         )
@@ -188,6 +252,11 @@ async fn create_all_tables(rb: &RBatis) -> Result<(), Box<dyn std::error::Error>
             adventure_days INTEGER DEFAULT 1,
             consecutive_login_days INTEGER DEFAULT 1,
             persona_type TEXT DEFAULT 'internal',
+            leaderboard_visible INTEGER DEFAULT 0,
+            coins INTEGER DEFAULT 0,
+            profile_visibility TEXT DEFAULT 'private',
+            profile_handle TEXT,
+            profile_token TEXT,
             created_at TEXT,
             updated_at TEXT,
             FOREIGN KEY (user_id) REFERENCES user (id)
@@ -267,7 +336,7 @@ async fn create_all_tables(rb: &RBatis) -> Result<(), Box<dyn std::error::Error>
             focus INTEGER DEFAULT 50,
             adaptability INTEGER DEFAULT 50,
             created_at TEXT,
-            UNIQUE(user_id, year, week_number),
+            UNIQUE(user_id, week_start_date),
             FOREIGN KEY (user_id) REFERENCES user (id)
         )
         "#,
@@ -284,6 +353,547 @@ async fn create_all_tables(rb: &RBatis) -> Result<(), Box<dyn std::error::Error>
             FOREIGN KEY (user_id) REFERENCES user (id)
         )
         "#,
+        // 技能別名表
+        r#"
+        CREATE TABLE IF NOT EXISTS skill_alias (
+            id TEXT PRIMARY KEY,
+            alias TEXT UNIQUE NOT NULL,
+            canonical_name TEXT NOT NULL,
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'))
+        )
+        "#,
+        // 通知歷史表
+        r#"
+        CREATE TABLE IF NOT EXISTS notification_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            title TEXT,
+            body TEXT,
+            data TEXT,
+            pushed INTEGER DEFAULT 0,
+            seen_at TEXT,
+            read_at TEXT,
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 好友關係表
+        r#"
+        CREATE TABLE IF NOT EXISTS friendship (
+            id TEXT PRIMARY KEY,
+            requester_id TEXT NOT NULL,
+            addressee_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(requester_id, addressee_id),
+            FOREIGN KEY (requester_id) REFERENCES user (id),
+            FOREIGN KEY (addressee_id) REFERENCES user (id)
+        )
+        "#,
+        // 共享任務參與者表
+        r#"
+        CREATE TABLE IF NOT EXISTS task_participant (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            created_at TEXT,
+            UNIQUE(task_id, user_id),
+            FOREIGN KEY (task_id) REFERENCES task (id),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 共享任務完成紀錄表（all 模式逐人記錄）
+        r#"
+        CREATE TABLE IF NOT EXISTS task_completion (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            completed_at TEXT,
+            UNIQUE(task_id, user_id),
+            FOREIGN KEY (task_id) REFERENCES task (id),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 冪等鍵表（避免重送請求重複建立資源）
+        r#"
+        CREATE TABLE IF NOT EXISTS idempotency_key (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            idempotency_key TEXT NOT NULL,
+            endpoint TEXT,
+            request_fingerprint TEXT,
+            resource_id TEXT,
+            response_status INTEGER,
+            response_body TEXT,
+            created_at TEXT,
+            expires_at TEXT,
+            UNIQUE(user_id, idempotency_key)
+        )
+        "#,
+        // 屬性變化紀錄表
+        r#"
+        CREATE TABLE IF NOT EXISTS attribute_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            attribute TEXT NOT NULL,
+            old_value INTEGER,
+            new_value INTEGER,
+            delta INTEGER,
+            source TEXT,
+            task_id TEXT,
+            created_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 登入裝置（session）表
+        r#"
+        CREATE TABLE IF NOT EXISTS user_session (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            device_name TEXT,
+            user_agent TEXT,
+            ip_address TEXT,
+            created_at TEXT,
+            last_seen_at TEXT,
+            expires_at TEXT,
+            revoked_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 稽核日誌表（不設外鍵：紀錄需保留，且可能對應不存在的帳號）
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id TEXT PRIMARY KEY,
+            user_id TEXT,
+            action TEXT NOT NULL,
+            ip_address TEXT,
+            detail TEXT,
+            created_at TEXT
+        )
+        "#,
+        // AI 每日使用次數（依使用者時區日期與類別計算額度）
+        r#"
+        CREATE TABLE IF NOT EXISTS ai_usage_daily (
+            user_id TEXT NOT NULL,
+            usage_date TEXT NOT NULL,
+            category TEXT NOT NULL,
+            request_count INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT,
+            PRIMARY KEY (user_id, usage_date, category)
+        )
+        "#,
+        // 管理員調整的個別使用者 AI 每日上限
+        r#"
+        CREATE TABLE IF NOT EXISTS ai_quota_override (
+            user_id TEXT NOT NULL,
+            category TEXT NOT NULL,
+            daily_limit INTEGER NOT NULL,
+            updated_at TEXT,
+            PRIMARY KEY (user_id, category)
+        )
+        "#,
+        // AI 請求紀錄（實際使用的模型、是否為單次覆寫）
+        r#"
+        CREATE TABLE IF NOT EXISTS ai_usage_log (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            category TEXT NOT NULL,
+            endpoint TEXT,
+            provider TEXT,
+            model TEXT,
+            is_override INTEGER DEFAULT 0,
+            response_status INTEGER,
+            created_at TEXT
+        )
+        "#,
+        // 任務附件（完成證明）
+        r#"
+        CREATE TABLE IF NOT EXISTS task_attachment (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            original_name TEXT,
+            stored_name TEXT NOT NULL,
+            mime_type TEXT,
+            size_bytes INTEGER,
+            sha256 TEXT,
+            created_at TEXT
+        )
+        "#,
+        // 聊天訊息附帶的圖片
+        r#"
+        CREATE TABLE IF NOT EXISTS chat_attachment (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            original_name TEXT,
+            stored_name TEXT NOT NULL,
+            mime_type TEXT,
+            size_bytes INTEGER,
+            sha256 TEXT,
+            created_at TEXT
+        )
+        "#,
+        // 專注（番茄鐘）時段
+        r#"
+        CREATE TABLE IF NOT EXISTS focus_session (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            task_id TEXT,
+            duration_minutes INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            experience_gained INTEGER DEFAULT 0,
+            started_at TEXT NOT NULL,
+            ended_at TEXT
+        )
+        "#,
+        // 任務取消紀錄（超過取消次數上限後需填寫原因）
+        r#"
+        CREATE TABLE IF NOT EXISTS cancellation_log (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            task_title TEXT,
+            cancel_count INTEGER NOT NULL,
+            reason TEXT,
+            created_at TEXT NOT NULL
+        )
+        "#,
+        // 每日三任務（當日選出的任務與重抽次數）
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_quest (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            quest_date TEXT NOT NULL,
+            picks TEXT NOT NULL,
+            skipped_task_ids TEXT,
+            reroll_count INTEGER DEFAULT 0,
+            bonus_awarded INTEGER DEFAULT 0,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, quest_date)
+        )
+        "#,
+        // 外部活動匯入：整合密鑰、活動類型與重複性任務的對應、已匯入的活動（依來源 external_id 去重）
+        r#"
+        CREATE TABLE IF NOT EXISTS integration_secret (
+            user_id TEXT PRIMARY KEY,
+            secret TEXT NOT NULL,
+            created_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS activity_mapping (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            activity_type TEXT NOT NULL,
+            task_id TEXT NOT NULL,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, activity_type)
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS integration_event (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            source TEXT NOT NULL,
+            external_id TEXT NOT NULL,
+            activity_type TEXT NOT NULL,
+            occurred_at TEXT NOT NULL,
+            metadata TEXT,
+            status TEXT NOT NULL,
+            task_id TEXT,
+            task_date TEXT,
+            created_at TEXT,
+            UNIQUE(user_id, source, external_id)
+        )
+        "#,
+        // 獎勵商店：使用者自訂的獎勵與兌換紀錄（以金幣兌換，不影響等級）
+        r#"
+        CREATE TABLE IF NOT EXISTS reward (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            icon TEXT,
+            cost INTEGER NOT NULL,
+            created_at TEXT,
+            updated_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS reward_redemption (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            reward_id TEXT,
+            reward_name TEXT NOT NULL,
+            cost INTEGER NOT NULL,
+            balance_after INTEGER NOT NULL,
+            created_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS task_comment (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            author TEXT NOT NULL,
+            content TEXT NOT NULL,
+            parent_comment_id TEXT,
+            created_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS user_memory (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            source TEXT NOT NULL,
+            active INTEGER NOT NULL DEFAULT 1,
+            confirmed INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT,
+            updated_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS task_snapshot (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            subtasks TEXT NOT NULL,
+            created_at TEXT
+        )
+        "#,
+        // 成就分享連結（擁有者產生的公開 token，可撤銷）
+        r#"
+        CREATE TABLE IF NOT EXISTS achievement_share (
+            id TEXT PRIMARY KEY,
+            user_achievement_id TEXT NOT NULL UNIQUE,
+            user_id TEXT NOT NULL,
+            token TEXT NOT NULL UNIQUE,
+            created_at TEXT,
+            FOREIGN KEY (user_achievement_id) REFERENCES user_achievement (id)
+        )
+        "#,
+        // 教練主動關心設定（使用者自行開啟，預設關閉）
+        r#"
+        CREATE TABLE IF NOT EXISTS coach_checkin_setting (
+            user_id TEXT PRIMARY KEY,
+            enabled INTEGER NOT NULL DEFAULT 0,
+            frequency_days INTEGER,
+            last_checkin_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 任務難度校準結果與經驗倍率設定
+        r#"
+        CREATE TABLE IF NOT EXISTS difficulty_calibration (
+            user_id TEXT PRIMARY KEY,
+            data TEXT,
+            apply_multipliers INTEGER NOT NULL DEFAULT 0,
+            computed_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 使用者設定（各類資料的保留天數，NULL 代表永久保留；聊天快速模式）
+        r#"
+        CREATE TABLE IF NOT EXISTS user_settings (
+            user_id TEXT PRIMARY KEY,
+            chat_retention_days INTEGER,
+            notification_retention_days INTEGER,
+            attribute_history_retention_days INTEGER,
+            chat_fast_mode INTEGER DEFAULT 0,
+            chat_fast_followup INTEGER DEFAULT 1,
+            achievement_autogen INTEGER DEFAULT 0,
+            timezone TEXT,
+            locale TEXT,
+            share_with_coach INTEGER DEFAULT 1,
+            week_start TEXT DEFAULT 'mon',
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 技能經驗值流水（技能詳情的成長歷史）
+        r#"
+        CREATE TABLE IF NOT EXISTS skill_experience_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            skill_id TEXT NOT NULL,
+            experience_gain INTEGER NOT NULL,
+            old_level INTEGER,
+            new_level INTEGER,
+            reason TEXT,
+            created_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id),
+            FOREIGN KEY (skill_id) REFERENCES skill (id)
+        )
+        "#,
+        // 個人 API token（只保存雜湊）
+        r#"
+        CREATE TABLE IF NOT EXISTS api_token (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            token_prefix TEXT,
+            scope TEXT DEFAULT 'read_write',
+            created_at TEXT,
+            last_used_at TEXT,
+            revoked_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 待確認完成的任務（記錄標記完成前的狀態，逾時還原）
+        r#"
+        CREATE TABLE IF NOT EXISTS task_pending_confirmation (
+            task_id TEXT PRIMARY KEY,
+            user_id TEXT,
+            previous_status INTEGER,
+            target_status INTEGER,
+            requested_at TEXT,
+            FOREIGN KEY (task_id) REFERENCES task (id)
+        )
+        "#,
+        // 職業任務生成追蹤（每個 AI 步驟一筆，由每晚彙整清除過期紀錄）
+        r#"
+        CREATE TABLE IF NOT EXISTS career_generation_trace (
+            id TEXT PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            user_id TEXT,
+            step TEXT NOT NULL,
+            step_order INTEGER,
+            model TEXT,
+            prompt_hash TEXT,
+            prompt TEXT,
+            prompt_tokens INTEGER,
+            output_tokens INTEGER,
+            duration_ms INTEGER,
+            output TEXT,
+            error TEXT,
+            created_at TEXT
+        )
+        "#,
+        // 背景工作狀態（長時間工作立即回傳工作 ID，客戶端輪詢進度與結果）
+        r#"
+        CREATE TABLE IF NOT EXISTS background_job (
+            id TEXT PRIMARY KEY,
+            user_id TEXT,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            progress INTEGER DEFAULT 0,
+            result TEXT,
+            error TEXT,
+            created_at TEXT,
+            started_at TEXT,
+            finished_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 任務標籤（與 skill_tags 分開的自訂分類，名稱在同一使用者內不分大小寫唯一）
+        r#"
+        CREATE TABLE IF NOT EXISTS tag (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL COLLATE NOCASE,
+            color TEXT,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, name),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS task_tag (
+            task_id TEXT NOT NULL,
+            tag_id TEXT NOT NULL,
+            created_at TEXT,
+            PRIMARY KEY (task_id, tag_id),
+            FOREIGN KEY (task_id) REFERENCES task (id),
+            FOREIGN KEY (tag_id) REFERENCES tag (id)
+        )
+        "#,
+        // 月報（保存彙整結果指紋與 AI 回顧，數字未變時不重複呼叫 AI）
+        r#"
+        CREATE TABLE IF NOT EXISTS monthly_report (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            month TEXT NOT NULL,
+            fingerprint TEXT,
+            report TEXT,
+            ai_summary TEXT,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, month),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 職業規劃審核佇列（接受職業規劃後、提交前暫存 AI 產生的任務）
+        r#"
+        CREATE TABLE IF NOT EXISTS career_review (
+            mainline_id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            selected_career TEXT NOT NULL,
+            learning_summary TEXT,
+            estimated_months INTEGER,
+            achievements TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            result TEXT,
+            created_at TEXT,
+            committed_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 拒絕的項目保留拒絕原因，供改善職業任務提示詞分析
+        r#"
+        CREATE TABLE IF NOT EXISTS career_review_item (
+            id TEXT PRIMARY KEY,
+            mainline_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            task_group TEXT NOT NULL,
+            item_order INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            difficulty INTEGER NOT NULL,
+            experience INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            rejection_reason TEXT,
+            task_id TEXT,
+            payload TEXT NOT NULL,
+            created_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 重複性任務每日子任務的每月彙總（壓縮舊子任務後保留完成日期與經驗值）
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_task_summary (
+            id TEXT PRIMARY KEY,
+            parent_task_id TEXT NOT NULL,
+            user_id TEXT,
+            month TEXT NOT NULL,
+            subtask_count INTEGER NOT NULL DEFAULT 0,
+            completed_count INTEGER NOT NULL DEFAULT 0,
+            completed_day_mask INTEGER NOT NULL DEFAULT 0,
+            experience_total INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(parent_task_id, month)
+        )
+        "#,
+        // VAPID 金鑰（管理員輪替後保存，重置資料庫時保留）
+        r#"
+        CREATE TABLE IF NOT EXISTS vapid_key (
+            id TEXT PRIMARY KEY,
+            public_key TEXT NOT NULL,
+            private_key_pem TEXT NOT NULL,
+            created_at TEXT
+        )
+        "#,
     ];
 
     for (i, sql) in tables.iter().enumerate() {
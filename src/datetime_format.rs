@@ -0,0 +1,237 @@
+// 日期時間格式：API 回應與資料庫一律使用 RFC 3339 UTC（DateTime::to_rfc3339，例如 2026-03-01T08:00:00.123+00:00）
+//
+// 舊程式曾寫入 Utc::now().to_string()（2026-03-01 08:00:00.123 UTC）、SQLite datetime('now')
+// （2026-03-01 08:00:00）與空字串；輸入時仍接受這些格式，啟動時把各資料表 *_at 欄位中的舊格式改寫為 RFC 3339。
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rbatis::RBatis;
+use rbs::Value;
+
+// 欄位中已是 RFC 3339 UTC 的值（其餘交給 parse 判斷）
+const CANONICAL_PATTERN: &str = "____-__-__T__:__:__%+00:00";
+
+pub fn format(dt: &DateTime<Utc>) -> String {
+    dt.to_rfc3339()
+}
+
+/// 解析 RFC 3339，並相容舊資料的格式；空字串或只有空白回傳 None
+pub fn parse(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(dt) = value.parse::<DateTime<Utc>>() {
+        return Some(dt);
+    }
+    // SQLite datetime 格式（可帶毫秒），以及 Utc::now().to_string() 的「 UTC」結尾
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f UTC"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|naive| DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc))
+}
+
+/// DateTime<Utc> 欄位：輸出 RFC 3339，輸入相容舊格式
+pub mod rfc3339 {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format(dt))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::parse(&value).ok_or_else(|| serde::de::Error::custom(format!("無法解析日期時間格式: {}", value)))
+    }
+}
+
+/// Option<DateTime<Utc>> 欄位：輸出 RFC 3339，輸入相容舊格式（空字串視為未設定）
+pub mod rfc3339_option {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(dt: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match dt {
+            Some(dt) => serializer.serialize_some(&super::format(dt)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) if value.trim().is_empty() => Ok(None),
+            Some(value) => super::parse(&value)
+                .map(Some)
+                .ok_or_else(|| serde::de::Error::custom(format!("無法解析日期時間格式: {}", value))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// 把所有資料表 *_at 欄位（created_at、updated_at、requested_at 等）中的舊格式改寫為 RFC 3339（空字串改為 NULL）；回傳更新筆數
+///
+/// 無法解析的值保留原樣並記錄警告
+pub async fn normalize_stored_datetimes(rb: &RBatis) -> Result<u64, rbatis::Error> {
+    let tables: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            vec![],
+        )
+        .await?;
+    let mut updated = 0;
+    for table in tables.iter().filter_map(|row| row["name"].as_str()) {
+        let columns: Vec<serde_json::Value> = rb.query_decode(&format!("PRAGMA table_info({})", table), vec![]).await?;
+        for column in columns
+            .iter()
+            .filter_map(|c| c["name"].as_str())
+            .filter(|name| name.ends_with("_at"))
+        {
+            updated += normalize_column(rb, table, column).await?;
+        }
+    }
+    Ok(updated)
+}
+
+async fn normalize_column(rb: &RBatis, table: &str, column: &str) -> Result<u64, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            &format!(
+                "SELECT rowid AS row_id, CAST({column} AS TEXT) AS value FROM {table}
+                 WHERE {column} IS NOT NULL AND {column} NOT LIKE ?"
+            ),
+            vec![Value::String(CANONICAL_PATTERN.to_string())],
+        )
+        .await?;
+    let sql = format!("UPDATE {table} SET {column} = ? WHERE rowid = ?");
+    let mut updated = 0;
+    for row in &rows {
+        let (Some(row_id), Some(value)) = (row["row_id"].as_i64(), row["value"].as_str()) else {
+            continue;
+        };
+        let normalized = if value.trim().is_empty() {
+            Value::Null
+        } else {
+            match parse(value) {
+                Some(dt) => Value::String(format(&dt)),
+                None => {
+                    log::warn!("{}.{} 有無法解析的日期時間 {:?}，保留原值", table, column, value);
+                    continue;
+                }
+            }
+        };
+        updated += rb.exec(&sql, vec![normalized, Value::I64(row_id)]).await?.rows_affected;
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::*;
+    use crate::test_utils;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::json;
+
+    const LEGACY: [&str; 4] = [
+        "2026-03-01 08:00:00.250 UTC",
+        "2026-03-01 08:00:00.250",
+        "2026-03-01T16:00:00.250+08:00",
+        "2026-03-01T08:00:00.250Z",
+    ];
+    const CANONICAL: &str = "2026-03-01T08:00:00.250+00:00";
+
+    // 以舊格式讀入後輸出 RFC 3339，再讀回結果不變
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(base: serde_json::Value, fields: &[&str]) {
+        for legacy in LEGACY {
+            let mut input = base.clone();
+            for field in fields {
+                input[*field] = json!(legacy);
+            }
+            let model: T = serde_json::from_value(input).unwrap();
+            let output = serde_json::to_value(&model).unwrap();
+            for field in fields {
+                assert_eq!(output[*field], CANONICAL, "{} ({})", field, legacy);
+            }
+            let again: T = serde_json::from_value(output.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&again).unwrap(), output);
+        }
+    }
+
+    #[test]
+    fn test_model_datetime_round_trip() {
+        assert_round_trip::<User>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<Task>(
+            json!({}),
+            &["due_date", "created_at", "updated_at", "start_date", "end_date", "last_cancelled_at"],
+        );
+        assert_round_trip::<Skill>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<SkillAlias>(json!({}), &["created_at"]);
+        assert_round_trip::<ChatMessage>(json!({}), &["created_at"]);
+        assert_round_trip::<UserProfile>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<UserAttributes>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<DailyProgress>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<RecurringTaskTemplate>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<Achievement>(json!({}), &["created_at"]);
+        assert_round_trip::<AchievementStats>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<UserAchievement>(json!({}), &["achieved_at"]);
+        assert_round_trip::<WeeklyAttributeSnapshot>(json!({}), &["created_at"]);
+        assert_round_trip::<AttributeHistory>(json!({}), &["created_at"]);
+        assert_round_trip::<UserSession>(json!({}), &["created_at", "last_seen_at", "expires_at", "revoked_at"]);
+        assert_round_trip::<AuditLog>(json!({}), &["created_at"]);
+        assert_round_trip::<UserCoachPreference>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<QuizResults>(json!({}), &["completed_at", "created_at", "updated_at"]);
+        assert_round_trip::<CareerMainlines>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<PushSubscription>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<UserNotificationSettings>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<NotificationHistory>(json!({}), &["seen_at", "read_at", "created_at"]);
+        assert_round_trip::<Friendship>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<TaskParticipant>(json!({}), &["created_at"]);
+        assert_round_trip::<TaskAttachment>(json!({}), &["created_at"]);
+        assert_round_trip::<ChatAttachment>(json!({}), &["created_at"]);
+        assert_round_trip::<FocusSession>(json!({}), &["started_at", "ended_at"]);
+        assert_round_trip::<DailyQuest>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<Reward>(json!({}), &["created_at", "updated_at"]);
+        assert_round_trip::<RewardRedemption>(json!({}), &["created_at"]);
+        assert_round_trip::<TaskComment>(json!({}), &["created_at"]);
+        assert_round_trip::<TaskSnapshot>(json!({}), &["created_at"]);
+
+        // 空字串視為未設定
+        let task: Task = serde_json::from_value(json!({"created_at": " "})).unwrap();
+        assert!(task.created_at.is_none());
+        assert!(serde_json::from_value::<Task>(json!({"created_at": "yesterday"})).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_normalize_stored_datetimes() {
+        let rb = test_utils::setup_db().await;
+        for (id, created_at, updated_at) in [
+            ("legacy-1", "2026-03-01 08:00:00.250 UTC", "2026-03-01 08:00:00"),
+            ("legacy-2", "2026-03-01T08:00:00.250Z", ""),
+            ("legacy-3", CANONICAL, "not a date"),
+        ] {
+            rb.exec(
+                "INSERT INTO task (id, title, status, created_at, updated_at) VALUES (?, '任務', 0, ?, ?)",
+                vec![
+                    Value::String(id.to_string()),
+                    Value::String(created_at.to_string()),
+                    Value::String(updated_at.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(normalize_stored_datetimes(&rb).await.unwrap(), 4);
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT created_at, updated_at FROM task ORDER BY id", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["created_at"], CANONICAL);
+        assert_eq!(rows[0]["updated_at"], "2026-03-01T08:00:00+00:00");
+        assert_eq!(rows[1]["created_at"], CANONICAL);
+        assert!(rows[1]["updated_at"].is_null());
+        assert_eq!(rows[2]["updated_at"], "not a date");
+        assert_eq!(normalize_stored_datetimes(&rb).await.unwrap(), 0);
+    }
+}
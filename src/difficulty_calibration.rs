@@ -0,0 +1,448 @@
+// 任務難度校準：依使用者實際的完成資料，計算各難度（1～5）的完成率與完成所需時間，
+// 並建議各難度的經驗值倍率（完成率偏低的難度給較高倍率）。
+//
+// 每個難度需達最小樣本數才會給建議；結果由背景工作定期重算並存於 difficulty_calibration。
+// 使用者開啟 apply_multipliers 後，伺服器發放任務經驗值時套用建議倍率。
+
+use std::time::Duration as StdDuration;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, Utc};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Task, TaskStatus};
+
+// 每個難度至少要有這麼多已結束（或放置夠久）的任務才給建議
+pub const MIN_BUCKET_SAMPLE: usize = 10;
+// 建立未滿 7 天的未完成任務還不能算「沒完成」，不列入樣本
+const OPEN_TASK_MIN_AGE_DAYS: i64 = 7;
+const MIN_MULTIPLIER: f64 = 0.5;
+const MAX_MULTIPLIER: f64 = 2.0;
+// 超過 7 天的校準結果由背景工作重算
+const STALE_AFTER_DAYS: i64 = 7;
+const JOB_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// 單一難度的統計
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyBucket {
+    pub difficulty: i32,
+    pub sample_size: usize,
+    pub completed: usize,
+    pub completion_rate: Option<f64>,
+    pub median_hours_to_complete: Option<f64>,
+    // 樣本不足時為 None
+    pub suggested_multiplier: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyCalibration {
+    pub buckets: Vec<DifficultyBucket>,
+    // 樣本足夠的難度合計的完成率（倍率以此為基準）
+    pub overall_completion_rate: Option<f64>,
+    pub min_sample_size: usize,
+    pub computed_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalibrationResponse {
+    #[serde(flatten)]
+    pub calibration: DifficultyCalibration,
+    pub apply_multipliers: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalibrationSettingsRequest {
+    pub apply_multipliers: bool,
+}
+
+fn round_to(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+/// 建議倍率：完成率低於整體時提高、高於整體時降低（0.5～2.0，取到小數一位）
+pub fn suggest_multiplier(bucket_rate: f64, overall_rate: f64) -> f64 {
+    if bucket_rate <= 0.0 {
+        return MAX_MULTIPLIER;
+    }
+    round_to((overall_rate / bucket_rate).clamp(MIN_MULTIPLIER, MAX_MULTIPLIER), 1)
+}
+
+/// 依任務計算校準結果（tasks 應已排除有子任務的父任務、重複性任務與每日任務實例）
+pub fn compute(tasks: &[Task], now: DateTime<Utc>) -> DifficultyCalibration {
+    let mut samples: Vec<(usize, usize, Vec<f64>)> = vec![(0, 0, Vec::new()); 5];
+    for task in tasks {
+        let Some(index) = task.difficulty.filter(|d| (1..=5).contains(d)).map(|d| (d - 1) as usize) else {
+            continue;
+        };
+        let completed = crate::shared_tasks::is_completed_status(task.status);
+        let cancelled = task.status == Some(TaskStatus::Cancelled.to_i32());
+        let old_enough = task.created_at.is_some_and(|created| now - created >= Duration::days(OPEN_TASK_MIN_AGE_DAYS));
+        if !completed && !cancelled && !old_enough {
+            continue;
+        }
+        let bucket = &mut samples[index];
+        bucket.0 += 1;
+        if completed {
+            bucket.1 += 1;
+            // 沒有完成時間欄位，以最後更新時間近似
+            if let (Some(created), Some(updated)) = (task.created_at, task.updated_at) {
+                bucket.2.push((updated - created).num_minutes().max(0) as f64 / 60.0);
+            }
+        }
+    }
+
+    let (qualified_total, qualified_completed) = samples
+        .iter()
+        .filter(|(total, _, _)| *total >= MIN_BUCKET_SAMPLE)
+        .fold((0, 0), |(t, c), (total, completed, _)| (t + total, c + completed));
+    let overall_completion_rate = (qualified_total > 0).then(|| qualified_completed as f64 / qualified_total as f64);
+
+    let buckets = samples
+        .into_iter()
+        .enumerate()
+        .map(|(index, (sample_size, completed, hours))| {
+            let completion_rate = (sample_size > 0).then(|| completed as f64 / sample_size as f64);
+            let suggested_multiplier = match (completion_rate, overall_completion_rate) {
+                (Some(rate), Some(overall)) if sample_size >= MIN_BUCKET_SAMPLE => Some(suggest_multiplier(rate, overall)),
+                _ => None,
+            };
+            DifficultyBucket {
+                difficulty: index as i32 + 1,
+                sample_size,
+                completed,
+                completion_rate: completion_rate.map(|r| round_to(r, 3)),
+                median_hours_to_complete: median(hours).map(|h| round_to(h, 1)),
+                suggested_multiplier,
+            }
+        })
+        .collect();
+
+    DifficultyCalibration {
+        buckets,
+        overall_completion_rate: overall_completion_rate.map(|r| round_to(r, 3)),
+        min_sample_size: MIN_BUCKET_SAMPLE,
+        computed_at: now.to_rfc3339(),
+    }
+}
+
+/// 重新計算並保存使用者的校準結果（保留 apply_multipliers 設定）
+pub async fn refresh(rb: &RBatis, user_id: &str) -> std::result::Result<DifficultyCalibration, rbatis::Error> {
+    let tasks: Vec<Task> = rb
+        .query_decode(
+            "SELECT * FROM task t
+             WHERE t.user_id = ? AND t.difficulty BETWEEN 1 AND 5
+               AND t.task_date IS NULL AND COALESCE(t.is_recurring, 0) = 0
+               AND NOT EXISTS (SELECT 1 FROM task c WHERE c.parent_task_id = t.id)",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let calibration = compute(&tasks, Utc::now());
+    rb.exec(
+        "INSERT INTO difficulty_calibration (user_id, data, computed_at) VALUES (?, ?, ?)
+         ON CONFLICT(user_id) DO UPDATE SET data = excluded.data, computed_at = excluded.computed_at",
+        vec![
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::String(serde_json::to_string(&calibration).unwrap_or_default()),
+            rbs::Value::String(calibration.computed_at.clone()),
+        ],
+    )
+    .await?;
+    Ok(calibration)
+}
+
+/// 讀取已保存的校準結果與倍率設定
+async fn load_stored(rb: &RBatis, user_id: &str) -> std::result::Result<Option<(Option<DifficultyCalibration>, bool)>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT data, apply_multipliers FROM difficulty_calibration WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(rows.first().map(|row| {
+        // SQLite 驅動會把 JSON 文字直接解成物件，兩種形式都接受
+        let calibration = match &row["data"] {
+            serde_json::Value::String(data) => serde_json::from_str(data).ok(),
+            data => serde_json::from_value(data.clone()).ok(),
+        };
+        (calibration, row["apply_multipliers"].as_i64() == Some(1))
+    }))
+}
+
+/// 發放任務經驗值時套用使用者的難度倍率（未開啟或沒有建議時維持原值）
+pub async fn adjusted_experience(rb: &RBatis, user_id: &str, difficulty: Option<i32>, base: i32) -> i32 {
+    let Ok(Some((Some(calibration), true))) = load_stored(rb, user_id).await else {
+        return base;
+    };
+    let multiplier = calibration
+        .buckets
+        .iter()
+        .find(|b| Some(b.difficulty) == difficulty)
+        .and_then(|b| b.suggested_multiplier)
+        .unwrap_or(1.0);
+    (base as f64 * multiplier).round() as i32
+}
+
+/// 給教練提示詞的摘要（只列出有建議倍率的難度；沒有時為 None）
+pub fn summarize(calibration: &DifficultyCalibration) -> Option<String> {
+    let lines: Vec<String> = calibration
+        .buckets
+        .iter()
+        .filter_map(|b| {
+            let multiplier = b.suggested_multiplier?;
+            let hours = b
+                .median_hours_to_complete
+                .map(|h| format!("，完成時間中位數 {:.0} 小時", h))
+                .unwrap_or_default();
+            Some(format!(
+                "- 難度 {}：{} 個任務完成 {} 個（{:.0}%）{}，建議經驗倍率 {:.1}",
+                b.difficulty,
+                b.sample_size,
+                b.completed,
+                b.completion_rate.unwrap_or(0.0) * 100.0,
+                hours,
+                multiplier
+            ))
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "使用者各難度任務的實際完成情況（依此判斷任務對他真正的難易，給建議時請參考）：\n{}",
+        lines.join("\n")
+    ))
+}
+
+/// 讀取已保存的校準摘要（教練聊天使用，不觸發重算）
+pub async fn prompt_summary(rb: &RBatis, user_id: &str) -> Option<String> {
+    let (calibration, _) = load_stored(rb, user_id).await.ok()??;
+    summarize(&calibration?)
+}
+
+/// 重算過期（或從未計算）且有任務的使用者；回傳重算人數
+async fn refresh_stale(rb: &RBatis) -> std::result::Result<usize, rbatis::Error> {
+    let cutoff = (Utc::now() - Duration::days(STALE_AFTER_DAYS)).to_rfc3339();
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT DISTINCT t.user_id FROM task t
+             LEFT JOIN difficulty_calibration d ON d.user_id = t.user_id
+             WHERE t.user_id IS NOT NULL AND (d.computed_at IS NULL OR julianday(d.computed_at) < julianday(?))",
+            vec![rbs::Value::String(cutoff)],
+        )
+        .await?;
+    let mut refreshed = 0;
+    for user_id in rows.iter().filter_map(|row| row["user_id"].as_str()) {
+        match refresh(rb, user_id).await {
+            Ok(_) => refreshed += 1,
+            Err(e) => log::warn!("重算難度校準失敗 (user_id: {}): {}", user_id, e),
+        }
+    }
+    Ok(refreshed)
+}
+
+/// 背景工作：定期重算過期的校準結果
+pub fn spawn_job(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(JOB_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match refresh_stale(&rb).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("已重算 {} 位使用者的難度校準", count),
+                Err(e) => log::error!("重算難度校準失敗: {}", e),
+            }
+        }
+    });
+}
+
+fn internal_error(e: rbatis::Error) -> HttpResponse {
+    log::error!("難度校準存取失敗: {}", e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("難度校準存取失敗: {}", e),
+    })
+}
+
+/// 難度校準表與建議倍率（尚未計算過時立即計算）
+pub async fn get_difficulty_calibration(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let rb = rb.get_ref();
+    let (stored, apply_multipliers) = match load_stored(rb, &user_id).await {
+        Ok(stored) => stored.unwrap_or((None, false)),
+        Err(e) => return Ok(internal_error(e)),
+    };
+    let calibration = match stored {
+        Some(calibration) => calibration,
+        None => match refresh(rb, &user_id).await {
+            Ok(calibration) => calibration,
+            Err(e) => return Ok(internal_error(e)),
+        },
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(CalibrationResponse { calibration, apply_multipliers }),
+        message: "獲取難度校準成功".to_string(),
+    }))
+}
+
+/// 設定是否在發放經驗值時套用建議倍率
+pub async fn update_calibration_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<CalibrationSettingsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let result = rb
+        .exec(
+            "INSERT INTO difficulty_calibration (user_id, apply_multipliers) VALUES (?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET apply_multipliers = excluded.apply_multipliers",
+            vec![
+                rbs::Value::String(user_id),
+                rbs::Value::I32(body.apply_multipliers as i32),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({"apply_multipliers": body.apply_multipliers})),
+            message: "難度倍率設定已更新".to_string(),
+        })),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    fn task(difficulty: i32, status: TaskStatus, age_days: i64, hours_to_finish: i64) -> Task {
+        let created = Utc::now() - Duration::days(age_days);
+        serde_json::from_value(json!({
+            "difficulty": difficulty,
+            "status": status.to_i32(),
+            "created_at": created.to_rfc3339(),
+            "updated_at": (created + Duration::hours(hours_to_finish)).to_rfc3339(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compute_requires_min_sample() {
+        let mut tasks = Vec::new();
+        // 難度 4：10 個完成 9 個；難度 2：10 個只完成 3 個；難度 5：樣本不足
+        for i in 0..10 {
+            tasks.push(task(4, if i < 9 { TaskStatus::Completed } else { TaskStatus::Cancelled }, 20, 24));
+            tasks.push(task(2, if i < 3 { TaskStatus::Completed } else { TaskStatus::Pending }, 20, 240));
+        }
+        tasks.push(task(5, TaskStatus::Completed, 20, 48));
+        // 剛建立的未完成任務不列入樣本
+        tasks.push(task(2, TaskStatus::Pending, 1, 0));
+
+        let calibration = compute(&tasks, Utc::now());
+        assert_eq!(calibration.overall_completion_rate, Some(0.6));
+        let bucket = |d: i32| calibration.buckets.iter().find(|b| b.difficulty == d).unwrap();
+        assert_eq!(bucket(2).sample_size, 10);
+        assert_eq!(bucket(2).completion_rate, Some(0.3));
+        assert_eq!(bucket(2).median_hours_to_complete, Some(240.0));
+        assert_eq!(bucket(2).suggested_multiplier, Some(2.0));
+        assert_eq!(bucket(4).suggested_multiplier, Some(0.7));
+        assert_eq!(bucket(5).sample_size, 1);
+        assert_eq!(bucket(5).suggested_multiplier, None);
+        assert_eq!(bucket(1).completion_rate, None);
+
+        let summary = summarize(&calibration).unwrap();
+        assert!(summary.contains("難度 2：10 個任務完成 3 個（30%），完成時間中位數 240 小時，建議經驗倍率 2.0"));
+        assert!(!summary.contains("難度 5"));
+        assert_eq!(summarize(&compute(&tasks[20..], Utc::now())), None);
+    }
+
+    #[actix_web::test]
+    async fn test_calibration_endpoint_and_multiplier_setting() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "calibration").await;
+        let created = (Utc::now() - Duration::days(30)).to_rfc3339();
+        for i in 0..10 {
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, task_type, difficulty, status, created_at, updated_at) VALUES (?, ?, '報帳', 'side', 2, ?, ?, ?)",
+                vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::I32(if i < 2 { 2 } else { 0 }),
+                    rbs::Value::String(created.clone()),
+                    rbs::Value::String(created.clone()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let uri = format!("/api/users/{}/analytics/difficulty-calibration", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["apply_multipliers"], false);
+        assert_eq!(body["data"]["min_sample_size"], MIN_BUCKET_SAMPLE);
+        assert_eq!(body["data"]["buckets"][1]["completion_rate"], 0.2);
+        // 只有一個難度達樣本數時，該難度即為整體基準
+        assert_eq!(body["data"]["buckets"][1]["suggested_multiplier"], 1.0);
+
+        // 未開啟時不套用倍率
+        assert_eq!(adjusted_experience(&rb, &user.id, Some(2), 10).await, 10);
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT data FROM difficulty_calibration WHERE user_id = ?", vec![rbs::Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        let mut calibration: DifficultyCalibration = serde_json::from_value(rows[0]["data"].clone()).unwrap();
+        calibration.buckets[1].suggested_multiplier = Some(1.5);
+        rb.exec(
+            "UPDATE difficulty_calibration SET data = ? WHERE user_id = ?",
+            vec![rbs::Value::String(serde_json::to_string(&calibration).unwrap()), rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("{}/settings", uri))
+            .insert_header(user.auth())
+            .set_json(json!({"apply_multipliers": true}))
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(adjusted_experience(&rb, &user.id, Some(2), 10).await, 15);
+        assert_eq!(adjusted_experience(&rb, &user.id, Some(5), 10).await, 10);
+        assert!(prompt_summary(&rb, &user.id).await.unwrap().contains("難度 2"));
+
+        let other = test_utils::create_user(&app, "calibration_other").await;
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 403);
+    }
+}
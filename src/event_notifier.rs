@@ -0,0 +1,638 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{NaiveTime, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Achievement, NotificationHistory};
+
+// 事件廣播通道容量（落後過多的訂閱者會略過舊事件）
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+// SSE 心跳間隔，避免代理伺服器關閉閒置連線
+const SSE_KEEP_ALIVE_SECS: u64 = 30;
+// 單次附在回應中或拉取的未讀事件上限
+const UNSEEN_EVENTS_LIMIT: usize = 20;
+
+/// 使用者事件（寫入通知歷史並推送到 SSE 串流）
+#[derive(Debug, Clone, Serialize)]
+pub struct UserEvent {
+    pub id: String,
+    pub user_id: String,
+    pub event_type: String,
+    pub title: String,
+    pub body: String,
+    pub data: serde_json::Value,
+    pub created_at: String,
+}
+
+static EVENT_BUS: OnceLock<broadcast::Sender<UserEvent>> = OnceLock::new();
+
+fn event_bus() -> &'static broadcast::Sender<UserEvent> {
+    EVENT_BUS.get_or_init(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+}
+
+/// 通知成就解鎖；同一批解鎖的成就合併為一則通知
+pub async fn notify_achievements_unlocked(rb: &RBatis, user_id: &str, achievements: &[Achievement]) {
+    if achievements.is_empty() {
+        return;
+    }
+
+    let names: Vec<String> = achievements
+        .iter()
+        .map(|a| a.name.clone().unwrap_or_else(|| "未知成就".to_string()))
+        .collect();
+    let total_xp: i32 = achievements.iter().map(|a| a.experience_reward.unwrap_or(0)).sum();
+
+    let (title, body) = if names.len() == 1 {
+        (
+            format!("🎉 解鎖成就：{}！+{} XP", names[0], total_xp),
+            achievements[0].description.clone().unwrap_or_else(|| "繼續保持，解鎖更多成就吧！".to_string()),
+        )
+    } else {
+        (
+            format!("🎉 一次解鎖 {} 個成就！+{} XP", names.len(), total_xp),
+            names.join("、"),
+        )
+    };
+
+    let data = serde_json::json!({
+        "achievement_ids": achievements.iter().filter_map(|a| a.id.clone()).collect::<Vec<_>>(),
+        "achievement_names": names,
+        "experience_reward": total_xp,
+    });
+
+    dispatch(rb, user_id, "achievement_unlocked", title, body, data).await;
+}
+
+/// 通知使用者等級提升
+pub async fn notify_level_up(rb: &RBatis, user_id: &str, previous_level: i32, new_level: i32) {
+    let title = format!("⬆️ 升級了！你已達到 Lv.{}", new_level);
+    let body = format!("從 Lv.{} 升到 Lv.{}，繼續前進吧！", previous_level, new_level);
+    let data = serde_json::json!({
+        "previous_level": previous_level,
+        "new_level": new_level,
+    });
+
+    dispatch(rb, user_id, "level_up", title, body, data).await;
+}
+
+/// 通知重複性任務期滿結算結果
+pub async fn notify_recurring_task_finished(
+    rb: &RBatis,
+    user_id: &str,
+    task_title: &str,
+    succeeded: bool,
+    completion_rate: f64,
+    target_rate: f64,
+) {
+    let (title, body) = if succeeded {
+        (
+            format!("🏆 「{}」挑戰成功！", task_title),
+            format!("完成率 {:.0}%，達成目標 {:.0}%，太棒了！", completion_rate * 100.0, target_rate * 100.0),
+        )
+    } else {
+        (
+            format!("「{}」已結束", task_title),
+            format!("完成率 {:.0}%，未達目標 {:.0}%，下次再接再厲！", completion_rate * 100.0, target_rate * 100.0),
+        )
+    };
+    let data = serde_json::json!({
+        "succeeded": succeeded,
+        "completion_rate": completion_rate,
+        "target_rate": target_rate,
+    });
+
+    dispatch(rb, user_id, "recurring_task_finished", title, body, data).await;
+}
+
+/// 通知挑戰任務截止結算結果（失敗時附上扣除的經驗值）
+pub async fn notify_challenge_finished(rb: &RBatis, user_id: &str, outcome: &crate::challenges::ChallengeOutcome) {
+    let rates = format!(
+        "達成率 {:.0}%，{}目標 {:.0}%",
+        outcome.progress_rate * 100.0,
+        if outcome.succeeded { "超過" } else { "未達" },
+        outcome.target_rate * 100.0
+    );
+    let (title, body) = if outcome.succeeded {
+        (format!("🏆 挑戰「{}」成功！", outcome.title), format!("{}，說到做到！", rates))
+    } else if outcome.xp_penalty > 0 {
+        (
+            format!("挑戰「{}」失敗", outcome.title),
+            format!("{}，扣除 {} XP。調整一下再挑戰一次吧！", rates, outcome.xp_penalty),
+        )
+    } else {
+        (format!("挑戰「{}」失敗", outcome.title), format!("{}，調整一下再挑戰一次吧！", rates))
+    };
+    let data = serde_json::to_value(outcome).unwrap_or_default();
+
+    dispatch(rb, user_id, "challenge_finished", title, body, data).await;
+}
+
+/// 通知職業主線完成
+pub async fn notify_mainline_completed(rb: &RBatis, user_id: &str, mainline_id: &str, career: &str) {
+    let title = format!("🎓 「{}」主線完成！", career);
+    let body = "所有主線任務都已完成，恭喜你踏出重要的一步！".to_string();
+    let data = serde_json::json!({
+        "mainline_id": mainline_id,
+        "career": career,
+    });
+
+    dispatch(rb, user_id, "mainline_completed", title, body, data).await;
+}
+
+/// 寫入排程通知（早安摘要、晚間總結、連續紀錄提醒等，內容由產生器提供）
+///
+/// 一律寫入通知歷史，未訂閱推送或未啟用 push-notifications 時仍可在通知中心看到。
+pub async fn notify_scheduled(rb: &RBatis, user_id: &str, event_type: &str, notification: &serde_json::Value) {
+    let (title, body, data) = scheduled_parts(notification);
+    dispatch(rb, user_id, event_type, title, body, data).await;
+}
+
+/// 排程通知內容寫入通知歷史時的標題、內文與附加資料
+pub fn scheduled_parts(notification: &serde_json::Value) -> (String, String, serde_json::Value) {
+    let title = notification["title"].as_str().unwrap_or("人生升級系統").to_string();
+    let body = notification["body"].as_str().unwrap_or_default().to_string();
+    let data = notification.get("data").cloned().unwrap_or(serde_json::Value::Null);
+    (title, body, data)
+}
+
+/// 寫入通知歷史、廣播到 SSE，並在允許時發送 Web Push
+async fn dispatch(
+    rb: &RBatis,
+    user_id: &str,
+    event_type: &str,
+    title: String,
+    body: String,
+    data: serde_json::Value,
+) {
+    let now = Utc::now();
+    let event = UserEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        event_type: event_type.to_string(),
+        title,
+        body,
+        data,
+        created_at: now.to_rfc3339(),
+    };
+
+    let history = NotificationHistory {
+        id: Some(event.id.clone()),
+        user_id: Some(event.user_id.clone()),
+        event_type: Some(event.event_type.clone()),
+        title: Some(event.title.clone()),
+        body: Some(event.body.clone()),
+        data: Some(event.data.to_string()),
+        pushed: Some(false),
+        seen_at: None,
+        read_at: None,
+        created_at: Some(now),
+    };
+    if let Err(e) = NotificationHistory::insert(rb, &history).await {
+        log::error!("寫入通知歷史失敗 (user_id: {}): {}", user_id, e);
+    }
+
+    // 沒有訂閱者時 send 會回傳錯誤，可忽略
+    let _ = event_bus().send(event.clone());
+
+    #[cfg(feature = "push-notifications")]
+    {
+        if should_push(rb, user_id, event_type).await {
+            match send_push(rb, &event).await {
+                Ok(_) => {
+                    let _ = rb
+                        .exec(
+                            "UPDATE notification_history SET pushed = 1 WHERE id = ?",
+                            vec![rbs::Value::String(event.id.clone())],
+                        )
+                        .await;
+                }
+                Err(e) => log::warn!("事件推送失敗 (user_id: {}): {}", user_id, e),
+            }
+        }
+    }
+}
+
+/// 依使用者通知設定判斷是否可推送（未建立設定時視為啟用）
+#[cfg(feature = "push-notifications")]
+async fn should_push(rb: &RBatis, user_id: &str, event_type: &str) -> bool {
+    let settings = crate::notification_categories::load_settings(rb, user_id).await;
+    let tz = chrono::FixedOffset::east_opt(8 * 3600).unwrap();
+    let now = Utc::now().with_timezone(&tz).time();
+    push_block_reason(settings.as_ref(), event_type, now).is_none()
+}
+
+/// 事件在指定時間（UTC+8）不可推送的原因：總開關、類別開關或勿擾時段；可推送時為 None
+///
+/// 推送排程的模擬模式也使用此判斷，確保模擬結果與實際推送一致
+pub fn push_block_reason(
+    settings: Option<&crate::models::UserNotificationSettings>,
+    event_type: &str,
+    now: NaiveTime,
+) -> Option<&'static str> {
+    let settings = settings?;
+    if !settings.enabled.unwrap_or(true) {
+        return Some("enabled");
+    }
+    if let Some(category) = crate::notification_categories::NotificationCategory::for_event(event_type) {
+        if !crate::notification_categories::category_enabled(settings, category) {
+            return Some("category");
+        }
+    }
+    match (settings.quiet_hours_start.as_deref(), settings.quiet_hours_end.as_deref()) {
+        (Some(start), Some(end)) if is_within_quiet_hours(now, start, end) => Some("quiet_hours"),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "push-notifications")]
+async fn send_push(rb: &RBatis, event: &UserEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let service = crate::push_service::PushService::new()?;
+    let payload = crate::models::PushNotificationPayload {
+        title: event.title.clone(),
+        body: event.body.clone(),
+        icon: Some("/icon-192x192.png".to_string()),
+        badge: Some("/badge-72x72.png".to_string()),
+        tag: Some(event.event_type.clone()),
+        data: Some(serde_json::json!({
+            "event_id": event.id,
+            "event_type": event.event_type,
+            "url": event.data.get("url"),
+            "detail": event.data,
+        })),
+    };
+    service.send_notification_to_user(rb, &event.user_id, &payload).await
+}
+
+/// 判斷時間是否落在勿擾時段內（支援跨午夜，例如 23:00 ~ 07:00）
+pub fn is_within_quiet_hours(now: NaiveTime, start: &str, end: &str) -> bool {
+    let (Ok(start), Ok(end)) = (
+        NaiveTime::parse_from_str(start, "%H:%M"),
+        NaiveTime::parse_from_str(end, "%H:%M"),
+    ) else {
+        return false;
+    };
+
+    if start <= end {
+        now >= start && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// 取得使用者通知歷史（最新 50 筆）
+pub async fn get_notification_history(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let result: Result<Vec<NotificationHistory>, _> = rb
+        .query_decode(
+            "SELECT * FROM notification_history WHERE user_id = ? ORDER BY created_at DESC LIMIT 50",
+            vec![rbs::Value::String(user_id.clone())],
+        )
+        .await;
+
+    match result {
+        Ok(history) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(history),
+            message: "獲取通知歷史成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取通知歷史失敗: {}", e),
+        })),
+    }
+}
+
+impl From<NotificationHistory> for UserEvent {
+    fn from(history: NotificationHistory) -> Self {
+        let data = history
+            .data
+            .as_deref()
+            .and_then(|d| serde_json::from_str(d).ok())
+            .unwrap_or(serde_json::Value::Null);
+        UserEvent {
+            id: history.id.unwrap_or_default(),
+            user_id: history.user_id.unwrap_or_default(),
+            event_type: history.event_type.unwrap_or_default(),
+            title: history.title.unwrap_or_default(),
+            body: history.body.unwrap_or_default(),
+            data,
+            created_at: history.created_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        }
+    }
+}
+
+/// 查詢尚未送達的事件（由舊到新）
+pub async fn fetch_unseen_events(rb: &RBatis, user_id: &str) -> Result<Vec<UserEvent>, rbatis::Error> {
+    let history: Vec<NotificationHistory> = rb
+        .query_decode(
+            "SELECT * FROM notification_history WHERE user_id = ? AND seen_at IS NULL ORDER BY created_at ASC LIMIT ?",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::U64(UNSEEN_EVENTS_LIMIT as u64)],
+        )
+        .await?;
+    Ok(history.into_iter().map(UserEvent::from).collect())
+}
+
+/// 將事件標記為已送達；回傳這次才被標記的事件 id（已標記過或不屬於該使用者的會略過）
+pub async fn mark_events_seen(rb: &RBatis, user_id: &str, event_ids: &[String]) -> Result<Vec<String>, rbatis::Error> {
+    let now = Utc::now().to_rfc3339();
+    let mut acked = Vec::new();
+    for event_id in event_ids {
+        let result = rb
+            .exec(
+                "UPDATE notification_history SET seen_at = ? WHERE id = ? AND user_id = ? AND seen_at IS NULL",
+                vec![
+                    rbs::Value::String(now.clone()),
+                    rbs::Value::String(event_id.clone()),
+                    rbs::Value::String(user_id.to_string()),
+                ],
+            )
+            .await?;
+        if result.rows_affected > 0 {
+            acked.push(event_id.clone());
+        }
+    }
+    Ok(acked)
+}
+
+/// 取出未送達的事件並標記為已送達，用於附在一般 API 回應中
+///
+/// 只回傳由這次請求標記的事件，同時進行的請求不會重複帶出同一事件；
+/// 查詢失敗時回傳空列表，不影響主要回應。
+pub async fn take_unseen_events(rb: &RBatis, user_id: &str) -> Vec<UserEvent> {
+    let events = match fetch_unseen_events(rb, user_id).await {
+        Ok(events) => events,
+        Err(e) => {
+            log::warn!("查詢未讀事件失敗 (user_id: {}): {}", user_id, e);
+            return Vec::new();
+        }
+    };
+    if events.is_empty() {
+        return events;
+    }
+    let ids: Vec<String> = events.iter().map(|e| e.id.clone()).collect();
+    match mark_events_seen(rb, user_id, &ids).await {
+        Ok(acked) => events.into_iter().filter(|e| acked.contains(&e.id)).collect(),
+        Err(e) => {
+            log::warn!("標記事件已送達失敗 (user_id: {}): {}", user_id, e);
+            Vec::new()
+        }
+    }
+}
+
+/// 附帶未讀事件的 API 回應
+#[derive(Serialize)]
+pub struct ApiResponseWithEvents<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub message: String,
+    pub unseen_events: Vec<UserEvent>,
+}
+
+/// 拉取未讀事件（不會標記為已送達，需另外呼叫 ack）
+pub async fn get_unseen_events(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match fetch_unseen_events(rb.get_ref(), &user_id).await {
+        Ok(events) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(events),
+            message: "獲取未讀事件成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取未讀事件失敗: {}", e),
+        })),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AckEventsRequest {
+    pub event_ids: Vec<String>,
+}
+
+/// 確認事件已收到；重複確認同一事件不會重複計算
+pub async fn ack_events(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<AckEventsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match mark_events_seen(rb.get_ref(), &user_id, &req.event_ids).await {
+        Ok(acked) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "acked": acked })),
+            message: "事件已確認".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("確認事件失敗: {}", e),
+        })),
+    }
+}
+
+/// 使用者事件串流 (SSE)
+///
+/// 即時推送成就解鎖、升級等事件
+pub async fn stream_user_events(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    // 確認使用者存在，避免替不存在的使用者保持連線
+    match crate::models::User::select_by_map(rb.get_ref(), value!{"id": &user_id}).await {
+        Ok(users) if !users.is_empty() => {}
+        _ => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "找不到該使用者".to_string(),
+            }));
+        }
+    }
+
+    let mut rx = event_bus().subscribe();
+    let stream = async_stream::stream! {
+        let mut keep_alive = tokio::time::interval(Duration::from_secs(SSE_KEEP_ALIVE_SECS));
+        loop {
+            tokio::select! {
+                received = rx.recv() => match received {
+                    Ok(event) if event.user_id == user_id => {
+                        let data = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
+                        yield Ok::<_, actix_web::Error>(web::Bytes::from(format!("event: {}\ndata: {}\n\n", event.event_type, data)));
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("SSE 訂閱者落後，略過 {} 個事件", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => {
+                    yield Ok::<_, actix_web::Error>(web::Bytes::from(": keep-alive\n\n"));
+                }
+            }
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(Box::pin(stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours_same_day() {
+        assert!(is_within_quiet_hours(t("13:30"), "13:00", "14:00"));
+        assert!(!is_within_quiet_hours(t("14:00"), "13:00", "14:00"));
+        assert!(!is_within_quiet_hours(t("08:00"), "13:00", "14:00"));
+    }
+
+    #[test]
+    fn test_quiet_hours_across_midnight() {
+        assert!(is_within_quiet_hours(t("23:30"), "23:00", "07:00"));
+        assert!(is_within_quiet_hours(t("03:00"), "23:00", "07:00"));
+        assert!(!is_within_quiet_hours(t("07:00"), "23:00", "07:00"));
+        assert!(!is_within_quiet_hours(t("12:00"), "23:00", "07:00"));
+    }
+
+    #[test]
+    fn test_quiet_hours_invalid_format() {
+        assert!(!is_within_quiet_hours(t("03:00"), "23", "07:00"));
+    }
+
+    #[actix_web::test]
+    async fn test_notification_history_rejects_other_users() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let owner = crate::test_utils::create_user(&app, "history_owner").await;
+        let intruder = crate::test_utils::create_user(&app, "history_intruder").await;
+        notify_level_up(&rb, &owner.id, 1, 2).await;
+
+        let uri = format!("/api/users/{}/notifications", owner.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(intruder.auth()).to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 403, "{}", body);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(owner.auth()).to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_homepage_does_not_take_other_users_events() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let owner = crate::test_utils::create_user(&app, "homepage_owner").await;
+        let intruder = crate::test_utils::create_user(&app, "homepage_intruder").await;
+        notify_level_up(&rb, &owner.id, 1, 2).await;
+
+        let uri = format!("/api/tasks/homepage?user_id={}", owner.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(intruder.auth()).to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 403, "{}", body);
+        assert!(body.get("unseen_events").is_none(), "{}", body);
+
+        // 事件仍然留給本人
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(owner.auth()).to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+        assert_eq!(body["unseen_events"].as_array().unwrap().len(), 1, "{}", body);
+        assert_eq!(body["unseen_events"][0]["event_type"], "level_up");
+    }
+
+    #[actix_web::test]
+    async fn test_event_stream_rejects_other_users() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let owner = crate::test_utils::create_user(&app, "stream_owner").await;
+        let intruder = crate::test_utils::create_user(&app, "stream_intruder").await;
+
+        let uri = format!("/api/users/{}/events", owner.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(intruder.auth()).to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 403, "{}", body);
+
+        // 本人可以訂閱（串流不會結束，只檢查回應標頭）
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(owner.auth()).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+    }
+
+    #[tokio::test]
+    async fn test_unseen_events_are_delivered_and_acked_once() {
+        let path = std::env::temp_dir().join(format!("lifeup_unseen_events_{}.db", uuid::Uuid::new_v4()));
+        let rb = RBatis::new();
+        rb.init(rbdc_sqlite::driver::SqliteDriver {}, &format!("sqlite://{}", path.display())).unwrap();
+        crate::create_tables(&rb).await;
+        crate::migrate_database(&rb).await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+
+        notify_level_up(&rb, "u1", 1, 2).await;
+        notify_mainline_completed(&rb, "u1", "m1", "資料科學家").await;
+
+        // 拉取不會標記為已送達
+        let pulled = fetch_unseen_events(&rb, "u1").await.unwrap();
+        assert_eq!(pulled.len(), 2);
+        assert_eq!(fetch_unseen_events(&rb, "u1").await.unwrap().len(), 2);
+
+        // 確認只計算一次，其他使用者無法確認
+        let first_id = vec![pulled[0].id.clone()];
+        assert!(mark_events_seen(&rb, "u2", &first_id).await.unwrap().is_empty());
+        assert_eq!(mark_events_seen(&rb, "u1", &first_id).await.unwrap(), first_id);
+        assert!(mark_events_seen(&rb, "u1", &first_id).await.unwrap().is_empty());
+
+        // 附在回應中的事件送出後即標記為已送達
+        let delivered = take_unseen_events(&rb, "u1").await;
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].event_type, "mainline_completed");
+        assert_eq!(delivered[0].data["career"], "資料科學家");
+        assert!(take_unseen_events(&rb, "u1").await.is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
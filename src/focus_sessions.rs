@@ -0,0 +1,490 @@
+// 專注（番茄鐘）時段：由後端記錄開始、完成與中止，確保跨裝置的連續紀錄與專注力獎勵一致
+//
+// 每位使用者同時只能有一個進行中的時段；完成時給予少量經驗值與專注力 +1，
+// 並計入「focus_session_complete」類型的成就條件。
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, NaiveDate, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::FocusConfig;
+use crate::models::FocusSession;
+
+pub const STATUS_ACTIVE: &str = "active";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_ABORTED: &str = "aborted";
+
+// 完成專注時段時的專注力成長
+const FOCUS_ATTRIBUTE_GAIN: i32 = 1;
+// 客戶端計時與伺服器時間的誤差容許
+const COMPLETION_GRACE_SECS: i64 = 30;
+const MAX_STATS_DAYS: i64 = 365;
+
+static FOCUS: OnceLock<FocusConfig> = OnceLock::new();
+
+fn config() -> &'static FocusConfig {
+    FOCUS.get_or_init(FocusConfig::default)
+}
+
+/// 啟動時套用設定
+pub fn init(config: FocusConfig) {
+    log::info!(
+        "專注時段: 每次完成 {} 經驗值，預設 {} 分鐘，上限 {} 分鐘",
+        config.experience_per_session,
+        config.default_duration_minutes,
+        config.max_duration_minutes
+    );
+    let _ = FOCUS.set(config);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartFocusRequest {
+    pub task_id: Option<String>,
+    pub duration_minutes: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FocusCompletion {
+    pub session: FocusSession,
+    pub experience_gained: i32,
+    pub attribute_gains: std::collections::HashMap<String, i32>,
+    pub unlocked_achievements: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FocusStatsQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FocusDay {
+    pub date: String,
+    pub sessions: i32,
+    pub minutes: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FocusStats {
+    pub user_id: String,
+    pub days: i64,
+    pub total_sessions: i32,
+    pub total_minutes: i32,
+    pub current_streak_days: i32,
+    pub daily: Vec<FocusDay>,
+}
+
+fn json_error(status: actix_web::http::StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+async fn active_session(rb: &RBatis, user_id: &str) -> Result<Option<FocusSession>, rbatis::Error> {
+    let sessions = FocusSession::select_by_map(rb, value!{"user_id": user_id, "status": STATUS_ACTIVE}).await?;
+    Ok(sessions.into_iter().next())
+}
+
+fn active_conflict(session: FocusSession) -> HttpResponse {
+    HttpResponse::Conflict().json(ApiResponse {
+        success: false,
+        data: Some(session),
+        message: "已有進行中的專注時段，請先完成或中止".to_string(),
+    })
+}
+
+/// 開始專注時段（可綁定任務）
+pub async fn start_focus_session(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<StartFocusRequest>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let config = config();
+    let duration = req.duration_minutes.unwrap_or(config.default_duration_minutes);
+    if duration < 1 || duration > config.max_duration_minutes {
+        return Ok(json_error(
+            StatusCode::BAD_REQUEST,
+            format!("專注時間需介於 1 到 {} 分鐘之間", config.max_duration_minutes),
+        ));
+    }
+
+    if let Some(task_id) = &req.task_id {
+        if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), task_id, Some(&user_id)).await {
+            return Ok(e.into_response());
+        }
+    }
+
+    match active_session(rb.get_ref(), &user_id).await {
+        Ok(Some(active)) => return Ok(active_conflict(active)),
+        Ok(None) => {}
+        Err(e) => return Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢專注時段失敗: {}", e))),
+    }
+
+    let session = FocusSession {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id.clone()),
+        task_id: req.task_id.clone(),
+        duration_minutes: Some(duration),
+        status: Some(STATUS_ACTIVE.to_string()),
+        experience_gained: Some(0),
+        started_at: Some(Utc::now()),
+        ended_at: None,
+    };
+    if let Err(e) = FocusSession::insert(rb.get_ref(), &session).await {
+        // 唯一索引擋下同時開始的第二個時段
+        if let Ok(Some(active)) = active_session(rb.get_ref(), &user_id).await {
+            return Ok(active_conflict(active));
+        }
+        return Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立專注時段失敗: {}", e)));
+    }
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(session),
+        message: format!("專注時段開始（{} 分鐘）", duration),
+    }))
+}
+
+// 只結束仍在進行中的時段，回傳是否由本次請求結束（避免重複領取獎勵）
+async fn finish_session(rb: &RBatis, session_id: &str, status: &str, experience: i32) -> Result<bool, rbatis::Error> {
+    let result = rb
+        .exec(
+            "UPDATE focus_session SET status = ?, ended_at = ?, experience_gained = ? WHERE id = ? AND status = ?",
+            vec![
+                rbs::Value::String(status.to_string()),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::I32(experience),
+                rbs::Value::String(session_id.to_string()),
+                rbs::Value::String(STATUS_ACTIVE.to_string()),
+            ],
+        )
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// 完成專注時段：給予經驗值與專注力獎勵，並檢查成就
+pub async fn complete_focus_session(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let session_id = path.into_inner();
+    let mut session = match crate::ownership::assert_focus_session_owner(rb.get_ref(), &session_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(session) => session,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if session.status.as_deref() != Some(STATUS_ACTIVE) {
+        return Ok(json_error(StatusCode::CONFLICT, "專注時段已結束"));
+    }
+    let user_id = session.user_id.clone().unwrap_or_default();
+
+    let now = Utc::now();
+    let duration = session.duration_minutes.unwrap_or(0);
+    let ends_at = session.started_at.unwrap_or(now) + Duration::minutes(duration as i64);
+    let remaining = (ends_at - now).num_seconds();
+    if remaining > COMPLETION_GRACE_SECS {
+        return Ok(json_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("專注時間尚未結束（剩餘約 {} 分鐘）", (remaining + 59) / 60),
+        ));
+    }
+
+    let experience = config().experience_per_session.max(0);
+    match finish_session(rb.get_ref(), &session_id, STATUS_COMPLETED, experience).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(json_error(StatusCode::CONFLICT, "專注時段已結束")),
+        Err(e) => return Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新專注時段失敗: {}", e))),
+    }
+    session.status = Some(STATUS_COMPLETED.to_string());
+    session.ended_at = Some(now);
+    session.experience_gained = Some(experience);
+
+    if experience > 0 {
+        if let Err(e) = crate::services::experience::apply_experience_gain(rb.get_ref(), &user_id, experience).await {
+            log::warn!("專注時段經驗值發放失敗: {}", e);
+        }
+    }
+
+    let deltas = [("focus".to_string(), FOCUS_ATTRIBUTE_GAIN)];
+    let attribute_gains = match crate::attribute_rewards::apply_attribute_deltas(
+        rb.get_ref(),
+        &user_id,
+        &deltas,
+        crate::attribute_rewards::SOURCE_FOCUS,
+        session.task_id.as_deref(),
+    )
+    .await
+    {
+        Ok(result) => result.applied_gains(),
+        Err(e) => {
+            log::warn!("專注力獎勵套用失敗: {}", e);
+            Default::default()
+        }
+    };
+
+    let unlocked_achievements = match crate::achievement_service::AchievementService::check_and_unlock_achievements(rb.get_ref(), &user_id).await {
+        Ok(unlocked) => unlocked.into_iter().filter_map(|a| a.name).collect(),
+        Err(e) => {
+            log::warn!("檢查成就失敗: {}", e);
+            Vec::new()
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(FocusCompletion {
+            session,
+            experience_gained: experience,
+            attribute_gains,
+            unlocked_achievements,
+        }),
+        message: "專注時段完成".to_string(),
+    }))
+}
+
+/// 中止專注時段（不給予獎勵）
+pub async fn abort_focus_session(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let session_id = path.into_inner();
+    let mut session = match crate::ownership::assert_focus_session_owner(rb.get_ref(), &session_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(session) => session,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if session.status.as_deref() != Some(STATUS_ACTIVE) {
+        return Ok(json_error(StatusCode::CONFLICT, "專注時段已結束"));
+    }
+    match finish_session(rb.get_ref(), &session_id, STATUS_ABORTED, 0).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(json_error(StatusCode::CONFLICT, "專注時段已結束")),
+        Err(e) => return Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新專注時段失敗: {}", e))),
+    }
+    session.status = Some(STATUS_ABORTED.to_string());
+    session.ended_at = Some(Utc::now());
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(session),
+        message: "專注時段已中止".to_string(),
+    }))
+}
+
+/// 將已完成的時段（結束日期, 分鐘數）整理為最近 days 天的每日統計，並計算連續天數
+///
+/// 連續天數從今天往回計算；今天尚未完成時從昨天起算，不算中斷
+pub fn summarize(completed: &[(NaiveDate, i32)], today: NaiveDate, days: i64) -> (Vec<FocusDay>, i32) {
+    let first_day = today - Duration::days(days - 1);
+    let mut per_day: BTreeMap<NaiveDate, (i32, i32)> = (0..days)
+        .map(|offset| (first_day + Duration::days(offset), (0, 0)))
+        .collect();
+    for (date, minutes) in completed {
+        if let Some(entry) = per_day.get_mut(date) {
+            entry.0 += 1;
+            entry.1 += minutes;
+        }
+    }
+    let daily = per_day
+        .into_iter()
+        .map(|(date, (sessions, minutes))| FocusDay {
+            date: date.format("%Y-%m-%d").to_string(),
+            sessions,
+            minutes,
+        })
+        .collect();
+
+    let active_days: HashSet<NaiveDate> = completed.iter().map(|(date, _)| *date).collect();
+    let mut day = if active_days.contains(&today) { today } else { today - Duration::days(1) };
+    let mut streak = 0;
+    while active_days.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    (daily, streak)
+}
+
+/// 專注統計：每日時段數、專注分鐘數與連續天數（本人或管理員）
+pub async fn get_focus_stats(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<FocusStatsQuery>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let user_id = path.into_inner();
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !crate::auth::is_admin_request(&http_req) {
+        return Ok(json_error(StatusCode::FORBIDDEN, "無權限查看此使用者的專注統計"));
+    }
+    let days = query.days.unwrap_or(30).clamp(1, MAX_STATS_DAYS);
+
+    let sessions = match FocusSession::select_by_map(rb.get_ref(), value!{"user_id": &user_id, "status": STATUS_COMPLETED}).await {
+        Ok(sessions) => sessions,
+        Err(e) => return Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢專注統計失敗: {}", e))),
+    };
+    let tz = crate::user_settings::user_timezone(rb.get_ref(), &user_id).await;
+    let completed: Vec<(NaiveDate, i32)> = sessions
+        .iter()
+        .filter_map(|s| {
+            let finished = s.ended_at.or(s.started_at)?;
+            Some((crate::local_date::local_date_in(finished, tz), s.duration_minutes.unwrap_or(0)))
+        })
+        .collect();
+    let (daily, current_streak_days) = summarize(&completed, crate::local_date::local_today_in(tz), days);
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(FocusStats {
+            user_id,
+            days,
+            total_sessions: daily.iter().map(|d| d.sessions).sum(),
+            total_minutes: daily.iter().map(|d| d.minutes).sum(),
+            current_streak_days,
+            daily,
+        }),
+        message: "獲取專注統計成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_summarize_fills_days_and_counts_streak() {
+        let today = date("2026-10-17");
+        let completed = vec![
+            (date("2026-10-16"), 25),
+            (date("2026-10-16"), 50),
+            (date("2026-10-15"), 25),
+            (date("2026-10-13"), 25),
+            (date("2026-09-01"), 25),
+        ];
+        let (daily, streak) = summarize(&completed, today, 5);
+        assert_eq!(daily.len(), 5);
+        assert_eq!(daily[0], FocusDay { date: "2026-10-13".to_string(), sessions: 1, minutes: 25 });
+        assert_eq!(daily[3], FocusDay { date: "2026-10-16".to_string(), sessions: 2, minutes: 75 });
+        assert_eq!(daily[4].sessions, 0);
+        // 今天尚未專注時從昨天起算
+        assert_eq!(streak, 2);
+
+        let (_, streak) = summarize(&[(today, 25)], today, 1);
+        assert_eq!(streak, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_focus_session_lifecycle_rewards_and_stats() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "focus_user").await;
+        let other = test_utils::create_user(&app, "focus_other").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/focus/start")
+            .insert_header(user.auth())
+            .set_json(json!({"duration_minutes": 25}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let session_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        // 同時只能有一個進行中的時段
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/focus/start")
+            .insert_header(user.auth())
+            .set_json(json!({}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["data"]["id"], session_id.as_str());
+
+        // 時間未到不能完成；其他使用者不能操作
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/focus/{}/complete", session_id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/focus/{}/complete", session_id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+
+        let started = (Utc::now() - Duration::minutes(26)).to_rfc3339();
+        rb.exec("UPDATE focus_session SET started_at = ? WHERE id = ?", vec![started.into(), session_id.clone().into()])
+            .await
+            .unwrap();
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/focus/{}/complete", session_id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["experience_gained"], config().experience_per_session);
+        assert_eq!(body["data"]["attribute_gains"]["focus"], 1);
+
+        // 重複完成不會再次給予獎勵
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/focus/{}/complete", session_id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::CONFLICT);
+
+        // 中止的時段不計入統計
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/focus/start")
+            .insert_header(user.auth())
+            .set_json(json!({"duration_minutes": 50}))
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/focus/{}/abort", body["data"]["id"].as_str().unwrap()))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/focus/stats?days=7", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total_sessions"], 1);
+        assert_eq!(body["data"]["total_minutes"], 25);
+        assert_eq!(body["data"]["current_streak_days"], 1);
+        assert_eq!(body["data"]["daily"].as_array().unwrap().len(), 7);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/focus/stats", user.id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,504 @@
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Friendship, User, UserProfile};
+
+pub const FRIENDSHIP_PENDING: &str = "pending";
+pub const FRIENDSHIP_ACCEPTED: &str = "accepted";
+
+#[derive(Deserialize)]
+pub struct FriendRequestPayload {
+    pub friend_id: Option<String>,
+    pub email: Option<String>,
+}
+
+// 好友摘要：只包含雙方同意分享的欄位
+#[derive(Serialize)]
+pub struct FriendSummary {
+    pub streak: i32,
+    pub today_completed: i64,
+    pub today_total: i64,
+    pub level: i32,
+}
+
+#[derive(Serialize)]
+pub struct FriendListItem {
+    pub friendship_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub status: String,
+    // 是否為對方送出的邀請（需要由自己接受）
+    pub incoming: bool,
+}
+
+fn unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "需要 JWT 認證".to_string(),
+    })
+}
+
+fn internal_error(action: &str, e: impl std::fmt::Display) -> HttpResponse {
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("{}失敗: {}", action, e),
+    })
+}
+
+/// 查詢兩位使用者之間的好友關係（不分方向）
+pub async fn find_friendship(rb: &RBatis, user_a: &str, user_b: &str) -> Result<Option<Friendship>, rbatis::Error> {
+    let rows: Vec<Friendship> = rb
+        .query_decode(
+            "SELECT * FROM friendship
+             WHERE (requester_id = ? AND addressee_id = ?) OR (requester_id = ? AND addressee_id = ?)
+             LIMIT 1",
+            vec![
+                rbs::Value::String(user_a.to_string()),
+                rbs::Value::String(user_b.to_string()),
+                rbs::Value::String(user_b.to_string()),
+                rbs::Value::String(user_a.to_string()),
+            ],
+        )
+        .await?;
+    Ok(rows.into_iter().next())
+}
+
+/// 是否為已接受的好友
+pub async fn is_accepted_friend(rb: &RBatis, user_a: &str, user_b: &str) -> Result<bool, rbatis::Error> {
+    Ok(find_friendship(rb, user_a, user_b)
+        .await?
+        .is_some_and(|f| f.status.as_deref() == Some(FRIENDSHIP_ACCEPTED)))
+}
+
+/// 取得使用者所有已接受好友的 user_id
+#[cfg_attr(not(feature = "push-notifications"), allow(dead_code))]
+pub async fn accepted_friend_ids(rb: &RBatis, user_id: &str) -> Result<Vec<String>, rbatis::Error> {
+    let rows: Vec<Friendship> = rb
+        .query_decode(
+            "SELECT * FROM friendship WHERE status = ? AND (requester_id = ? OR addressee_id = ?)",
+            vec![
+                rbs::Value::String(FRIENDSHIP_ACCEPTED.to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(user_id.to_string()),
+            ],
+        )
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|f| {
+            if f.requester_id.as_deref() == Some(user_id) {
+                f.addressee_id
+            } else {
+                f.requester_id
+            }
+        })
+        .collect())
+}
+
+/// 今日任務完成數與總數（以該使用者時區的今天計算）
+///
+/// 總數 = 今日的每日任務 + 今日完成的其他任務
+pub async fn today_task_counts(rb: &RBatis, user_id: &str) -> (i64, i64) {
+    let today = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb, user_id).await);
+
+    let completed: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) as count FROM task
+             WHERE user_id = ? AND status IN (2, 6) AND (task_date = ? OR date(updated_at) = date('now'))",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(today.clone())],
+        )
+        .await
+        .unwrap_or(0);
+
+    let total: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) as count FROM task
+             WHERE user_id = ? AND status != 3
+             AND (task_date = ? OR (status IN (2, 6) AND date(updated_at) = date('now')))",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(today)],
+        )
+        .await
+        .unwrap_or(0);
+
+    (completed, total.max(completed))
+}
+
+/// 列出好友與待處理的邀請
+pub async fn list_friends(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthorized());
+    };
+
+    let rows: Vec<Friendship> = match rb
+        .query_decode(
+            "SELECT * FROM friendship WHERE requester_id = ? OR addressee_id = ? ORDER BY created_at DESC",
+            vec![rbs::Value::String(user_id.clone()), rbs::Value::String(user_id.clone())],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => return Ok(internal_error("查詢好友列表", e)),
+    };
+
+    let mut items = Vec::new();
+    for friendship in rows {
+        let incoming = friendship.addressee_id.as_deref() == Some(user_id.as_str());
+        let other_id = if incoming {
+            friendship.requester_id.clone()
+        } else {
+            friendship.addressee_id.clone()
+        }
+        .unwrap_or_default();
+        let name = User::select_by_map(rb.get_ref(), value!{"id": &other_id})
+            .await
+            .ok()
+            .and_then(|users| users.into_iter().next())
+            .and_then(|u| u.name)
+            .unwrap_or_default();
+
+        items.push(FriendListItem {
+            friendship_id: friendship.id.clone().unwrap_or_default(),
+            user_id: other_id,
+            name,
+            status: friendship.status.clone().unwrap_or_default(),
+            incoming,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(items),
+        message: "獲取好友列表成功".to_string(),
+    }))
+}
+
+/// 送出好友邀請（以 user_id 或 email 指定對象）
+pub async fn send_friend_request(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<FriendRequestPayload>,
+) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthorized());
+    };
+
+    let target = match (&req.friend_id, &req.email) {
+        (Some(id), _) => User::select_by_map(rb.get_ref(), value!{"id": id}).await,
+        (None, Some(email)) => User::select_by_map(rb.get_ref(), value!{"email": email.trim().to_lowercase()}).await,
+        (None, None) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "請提供 friend_id 或 email".to_string(),
+            }));
+        }
+    };
+    let friend_id = match target {
+        Ok(users) => match users.into_iter().next().and_then(|u| u.id) {
+            Some(id) => id,
+            None => {
+                return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "找不到該使用者".to_string(),
+                }));
+            }
+        },
+        Err(e) => return Ok(internal_error("查詢使用者", e)),
+    };
+
+    if friend_id == user_id {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "不能將自己加為好友".to_string(),
+        }));
+    }
+
+    match find_friendship(rb.get_ref(), &user_id, &friend_id).await {
+        Ok(Some(existing)) => {
+            return Ok(HttpResponse::Conflict().json(ApiResponse {
+                success: false,
+                data: Some(existing),
+                message: "已存在好友關係或邀請".to_string(),
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => return Ok(internal_error("查詢好友關係", e)),
+    }
+
+    let friendship = Friendship {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        requester_id: Some(user_id),
+        addressee_id: Some(friend_id),
+        status: Some(FRIENDSHIP_PENDING.to_string()),
+        created_at: Some(Utc::now()),
+        updated_at: Some(Utc::now()),
+    };
+
+    match Friendship::insert(rb.get_ref(), &friendship).await {
+        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(friendship),
+            message: "好友邀請已送出".to_string(),
+        })),
+        Err(e) => Ok(internal_error("送出好友邀請", e)),
+    }
+}
+
+/// 接受好友邀請（僅被邀請者可以接受）
+pub async fn accept_friend_request(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthorized());
+    };
+    let friendship_id = path.into_inner();
+
+    let friendship = match Friendship::select_by_map(rb.get_ref(), value!{"id": &friendship_id}).await {
+        Ok(rows) => rows.into_iter().next(),
+        Err(e) => return Ok(internal_error("查詢好友邀請", e)),
+    };
+    let Some(mut friendship) = friendship.filter(|f| f.addressee_id.as_deref() == Some(user_id.as_str())) else {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "找不到該好友邀請".to_string(),
+        }));
+    };
+
+    if friendship.status.as_deref() == Some(FRIENDSHIP_ACCEPTED) {
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(friendship),
+            message: "已經是好友".to_string(),
+        }));
+    }
+
+    friendship.status = Some(FRIENDSHIP_ACCEPTED.to_string());
+    friendship.updated_at = Some(Utc::now());
+    match Friendship::update_by_map(rb.get_ref(), &friendship, value!{"id": &friendship_id}).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(friendship),
+            message: "已接受好友邀請".to_string(),
+        })),
+        Err(e) => Ok(internal_error("接受好友邀請", e)),
+    }
+}
+
+/// 移除好友（也可用於取消或拒絕尚未接受的邀請）
+pub async fn remove_friend(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthorized());
+    };
+    let friend_id = path.into_inner();
+
+    let friendship = match find_friendship(rb.get_ref(), &user_id, &friend_id).await {
+        Ok(Some(f)) => f,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "找不到好友關係".to_string(),
+            }));
+        }
+        Err(e) => return Ok(internal_error("查詢好友關係", e)),
+    };
+
+    match Friendship::delete_by_map(rb.get_ref(), value!{"id": &friendship.id}).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "已移除好友".to_string(),
+        })),
+        Err(e) => Ok(internal_error("移除好友", e)),
+    }
+}
+
+/// 好友摘要：連續天數、今日完成數/總數、等級
+pub async fn get_friend_summary(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthorized());
+    };
+    let friend_id = path.into_inner();
+
+    match is_accepted_friend(rb.get_ref(), &user_id, &friend_id).await {
+        Ok(true) => {}
+        // 不區分「不存在」與「非好友」，避免洩漏使用者資訊
+        Ok(false) => {
+            return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "僅能查看已接受的好友".to_string(),
+            }));
+        }
+        Err(e) => return Ok(internal_error("查詢好友關係", e)),
+    }
+
+    let profile = UserProfile::select_by_map(rb.get_ref(), value!{"user_id": &friend_id})
+        .await
+        .ok()
+        .and_then(|rows| rows.into_iter().next());
+    let (today_completed, today_total) = today_task_counts(rb.get_ref(), &friend_id).await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(FriendSummary {
+            streak: profile.as_ref().and_then(|p| p.consecutive_login_days).unwrap_or(0),
+            today_completed,
+            today_total,
+            level: profile.as_ref().and_then(|p| p.level).unwrap_or(1),
+        }),
+        message: "獲取好友摘要成功".to_string(),
+    }))
+}
+
+/// 晚間總結為零完成時要提醒的夥伴與通知內容（需使用者開啟 notify_partner_on_miss）
+pub async fn partner_nudges(
+    rb: &RBatis,
+    user_id: &str,
+) -> Result<Vec<(String, serde_json::Value)>, Box<dyn std::error::Error + Send + Sync>> {
+    let (completed, _) = today_task_counts(rb, user_id).await;
+    if completed > 0 {
+        return Ok(Vec::new());
+    }
+
+    let name = User::select_by_map(rb, value!{"id": user_id})
+        .await?
+        .into_iter()
+        .next()
+        .and_then(|u| u.name)
+        .unwrap_or_else(|| "你的夥伴".to_string());
+
+    let notification = serde_json::json!({
+        "title": "夥伴需要你的鼓勵",
+        "body": format!("{} 今天還沒有完成任務，傳個訊息為對方打氣吧！💪", name),
+        "data": { "url": "/friends", "type": "partner_nudge", "friend_id": user_id },
+    });
+
+    let mut nudges = Vec::new();
+    for partner_id in accepted_friend_ids(rb, user_id).await? {
+        // 尊重夥伴本身的通知開關（夥伴提醒屬於連續紀錄中斷風險類別）
+        let partner_settings = crate::notification_categories::load_settings(rb, &partner_id).await;
+        if partner_settings.is_some_and(|s| {
+            !crate::notification_categories::is_enabled(&s, crate::notification_categories::NotificationCategory::StreakRisk)
+        }) {
+            continue;
+        }
+
+        nudges.push((partner_id, notification.clone()));
+    }
+
+    Ok(nudges)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{self, call_json, TestUser};
+
+    async fn send_request<S>(app: &S, from: &TestUser, to: &TestUser) -> String
+    where
+        S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::post()
+            .uri("/api/friends/requests")
+            .insert_header(from.auth())
+            .set_json(json!({ "friend_id": to.id }))
+            .to_request();
+        let (status, body) = call_json(app, req).await;
+        assert_eq!(status.as_u16(), 201, "{}", body);
+        body["data"]["id"].as_str().unwrap().to_string()
+    }
+
+    #[actix_web::test]
+    async fn test_summary_only_visible_to_accepted_friends() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let alice = test_utils::create_user(&app, "friend_alice").await;
+        let bob = test_utils::create_user(&app, "friend_bob").await;
+        let carol = test_utils::create_user(&app, "friend_carol").await;
+        let dave = test_utils::create_user(&app, "friend_dave").await;
+
+        let accepted = send_request(&app, &alice, &bob).await;
+        let pending = send_request(&app, &alice, &carol).await;
+
+        // 只有被邀請者可以接受邀請
+        let req = test::TestRequest::post().uri(&format!("/api/friends/requests/{}/accept", pending)).insert_header(alice.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0.as_u16(), 404);
+        let req = test::TestRequest::post().uri(&format!("/api/friends/requests/{}/accept", accepted)).insert_header(bob.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+
+        let summary = |viewer: &TestUser, target: &TestUser| {
+            test::TestRequest::get()
+                .uri(&format!("/api/friends/{}/summary", target.id))
+                .insert_header(viewer.auth())
+                .to_request()
+        };
+
+        // 非好友與尚未接受的邀請（雙方向）都被拒絕
+        for (viewer, target) in [(&dave, &alice), (&alice, &carol), (&carol, &alice)] {
+            let (status, body) = call_json(&app, summary(viewer, target)).await;
+            assert_eq!(status.as_u16(), 403, "{}", body);
+            assert!(body["data"].is_null());
+        }
+
+        // 已接受的好友（雙方向）可以查看
+        for (viewer, target) in [(&alice, &bob), (&bob, &alice)] {
+            let (status, body) = call_json(&app, summary(viewer, target)).await;
+            assert_eq!(status.as_u16(), 200, "{}", body);
+            assert_eq!(body["data"]["level"], 1);
+            assert_eq!(body["data"]["today_completed"], 0);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_partner_nudges_only_reach_accepted_partners() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let alice = test_utils::create_user(&app, "nudge_alice").await;
+        let bob = test_utils::create_user(&app, "nudge_bob").await;
+        let carol = test_utils::create_user(&app, "nudge_carol").await;
+        let _dave = test_utils::create_user(&app, "nudge_dave").await;
+
+        let accepted = send_request(&app, &bob, &alice).await;
+        send_request(&app, &alice, &carol).await;
+        let req = test::TestRequest::post().uri(&format!("/api/friends/requests/{}/accept", accepted)).insert_header(alice.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0.as_u16(), 200);
+
+        let nudges = partner_nudges(&rb, &alice.id).await.unwrap();
+        let partners: Vec<&str> = nudges.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(partners, vec![bob.id.as_str()]);
+        assert_eq!(nudges[0].1["data"]["friend_id"], alice.id);
+
+        // 今天已有完成的任務時不提醒
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, task_date) VALUES ('t1', ?, '晨跑', 2, ?)",
+            vec![rbs::Value::String(alice.id.clone()), rbs::Value::String(crate::local_date::local_today_string())],
+        )
+        .await
+        .unwrap();
+        assert!(partner_nudges(&rb, &alice.id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+// 遊戲化用戶資料快取：GET /api/users/{id}/gamified 每個畫面都會呼叫，組合資料需要多次查詢，
+// 因此在程序內依使用者快取組合好的結果（短 TTL、數量上限，超過時淘汰最久未使用的項目）。
+//
+// 經驗值、屬性、個人資料、今日進度或介面設定變更時，寫入路徑必須呼叫 invalidate(user_id)，
+// 下一次讀取就會重新組合，不必等 TTL 到期。除錯時可以 GAMIFIED_CACHE_ENABLED=false 關閉。
+//
+// 組合資料前先以 generation(user_id) 取得世代，put 時若期間有 invalidate 就不寫入，
+// 避免讀到舊資料的請求在清除之後才把舊結果放回快取。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config::GamifiedCacheConfig;
+
+struct CacheEntry {
+    payload: serde_json::Value,
+    stored_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct GamifiedCache {
+    entries: HashMap<String, CacheEntry>,
+    // 單調遞增的使用序號，用來找出最久未使用的項目；也作為清除快取時的世代值
+    clock: u64,
+    // 每位使用者最近一次 invalidate 的世代
+    generations: HashMap<String, u64>,
+    // 最近一次 clear（或世代表重置）的世代，未記錄的使用者以此為準
+    cleared_at: u64,
+}
+
+// 世代表超過此數量時整批重置，只會讓進行中的組合略過寫入
+const MAX_GENERATIONS: usize = 10_000;
+
+impl GamifiedCache {
+    fn generation(&self, user_id: &str) -> u64 {
+        self.generations.get(user_id).copied().unwrap_or(0).max(self.cleared_at)
+    }
+
+    fn bump(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+static GAMIFIED_CACHE_CONFIG: OnceLock<GamifiedCacheConfig> = OnceLock::new();
+static GAMIFIED_CACHE: OnceLock<Mutex<GamifiedCache>> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: GamifiedCacheConfig) {
+    if config.enabled {
+        log::info!("遊戲化資料快取: TTL {} 秒、最多 {} 位使用者", config.ttl_seconds, config.capacity);
+    } else {
+        log::info!("遊戲化資料快取已停用");
+    }
+    if GAMIFIED_CACHE_CONFIG.set(config).is_err() {
+        log::warn!("遊戲化資料快取設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static GamifiedCacheConfig {
+    GAMIFIED_CACHE_CONFIG.get_or_init(GamifiedCacheConfig::default)
+}
+
+fn cache() -> &'static Mutex<GamifiedCache> {
+    GAMIFIED_CACHE.get_or_init(|| Mutex::new(GamifiedCache::default()))
+}
+
+fn enabled() -> bool {
+    let config = config();
+    config.enabled && config.ttl_seconds > 0 && config.capacity > 0
+}
+
+/// 取得未過期的快取結果
+pub fn get(user_id: &str) -> Option<serde_json::Value> {
+    if !enabled() {
+        return None;
+    }
+    let ttl = Duration::from_secs(config().ttl_seconds);
+    let mut cache = cache().lock().ok()?;
+    cache.clock += 1;
+    let clock = cache.clock;
+    match cache.entries.get_mut(user_id) {
+        Some(entry) if entry.stored_at.elapsed() < ttl => {
+            entry.last_used = clock;
+            Some(entry.payload.clone())
+        }
+        Some(_) => {
+            cache.entries.remove(user_id);
+            None
+        }
+        None => None,
+    }
+}
+
+/// 組合資料前取得目前的世代，之後傳給 put
+pub fn generation(user_id: &str) -> u64 {
+    cache().lock().map(|cache| cache.generation(user_id)).unwrap_or(0)
+}
+
+/// 保存組合好的結果；取得世代後若已被 invalidate 則不寫入，超過上限時淘汰最久未使用的項目
+pub fn put(user_id: &str, generation: u64, payload: &serde_json::Value) {
+    if !enabled() {
+        return;
+    }
+    let capacity = config().capacity;
+    let Ok(mut cache) = cache().lock() else {
+        return;
+    };
+    if cache.generation(user_id) != generation {
+        return;
+    }
+    cache.clock += 1;
+    let clock = cache.clock;
+    if !cache.entries.contains_key(user_id) {
+        while cache.entries.len() >= capacity {
+            let Some(oldest) = cache.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone()) else {
+                break;
+            };
+            cache.entries.remove(&oldest);
+        }
+    }
+    cache.entries.insert(
+        user_id.to_string(),
+        CacheEntry {
+            payload: payload.clone(),
+            stored_at: Instant::now(),
+            last_used: clock,
+        },
+    );
+}
+
+/// 使用者的經驗值、屬性、個人資料、今日進度或設定變更後清除快取
+pub fn invalidate(user_id: &str) {
+    if let Ok(mut cache) = cache().lock() {
+        cache.entries.remove(user_id);
+        let generation = cache.bump();
+        if cache.generations.len() >= MAX_GENERATIONS {
+            cache.generations.clear();
+            cache.cleared_at = generation;
+        } else {
+            cache.generations.insert(user_id.to_string(), generation);
+        }
+    }
+}
+
+/// 清除所有快取（資料庫重置時使用）
+pub fn clear() {
+    if let Ok(mut cache) = cache().lock() {
+        cache.entries.clear();
+        cache.generations.clear();
+        cache.cleared_at = cache.bump();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::json;
+
+    use crate::models::TaskStatus;
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_put_after_invalidate_does_not_store_stale_read() {
+        let user_id = format!("cache_{}", uuid::Uuid::new_v4());
+
+        // 讀取開始後、寫入快取前，另一個請求更新資料並清除快取
+        let generation = super::generation(&user_id);
+        super::invalidate(&user_id);
+        super::put(&user_id, generation, &json!({"experience": 0}));
+        assert!(super::get(&user_id).is_none(), "清除前讀到的資料不應寫入快取");
+
+        // 清除之後才開始的讀取可以正常寫入
+        let generation = super::generation(&user_id);
+        super::put(&user_id, generation, &json!({"experience": 30}));
+        assert_eq!(super::get(&user_id), Some(json!({"experience": 30})));
+    }
+
+    #[actix_web::test]
+    async fn test_task_completion_is_visible_in_next_gamified_read() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "cached_player").await;
+
+        let gamified = || {
+            test::TestRequest::get()
+                .uri(&format!("/api/users/{}/gamified", user.id))
+                .insert_header(user.auth())
+                .to_request()
+        };
+        let (_, before) = call_json(&app, gamified()).await;
+        assert!(super::get(&user.id).is_some(), "讀取後應寫入快取");
+        let endurance_before = before["data"]["attributes"]["endurance"].as_i64().unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "快取測試", "task_type": "side", "experience": 30, "attributes": {"endurance": 2}}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", task_id))
+            .insert_header(user.auth())
+            .set_json(json!({"status": TaskStatus::Completed.to_i32(), "version": body["data"]["version"]}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let reward = body["data"]["experience_reward"].as_i64().unwrap();
+
+        // TTL 尚未到期，但完成任務套用屬性獎勵時已清除快取
+        let (_, after) = call_json(&app, gamified()).await;
+        assert_eq!(after["data"]["attributes"]["endurance"].as_i64().unwrap(), endurance_before + 2, "{}", after);
+        assert_eq!(after["data"]["todayProgress"]["attributeGains"]["endurance"], 2);
+
+        // 前端接著回報經驗值
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/users/{}/experience", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"experience_gain": reward}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+        let (_, after) = call_json(&app, gamified()).await;
+        assert_eq!(after["data"]["experience"].as_i64().unwrap(), reward, "{}", after);
+    }
+}
@@ -0,0 +1,295 @@
+// 重複性任務的習慣統計：目前/最長連續天數、近 12 週完成日曆與最佳星期
+//
+// 只計算依重複模式應執行的日子（例如 weekdays 不會因週末未完成而中斷連續紀錄）；
+// 結果依任務與日期快取，子任務狀態變更時清除。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rbatis::RBatis;
+use serde::Serialize;
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Task, TaskStatus};
+use crate::recurring_progress::is_scheduled_day;
+
+// 完成日曆顯示的週數
+const CALENDAR_WEEKS: i64 = 12;
+
+const WEEKDAY_NAMES: [&str; 7] = ["週一", "週二", "週三", "週四", "週五", "週六", "週日"];
+
+/// 完成日曆中的一天（GitHub 風格格子）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HabitDay {
+    pub date: String,
+    pub weekday: u32, // 0 = 週一
+    pub scheduled: bool,
+    pub completed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BestWeekday {
+    pub weekday: u32,
+    pub name: String,
+    pub completed_days: i32,
+    pub scheduled_days: i32,
+    pub completion_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HabitStats {
+    pub task_id: String,
+    pub recurrence_pattern: String,
+    pub current_streak: i32,
+    pub longest_streak: i32,
+    pub completed_days: i32,
+    pub scheduled_days: i32,
+    pub best_weekday: Option<BestWeekday>,
+    pub calendar: Vec<HabitDay>,
+}
+
+// (task_id, 使用者時區日期) -> 統計結果
+type HabitStatsCache = HashMap<(String, String), HabitStats>;
+
+static HABIT_STATS_CACHE: OnceLock<Mutex<HabitStatsCache>> = OnceLock::new();
+
+fn habit_stats_cache() -> &'static Mutex<HabitStatsCache> {
+    HABIT_STATS_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 子任務狀態變更時清除該任務的快取
+pub fn invalidate(task_id: &str) {
+    if let Ok(mut cache) = habit_stats_cache().lock() {
+        cache.retain(|(cached_task_id, _), _| cached_task_id != task_id);
+    }
+}
+
+/// 依已完成的日期計算習慣統計（today 尚未完成時不算中斷）
+pub fn compute_habit_stats(
+    start: NaiveDate,
+    end: NaiveDate,
+    today: NaiveDate,
+    pattern: &str,
+    completed: &HashSet<NaiveDate>,
+) -> (i32, i32, Option<BestWeekday>, Vec<HabitDay>) {
+    let last_day = end.min(today);
+    let scheduled = |day: NaiveDate| day >= start && day <= end && is_scheduled_day(start, day, pattern);
+
+    let mut longest = 0;
+    let mut running = 0;
+    let mut per_weekday = [(0i32, 0i32); 7]; // (完成, 應執行)
+    let mut day = start;
+    while day <= last_day {
+        if scheduled(day) {
+            let done = completed.contains(&day);
+            if done {
+                running += 1;
+                longest = longest.max(running);
+            } else if day != today {
+                running = 0;
+            }
+            // 今天還沒結束，尚未完成不計入星期統計
+            if done || day != today {
+                let index = day.weekday().num_days_from_monday() as usize;
+                per_weekday[index].1 += 1;
+                if done {
+                    per_weekday[index].0 += 1;
+                }
+            }
+        }
+        day += Duration::days(1);
+    }
+
+    let mut current = 0;
+    let mut day = last_day;
+    while day >= start {
+        if scheduled(day) {
+            if completed.contains(&day) {
+                current += 1;
+            } else if day != today {
+                break;
+            }
+        }
+        day -= Duration::days(1);
+    }
+
+    // 完成率最高的星期，同率時完成次數多者優先
+    let best_weekday = per_weekday
+        .iter()
+        .enumerate()
+        .filter(|(_, (done, planned))| *planned > 0 && *done > 0)
+        .map(|(index, (done, planned))| BestWeekday {
+            weekday: index as u32,
+            name: WEEKDAY_NAMES[index].to_string(),
+            completed_days: *done,
+            scheduled_days: *planned,
+            completion_rate: *done as f64 / *planned as f64,
+        })
+        .max_by(|a, b| {
+            a.completion_rate
+                .total_cmp(&b.completion_rate)
+                .then(a.completed_days.cmp(&b.completed_days))
+                .then(b.weekday.cmp(&a.weekday))
+        });
+
+    // 從 11 週前的週一開始到今天，方便前端依週排成格子
+    let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let calendar_start = this_monday - Duration::weeks(CALENDAR_WEEKS - 1);
+    let calendar = (0..=(today - calendar_start).num_days())
+        .map(|offset| {
+            let day = calendar_start + Duration::days(offset);
+            HabitDay {
+                date: day.format("%Y-%m-%d").to_string(),
+                weekday: day.weekday().num_days_from_monday(),
+                scheduled: scheduled(day),
+                completed: completed.contains(&day),
+            }
+        })
+        .collect();
+
+    (current, longest, best_weekday, calendar)
+}
+
+// 一次彙總查詢取得有任一子任務完成的日期（含已壓縮成每月彙總的子任務）
+async fn completed_dates(rb: &RBatis, parent_task_id: &str) -> Result<HashSet<NaiveDate>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT task_date FROM task
+             WHERE parent_task_id = ? AND task_date IS NOT NULL AND status = ?
+             GROUP BY task_date",
+            vec![
+                rbs::Value::String(parent_task_id.to_string()),
+                rbs::Value::I32(TaskStatus::DailyCompleted.to_i32()),
+            ],
+        )
+        .await?;
+    let mut dates = crate::daily_compaction::archived_completed_dates(rb, parent_task_id).await?;
+    dates.extend(
+        rows.iter()
+            .filter_map(|row| row.get("task_date").and_then(|v| v.as_str()))
+            .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()),
+    );
+    Ok(dates)
+}
+
+pub(crate) async fn load_habit_stats(rb: &RBatis, task: &Task) -> Result<HabitStats, rbatis::Error> {
+    let task_id = task.id.clone().unwrap_or_default();
+    let tz = crate::recurring_progress::owner_timezone(rb, task).await;
+    let today = crate::local_date::local_today_in(tz);
+    let key = (task_id.clone(), today.format("%Y-%m-%d").to_string());
+    if let Some(cached) = habit_stats_cache().lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+        return Ok(cached);
+    }
+
+    let now = Utc::now();
+    let start = crate::local_date::local_date_in(task.start_date.or(task.created_at).unwrap_or(now), tz);
+    let end = crate::local_date::local_date_in(task.end_date.unwrap_or(now + Duration::days(365)), tz);
+    let pattern = task.recurrence_pattern.clone().unwrap_or_else(|| "daily".to_string());
+    let completed = completed_dates(rb, &task_id).await?;
+
+    let (current_streak, longest_streak, best_weekday, calendar) = compute_habit_stats(start, end, today, &pattern, &completed);
+    let scheduled_days = if today < start {
+        0
+    } else {
+        crate::recurring_progress::count_scheduled_days(start, ((end.min(today) - start).num_days() + 1) as i32, &pattern)
+    };
+    let stats = HabitStats {
+        task_id,
+        current_streak,
+        longest_streak,
+        completed_days: completed.iter().filter(|d| **d >= start && **d <= end.min(today)).count() as i32,
+        scheduled_days,
+        best_weekday,
+        calendar,
+        recurrence_pattern: pattern,
+    };
+
+    if let Ok(mut cache) = habit_stats_cache().lock() {
+        // 只保留今天的快取
+        cache.retain(|(_, date), _| *date == key.1);
+        cache.insert(key, stats.clone());
+    }
+    Ok(stats)
+}
+
+/// 取得重複性任務的習慣統計（任務擁有者或共享任務參與者）
+pub async fn get_habit_stats(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    let task = match crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if task.is_recurring != Some(1) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "只有重複性任務有習慣統計".to_string(),
+        }));
+    }
+
+    match load_habit_stats(rb.get_ref(), &task).await {
+        Ok(stats) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(stats),
+            message: "獲取習慣統計成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取習慣統計失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn dates(list: &[&str]) -> HashSet<NaiveDate> {
+        list.iter().map(|d| date(d)).collect()
+    }
+
+    #[test]
+    fn test_weekday_habit_streak_skips_weekends() {
+        // 2026-03-02 為週一；週五完成後跨過週末，週一、週二繼續完成
+        let start = date("2026-03-02");
+        let today = date("2026-03-10"); // 週二
+        let completed = dates(&["2026-03-04", "2026-03-05", "2026-03-06", "2026-03-09", "2026-03-10"]);
+        let (current, longest, best, calendar) =
+            compute_habit_stats(start, date("2026-12-31"), today, "weekdays", &completed);
+        assert_eq!(current, 5);
+        assert_eq!(longest, 5);
+        // 週三至週五皆為 100%，同率時取較早的星期
+        assert_eq!(best.unwrap().name, "週三");
+
+        // 日曆從 11 週前的週一開始，到今天為止
+        assert_eq!(calendar.len(), 7 * 11 + 2);
+        assert_eq!(calendar[0].weekday, 0);
+        let saturday = calendar.iter().find(|d| d.date == "2026-03-07").unwrap();
+        assert!(!saturday.scheduled && !saturday.completed);
+        assert!(calendar.iter().find(|d| d.date == "2026-03-02").unwrap().scheduled);
+    }
+
+    #[test]
+    fn test_today_pending_does_not_break_streak() {
+        let start = date("2026-03-01");
+        let today = date("2026-03-08");
+        let completed = dates(&["2026-03-01", "2026-03-02", "2026-03-03", "2026-03-06", "2026-03-07"]);
+        let (current, longest, _, _) = compute_habit_stats(start, date("2026-03-31"), today, "daily", &completed);
+        assert_eq!(current, 2);
+        assert_eq!(longest, 3);
+
+        let (current, _, _, _) = compute_habit_stats(start, date("2026-03-31"), date("2026-03-09"), "daily", &completed);
+        assert_eq!(current, 0);
+    }
+}
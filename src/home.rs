@@ -0,0 +1,209 @@
+// 首頁整合 API：一次取得遊戲化資料（含介面所需的使用者設定）、首頁任務、今日三任務、未讀事件與連續紀錄摘要
+//
+// 各區塊以 tokio::join! 同時查詢，可用 include 參數（逗號分隔）只取需要的區塊；
+// 原本的個別 API 保持不變。
+
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use rbatis::RBatis;
+use serde::Serialize;
+
+use crate::ai_tasks::ApiResponse;
+use crate::daily_quests::DailyQuestResponse;
+use crate::event_notifier::UserEvent;
+use crate::streak_reminder::StreakAtRisk;
+
+/// 可選的區塊（include 參數的值）
+pub const SECTIONS: [&str; 5] = ["profile", "tasks", "daily_quests", "unseen_events", "streak"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Include {
+    profile: bool,
+    tasks: bool,
+    daily_quests: bool,
+    unseen_events: bool,
+    streak: bool,
+}
+
+impl Include {
+    /// 未指定時包含所有區塊；未知的區塊名稱回傳錯誤訊息
+    fn parse(value: Option<&str>) -> std::result::Result<Self, String> {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+            return Ok(Include { profile: true, tasks: true, daily_quests: true, unseen_events: true, streak: true });
+        };
+        let mut include = Include { profile: false, tasks: false, daily_quests: false, unseen_events: false, streak: false };
+        for section in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match section {
+                "profile" => include.profile = true,
+                "tasks" => include.tasks = true,
+                "daily_quests" => include.daily_quests = true,
+                "unseen_events" => include.unseen_events = true,
+                "streak" => include.streak = true,
+                _ => return Err(format!("未知的首頁區塊: {}（可用: {}）", section, SECTIONS.join(", "))),
+            }
+        }
+        Ok(include)
+    }
+}
+
+/// 連續紀錄摘要：連續登入天數與今天快中斷的習慣
+#[derive(Debug, Serialize)]
+pub struct StreakSummary {
+    pub consecutive_login_days: i64,
+    pub last_login_date: Option<String>,
+    pub at_risk: Vec<StreakAtRisk>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HomePayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<rbs::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_quests: Option<DailyQuestResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unseen_events: Option<Vec<UserEvent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub streak: Option<StreakSummary>,
+}
+
+async fn load_streak_summary(rb: &RBatis, user_id: &str) -> std::result::Result<StreakSummary, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT consecutive_login_days, last_login_date FROM user_profile WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let row = rows.first();
+    Ok(StreakSummary {
+        consecutive_login_days: row.and_then(|r| r["consecutive_login_days"].as_i64()).unwrap_or(0),
+        last_login_date: row.and_then(|r| r["last_login_date"].as_str()).map(str::to_string),
+        at_risk: crate::streak_reminder::find_at_risk_tasks(rb, user_id).await?,
+    })
+}
+
+fn internal_error(e: rbatis::Error) -> HttpResponse {
+    log::error!("獲取首頁資料失敗: {}", e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("獲取首頁資料失敗: {}", e),
+    })
+}
+
+/// 首頁整合資料
+pub async fn get_home(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let include = match Include::parse(query.get("include").map(String::as_str)) {
+        Ok(include) => include,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message,
+            }))
+        }
+    };
+
+    let rb = rb.get_ref();
+    let user_id = user_id.as_str();
+    let (profile, tasks, daily_quests, unseen_events, streak) = tokio::join!(
+        async { if include.profile { Some(crate::routes::load_gamified_data(rb, user_id).await) } else { None } },
+        async { if include.tasks { Some(crate::routes::load_homepage_tasks(rb, user_id).await) } else { None } },
+        async { if include.daily_quests { Some(crate::daily_quests::load_today(rb, user_id).await) } else { None } },
+        async { if include.unseen_events { Some(crate::event_notifier::take_unseen_events(rb, user_id).await) } else { None } },
+        async { if include.streak { Some(load_streak_summary(rb, user_id).await) } else { None } },
+    );
+
+    let payload = HomePayload {
+        profile: match profile.transpose() {
+            Ok(profile) => profile,
+            Err(response) => return Ok(response),
+        },
+        tasks: match tasks.transpose() {
+            Ok(tasks) => tasks,
+            Err(e) => return Ok(internal_error(e)),
+        },
+        daily_quests: match daily_quests.transpose() {
+            Ok(quests) => quests,
+            Err(e) => return Ok(internal_error(e)),
+        },
+        unseen_events,
+        streak: match streak.transpose() {
+            Ok(streak) => streak,
+            Err(e) => return Ok(internal_error(e)),
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(payload),
+        message: "獲取首頁資料成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[test]
+    fn test_include_parse() {
+        assert!(Include::parse(None).unwrap().streak);
+        let include = Include::parse(Some("profile, streak")).unwrap();
+        assert!(include.profile && include.streak);
+        assert!(!include.tasks && !include.daily_quests && !include.unseen_events);
+        assert!(Include::parse(Some("profile,achievements")).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_home_payload_sections() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "home_payload").await;
+        let other = test_utils::create_user(&app, "home_other").await;
+        crate::event_notifier::notify_level_up(&rb, &user.id, 1, 2).await;
+
+        let uri = format!("/api/users/{}/home", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let data = &body["data"];
+        assert_eq!(data["profile"]["id"], user.id.as_str());
+        assert!(data["tasks"].is_array());
+        assert!(data["daily_quests"]["picks"].is_array());
+        assert_eq!(data["unseen_events"].as_array().unwrap().len(), 1);
+        assert_eq!(data["streak"]["consecutive_login_days"], 1);
+
+        // 只取指定區塊；未讀事件已在上一次送達
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?include=streak,unseen_events", uri))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let keys: Vec<&String> = body["data"].as_object().unwrap().keys().collect();
+        assert_eq!(keys.len(), 2);
+        assert!(body["data"]["unseen_events"].as_array().unwrap().is_empty());
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?include=achievements", uri))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 400);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 403);
+    }
+}
@@ -0,0 +1,223 @@
+use std::future::Future;
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, Utc};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+
+// 冪等鍵保留時間
+const IDEMPOTENCY_TTL_HOURS: i64 = 24;
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+// 請求內容中的冪等鍵欄位，計算指紋時需排除
+const BODY_KEY_FIELD: &str = "client_request_id";
+
+#[derive(Debug, Deserialize)]
+struct IdempotencyRecord {
+    request_fingerprint: Option<String>,
+    response_status: Option<i32>,
+    response_body: Option<String>,
+}
+
+/// 計算請求內容指紋（排除 client_request_id，欄位順序不影響結果）
+pub fn request_fingerprint<T: Serialize>(payload: &T) -> String {
+    let mut value = serde_json::to_value(payload).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove(BODY_KEY_FIELD);
+    }
+    value.to_string()
+}
+
+/// 從 Idempotency-Key 標頭或 client_request_id 取得冪等鍵
+fn resolve_key(http_req: &HttpRequest, body_key: Option<String>) -> Option<String> {
+    http_req
+        .headers()
+        .get(IDEMPOTENCY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+        .or(body_key)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// 以冪等鍵包裝建立資源的處理函式
+///
+/// 同一使用者重送相同鍵時回傳第一次建立的結果並標記 replayed: true；
+/// 相同鍵但請求內容不同時回傳 409。未提供鍵時直接執行。
+pub async fn run_idempotent<F, Fut>(
+    rb: &RBatis,
+    http_req: &HttpRequest,
+    user_id: Option<String>,
+    body_key: Option<String>,
+    endpoint: &str,
+    fingerprint: String,
+    handler: F,
+) -> Result<HttpResponse>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<HttpResponse>>,
+{
+    let Some(key) = resolve_key(http_req, body_key) else {
+        return handler().await;
+    };
+    let scope_user = crate::auth::current_user_id(http_req)
+        .or(user_id)
+        .unwrap_or_else(|| "anonymous".to_string());
+    let fingerprint = format!("{}:{}", endpoint, fingerprint);
+    let now = Utc::now();
+
+    // 清除過期的冪等鍵
+    let _ = rb
+        .exec(
+            "DELETE FROM idempotency_key WHERE expires_at < ?",
+            vec![rbs::Value::String(now.to_rfc3339())],
+        )
+        .await;
+
+    // 先佔用冪等鍵，避免並發重送同時建立資源
+    let reserved = rb
+        .exec(
+            "INSERT OR IGNORE INTO idempotency_key (id, user_id, idempotency_key, endpoint, request_fingerprint, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            vec![
+                rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                rbs::Value::String(scope_user.clone()),
+                rbs::Value::String(key.clone()),
+                rbs::Value::String(endpoint.to_string()),
+                rbs::Value::String(fingerprint.clone()),
+                rbs::Value::String(now.to_rfc3339()),
+                rbs::Value::String((now + Duration::hours(IDEMPOTENCY_TTL_HOURS)).to_rfc3339()),
+            ],
+        )
+        .await;
+
+    match reserved {
+        Ok(result) if result.rows_affected > 0 => {}
+        Ok(_) => return replay(rb, &scope_user, &key, &fingerprint).await,
+        Err(e) => {
+            log::error!("寫入冪等鍵失敗: {}", e);
+            return handler().await;
+        }
+    }
+
+    let response = handler().await?;
+    let status = response.status();
+    if !status.is_success() {
+        // 失敗的請求釋放冪等鍵，讓客戶端可以重試
+        release(rb, &scope_user, &key).await;
+        return Ok(response);
+    }
+
+    let body = match actix_web::body::to_bytes(response.into_body()).await {
+        Ok(body) => body,
+        Err(e) => {
+            release(rb, &scope_user, &key).await;
+            log::error!("讀取回應內容失敗: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "讀取回應內容失敗".to_string(),
+            }));
+        }
+    };
+
+    let resource_id = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| {
+            let data = v.get("data")?;
+            data.get("id")
+                .or_else(|| data.get("task").and_then(|t| t.get("id")))
+                .and_then(|id| id.as_str())
+                .map(|s| s.to_string())
+        });
+
+    let saved = rb
+        .exec(
+            "UPDATE idempotency_key SET resource_id = ?, response_status = ?, response_body = ? WHERE user_id = ? AND idempotency_key = ?",
+            vec![
+                resource_id.map(rbs::Value::String).unwrap_or(rbs::Value::Null),
+                rbs::Value::I32(status.as_u16() as i32),
+                rbs::Value::String(String::from_utf8_lossy(&body).to_string()),
+                rbs::Value::String(scope_user),
+                rbs::Value::String(key),
+            ],
+        )
+        .await;
+    if let Err(e) = saved {
+        log::error!("儲存冪等鍵結果失敗: {}", e);
+    }
+
+    Ok(HttpResponse::build(status)
+        .content_type("application/json")
+        .body(body))
+}
+
+/// 回傳先前相同冪等鍵的結果
+async fn replay(rb: &RBatis, user_id: &str, key: &str, fingerprint: &str) -> Result<HttpResponse> {
+    let record: Option<IdempotencyRecord> = rb
+        .query_decode(
+            "SELECT request_fingerprint, response_status, response_body FROM idempotency_key WHERE user_id = ? AND idempotency_key = ?",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(key.to_string())],
+        )
+        .await
+        .unwrap_or(None);
+
+    let Some(record) = record else {
+        return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "冪等鍵狀態異常，請重試".to_string(),
+        }));
+    };
+
+    if record.request_fingerprint.as_deref() != Some(fingerprint) {
+        return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "相同 Idempotency-Key 的請求內容不一致".to_string(),
+        }));
+    }
+
+    let (Some(status), Some(body)) = (record.response_status, record.response_body) else {
+        return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "相同 Idempotency-Key 的請求仍在處理中".to_string(),
+        }));
+    };
+
+    log::info!("重送請求命中冪等鍵 (user_id: {}, key: {})", user_id, key);
+    let mut value: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("replayed".to_string(), serde_json::Value::Bool(true));
+    }
+    let status = StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
+    Ok(HttpResponse::build(status).json(value))
+}
+
+async fn release(rb: &RBatis, user_id: &str, key: &str) {
+    let _ = rb
+        .exec(
+            "DELETE FROM idempotency_key WHERE user_id = ? AND idempotency_key = ?",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(key.to_string())],
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_ignores_client_request_id() {
+        let a = serde_json::json!({"title": "學習日文", "client_request_id": "abc"});
+        let b = serde_json::json!({"client_request_id": "xyz", "title": "學習日文"});
+        assert_eq!(request_fingerprint(&a), request_fingerprint(&b));
+    }
+
+    #[test]
+    fn test_fingerprint_detects_different_payload() {
+        let a = serde_json::json!({"title": "學習日文"});
+        let b = serde_json::json!({"title": "學習英文"});
+        assert_ne!(request_fingerprint(&a), request_fingerprint(&b));
+    }
+}
@@ -0,0 +1,215 @@
+// 管理員代理檢視：客服排查問題時以使用者身分唯讀查看資料，不需請使用者截圖
+//
+// token 的主體是被檢視的使用者，act 記錄簽發的管理員；有效期 15 分鐘且不可延長，
+// 不綁定登入裝置（使用者「登出所有裝置」後同樣失效）。JwtAuth 拒絕所有非讀取請求，
+// 並把每個請求寫入稽核日誌。代理檢視中不具管理員權限，無法再代理其他使用者。
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
+use rbatis::RBatis;
+use rbs::Value;
+
+use crate::ai_tasks::ApiResponse;
+use crate::auth::{Claims, ImpersonationActor};
+
+pub fn is_read_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+/// 代理檢視中的一個請求（稽核日誌使用）
+pub struct ImpersonatedRequest {
+    user_id: String,
+    actor: ImpersonationActor,
+    method: String,
+    path: String,
+    ip: Option<String>,
+}
+
+impl ImpersonatedRequest {
+    pub fn new(req: &ServiceRequest, user_id: &str, actor: ImpersonationActor) -> Self {
+        ImpersonatedRequest {
+            user_id: user_id.to_string(),
+            actor,
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            ip: req.connection_info().realip_remote_addr().map(str::to_string),
+        }
+    }
+
+    pub async fn record(&self, rb: &RBatis, status: StatusCode, rejected: bool) {
+        crate::audit_log::record(
+            rb,
+            crate::audit_log::ACTION_IMPERSONATED_REQUEST,
+            Some(&self.user_id),
+            self.ip.as_deref(),
+            serde_json::json!({
+                "admin_id": self.actor.sub,
+                "admin_email": self.actor.email,
+                "impersonated_user_id": self.user_id,
+                "method": self.method,
+                "path": self.path,
+                "status": status.as_u16(),
+                "rejected": rejected,
+            }),
+        )
+        .await;
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// POST /api/admin/impersonate/{user_id}：簽發唯讀的代理檢視 token
+pub async fn impersonate_user(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(error(StatusCode::FORBIDDEN, "需要管理員權限"));
+    }
+    let Some(admin) = http_req.extensions().get::<Claims>().cloned() else {
+        return Ok(error(StatusCode::UNAUTHORIZED, "請先登入"));
+    };
+    let user_id = path.into_inner();
+    if user_id == admin.sub {
+        return Ok(error(StatusCode::BAD_REQUEST, "不能代理檢視自己的帳號"));
+    }
+
+    let target: Option<serde_json::Value> = match rb
+        .query_decode(
+            "SELECT id, email, COALESCE(token_version, 0) AS token_version FROM user WHERE id = ?",
+            vec![Value::String(user_id.clone())],
+        )
+        .await
+    {
+        Ok(target) => target,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢用戶失敗: {}", e))),
+    };
+    let Some(target) = target.filter(|t| !t.is_null()) else {
+        return Ok(error(StatusCode::NOT_FOUND, "用戶不存在"));
+    };
+
+    let actor = ImpersonationActor { sub: admin.sub.clone(), email: admin.email.clone() };
+    let (token, expires_at) = match crate::auth::generate_impersonation_jwt(
+        &user_id,
+        target["email"].as_str().unwrap_or_default(),
+        target["token_version"].as_i64().unwrap_or(0) as i32,
+        actor,
+    ) {
+        Ok(issued) => issued,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("簽發代理檢視 token 失敗: {}", e))),
+    };
+
+    let ip = http_req.connection_info().realip_remote_addr().map(str::to_string);
+    crate::audit_log::record(
+        rb.get_ref(),
+        crate::audit_log::ACTION_IMPERSONATION_STARTED,
+        Some(&user_id),
+        ip.as_deref(),
+        serde_json::json!({
+            "admin_id": admin.sub,
+            "admin_email": admin.email,
+            "impersonated_user_id": user_id,
+            "expires_at": expires_at.to_rfc3339(),
+        }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "token": token,
+            "user_id": user_id,
+            "read_only": true,
+            "expires_at": expires_at.to_rfc3339(),
+        })),
+        message: format!("已簽發代理檢視 token，{} 分鐘內有效", crate::auth::IMPERSONATION_EXPIRATION_MINUTES),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_impersonation_is_read_only_and_audited() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let admin = test_utils::create_user(&app, "impersonate-admin").await;
+        let user = test_utils::create_user(&app, "impersonated").await;
+        test_utils::grant_admin("impersonate-admin");
+
+        // 一般使用者不能代理
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/admin/impersonate/{}", admin.id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/admin/impersonate/{}", user.id))
+            .insert_header(admin.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let token = body["data"]["token"].as_str().unwrap().to_string();
+        let claims = crate::auth::verify_jwt(&token).unwrap();
+        assert_eq!(claims.sub, user.id);
+        assert_eq!(claims.act.as_ref().map(|a| a.sub.as_str()), Some(admin.id.as_str()));
+        assert!(claims.read_only);
+        assert!(claims.exp - claims.iat <= 15 * 60);
+        let bearer = ("Authorization", format!("Bearer {}", token));
+
+        // 可以讀取使用者資料
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}", user.id))
+            .insert_header(bearer.clone())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+
+        // 不能寫入，也不具管理員權限
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tags")
+            .insert_header(bearer.clone())
+            .set_json(serde_json::json!({"name": "work"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/admin/metrics")
+            .insert_header(bearer.clone())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT action, user_id, detail FROM audit_log WHERE action IN (?, ?)",
+                vec![
+                    Value::String(crate::audit_log::ACTION_IMPERSONATION_STARTED.to_string()),
+                    Value::String(crate::audit_log::ACTION_IMPERSONATED_REQUEST.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|row| row["user_id"] == user.id.as_str()));
+        let details: Vec<serde_json::Value> =
+            rows.iter().map(|row| match &row["detail"] {
+                serde_json::Value::String(text) => serde_json::from_str(text).unwrap(),
+                detail => detail.clone(),
+            }).collect();
+        assert!(details.iter().all(|d| d["admin_id"] == admin.id.as_str()));
+        let rejected: Vec<&serde_json::Value> = details.iter().filter(|d| d["rejected"] == true).collect();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0]["method"], "POST");
+        assert_eq!(rejected[0]["path"], "/api/tags");
+    }
+}
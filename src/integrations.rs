@@ -0,0 +1,727 @@
+// 外部活動匯入：其他 App（例如跑步紀錄）完成活動後呼叫 POST /api/integrations/ingest，
+// 依使用者設定的活動對應自動完成重複性任務當天的子任務。
+//
+// 匯入端點不使用 JWT，改以每位使用者的整合密鑰驗證：
+//   X-LifeUp-User-Id: 使用者 ID
+//   X-LifeUp-Signature: sha256=<以密鑰對原始請求內容計算的 HMAC-SHA256（hex）>
+// 密鑰需要用來驗證簽章，因此以明文保存，只在建立（輪替）時回傳一次。
+// 同一來源的 external_id 只處理一次；沒有對應的活動類型保存為未對應事件，待使用者設定對應。
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use rbatis::RBatis;
+use rbs::{value, Value};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::Task;
+
+pub const USER_HEADER: &str = "X-LifeUp-User-Id";
+pub const SIGNATURE_HEADER: &str = "X-LifeUp-Signature";
+const SIGNATURE_PREFIX: &str = "sha256=";
+const SECRET_PREFIX: &str = "lu_whsec_";
+
+pub const EVENT_COMPLETED: &str = "completed";
+pub const EVENT_UNMATCHED: &str = "unmatched";
+
+// 欄位長度與 metadata 大小上限
+const FIELD_MAX_CHARS: usize = 100;
+const METADATA_MAX_BYTES: usize = 4 * 1024;
+// 活動時間最多可比伺服器時間晚多久（容許裝置時鐘誤差）
+const MAX_FUTURE_SKEW_MINUTES: i64 = 10;
+const DEFAULT_UNMATCHED_LIMIT: i64 = 50;
+const MAX_UNMATCHED_LIMIT: i64 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct IngestPayload {
+    pub source: String,
+    pub external_id: String,
+    pub activity_type: String,
+    pub occurred_at: String,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MappingRequest {
+    pub activity_type: Option<String>,
+    pub task_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnmatchedQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityMapping {
+    pub id: String,
+    pub user_id: String,
+    pub activity_type: String,
+    pub task_id: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrationEvent {
+    pub id: String,
+    pub user_id: String,
+    pub source: String,
+    pub external_id: String,
+    pub activity_type: String,
+    pub occurred_at: String,
+    pub metadata: Option<serde_json::Value>,
+    pub status: String,
+    pub task_id: Option<String>,
+    pub task_date: Option<String>,
+    pub created_at: Option<String>,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// 活動類型比對不分大小寫與前後空白
+fn normalize_activity_type(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn signature_mac(secret: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意長度的金鑰");
+    mac.update(body);
+    mac
+}
+
+/// 計算請求內容的簽章標頭值（sha256=<hex>），與外部 App 的簽法相同，供測試使用
+#[cfg(test)]
+fn sign(secret: &str, body: &[u8]) -> String {
+    format!("{}{}", SIGNATURE_PREFIX, hex::encode(signature_mac(secret, body).finalize().into_bytes()))
+}
+
+/// 以固定時間比較簽章，避免依比對時間推測正確值
+fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(provided) = signature.trim().strip_prefix(SIGNATURE_PREFIX) else {
+        return false;
+    };
+    let Ok(provided) = hex::decode(provided) else {
+        return false;
+    };
+    signature_mac(secret, body).verify_slice(&provided).is_ok()
+}
+
+async fn load_secret(rb: &RBatis, user_id: &str) -> std::result::Result<Option<String>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT secret FROM integration_secret WHERE user_id = ?",
+            vec![Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(rows.first().and_then(|row| row["secret"].as_str()).map(str::to_string))
+}
+
+/// 驗證匯入內容的欄位；回傳正規化的活動類型與活動時間
+fn validate_payload(payload: &IngestPayload) -> std::result::Result<(String, DateTime<Utc>), String> {
+    for (name, value) in [
+        ("source", &payload.source),
+        ("external_id", &payload.external_id),
+        ("activity_type", &payload.activity_type),
+    ] {
+        if value.trim().is_empty() {
+            return Err(format!("{} 不能為空", name));
+        }
+        if value.chars().count() > FIELD_MAX_CHARS {
+            return Err(format!("{} 最多 {} 個字", name, FIELD_MAX_CHARS));
+        }
+    }
+    let occurred_at = DateTime::parse_from_rfc3339(payload.occurred_at.trim())
+        .map_err(|_| "occurred_at 必須是 RFC 3339 時間".to_string())?
+        .with_timezone(&Utc);
+    if occurred_at > Utc::now() + chrono::Duration::minutes(MAX_FUTURE_SKEW_MINUTES) {
+        return Err("occurred_at 不能是未來時間".to_string());
+    }
+    if payload.metadata.as_ref().is_some_and(|m| m.to_string().len() > METADATA_MAX_BYTES) {
+        return Err(format!("metadata 最多 {} bytes", METADATA_MAX_BYTES));
+    }
+    Ok((normalize_activity_type(&payload.activity_type), occurred_at))
+}
+
+async fn find_event(rb: &RBatis, user_id: &str, source: &str, external_id: &str) -> std::result::Result<Option<IntegrationEvent>, rbatis::Error> {
+    let events: Vec<IntegrationEvent> = rb
+        .query_decode(
+            "SELECT * FROM integration_event WHERE user_id = ? AND source = ? AND external_id = ?",
+            vec![
+                Value::String(user_id.to_string()),
+                Value::String(source.to_string()),
+                Value::String(external_id.to_string()),
+            ],
+        )
+        .await?;
+    Ok(events.into_iter().next())
+}
+
+async fn find_mapping(rb: &RBatis, user_id: &str, activity_type: &str) -> std::result::Result<Option<ActivityMapping>, rbatis::Error> {
+    let mappings: Vec<ActivityMapping> = rb
+        .query_decode(
+            "SELECT * FROM activity_mapping WHERE user_id = ? AND activity_type = ?",
+            vec![Value::String(user_id.to_string()), Value::String(activity_type.to_string())],
+        )
+        .await?;
+    Ok(mappings.into_iter().next())
+}
+
+async fn insert_event(rb: &RBatis, event: &IntegrationEvent) -> std::result::Result<(), rbatis::Error> {
+    rb.exec(
+        "INSERT INTO integration_event (id, user_id, source, external_id, activity_type, occurred_at, metadata, status, task_id, task_date, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        vec![
+            Value::String(event.id.clone()),
+            Value::String(event.user_id.clone()),
+            Value::String(event.source.clone()),
+            Value::String(event.external_id.clone()),
+            Value::String(event.activity_type.clone()),
+            Value::String(event.occurred_at.clone()),
+            event.metadata.as_ref().map(|m| Value::String(m.to_string())).unwrap_or(Value::Null),
+            Value::String(event.status.clone()),
+            event.task_id.clone().map(Value::String).unwrap_or(Value::Null),
+            event.task_date.clone().map(Value::String).unwrap_or(Value::Null),
+            Value::String(Utc::now().to_rfc3339()),
+        ],
+    )
+    .await
+    .map(|_| ())
+}
+
+/// 對應的任務必須是使用者自己的重複性父任務
+async fn validate_mapping_task(rb: &RBatis, user_id: &str, task_id: &str) -> std::result::Result<(), HttpResponse> {
+    match Task::select_by_map(rb, value!{"id": task_id}).await {
+        Ok(tasks) => match tasks.into_iter().next() {
+            Some(task) if task.user_id.as_deref() != Some(user_id) => Err(error(StatusCode::FORBIDDEN, "無權限存取此任務")),
+            Some(task) if task.is_recurring != Some(1) || task.parent_task_id.is_some() => {
+                Err(error(StatusCode::UNPROCESSABLE_ENTITY, "活動只能對應到重複性任務"))
+            }
+            Some(_) => Ok(()),
+            None => Err(error(StatusCode::NOT_FOUND, "找不到任務")),
+        },
+        Err(e) => Err(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢任務失敗: {}", e))),
+    }
+}
+
+/// POST /api/integrations/ingest：匯入外部 App 完成的活動（以整合密鑰簽章驗證，不需登入）
+pub async fn ingest(http_req: HttpRequest, rb: web::Data<RBatis>, body: web::Bytes) -> Result<HttpResponse> {
+    let header = |name: &str| http_req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::trim).filter(|v| !v.is_empty());
+    let (Some(user_id), Some(signature)) = (header(USER_HEADER), header(SIGNATURE_HEADER)) else {
+        return Ok(error(StatusCode::UNAUTHORIZED, format!("缺少 {} 或 {} 標頭", USER_HEADER, SIGNATURE_HEADER)));
+    };
+    let user_id = user_id.to_string();
+    // 未設定密鑰與簽章錯誤回傳相同訊息，不透露使用者是否存在
+    let secret = match load_secret(rb.get_ref(), &user_id).await {
+        Ok(secret) => secret,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("讀取整合密鑰失敗: {}", e))),
+    };
+    if !secret.is_some_and(|secret| verify_signature(&secret, &body, signature)) {
+        log::warn!("外部活動匯入簽章驗證失敗: user {}", user_id);
+        return Ok(error(StatusCode::UNAUTHORIZED, "簽章驗證失敗"));
+    }
+
+    let payload: IngestPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, format!("請求格式錯誤: {}", e))),
+    };
+    let (activity_type, occurred_at) = match validate_payload(&payload) {
+        Ok(validated) => validated,
+        Err(message) => return Ok(error(StatusCode::BAD_REQUEST, message)),
+    };
+    let source = payload.source.trim().to_string();
+    let external_id = payload.external_id.trim().to_string();
+
+    // 同一來源的 external_id 只處理一次，重送時回傳第一次的結果
+    match find_event(rb.get_ref(), &user_id, &source, &external_id).await {
+        Ok(Some(event)) => {
+            return Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({
+                    "event_id": event.id,
+                    "status": event.status,
+                    "task_id": event.task_id,
+                    "date": event.task_date,
+                    "duplicate": true,
+                })),
+                message: "此活動已匯入過".to_string(),
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢匯入紀錄失敗: {}", e))),
+    }
+
+    let mut event = IntegrationEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.clone(),
+        source,
+        external_id,
+        activity_type: activity_type.clone(),
+        occurred_at: occurred_at.to_rfc3339(),
+        metadata: payload.metadata.clone(),
+        status: EVENT_UNMATCHED.to_string(),
+        task_id: None,
+        task_date: None,
+        created_at: None,
+    };
+
+    let mapping = match find_mapping(rb.get_ref(), &user_id, &activity_type).await {
+        Ok(mapping) => mapping,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢活動對應失敗: {}", e))),
+    };
+    let parent_task = match &mapping {
+        Some(mapping) => match Task::select_by_map(rb.get_ref(), value!{"id": &mapping.task_id}).await {
+            Ok(tasks) => tasks.into_iter().next(),
+            Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢任務失敗: {}", e))),
+        },
+        None => None,
+    };
+
+    // 沒有對應（或對應的任務已刪除）時保存為未對應事件
+    let Some(parent_task) = parent_task else {
+        if let Err(e) = insert_event(rb.get_ref(), &event).await {
+            return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("保存匯入紀錄失敗: {}", e)));
+        }
+        log::info!("使用者 {} 匯入未對應的活動類型 {}（來源 {}）", user_id, activity_type, event.source);
+        return Ok(HttpResponse::Accepted().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "event_id": event.id,
+                "status": event.status,
+                "duplicate": false,
+            })),
+            message: format!("活動類型 {} 尚未對應任務，已保存待處理", activity_type),
+        }));
+    };
+
+    // 活動日期依使用者時區決定，再沿用補記完成的流程（必要時依模板建立當天子任務）
+    let settings = match crate::user_settings::load_ui_settings(rb.get_ref(), &user_id).await {
+        Ok(settings) => settings,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("讀取使用者設定失敗: {}", e))),
+    };
+    let tz = crate::local_date::parse_utc_offset(&settings.timezone).unwrap_or_else(crate::local_date::default_timezone);
+    let day = occurred_at.with_timezone(&tz).date_naive();
+    let (data, _) = match crate::routes::complete_recurring_day_for(rb.get_ref(), &parent_task, day, true).await {
+        Ok(result) => result,
+        Err((_, message)) => return Ok(error(StatusCode::UNPROCESSABLE_ENTITY, message)),
+    };
+
+    event.status = EVENT_COMPLETED.to_string();
+    event.task_id = parent_task.id.clone();
+    event.task_date = Some(day.format("%Y-%m-%d").to_string());
+    if let Err(e) = insert_event(rb.get_ref(), &event).await {
+        // 完成流程本身可重複執行，保存紀錄失敗只影響去重
+        log::warn!("保存匯入紀錄失敗 ({}): {}", event.id, e);
+    }
+    log::info!("使用者 {} 匯入活動 {} 完成任務 {} 於 {}", user_id, activity_type, parent_task.id.as_deref().unwrap_or_default(), day);
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "event_id": event.id,
+            "status": event.status,
+            "task_id": event.task_id,
+            "date": event.task_date,
+            "newly_completed": data["newly_completed"],
+            "duplicate": false,
+        })),
+        message: format!("已完成 {} 的任務", day.format("%Y-%m-%d")),
+    }))
+}
+
+/// GET /api/integrations/secret：是否已設定整合密鑰（不回傳密鑰本身）
+pub async fn get_secret_status(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let rows: Vec<serde_json::Value> = match rb
+        .query_decode(
+            "SELECT created_at FROM integration_secret WHERE user_id = ?",
+            vec![Value::String(user_id)],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("讀取整合密鑰失敗: {}", e))),
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "configured": !rows.is_empty(),
+            "created_at": rows.first().map(|row| row["created_at"].clone()),
+        })),
+        message: "獲取整合密鑰狀態成功".to_string(),
+    }))
+}
+
+/// POST /api/integrations/secret：建立或輪替整合密鑰（舊密鑰立即失效，新密鑰只回傳這一次）
+pub async fn rotate_secret(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret = format!("{}{}", SECRET_PREFIX, hex::encode(bytes));
+    let now = Utc::now().to_rfc3339();
+    let result = rb
+        .exec(
+            "INSERT INTO integration_secret (user_id, secret, created_at) VALUES (?, ?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET secret = excluded.secret, created_at = excluded.created_at",
+            vec![Value::String(user_id.clone()), Value::String(secret.clone()), Value::String(now.clone())],
+        )
+        .await;
+    if let Err(e) = result {
+        return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立整合密鑰失敗: {}", e)));
+    }
+    log::info!("使用者 {} 建立新的整合密鑰", user_id);
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "secret": secret,
+            "created_at": now,
+            "user_header": USER_HEADER,
+            "signature_header": SIGNATURE_HEADER,
+        })),
+        message: "整合密鑰已建立，請妥善保存（之後無法再次查看）".to_string(),
+    }))
+}
+
+/// DELETE /api/integrations/secret：刪除整合密鑰，停用外部活動匯入
+pub async fn delete_secret(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    match rb.exec("DELETE FROM integration_secret WHERE user_id = ?", vec![Value::String(user_id)]).await {
+        Ok(result) if result.rows_affected == 0 => Ok(error(StatusCode::NOT_FOUND, "尚未設定整合密鑰")),
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "整合密鑰已刪除".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除整合密鑰失敗: {}", e))),
+    }
+}
+
+/// GET /api/integrations/mappings
+pub async fn list_mappings(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let mappings: std::result::Result<Vec<ActivityMapping>, _> = rb
+        .query_decode(
+            "SELECT * FROM activity_mapping WHERE user_id = ? ORDER BY activity_type",
+            vec![Value::String(user_id)],
+        )
+        .await;
+    match mappings {
+        Ok(mappings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(mappings),
+            message: "獲取活動對應成功".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("獲取活動對應失敗: {}", e))),
+    }
+}
+
+/// POST /api/integrations/mappings：將活動類型對應到重複性任務（每種活動類型只能對應一個任務）
+pub async fn create_mapping(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<MappingRequest>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let activity_type = normalize_activity_type(req.activity_type.as_deref().unwrap_or_default());
+    let task_id = req.task_id.as_deref().map(str::trim).unwrap_or_default().to_string();
+    if activity_type.is_empty() || task_id.is_empty() {
+        return Ok(error(StatusCode::BAD_REQUEST, "activity_type 與 task_id 為必填"));
+    }
+    if activity_type.chars().count() > FIELD_MAX_CHARS {
+        return Ok(error(StatusCode::BAD_REQUEST, format!("activity_type 最多 {} 個字", FIELD_MAX_CHARS)));
+    }
+    if let Err(resp) = validate_mapping_task(rb.get_ref(), &user_id, &task_id).await {
+        return Ok(resp);
+    }
+    match find_mapping(rb.get_ref(), &user_id, &activity_type).await {
+        Ok(Some(_)) => return Ok(error(StatusCode::CONFLICT, format!("活動類型 {} 已有對應的任務", activity_type))),
+        Ok(None) => {}
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢活動對應失敗: {}", e))),
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let mapping = ActivityMapping {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id,
+        activity_type,
+        task_id,
+        created_at: Some(now.clone()),
+        updated_at: Some(now),
+    };
+    let result = rb
+        .exec(
+            "INSERT INTO activity_mapping (id, user_id, activity_type, task_id, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+            vec![
+                Value::String(mapping.id.clone()),
+                Value::String(mapping.user_id.clone()),
+                Value::String(mapping.activity_type.clone()),
+                Value::String(mapping.task_id.clone()),
+                Value::String(mapping.created_at.clone().unwrap_or_default()),
+                Value::String(mapping.updated_at.clone().unwrap_or_default()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(mapping),
+            message: "活動對應已建立".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立活動對應失敗: {}", e))),
+    }
+}
+
+async fn load_own_mapping(rb: &RBatis, user_id: &str, mapping_id: &str) -> std::result::Result<ActivityMapping, HttpResponse> {
+    let mappings: Vec<ActivityMapping> = rb
+        .query_decode("SELECT * FROM activity_mapping WHERE id = ?", vec![Value::String(mapping_id.to_string())])
+        .await
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢活動對應失敗: {}", e)))?;
+    match mappings.into_iter().next() {
+        Some(mapping) if mapping.user_id == user_id => Ok(mapping),
+        Some(_) => Err(error(StatusCode::FORBIDDEN, "無權限修改此活動對應")),
+        None => Err(error(StatusCode::NOT_FOUND, "找不到活動對應")),
+    }
+}
+
+/// PUT /api/integrations/mappings/{id}：改為對應到其他重複性任務
+pub async fn update_mapping(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<MappingRequest>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let mut mapping = match load_own_mapping(rb.get_ref(), &user_id, &path.into_inner()).await {
+        Ok(mapping) => mapping,
+        Err(resp) => return Ok(resp),
+    };
+    let Some(task_id) = req.task_id.as_deref().map(str::trim).filter(|id| !id.is_empty()) else {
+        return Ok(error(StatusCode::BAD_REQUEST, "task_id 為必填"));
+    };
+    if let Err(resp) = validate_mapping_task(rb.get_ref(), &user_id, task_id).await {
+        return Ok(resp);
+    }
+    mapping.task_id = task_id.to_string();
+    mapping.updated_at = Some(Utc::now().to_rfc3339());
+    let result = rb
+        .exec(
+            "UPDATE activity_mapping SET task_id = ?, updated_at = ? WHERE id = ?",
+            vec![
+                Value::String(mapping.task_id.clone()),
+                Value::String(mapping.updated_at.clone().unwrap_or_default()),
+                Value::String(mapping.id.clone()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(mapping),
+            message: "活動對應已更新".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新活動對應失敗: {}", e))),
+    }
+}
+
+/// DELETE /api/integrations/mappings/{id}
+pub async fn delete_mapping(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let mapping = match load_own_mapping(rb.get_ref(), &user_id, &path.into_inner()).await {
+        Ok(mapping) => mapping,
+        Err(resp) => return Ok(resp),
+    };
+    match rb.exec("DELETE FROM activity_mapping WHERE id = ?", vec![Value::String(mapping.id)]).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "活動對應已刪除".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除活動對應失敗: {}", e))),
+    }
+}
+
+/// GET /api/integrations/unmatched?limit=50：尚未對應任務的匯入活動（新到舊）
+pub async fn list_unmatched(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<UnmatchedQuery>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_UNMATCHED_LIMIT).clamp(1, MAX_UNMATCHED_LIMIT);
+    let events: std::result::Result<Vec<IntegrationEvent>, _> = rb
+        .query_decode(
+            "SELECT * FROM integration_event WHERE user_id = ? AND status = ? ORDER BY occurred_at DESC LIMIT ?",
+            vec![Value::String(user_id), Value::String(EVENT_UNMATCHED.to_string()), Value::I64(limit)],
+        )
+        .await;
+    match events {
+        Ok(events) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(events),
+            message: "獲取未對應的活動成功".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("獲取未對應的活動失敗: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use serde_json::json;
+
+    use crate::test_utils::{self, call_json};
+
+    #[test]
+    fn test_hmac_matches_rfc_4231_vector() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert!(verify_signature("Jefe", b"body", &sign("Jefe", b"body")));
+        assert!(!verify_signature("Jefe", b"body!", &sign("Jefe", b"body")));
+        assert!(!verify_signature("Jefe", b"body", "sha256=not-hex"));
+    }
+
+    #[actix_web::test]
+    async fn test_ingest_completes_mapped_recurring_task_once() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "morning_runner").await;
+
+        let req = TestRequest::post()
+            .uri("/api/recurring-tasks")
+            .insert_header(user.auth())
+            .set_json(json!({
+                "user_id": user.id,
+                "title": "晨跑",
+                "recurrence_pattern": "daily",
+                "subtask_templates": [
+                    {"title": "跑 3 公里", "description": null, "difficulty": 2, "experience": 20, "order": 1, "skill_tags": null}
+                ]
+            }))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+        rb.exec(
+            "UPDATE task SET start_date = ? WHERE id = ?",
+            vec![
+                Value::String((Utc::now() - chrono::Duration::days(3)).to_rfc3339()),
+                Value::String(task_id.clone()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let ingest = |secret: &str, payload: serde_json::Value| {
+            let body = payload.to_string();
+            TestRequest::post()
+                .uri("/api/integrations/ingest")
+                .insert_header((USER_HEADER, user.id.clone()))
+                .insert_header((SIGNATURE_HEADER, sign(secret, body.as_bytes())))
+                .insert_header(("Content-Type", "application/json"))
+                .set_payload(body)
+                .to_request()
+        };
+        let run = |external_id: &str, activity_type: &str| {
+            json!({
+                "source": "strava",
+                "external_id": external_id,
+                "activity_type": activity_type,
+                "occurred_at": Utc::now().to_rfc3339(),
+                "metadata": {"distance_km": 3.2},
+            })
+        };
+
+        // 尚未建立密鑰
+        assert_eq!(call_json(&app, ingest("guess", run("a1", "run"))).await.0, StatusCode::UNAUTHORIZED);
+        let req = TestRequest::post().uri("/api/integrations/secret").insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let secret = body["data"]["secret"].as_str().unwrap().to_string();
+        assert_eq!(call_json(&app, ingest("wrong", run("a1", "run"))).await.0, StatusCode::UNAUTHORIZED);
+
+        // 沒有對應的活動類型保存為未對應事件
+        let (status, body) = call_json(&app, ingest(&secret, run("a1", "Run"))).await;
+        assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+        assert_eq!(body["data"]["status"], EVENT_UNMATCHED);
+        let req = TestRequest::get().uri("/api/integrations/unmatched").insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let unmatched = body["data"].as_array().unwrap();
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0]["activity_type"], "run");
+
+        let req = TestRequest::post()
+            .uri("/api/integrations/mappings")
+            .insert_header(user.auth())
+            .set_json(json!({"activity_type": "RUN", "task_id": task_id}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let req = TestRequest::post()
+            .uri("/api/integrations/mappings")
+            .insert_header(user.auth())
+            .set_json(json!({"activity_type": "run", "task_id": task_id}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::CONFLICT);
+
+        let (status, body) = call_json(&app, ingest(&secret, run("a2", "run"))).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["status"], EVENT_COMPLETED);
+        assert_eq!(body["data"]["newly_completed"], 1);
+        let today = crate::local_date::local_today_string();
+        assert_eq!(body["data"]["date"], today.as_str());
+        let subtasks = Task::select_by_map(&rb, value!{"parent_task_id": &task_id, "task_date": &today}).await.unwrap();
+        assert_eq!(subtasks.len(), 1);
+        assert_eq!(subtasks[0].status, Some(crate::models::TaskStatus::DailyCompleted.to_i32()));
+
+        // 重送相同 external_id 不會重複處理
+        let (status, body) = call_json(&app, ingest(&secret, run("a2", "run"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["duplicate"], true);
+        let count: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM integration_event WHERE user_id = ?", vec![Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,357 @@
+// 長時間工作的狀態追蹤：工作寫入 background_job 表，交給 background_jobs 佇列（有上限的並行數）執行
+//
+// API 立即回傳工作 ID，客戶端以 GET /api/jobs/{id} 輪詢進度與結果。
+// 伺服器重啟時尚未完成的工作標記為 interrupted，不會自動重新執行。
+
+use std::future::Future;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::Value;
+use serde::Deserialize;
+
+use crate::ai_tasks::ApiResponse;
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_INTERRUPTED: &str = "interrupted";
+
+pub const KIND_RECOMPUTE: &str = "recompute";
+pub const KIND_DAILY_PROGRESS_REBUILD: &str = "daily_progress_rebuild";
+
+// 使用者工作列表最多回傳筆數
+const MAX_LIST_LIMIT: i64 = 100;
+const DEFAULT_LIST_LIMIT: i64 = 20;
+
+/// 執行中的工作用來回報進度
+#[derive(Clone)]
+pub struct JobContext {
+    rb: RBatis,
+    job_id: String,
+}
+
+impl JobContext {
+    /// 更新進度（0–100）；寫入失敗只寫日誌
+    pub async fn progress(&self, progress: i32) {
+        let result = self
+            .rb
+            .exec(
+                "UPDATE background_job SET progress = ?, updated_at = ? WHERE id = ?",
+                vec![
+                    Value::I32(progress.clamp(0, 100)),
+                    Value::String(Utc::now().to_rfc3339()),
+                    Value::String(self.job_id.clone()),
+                ],
+            )
+            .await;
+        if let Err(e) = result {
+            log::warn!("更新背景工作 {} 進度失敗: {}", self.job_id, e);
+        }
+    }
+
+    async fn finish(&self, status: &str, result: Option<String>, error: Option<String>) {
+        let now = Utc::now().to_rfc3339();
+        let outcome = self
+            .rb
+            .exec(
+                "UPDATE background_job SET status = ?, progress = CASE WHEN ? = 'succeeded' THEN 100 ELSE progress END,
+                     result = ?, error = ?, finished_at = ?, updated_at = ?
+                 WHERE id = ?",
+                vec![
+                    Value::String(status.to_string()),
+                    Value::String(status.to_string()),
+                    result.map(Value::String).unwrap_or(Value::Null),
+                    error.map(Value::String).unwrap_or(Value::Null),
+                    Value::String(now.clone()),
+                    Value::String(now),
+                    Value::String(self.job_id.clone()),
+                ],
+            )
+            .await;
+        if let Err(e) = outcome {
+            log::error!("更新背景工作 {} 狀態為 {} 失敗: {}", self.job_id, status, e);
+        }
+    }
+}
+
+/// 建立工作紀錄並送入背景佇列執行
+pub struct JobRunner {
+    rb: RBatis,
+}
+
+impl JobRunner {
+    pub fn new(rb: &RBatis) -> Self {
+        JobRunner { rb: rb.clone() }
+    }
+
+    /// 送出工作並回傳工作 ID；佇列已滿時工作標記為 failed 並回傳 Err
+    pub async fn enqueue<F, Fut>(&self, user_id: Option<&str>, kind: &str, job: F) -> std::result::Result<String, String>
+    where
+        F: FnOnce(JobContext) -> Fut + Send + 'static,
+        Fut: Future<Output = std::result::Result<serde_json::Value, String>> + Send + 'static,
+    {
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        self.rb
+            .exec(
+                "INSERT INTO background_job (id, user_id, kind, status, progress, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, 0, ?, ?)",
+                vec![
+                    Value::String(job_id.clone()),
+                    user_id.map(|id| Value::String(id.to_string())).unwrap_or(Value::Null),
+                    Value::String(kind.to_string()),
+                    Value::String(STATUS_QUEUED.to_string()),
+                    Value::String(now.clone()),
+                    Value::String(now),
+                ],
+            )
+            .await
+            .map_err(|e| format!("建立背景工作失敗: {}", e))?;
+
+        let context = JobContext { rb: self.rb.clone(), job_id: job_id.clone() };
+        let name = format!("{} ({})", kind, job_id);
+        let submitted = crate::background_jobs::submit(&name, {
+            let context = context.clone();
+            async move { run(context, job).await }
+        });
+        if !submitted {
+            context
+                .finish(STATUS_FAILED, None, Some("背景工作佇列已滿".to_string()))
+                .await;
+            return Err("背景工作佇列已滿，請稍後再試".to_string());
+        }
+        Ok(job_id)
+    }
+}
+
+async fn run<F, Fut>(context: JobContext, job: F)
+where
+    F: FnOnce(JobContext) -> Fut,
+    Fut: Future<Output = std::result::Result<serde_json::Value, String>>,
+{
+    let now = Utc::now().to_rfc3339();
+    if let Err(e) = context
+        .rb
+        .exec(
+            "UPDATE background_job SET status = ?, started_at = ?, updated_at = ? WHERE id = ?",
+            vec![
+                Value::String(STATUS_RUNNING.to_string()),
+                Value::String(now.clone()),
+                Value::String(now),
+                Value::String(context.job_id.clone()),
+            ],
+        )
+        .await
+    {
+        log::warn!("更新背景工作 {} 為執行中失敗: {}", context.job_id, e);
+    }
+
+    match job(context.clone()).await {
+        Ok(result) => context.finish(STATUS_SUCCEEDED, Some(result.to_string()), None).await,
+        Err(error) => {
+            log::error!("背景工作 {} 失敗: {}", context.job_id, error);
+            context.finish(STATUS_FAILED, None, Some(error)).await
+        }
+    }
+}
+
+/// 啟動時把上次程序結束前未完成的工作標記為 interrupted，回傳筆數
+pub async fn mark_interrupted(rb: &RBatis) -> std::result::Result<u64, rbatis::Error> {
+    let now = Utc::now().to_rfc3339();
+    let result = rb
+        .exec(
+            "UPDATE background_job SET status = ?, error = '伺服器重新啟動，工作已中斷', finished_at = ?, updated_at = ?
+             WHERE status IN (?, ?)",
+            vec![
+                Value::String(STATUS_INTERRUPTED.to_string()),
+                Value::String(now.clone()),
+                Value::String(now),
+                Value::String(STATUS_QUEUED.to_string()),
+                Value::String(STATUS_RUNNING.to_string()),
+            ],
+        )
+        .await?;
+    Ok(result.rows_affected)
+}
+
+const JOB_COLUMNS: &str =
+    "id, user_id, kind, status, progress, result, error, created_at, started_at, finished_at, updated_at";
+
+// result 以 JSON 字串儲存，回傳時還原為物件
+fn decode_result(mut job: serde_json::Value) -> serde_json::Value {
+    if let Some(text) = job["result"].as_str() {
+        job["result"] = serde_json::from_str(text).unwrap_or_else(|_| serde_json::Value::String(text.to_string()));
+    }
+    job
+}
+
+fn internal_error(context: &str, e: rbatis::Error) -> HttpResponse {
+    log::error!("{}: {}", context, e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("{}: {}", context, e),
+    })
+}
+
+/// 查詢單一工作（建立者或管理員）
+pub async fn get_job(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let job_id = path.into_inner();
+    let jobs: Vec<serde_json::Value> = match rb
+        .query_decode(
+            &format!("SELECT {} FROM background_job WHERE id = ?", JOB_COLUMNS),
+            vec![Value::String(job_id)],
+        )
+        .await
+    {
+        Ok(jobs) => jobs,
+        Err(e) => return Ok(internal_error("查詢背景工作失敗", e)),
+    };
+    let not_found = || {
+        HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "找不到此背景工作".to_string(),
+        })
+    };
+    let Some(job) = jobs.into_iter().next() else {
+        return Ok(not_found());
+    };
+    // 非建立者一律回傳 404，不透露工作是否存在
+    let is_owner = job["user_id"].as_str().is_some()
+        && crate::auth::current_user_id(&http_req).as_deref() == job["user_id"].as_str();
+    if !is_owner && !crate::auth::is_admin_request(&http_req) {
+        return Ok(not_found());
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(decode_result(job)),
+        message: "獲取背景工作成功".to_string(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct JobListQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// 列出使用者的工作（新到舊）
+pub async fn list_user_jobs(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<JobListQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "無權限查看此使用者的背景工作".to_string(),
+        }));
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+    let mut sql = format!("SELECT {} FROM background_job WHERE user_id = ?", JOB_COLUMNS);
+    let mut args = vec![Value::String(user_id)];
+    if let Some(status) = query.status.as_deref().filter(|s| !s.is_empty()) {
+        sql.push_str(" AND status = ?");
+        args.push(Value::String(status.to_string()));
+    }
+    sql.push_str(" ORDER BY created_at DESC LIMIT ?");
+    args.push(Value::I64(limit));
+
+    match rb.query_decode::<Vec<serde_json::Value>>(&sql, args).await {
+        Ok(jobs) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(jobs.into_iter().map(decode_result).collect::<Vec<_>>()),
+            message: "獲取背景工作列表成功".to_string(),
+        })),
+        Err(e) => Ok(internal_error("查詢背景工作列表失敗", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    async fn wait_for_status(rb: &RBatis, job_id: &str, status: &str) {
+        for _ in 0..100 {
+            let current: String = rb
+                .query_decode("SELECT status FROM background_job WHERE id = ?", vec![Value::String(job_id.to_string())])
+                .await
+                .unwrap();
+            if current == status {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("背景工作 {} 未在時限內變為 {}", job_id, status);
+    }
+
+    #[actix_web::test]
+    async fn test_job_lifecycle_and_interrupted_after_restart() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let owner = test_utils::create_user(&app, "job-owner").await;
+        let other = test_utils::create_user(&app, "job-other").await;
+
+        let runner = JobRunner::new(&rb);
+        let done = runner
+            .enqueue(Some(&owner.id), "test", |ctx| async move {
+                ctx.progress(50).await;
+                Ok(serde_json::json!({"rows": 3}))
+            })
+            .await
+            .unwrap();
+        wait_for_status(&rb, &done, STATUS_SUCCEEDED).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/jobs/{}", done))
+            .insert_header(owner.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["progress"], 100);
+        assert_eq!(body["data"]["result"]["rows"], 3);
+
+        // 其他使用者看不到
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/jobs/{}", done))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 404);
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/jobs", owner.id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        // 模擬重啟前仍在執行的工作
+        rb.exec(
+            "INSERT INTO background_job (id, user_id, kind, status, progress, created_at, updated_at)
+             VALUES ('stale', ?, 'test', 'running', 40, '2026-01-01T00:00:00+00:00', '2026-01-01T00:00:00+00:00')",
+            vec![Value::String(owner.id.clone())],
+        )
+        .await
+        .unwrap();
+        assert_eq!(mark_interrupted(&rb).await.unwrap(), 1);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/jobs?status=interrupted", owner.id))
+            .insert_header(owner.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let jobs = body["data"].as_array().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0]["id"], "stale");
+        assert_eq!(jobs[0]["progress"], 40);
+    }
+}
@@ -0,0 +1,385 @@
+// 使用者語言：決定 AI 生成內容的語言與 API 回應訊息的語系
+//
+// 語言取自使用者設定的 locale（user_settings），JwtAuth 驗證通過後查詢（程序內快取，設定變更時清除），
+// 以 tokio task-local 保存在請求範圍內；提示詞建構函數以 directive() / current() 取得語言指示，
+// 不必逐層傳遞。背景工作（tokio::spawn）不在請求範圍內：由請求觸發的以 in_current 帶入目前語言，
+// 排程工作則以 user_language 查詢後用 scope 包住。
+//
+// AI 回傳的 JSON 文字欄位以簡單的字元統計檢查是否為預期語言（見 mismatched_field），
+// 不符時由 ai_service 的 LanguageGuard 以更嚴格的指示重試一次。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+
+use rbatis::RBatis;
+use rbs::Value;
+
+/// 支援的語言，預設繁體中文
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    ZhTw,
+    ZhCn,
+    En,
+    Ja,
+}
+
+impl Language {
+    /// 由語系代碼判斷（不分大小寫，接受 zh-Hant / zh-Hans / en-US / ja-JP 等變體）；無法辨識時為 None
+    pub fn from_code(code: &str) -> Option<Language> {
+        let code = code.trim().to_ascii_lowercase().replace('_', "-");
+        match code.as_str() {
+            "zh" | "zh-tw" | "zh-hk" | "zh-mo" | "zh-hant" => Some(Language::ZhTw),
+            "zh-cn" | "zh-sg" | "zh-hans" => Some(Language::ZhCn),
+            c if c.starts_with("zh-hant") => Some(Language::ZhTw),
+            c if c.starts_with("zh-hans") => Some(Language::ZhCn),
+            c if c == "en" || c.starts_with("en-") => Some(Language::En),
+            c if c == "ja" || c.starts_with("ja-") => Some(Language::Ja),
+            _ => None,
+        }
+    }
+
+    /// 由 Accept-Language 標頭判斷，取第一個支援的語言（不處理 q 值排序，瀏覽器已依偏好排列）
+    pub fn from_accept_language(header: &str) -> Option<Language> {
+        header
+            .split(',')
+            .filter_map(|part| part.split(';').next())
+            .find_map(Language::from_code)
+    }
+
+    /// 儲存在 user_settings.locale 的代碼
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::ZhTw => "zh-TW",
+            Language::ZhCn => "zh-CN",
+            Language::En => "en",
+            Language::Ja => "ja",
+        }
+    }
+
+    /// 提示詞中使用的語言名稱
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::ZhTw => "繁體中文",
+            Language::ZhCn => "简体中文",
+            Language::En => "English",
+            Language::Ja => "日本語",
+        }
+    }
+
+    /// 加在提示詞中的語言指示
+    pub fn directive(&self) -> &'static str {
+        match self {
+            Language::ZhTw => "一律使用繁體中文回答。",
+            Language::ZhCn => "一律使用简体中文回答，不要使用繁體字。",
+            Language::En => "Always respond in English.",
+            Language::Ja => "必ず日本語で回答してください。",
+        }
+    }
+
+    /// 生成內容語言不符、重試時使用的指示
+    pub fn strict_directive(&self) -> String {
+        format!(
+            "上一次的輸出語言錯誤。{} 所有文字欄位（title、description、name 等）都必須只使用{}，不可混用其他語言。",
+            self.directive(),
+            self.name()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PromptLanguage {
+    language: Language,
+    // 重試時使用較嚴格的指示
+    strict: bool,
+}
+
+tokio::task_local! {
+    static PROMPT_LANGUAGE: PromptLanguage;
+}
+
+/// 在指定語言的範圍內執行
+pub fn scope<F: Future>(language: Language, future: F) -> impl Future<Output = F::Output> {
+    PROMPT_LANGUAGE.scope(PromptLanguage { language, strict: false }, future)
+}
+
+/// 以嚴格指示在指定語言的範圍內執行（生成內容語言不符時重試用）
+pub fn scope_strict<F: Future>(language: Language, future: F) -> impl Future<Output = F::Output> {
+    PROMPT_LANGUAGE.scope(PromptLanguage { language, strict: true }, future)
+}
+
+/// 帶入目前語言（在請求範圍內呼叫），供 tokio::spawn 的背景工作使用
+pub fn in_current<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    scope(current(), future)
+}
+
+/// 目前的語言；不在任何範圍內時為預設的繁體中文
+pub fn current() -> Language {
+    PROMPT_LANGUAGE.try_with(|p| p.language).unwrap_or_default()
+}
+
+/// 目前語言的提示詞指示（重試範圍內為嚴格版本）
+pub fn directive() -> String {
+    match PROMPT_LANGUAGE.try_with(|p| *p) {
+        Ok(PromptLanguage { language, strict: true }) => language.strict_directive(),
+        Ok(PromptLanguage { language, strict: false }) => language.directive().to_string(),
+        Err(_) => Language::default().directive().to_string(),
+    }
+}
+
+// 使用者語言快取：每個已登入的請求都需要，設定只在 user_settings 儲存時變更
+static LANGUAGE_CACHE: OnceLock<Mutex<HashMap<String, Language>>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<HashMap<String, Language>> {
+    LANGUAGE_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 使用者設定的語言；未設定、無法辨識或查詢失敗時為預設語言
+pub async fn user_language(rb: &RBatis, user_id: &str) -> Language {
+    if let Some(language) = cache().lock().ok().and_then(|c| c.get(user_id).copied()) {
+        return language;
+    }
+    let rows: Result<Vec<serde_json::Value>, _> = rb
+        .query_decode(
+            "SELECT locale FROM user_settings WHERE user_id = ?",
+            vec![Value::String(user_id.to_string())],
+        )
+        .await;
+    match rows {
+        Ok(rows) => {
+            let language = rows
+                .first()
+                .and_then(|row| row["locale"].as_str())
+                .and_then(Language::from_code)
+                .unwrap_or_default();
+            if let Ok(mut cache) = cache().lock() {
+                cache.insert(user_id.to_string(), language);
+            }
+            language
+        }
+        Err(e) => {
+            log::warn!("讀取使用者 {} 的語言設定失敗，使用預設語言: {}", user_id, e);
+            Language::default()
+        }
+    }
+}
+
+/// 使用者設定變更後清除快取
+pub fn invalidate(user_id: &str) {
+    if let Ok(mut cache) = cache().lock() {
+        cache.remove(user_id);
+    }
+}
+
+/// 清除所有快取（重設資料庫時）
+pub fn clear() {
+    if let Ok(mut cache) = cache().lock() {
+        cache.clear();
+    }
+}
+
+// 繁簡對照：只收錄常見且簡體寫法不會出現在繁體中文的字，用來區分繁簡
+const HAN_VARIANTS: [(char, char); 60] = [
+    ('學', '学'), ('習', '习'), ('務', '务'), ('練', '练'), ('讀', '读'), ('寫', '写'),
+    ('說', '说'), ('語', '语'), ('時', '时'), ('間', '间'), ('這', '这'), ('們', '们'),
+    ('個', '个'), ('為', '为'), ('會', '会'), ('動', '动'), ('進', '进'), ('發', '发'),
+    ('書', '书'), ('長', '长'), ('開', '开'), ('關', '关'), ('計', '计'), ('實', '实'),
+    ('現', '现'), ('題', '题'), ('問', '问'), ('體', '体'), ('運', '运'), ('記', '记'),
+    ('經', '经'), ('驗', '验'), ('續', '续'), ('設', '设'), ('標', '标'), ('專', '专'),
+    ('業', '业'), ('項', '项'), ('與', '与'), ('讓', '让'), ('從', '从'), ('對', '对'),
+    ('級', '级'), ('戰', '战'), ('達', '达'), ('總', '总'), ('結', '结'), ('識', '识'),
+    ('論', '论'), ('視', '视'), ('頻', '频'), ('網', '网'), ('絡', '络'), ('質', '质'),
+    ('節', '节'), ('師', '师'), ('調', '调'), ('應', '应'), ('該', '该'), ('認', '认'),
+];
+
+#[derive(Debug, Default, PartialEq)]
+struct ScriptCounts {
+    han: usize,
+    kana: usize,
+    hangul: usize,
+    latin: usize,
+    traditional: usize,
+    simplified: usize,
+}
+
+impl ScriptCounts {
+    fn of(text: &str) -> Self {
+        let mut counts = ScriptCounts::default();
+        for c in text.chars() {
+            match c as u32 {
+                0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0xF900..=0xFAFF => {
+                    counts.han += 1;
+                    if HAN_VARIANTS.iter().any(|(t, _)| *t == c) {
+                        counts.traditional += 1;
+                    } else if HAN_VARIANTS.iter().any(|(_, s)| *s == c) {
+                        counts.simplified += 1;
+                    }
+                }
+                0x3040..=0x30FF | 0x31F0..=0x31FF => counts.kana += 1,
+                0xAC00..=0xD7AF | 0x1100..=0x11FF => counts.hangul += 1,
+                _ if c.is_ascii_alphabetic() => counts.latin += 1,
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+/// 文字是否像是指定語言（字元統計的簡單判斷；太短或只有數字、符號時視為符合）
+///
+/// 中文與日文允許夾雜英文專有名詞（例如「Python 程式設計」），英文允許少量漢字；
+/// 日文必須含假名，只有很短的純漢字標題例外。
+pub fn matches_script(text: &str, language: Language) -> bool {
+    let c = ScriptCounts::of(text);
+    let cjk = c.han + c.kana + c.hangul;
+    if cjk + c.latin < 2 {
+        return true;
+    }
+    match language {
+        Language::En => cjk * 5 <= c.latin,
+        Language::ZhTw | Language::ZhCn => {
+            let is_chinese = c.han > 0 && c.hangul == 0 && c.kana * 5 <= c.han && c.latin <= c.han * 4;
+            let right_variant = match language {
+                Language::ZhTw => c.simplified <= c.traditional,
+                _ => c.traditional <= c.simplified,
+            };
+            is_chinese && right_variant
+        }
+        Language::Ja => {
+            let japanese = c.han + c.kana;
+            japanese > 0 && c.hangul == 0 && c.latin <= japanese * 4 && (c.kana > 0 || c.han <= 8)
+        }
+    }
+}
+
+// 需要檢查語言的 JSON 文字欄位
+const CHECKED_FIELDS: [&str; 3] = ["title", "description", "name"];
+
+/// 第一個語言不符的文字欄位（含巢狀的子任務），回傳欄位名稱與內容；全部符合時為 None
+pub fn mismatched_field(value: &serde_json::Value, language: Language) -> Option<(String, String)> {
+    match value {
+        serde_json::Value::Object(map) => map.iter().find_map(|(key, value)| match value {
+            serde_json::Value::String(text) if CHECKED_FIELDS.contains(&key.as_str()) => {
+                (!matches_script(text, language)).then(|| (key.clone(), text.clone()))
+            }
+            other => mismatched_field(other, language),
+        }),
+        serde_json::Value::Array(items) => items.iter().find_map(|item| mismatched_field(item, language)),
+        _ => None,
+    }
+}
+
+// 常見回應訊息的翻譯（繁中、簡中、英文、日文），未收錄的訊息維持繁體中文
+const MESSAGES: [(&str, &str, &str, &str); 26] = [
+    ("缺少 Authorization header", "缺少 Authorization header", "Missing Authorization header", "Authorization ヘッダーがありません"),
+    ("請先登入", "请先登录", "Please sign in first", "ログインしてください"),
+    ("需要 JWT 認證", "需要 JWT 认证", "JWT authentication required", "JWT 認証が必要です"),
+    ("無法驗證登入狀態", "无法验证登录状态", "Unable to verify sign-in status", "ログイン状態を確認できません"),
+    ("API token 無效或已撤銷", "API token 无效或已撤销", "API token is invalid or revoked", "API トークンが無効または失効しています"),
+    ("此 API token 僅能讀取資料", "此 API token 仅能读取数据", "This API token is read-only", "この API トークンは読み取り専用です"),
+    ("代理檢視模式僅能讀取資料", "代理查看模式仅能读取数据", "Impersonation sessions are read-only", "代理閲覧モードでは読み取りのみ可能です"),
+    ("需要管理員權限", "需要管理员权限", "Administrator privileges required", "管理者権限が必要です"),
+    ("缺少user_id參數", "缺少user_id参数", "Missing user_id parameter", "user_id パラメータがありません"),
+    ("系統錯誤，請稍後再試", "系统错误，请稍后再试", "System error, please try again later", "システムエラーです。しばらくしてから再試行してください"),
+    ("用戶不存在", "用户不存在", "User not found", "ユーザーが見つかりません"),
+    ("任務不存在", "任务不存在", "Task not found", "タスクが見つかりません"),
+    ("技能不存在", "技能不存在", "Skill not found", "スキルが見つかりません"),
+    ("無權限存取此任務", "无权限访问此任务", "You do not have access to this task", "このタスクへのアクセス権がありません"),
+    ("無權限存取此技能", "无权限访问此技能", "You do not have access to this skill", "このスキルへのアクセス権がありません"),
+    ("成就不存在", "成就不存在", "Achievement not found", "実績が見つかりません"),
+    ("成就已經解鎖", "成就已经解锁", "Achievement already unlocked", "実績はすでに解除されています"),
+    ("日期格式錯誤，請使用 YYYY-MM-DD", "日期格式错误，请使用 YYYY-MM-DD", "Invalid date format, use YYYY-MM-DD", "日付の形式が正しくありません（YYYY-MM-DD）"),
+    ("任務已被其他裝置更新，請合併後重試", "任务已被其他设备更新，请合并后重试", "The task was updated on another device; merge and retry", "タスクは別の端末で更新されました。統合してから再試行してください"),
+    ("登入成功", "登录成功", "Signed in successfully", "ログインしました"),
+    ("AI 回應成功", "AI 回复成功", "AI replied successfully", "AI が応答しました"),
+    ("獲取使用者設定成功", "获取用户设置成功", "Settings loaded", "設定を取得しました"),
+    ("使用者設定已更新", "用户设置已更新", "Settings updated", "設定を更新しました"),
+    ("任務建立成功", "任务创建成功", "Task created", "タスクを作成しました"),
+    ("任務更新成功", "任务更新成功", "Task updated", "タスクを更新しました"),
+    ("API v1 已停用，請改用 /api/v2", "API v1 已停用，请改用 /api/v2", "API v1 has been disabled; use /api/v2", "API v1 は無効です。/api/v2 を使用してください"),
+];
+
+/// 回應訊息的在地化版本；未收錄的訊息回傳 None（維持原文）
+pub fn localize_message(message: &str, language: Language) -> Option<&'static str> {
+    let (_, zh_cn, en, ja) = MESSAGES.iter().find(|(zh_tw, ..)| *zh_tw == message)?;
+    match language {
+        Language::ZhTw => None,
+        Language::ZhCn => Some(zh_cn),
+        Language::En => Some(en),
+        Language::Ja => Some(ja),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_language_codes_and_accept_language() {
+        for language in [Language::ZhTw, Language::ZhCn, Language::En, Language::Ja] {
+            assert_eq!(Language::from_code(language.code()), Some(language));
+        }
+        // 使用者設定接受的語系都對應到同一個代碼
+        for locale in crate::user_settings::LOCALES {
+            assert_eq!(Language::from_code(locale).map(|l| l.code()), Some(locale));
+        }
+        assert_eq!(Language::from_code("zh-Hant-TW"), Some(Language::ZhTw));
+        assert_eq!(Language::from_code("en-US"), Some(Language::En));
+        assert_eq!(Language::from_code("ko"), None);
+        assert_eq!(Language::from_accept_language("ko-KR,ja;q=0.9,en;q=0.8"), Some(Language::Ja));
+        assert_eq!(Language::from_accept_language("zh-CN,zh;q=0.9"), Some(Language::ZhCn));
+        assert_eq!(Language::from_accept_language("fr"), None);
+    }
+
+    #[test]
+    fn test_script_heuristic() {
+        assert!(matches_script("每天閱讀 30 分鐘", Language::ZhTw));
+        assert!(matches_script("學習 Python 程式設計", Language::ZhTw));
+        assert!(!matches_script("Read for 30 minutes every day", Language::ZhTw));
+        assert!(!matches_script("每天学习英语", Language::ZhTw));
+        assert!(matches_script("每天学习英语", Language::ZhCn));
+        assert!(!matches_script("每天學習英語", Language::ZhCn));
+        assert!(matches_script("Read for 30 minutes every day", Language::En));
+        assert!(!matches_script("每天閱讀 30 分鐘", Language::En));
+        assert!(matches_script("毎日30分読書する", Language::Ja));
+        assert!(matches_script("英語学習", Language::Ja));
+        assert!(!matches_script("每天閱讀三十分鐘並寫下讀書心得", Language::Ja));
+        // 太短或只有數字、emoji 無法判斷
+        assert!(matches_script("🏃 5", Language::En));
+    }
+
+    #[test]
+    fn test_mismatched_field_checks_nested_text_fields() {
+        let plan = json!({
+            "main_task": {"title": "學習吉他", "description": "每天練習和弦", "task_type": "main"},
+            "subtasks": [{"title": "練習 C 和弦"}, {"title": "Practice strumming patterns"}],
+        });
+        assert_eq!(
+            mismatched_field(&plan, Language::ZhTw),
+            Some(("title".to_string(), "Practice strumming patterns".to_string()))
+        );
+        // 非文字欄位（task_type 等）不檢查
+        assert!(mismatched_field(&json!({"task_type": "main", "title": "Learn guitar"}), Language::En).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scope_sets_current_and_directive() {
+        assert_eq!(current(), Language::ZhTw);
+        assert_eq!(directive(), "一律使用繁體中文回答。");
+        scope(Language::En, async {
+            assert_eq!(current(), Language::En);
+            assert_eq!(directive(), "Always respond in English.");
+        })
+        .await;
+        scope_strict(Language::Ja, async {
+            assert!(directive().contains("日本語"));
+            assert!(directive().contains("上一次的輸出語言錯誤"));
+        })
+        .await;
+        assert_eq!(localize_message("任務不存在", Language::En), Some("Task not found"));
+        assert_eq!(localize_message("任務不存在", Language::ZhTw), None);
+        assert_eq!(localize_message("未收錄的訊息", Language::Ja), None);
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+
+// 排行榜快取有效時間
+const LEADERBOARD_CACHE_TTL: Duration = Duration::from_secs(180);
+// 預設回傳前幾名
+const DEFAULT_LEADERBOARD_LIMIT: usize = 10;
+const MAX_LEADERBOARD_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub period: Option<String>,  // week | month | all
+    pub metric: Option<String>,  // experience | tasks_completed
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardVisibilityRequest {
+    pub visible: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardRow {
+    pub rank: usize,
+    pub user_id: String,
+    pub display_name: String,
+    pub level: i32,
+    pub value: i64,
+    pub is_self: bool,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardResponse {
+    pub period: String,
+    pub metric: String,
+    pub rows: Vec<LeaderboardRow>,
+    // 呼叫者自己的排名（不在前 N 名或未公開時仍會回傳）
+    pub me: Option<LeaderboardRow>,
+}
+
+// 排名計算用的原始資料
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub user_id: String,
+    pub display_name: String,
+    pub level: i32,
+    pub value: i64,
+    pub visible: bool,
+    pub first_achievement_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProfileRow {
+    user_id: Option<String>,
+    name: Option<String>,
+    level: Option<i32>,
+    leaderboard_visible: Option<i32>,
+    first_achievement_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MetricRow {
+    user_id: Option<String>,
+    value: Option<i64>,
+}
+
+// (期間起始日, metric) -> (計算時間, 已排序資料)；起始日依呼叫者時區計算，全部期間為 None
+type LeaderboardCache = HashMap<(Option<NaiveDate>, String), (Instant, Vec<LeaderboardEntry>)>;
+
+static LEADERBOARD_CACHE: OnceLock<Mutex<LeaderboardCache>> = OnceLock::new();
+
+fn leaderboard_cache() -> &'static Mutex<LeaderboardCache> {
+    LEADERBOARD_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 依指標排序：數值高者在前，同分時較早取得第一個成就者在前
+pub fn sort_entries(entries: &mut [LeaderboardEntry]) {
+    entries.sort_by(|a, b| {
+        b.value
+            .cmp(&a.value)
+            .then_with(|| match (&a.first_achievement_at, &b.first_achievement_at) {
+                (Some(x), Some(y)) => x.cmp(y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            })
+            .then_with(|| a.user_id.cmp(&b.user_id))
+    });
+}
+
+/// 由已排序的資料產生公開排行榜與呼叫者自己的排名
+///
+/// 只有公開的使用者會出現在榜上；呼叫者未公開時，以其在公開名單中應有的位置計算真實排名
+pub fn build_board(
+    sorted: &[LeaderboardEntry],
+    caller_id: Option<&str>,
+    limit: usize,
+) -> (Vec<LeaderboardRow>, Option<LeaderboardRow>) {
+    let mut rows = Vec::new();
+    let mut me = None;
+    let mut visible_rank = 0;
+
+    for entry in sorted {
+        let is_self = caller_id == Some(entry.user_id.as_str());
+        if !entry.visible && !is_self {
+            continue;
+        }
+        let rank = visible_rank + 1;
+        if entry.visible {
+            visible_rank += 1;
+        }
+
+        let row = LeaderboardRow {
+            rank,
+            user_id: entry.user_id.clone(),
+            display_name: entry.display_name.clone(),
+            level: entry.level,
+            value: entry.value,
+            is_self,
+        };
+        if entry.visible && rows.len() < limit {
+            rows.push(row.clone());
+        }
+        if is_self {
+            me = Some(row);
+        }
+    }
+
+    (rows, me)
+}
+
+fn period_start(period: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match period {
+        "week" => Some(today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)),
+        "month" => today.with_day(1),
+        _ => None,
+    }
+}
+
+async fn compute_entries(rb: &RBatis, start: Option<NaiveDate>, metric: &str) -> Result<Vec<LeaderboardEntry>, rbatis::Error> {
+    let profiles: Vec<ProfileRow> = rb
+        .query_decode(
+            "SELECT p.user_id, u.name, p.level, p.leaderboard_visible,
+                    (SELECT MIN(ua.achieved_at) FROM user_achievement ua WHERE ua.user_id = p.user_id) AS first_achievement_at
+             FROM user_profile p JOIN user u ON u.id = p.user_id",
+            vec![],
+        )
+        .await?;
+
+    let start = start.map(|d| d.format("%Y-%m-%d").to_string());
+    let (sql, args) = match (metric, start) {
+        ("tasks_completed", Some(start)) => (
+            "SELECT user_id, COUNT(*) AS value FROM task WHERE status IN (2, 6) AND updated_at >= ? GROUP BY user_id",
+            vec![rbs::Value::String(start)],
+        ),
+        ("tasks_completed", None) => (
+            "SELECT user_id, COUNT(*) AS value FROM task WHERE status IN (2, 6) GROUP BY user_id",
+            vec![],
+        ),
+        (_, Some(start)) => (
+            "SELECT user_id, SUM(experience_gained) AS value FROM daily_progress WHERE date >= ? GROUP BY user_id",
+            vec![rbs::Value::String(start)],
+        ),
+        (_, None) => (
+            "SELECT user_id, SUM(experience_gained) AS value FROM daily_progress GROUP BY user_id",
+            vec![],
+        ),
+    };
+    let metrics: Vec<MetricRow> = rb.query_decode(sql, args).await?;
+    let values: HashMap<String, i64> = metrics
+        .into_iter()
+        .filter_map(|m| Some((m.user_id?, m.value.unwrap_or(0))))
+        .collect();
+
+    let mut entries: Vec<LeaderboardEntry> = profiles
+        .into_iter()
+        .filter_map(|p| {
+            let user_id = p.user_id?;
+            Some(LeaderboardEntry {
+                value: values.get(&user_id).copied().unwrap_or(0),
+                display_name: p.name.unwrap_or_else(|| "冒險者".to_string()),
+                level: p.level.unwrap_or(1),
+                visible: p.leaderboard_visible.unwrap_or(0) == 1,
+                first_achievement_at: p.first_achievement_at,
+                user_id,
+            })
+        })
+        .collect();
+    sort_entries(&mut entries);
+    Ok(entries)
+}
+
+/// 排行榜（僅包含選擇公開的使用者）
+pub async fn get_leaderboard(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<LeaderboardQuery>,
+) -> Result<HttpResponse> {
+    let period = query.period.clone().unwrap_or_else(|| "week".to_string());
+    let metric = query.metric.clone().unwrap_or_else(|| "experience".to_string());
+    if !["week", "month", "all"].contains(&period.as_str()) || !["experience", "tasks_completed"].contains(&metric.as_str()) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "period 需為 week|month|all，metric 需為 experience|tasks_completed".to_string(),
+        }));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT).clamp(1, MAX_LEADERBOARD_LIMIT);
+    let caller_id = crate::auth::current_user_id(&http_req);
+
+    let tz = match caller_id.as_deref() {
+        Some(caller_id) => crate::user_settings::user_timezone(rb.get_ref(), caller_id).await,
+        None => crate::local_date::default_timezone(),
+    };
+    let start = period_start(&period, crate::local_date::local_today_in(tz));
+    let key = (start, metric.clone());
+    let cached = leaderboard_cache()
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(&key).filter(|(at, _)| at.elapsed() < LEADERBOARD_CACHE_TTL).map(|(_, e)| e.clone()));
+
+    let entries = match cached {
+        Some(entries) => entries,
+        None => match compute_entries(rb.get_ref(), start, &metric).await {
+            Ok(entries) => {
+                if let Ok(mut cache) = leaderboard_cache().lock() {
+                    cache.insert(key, (Instant::now(), entries.clone()));
+                }
+                entries
+            }
+            Err(e) => {
+                log::error!("計算排行榜失敗: {}", e);
+                return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("計算排行榜失敗: {}", e),
+                }));
+            }
+        },
+    };
+
+    let (rows, me) = build_board(&entries, caller_id.as_deref(), limit);
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(LeaderboardResponse { period, metric, rows, me }),
+        message: "獲取排行榜成功".to_string(),
+    }))
+}
+
+/// 設定是否公開於排行榜
+pub async fn update_leaderboard_visibility(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<LeaderboardVisibilityRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match rb
+        .exec(
+            "UPDATE user_profile SET leaderboard_visible = ?, updated_at = ? WHERE user_id = ?",
+            vec![
+                rbs::Value::I32(if req.visible { 1 } else { 0 }),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(user_id.clone()),
+            ],
+        )
+        .await
+    {
+        Ok(result) if result.rows_affected > 0 => {
+            // 讓下一次查詢立即反映變更
+            if let Ok(mut cache) = leaderboard_cache().lock() {
+                cache.clear();
+            }
+            crate::gamified_cache::invalidate(&user_id);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({ "user_id": user_id, "leaderboard_visible": req.visible })),
+                message: "排行榜公開設定已更新".to_string(),
+            }))
+        }
+        Ok(_) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "找不到該使用者資料".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("更新排行榜公開設定失敗: {}", e),
+        })),
+    }
+}
+
+/// 記錄今日（使用者時區）獲得的經驗值，作為排行榜的經驗值流水
+pub async fn record_experience_gain(rb: &RBatis, user_id: &str, experience_gain: i32) -> Result<(), rbatis::Error> {
+    let today = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb, user_id).await);
+    let now = Utc::now().to_rfc3339();
+
+    rb.exec(
+        "INSERT INTO daily_progress (id, user_id, date, completed_tasks, total_tasks, experience_gained, created_at, updated_at)
+         VALUES (?, ?, ?, 0, 0, ?, ?, ?)
+         ON CONFLICT(user_id, date) DO UPDATE SET
+             experience_gained = COALESCE(experience_gained, 0) + excluded.experience_gained,
+             updated_at = excluded.updated_at",
+        vec![
+            rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::String(today),
+            rbs::Value::I32(experience_gain),
+            rbs::Value::String(now.clone()),
+            rbs::Value::String(now),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, value: i64, visible: bool, first: Option<&str>) -> LeaderboardEntry {
+        LeaderboardEntry {
+            user_id: id.to_string(),
+            display_name: id.to_string(),
+            level: 1,
+            value,
+            visible,
+            first_achievement_at: first.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_ties_broken_by_earliest_achievement() {
+        let mut entries = vec![
+            entry("b", 100, true, Some("2025-02-01")),
+            entry("c", 100, true, None),
+            entry("a", 100, true, Some("2025-01-01")),
+            entry("d", 200, true, None),
+        ];
+        sort_entries(&mut entries);
+        let order: Vec<&str> = entries.iter().map(|e| e.user_id.as_str()).collect();
+        assert_eq!(order, vec!["d", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_hidden_users_excluded_and_caller_appended() {
+        let mut entries = vec![
+            entry("top", 500, true, None),
+            entry("hidden", 400, false, None),
+            entry("second", 300, true, None),
+            entry("me", 250, false, None),
+            entry("third", 200, true, None),
+        ];
+        sort_entries(&mut entries);
+
+        let (rows, me) = build_board(&entries, Some("me"), 2);
+        let ids: Vec<&str> = rows.iter().map(|r| r.user_id.as_str()).collect();
+        assert_eq!(ids, vec!["top", "second"]);
+        assert_eq!(rows[1].rank, 2);
+
+        let me = me.unwrap();
+        assert_eq!(me.rank, 3);
+        assert!(me.is_self);
+
+        // 其他人的名次不受未公開的呼叫者影響
+        let (rows, _) = build_board(&entries, Some("me"), 10);
+        assert_eq!(rows.last().unwrap().user_id, "third");
+        assert_eq!(rows.last().unwrap().rank, 3);
+    }
+    #[actix_web::test]
+    async fn test_visibility_only_changed_by_owner_and_caller_from_jwt() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let owner = crate::test_utils::create_user(&app, "board_owner").await;
+        let intruder = crate::test_utils::create_user(&app, "board_intruder").await;
+
+        let uri = format!("/api/users/{}/leaderboard-visibility", owner.id);
+        let req = actix_web::test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(intruder.auth())
+            .set_json(serde_json::json!({ "visible": true }))
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 403, "{}", body);
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT leaderboard_visible FROM user_profile WHERE user_id = ?", vec![rbs::Value::String(owner.id.clone())])
+            .await
+            .unwrap();
+        assert_ne!(rows[0]["leaderboard_visible"], 1);
+
+        let req = actix_web::test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(owner.auth())
+            .set_json(serde_json::json!({ "visible": true }))
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+
+        // 查詢參數中的 user_id 不會改變呼叫者身分
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/leaderboard?period=all&user_id={}", owner.id))
+            .insert_header(intruder.auth())
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+        assert_eq!(body["data"]["me"]["user_id"], intruder.id);
+    }
+}
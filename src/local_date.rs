@@ -0,0 +1,119 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+
+// task_date 與每日統計以使用者時區的日期為準；未設定時區的使用者使用 DEFAULT_USER_TIMEZONE（預設台灣 UTC+8）
+const FALLBACK_UTC_OFFSET_SECS: i32 = 8 * 3600;
+
+static DEFAULT_TIMEZONE: OnceLock<FixedOffset> = OnceLock::new();
+
+/// 啟動時套用預設時區設定（±HH:MM）；格式錯誤時維持 UTC+8
+pub fn init(timezone: &str) {
+    let offset = parse_utc_offset(timezone).unwrap_or_else(|| {
+        log::warn!("DEFAULT_USER_TIMEZONE 格式錯誤: {}，使用 +08:00", timezone);
+        fallback_timezone()
+    });
+    log::info!("使用者預設時區: {}", offset);
+    if DEFAULT_TIMEZONE.set(offset).is_err() {
+        log::warn!("使用者預設時區已初始化，忽略重複設定");
+    }
+}
+
+fn fallback_timezone() -> FixedOffset {
+    FixedOffset::east_opt(FALLBACK_UTC_OFFSET_SECS).unwrap()
+}
+
+/// 未個別設定時區的使用者所用的時區
+pub fn default_timezone() -> FixedOffset {
+    *DEFAULT_TIMEZONE.get_or_init(fallback_timezone)
+}
+
+/// UTC 時間在指定時區的日期
+pub fn local_date_in(dt: DateTime<Utc>, tz: FixedOffset) -> NaiveDate {
+    dt.with_timezone(&tz).date_naive()
+}
+
+/// 指定時區的今天
+pub fn local_today_in(tz: FixedOffset) -> NaiveDate {
+    local_date_in(Utc::now(), tz)
+}
+
+/// 指定時區的今天（task_date 格式 YYYY-MM-DD）
+pub fn local_today_string_in(tz: FixedOffset) -> String {
+    local_today_in(tz).format("%Y-%m-%d").to_string()
+}
+
+/// UTC 時間在預設時區的日期
+pub fn local_date(dt: DateTime<Utc>) -> NaiveDate {
+    local_date_in(dt, default_timezone())
+}
+
+/// 預設時區的今天
+pub fn local_today() -> NaiveDate {
+    local_today_in(default_timezone())
+}
+
+/// 預設時區的今天（task_date 格式 YYYY-MM-DD）；正式路徑應依使用者時區改用 local_today_string_in
+#[cfg(test)]
+pub fn local_today_string() -> String {
+    local_today_string_in(default_timezone())
+}
+
+/// 解析使用者設定的 UTC 偏移（±HH:MM）；格式錯誤時回傳 None
+pub fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match value.trim().split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+    FixedOffset::east_opt(sign * seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_before_local_midnight_keeps_same_day() {
+        // 台灣 2026-03-01 23:59:59
+        let dt = Utc.with_ymd_and_hms(2026, 3, 1, 15, 59, 59).unwrap();
+        assert_eq!(local_date(dt), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+    }
+
+    #[test]
+    fn test_after_local_midnight_moves_to_next_day() {
+        // 台灣 2026-03-02 00:00:00，UTC 仍是 3/1
+        let dt = Utc.with_ymd_and_hms(2026, 3, 1, 16, 0, 0).unwrap();
+        assert_eq!(local_date(dt), NaiveDate::from_ymd_opt(2026, 3, 2).unwrap());
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("+08:00"), Some(default_timezone()));
+        assert_eq!(parse_utc_offset("-05:30"), FixedOffset::west_opt(5 * 3600 + 30 * 60));
+        assert_eq!(parse_utc_offset("08:00"), None);
+        assert_eq!(parse_utc_offset("+8"), None);
+    }
+
+    #[test]
+    fn test_utc_midnight_is_local_morning_same_day() {
+        // UTC 跨日前後（台灣 07:59 / 08:00）都屬於台灣的同一天
+        let before = Utc.with_ymd_and_hms(2026, 3, 1, 23, 59, 59).unwrap();
+        let after = Utc.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap();
+        assert_eq!(local_date(before), NaiveDate::from_ymd_opt(2026, 3, 2).unwrap());
+        assert_eq!(local_date(after), NaiveDate::from_ymd_opt(2026, 3, 2).unwrap());
+    }
+
+    #[test]
+    fn test_explicit_offset_crosses_midnight_independently() {
+        // UTC 2026-03-01 04:30 是紐約（-05:00）的 2/28 深夜、台灣的 3/1 中午
+        let dt = Utc.with_ymd_and_hms(2026, 3, 1, 4, 30, 0).unwrap();
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(local_date_in(dt, new_york), NaiveDate::from_ymd_opt(2026, 2, 28).unwrap());
+        assert_eq!(local_date_in(dt + chrono::Duration::hours(1), new_york), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        assert_eq!(local_date(dt), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+    }
+}
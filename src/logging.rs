@@ -0,0 +1,281 @@
+// 日誌初始化：主控台 + 輪替日誌檔
+//
+// 日誌檔超過大小上限或到達時間間隔即輪替為封存檔（可 gzip），只保留固定數量；
+// 目錄無法寫入時只輸出到主控台並發出警告，不讓服務因日誌而無法啟動。
+// LOG_FORMAT=json 時每行輸出一個 JSON 物件，請求範圍內的日誌帶上 request_id / user_id。
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use log4rs::append::console::ConsoleAppender;
+use log4rs::append::rolling_file::policy::compound::roll::delete::DeleteRoller;
+use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
+use log4rs::append::rolling_file::policy::compound::roll::Roll;
+use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::time::{TimeTrigger, TimeTriggerConfig, TimeTriggerInterval};
+use log4rs::append::rolling_file::policy::compound::trigger::Trigger;
+use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
+use log4rs::append::rolling_file::{LogFile, RollingFileAppender};
+use log4rs::config::{Appender, Root};
+use log4rs::encode::pattern::PatternEncoder;
+use log4rs::encode::Encode;
+use log4rs::Config as Log4rsConfig;
+
+use crate::config::LogFileConfig;
+use crate::request_context;
+
+const LOG_PATTERN: &str = "{d(%Y-%m-%d %H:%M:%S)} [{l}] {t} - {m}{n}";
+const LOG_FILE_NAME: &str = "lifeup.log";
+
+// 目前寫入的日誌檔（console-only 時為 None）
+static ACTIVE_LOG_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// 目前寫入的日誌檔路徑（深度健康檢查使用）
+pub fn active_log_file() -> Option<String> {
+    ACTIVE_LOG_FILE
+        .get()
+        .and_then(|path| path.as_ref())
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+// JSON 格式：level、target、message 與請求上下文欄位都是最上層的 key
+#[derive(Debug)]
+struct JsonLineEncoder;
+
+impl Encode for JsonLineEncoder {
+    fn encode(&self, w: &mut dyn log4rs::encode::Write, record: &log::Record) -> anyhow::Result<()> {
+        let mut line = serde_json::json!({
+            "time": chrono::Utc::now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+        if let Some(fields) = request_context::current_fields() {
+            line["request_id"] = fields.request_id.into();
+            if let Some(user_id) = fields.user_id {
+                line["user_id"] = user_id.into();
+            }
+            if let Some(latency_ms) = fields.latency_ms {
+                line["latency_ms"] = latency_ms.into();
+            }
+        }
+        serde_json::to_writer(&mut *w, &line)?;
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+fn encoder(json: bool) -> Box<dyn Encode> {
+    if json {
+        Box::new(JsonLineEncoder)
+    } else {
+        Box::new(PatternEncoder::new(LOG_PATTERN))
+    }
+}
+
+/// 是否使用 JSON 日誌格式
+pub fn is_json_format(format: &str) -> bool {
+    format.eq_ignore_ascii_case("json")
+}
+
+// JSON 格式時 panic 也記錄成結構化的 error 事件（保留原本輸出到 stderr 的行為）
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        log::error!(target: "panic", "panic at {}: {}", location, payload);
+        default_hook(info);
+    }));
+}
+
+// 大小或時間任一條件成立即輪替
+#[derive(Debug)]
+struct SizeOrTimeTrigger {
+    size: SizeTrigger,
+    time: Option<TimeTrigger>,
+}
+
+impl Trigger for SizeOrTimeTrigger {
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        if self.size.trigger(file)? {
+            return Ok(true);
+        }
+        match &self.time {
+            Some(time) => time.trigger(file),
+            None => Ok(false),
+        }
+    }
+
+    // 時間輪替需在寫入前檢查，確保新的一天從新檔案開始
+    fn is_pre_process(&self) -> bool {
+        self.time.is_some()
+    }
+}
+
+fn time_interval(hours: i64) -> Option<TimeTriggerInterval> {
+    match hours {
+        h if h <= 0 => None,
+        h if h % 24 == 0 => Some(TimeTriggerInterval::Day(h / 24)),
+        h => Some(TimeTriggerInterval::Hour(h)),
+    }
+}
+
+/// 封存檔名稱樣式（{} 為序號，啟用 gzip 時以 .gz 結尾由 log4rs 壓縮）
+pub fn archive_pattern(dir: &Path, gzip: bool) -> String {
+    let suffix = if gzip { ".gz" } else { "" };
+    dir.join(format!("lifeup.{{}}.log{}", suffix)).to_string_lossy().to_string()
+}
+
+fn rolling_appender(config: &LogFileConfig, json: bool) -> anyhow::Result<(RollingFileAppender, PathBuf)> {
+    let dir = Path::new(&config.dir);
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(LOG_FILE_NAME);
+    // 先確認可寫入，避免啟動後才在每次寫入時失敗
+    std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    let path = std::fs::canonicalize(&path).unwrap_or(path);
+
+    let trigger = SizeOrTimeTrigger {
+        size: SizeTrigger::new(config.max_size_mb.max(1) * 1024 * 1024),
+        time: time_interval(config.rotate_interval_hours).map(|interval| {
+            TimeTrigger::new(TimeTriggerConfig {
+                interval,
+                modulate: true,
+                max_random_delay: 0,
+            })
+        }),
+    };
+    let roller: Box<dyn Roll> = if config.keep_archives == 0 {
+        Box::new(DeleteRoller::new())
+    } else {
+        Box::new(FixedWindowRoller::builder().build(&archive_pattern(dir, config.gzip), config.keep_archives)?)
+    };
+    let appender = RollingFileAppender::builder()
+        .encoder(encoder(json))
+        .build(&path, Box::new(CompoundPolicy::new(Box::new(trigger), roller)))?;
+    Ok((appender, path))
+}
+
+/// 初始化日誌系統；日誌目錄無法寫入時退回只輸出到主控台
+pub fn init(level: log::LevelFilter, format: &str, config: &LogFileConfig) {
+    let json = is_json_format(format);
+    let stdout = ConsoleAppender::builder().encoder(encoder(json)).build();
+    let mut builder = Log4rsConfig::builder().appender(Appender::builder().build("stdout", Box::new(stdout)));
+    let mut root = Root::builder().appender("stdout");
+
+    let file_error = match rolling_appender(config, json) {
+        Ok((appender, path)) => {
+            builder = builder.appender(Appender::builder().build("logfile", Box::new(appender)));
+            root = root.appender("logfile");
+            let _ = ACTIVE_LOG_FILE.set(Some(path));
+            None
+        }
+        Err(e) => Some(e),
+    };
+
+    match builder.build(root.build(level)) {
+        Ok(log_config) => {
+            if let Err(e) = log4rs::init_config(log_config) {
+                eprintln!("日誌初始化失敗: {}", e);
+            }
+        }
+        Err(e) => eprintln!("日誌配置失敗: {}", e),
+    }
+    if json {
+        install_panic_hook();
+    }
+
+    match file_error {
+        None => log::info!(
+            "日誌檔: {}（{}MB 或每 {} 小時輪替，保留 {} 個封存檔{}）",
+            active_log_file().unwrap_or_default(),
+            config.max_size_mb,
+            config.rotate_interval_hours,
+            config.keep_archives,
+            if config.gzip { "，gzip 壓縮" } else { "" }
+        ),
+        Some(e) => log::warn!("⚠️⚠️⚠️ 無法寫入日誌目錄 {}: {}，日誌只會輸出到主控台！", config.dir, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_interval_and_archive_pattern() {
+        assert!(time_interval(0).is_none());
+        assert!(matches!(time_interval(24), Some(TimeTriggerInterval::Day(1))));
+        assert!(matches!(time_interval(6), Some(TimeTriggerInterval::Hour(6))));
+        assert_eq!(archive_pattern(Path::new("/var/log/lifeup"), true), "/var/log/lifeup/lifeup.{}.log.gz");
+        assert_eq!(archive_pattern(Path::new("logs"), false), "logs/lifeup.{}.log");
+    }
+
+    #[test]
+    fn test_unwritable_dir_is_reported() {
+        // 以檔案佔住目錄路徑，create_dir_all 會失敗
+        let blocker = std::env::temp_dir().join(format!("lifeup_log_blocker_{}", std::process::id()));
+        std::fs::write(&blocker, b"").unwrap();
+        let config = LogFileConfig {
+            dir: blocker.join("logs").to_string_lossy().to_string(),
+            ..LogFileConfig::default()
+        };
+        assert!(rolling_appender(&config, false).is_err());
+        std::fs::remove_file(&blocker).unwrap();
+    }
+
+    fn encode_json(record: &log::Record) -> serde_json::Value {
+        let mut writer = log4rs::encode::writer::simple::SimpleWriter(Vec::new());
+        JsonLineEncoder.encode(&mut writer, record).unwrap();
+        let line = String::from_utf8(writer.0).unwrap();
+        assert!(line.ends_with('\n') && line.matches('\n').count() == 1);
+        serde_json::from_str(line.trim_end()).unwrap()
+    }
+
+    #[test]
+    fn test_json_line_is_parseable() {
+        let args = format_args!("任務 {} 完成", 42);
+        let record = log::Record::builder()
+            .level(log::Level::Warn)
+            .target("lifeup::tasks")
+            .args(args)
+            .build();
+        let value = encode_json(&record);
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "lifeup::tasks");
+        assert_eq!(value["message"], "任務 42 完成");
+        assert!(value["time"].is_string());
+        // 不在請求範圍內時不帶 request_id
+        assert!(value.get("request_id").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_json_line_includes_request_context() {
+        use crate::request_context::{RequestContextMiddleware, REQUEST_ID_HEADER};
+        use actix_web::{web, App, HttpResponse};
+
+        let app = actix_web::test::init_service(App::new().wrap(RequestContextMiddleware { access_log: false }).route(
+            "/log",
+            web::get().to(|| async {
+                request_context::set_user_id("user-1");
+                let args = format_args!("handled");
+                let record = log::Record::builder().level(log::Level::Info).target("lifeup").args(args).build();
+                HttpResponse::Ok().json(encode_json(&record))
+            }),
+        ))
+        .await;
+        let req = actix_web::test::TestRequest::get().uri("/log").insert_header((REQUEST_ID_HEADER, "req-7")).to_request();
+        let value: serde_json::Value = actix_web::test::call_and_read_body_json(&app, req).await;
+        assert_eq!(value["request_id"], "req-7");
+        assert_eq!(value["user_id"], "user-1");
+        assert_eq!(value["level"], "INFO");
+    }
+}
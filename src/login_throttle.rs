@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use actix_web::HttpResponse;
+use chrono::{DateTime, Duration, Utc};
+use rbatis::RBatis;
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::LoginThrottleConfig;
+
+// 與密碼錯誤相同的訊息，鎖定中的帳號不可被辨識
+pub const INVALID_CREDENTIALS_MESSAGE: &str = "Email 或密碼錯誤";
+// 遞增鎖定的上限
+const MAX_LOCKOUT_SECS: i64 = 24 * 3600;
+// 每張表（email / IP）的紀錄上限，避免大量不同 email 或 IP 撐大記憶體
+const MAX_ENTRIES: usize = 10_000;
+// 達到上限時一次清到此數量，不必每次失敗都掃描整張表
+const EVICT_TO: usize = 9_000;
+
+/// 登入被阻擋的原因與剩餘秒數
+#[derive(Debug, PartialEq)]
+pub enum LoginBlock {
+    // 帳號鎖定：回應與密碼錯誤相同，只多 Retry-After
+    AccountLocked(i64),
+    // 同一 IP 失敗過多：回傳 429
+    IpThrottled(i64),
+}
+
+/// 本次失敗新觸發的限制（用於寫入稽核日誌）
+#[derive(Debug, Default, PartialEq)]
+pub struct FailureOutcome {
+    pub locked_secs: Option<i64>,
+    pub ip_throttled_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct FailureWindow {
+    count: u32,
+    window_start: DateTime<Utc>,
+}
+
+impl FailureWindow {
+    /// 累計一次失敗；窗口過期時重新計算
+    fn record(window: &mut Option<FailureWindow>, now: DateTime<Utc>, window_secs: i64) -> u32 {
+        match window {
+            Some(w) if now - w.window_start < Duration::seconds(window_secs) => {
+                w.count += 1;
+                w.count
+            }
+            _ => {
+                *window = Some(FailureWindow { count: 1, window_start: now });
+                1
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EmailState {
+    failures: Option<FailureWindow>,
+    locked_until: Option<DateTime<Utc>>,
+    // 已鎖定次數，每次鎖定時間加倍；登入成功後歸零
+    lockout_level: u32,
+    last_failure_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct IpState {
+    failures: Option<FailureWindow>,
+    blocked_until: Option<DateTime<Utc>>,
+    last_failure_at: Option<DateTime<Utc>>,
+}
+
+fn remaining_secs(until: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<i64> {
+    let until = until?;
+    if until <= now {
+        return None;
+    }
+    // 無條件進位，避免 Retry-After 為 0
+    Some(((until - now).num_milliseconds() + 999) / 1000)
+}
+
+/// 記憶體中的登入失敗紀錄（依 email 與 IP 分別計算）
+pub struct LoginThrottle {
+    config: LoginThrottleConfig,
+    emails: HashMap<String, EmailState>,
+    ips: HashMap<String, IpState>,
+}
+
+impl LoginThrottle {
+    pub fn new(config: LoginThrottleConfig) -> Self {
+        LoginThrottle { config, emails: HashMap::new(), ips: HashMap::new() }
+    }
+
+    /// 檢查是否允許這次登入嘗試（先檢查 IP，再檢查帳號）
+    pub fn check(&self, email: &str, ip: Option<&str>, now: DateTime<Utc>) -> Option<LoginBlock> {
+        if let Some(secs) = ip
+            .and_then(|ip| self.ips.get(ip))
+            .and_then(|state| remaining_secs(state.blocked_until, now))
+        {
+            return Some(LoginBlock::IpThrottled(secs));
+        }
+        self.emails
+            .get(email)
+            .and_then(|state| remaining_secs(state.locked_until, now))
+            .map(LoginBlock::AccountLocked)
+    }
+
+    /// 記錄一次失敗，回傳新觸發的鎖定
+    pub fn record_failure(&mut self, email: &str, ip: Option<&str>, now: DateTime<Utc>) -> FailureOutcome {
+        if self.emails.len() >= MAX_ENTRIES {
+            evict(&mut self.emails, |state| state.last_failure_at, now);
+        }
+        if self.ips.len() >= MAX_ENTRIES {
+            evict(&mut self.ips, |state| state.last_failure_at, now);
+        }
+        let window_secs = self.config.window_secs;
+        let mut outcome = FailureOutcome::default();
+
+        let state = self.emails.entry(email.to_string()).or_default();
+        state.last_failure_at = Some(now);
+        if FailureWindow::record(&mut state.failures, now, window_secs) >= self.config.max_failures_per_email {
+            let secs = self
+                .config
+                .lockout_secs
+                .saturating_mul(1i64 << state.lockout_level.min(16))
+                .min(MAX_LOCKOUT_SECS);
+            state.locked_until = Some(now + Duration::seconds(secs));
+            state.lockout_level += 1;
+            state.failures = None;
+            outcome.locked_secs = Some(secs);
+        }
+
+        if let Some(ip) = ip {
+            let state = self.ips.entry(ip.to_string()).or_default();
+            state.last_failure_at = Some(now);
+            if FailureWindow::record(&mut state.failures, now, window_secs) >= self.config.max_failures_per_ip {
+                state.blocked_until = Some(now + Duration::seconds(window_secs));
+                state.failures = None;
+                outcome.ip_throttled_secs = Some(window_secs);
+            }
+        }
+        outcome
+    }
+
+    /// 登入成功：清除該 email 的失敗與鎖定紀錄
+    pub fn record_success(&mut self, email: &str) {
+        self.emails.remove(email);
+    }
+
+}
+
+/// 先清除超過最長鎖定時間都沒有失敗的項目，仍超過 EVICT_TO 時移除最久沒有失敗的項目
+fn evict<S>(map: &mut HashMap<String, S>, last_failure_at: impl Fn(&S) -> Option<DateTime<Utc>>, now: DateTime<Utc>) {
+    map.retain(|_, state| {
+        last_failure_at(state).is_some_and(|t| now - t <= Duration::seconds(MAX_LOCKOUT_SECS))
+    });
+    if map.len() <= EVICT_TO {
+        return;
+    }
+    let mut by_age: Vec<(Option<DateTime<Utc>>, String)> =
+        map.iter().map(|(key, state)| (last_failure_at(state), key.clone())).collect();
+    by_age.sort_unstable();
+    let excess = map.len() - EVICT_TO;
+    for (_, key) in by_age.into_iter().take(excess) {
+        map.remove(&key);
+    }
+}
+
+static LOGIN_THROTTLE: OnceLock<Mutex<LoginThrottle>> = OnceLock::new();
+
+fn throttle() -> &'static Mutex<LoginThrottle> {
+    LOGIN_THROTTLE.get_or_init(|| Mutex::new(LoginThrottle::new(LoginThrottleConfig::default())))
+}
+
+/// 啟動時套用設定
+pub fn init(config: LoginThrottleConfig) {
+    log::info!(
+        "登入節流: 每個 email {} 次 / 每個 IP {} 次失敗（{} 秒內）後限制，首次鎖定 {} 秒",
+        config.max_failures_per_email,
+        config.max_failures_per_ip,
+        config.window_secs,
+        config.lockout_secs
+    );
+    if let Ok(mut guard) = throttle().lock() {
+        *guard = LoginThrottle::new(config);
+    }
+}
+
+/// 節流用的用戶端 IP：取連線的對端位址
+///
+/// X-Forwarded-For / Forwarded 由用戶端送出，可任意偽造，不可作為節流的 key。
+pub fn client_ip(req: &actix_web::HttpRequest) -> Option<String> {
+    req.peer_addr().map(|addr| addr.ip().to_string())
+}
+
+/// 與密碼錯誤相同的回應；鎖定時附上 Retry-After
+pub fn invalid_credentials_response(retry_after: Option<i64>) -> HttpResponse {
+    let mut builder = HttpResponse::Unauthorized();
+    if let Some(secs) = retry_after {
+        builder.insert_header((actix_web::http::header::RETRY_AFTER, secs.to_string()));
+    }
+    builder.json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: INVALID_CREDENTIALS_MESSAGE.to_string(),
+    })
+}
+
+/// 登入前檢查；被阻擋時回傳應直接送出的回應
+pub fn blocked_response(email: &str, ip: Option<&str>) -> Option<HttpResponse> {
+    let block = throttle().lock().ok()?.check(email, ip, Utc::now())?;
+    match block {
+        LoginBlock::AccountLocked(secs) => {
+            log::warn!("帳號鎖定中，拒絕登入: email={} ip={:?}", email, ip);
+            Some(invalid_credentials_response(Some(secs)))
+        }
+        LoginBlock::IpThrottled(secs) => {
+            log::warn!("IP 登入失敗次數過多: ip={:?}", ip);
+            Some(
+                HttpResponse::TooManyRequests()
+                    .insert_header((actix_web::http::header::RETRY_AFTER, secs.to_string()))
+                    .json(ApiResponse::<()> {
+                        success: false,
+                        data: None,
+                        message: "登入嘗試次數過多，請稍後再試".to_string(),
+                    }),
+            )
+        }
+    }
+}
+
+/// 記錄登入失敗並回傳密碼錯誤的回應；觸發鎖定時寫入稽核日誌
+pub async fn failed_login_response(rb: &RBatis, email: &str, ip: Option<&str>) -> HttpResponse {
+    let outcome = match throttle().lock() {
+        Ok(mut guard) => guard.record_failure(email, ip, Utc::now()),
+        Err(_) => FailureOutcome::default(),
+    };
+
+    if let Some(secs) = outcome.locked_secs {
+        log::warn!("帳號因登入失敗過多被鎖定 {} 秒: email={}", secs, email);
+        crate::audit_log::record(
+            rb,
+            crate::audit_log::ACTION_LOGIN_LOCKOUT,
+            None,
+            ip,
+            serde_json::json!({ "email": email, "lockout_secs": secs }),
+        )
+        .await;
+    }
+    if let Some(secs) = outcome.ip_throttled_secs {
+        log::warn!("IP 因登入失敗過多被限制 {} 秒: ip={:?}", secs, ip);
+        crate::audit_log::record(
+            rb,
+            crate::audit_log::ACTION_LOGIN_IP_THROTTLED,
+            None,
+            ip,
+            serde_json::json!({ "email": email, "throttle_secs": secs }),
+        )
+        .await;
+    }
+    invalid_credentials_response(outcome.locked_secs)
+}
+
+/// 登入成功時重設該帳號的失敗計數與鎖定等級
+pub fn record_success(email: &str) {
+    if let Ok(mut guard) = throttle().lock() {
+        guard.record_success(email);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> LoginThrottleConfig {
+        LoginThrottleConfig {
+            max_failures_per_email: 5,
+            max_failures_per_ip: 8,
+            window_secs: 900,
+            lockout_secs: 900,
+        }
+    }
+
+    #[test]
+    fn test_email_burst_locks_with_incremental_lockout() {
+        let mut throttle = LoginThrottle::new(test_config());
+        let start = Utc::now();
+        let email = "victim@lifeup.com";
+
+        for i in 0..4 {
+            let outcome = throttle.record_failure(email, Some("10.0.0.1"), start + Duration::seconds(i));
+            assert_eq!(outcome.locked_secs, None);
+        }
+        assert_eq!(throttle.check(email, Some("10.0.0.2"), start), None);
+
+        let outcome = throttle.record_failure(email, Some("10.0.0.2"), start + Duration::seconds(4));
+        assert_eq!(outcome.locked_secs, Some(900));
+        // 換 IP 仍然鎖定，其他帳號不受影響
+        let now = start + Duration::seconds(10);
+        assert_eq!(throttle.check(email, Some("10.0.0.3"), now), Some(LoginBlock::AccountLocked(894)));
+        assert_eq!(throttle.check("other@lifeup.com", Some("10.0.0.3"), now), None);
+
+        // 鎖定解除後再次連續失敗，鎖定時間加倍
+        let later = start + Duration::seconds(905);
+        assert_eq!(throttle.check(email, None, later), None);
+        let mut outcome = FailureOutcome::default();
+        for i in 0..5 {
+            outcome = throttle.record_failure(email, None, later + Duration::seconds(i));
+        }
+        assert_eq!(outcome.locked_secs, Some(1800));
+
+        // 登入成功後鎖定等級歸零
+        throttle.record_success(email);
+        assert_eq!(throttle.check(email, None, later), None);
+        for i in 0..5 {
+            outcome = throttle.record_failure(email, None, later + Duration::seconds(i));
+        }
+        assert_eq!(outcome.locked_secs, Some(900));
+    }
+
+    #[test]
+    fn test_failures_outside_window_do_not_accumulate() {
+        let mut throttle = LoginThrottle::new(test_config());
+        let start = Utc::now();
+        for i in 0..10 {
+            let outcome = throttle.record_failure("slow@lifeup.com", None, start + Duration::seconds(i * 300));
+            assert_eq!(outcome.locked_secs, None);
+        }
+    }
+
+    #[test]
+    fn test_ip_burst_across_many_emails_is_throttled() {
+        let mut throttle = LoginThrottle::new(test_config());
+        let start = Utc::now();
+        let mut outcome = FailureOutcome::default();
+        for i in 0..8 {
+            outcome = throttle.record_failure(&format!("user{}@lifeup.com", i), Some("10.0.0.9"), start);
+        }
+        assert_eq!(outcome.ip_throttled_secs, Some(900));
+        assert_eq!(outcome.locked_secs, None);
+        assert_eq!(
+            throttle.check("new@lifeup.com", Some("10.0.0.9"), start + Duration::seconds(100)),
+            Some(LoginBlock::IpThrottled(800))
+        );
+        assert_eq!(throttle.check("new@lifeup.com", Some("10.0.0.10"), start), None);
+    }
+
+    #[test]
+    fn test_full_tables_evict_the_oldest_entries() {
+        let mut throttle = LoginThrottle::new(test_config());
+        let start = Utc::now();
+        for i in 0..MAX_ENTRIES + 10 {
+            let now = start + Duration::milliseconds(i as i64);
+            throttle.record_failure(&format!("user{}@lifeup.com", i), Some(&format!("ip-{}", i)), now);
+        }
+        assert!(throttle.emails.len() <= MAX_ENTRIES);
+        assert!(throttle.ips.len() <= MAX_ENTRIES);
+        // 最早的紀錄被移除，最近的保留
+        assert!(!throttle.emails.contains_key("user0@lifeup.com"));
+        assert!(!throttle.ips.contains_key("ip-0"));
+        assert!(throttle.emails.contains_key(&format!("user{}@lifeup.com", MAX_ENTRIES + 9)));
+        assert!(throttle.ips.contains_key(&format!("ip-{}", MAX_ENTRIES + 9)));
+    }
+
+    #[test]
+    fn test_locked_response_matches_wrong_password_except_retry_after() {
+        let wrong_password = invalid_credentials_response(None);
+        let locked = invalid_credentials_response(Some(900));
+        assert_eq!(wrong_password.status(), locked.status());
+        assert!(wrong_password.headers().get("retry-after").is_none());
+        assert_eq!(locked.headers().get("retry-after").unwrap(), "900");
+    }
+}
@@ -0,0 +1,296 @@
+// 郵件服務模組定義
+
+// 子模組聲明
+mod smtp;
+mod templates;
+
+// 重新導出公開的 API
+pub use smtp::SmtpMailer;
+pub use templates::{escape_html, MailLanguage, MailTemplate, RenderedMail};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::MailConfig;
+
+// 重試的起始等待時間，之後每次加倍
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+// 郵件服務 trait
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn send(&self, to: &str, subject: &str, html_body: &str) -> Result<()>;
+}
+
+/// 開發用：只把郵件內容寫入日誌，不實際發送
+pub struct LogMailer;
+
+#[async_trait::async_trait]
+impl Mailer for LogMailer {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    async fn send(&self, to: &str, subject: &str, html_body: &str) -> Result<()> {
+        log::info!("📧 [LogMailer] to={} subject={}\n{}", to, subject, html_body);
+        Ok(())
+    }
+}
+
+// 郵件服務工廠函數
+pub fn create_mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>> {
+    match config.provider.to_lowercase().as_str() {
+        "smtp" => Ok(Arc::new(SmtpMailer::new(config)?)),
+        "log" => Ok(Arc::new(LogMailer)),
+        _ => Err(anyhow::anyhow!("不支援的郵件服務選項: {}", config.provider)),
+    }
+}
+
+/// 郵件發送統計（程序啟動後累計）
+#[derive(Debug, Default)]
+struct MailMetrics {
+    queued: AtomicU64,
+    sent: AtomicU64,
+    retried: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MailMetricsSnapshot {
+    pub queued: u64,
+    pub sent: u64,
+    pub retried: u64,
+    pub failed: u64,
+}
+
+static MAIL_METRICS: MailMetrics = MailMetrics {
+    queued: AtomicU64::new(0),
+    sent: AtomicU64::new(0),
+    retried: AtomicU64::new(0),
+    failed: AtomicU64::new(0),
+};
+
+pub fn metrics_snapshot() -> MailMetricsSnapshot {
+    MailMetricsSnapshot {
+        queued: MAIL_METRICS.queued.load(Ordering::Relaxed),
+        sent: MAIL_METRICS.sent.load(Ordering::Relaxed),
+        retried: MAIL_METRICS.retried.load(Ordering::Relaxed),
+        failed: MAIL_METRICS.failed.load(Ordering::Relaxed),
+    }
+}
+
+struct MailerState {
+    mailer: Arc<dyn Mailer>,
+    max_attempts: u32,
+}
+
+static MAILER: OnceLock<MailerState> = OnceLock::new();
+
+/// 啟動時建立郵件服務；設定錯誤時退回 LogMailer，避免郵件功能拖垮伺服器
+pub fn init(config: &MailConfig) {
+    let mailer = create_mailer(config).unwrap_or_else(|e| {
+        log::error!("郵件服務初始化失敗，改用日誌模式: {}", e);
+        Arc::new(LogMailer)
+    });
+    log::info!("郵件服務: {}", mailer.name());
+    let state = MailerState { mailer, max_attempts: config.max_attempts.max(1) };
+    if MAILER.set(state).is_err() {
+        log::warn!("郵件服務已初始化，忽略重複設定");
+    }
+}
+
+fn state() -> &'static MailerState {
+    MAILER.get_or_init(|| MailerState { mailer: Arc::new(LogMailer), max_attempts: 1 })
+}
+
+/// 發送郵件並在失敗時重試（等待時間依次加倍）；回傳是否成功
+pub async fn deliver_with_retry(
+    mailer: &dyn Mailer,
+    to: &str,
+    mail: &RenderedMail,
+    max_attempts: u32,
+    base_delay: Duration,
+) -> bool {
+    let mut delay = base_delay;
+    for attempt in 1..=max_attempts {
+        match mailer.send(to, &mail.subject, &mail.html_body).await {
+            Ok(()) => {
+                MAIL_METRICS.sent.fetch_add(1, Ordering::Relaxed);
+                log::info!("郵件已發送: to={} subject={} (第 {} 次嘗試)", to, mail.subject, attempt);
+                return true;
+            }
+            Err(e) if attempt < max_attempts => {
+                MAIL_METRICS.retried.fetch_add(1, Ordering::Relaxed);
+                log::warn!("郵件發送失敗，{:?} 後重試 ({}/{}): to={} error={}", delay, attempt, max_attempts, to, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => {
+                MAIL_METRICS.failed.fetch_add(1, Ordering::Relaxed);
+                log::error!("郵件發送失敗，已放棄 ({} 次嘗試): to={} subject={} error={}", attempt, to, mail.subject, e);
+            }
+        }
+    }
+    false
+}
+
+/// 在背景發送郵件，不阻塞請求（供密碼重設、信箱驗證、每週與每月報告呼叫）
+pub fn send_in_background(to: String, mail: RenderedMail) {
+    MAIL_METRICS.queued.fetch_add(1, Ordering::Relaxed);
+    let state = state();
+    let mailer = state.mailer.clone();
+    let max_attempts = state.max_attempts;
+    tokio::spawn(async move {
+        deliver_with_retry(mailer.as_ref(), &to, &mail, max_attempts, RETRY_BASE_DELAY).await;
+    });
+}
+
+/// 以模板渲染後在背景發送
+pub fn send_template_in_background(to: String, template: &MailTemplate, language: MailLanguage) {
+    send_in_background(to, template.render(language));
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MailTestRequest {
+    pub to: String,
+    // password_reset、email_verification、weekly_report；未指定時發送簡單的測試信
+    pub template: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MailTestResponse {
+    pub provider: String,
+    pub metrics: MailMetricsSnapshot,
+}
+
+fn sample_mail(template: Option<&str>, language: MailLanguage) -> std::result::Result<RenderedMail, String> {
+    let template = match template {
+        None => {
+            return Ok(RenderedMail {
+                subject: "LifeUp 郵件設定測試".to_string(),
+                html_body: "<p>如果你收到這封信，代表郵件服務設定正確。</p>".to_string(),
+            })
+        }
+        Some("password_reset") => MailTemplate::PasswordReset {
+            user_name: "LifeUp".to_string(),
+            reset_url: "https://lifeup.app/reset-password?token=test".to_string(),
+            expires_minutes: 30,
+        },
+        Some("email_verification") => MailTemplate::EmailVerification {
+            user_name: "LifeUp".to_string(),
+            verify_url: "https://lifeup.app/verify-email?token=test".to_string(),
+        },
+        Some("weekly_report") => MailTemplate::WeeklyReport {
+            user_name: "LifeUp".to_string(),
+            completed_tasks: 12,
+            experience_gained: 340,
+            current_level: 5,
+        },
+        Some(other) => return Err(format!("未知的郵件模板: {}", other)),
+    };
+    Ok(template.render(language))
+}
+
+/// 管理員測試郵件設定：直接（非背景）發送一次，回傳實際的錯誤訊息
+pub async fn send_test_mail(http_req: HttpRequest, req: web::Json<MailTestRequest>) -> ActixResult<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+
+    let language = MailLanguage::from_code(req.language.as_deref().unwrap_or("zh-TW"));
+    let mail = match sample_mail(req.template.as_deref(), language) {
+        Ok(mail) => mail,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message,
+            }))
+        }
+    };
+
+    let state = state();
+    let result = state.mailer.send(req.to.trim(), &mail.subject, &mail.html_body).await;
+    match &result {
+        Ok(()) => MAIL_METRICS.sent.fetch_add(1, Ordering::Relaxed),
+        Err(_) => MAIL_METRICS.failed.fetch_add(1, Ordering::Relaxed),
+    };
+    let data = MailTestResponse { provider: state.mailer.name().to_string(), metrics: metrics_snapshot() };
+
+    match result {
+        Ok(()) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(data),
+            message: format!("測試郵件已發送至 {}", req.to.trim()),
+        })),
+        Err(e) => {
+            log::error!("測試郵件發送失敗: {}", e);
+            Ok(HttpResponse::BadGateway().json(ApiResponse {
+                success: false,
+                data: Some(data),
+                message: format!("測試郵件發送失敗: {}", e),
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    struct FlakyMailer {
+        failures_left: AtomicU32,
+        attempts: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl Mailer for FlakyMailer {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn send(&self, _to: &str, _subject: &str, _html_body: &str) -> Result<()> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            if self.failures_left.load(Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(anyhow::anyhow!("SMTP 暫時無法連線"));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_until_success_or_gives_up() {
+        let mail = RenderedMail { subject: "測試".to_string(), html_body: "<p>測試</p>".to_string() };
+
+        let flaky = FlakyMailer { failures_left: AtomicU32::new(2), attempts: AtomicU32::new(0) };
+        assert!(deliver_with_retry(&flaky, "u1@lifeup.com", &mail, 3, Duration::from_millis(1)).await);
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 3);
+
+        let broken = FlakyMailer { failures_left: AtomicU32::new(10), attempts: AtomicU32::new(0) };
+        assert!(!deliver_with_retry(&broken, "u1@lifeup.com", &mail, 3, Duration::from_millis(1)).await);
+        assert_eq!(broken.attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_factory_rejects_incomplete_smtp_config() {
+        let mut config = crate::config::Config::from_env().app.mail;
+        config.provider = "smtp".to_string();
+        config.smtp_host = None;
+        assert!(create_mailer(&config).is_err());
+        config.provider = "log".to_string();
+        assert_eq!(create_mailer(&config).unwrap().name(), "log");
+    }
+}
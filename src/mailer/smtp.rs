@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::Mailer;
+use crate::config::MailConfig;
+
+/// 透過 SMTP 發送郵件
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &MailConfig) -> Result<Self> {
+        let host = config
+            .smtp_host
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("SMTP_HOST 未設定"))?;
+        let from: Mailbox = config
+            .from_address
+            .parse()
+            .with_context(|| format!("MAIL_FROM 格式錯誤: {}", config.from_address))?;
+
+        let builder = if config.smtp_starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+        } else {
+            log::warn!("SMTP 未啟用 STARTTLS，郵件將以明文傳輸（僅適用於本機測試）");
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        let mut builder = builder.port(config.smtp_port);
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpMailer { transport: builder.build(), from })
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn send(&self, to: &str, subject: &str, html_body: &str) -> Result<()> {
+        let to: Mailbox = to.parse().with_context(|| format!("收件人格式錯誤: {}", to))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_HTML)
+            .body(html_body.to_string())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
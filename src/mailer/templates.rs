@@ -0,0 +1,198 @@
+// 交易郵件模板（繁體中文與英文）
+
+/// 郵件語言
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MailLanguage {
+    ZhTw,
+    En,
+}
+
+impl MailLanguage {
+    /// 由語言代碼判斷（en、en-US 等為英文，其餘預設繁體中文）
+    pub fn from_code(code: &str) -> Self {
+        if code.trim().to_ascii_lowercase().starts_with("en") {
+            MailLanguage::En
+        } else {
+            MailLanguage::ZhTw
+        }
+    }
+}
+
+/// 交易郵件
+#[derive(Debug, Clone)]
+pub enum MailTemplate {
+    PasswordReset {
+        user_name: String,
+        reset_url: String,
+        expires_minutes: i64,
+    },
+    EmailVerification {
+        user_name: String,
+        verify_url: String,
+    },
+    WeeklyReport {
+        user_name: String,
+        completed_tasks: i32,
+        experience_gained: i32,
+        current_level: i32,
+    },
+    MonthlyReport {
+        user_name: String,
+        // YYYY-MM
+        month: String,
+        completed_tasks: i64,
+        experience_gained: i64,
+        new_achievements: usize,
+        summary: Option<String>,
+    },
+}
+
+/// 渲染完成的郵件
+#[derive(Debug, Clone)]
+pub struct RenderedMail {
+    pub subject: String,
+    pub html_body: String,
+}
+
+/// 轉義使用者提供的文字，避免插入 HTML
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn layout(language: MailLanguage, title: &str, content: &str) -> String {
+    let (lang, footer) = match language {
+        MailLanguage::ZhTw => ("zh-TW", "這封信由 LifeUp 自動發送，請勿直接回覆。"),
+        MailLanguage::En => ("en", "This email was sent automatically by LifeUp. Please do not reply."),
+    };
+    format!(
+        "<!DOCTYPE html><html lang=\"{lang}\"><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+         <body style=\"font-family: sans-serif; color: #333;\">\
+         <h2>{title}</h2>{content}\
+         <p style=\"color: #999; font-size: 12px;\">{footer}</p></body></html>",
+        lang = lang,
+        title = escape_html(title),
+        content = content,
+        footer = footer,
+    )
+}
+
+fn button(url: &str, label: &str) -> String {
+    format!(
+        "<p><a href=\"{url}\" style=\"display: inline-block; padding: 10px 20px; background: #4f46e5; color: #fff; text-decoration: none; border-radius: 6px;\">{label}</a></p>",
+        url = escape_html(url),
+        label = label,
+    )
+}
+
+impl MailTemplate {
+    pub fn render(&self, language: MailLanguage) -> RenderedMail {
+        let (subject, content) = match (self, language) {
+            (MailTemplate::PasswordReset { user_name, reset_url, expires_minutes }, MailLanguage::ZhTw) => (
+                "重設你的 LifeUp 密碼".to_string(),
+                format!(
+                    "<p>{}，你好：</p><p>我們收到重設密碼的請求，請在 {} 分鐘內點擊下方按鈕設定新密碼。</p>{}<p>如果這不是你本人的操作，請忽略這封信。</p>",
+                    escape_html(user_name),
+                    expires_minutes,
+                    button(reset_url, "重設密碼"),
+                ),
+            ),
+            (MailTemplate::PasswordReset { user_name, reset_url, expires_minutes }, MailLanguage::En) => (
+                "Reset your LifeUp password".to_string(),
+                format!(
+                    "<p>Hi {},</p><p>We received a request to reset your password. Use the button below within {} minutes to choose a new one.</p>{}<p>If you did not request this, you can ignore this email.</p>",
+                    escape_html(user_name),
+                    expires_minutes,
+                    button(reset_url, "Reset password"),
+                ),
+            ),
+            (MailTemplate::EmailVerification { user_name, verify_url }, MailLanguage::ZhTw) => (
+                "驗證你的 LifeUp 電子郵件".to_string(),
+                format!(
+                    "<p>{}，歡迎加入 LifeUp！</p><p>請點擊下方按鈕完成電子郵件驗證。</p>{}",
+                    escape_html(user_name),
+                    button(verify_url, "驗證電子郵件"),
+                ),
+            ),
+            (MailTemplate::EmailVerification { user_name, verify_url }, MailLanguage::En) => (
+                "Verify your LifeUp email".to_string(),
+                format!(
+                    "<p>Welcome to LifeUp, {}!</p><p>Please confirm your email address using the button below.</p>{}",
+                    escape_html(user_name),
+                    button(verify_url, "Verify email"),
+                ),
+            ),
+            (MailTemplate::WeeklyReport { user_name, completed_tasks, experience_gained, current_level }, MailLanguage::ZhTw) => (
+                "你的 LifeUp 每週報告".to_string(),
+                format!(
+                    "<p>{}，這是你本週的成長紀錄：</p><ul><li>完成任務：{} 個</li><li>獲得經驗：{} XP</li><li>目前等級：Lv.{}</li></ul><p>繼續保持！</p>",
+                    escape_html(user_name),
+                    completed_tasks,
+                    experience_gained,
+                    current_level,
+                ),
+            ),
+            (MailTemplate::WeeklyReport { user_name, completed_tasks, experience_gained, current_level }, MailLanguage::En) => (
+                "Your LifeUp weekly report".to_string(),
+                format!(
+                    "<p>Hi {}, here is your progress this week:</p><ul><li>Tasks completed: {}</li><li>Experience gained: {} XP</li><li>Current level: Lv.{}</li></ul><p>Keep it up!</p>",
+                    escape_html(user_name),
+                    completed_tasks,
+                    experience_gained,
+                    current_level,
+                ),
+            ),
+            (MailTemplate::MonthlyReport { user_name, month, completed_tasks, experience_gained, new_achievements, summary }, MailLanguage::ZhTw) => (
+                format!("你的 LifeUp {} 月報", month),
+                format!(
+                    "<p>{}，這是你 {} 的成長紀錄：</p><ul><li>完成任務：{} 個</li><li>獲得經驗：{} XP</li><li>新成就：{} 個</li></ul>{}",
+                    escape_html(user_name),
+                    escape_html(month),
+                    completed_tasks,
+                    experience_gained,
+                    new_achievements,
+                    summary.as_deref().map(|s| format!("<p>{}</p>", escape_html(s))).unwrap_or_default(),
+                ),
+            ),
+            (MailTemplate::MonthlyReport { user_name, month, completed_tasks, experience_gained, new_achievements, summary }, MailLanguage::En) => (
+                format!("Your LifeUp monthly report for {}", month),
+                format!(
+                    "<p>Hi {}, here is your progress in {}:</p><ul><li>Tasks completed: {}</li><li>Experience gained: {} XP</li><li>New achievements: {}</li></ul>{}",
+                    escape_html(user_name),
+                    escape_html(month),
+                    completed_tasks,
+                    experience_gained,
+                    new_achievements,
+                    summary.as_deref().map(|s| format!("<p>{}</p>", escape_html(s))).unwrap_or_default(),
+                ),
+            ),
+        };
+        let html_body = layout(language, &subject, &content);
+        RenderedMail { subject, html_body }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_render_both_languages_and_escape_names() {
+        let template = MailTemplate::PasswordReset {
+            user_name: "<b>小明</b>".to_string(),
+            reset_url: "https://lifeup.app/reset?token=a&b".to_string(),
+            expires_minutes: 30,
+        };
+        let zh = template.render(MailLanguage::from_code("zh-TW"));
+        let en = template.render(MailLanguage::from_code("en-US"));
+        assert_eq!(zh.subject, "重設你的 LifeUp 密碼");
+        assert_eq!(en.subject, "Reset your LifeUp password");
+        assert!(zh.html_body.contains("&lt;b&gt;小明&lt;/b&gt;"));
+        assert!(!zh.html_body.contains("<b>小明</b>"));
+        assert!(en.html_body.contains("token=a&amp;b"));
+        assert!(en.html_body.contains("lang=\"en\""));
+    }
+}
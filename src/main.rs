@@ -1,6 +1,7 @@
 mod config;
 mod models;
 mod routes;
+mod services;
 mod auth;
 mod validation;
 // mod rate_limit; // TODO: 暫時禁用，等待 actix-governor 版本兼容性問題解決
@@ -10,18 +11,93 @@ mod ai_service;
 mod ai_tasks;
 mod ai_tasks_achievement;
 mod achievement_service;
+mod achievement_share;
 mod career_routes;
 mod behavior_analytics;
 mod progressive_career_gen;
 #[cfg(feature = "push-notifications")]
 mod push_service;
 #[cfg(feature = "push-notifications")]
+mod vapid_keys;
 mod push_scheduler;
 mod calendar_service;
+mod skill_normalizer;
+mod event_notifier;
+mod leaderboard;
+mod friends;
+mod shared_tasks;
+mod idempotency;
+mod task_update;
+mod recurring_progress;
+mod recurring_preview;
+mod attribute_recommendations;
+mod chat_fast_mode;
+mod nightly_digest;
+mod background_jobs;
+mod achievement_autogen;
+mod achievement_check;
+mod user_settings;
+mod api_tokens;
+mod local_date;
+mod attribute_rewards;
+mod recompute;
+mod cors_policy;
+mod sessions;
+mod audit_log;
+mod logging;
+mod login_throttle;
+mod ai_quota;
+mod task_attachments;
+mod chat_attachments;
+mod task_comments;
+mod task_tree;
+mod focus_sessions;
+mod task_cancellation;
+mod daily_quests;
+mod reward_shop;
+mod habit_stats;
+mod home;
+mod task_filters;
+mod slow_log;
+mod api_envelope;
+mod api_version;
+mod request_context;
+mod mailer;
 mod notification_generator;
-use actix_cors::Cors;
+mod notification_categories;
+mod notification_center;
+mod streak_reminder;
+mod coach_checkin;
+mod difficulty_calibration;
+mod data_retention;
+mod challenges;
+mod public_profile;
+mod task_confirmation;
+mod coach_context;
+mod user_memory;
+mod career_trace;
+mod career_review;
+mod registration_guard;
+mod week_start;
+mod job_runner;
+mod task_tags;
+mod monthly_report;
+mod impersonation;
+mod new_user_defaults;
+mod task_types;
+mod skill_categories;
+mod attribute_compare;
+mod daily_compaction;
+mod reward_config;
+mod datetime_format;
+mod integrations;
+mod gamified_cache;
+mod ownership;
+mod language;
+#[cfg(test)]
+mod test_utils;
 use actix_web::{web, App, HttpServer};
-use actix_web::middleware::Logger;
+use actix_web::middleware::{Condition, Logger};
 use rbatis::RBatis;
 use rbdc_sqlite::driver::SqliteDriver;
 use rustls::{Certificate, PrivateKey, ServerConfig};
@@ -30,9 +106,8 @@ use std::fs::File;
 use std::io::BufReader;
 
 use config::Config;
-use routes::*;
 use database_reset::reset_database;
-use seed_data::{seed_database, seed_minimum_user_data};
+use seed_data::{seed_database, seed_profile_user};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -42,6 +117,7 @@ async fn main() -> std::io::Result<()> {
     let reset_db = args.contains(&"--reset-db".to_string());
     let init_db = args.contains(&"--init-db".to_string());
     let seed_only = args.contains(&"--seed".to_string());
+    let seed_profile = args.iter().find_map(|arg| arg.strip_prefix("--seed-profile=")).map(str::to_string);
 
     // 根據命令行參數載入對應的 .env 文件
     if is_production {
@@ -63,36 +139,8 @@ async fn main() -> std::io::Result<()> {
         _ => log::LevelFilter::Info,  // 默認為 Info 級別
     };
 
-    // 使用 log4rs 初始化日誌系統
-    use log4rs::{
-        append::{console::ConsoleAppender, file::FileAppender},
-        config::{Appender, Root},
-        encode::pattern::PatternEncoder,
-        Config as Log4rsConfig,
-    };
-
-    // 創建控制台輸出器（stdout）
-    let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new("{d(%Y-%m-%d %H:%M:%S)} [{l}] {t} - {m}{n}")))
-        .build();
-
-    // 創建文件輸出器
-    let logfile = FileAppender::builder()
-        .encoder(Box::new(PatternEncoder::new("{d(%Y-%m-%d %H:%M:%S)} [{l}] {t} - {m}{n}")))
-        .build("logs/lifeup.log")
-        .expect("無法創建日誌文件");
-
-    // 配置 log4rs
-    let log_config = Log4rsConfig::builder()
-        .appender(Appender::builder().build("stdout", Box::new(stdout)))
-        .appender(Appender::builder().build("logfile", Box::new(logfile)))
-        .build(Root::builder()
-            .appender("stdout")
-            .appender("logfile")
-            .build(log_level))
-        .expect("日誌配置失敗");
-
-    log4rs::init_config(log_config).expect("日誌初始化失敗");
+    // 初始化日誌系統（主控台 + 輪替日誌檔）
+    logging::init(log_level, &config.app.log_format, &config.app.log_file);
 
     if is_production {
         log::info!("LifeUp Backend 啟動中... [生產模式]");
@@ -104,7 +152,21 @@ async fn main() -> std::io::Result<()> {
     log::info!("環境: {}", config.app.environment);
     log::info!("日誌級別: {}", config.app.log_level);
     log::info!("數據庫: {}", if config.database.url.contains("sqlite") { "SQLite" } else { "其他" });
-    log::info!("允許的 CORS 來源: {:?}", config.server.allowed_origins);
+    let env_file = if is_production { ".env.production" } else { ".env.development" };
+    login_throttle::init(config.app.login_throttle.clone());
+    ai_quota::init(config.app.ai_quota.clone());
+    task_attachments::init(config.app.attachments.clone());
+    focus_sessions::init(config.app.focus.clone());
+    daily_quests::init(config.app.daily_quests.clone());
+    slow_log::init(config.app.slow_log.clone());
+    api_envelope::init(config.app.legacy_response_fields, config.app.legacy_expert_prefix);
+    mailer::init(&config.app.mail);
+    cors_policy::init(
+        &config.server.allowed_origins,
+        config.server.allow_any_origin,
+        is_production,
+        env_file,
+    );
 
     // AI 配置調試日誌 (不記錄 API 金鑰)
     log::info!("AI 配置載入: API_OPTION={}", config.app.ai.api_option);
@@ -118,6 +180,7 @@ async fn main() -> std::io::Result<()> {
     
     // 連接資料庫
     rb.init(SqliteDriver {}, &config.database.url).unwrap();
+    rb.intercepts.push(std::sync::Arc::new(slow_log::SlowQueryIntercept::new()));
     log::info!("資料庫連接成功: {}", config.database.url);
 
     // 處理資料庫重置命令 (--reset-db: 完全重置 + 插入測試資料)
@@ -157,41 +220,96 @@ async fn main() -> std::io::Result<()> {
         return Ok(());
     }
 
+    // 依種子設定檔建立使用者 (--seed-profile=<名稱>: 保留現有表，以非預設的新帳號設定建立一位使用者)
+    if let Some(profile) = seed_profile {
+        log::info!("依種子設定檔 {} 建立使用者...", profile);
+        if let Err(e) = seed_profile_user(&rb, &profile).await {
+            log::error!("種子設定檔建立失敗: {}", e);
+            return Err(std::io::Error::other(e.to_string()));
+        }
+        return Ok(());
+    }
+
+    // 遷移時補齊的遊戲化資料與任務類型修正都依設定進行，須在遷移前初始化
+    new_user_defaults::init(config.app.new_user_defaults.clone());
+    task_types::init(config.app.task_types.clone());
+    reward_config::init(config.app.reward.clone());
+    local_date::init(&config.app.default_timezone);
+
     // 確保資料表存在並執行必要的遷移
     create_tables(&rb).await;
     migrate_database(&rb).await;
+    skill_normalizer::seed_default_aliases(&rb).await;
+    recurring_progress::spawn_expiry_sweeper(rb.clone());
 
-    // 初始化日曆服務（用於假日判斷）
-    let calendar_service = match calendar_service::CalendarService::new() {
-        Ok(service) => {
-            log::info!("日曆服務初始化成功，載入 {} 個假日", service.get_holiday_count());
-            service
-        }
-        Err(e) => {
-            log::warn!("日曆服務初始化失敗: {}", e);
-            log::info!("將使用簡單的週末判斷作為備用");
-            calendar_service::CalendarService::new().unwrap()
-        }
-    };
+    // 初始化日曆服務（用於假日判斷，與重複性任務預覽共用；假日資料缺少時只判斷週末）
+    let calendar_service = calendar_service::shared().clone();
+    match calendar_service.status().degraded_reason {
+        Some(reason) => log::warn!("日曆服務以只判斷週末的降級模式運作: {}", reason),
+        None => log::info!("日曆服務初始化成功，載入 {} 個假日", calendar_service.get_holiday_count()),
+    }
 
-    // 啟動推送通知調度器（僅在啟用推送通知功能時）
+    // 啟動定時通知調度器（通知一律寫入通知中心，Web Push 需啟用 push-notifications）
     #[cfg(feature = "push-notifications")]
-    {
-        if let Err(e) = push_scheduler::start_push_scheduler(rb.clone(), calendar_service.clone()).await {
-            log::warn!("推送通知調度器啟動失敗（可能是 VAPID 金鑰未配置）: {}", e);
-            log::info!("推送通知功能將不可用，但不影響其他服務運行");
-        } else {
-            log::info!("推送通知調度器已成功啟動");
-        }
+    vapid_keys::load(&rb).await;
+    streak_reminder::init(config.app.streak_reminder.clone());
+    notification_center::init(config.app.notification_center.clone());
+    gamified_cache::init(config.app.gamified_cache.clone());
+    coach_checkin::init(config.app.coach_checkin.clone());
+    chat_fast_mode::init(config.app.chat_fast_mode.clone());
+    ai_service::init_content_sanitizer(config.app.ai_sanitize.clone());
+    data_retention::init(config.app.data_retention.clone());
+    nightly_digest::init(config.app.nightly_digest.clone());
+    daily_compaction::init(config.app.daily_compaction.clone());
+    background_jobs::init(config.app.background_jobs.clone());
+    achievement_autogen::init(config.app.achievement_autogen.clone());
+    achievement_check::init(config.app.achievement_check.clone());
+    recurring_progress::init(config.app.recurring.clone());
+    task_confirmation::init(config.app.task_confirmation.clone());
+    task_cancellation::init(config.app.task_cancellation.clone());
+    career_trace::init(config.app.career_trace.clone());
+    registration_guard::init(config.app.registration_guard.clone());
+    attribute_rewards::init_skill_attribute(config.app.skill_attribute.clone());
+    notification_center::spawn_cleanup(rb.clone());
+    if let Err(e) = push_scheduler::start_push_scheduler(rb.clone(), calendar_service.clone()).await {
+        log::warn!("定時通知調度器啟動失敗: {}", e);
+        log::info!("定時通知將不可用，但不影響其他服務運行");
+    } else {
+        log::info!("定時通知調度器已成功啟動");
     }
     #[cfg(not(feature = "push-notifications"))]
-    log::info!("推送通知功能已停用（未啟用 push-notifications feature）");
+    log::info!("Web Push 已停用（未啟用 push-notifications feature），通知只會出現在通知中心");
 
     let server_addr = config.server_addr();
+    let json_logs = logging::is_json_format(&config.app.log_format);
 
     // 共享資料庫連線
     let rb_data = web::Data::new(rb.clone());
 
+    // 檢查設定中引用的每個 AI 服務提供者（含各等級的 provider:model）都有 API key
+    if let Err(e) = ai_service::validate_provider_keys(&config.app.ai) {
+        log::error!("AI 服務提供者設定錯誤: {}", e);
+    }
+
+    // 共享 AI 服務（只在啟動時建立一次）
+    let ai_service = ai_service::SharedAIService::from_config(&config.app.ai);
+    if let Err(e) = ai_service.get() {
+        log::warn!("AI 服務初始化失敗，AI 相關功能將無法使用: {}", e);
+    }
+    // 教練主動關心（使用背景模型，需在 AI 服務建立後啟動）
+    coach_checkin::spawn_scheduler(rb.clone(), ai_service.clone());
+    // 任務難度校準（定期重算過期的結果）
+    difficulty_calibration::spawn_job(rb.clone());
+    // 依使用者保留策略每天清除過期資料
+    data_retention::spawn_scheduler(rb.clone());
+    // 使用者時區午夜後彙整前一天的每日進度與連續紀錄
+    nightly_digest::spawn_scheduler(rb.clone());
+    // 結算已過結束日期的挑戰任務
+    challenges::spawn_sweeper(rb.clone());
+    // 還原逾時未確認的待確認完成任務
+    task_confirmation::spawn_scheduler(rb.clone());
+    let ai_data = web::Data::new(ai_service);
+
     // 根據環境決定使用 HTTP 還是 HTTPS
     if is_production {
         // 生產模式：使用 HTTPS
@@ -222,133 +340,22 @@ async fn main() -> std::io::Result<()> {
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
 
         HttpServer::new(move || {
-            // 設定 CORS - 只允許配置的來源 (HTTPS)
-            log::info!("配置 CORS，允許的來源: {:?}", config.server.allowed_origins);
-
-            let mut cors = Cors::default()
-                .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"])
-                .allowed_headers(vec![
-                    actix_web::http::header::AUTHORIZATION,
-                    actix_web::http::header::ACCEPT,
-                    actix_web::http::header::CONTENT_TYPE,
-                    actix_web::http::header::HeaderName::from_static("x-requested-with"),
-                ])
-                .expose_headers(vec![actix_web::http::header::CONTENT_TYPE])
-                .supports_credentials()
-                .max_age(3600);
-
-            // 添加允許的來源
-            for origin in &config.server.allowed_origins {
-                log::info!("添加 CORS 來源: {}", origin);
-                cors = cors.allowed_origin(origin.as_str());
-            }
+            // 設定 CORS - 只允許配置的來源（支援子網域萬用字元，設定可由管理員重新載入）
+            let cors = cors_policy::build_cors();
 
             App::new()
-                // HTTP 請求日誌
-                .wrap(Logger::default())
+                // HTTP 請求日誌（JSON 格式時由 RequestContextMiddleware 記錄）
+                .wrap(Condition::new(!json_logs, Logger::default()))
+                .wrap(slow_log::SlowRequestLog)
+                // 錯誤回應統一為 ApiResponse 並附上 code
+                .wrap(api_envelope::ApiEnvelope)
                 .wrap(cors)
+                // 請求 id 與使用者帶入日誌上下文（最外層，涵蓋所有中間件）
+                .wrap(request_context::RequestContextMiddleware { access_log: json_logs })
                 .app_data(rb_data.clone())
-            // === 公開路由（不需要 JWT 認證）===
-            .route("/health", web::get().to(health_check))
-            .route("/api/auth/login", web::post().to(login))
-            .route("/api/users", web::post().to(create_user))  // 註冊
-
-            // === 受保護路由（需要 JWT 認證）===
-            .service(
-                web::scope("/api")
-                    .wrap(auth::JwtAuth)  // 🔒 應用 JWT 認證中間件
-                    // 認證相關
-                    .route("/auth/logout", web::post().to(logout))
-                    // 使用者相關
-                    .route("/users", web::get().to(get_users))
-                    .route("/users/{id}", web::get().to(get_user))
-                    .route("/users/{id}/gamified", web::get().to(get_gamified_user_data))
-                    .route("/users/{id}/experience", web::post().to(update_user_experience))
-                    .route("/users/{id}/attributes", web::post().to(update_user_attributes))
-                    .route("/users/{user_id}/achievements", web::get().to(get_user_achievements))
-                    .route("/users/{user_id}/achievements/status", web::get().to(get_user_achievements_status))
-                    .route("/users/{user_id}/achievements/{achievement_id}/unlock", web::post().to(unlock_user_achievement))
-                    .route("/users/{user_id}/attributes/weekly/{weeks_ago}", web::get().to(get_weekly_attributes))
-                    .route("/users/{user_id}/reset", web::delete().to(reset_user_data))
-                    .route("/users/{user_id}/reset", web::post().to(reset_user_data_selective))
-                    .route("/users/{user_id}/task-history", web::get().to(get_task_history))
-                    // 任務相關路由
-                    .route("/tasks", web::get().to(get_tasks))
-                    .route("/tasks", web::post().to(create_task))
-                    .route("/tasks/homepage", web::get().to(get_homepage_tasks))
-                    .route("/tasks/type/{task_type}", web::get().to(get_tasks_by_type))
-                    .route("/tasks/{id}", web::get().to(get_task))
-                    .route("/tasks/{id}", web::put().to(update_task))
-                    .route("/tasks/{id}", web::delete().to(delete_task))
-                    .route("/tasks/{id}/start", web::post().to(start_task))
-                    .route("/tasks/{id}/subtasks", web::get().to(get_subtasks))
-                    .route("/tasks/{id}/pause", web::put().to(pause_task))
-                    .route("/tasks/{id}/cancel", web::put().to(cancel_task))
-                    .route("/tasks/{id}/restart", web::put().to(restart_task))
-                    .route("/tasks/{id}/generate-daily", web::post().to(generate_daily_tasks))
-                    .route("/tasks/{id}/progress", web::get().to(get_task_progress))
-                    .route("/tasks/generate-skill-tags", web::post().to(generate_skill_tags))
-                    .route("/tasks/generate", web::post().to(crate::ai_tasks::generate_task_with_ai))
-                    .route("/tasks/generate-json", web::post().to(crate::ai_tasks::generate_task_json))
-                    .route("/tasks/generate-daily-task-json", web::post().to(crate::ai_tasks::generate_daily_task_json))
-                    .route("/tasks/insert-json", web::post().to(crate::ai_tasks::insert_task_from_json))
-                    .route("/tasks/create-from-json", web::post().to(crate::ai_tasks::create_task_from_json))
-                    .route("/tasks/validate-preview", web::post().to(crate::ai_tasks::validate_and_preview_task))
-                    .route("/tasks/generate-from-chat", web::post().to(crate::ai_tasks::generate_task_from_chat))
-                    .route("/tasks/generate-with-expert", web::post().to(crate::ai_tasks::generate_task_with_expert))
-                    .route("/tasks/match-expert", web::post().to(crate::ai_tasks::match_expert_only))
-                    .route("/tasks/expert-analysis", web::post().to(crate::ai_tasks::expert_analysis))
-                    .route("/tasks/generate-subtasks", web::post().to(crate::ai_tasks::generate_subtasks_for_task))
-                    .route("/tasks/classify-intent", web::post().to(crate::ai_tasks::classify_user_intent))
-                    // 重複性任務路由
-                    .route("/recurring-tasks", web::post().to(create_recurring_task))
-                    // 技能相關路由
-                    .route("/skills", web::get().to(get_skills))
-                    .route("/skills", web::post().to(create_skill))
-                    .route("/skills/{id}/experience", web::post().to(update_skill_experience))
-                    .route("/skills/{skill_name}/tasks", web::get().to(get_tasks_by_skill))
-                    // 聊天相關路由
-                    .route("/chat/messages", web::get().to(get_chat_messages))
-                    .route("/chat/messages/all", web::get().to(get_all_chat_messages))
-                    .route("/chat/send", web::post().to(send_message))
-                    .route("/chat/save-message", web::post().to(save_chat_message))
-                    .route("/chat/chatgpt", web::post().to(send_message_to_chatgpt))
-                    .route("/chat/personality", web::post().to(send_message_with_personality))
-                    .route("/chat/test-personality", web::post().to(send_message_with_direct_personality))
-                    .route("/chat/test", web::get().to(test_endpoint))
-                    // 教練個性相關路由
-                    .route("/coach/personalities", web::get().to(get_available_personalities))
-                    .route("/coach/personality", web::post().to(set_coach_personality))
-                    .route("/coach/personality/current", web::get().to(get_current_personality))
-                    // 成就相關路由
-                    .route("/achievements", web::get().to(get_achievements))
-                    .route("/achievements/{id}", web::get().to(get_achievement_details))
-                    .route("/achievements/sync-stats", web::post().to(sync_achievement_statistics))
-                    .route("/achievements/generate", web::post().to(generate_achievement_with_ai))
-                    .route("/achievements/generate-from-tasks/{user_id}", web::post().to(crate::ai_tasks::generate_achievement_from_tasks))
-                    // 職業主線任務系統路由
-                    .route("/quiz/save-results", web::post().to(crate::career_routes::save_quiz_results))
-                    .route("/career/generate-tasks", web::post().to(crate::career_routes::generate_career_tasks))
-                    .route("/career/accept-tasks", web::post().to(crate::career_routes::accept_career_tasks))
-                    .route("/career/import", web::post().to(crate::career_routes::import_career_tasks))
-                    .route("/career/generate-tasks-progressive", web::post().to(crate::progressive_career_gen::generate_career_tasks_progressive_sse))
-                    .app_data(web::Data::new(config.clone()))
-            )
-            // 職業主線任務系統路由
-            .route("/api/quiz/save-results", web::post().to(crate::career_routes::save_quiz_results))
-            .route("/api/career/generate-tasks", web::post().to(crate::career_routes::generate_career_tasks))
-            .route("/api/career/accept-tasks", web::post().to(crate::career_routes::accept_career_tasks))
-            .route("/api/career/import", web::post().to(crate::career_routes::import_career_tasks))
-            // 多步驟漸進式任務生成（SSE）
-            .route("/api/career/generate-tasks-progressive", web::post().to(crate::progressive_career_gen::generate_career_tasks_progressive_sse))
-            .app_data(web::Data::new(config.clone()))
-            // 使用者資料重置路由
-            .route("/api/users/{user_id}/reset", web::delete().to(reset_user_data))
-            .route("/api/users/{user_id}/reset", web::post().to(reset_user_data_selective))
-            // 任務歷史路由
-            .route("/api/users/{user_id}/task-history", web::get().to(get_task_history))
-            // 推送通知相關路由（條件編譯）
-            .configure(configure_push_routes)
+                .app_data(ai_data.clone())
+                // 註冊所有路由（見 routes::configure）
+                .configure(|cfg| routes::configure(cfg, config.clone()))
         })
         .workers(2)
         .bind_rustls_021(&server_addr, rustls_config)?
@@ -359,133 +366,22 @@ async fn main() -> std::io::Result<()> {
         log::info!("啟動 HTTP 伺服器在 http://{}", &server_addr);
 
         HttpServer::new(move || {
-            // 設定 CORS - 只允許配置的來源 (HTTP)
-            log::info!("配置 CORS，允許的來源: {:?}", config.server.allowed_origins);
-
-            let mut cors = Cors::default()
-                .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"])
-                .allowed_headers(vec![
-                    actix_web::http::header::AUTHORIZATION,
-                    actix_web::http::header::ACCEPT,
-                    actix_web::http::header::CONTENT_TYPE,
-                    actix_web::http::header::HeaderName::from_static("x-requested-with"),
-                ])
-                .expose_headers(vec![actix_web::http::header::CONTENT_TYPE])
-                .supports_credentials()
-                .max_age(3600);
-
-            // 添加允許的來源
-            for origin in &config.server.allowed_origins {
-                log::info!("添加 CORS 來源: {}", origin);
-                cors = cors.allowed_origin(origin.as_str());
-            }
+            // 設定 CORS - 只允許配置的來源（支援子網域萬用字元，設定可由管理員重新載入）
+            let cors = cors_policy::build_cors();
 
             App::new()
-                // HTTP 請求日誌
-                .wrap(Logger::default())
+                // HTTP 請求日誌（JSON 格式時由 RequestContextMiddleware 記錄）
+                .wrap(Condition::new(!json_logs, Logger::default()))
+                .wrap(slow_log::SlowRequestLog)
+                // 錯誤回應統一為 ApiResponse 並附上 code
+                .wrap(api_envelope::ApiEnvelope)
                 .wrap(cors)
+                // 請求 id 與使用者帶入日誌上下文（最外層，涵蓋所有中間件）
+                .wrap(request_context::RequestContextMiddleware { access_log: json_logs })
                 .app_data(rb_data.clone())
-            // === 公開路由（不需要 JWT 認證）===
-            .route("/health", web::get().to(health_check))
-            .route("/api/auth/login", web::post().to(login))
-            .route("/api/users", web::post().to(create_user))  // 註冊
-
-            // === 受保護路由（需要 JWT 認證）===
-            .service(
-                web::scope("/api")
-                    .wrap(auth::JwtAuth)  // 🔒 應用 JWT 認證中間件
-                    // 認證相關
-                    .route("/auth/logout", web::post().to(logout))
-                    // 使用者相關
-                    .route("/users", web::get().to(get_users))
-                    .route("/users/{id}", web::get().to(get_user))
-                    .route("/users/{id}/gamified", web::get().to(get_gamified_user_data))
-                    .route("/users/{id}/experience", web::post().to(update_user_experience))
-                    .route("/users/{id}/attributes", web::post().to(update_user_attributes))
-                    .route("/users/{user_id}/achievements", web::get().to(get_user_achievements))
-                    .route("/users/{user_id}/achievements/status", web::get().to(get_user_achievements_status))
-                    .route("/users/{user_id}/achievements/{achievement_id}/unlock", web::post().to(unlock_user_achievement))
-                    .route("/users/{user_id}/attributes/weekly/{weeks_ago}", web::get().to(get_weekly_attributes))
-                    .route("/users/{user_id}/reset", web::delete().to(reset_user_data))
-                    .route("/users/{user_id}/reset", web::post().to(reset_user_data_selective))
-                    .route("/users/{user_id}/task-history", web::get().to(get_task_history))
-                    // 任務相關路由
-                    .route("/tasks", web::get().to(get_tasks))
-                    .route("/tasks", web::post().to(create_task))
-                    .route("/tasks/homepage", web::get().to(get_homepage_tasks))
-                    .route("/tasks/type/{task_type}", web::get().to(get_tasks_by_type))
-                    .route("/tasks/{id}", web::get().to(get_task))
-                    .route("/tasks/{id}", web::put().to(update_task))
-                    .route("/tasks/{id}", web::delete().to(delete_task))
-                    .route("/tasks/{id}/start", web::post().to(start_task))
-                    .route("/tasks/{id}/subtasks", web::get().to(get_subtasks))
-                    .route("/tasks/{id}/pause", web::put().to(pause_task))
-                    .route("/tasks/{id}/cancel", web::put().to(cancel_task))
-                    .route("/tasks/{id}/restart", web::put().to(restart_task))
-                    .route("/tasks/{id}/generate-daily", web::post().to(generate_daily_tasks))
-                    .route("/tasks/{id}/progress", web::get().to(get_task_progress))
-                    .route("/tasks/generate-skill-tags", web::post().to(generate_skill_tags))
-                    .route("/tasks/generate", web::post().to(crate::ai_tasks::generate_task_with_ai))
-                    .route("/tasks/generate-json", web::post().to(crate::ai_tasks::generate_task_json))
-                    .route("/tasks/generate-daily-task-json", web::post().to(crate::ai_tasks::generate_daily_task_json))
-                    .route("/tasks/insert-json", web::post().to(crate::ai_tasks::insert_task_from_json))
-                    .route("/tasks/create-from-json", web::post().to(crate::ai_tasks::create_task_from_json))
-                    .route("/tasks/validate-preview", web::post().to(crate::ai_tasks::validate_and_preview_task))
-                    .route("/tasks/generate-from-chat", web::post().to(crate::ai_tasks::generate_task_from_chat))
-                    .route("/tasks/generate-with-expert", web::post().to(crate::ai_tasks::generate_task_with_expert))
-                    .route("/tasks/match-expert", web::post().to(crate::ai_tasks::match_expert_only))
-                    .route("/tasks/expert-analysis", web::post().to(crate::ai_tasks::expert_analysis))
-                    .route("/tasks/generate-subtasks", web::post().to(crate::ai_tasks::generate_subtasks_for_task))
-                    .route("/tasks/classify-intent", web::post().to(crate::ai_tasks::classify_user_intent))
-                    // 重複性任務路由
-                    .route("/recurring-tasks", web::post().to(create_recurring_task))
-                    // 技能相關路由
-                    .route("/skills", web::get().to(get_skills))
-                    .route("/skills", web::post().to(create_skill))
-                    .route("/skills/{id}/experience", web::post().to(update_skill_experience))
-                    .route("/skills/{skill_name}/tasks", web::get().to(get_tasks_by_skill))
-                    // 聊天相關路由
-                    .route("/chat/messages", web::get().to(get_chat_messages))
-                    .route("/chat/messages/all", web::get().to(get_all_chat_messages))
-                    .route("/chat/send", web::post().to(send_message))
-                    .route("/chat/save-message", web::post().to(save_chat_message))
-                    .route("/chat/chatgpt", web::post().to(send_message_to_chatgpt))
-                    .route("/chat/personality", web::post().to(send_message_with_personality))
-                    .route("/chat/test-personality", web::post().to(send_message_with_direct_personality))
-                    .route("/chat/test", web::get().to(test_endpoint))
-                    // 教練個性相關路由
-                    .route("/coach/personalities", web::get().to(get_available_personalities))
-                    .route("/coach/personality", web::post().to(set_coach_personality))
-                    .route("/coach/personality/current", web::get().to(get_current_personality))
-                    // 成就相關路由
-                    .route("/achievements", web::get().to(get_achievements))
-                    .route("/achievements/{id}", web::get().to(get_achievement_details))
-                    .route("/achievements/sync-stats", web::post().to(sync_achievement_statistics))
-                    .route("/achievements/generate", web::post().to(generate_achievement_with_ai))
-                    .route("/achievements/generate-from-tasks/{user_id}", web::post().to(crate::ai_tasks::generate_achievement_from_tasks))
-                    // 職業主線任務系統路由
-                    .route("/quiz/save-results", web::post().to(crate::career_routes::save_quiz_results))
-                    .route("/career/generate-tasks", web::post().to(crate::career_routes::generate_career_tasks))
-                    .route("/career/accept-tasks", web::post().to(crate::career_routes::accept_career_tasks))
-                    .route("/career/import", web::post().to(crate::career_routes::import_career_tasks))
-                    .route("/career/generate-tasks-progressive", web::post().to(crate::progressive_career_gen::generate_career_tasks_progressive_sse))
-                    .app_data(web::Data::new(config.clone()))
-            )
-            // 職業主線任務系統路由
-            .route("/api/quiz/save-results", web::post().to(crate::career_routes::save_quiz_results))
-            .route("/api/career/generate-tasks", web::post().to(crate::career_routes::generate_career_tasks))
-            .route("/api/career/accept-tasks", web::post().to(crate::career_routes::accept_career_tasks))
-            .route("/api/career/import", web::post().to(crate::career_routes::import_career_tasks))
-            // 多步驟漸進式任務生成（SSE）
-            .route("/api/career/generate-tasks-progressive", web::post().to(crate::progressive_career_gen::generate_career_tasks_progressive_sse))
-            .app_data(web::Data::new(config.clone()))
-            // 使用者資料重置路由
-            .route("/api/users/{user_id}/reset", web::delete().to(reset_user_data))
-            .route("/api/users/{user_id}/reset", web::post().to(reset_user_data_selective))
-            // 任務歷史路由
-            .route("/api/users/{user_id}/task-history", web::get().to(get_task_history))
-            // 推送通知相關路由（條件編譯）
-            .configure(configure_push_routes)
+                .app_data(ai_data.clone())
+                // 註冊所有路由（見 routes::configure）
+                .configure(|cfg| routes::configure(cfg, config.clone()))
         })
         .workers(2)
         .bind(&server_addr)?
@@ -503,6 +399,7 @@ async fn create_tables(rb: &RBatis) {
             name TEXT,
             email TEXT,
             password_hash TEXT,
+            token_version INTEGER DEFAULT 0,
             created_at TEXT,
             updated_at TEXT
         )
@@ -538,6 +435,13 @@ async fn create_tables(rb: &RBatis) {
             career_mainline_id TEXT,
             task_category TEXT,
             attributes TEXT,
+            completion_mode TEXT,
+            version INTEGER DEFAULT 0,
+            require_proof INTEGER DEFAULT 0,
+            stake TEXT,
+            retro_completed INTEGER DEFAULT 0,
+            requires_confirmation INTEGER DEFAULT 0,
+            challenge_fail_penalty INTEGER,
             FOREIGN KEY (user_id) REFERENCES user (id),
             FOREIGN KEY (parent_task_id) REFERENCES task (id)
         )
@@ -567,6 +471,9 @@ async fn create_tables(rb: &RBatis) {
             user_id TEXT,
             role TEXT,
             content TEXT,
+            source TEXT DEFAULT 'client',
+            expert_name TEXT,
+            attachment_id TEXT,
             created_at TEXT,
             FOREIGN KEY (user_id) REFERENCES user (id)
         )
@@ -598,6 +505,11 @@ async fn create_tables(rb: &RBatis) {
             adventure_days INTEGER DEFAULT 1,
             consecutive_login_days INTEGER DEFAULT 1,
             persona_type TEXT DEFAULT 'internal',
+            leaderboard_visible INTEGER DEFAULT 0,
+            coins INTEGER DEFAULT 0,
+            profile_visibility TEXT DEFAULT 'private',
+            profile_handle TEXT,
+            profile_token TEXT,
             created_at TEXT,
             updated_at TEXT,
             FOREIGN KEY (user_id) REFERENCES user (id)
@@ -677,7 +589,7 @@ async fn create_tables(rb: &RBatis) {
             focus INTEGER DEFAULT 50,
             adaptability INTEGER DEFAULT 50,
             created_at TEXT,
-            UNIQUE(user_id, year, week_number),
+            UNIQUE(user_id, week_start_date),
             FOREIGN KEY (user_id) REFERENCES user (id)
         )
         "#,
@@ -703,8 +615,8 @@ async fn create_tables(rb: &RBatis) {
             workstyle_results TEXT NOT NULL,
             completed_at TEXT NOT NULL,
             is_active BOOLEAN DEFAULT TRUE,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
+            updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
             FOREIGN KEY (user_id) REFERENCES user (id)
         )
         "#,
@@ -720,8 +632,8 @@ async fn create_tables(rb: &RBatis) {
             estimated_completion_months INTEGER,
             status TEXT DEFAULT 'active',
             progress_percentage REAL DEFAULT 0.0,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
+            updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
             FOREIGN KEY (user_id) REFERENCES user (id),
             FOREIGN KEY (quiz_result_id) REFERENCES quiz_results (id)
         )
@@ -732,11 +644,565 @@ async fn create_tables(rb: &RBatis) {
             id TEXT PRIMARY KEY,
             achievement_id TEXT UNIQUE NOT NULL,
             completion_count INTEGER DEFAULT 0,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
+            updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
             FOREIGN KEY (achievement_id) REFERENCES achievement (id)
         )
         "#,
+        // 技能別名表
+        r#"
+        CREATE TABLE IF NOT EXISTS skill_alias (
+            id TEXT PRIMARY KEY,
+            alias TEXT UNIQUE NOT NULL,
+            canonical_name TEXT NOT NULL,
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'))
+        )
+        "#,
+        // 通知歷史表
+        r#"
+        CREATE TABLE IF NOT EXISTS notification_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            title TEXT,
+            body TEXT,
+            data TEXT,
+            pushed INTEGER DEFAULT 0,
+            seen_at TEXT,
+            read_at TEXT,
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 好友關係表
+        r#"
+        CREATE TABLE IF NOT EXISTS friendship (
+            id TEXT PRIMARY KEY,
+            requester_id TEXT NOT NULL,
+            addressee_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(requester_id, addressee_id),
+            FOREIGN KEY (requester_id) REFERENCES user (id),
+            FOREIGN KEY (addressee_id) REFERENCES user (id)
+        )
+        "#,
+        // 共享任務參與者表
+        r#"
+        CREATE TABLE IF NOT EXISTS task_participant (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            created_at TEXT,
+            UNIQUE(task_id, user_id),
+            FOREIGN KEY (task_id) REFERENCES task (id),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 共享任務完成紀錄表（all 模式逐人記錄）
+        r#"
+        CREATE TABLE IF NOT EXISTS task_completion (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            completed_at TEXT,
+            UNIQUE(task_id, user_id),
+            FOREIGN KEY (task_id) REFERENCES task (id),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 冪等鍵表（避免重送請求重複建立資源）
+        r#"
+        CREATE TABLE IF NOT EXISTS idempotency_key (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            idempotency_key TEXT NOT NULL,
+            endpoint TEXT,
+            request_fingerprint TEXT,
+            resource_id TEXT,
+            response_status INTEGER,
+            response_body TEXT,
+            created_at TEXT,
+            expires_at TEXT,
+            UNIQUE(user_id, idempotency_key)
+        )
+        "#,
+        // 屬性變化紀錄表
+        r#"
+        CREATE TABLE IF NOT EXISTS attribute_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            attribute TEXT NOT NULL,
+            old_value INTEGER,
+            new_value INTEGER,
+            delta INTEGER,
+            source TEXT,
+            task_id TEXT,
+            created_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 登入裝置（session）表
+        r#"
+        CREATE TABLE IF NOT EXISTS user_session (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            device_name TEXT,
+            user_agent TEXT,
+            ip_address TEXT,
+            created_at TEXT,
+            last_seen_at TEXT,
+            expires_at TEXT,
+            revoked_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 稽核日誌表（不設外鍵：紀錄需保留，且可能對應不存在的帳號）
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id TEXT PRIMARY KEY,
+            user_id TEXT,
+            action TEXT NOT NULL,
+            ip_address TEXT,
+            detail TEXT,
+            created_at TEXT
+        )
+        "#,
+        // AI 每日使用次數（依使用者時區日期與類別計算額度）
+        r#"
+        CREATE TABLE IF NOT EXISTS ai_usage_daily (
+            user_id TEXT NOT NULL,
+            usage_date TEXT NOT NULL,
+            category TEXT NOT NULL,
+            request_count INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT,
+            PRIMARY KEY (user_id, usage_date, category)
+        )
+        "#,
+        // 管理員調整的個別使用者 AI 每日上限
+        r#"
+        CREATE TABLE IF NOT EXISTS ai_quota_override (
+            user_id TEXT NOT NULL,
+            category TEXT NOT NULL,
+            daily_limit INTEGER NOT NULL,
+            updated_at TEXT,
+            PRIMARY KEY (user_id, category)
+        )
+        "#,
+        // AI 請求紀錄（實際使用的模型、是否為單次覆寫）
+        r#"
+        CREATE TABLE IF NOT EXISTS ai_usage_log (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            category TEXT NOT NULL,
+            endpoint TEXT,
+            provider TEXT,
+            model TEXT,
+            is_override INTEGER DEFAULT 0,
+            response_status INTEGER,
+            created_at TEXT
+        )
+        "#,
+        // 任務附件（完成證明）
+        r#"
+        CREATE TABLE IF NOT EXISTS task_attachment (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            original_name TEXT,
+            stored_name TEXT NOT NULL,
+            mime_type TEXT,
+            size_bytes INTEGER,
+            sha256 TEXT,
+            created_at TEXT
+        )
+        "#,
+        // 聊天訊息附帶的圖片
+        r#"
+        CREATE TABLE IF NOT EXISTS chat_attachment (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            original_name TEXT,
+            stored_name TEXT NOT NULL,
+            mime_type TEXT,
+            size_bytes INTEGER,
+            sha256 TEXT,
+            created_at TEXT
+        )
+        "#,
+        // 專注（番茄鐘）時段
+        r#"
+        CREATE TABLE IF NOT EXISTS focus_session (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            task_id TEXT,
+            duration_minutes INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            experience_gained INTEGER DEFAULT 0,
+            started_at TEXT NOT NULL,
+            ended_at TEXT
+        )
+        "#,
+        // 任務取消紀錄（超過取消次數上限後需填寫原因）
+        r#"
+        CREATE TABLE IF NOT EXISTS cancellation_log (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            task_title TEXT,
+            cancel_count INTEGER NOT NULL,
+            reason TEXT,
+            created_at TEXT NOT NULL
+        )
+        "#,
+        // 每日三任務（當日選出的任務與重抽次數）
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_quest (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            quest_date TEXT NOT NULL,
+            picks TEXT NOT NULL,
+            skipped_task_ids TEXT,
+            reroll_count INTEGER DEFAULT 0,
+            bonus_awarded INTEGER DEFAULT 0,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, quest_date)
+        )
+        "#,
+        // 外部活動匯入：整合密鑰、活動類型與重複性任務的對應、已匯入的活動（依來源 external_id 去重）
+        r#"
+        CREATE TABLE IF NOT EXISTS integration_secret (
+            user_id TEXT PRIMARY KEY,
+            secret TEXT NOT NULL,
+            created_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS activity_mapping (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            activity_type TEXT NOT NULL,
+            task_id TEXT NOT NULL,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, activity_type)
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS integration_event (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            source TEXT NOT NULL,
+            external_id TEXT NOT NULL,
+            activity_type TEXT NOT NULL,
+            occurred_at TEXT NOT NULL,
+            metadata TEXT,
+            status TEXT NOT NULL,
+            task_id TEXT,
+            task_date TEXT,
+            created_at TEXT,
+            UNIQUE(user_id, source, external_id)
+        )
+        "#,
+        // 獎勵商店：使用者自訂的獎勵與兌換紀錄（以金幣兌換，不影響等級）
+        r#"
+        CREATE TABLE IF NOT EXISTS reward (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            icon TEXT,
+            cost INTEGER NOT NULL,
+            created_at TEXT,
+            updated_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS reward_redemption (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            reward_id TEXT,
+            reward_name TEXT NOT NULL,
+            cost INTEGER NOT NULL,
+            balance_after INTEGER NOT NULL,
+            created_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS task_comment (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            author TEXT NOT NULL,
+            content TEXT NOT NULL,
+            parent_comment_id TEXT,
+            created_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS user_memory (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            source TEXT NOT NULL,
+            active INTEGER NOT NULL DEFAULT 1,
+            confirmed INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT,
+            updated_at TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS task_snapshot (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            subtasks TEXT NOT NULL,
+            created_at TEXT
+        )
+        "#,
+        // 推送訂閱表
+        r#"
+        CREATE TABLE IF NOT EXISTS push_subscription (
+            id TEXT PRIMARY KEY,
+            user_id TEXT,
+            endpoint TEXT NOT NULL UNIQUE,
+            p256dh_key TEXT NOT NULL,
+            auth_key TEXT NOT NULL,
+            created_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 成就分享連結（擁有者產生的公開 token，可撤銷）
+        r#"
+        CREATE TABLE IF NOT EXISTS achievement_share (
+            id TEXT PRIMARY KEY,
+            user_achievement_id TEXT NOT NULL UNIQUE,
+            user_id TEXT NOT NULL,
+            token TEXT NOT NULL UNIQUE,
+            created_at TEXT,
+            FOREIGN KEY (user_achievement_id) REFERENCES user_achievement (id)
+        )
+        "#,
+        // 教練主動關心設定（使用者自行開啟，預設關閉）
+        r#"
+        CREATE TABLE IF NOT EXISTS coach_checkin_setting (
+            user_id TEXT PRIMARY KEY,
+            enabled INTEGER NOT NULL DEFAULT 0,
+            frequency_days INTEGER,
+            last_checkin_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 任務難度校準結果與經驗倍率設定
+        r#"
+        CREATE TABLE IF NOT EXISTS difficulty_calibration (
+            user_id TEXT PRIMARY KEY,
+            data TEXT,
+            apply_multipliers INTEGER NOT NULL DEFAULT 0,
+            computed_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 使用者設定（各類資料的保留天數，NULL 代表永久保留；聊天快速模式）
+        r#"
+        CREATE TABLE IF NOT EXISTS user_settings (
+            user_id TEXT PRIMARY KEY,
+            chat_retention_days INTEGER,
+            notification_retention_days INTEGER,
+            attribute_history_retention_days INTEGER,
+            chat_fast_mode INTEGER DEFAULT 0,
+            chat_fast_followup INTEGER DEFAULT 1,
+            achievement_autogen INTEGER DEFAULT 0,
+            timezone TEXT,
+            locale TEXT,
+            share_with_coach INTEGER DEFAULT 1,
+            week_start TEXT DEFAULT 'mon',
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 技能經驗值流水（技能詳情的成長歷史）
+        r#"
+        CREATE TABLE IF NOT EXISTS skill_experience_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            skill_id TEXT NOT NULL,
+            experience_gain INTEGER NOT NULL,
+            old_level INTEGER,
+            new_level INTEGER,
+            reason TEXT,
+            created_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id),
+            FOREIGN KEY (skill_id) REFERENCES skill (id)
+        )
+        "#,
+        // 個人 API token（只保存雜湊）
+        r#"
+        CREATE TABLE IF NOT EXISTS api_token (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            token_prefix TEXT,
+            scope TEXT DEFAULT 'read_write',
+            created_at TEXT,
+            last_used_at TEXT,
+            revoked_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 待確認完成的任務（記錄標記完成前的狀態，逾時還原）
+        r#"
+        CREATE TABLE IF NOT EXISTS task_pending_confirmation (
+            task_id TEXT PRIMARY KEY,
+            user_id TEXT,
+            previous_status INTEGER,
+            target_status INTEGER,
+            requested_at TEXT,
+            FOREIGN KEY (task_id) REFERENCES task (id)
+        )
+        "#,
+        // 職業任務生成追蹤（每個 AI 步驟一筆，由每晚彙整清除過期紀錄）
+        r#"
+        CREATE TABLE IF NOT EXISTS career_generation_trace (
+            id TEXT PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            user_id TEXT,
+            step TEXT NOT NULL,
+            step_order INTEGER,
+            model TEXT,
+            prompt_hash TEXT,
+            prompt TEXT,
+            prompt_tokens INTEGER,
+            output_tokens INTEGER,
+            duration_ms INTEGER,
+            output TEXT,
+            error TEXT,
+            created_at TEXT
+        )
+        "#,
+        // 背景工作狀態（長時間工作立即回傳工作 ID，客戶端輪詢進度與結果）
+        r#"
+        CREATE TABLE IF NOT EXISTS background_job (
+            id TEXT PRIMARY KEY,
+            user_id TEXT,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            progress INTEGER DEFAULT 0,
+            result TEXT,
+            error TEXT,
+            created_at TEXT,
+            started_at TEXT,
+            finished_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 任務標籤（與 skill_tags 分開的自訂分類，名稱在同一使用者內不分大小寫唯一）
+        r#"
+        CREATE TABLE IF NOT EXISTS tag (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL COLLATE NOCASE,
+            color TEXT,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, name),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS task_tag (
+            task_id TEXT NOT NULL,
+            tag_id TEXT NOT NULL,
+            created_at TEXT,
+            PRIMARY KEY (task_id, tag_id),
+            FOREIGN KEY (task_id) REFERENCES task (id),
+            FOREIGN KEY (tag_id) REFERENCES tag (id)
+        )
+        "#,
+        // 月報（保存彙整結果指紋與 AI 回顧，數字未變時不重複呼叫 AI）
+        r#"
+        CREATE TABLE IF NOT EXISTS monthly_report (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            month TEXT NOT NULL,
+            fingerprint TEXT,
+            report TEXT,
+            ai_summary TEXT,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(user_id, month),
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 職業規劃審核佇列（接受職業規劃後、提交前暫存 AI 產生的任務）
+        r#"
+        CREATE TABLE IF NOT EXISTS career_review (
+            mainline_id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            selected_career TEXT NOT NULL,
+            learning_summary TEXT,
+            estimated_months INTEGER,
+            achievements TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            result TEXT,
+            created_at TEXT,
+            committed_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 拒絕的項目保留拒絕原因，供改善職業任務提示詞分析
+        r#"
+        CREATE TABLE IF NOT EXISTS career_review_item (
+            id TEXT PRIMARY KEY,
+            mainline_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            task_group TEXT NOT NULL,
+            item_order INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            difficulty INTEGER NOT NULL,
+            experience INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            rejection_reason TEXT,
+            task_id TEXT,
+            payload TEXT NOT NULL,
+            created_at TEXT,
+            updated_at TEXT,
+            FOREIGN KEY (user_id) REFERENCES user (id)
+        )
+        "#,
+        // 重複性任務每日子任務的每月彙總（壓縮舊子任務後保留完成日期與經驗值）
+        r#"
+        CREATE TABLE IF NOT EXISTS daily_task_summary (
+            id TEXT PRIMARY KEY,
+            parent_task_id TEXT NOT NULL,
+            user_id TEXT,
+            month TEXT NOT NULL,
+            subtask_count INTEGER NOT NULL DEFAULT 0,
+            completed_count INTEGER NOT NULL DEFAULT 0,
+            completed_day_mask INTEGER NOT NULL DEFAULT 0,
+            experience_total INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT,
+            updated_at TEXT,
+            UNIQUE(parent_task_id, month)
+        )
+        "#,
+        // VAPID 金鑰（管理員輪替後保存，重置資料庫時保留）
+        r#"
+        CREATE TABLE IF NOT EXISTS vapid_key (
+            id TEXT PRIMARY KEY,
+            public_key TEXT NOT NULL,
+            private_key_pem TEXT NOT NULL,
+            created_at TEXT
+        )
+        "#,
     ];
 
     for (i, sql) in tables.iter().enumerate() {
@@ -749,31 +1215,6 @@ async fn create_tables(rb: &RBatis) {
     log::info!("所有資料庫表建立完成");
 }
 
-/// 配置推送通知相關路由（僅在啟用 push-notifications feature 時）
-#[cfg(feature = "push-notifications")]
-fn configure_push_routes(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg
-        // 推送通知路由
-        .route("/api/push/subscribe", web::post().to(subscribe_push))
-        .route("/api/push/unsubscribe", web::post().to(unsubscribe_push))
-        .route("/api/push/test/{user_id}", web::post().to(send_test_push))
-        .route("/api/push/vapid-public-key", web::get().to(get_vapid_public_key))
-        .route("/api/push/subscriptions", web::get().to(get_all_subscriptions))
-        .route("/api/push/clear-all", web::post().to(clear_all_subscriptions))
-        .route("/api/notifications/test-push/{user_id}", web::post().to(send_delayed_test_push))
-        // 通知設定路由
-        .route("/api/notification-settings/{user_id}", web::get().to(get_notification_settings))
-        .route("/api/notification-settings/{user_id}", web::put().to(update_notification_settings))
-        .route("/api/notifications/preview-morning/{user_id}", web::post().to(preview_morning_notification))
-        .route("/api/notifications/preview-evening/{user_id}", web::post().to(preview_evening_notification));
-}
-
-/// 配置推送通知相關路由的空實現（當未啟用 push-notifications feature 時）
-#[cfg(not(feature = "push-notifications"))]
-fn configure_push_routes(_cfg: &mut actix_web::web::ServiceConfig) {
-    // 推送通知功能未啟用，不配置任何路由
-}
-
 async fn migrate_database(rb: &RBatis) {
     // 創建用戶通知設定表
     let create_table_query = r#"
@@ -788,8 +1229,8 @@ async fn migrate_database(rb: &RBatis) {
             evening_enabled INTEGER DEFAULT 1,
             evening_time TEXT DEFAULT '22:00',
             custom_schedules TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
+            updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')),
             FOREIGN KEY (user_id) REFERENCES user(id)
         )
     "#;
@@ -813,6 +1254,78 @@ async fn migrate_database(rb: &RBatis) {
         "CREATE UNIQUE INDEX IF NOT EXISTS idx_user_email_unique ON user(email)",
         // 添加最後登入日期欄位，用於計算連續登入天數
         "ALTER TABLE user_profile ADD COLUMN last_login_date TEXT",
+        // 勿擾時段
+        "ALTER TABLE user_notification_settings ADD COLUMN quiet_hours_start TEXT",
+        "ALTER TABLE user_notification_settings ADD COLUMN quiet_hours_end TEXT",
+        // 排行榜公開設定
+        "ALTER TABLE user_profile ADD COLUMN leaderboard_visible INTEGER DEFAULT 0",
+        // 好友提醒設定
+        "ALTER TABLE user_notification_settings ADD COLUMN notify_partner_on_miss INTEGER DEFAULT 0",
+        // 通知類別開關（預設全部開啟）
+        r#"ALTER TABLE user_notification_settings ADD COLUMN categories TEXT DEFAULT '{"achievement":true,"custom":true,"due_reminder":true,"evening":true,"morning":true,"streak_risk":true}'"#,
+        // 共享任務完成條件
+        "ALTER TABLE task ADD COLUMN completion_mode TEXT",
+        // 任務樂觀鎖版本號
+        "ALTER TABLE task ADD COLUMN version INTEGER DEFAULT 0",
+        // 任務完成前是否必須上傳附件
+        "ALTER TABLE task ADD COLUMN require_proof INTEGER DEFAULT 0",
+        // 每位使用者同時只能有一個進行中的專注時段
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_focus_session_active ON focus_session(user_id) WHERE status = 'active'",
+        // 獎勵商店金幣（與經驗值 1:1 累積，兌換獎勵時扣除）
+        "ALTER TABLE user_profile ADD COLUMN coins INTEGER DEFAULT 0",
+        // JWT 版本號（登出所有裝置時遞增）
+        "ALTER TABLE user ADD COLUMN token_version INTEGER DEFAULT 0",
+        // 事件已送達使用者的時間（未送達的事件會附在回應中）
+        "ALTER TABLE notification_history ADD COLUMN seen_at TEXT",
+        // 聊天訊息建立者（舊資料無法判斷來源，一律視為前端保存）
+        "ALTER TABLE chat_message ADD COLUMN source TEXT DEFAULT 'client'",
+        // 通知中心已讀狀態
+        "ALTER TABLE notification_history ADD COLUMN read_at TEXT",
+        "CREATE INDEX IF NOT EXISTS idx_notification_history_user_created ON notification_history(user_id, created_at)",
+        // 公開個人檔案（預設不公開；代號全站唯一，未公開連結憑隨機 token 存取）
+        "ALTER TABLE user_profile ADD COLUMN profile_visibility TEXT DEFAULT 'private'",
+        "ALTER TABLE user_profile ADD COLUMN profile_handle TEXT",
+        "ALTER TABLE user_profile ADD COLUMN profile_token TEXT",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_user_profile_handle ON user_profile(profile_handle)",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_user_profile_token ON user_profile(profile_token)",
+        // 挑戰任務賭注說明
+        "ALTER TABLE task ADD COLUMN stake TEXT",
+        // 挑戰失敗改用獨立狀態 Failed(8)，舊資料中期滿未達標的挑戰一併轉換
+        "UPDATE task SET status = 8 WHERE task_type = 'challenge' AND parent_task_id IS NULL AND status = 7",
+        // 技能詳情依技能分頁查詢經驗值流水
+        "CREATE INDEX IF NOT EXISTS idx_skill_experience_history_skill_created ON skill_experience_history(skill_id, created_at)",
+        // 重複性任務事後補記完成的標記（分析時可區分）
+        "ALTER TABLE task ADD COLUMN retro_completed INTEGER DEFAULT 0",
+        "ALTER TABLE chat_message ADD COLUMN expert_name TEXT",
+        "ALTER TABLE user_settings ADD COLUMN chat_fast_mode INTEGER DEFAULT 0",
+        "ALTER TABLE user_settings ADD COLUMN chat_fast_followup INTEGER DEFAULT 1",
+        "ALTER TABLE user_settings ADD COLUMN achievement_autogen INTEGER DEFAULT 0",
+        "ALTER TABLE user_settings ADD COLUMN timezone TEXT",
+        "ALTER TABLE user_settings ADD COLUMN locale TEXT",
+        "ALTER TABLE task ADD COLUMN requires_confirmation INTEGER DEFAULT 0",
+        "ALTER TABLE user_settings ADD COLUMN share_with_coach INTEGER DEFAULT 1",
+        // 依生成工作查詢職業任務生成追蹤
+        "CREATE INDEX IF NOT EXISTS idx_career_generation_trace_job ON career_generation_trace(job_id, step_order)",
+        "ALTER TABLE user_settings ADD COLUMN week_start TEXT DEFAULT 'mon'",
+        "CREATE INDEX IF NOT EXISTS idx_background_job_user ON background_job(user_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_task_tag_tag ON task_tag(tag_id)",
+        "CREATE INDEX IF NOT EXISTS idx_career_review_item_mainline ON career_review_item(mainline_id, item_order)",
+        // 挑戰任務建立時記錄失敗扣除經驗值（NULL 的舊挑戰結算時採用目前設定）
+        "ALTER TABLE task ADD COLUMN challenge_fail_penalty INTEGER",
+        // 聊天訊息附帶的圖片
+        "ALTER TABLE chat_message ADD COLUMN attachment_id TEXT",
+        "CREATE INDEX IF NOT EXISTS idx_chat_attachment_user ON chat_attachment(user_id)",
+        // 父任務列表的子任務統計（涵蓋索引，統計時不需讀取資料列）
+        "CREATE INDEX IF NOT EXISTS idx_task_parent ON task(parent_task_id, status, task_date)",
+        // 未對應活動列表
+        "CREATE INDEX IF NOT EXISTS idx_integration_event_status ON integration_event(user_id, status, occurred_at)",
+        // 任務取消統計
+        "CREATE INDEX IF NOT EXISTS idx_cancellation_log_user ON cancellation_log(user_id, created_at)",
+        // 教練記憶
+        "CREATE INDEX IF NOT EXISTS idx_user_memory_user ON user_memory(user_id, created_at)",
+        // 注意：task_date 自此改以使用者時區（UTC+8，見 local_date.rs）計算。
+        // 舊資料中於台灣時間 00:00~08:00 產生的每日子任務，task_date 可能比實際早一天，
+        // 不做自動修正（無法區分使用者手動指定的日期），必要時請依 created_at 人工校正。
     ];
 
     // SQLite 不支援直接修改欄位約束，需要重建表
@@ -883,6 +1396,91 @@ async fn migrate_database(rb: &RBatis) {
             }
         }
     }
+    normalize_chat_roles(rb).await;
+    normalize_empty_task_dates(rb).await;
+    if let Err(e) = week_start::migrate_weekly_snapshots(rb).await {
+        log::warn!("遷移週屬性快照失敗: {}", e);
+    }
+    // 把 *_at 欄位中的舊日期時間格式改寫為 RFC 3339
+    match datetime_format::normalize_stored_datetimes(rb).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("已將 {} 個日期時間欄位改為 RFC 3339", count),
+        Err(e) => log::warn!("統一日期時間格式失敗: {}", e),
+    }
+    // 上次程序結束時仍在排隊或執行的背景工作不會自動恢復
+    match job_runner::mark_interrupted(rb).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("已將 {} 個未完成的背景工作標記為中斷", count),
+        Err(e) => log::warn!("標記中斷的背景工作失敗: {}", e),
+    }
+    // 修正舊資料中拼寫不一的任務類型（例如 dailyrecurring）
+    match task_types::normalize_stored_types(rb).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("已修正 {} 個任務的任務類型", count),
+        Err(e) => log::warn!("修正任務類型失敗: {}", e),
+    }
+    // 將舊資料中的自由文字技能分類對應到固定分類
+    match skill_categories::normalize_stored_categories(rb).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("已修正 {} 個技能的分類", count),
+        Err(e) => log::warn!("修正技能分類失敗: {}", e),
+    }
+    // 補齊舊版以懶初始化建立、尚缺遊戲化資料的使用者
+    match services::user_activity::backfill_missing_rows(rb).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("已為 {} 位使用者補齊遊戲化資料", count),
+        Err(e) => log::warn!("補齊使用者遊戲化資料失敗: {}", e),
+    }
     log::info!("資料庫遷移完成");
 }
 
+// 舊程式曾在 task 的日期欄位寫入空字串，統一改為 NULL（已修正的資料不會再被更新）
+async fn normalize_empty_task_dates(rb: &RBatis) {
+    let columns = ["due_date", "task_date", "start_date", "end_date", "last_cancelled_at", "created_at", "updated_at"];
+    for column in columns {
+        let sql = format!("UPDATE task SET {column} = NULL WHERE {column} IS NOT NULL AND TRIM({column}) = ''");
+        match rb.exec(&sql, vec![]).await {
+            Ok(result) if result.rows_affected > 0 => {
+                log::info!("已將 {} 筆任務的空字串 {} 改為 NULL", result.rows_affected, column)
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("修正任務空字串 {} 失敗: {}", column, e),
+        }
+    }
+}
+
+// 將舊資料中無效的聊天角色（例如拼錯的 assistnat）修正為最接近的有效角色
+async fn normalize_chat_roles(rb: &RBatis) {
+    #[derive(serde::Deserialize)]
+    struct RoleRow {
+        id: String,
+        role: Option<String>,
+    }
+
+    let valid = models::ChatRole::all_valid_strings();
+    let placeholders = vec!["?"; valid.len()].join(", ");
+    let sql = format!("SELECT id, role FROM chat_message WHERE role IS NULL OR role NOT IN ({})", placeholders);
+    let args = valid.iter().map(|r| rbs::Value::String(r.to_string())).collect();
+    let rows: Vec<RoleRow> = match rb.query_decode(&sql, args).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            log::warn!("檢查聊天訊息角色失敗: {}", e);
+            return;
+        }
+    };
+
+    for row in rows {
+        let role = models::ChatRole::closest(row.role.as_deref().unwrap_or(""));
+        match rb
+            .exec(
+                "UPDATE chat_message SET role = ? WHERE id = ?",
+                vec![rbs::Value::String(role.as_str().to_string()), rbs::Value::String(row.id.clone())],
+            )
+            .await
+        {
+            Ok(_) => log::info!("聊天訊息 {} 的角色 {:?} 已修正為 {}", row.id, row.role, role.as_str()),
+            Err(e) => log::warn!("修正聊天訊息 {} 的角色失敗: {}", row.id, e),
+        }
+    }
+}
+
@@ -30,6 +30,10 @@ pub enum AchievementRequirementType {
     FocusAttribute,         // 專注力屬性達成
     #[serde(rename = "adaptability_attribute")]
     AdaptabilityAttribute,  // 適應力屬性達成
+    #[serde(rename = "focus_session_complete")]
+    FocusSessionComplete,   // 完成專注時段次數
+    #[serde(rename = "daily_quest_complete")]
+    DailyQuestComplete,     // 完成每日三任務次數
 }
 
 impl AchievementRequirementType {
@@ -47,6 +51,8 @@ impl AchievementRequirementType {
             "social_attribute" => Some(AchievementRequirementType::SocialAttribute),
             "focus_attribute" => Some(AchievementRequirementType::FocusAttribute),
             "adaptability_attribute" => Some(AchievementRequirementType::AdaptabilityAttribute),
+            "focus_session_complete" => Some(AchievementRequirementType::FocusSessionComplete),
+            "daily_quest_complete" => Some(AchievementRequirementType::DailyQuestComplete),
             _ => None,
         }
     }
@@ -65,6 +71,8 @@ impl AchievementRequirementType {
             AchievementRequirementType::SocialAttribute => "social_attribute",
             AchievementRequirementType::FocusAttribute => "focus_attribute",
             AchievementRequirementType::AdaptabilityAttribute => "adaptability_attribute",
+            AchievementRequirementType::FocusSessionComplete => "focus_session_complete",
+            AchievementRequirementType::DailyQuestComplete => "daily_quest_complete",
         }
     }
 
@@ -82,6 +90,8 @@ impl AchievementRequirementType {
             "social_attribute",
             "focus_attribute",
             "adaptability_attribute",
+            "focus_session_complete",
+            "daily_quest_complete",
         ]
     }
 }
@@ -97,6 +107,8 @@ pub enum TaskStatus {
     DailyInProgress = 5,  // 每日任務進行中
     DailyCompleted = 6,   // 每日任務已完成
     DailyNotCompleted = 7, // 每日任務未完成
+    Failed = 8,           // 挑戰失敗（只由截止日結算設定）
+    PendingConfirmation = 9, // 待確認完成（需要二次確認的任務，逾時自動還原）
 }
 
 impl TaskStatus {
@@ -111,6 +123,8 @@ impl TaskStatus {
             5 => Some(TaskStatus::DailyInProgress),
             6 => Some(TaskStatus::DailyCompleted),
             7 => Some(TaskStatus::DailyNotCompleted),
+            8 => Some(TaskStatus::Failed),
+            9 => Some(TaskStatus::PendingConfirmation),
             _ => None,
         }
     }
@@ -126,6 +140,8 @@ impl TaskStatus {
             TaskStatus::DailyInProgress => 5,
             TaskStatus::DailyCompleted => 6,
             TaskStatus::DailyNotCompleted => 7,
+            TaskStatus::Failed => 8,
+            TaskStatus::PendingConfirmation => 9,
         }
     }
 
@@ -140,6 +156,8 @@ impl TaskStatus {
             TaskStatus::DailyInProgress => "daily_in_progress",
             TaskStatus::DailyCompleted => "daily_completed",
             TaskStatus::DailyNotCompleted => "daily_not_completed",
+            TaskStatus::Failed => "failed",
+            TaskStatus::PendingConfirmation => "pending_confirmation",
         }
     }
 
@@ -154,41 +172,20 @@ impl TaskStatus {
             "daily_in_progress" => Some(TaskStatus::DailyInProgress),
             "daily_completed" => Some(TaskStatus::DailyCompleted),
             "daily_not_completed" => Some(TaskStatus::DailyNotCompleted),
+            "failed" => Some(TaskStatus::Failed),
+            "pending_confirmation" => Some(TaskStatus::PendingConfirmation),
             _ => None,
         }
     }
 }
 
-// 自定義反序列化函數處理空字串的 DateTime
-fn deserialize_optional_datetime<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+// 文字日期欄位（例如 task_date）：空字串或只有空白視為未設定
+fn deserialize_optional_date_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    use chrono::NaiveDateTime;
-
     let opt: Option<String> = Option::deserialize(deserializer)?;
-    match opt {
-        Some(s) if s.is_empty() => Ok(None),
-        Some(s) => {
-            // 嘗試解析 ISO 8601 格式（RFC3339）
-            if let Ok(dt) = s.parse::<DateTime<Utc>>() {
-                return Ok(Some(dt));
-            }
-
-            // 嘗試解析 SQLite datetime 格式：YYYY-MM-DD HH:MM:SS
-            if let Ok(naive) = NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S") {
-                return Ok(Some(DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc)));
-            }
-
-            // 嘗試解析 SQLite datetime 格式（帶毫秒）：YYYY-MM-DD HH:MM:SS.fff
-            if let Ok(naive) = NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S%.f") {
-                return Ok(Some(DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc)));
-            }
-
-            Err(serde::de::Error::custom(format!("無法解析日期時間格式: {}", s)))
-        },
-        None => Ok(None),
-    }
+    Ok(opt.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()))
 }
 
 // 自定義反序列化函數處理 requirement_type 字段
@@ -237,6 +234,22 @@ where
     }
 }
 
+// 自定義反序列化函數處理存成 JSON 字串的欄位（SQLite 驅動可能已解析為物件）
+fn deserialize_json_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+    use serde_json::Value;
+
+    let opt: Option<Value> = Option::deserialize(deserializer)?;
+    match opt {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(other) => Ok(Some(serde_json::to_string(&other).map_err(Error::custom)?)),
+    }
+}
+
 // 自定義反序列化函數處理 SQLite 的 boolean 欄位（可能是 integer 0/1）
 fn deserialize_optional_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
 where
@@ -343,7 +356,9 @@ pub struct User {
     pub name: Option<String>,
     pub email: Option<String>,
     pub password_hash: Option<String>, // 密碼哈希
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(User{});
@@ -367,6 +382,9 @@ pub struct CreateUserRequest {
 
     #[validate(custom(function = "validate_password_strength"))]
     pub password: String,
+
+    // 人機驗證 token（啟用 REGISTRATION_CAPTCHA_PROVIDER 時必填）
+    pub captcha_token: Option<String>,
 }
 
 // 更新使用者的請求
@@ -411,29 +429,37 @@ pub struct Task {
     pub parent_task_id: Option<String>,
     pub is_parent_task: Option<i32>,
     pub task_order: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub due_date: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
     pub is_recurring: Option<i32>,
     pub recurrence_pattern: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub start_date: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub end_date: Option<DateTime<Utc>>,
     pub completion_target: Option<f64>,
     pub completion_rate: Option<f64>,
+    #[serde(deserialize_with = "deserialize_optional_date_text", default)]
     pub task_date: Option<String>,
     pub cancel_count: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub last_cancelled_at: Option<DateTime<Utc>>,
     #[serde(deserialize_with = "deserialize_skill_tags", default)]
     pub skill_tags: Option<Vec<String>>,
     pub career_mainline_id: Option<String>,
     pub task_category: Option<String>,
     pub attributes: Option<serde_json::Value>,  // 任務完成時獲得的屬性獎勵 {"intelligence": 5, "creativity": 3}
+    pub completion_mode: Option<String>,  // 共享任務完成條件：any（任一參與者完成）/ all（全員完成），非共享任務為 NULL
+    pub version: Option<i32>,  // 樂觀鎖版本號，每次透過 update_task 更新成功後遞增
+    pub require_proof: Option<i32>,  // 1 = 完成前必須上傳附件（完成證明）
+    pub stake: Option<String>,  // 挑戰任務的賭注說明（例如「失敗請全組喝飲料」）
+    pub retro_completed: Option<i32>,  // 1 = 事後補記完成（重複性任務補記過去日期）
+    pub requires_confirmation: Option<i32>,  // 1 = 標記完成後需再確認一次才算完成（職業主線階段任務等）
+    pub challenge_fail_penalty: Option<i32>,  // 挑戰任務建立時記錄的失敗扣除經驗值（之後調整設定不影響既有挑戰）
 }
 crud!(Task{});
 
@@ -459,13 +485,24 @@ pub struct Skill {
     pub experience: Option<i32>,
     pub max_experience: Option<i32>,
     pub icon: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(Skill{});
 
+// 技能別名（別名 -> 標準技能名稱），用於正規化 AI 生成的技能標籤
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkillAlias {
+    pub id: Option<String>,
+    pub alias: Option<String>,
+    pub canonical_name: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(SkillAlias{});
+
 // Chat message model
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -473,11 +510,95 @@ pub struct ChatMessage {
     pub user_id: Option<String>,
     pub role: Option<String>,
     pub content: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    // 訊息建立者：client（前端透過 API 保存）或 backend（後端自行寫入）
+    #[serde(default)]
+    pub source: Option<String>,
+    // AI 回覆時匹配到的專家名稱（僅後端寫入的 assistant 訊息）
+    #[serde(default)]
+    pub expert_name: Option<String>,
+    // 使用者訊息附帶的圖片（chat_attachment.id，下載路徑 /api/chat/attachments/{id}）
+    #[serde(default)]
+    pub attachment_id: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
 }
 crud!(ChatMessage{});
 
+pub const CHAT_SOURCE_CLIENT: &str = "client";
+pub const CHAT_SOURCE_BACKEND: &str = "backend";
+
+// 聊天訊息角色
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    User,
+    Assistant,
+    Coach,
+    System,
+}
+
+impl ChatRole {
+    pub const ALL: [ChatRole; 4] = [ChatRole::User, ChatRole::Assistant, ChatRole::Coach, ChatRole::System];
+
+    pub fn from_string(v: &str) -> Option<ChatRole> {
+        match v {
+            "user" => Some(ChatRole::User),
+            "assistant" => Some(ChatRole::Assistant),
+            "coach" => Some(ChatRole::Coach),
+            "system" => Some(ChatRole::System),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+            ChatRole::Coach => "coach",
+            ChatRole::System => "system",
+        }
+    }
+
+    pub fn all_valid_strings() -> Vec<&'static str> {
+        ChatRole::ALL.iter().map(|r| r.as_str()).collect()
+    }
+
+    /// 舊資料中無效的角色（拼錯、大小寫不同、別名）對應到最接近的有效角色；無法判斷時視為 user
+    pub fn closest(v: &str) -> ChatRole {
+        let normalized = v.trim().to_lowercase();
+        if let Some(role) = ChatRole::from_string(&normalized) {
+            return role;
+        }
+        match normalized.as_str() {
+            "ai" | "bot" | "model" | "gpt" | "chatgpt" => return ChatRole::Assistant,
+            "human" | "me" => return ChatRole::User,
+            "expert" | "trainer" => return ChatRole::Coach,
+            _ => {}
+        }
+        ChatRole::ALL
+            .iter()
+            .map(|role| (edit_distance(&normalized, role.as_str()), *role))
+            .filter(|(distance, role)| *distance <= role.as_str().len() / 3)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, role)| role)
+            .unwrap_or(ChatRole::User)
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 // User profile and attributes
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserProfile {
@@ -491,9 +612,12 @@ pub struct UserProfile {
     pub consecutive_login_days: Option<i32>,
     pub last_login_date: Option<String>,
     pub persona_type: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(deserialize_with = "deserialize_optional_bool", default)]
+    pub leaderboard_visible: Option<bool>, // 是否公開於排行榜（預設不公開）
+    pub coins: Option<i32>, // 獎勵商店金幣，與經驗值獲得量 1:1 累積，兌換時扣除（不影響等級）
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(UserProfile{});
@@ -508,9 +632,9 @@ pub struct UserAttributes {
     pub social: Option<i32>,
     pub focus: Option<i32>,
     pub adaptability: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(UserAttributes{});
@@ -524,9 +648,9 @@ pub struct DailyProgress {
     pub total_tasks: Option<i32>,
     pub experience_gained: Option<i32>,
     pub attributes_gained: Option<serde_json::Value>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(DailyProgress{});
@@ -540,9 +664,9 @@ pub struct RecurringTaskTemplate {
     pub difficulty: Option<i32>,
     pub experience: Option<i32>,
     pub task_order: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
     #[serde(deserialize_with = "deserialize_skill_tags", default)]
     pub skill_tags: Option<Vec<String>>,
@@ -563,7 +687,7 @@ pub struct Achievement {
     pub experience_reward: Option<i32>,
     pub career_mainline_id: Option<String>,  // 關聯的職業主線 ID
     pub related_task_id: Option<String>,     // 關聯的任務 ID
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
 }
 crud!(Achievement{});
@@ -573,9 +697,9 @@ pub struct AchievementStats {
     pub id: Option<String>,
     pub achievement_id: Option<String>,
     pub completion_count: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(AchievementStats{});
@@ -585,7 +709,7 @@ pub struct UserAchievement {
     pub id: Option<String>,
     pub user_id: Option<String>,
     pub achievement_id: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub achieved_at: Option<DateTime<Utc>>,
     pub progress: Option<i32>,
 }
@@ -604,11 +728,58 @@ pub struct WeeklyAttributeSnapshot {
     pub social: Option<i32>,
     pub focus: Option<i32>,
     pub adaptability: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
 }
 crud!(WeeklyAttributeSnapshot{});
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttributeHistory {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub attribute: Option<String>,
+    pub old_value: Option<i32>,
+    pub new_value: Option<i32>,
+    pub delta: Option<i32>,
+    pub source: Option<String>,   // "task" | "manual"
+    pub task_id: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(AttributeHistory{});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserSession {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub device_name: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub last_seen_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+crud!(UserSession{});
+
+// 稽核日誌（detail 為 JSON 字串）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub action: Option<String>,
+    pub ip_address: Option<String>,
+    #[serde(deserialize_with = "deserialize_json_string", default)]
+    pub detail: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(AuditLog{});
+
 // Coach personality
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum CoachPersonalityType {
@@ -657,9 +828,9 @@ pub struct UserCoachPreference {
     pub id: Option<String>,
     pub user_id: Option<String>,
     pub personality_type: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(UserCoachPreference{});
@@ -698,10 +869,28 @@ pub struct DirectPersonalityChatRequest {
     pub personality_type: String,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComparePersonalitiesRequest {
+    pub message: String,
+    pub personalities: Vec<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChatWithPersonalityRequest {
     pub message: String,
     pub user_id: Option<String>,
+    // 附帶的圖片（JSON 請求以 base64 傳送；multipart 請求改用檔案欄位）
+    #[serde(default)]
+    pub attachment: Option<ChatAttachmentInput>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatAttachmentInput {
+    // base64 內容，也接受 data:image/png;base64,... 形式
+    pub data: String,
+    // 未提供時取自 data URL
+    pub mime_type: Option<String>,
+    pub filename: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -747,7 +936,7 @@ pub struct CreateTaskRequest {
 
     pub parent_task_id: Option<String>,
     pub task_order: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub due_date: Option<DateTime<Utc>>,
     pub task_date: Option<String>,
     pub is_recurring: Option<i32>,
@@ -755,13 +944,43 @@ pub struct CreateTaskRequest {
     #[validate(length(max = 50))]
     pub recurrence_pattern: Option<String>,
 
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub start_date: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub end_date: Option<DateTime<Utc>>,
     pub completion_target: Option<f64>,
     pub skill_tags: Option<Vec<String>>,
     pub attributes: Option<serde_json::Value>,
+    // 共享任務完成條件：any / all
+    pub completion_mode: Option<String>,
+    // 完成前必須上傳附件作為證明
+    pub require_proof: Option<bool>,
+    // 挑戰任務的賭注說明
+    #[validate(length(max = 500))]
+    pub stake: Option<String>,
+    // 客戶端請求 ID（冪等鍵，亦可用 Idempotency-Key 標頭）
+    pub client_request_id: Option<String>,
+    // 標記完成後需再確認一次才算完成
+    pub requires_confirmation: Option<bool>,
+    // 建立來源：manual（預設）、import、clone、career；非 manual 不自動生成成就
+    #[validate(length(max = 20))]
+    pub source: Option<String>,
+}
+
+// 可清空欄位的三種狀態：未提供 = None、null = Some(None)、有值 = Some(Some(v))
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Some(Option::deserialize(deserializer)?))
+}
+
+fn deserialize_nullable_datetime<'de, D>(deserializer: D) -> Result<Option<Option<DateTime<Utc>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Some(crate::datetime_format::rfc3339_option::deserialize(deserializer)?))
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Validate)]
@@ -769,8 +988,10 @@ pub struct UpdateTaskRequest {
     #[validate(custom(function = "validate_task_title"))]
     pub title: Option<String>,
 
+    // 以下可清空的欄位：傳 null 代表清空，未提供則保留原值
     #[validate(length(max = 5000))]
-    pub description: Option<String>,
+    #[serde(deserialize_with = "deserialize_nullable", default)]
+    pub description: Option<Option<String>>,
 
     #[validate(range(min = 0, max = 7))]
     pub status: Option<i32>,
@@ -787,9 +1008,37 @@ pub struct UpdateTaskRequest {
     #[validate(range(min = 0, max = 10000))]
     pub experience: Option<i32>,
 
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
-    pub due_date: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "deserialize_nullable_datetime", default)]
+    pub due_date: Option<Option<DateTime<Utc>>>,
     pub task_order: Option<i32>,
+
+    #[validate(length(max = 20))]
+    #[serde(deserialize_with = "deserialize_nullable", default)]
+    pub task_date: Option<Option<String>>,
+
+    #[validate(range(min = 0.0, max = 1.0))]
+    #[serde(deserialize_with = "deserialize_nullable", default)]
+    pub completion_target: Option<Option<f64>>,
+
+    #[serde(deserialize_with = "deserialize_nullable", default)]
+    pub skill_tags: Option<Option<Vec<String>>>,
+
+    #[serde(deserialize_with = "deserialize_nullable", default)]
+    pub attributes: Option<Option<serde_json::Value>>,
+
+    pub require_proof: Option<bool>,
+
+    #[validate(length(max = 500))]
+    #[serde(deserialize_with = "deserialize_nullable", default)]
+    pub stake: Option<Option<String>>,
+
+    pub requires_confirmation: Option<bool>,
+
+    // 需要確認的任務：與完成狀態一起帶 true 時直接完成，不經過待確認
+    pub confirm: Option<bool>,
+
+    // 客戶端最後讀取到的任務版本（樂觀鎖）
+    pub version: Option<i32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Validate)]
@@ -821,6 +1070,23 @@ pub struct CreateSkillRequest {
     pub icon: Option<String>,
 }
 
+// 更新技能基本資料；未帶的欄位保持不變
+#[derive(Clone, Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateSkillRequest {
+    #[validate(length(min = 2, max = 100))]
+    pub name: Option<String>,
+
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+
+    pub category: Option<String>,
+
+    #[validate(length(max = 50))]
+    pub attribute: Option<String>,
+
+    pub icon: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UpdateSkillExperienceRequest {
     pub experience_gain: i32,
@@ -963,12 +1229,12 @@ pub struct QuizResults {
     pub interests_results: Option<String>,
     pub talents_results: Option<String>,
     pub workstyle_results: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub completed_at: Option<DateTime<Utc>>,
     pub is_active: Option<i32>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(QuizResults{});
@@ -984,9 +1250,9 @@ pub struct CareerMainlines {
     pub estimated_completion_months: Option<i32>,
     pub status: Option<String>,
     pub progress_percentage: Option<f64>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(CareerMainlines{});
@@ -999,6 +1265,7 @@ pub struct TaskHistoryItem {
     pub id: String,
     pub title: String,
     pub task_type: String,
+    #[serde(with = "crate::datetime_format::rfc3339")]
     pub completed_at: DateTime<Utc>,
     pub experience: i32,
 }
@@ -1040,9 +1307,9 @@ pub struct PushSubscription {
     pub endpoint: Option<String>,
     pub p256dh_key: Option<String>,
     pub auth_key: Option<String>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(PushSubscription{});
@@ -1097,13 +1364,218 @@ pub struct UserNotificationSettings {
     pub evening_time: Option<String>,
     #[serde(deserialize_with = "deserialize_custom_schedules", default)]
     pub custom_schedules: Option<String>, // JSON string
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    pub quiet_hours_start: Option<String>, // HH:MM，為空表示不啟用勿擾時段
+    pub quiet_hours_end: Option<String>,   // HH:MM，可跨午夜（例如 23:00 ~ 07:00）
+    #[serde(deserialize_with = "deserialize_optional_bool", default)]
+    pub notify_partner_on_miss: Option<bool>, // 晚間總結零完成時提醒好友（預設關閉）
+    #[serde(deserialize_with = "deserialize_json_string", default)]
+    pub categories: Option<String>, // JSON 物件 { 類別: bool }，見 notification_categories
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub created_at: Option<DateTime<Utc>>,
-    #[serde(deserialize_with = "deserialize_optional_datetime", default)]
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 crud!(UserNotificationSettings{});
 
+// 通知歷史紀錄（成就解鎖、升級等事件）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NotificationHistory {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub event_type: Option<String>, // achievement_unlocked, level_up
+    pub title: Option<String>,
+    pub body: Option<String>,
+    #[serde(deserialize_with = "deserialize_json_string", default)]
+    pub data: Option<String>, // JSON string
+    #[serde(deserialize_with = "deserialize_optional_bool", default)]
+    pub pushed: Option<bool>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub seen_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub read_at: Option<DateTime<Utc>>, // 使用者在通知中心讀取的時間
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(NotificationHistory{});
+
+// 好友 / 監督夥伴關係
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Friendship {
+    pub id: Option<String>,
+    pub requester_id: Option<String>,
+    pub addressee_id: Option<String>,
+    pub status: Option<String>, // pending, accepted
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+crud!(Friendship{});
+
+// 共享任務參與者（不含任務擁有者）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskParticipant {
+    pub id: Option<String>,
+    pub task_id: Option<String>,
+    pub user_id: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(TaskParticipant{});
+
+// 任務附件（完成證明）；檔案存放於 {storage_dir}/{user_id}/{stored_name}，user_id 為上傳者
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskAttachment {
+    pub id: Option<String>,
+    pub task_id: Option<String>,
+    pub user_id: Option<String>,
+    pub original_name: Option<String>,
+    pub stored_name: Option<String>,
+    pub mime_type: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub sha256: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(TaskAttachment{});
+
+// 聊天訊息附帶的圖片；檔案存放於 {storage_dir}/chat/{user_id}/{stored_name}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatAttachment {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub original_name: Option<String>,
+    pub stored_name: Option<String>,
+    pub mime_type: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub sha256: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(ChatAttachment{});
+
+// 專注（番茄鐘）時段；status: active / completed / aborted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FocusSession {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub task_id: Option<String>,
+    pub duration_minutes: Option<i32>,
+    pub status: Option<String>,
+    pub experience_gained: Option<i32>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub ended_at: Option<DateTime<Utc>>,
+}
+crud!(FocusSession{});
+
+// 任務取消紀錄；reason 在取消次數超過軟上限後為必填，之前可省略
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CancellationLog {
+    pub id: Option<String>,
+    pub task_id: Option<String>,
+    pub user_id: Option<String>,
+    pub task_title: Option<String>,
+    pub cancel_count: Option<i32>,
+    pub reason: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(CancellationLog{});
+
+// 每日三任務（每位使用者每天一筆）；picks 為 JSON 陣列 [{slot, task_id, rationale, score}]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DailyQuest {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub quest_date: Option<String>,
+    pub picks: Option<serde_json::Value>,
+    pub skipped_task_ids: Option<serde_json::Value>,  // 當日重抽換掉的任務（JSON 陣列），不會再被選中
+    pub reroll_count: Option<i32>,
+    pub bonus_awarded: Option<i32>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+crud!(DailyQuest{});
+
+// 使用者自訂的獎勵（例如「買一杯手搖」= 200 金幣）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reward {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cost: Option<i32>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+crud!(Reward{});
+
+// 獎勵兌換紀錄；保留兌換當下的名稱與花費，獎勵刪除後仍可查詢
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RewardRedemption {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub reward_id: Option<String>,
+    pub reward_name: Option<String>,
+    pub cost: Option<i32>,
+    pub balance_after: Option<i32>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(RewardRedemption{});
+
+// 任務留言串；author: user / coach，最多兩層（留言與其回覆）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskComment {
+    pub id: Option<String>,
+    pub task_id: Option<String>,
+    pub user_id: Option<String>,  // 留言者；教練回覆則為詢問的使用者
+    pub author: Option<String>,
+    pub content: Option<String>,
+    pub parent_comment_id: Option<String>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(TaskComment{});
+
+// 教練記憶：使用者希望教練長期記住的事實；source: user / inferred（AI 提議，需確認才會生效）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserMemory {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
+    pub content: Option<String>,
+    pub source: Option<String>,
+    #[serde(deserialize_with = "deserialize_optional_bool", default)]
+    pub active: Option<bool>,
+    #[serde(deserialize_with = "deserialize_optional_bool", default)]
+    pub confirmed: Option<bool>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+crud!(UserMemory{});
+
+// 任務快照；kind = cancel 時 subtasks 為取消時刪除的子任務（JSON 陣列），重新開始時可還原
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskSnapshot {
+    pub id: Option<String>,
+    pub task_id: Option<String>,
+    pub user_id: Option<String>,
+    pub kind: Option<String>,
+    pub subtasks: Option<serde_json::Value>,
+    #[serde(with = "crate::datetime_format::rfc3339_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+crud!(TaskSnapshot{});
+
 // 更新通知設定請求
 #[derive(Clone, Debug, Deserialize)]
 pub struct UpdateNotificationSettingsRequest {
@@ -1115,6 +1587,11 @@ pub struct UpdateNotificationSettingsRequest {
     pub evening_enabled: Option<bool>,
     pub evening_time: Option<String>,
     pub custom_schedules: Option<Vec<CustomSchedule>>,
+    pub quiet_hours_start: Option<String>,
+    pub quiet_hours_end: Option<String>,
+    pub notify_partner_on_miss: Option<bool>,
+    // 只需帶入要變更的類別，未知類別會被拒絕
+    pub categories: Option<std::collections::BTreeMap<String, bool>>,
 }
 
 // 自定義通知時段
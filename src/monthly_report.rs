@@ -0,0 +1,541 @@
+// 月報：彙整一個月的任務完成、經驗值與技能升級、屬性變化（當月第一與最後一份週快照）、新成就與最長連續天數，
+// 並附上 AI 撰寫的月度回顧
+//
+// 月份起訖依使用者設定的時區（user_settings.timezone）計算。彙整數字每次即時計算；
+// AI 回顧與彙整結果的指紋一起存在 monthly_report，數字沒有變化時直接沿用，不會重複呼叫 AI。
+// 沒有任何活動的月份回傳全為 0 的報告，不呼叫 AI。
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, TimeZone, Utc};
+use rbatis::RBatis;
+use rbs::{value, Value};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ai_service::SharedAIService;
+use crate::ai_tasks::ApiResponse;
+use crate::language::Language;
+use crate::models::TaskStatus;
+
+const ATTRIBUTES: [&str; 6] = ["intelligence", "endurance", "creativity", "social", "focus", "adaptability"];
+// 月報中列出的習慣連續天數筆數
+const MAX_HABIT_STREAKS: usize = 3;
+
+#[derive(Debug, Deserialize)]
+pub struct MonthlyReportQuery {
+    // YYYY-MM，未指定時為上個月
+    pub month: Option<String>,
+    // email：同時寄送到使用者信箱
+    pub deliver: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonthlyAchievement {
+    pub achievement_id: String,
+    pub name: String,
+    pub achieved_at: Option<String>,
+}
+
+/// 重複性任務在當月最長的連續完成天數
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HabitStreak {
+    pub task_id: String,
+    pub title: String,
+    pub days: i64,
+}
+
+/// 月報的彙整數字（AI 回顧的輸入，也是快取的指紋來源）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MonthlyAggregates {
+    pub tasks_completed: i64,
+    pub tasks_completed_by_type: BTreeMap<String, i64>,
+    pub experience_gained: i64,
+    pub skill_experience_gained: i64,
+    pub skill_levels_gained: i64,
+    // 當月最後一份與第一份週快照的差值；快照少於兩份時為 0
+    pub attribute_changes: BTreeMap<String, i64>,
+    pub new_achievements: Vec<MonthlyAchievement>,
+    // 當月有完成任務的最長連續天數
+    pub longest_active_streak: i64,
+    pub best_habit_streaks: Vec<HabitStreak>,
+}
+
+impl MonthlyAggregates {
+    pub fn is_empty(&self) -> bool {
+        self.tasks_completed == 0
+            && self.skill_experience_gained == 0
+            && self.new_achievements.is_empty()
+            && self.attribute_changes.values().all(|delta| *delta == 0)
+    }
+
+    fn fingerprint(&self) -> String {
+        hex::encode(Sha256::digest(serde_json::to_vec(self).unwrap_or_default()))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthlyReport {
+    pub user_id: String,
+    pub month: String,
+    pub timezone: String,
+    // 使用者時區的第一天與最後一天
+    pub first_day: String,
+    pub last_day: String,
+    // 月份尚未結束時為 true，數字仍會變動
+    pub partial: bool,
+    #[serde(flatten)]
+    pub aggregates: MonthlyAggregates,
+    pub ai_summary: Option<String>,
+}
+
+/// 解析 YYYY-MM，回傳當月第一天
+pub fn parse_month(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", value.trim()), "%Y-%m-%d").ok()
+}
+
+/// 月份在指定時區的起訖（UTC，含起點、不含終點）與最後一天
+fn month_bounds(first_day: NaiveDate, tz: FixedOffset) -> (DateTime<Utc>, DateTime<Utc>, NaiveDate) {
+    let next_month = first_day + Months::new(1);
+    let to_utc = |date: NaiveDate| {
+        tz.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .single()
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_default()
+    };
+    (to_utc(first_day), to_utc(next_month), next_month - Duration::days(1))
+}
+
+/// 已排序日期中最長的連續天數
+pub fn longest_run(days: &BTreeSet<NaiveDate>) -> i64 {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        current = match previous {
+            Some(prev) if *day - prev == Duration::days(1) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(*day);
+    }
+    longest
+}
+
+/// 計算使用者某月的彙整數字
+pub async fn compute_aggregates(
+    rb: &RBatis,
+    user_id: &str,
+    first_day: NaiveDate,
+    tz: FixedOffset,
+) -> std::result::Result<MonthlyAggregates, rbatis::Error> {
+    let (start, end, last_day) = month_bounds(first_day, tz);
+    let first = first_day.format("%Y-%m-%d").to_string();
+    let last = last_day.format("%Y-%m-%d").to_string();
+    let mut aggregates = MonthlyAggregates::default();
+
+    // 完成的任務：每日任務以 task_date 為準，其餘以完成（最後更新）時間為準；不含父任務，避免與子任務重複計算
+    let tasks: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT id, title, task_type, experience, task_date, updated_at, parent_task_id FROM task
+             WHERE user_id = ? AND status IN (?, ?) AND COALESCE(is_parent_task, 0) = 0
+               AND ((task_date IS NOT NULL AND task_date >= ? AND task_date <= ?)
+                    OR (task_date IS NULL AND julianday(updated_at) >= julianday(?) AND julianday(updated_at) < julianday(?)))",
+            vec![
+                Value::String(user_id.to_string()),
+                Value::I32(TaskStatus::Completed.to_i32()),
+                Value::I32(TaskStatus::DailyCompleted.to_i32()),
+                Value::String(first.clone()),
+                Value::String(last.clone()),
+                Value::String(start.to_rfc3339()),
+                Value::String(end.to_rfc3339()),
+            ],
+        )
+        .await?;
+    let mut active_days = BTreeSet::new();
+    let mut habit_days: HashMap<String, (String, BTreeSet<NaiveDate>)> = HashMap::new();
+    for task in &tasks {
+        aggregates.tasks_completed += 1;
+        let task_type = task["task_type"].as_str().unwrap_or("other").to_string();
+        *aggregates.tasks_completed_by_type.entry(task_type).or_default() += 1;
+        aggregates.experience_gained += task["experience"].as_i64().unwrap_or(0);
+
+        let day = task["task_date"]
+            .as_str()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .or_else(|| {
+                task["updated_at"]
+                    .as_str()
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                    .map(|t| t.with_timezone(&tz).date_naive())
+            });
+        let Some(day) = day else { continue };
+        active_days.insert(day);
+        if let (Some(parent_id), true) = (task["parent_task_id"].as_str(), task["task_date"].is_string()) {
+            habit_days
+                .entry(parent_id.to_string())
+                .or_insert_with(|| (task["title"].as_str().unwrap_or_default().to_string(), BTreeSet::new()))
+                .1
+                .insert(day);
+        }
+    }
+    aggregates.longest_active_streak = longest_run(&active_days);
+    let mut streaks: Vec<HabitStreak> = habit_days
+        .into_iter()
+        .map(|(task_id, (title, days))| HabitStreak { task_id, title, days: longest_run(&days) })
+        .collect();
+    streaks.sort_by(|a, b| b.days.cmp(&a.days).then_with(|| a.title.cmp(&b.title)));
+    streaks.truncate(MAX_HABIT_STREAKS);
+    aggregates.best_habit_streaks = streaks;
+
+    let skills: serde_json::Value = rb
+        .query_decode(
+            "SELECT COALESCE(SUM(experience_gain), 0) AS experience,
+                    COALESCE(SUM(MAX(COALESCE(new_level, 0) - COALESCE(old_level, 0), 0)), 0) AS levels
+             FROM skill_experience_history
+             WHERE user_id = ? AND julianday(created_at) >= julianday(?) AND julianday(created_at) < julianday(?)",
+            vec![
+                Value::String(user_id.to_string()),
+                Value::String(start.to_rfc3339()),
+                Value::String(end.to_rfc3339()),
+            ],
+        )
+        .await?;
+    aggregates.skill_experience_gained = skills["experience"].as_i64().unwrap_or(0);
+    aggregates.skill_levels_gained = skills["levels"].as_i64().unwrap_or(0);
+
+    let snapshots: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT * FROM weekly_attribute_snapshot
+             WHERE user_id = ? AND week_start_date >= ? AND week_start_date <= ?
+             ORDER BY week_start_date",
+            vec![Value::String(user_id.to_string()), Value::String(first), Value::String(last)],
+        )
+        .await?;
+    for attribute in ATTRIBUTES {
+        let delta = match (snapshots.first(), snapshots.last()) {
+            (Some(first), Some(last)) => last[attribute].as_i64().unwrap_or(0) - first[attribute].as_i64().unwrap_or(0),
+            _ => 0,
+        };
+        aggregates.attribute_changes.insert(attribute.to_string(), delta);
+    }
+
+    aggregates.new_achievements = rb
+        .query_decode(
+            "SELECT ua.achievement_id, COALESCE(a.name, '') AS name, ua.achieved_at
+             FROM user_achievement ua LEFT JOIN achievement a ON a.id = ua.achievement_id
+             WHERE ua.user_id = ? AND julianday(ua.achieved_at) >= julianday(?) AND julianday(ua.achieved_at) < julianday(?)
+             ORDER BY ua.achieved_at",
+            vec![
+                Value::String(user_id.to_string()),
+                Value::String(start.to_rfc3339()),
+                Value::String(end.to_rfc3339()),
+            ],
+        )
+        .await?;
+
+    Ok(aggregates)
+}
+
+fn build_summary_prompt(month: &str, aggregates: &MonthlyAggregates, language: Language) -> String {
+    let by_type = aggregates
+        .tasks_completed_by_type
+        .iter()
+        .map(|(task_type, count)| format!("{} {}", task_type, count))
+        .collect::<Vec<_>>()
+        .join("、");
+    let attributes = aggregates
+        .attribute_changes
+        .iter()
+        .filter(|(_, delta)| **delta != 0)
+        .map(|(attribute, delta)| format!("{} {:+}", attribute, delta))
+        .collect::<Vec<_>>()
+        .join("、");
+    let achievements = aggregates.new_achievements.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join("、");
+    let habits = aggregates
+        .best_habit_streaks
+        .iter()
+        .map(|s| format!("{} 連續 {} 天", s.title, s.days))
+        .collect::<Vec<_>>()
+        .join("、");
+    let language = language.directive();
+    format!(
+        "你是使用者的成長教練，請根據以下 {month} 的數據寫一段約 120 字的月度回顧：肯定進步、點出值得注意的地方，並給一個下個月的具體建議。\
+         只能使用提供的數據，不要編造，只輸出段落本身。{language}\n\
+         - 完成任務：{} 個（{}）\n- 獲得經驗：{} XP，技能經驗 {}，技能升級 {} 次\n- 屬性變化：{}\n- 新成就：{}\n\
+         - 最長連續完成天數：{} 天\n- 習慣連續紀錄：{}",
+        aggregates.tasks_completed,
+        if by_type.is_empty() { "無".to_string() } else { by_type },
+        aggregates.experience_gained,
+        aggregates.skill_experience_gained,
+        aggregates.skill_levels_gained,
+        if attributes.is_empty() { "無".to_string() } else { attributes },
+        if achievements.is_empty() { "無".to_string() } else { achievements },
+        aggregates.longest_active_streak,
+        if habits.is_empty() { "無".to_string() } else { habits },
+    )
+}
+
+/// 取得 AI 回顧：彙整數字與上次相同時沿用已儲存的內容，否則重新產生並儲存
+async fn summary_for(
+    rb: &RBatis,
+    ai: &SharedAIService,
+    user_id: &str,
+    month: &str,
+    aggregates: &MonthlyAggregates,
+    language: Language,
+) -> std::result::Result<Option<String>, rbatis::Error> {
+    if aggregates.is_empty() {
+        return Ok(None);
+    }
+    // 語言不同時重新產生（預設語言維持原本的指紋，既有的回顧不必重做）
+    let fingerprint = match language {
+        Language::ZhTw => aggregates.fingerprint(),
+        other => format!("{}:{}", aggregates.fingerprint(), other.code()),
+    };
+    let stored: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT fingerprint, ai_summary FROM monthly_report WHERE user_id = ? AND month = ?",
+            vec![Value::String(user_id.to_string()), Value::String(month.to_string())],
+        )
+        .await?;
+    if let Some(row) = stored.first() {
+        if row["fingerprint"].as_str() == Some(fingerprint.as_str()) && row["ai_summary"].is_string() {
+            return Ok(row["ai_summary"].as_str().map(str::to_string));
+        }
+    }
+
+    let prompt = build_summary_prompt(month, aggregates, language);
+    let generated = match ai.get() {
+        Ok(service) => service.generate_with_model(ai.background_model(), &prompt).await,
+        Err(e) => Err(e),
+    };
+    let summary = match generated {
+        Ok(text) if !text.trim().is_empty() => text.trim().to_string(),
+        Ok(_) => return Ok(None),
+        Err(e) => {
+            // 失敗時不儲存，下次請求再試
+            log::warn!("產生使用者 {} {} 月報回顧失敗: {}", user_id, month, e);
+            return Ok(None);
+        }
+    };
+
+    let now = Utc::now().to_rfc3339();
+    rb.exec(
+        "INSERT INTO monthly_report (id, user_id, month, fingerprint, report, ai_summary, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(user_id, month) DO UPDATE SET
+             fingerprint = excluded.fingerprint, report = excluded.report,
+             ai_summary = excluded.ai_summary, updated_at = excluded.updated_at",
+        vec![
+            Value::String(uuid::Uuid::new_v4().to_string()),
+            Value::String(user_id.to_string()),
+            Value::String(month.to_string()),
+            Value::String(fingerprint),
+            Value::String(serde_json::to_string(aggregates).unwrap_or_default()),
+            Value::String(summary.clone()),
+            Value::String(now.clone()),
+            Value::String(now),
+        ],
+    )
+    .await?;
+    Ok(Some(summary))
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// GET /api/users/{id}/reports/monthly?month=YYYY-MM[&deliver=email]
+pub async fn get_monthly_report(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<SharedAIService>,
+    path: web::Path<String>,
+    query: web::Query<MonthlyReportQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !crate::auth::is_admin_request(&http_req) {
+        return Ok(error(StatusCode::FORBIDDEN, "無權限查看此使用者的月報"));
+    }
+    let deliver_email = match query.deliver.as_deref().map(str::trim) {
+        None | Some("") => false,
+        Some("email") => true,
+        Some(other) => return Ok(error(StatusCode::BAD_REQUEST, format!("不支援的寄送方式: {}（目前只支援 email）", other))),
+    };
+
+    let user = match crate::models::User::select_by_map(rb.get_ref(), value!{"id": &user_id}).await {
+        Ok(users) => match users.into_iter().next() {
+            Some(user) => user,
+            None => return Ok(error(StatusCode::NOT_FOUND, "用戶不存在")),
+        },
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢用戶失敗: {}", e))),
+    };
+    let settings = match crate::user_settings::load_ui_settings(rb.get_ref(), &user_id).await {
+        Ok(settings) => settings,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("讀取使用者設定失敗: {}", e))),
+    };
+    let tz = crate::local_date::parse_utc_offset(&settings.timezone).unwrap_or_else(crate::local_date::default_timezone);
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    let this_month = today.with_day(1).unwrap_or(today);
+
+    let first_day = match query.month.as_deref() {
+        Some(month) => match parse_month(month) {
+            Some(first_day) => first_day,
+            None => return Ok(error(StatusCode::BAD_REQUEST, "month 格式錯誤，請使用 YYYY-MM")),
+        },
+        None => this_month - Months::new(1),
+    };
+    if first_day > this_month {
+        return Ok(error(StatusCode::BAD_REQUEST, "不能查詢尚未開始的月份"));
+    }
+    let month = first_day.format("%Y-%m").to_string();
+
+    let aggregates = match compute_aggregates(rb.get_ref(), &user_id, first_day, tz).await {
+        Ok(aggregates) => aggregates,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("計算月報失敗: {}", e))),
+    };
+    let language = crate::mailer::MailLanguage::from_code(&settings.locale);
+    let summary_language = Language::from_code(&settings.locale).unwrap_or_default();
+    let ai_summary = match summary_for(rb.get_ref(), ai.get_ref(), &user_id, &month, &aggregates, summary_language).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            log::warn!("讀取或儲存使用者 {} {} 月報回顧失敗: {}", user_id, month, e);
+            None
+        }
+    };
+
+    let (_, _, last_day) = month_bounds(first_day, tz);
+    let report = MonthlyReport {
+        user_id,
+        month,
+        timezone: settings.timezone,
+        first_day: first_day.format("%Y-%m-%d").to_string(),
+        last_day: last_day.format("%Y-%m-%d").to_string(),
+        partial: last_day >= today,
+        aggregates,
+        ai_summary,
+    };
+
+    let mut message = format!("獲取 {} 月報成功", report.month);
+    if deliver_email {
+        match user.email.as_deref().filter(|email| !email.trim().is_empty()) {
+            Some(email) => {
+                let template = crate::mailer::MailTemplate::MonthlyReport {
+                    user_name: user.name.clone().unwrap_or_default(),
+                    month: report.month.clone(),
+                    completed_tasks: report.aggregates.tasks_completed,
+                    experience_gained: report.aggregates.experience_gained,
+                    new_achievements: report.aggregates.new_achievements.len(),
+                    summary: report.ai_summary.clone(),
+                };
+                crate::mailer::send_template_in_background(email.to_string(), &template, language);
+                message.push_str("，已排入寄送");
+            }
+            None => return Ok(error(StatusCode::UNPROCESSABLE_ENTITY, "使用者沒有設定信箱，無法寄送月報")),
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(report),
+        message,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_month_bounds_follow_timezone_and_longest_run() {
+        let first = parse_month("2026-02").unwrap();
+        let (start, end, last) = month_bounds(first, FixedOffset::east_opt(8 * 3600).unwrap());
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 1, 31, 16, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 2, 28, 16, 0, 0).unwrap());
+        assert_eq!(last, NaiveDate::from_ymd_opt(2026, 2, 28).unwrap());
+        let (start, _, _) = month_bounds(first, FixedOffset::west_opt(5 * 3600).unwrap());
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 2, 1, 5, 0, 0).unwrap());
+        assert!(parse_month("2026-13").is_none());
+
+        let days: BTreeSet<NaiveDate> = ["2026-02-01", "2026-02-02", "2026-02-04", "2026-02-05", "2026-02-06"]
+            .iter()
+            .map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap())
+            .collect();
+        assert_eq!(longest_run(&days), 3);
+        assert_eq!(longest_run(&BTreeSet::new()), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_monthly_report_caches_summary_and_skips_ai_for_empty_month() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["二月穩定完成晨跑，下個月試著加入閱讀。"]);
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "monthly").await;
+
+        for (title, task_type, status, task_date, updated_at) in [
+            ("晨跑", "daily", 6, Some("2026-02-01"), "2026-02-01T01:00:00Z"),
+            ("晨跑", "daily", 6, Some("2026-02-02"), "2026-02-02T01:00:00Z"),
+            // 台灣時間 3/1 00:30，屬於三月
+            ("寫報告", "main", 2, None, "2026-02-28T16:30:00Z"),
+            // 台灣時間 2/28 23:30，屬於二月
+            ("整理桌面", "side", 2, None, "2026-02-28T15:30:00Z"),
+        ] {
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, task_type, status, experience, task_date, parent_task_id, updated_at)
+                 VALUES (?, ?, ?, ?, ?, 10, ?, NULL, ?)",
+                vec![
+                    Value::String(uuid::Uuid::new_v4().to_string()),
+                    Value::String(user.id.clone()),
+                    Value::String(title.to_string()),
+                    Value::String(task_type.to_string()),
+                    Value::I32(status),
+                    task_date.map(|d| Value::String(d.to_string())).unwrap_or(Value::Null),
+                    Value::String(updated_at.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let uri = format!("/api/users/{}/reports/monthly?month=2026-02", user.id);
+        for _ in 0..2 {
+            let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, 200, "{}", body);
+            assert_eq!(body["data"]["tasks_completed"], 3);
+            assert_eq!(body["data"]["tasks_completed_by_type"], serde_json::json!({"daily": 2, "side": 1}));
+            assert_eq!(body["data"]["experience_gained"], 30);
+            assert_eq!(body["data"]["longest_active_streak"], 2);
+            assert_eq!(body["data"]["first_day"], "2026-02-01");
+            assert_eq!(body["data"]["last_day"], "2026-02-28");
+            assert_eq!(body["data"]["ai_summary"], "二月穩定完成晨跑，下個月試著加入閱讀。");
+        }
+        // 第二次沿用已儲存的回顧
+        assert_eq!(mock.prompts("generate_with_model").len(), 1);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/reports/monthly?month=2025-12", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["tasks_completed"], 0);
+        assert_eq!(body["data"]["attribute_changes"]["focus"], 0);
+        assert!(body["data"]["ai_summary"].is_null());
+        assert_eq!(mock.prompts("generate_with_model").len(), 1);
+
+        let other = test_utils::create_user(&app, "monthly-other").await;
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+}
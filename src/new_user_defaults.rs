@@ -0,0 +1,194 @@
+// 新帳號的初始遊戲化資料與入門任務包
+//
+// 預設值只在建立 user_profile / user_attributes 時寫入，調整設定不會改動既有使用者。
+// 入門任務包是內建的任務清單，設定 NEW_USER_STARTER_PACK 後每個新帳號註冊時都會建立一份。
+
+use std::sync::OnceLock;
+use chrono::Utc;
+use rbatis::executor::Executor;
+use rbs::Value;
+
+use crate::config::NewUserDefaultsConfig;
+use crate::models::TaskStatus;
+
+static DEFAULTS: OnceLock<NewUserDefaultsConfig> = OnceLock::new();
+
+/// 入門任務包中的一個任務
+pub struct StarterTask {
+    pub title: &'static str,
+    pub description: &'static str,
+    pub task_type: &'static str,
+    pub difficulty: i32,
+    pub experience: i32,
+}
+
+const BASIC_PACK: &[StarterTask] = &[
+    StarterTask { title: "完成個人資料", description: "填寫暱稱與目標，讓教練更了解你", task_type: "side", difficulty: 1, experience: 20 },
+    StarterTask { title: "每天喝八杯水", description: "養成規律補充水分的習慣", task_type: "daily", difficulty: 1, experience: 10 },
+    StarterTask { title: "設定第一個主線目標", description: "想一件這個月最想完成的事，拆成幾個小步驟", task_type: "main", difficulty: 2, experience: 50 },
+];
+
+const STUDENT_PACK: &[StarterTask] = &[
+    StarterTask { title: "整理本週課表", description: "把上課與考試時間排進行事曆", task_type: "side", difficulty: 1, experience: 20 },
+    StarterTask { title: "每日複習 30 分鐘", description: "當天複習上課筆記，避免考前臨時抱佛腳", task_type: "daily", difficulty: 2, experience: 15 },
+    StarterTask { title: "讀完一本課外書", description: "選一本有興趣的書，每週讀幾章", task_type: "main", difficulty: 3, experience: 80 },
+];
+
+const FAMILY_PACK: &[StarterTask] = &[
+    StarterTask { title: "整理自己的書包", description: "睡前把明天要帶的東西準備好", task_type: "daily", difficulty: 1, experience: 10 },
+    StarterTask { title: "幫忙做一件家事", description: "擦桌子、倒垃圾或收衣服都可以", task_type: "daily", difficulty: 1, experience: 10 },
+    StarterTask { title: "和家人一起運動", description: "週末一起散步或騎腳踏車", task_type: "side", difficulty: 2, experience: 30 },
+];
+
+/// 依名稱取得內建的入門任務包
+pub fn starter_pack(name: &str) -> Option<&'static [StarterTask]> {
+    match name.trim().to_lowercase().as_str() {
+        "basic" => Some(BASIC_PACK),
+        "student" => Some(STUDENT_PACK),
+        "family" => Some(FAMILY_PACK),
+        _ => None,
+    }
+}
+
+/// 啟動時套用新帳號初始資料設定
+pub fn init(config: NewUserDefaultsConfig) {
+    log::info!(
+        "新帳號初始資料: 等級 {}、升級經驗 {}、稱號 {}、屬性 {}（覆寫 {:?}）、入門任務包 {}",
+        config.level,
+        config.max_experience,
+        config.title,
+        config.attribute_baseline,
+        config.attribute_overrides,
+        config.starter_pack.as_deref().unwrap_or("無")
+    );
+    if let Some(pack) = config.starter_pack.as_deref() {
+        if starter_pack(pack).is_none() {
+            log::warn!("未知的入門任務包 {}，新帳號不會建立入門任務", pack);
+        }
+    }
+    if DEFAULTS.set(config).is_err() {
+        log::warn!("新帳號初始資料設定已初始化，忽略重複設定");
+    }
+}
+
+/// 目前的新帳號初始資料設定（未初始化時使用預設值）
+pub fn current() -> &'static NewUserDefaultsConfig {
+    DEFAULTS.get_or_init(NewUserDefaultsConfig::default)
+}
+
+/// 為使用者建立入門任務包中的任務；可在交易中執行。回傳建立的任務數（未知的任務包為 0）
+pub async fn create_starter_tasks(
+    rb: &dyn Executor,
+    user_id: &str,
+    pack: &str,
+) -> std::result::Result<usize, rbatis::Error> {
+    let Some(tasks) = starter_pack(pack) else {
+        return Ok(0);
+    };
+    let now = Utc::now().to_rfc3339();
+    for task in tasks {
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, description, task_type, difficulty, experience, status, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            vec![
+                Value::String(uuid::Uuid::new_v4().to_string()),
+                Value::String(user_id.to_string()),
+                Value::String(task.title.to_string()),
+                Value::String(task.description.to_string()),
+                Value::String(task.task_type.to_string()),
+                Value::I32(task.difficulty),
+                Value::I32(task.experience),
+                Value::I32(TaskStatus::Pending.to_i32()),
+                Value::String(now.clone()),
+                Value::String(now.clone()),
+            ],
+        )
+        .await?;
+    }
+    Ok(tasks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::user_activity::ensure_user_rows_with;
+    use crate::test_utils;
+
+    async fn profile_and_attributes(rb: &rbatis::RBatis, user_id: &str) -> (serde_json::Value, serde_json::Value) {
+        let profile: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT level, experience, max_experience, title FROM user_profile WHERE user_id = ?",
+                vec![Value::String(user_id.to_string())],
+            )
+            .await
+            .unwrap();
+        let attributes: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT intelligence, endurance, creativity, social, focus, adaptability FROM user_attributes WHERE user_id = ?",
+                vec![Value::String(user_id.to_string())],
+            )
+            .await
+            .unwrap();
+        (profile[0].clone(), attributes[0].clone())
+    }
+
+    #[actix_web::test]
+    async fn test_seed_profile_applies_non_default_values() {
+        let rb = test_utils::setup_db().await;
+        let user_id = crate::seed_data::seed_profile_user(&rb, "family").await.unwrap();
+
+        let (profile, attributes) = profile_and_attributes(&rb, &user_id).await;
+        assert_eq!(profile["level"], 1);
+        assert_eq!(profile["experience"], 0);
+        assert_eq!(profile["max_experience"], 60);
+        assert_eq!(profile["title"], "小小探險家");
+        assert_eq!(attributes["intelligence"], 30);
+        assert_eq!(attributes["creativity"], 60);
+        assert_eq!(attributes["social"], 45);
+        assert_eq!(attributes["adaptability"], 30);
+
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT title FROM task WHERE user_id = ? AND status = ? ORDER BY title",
+                vec![Value::String(user_id.clone()), Value::I32(TaskStatus::Pending.to_i32())],
+            )
+            .await
+            .unwrap();
+        let titles: Vec<&str> = rows.iter().filter_map(|row| row["title"].as_str()).collect();
+        let mut expected: Vec<&str> = FAMILY_PACK.iter().map(|t| t.title).collect();
+        expected.sort();
+        assert_eq!(titles, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_defaults_only_apply_to_new_rows() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "defaults_existing").await;
+
+        // 未設定時沿用原本的預設值，且不建立入門任務
+        let (profile, attributes) = profile_and_attributes(&rb, &user.id).await;
+        assert_eq!(profile["max_experience"], 100);
+        assert_eq!(profile["title"], "新手冒險者");
+        assert_eq!(attributes["focus"], 50);
+        let tasks: i64 = rb
+            .query_decode("SELECT COUNT(*) AS count FROM task WHERE user_id = ?", vec![Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(tasks, 0);
+
+        // 換成其他設定後，既有使用者的資料不變
+        let changed = crate::seed_data::seed_profile("family").unwrap();
+        ensure_user_rows_with(&rb, &user.id, &changed).await.unwrap();
+        let (profile, attributes) = profile_and_attributes(&rb, &user.id).await;
+        assert_eq!(profile["title"], "新手冒險者");
+        assert_eq!(attributes["creativity"], 50);
+    }
+
+    #[test]
+    fn test_starter_pack_lookup() {
+        assert_eq!(starter_pack(" Family ").map(|p| p.len()), Some(FAMILY_PACK.len()));
+        assert!(starter_pack("basic").is_some());
+        assert!(starter_pack("unknown").is_none());
+    }
+}
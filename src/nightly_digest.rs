@@ -0,0 +1,582 @@
+// 每晚彙整：使用者時區午夜後結算前一天，讓晚間通知、週報與趨勢分析直接讀取結果
+//
+// 對前一天有任務活動的使用者：把未完成的重複性子任務標記為 DailyNotCompleted、
+// 由任務表完成當日 daily_progress（完成數、經驗值，缺少時補上屬性成長），
+// 並重置已中斷的連續登入天數，最後清除過期的職業任務生成追蹤（設定啟用時一併壓縮舊的每日子任務）。所有步驟都可重複執行；使用者分批處理，
+// 批次之間讓出執行緒，避免長時間持有 SQLite 寫入鎖。
+// 使用者依設定的時區分組，每組在該時區的 run_time 各自彙整；不分使用者的清理只隨預設時區那組執行。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration as StdDuration;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone, Timelike, Utc};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::NightlyDigestConfig;
+use crate::models::TaskStatus;
+
+static NIGHTLY_DIGEST_CONFIG: OnceLock<NightlyDigestConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: NightlyDigestConfig) {
+    log::info!("每晚彙整: 每天 {}，每批 {} 位使用者", config.run_time, config.batch_size);
+    if NIGHTLY_DIGEST_CONFIG.set(config).is_err() {
+        log::warn!("每晚彙整設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static NightlyDigestConfig {
+    NIGHTLY_DIGEST_CONFIG.get_or_init(NightlyDigestConfig::default)
+}
+
+/// 一次彙整的結果
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestRunStatus {
+    pub date: String,
+    // 這一輪處理的時區（±HH:MM）
+    pub timezone: String,
+    // scheduled 或 manual
+    pub trigger: &'static str,
+    #[serde(with = "crate::datetime_format::rfc3339")]
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "crate::datetime_format::rfc3339")]
+    pub finished_at: DateTime<Utc>,
+    pub users_processed: u64,
+    pub users_failed: u64,
+    pub subtasks_marked_missed: u64,
+    pub login_streaks_reset: u64,
+    pub career_traces_pruned: u64,
+    pub subtasks_compacted: u64,
+    pub error: Option<String>,
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static LAST_RUN: OnceLock<Mutex<Option<DigestRunStatus>>> = OnceLock::new();
+
+fn last_run() -> &'static Mutex<Option<DigestRunStatus>> {
+    LAST_RUN.get_or_init(|| Mutex::new(None))
+}
+
+/// 最近一次彙整狀態（管理員執行期統計使用）
+pub fn metrics_snapshot() -> serde_json::Value {
+    serde_json::json!({
+        "running": RUNNING.load(Ordering::Relaxed),
+        "last_run": last_run().lock().ok().and_then(|status| status.clone()),
+    })
+}
+
+/// 時區相同的一組使用者
+#[derive(Debug, Clone)]
+pub struct TimezoneGroup {
+    pub offset: FixedOffset,
+    // 對應的 user_settings.timezone 原始值；空字串代表沒有設定（歸入預設時區）
+    settings: Vec<String>,
+}
+
+impl TimezoneGroup {
+    /// 限定 column 指定的使用者屬於此組的 SQL 條件與參數
+    fn member_filter(&self, column: &str) -> (String, Vec<rbs::Value>) {
+        let placeholders = vec!["?"; self.settings.len()].join(", ");
+        (
+            format!("COALESCE((SELECT timezone FROM user_settings WHERE user_id = {column}), '') IN ({placeholders})"),
+            self.settings.iter().map(|s| rbs::Value::String(s.clone())).collect(),
+        )
+    }
+}
+
+/// 依使用者設定的時區分組；沒有設定或格式錯誤的使用者歸入預設時區（此組一定存在且排在第一個）
+pub async fn timezone_groups(rb: &RBatis) -> std::result::Result<Vec<TimezoneGroup>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode("SELECT DISTINCT COALESCE(timezone, '') AS timezone FROM user_settings", vec![])
+        .await?;
+    let default = crate::local_date::default_timezone();
+    let mut groups = vec![TimezoneGroup { offset: default, settings: vec![String::new()] }];
+    for value in rows.iter().filter_map(|row| row["timezone"].as_str()) {
+        let offset = crate::local_date::parse_utc_offset(value).unwrap_or(default);
+        match groups.iter_mut().find(|group| group.offset == offset) {
+            Some(group) if group.settings.iter().any(|s| s == value) => {}
+            Some(group) => group.settings.push(value.to_string()),
+            None => groups.push(TimezoneGroup { offset, settings: vec![value.to_string()] }),
+        }
+    }
+    Ok(groups)
+}
+
+/// 當地時間正好是 run_time 的時區組，與各自要彙整的日期（當地的昨天）
+fn due_groups<'a>(groups: &'a [TimezoneGroup], now: DateTime<Utc>, run_time: &str) -> Vec<(&'a TimezoneGroup, NaiveDate)> {
+    groups
+        .iter()
+        .filter_map(|group| {
+            let local = now.with_timezone(&group.offset);
+            (format!("{:02}:{:02}", local.hour(), local.minute()) == run_time)
+                .then(|| (group, local.date_naive() - Duration::days(1)))
+        })
+        .collect()
+}
+
+/// 指定時區某一天的起訖時間（UTC）
+fn local_day_bounds(date: NaiveDate, tz: FixedOffset) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = tz
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_default();
+    (start, start + Duration::days(1))
+}
+
+// 此時區組當天有任務（或已有每日進度）的使用者
+async fn active_users(rb: &RBatis, group: &TimezoneGroup, date: NaiveDate) -> std::result::Result<Vec<String>, rbatis::Error> {
+    let date_str = date.format("%Y-%m-%d").to_string();
+    let (start, end) = local_day_bounds(date, group.offset);
+    let (member_filter, member_args) = group.member_filter("active.user_id");
+    let sql = format!(
+        "SELECT user_id FROM (
+             SELECT user_id FROM task
+             WHERE user_id IS NOT NULL AND (task_date = ? OR (task_date IS NULL
+                   AND julianday(updated_at) >= julianday(?) AND julianday(updated_at) < julianday(?)))
+             UNION
+             SELECT user_id FROM daily_progress WHERE date = ?
+         ) active
+         WHERE {member_filter}
+         ORDER BY user_id"
+    );
+    let mut args = vec![
+        rbs::Value::String(date_str.clone()),
+        rbs::Value::String(start.to_rfc3339()),
+        rbs::Value::String(end.to_rfc3339()),
+        rbs::Value::String(date_str),
+    ];
+    args.extend(member_args);
+    let rows: Vec<serde_json::Value> = rb.query_decode(&sql, args).await?;
+    Ok(rows.iter().filter_map(|row| row["user_id"].as_str().map(str::to_string)).collect())
+}
+
+/// 把一批使用者當天尚未完成的重複性子任務標記為 DailyNotCompleted；回傳更新筆數
+async fn mark_missed_subtasks(rb: &RBatis, user_ids: &[String], date: NaiveDate) -> std::result::Result<u64, rbatis::Error> {
+    if user_ids.is_empty() {
+        return Ok(0);
+    }
+    let placeholders = vec!["?"; user_ids.len()].join(", ");
+    let sql = format!(
+        "UPDATE task SET status = ?, updated_at = ?
+         WHERE task_date = ? AND status IN (?, ?, ?, ?) AND user_id IN ({placeholders})
+           AND parent_task_id IN (SELECT id FROM task WHERE is_recurring = 1)"
+    );
+    let mut args = vec![
+        rbs::Value::I32(TaskStatus::DailyNotCompleted.to_i32()),
+        rbs::Value::String(Utc::now().to_rfc3339()),
+        rbs::Value::String(date.format("%Y-%m-%d").to_string()),
+        rbs::Value::I32(TaskStatus::Pending.to_i32()),
+        rbs::Value::I32(TaskStatus::InProgress.to_i32()),
+        rbs::Value::I32(TaskStatus::Paused.to_i32()),
+        rbs::Value::I32(TaskStatus::DailyInProgress.to_i32()),
+    ];
+    args.extend(user_ids.iter().map(|id| rbs::Value::String(id.clone())));
+    Ok(rb.exec(&sql, args).await?.rows_affected)
+}
+
+/// 完成使用者當天的 daily_progress；屬性成長沒有即時紀錄時以任務的屬性獎勵補上
+async fn finalize_daily_progress(rb: &RBatis, user_id: &str, date: NaiveDate) -> std::result::Result<(), rbatis::Error> {
+    let snapshot = crate::recompute::refresh_daily_progress(rb, user_id, date).await?;
+    if snapshot.attributes_gained.as_object().is_some_and(|gains| !gains.is_empty()) {
+        rb.exec(
+            "UPDATE daily_progress SET attributes_gained = ?
+             WHERE user_id = ? AND date = ? AND (attributes_gained IS NULL OR attributes_gained IN ('', '{}'))",
+            vec![
+                rbs::Value::String(snapshot.attributes_gained.to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(date.format("%Y-%m-%d").to_string()),
+            ],
+        )
+        .await?;
+        crate::gamified_cache::invalidate(user_id);
+    }
+    Ok(())
+}
+
+/// 此時區組當天沒有登入的使用者，連續登入天數歸零（分批更新）；回傳更新筆數
+async fn reset_broken_login_streaks(
+    rb: &RBatis,
+    group: &TimezoneGroup,
+    date: NaiveDate,
+    batch_size: i64,
+) -> std::result::Result<u64, rbatis::Error> {
+    let (member_filter, member_args) = group.member_filter("user_profile.user_id");
+    let sql = format!(
+        "UPDATE user_profile SET consecutive_login_days = 0, updated_at = ?
+         WHERE id IN (
+             SELECT id FROM user_profile
+             WHERE last_login_date < ? AND consecutive_login_days > 0 AND {member_filter} LIMIT ?
+         )"
+    );
+    let mut reset = 0;
+    loop {
+        let mut args = vec![
+            rbs::Value::String(Utc::now().to_rfc3339()),
+            rbs::Value::String(date.format("%Y-%m-%d").to_string()),
+        ];
+        args.extend(member_args.iter().cloned());
+        args.push(rbs::Value::I64(batch_size));
+        let affected = rb.exec(&sql, args).await?.rows_affected;
+        reset += affected;
+        if (affected as i64) < batch_size {
+            // 一次更新多位使用者，直接清除全部快取
+            if reset > 0 {
+                crate::gamified_cache::clear();
+            }
+            return Ok(reset);
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+/// 彙整一個時區組的指定日期；同一時間只允許一輪（已在執行時回傳 None）
+pub async fn run_digest(
+    rb: &RBatis,
+    group: &TimezoneGroup,
+    date: NaiveDate,
+    batch_size: i64,
+    trigger: &'static str,
+) -> Option<DigestRunStatus> {
+    if RUNNING.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return None;
+    }
+
+    let mut status = DigestRunStatus {
+        date: date.format("%Y-%m-%d").to_string(),
+        timezone: group.offset.to_string(),
+        trigger,
+        started_at: Utc::now(),
+        finished_at: Utc::now(),
+        users_processed: 0,
+        users_failed: 0,
+        subtasks_marked_missed: 0,
+        login_streaks_reset: 0,
+        career_traces_pruned: 0,
+        subtasks_compacted: 0,
+        error: None,
+    };
+    if let Err(e) = digest_users(rb, group, date, batch_size.max(1), &mut status).await {
+        log::error!("每晚彙整 {}（{}）失敗: {}", status.date, status.timezone, e);
+        status.error = Some(e.to_string());
+    }
+    status.finished_at = Utc::now();
+    log::info!(
+        "每晚彙整 {}（{}，{}）：{} 位使用者、{} 個子任務標記未完成、{} 位連續登入歸零",
+        status.date,
+        status.timezone,
+        trigger,
+        status.users_processed,
+        status.subtasks_marked_missed,
+        status.login_streaks_reset
+    );
+
+    if let Ok(mut last) = last_run().lock() {
+        *last = Some(status.clone());
+    }
+    RUNNING.store(false, Ordering::SeqCst);
+    Some(status)
+}
+
+async fn digest_users(
+    rb: &RBatis,
+    group: &TimezoneGroup,
+    date: NaiveDate,
+    batch_size: i64,
+    status: &mut DigestRunStatus,
+) -> std::result::Result<(), rbatis::Error> {
+    let user_ids = active_users(rb, group, date).await?;
+    for chunk in user_ids.chunks(batch_size as usize) {
+        status.subtasks_marked_missed += mark_missed_subtasks(rb, chunk, date).await?;
+        for user_id in chunk {
+            match finalize_daily_progress(rb, user_id, date).await {
+                Ok(()) => status.users_processed += 1,
+                Err(e) => {
+                    log::warn!("彙整使用者 {} 的每日進度失敗: {}", user_id, e);
+                    status.users_failed += 1;
+                }
+            }
+        }
+        // 讓其他寫入有機會取得鎖
+        tokio::task::yield_now().await;
+    }
+    status.login_streaks_reset = reset_broken_login_streaks(rb, group, date, batch_size).await?;
+    // 以下不分使用者，每天只隨預設時區那組執行一次
+    if group.offset != crate::local_date::default_timezone() {
+        return Ok(());
+    }
+    status.career_traces_pruned = crate::career_trace::prune(rb, Utc::now()).await?;
+    if crate::daily_compaction::nightly_enabled() {
+        match crate::daily_compaction::run_compaction(rb, crate::daily_compaction::default_cutoff_days(), false).await {
+            Some(report) => status.subtasks_compacted = report?.subtasks,
+            None => log::warn!("每日子任務壓縮仍在執行，略過本次壓縮"),
+        }
+    }
+    Ok(())
+}
+
+/// 每分鐘檢查一次，各時區組在當地的設定時間彙整當地的前一天
+pub fn spawn_scheduler(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let groups = match timezone_groups(&rb).await {
+                Ok(groups) => groups,
+                Err(e) => {
+                    log::error!("查詢使用者時區失敗: {}", e);
+                    continue;
+                }
+            };
+            for (group, yesterday) in due_groups(&groups, Utc::now(), &config().run_time) {
+                if run_digest(&rb, group, yesterday, config().batch_size, "scheduled").await.is_none() {
+                    log::warn!("每晚彙整仍在執行，略過 {} 的本次排程", group.offset);
+                }
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DigestRunQuery {
+    // YYYY-MM-DD，未指定時為各時區的昨天
+    pub date: Option<String>,
+}
+
+/// 管理員手動執行彙整（可指定過去日期補跑）；依序處理當天已結束的每個時區組
+pub async fn run_digest_now(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<DigestRunQuery>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+
+    let requested = match query.date.as_deref() {
+        Some(raw) => match NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d") {
+            Ok(date) => Some(date),
+            Err(_) => {
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "日期格式錯誤，請使用 YYYY-MM-DD".to_string(),
+                }))
+            }
+        },
+        None => None,
+    };
+    let groups = match timezone_groups(rb.get_ref()).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢使用者時區失敗: {}", e),
+            }))
+        }
+    };
+    // 指定日期在該時區還沒結束的組不處理
+    let due: Vec<(&TimezoneGroup, NaiveDate)> = groups
+        .iter()
+        .filter_map(|group| {
+            let today = crate::local_date::local_today_in(group.offset);
+            let date = requested.unwrap_or(today - Duration::days(1));
+            (date < today).then_some((group, date))
+        })
+        .collect();
+    if due.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "只能彙整今天以前的日期".to_string(),
+        }));
+    }
+
+    let mut statuses = Vec::new();
+    for (group, date) in due {
+        match run_digest(rb.get_ref(), group, date, config().batch_size, "manual").await {
+            Some(status) => statuses.push(status),
+            None => {
+                return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "每晚彙整正在執行，請稍後再試".to_string(),
+                }))
+            }
+        }
+    }
+    let failed: Vec<String> = statuses
+        .iter()
+        .filter_map(|status| status.error.as_ref().map(|e| format!("{}（{}）: {}", status.date, status.timezone, e)))
+        .collect();
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: failed.is_empty(),
+        message: if failed.is_empty() {
+            format!("已彙整 {} 個時區", statuses.len())
+        } else {
+            format!("彙整失敗: {}", failed.join("；"))
+        },
+        data: Some(statuses),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    async fn insert_task(rb: &RBatis, id: &str, user_id: &str, parent: Option<&str>, status: TaskStatus, task_date: Option<&str>) {
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, parent_task_id, is_recurring, recurrence_pattern, task_date, experience, attributes, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, 'daily', ?, 10, ?, ?, ?)",
+            vec![
+                rbs::Value::String(id.to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(id.to_string()),
+                rbs::Value::I32(status.to_i32()),
+                parent.map(|p| rbs::Value::String(p.to_string())).unwrap_or(rbs::Value::Null),
+                rbs::Value::I32(parent.is_none() as i32),
+                task_date.map(|d| rbs::Value::String(d.to_string())).unwrap_or(rbs::Value::Null),
+                rbs::Value::String(r#"{"focus": 2}"#.to_string()),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await
+        .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_digest_finalizes_day_and_is_idempotent() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "digest").await;
+        let date = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+
+        insert_task(&rb, "habit", &user.id, None, TaskStatus::DailyInProgress, None).await;
+        insert_task(&rb, "done", &user.id, Some("habit"), TaskStatus::DailyCompleted, Some("2026-03-01")).await;
+        insert_task(&rb, "missed", &user.id, Some("habit"), TaskStatus::DailyInProgress, Some("2026-03-01")).await;
+        insert_task(&rb, "next_day", &user.id, Some("habit"), TaskStatus::DailyInProgress, Some("2026-03-02")).await;
+        rb.exec(
+            "UPDATE user_profile SET consecutive_login_days = 5, last_login_date = '2026-02-27' WHERE user_id = ?",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        let groups = timezone_groups(&rb).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        let status = run_digest(&rb, &groups[0], date, 1, "manual").await.unwrap();
+        assert!(status.error.is_none(), "{:?}", status.error);
+        assert_eq!(status.users_processed, 1);
+        assert_eq!(status.subtasks_marked_missed, 1);
+        assert_eq!(status.login_streaks_reset, 1);
+
+        let statuses: Vec<serde_json::Value> = rb
+            .query_decode("SELECT id, status FROM task WHERE parent_task_id = 'habit' ORDER BY id", vec![])
+            .await
+            .unwrap();
+        let statuses: Vec<(&str, i64)> = statuses
+            .iter()
+            .map(|r| (r["id"].as_str().unwrap(), r["status"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(statuses, vec![("done", 6), ("missed", 7), ("next_day", 5)]);
+
+        let progress: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT completed_tasks, total_tasks, experience_gained, attributes_gained FROM daily_progress WHERE user_id = ?",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0]["completed_tasks"], 1);
+        assert_eq!(progress[0]["total_tasks"], 2);
+        assert_eq!(progress[0]["experience_gained"], 10);
+        let gained = crate::attribute_rewards::merge_attribute_gains(
+            progress[0].get("attributes_gained"),
+            &std::collections::HashMap::new(),
+        );
+        assert_eq!(gained, serde_json::json!({"focus": 2}));
+
+        // 重跑不會重複計入
+        let status = run_digest(&rb, &groups[0], date, 1, "manual").await.unwrap();
+        assert_eq!((status.subtasks_marked_missed, status.login_streaks_reset), (0, 0));
+        let rows: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM daily_progress WHERE user_id = ?", vec![rbs::Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(metrics_snapshot()["last_run"]["date"], "2026-03-01");
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/admin/digest/run?date=2026-03-01")
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+
+    #[actix_web::test]
+    async fn test_digest_runs_each_timezone_after_its_own_midnight() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let taipei = test_utils::create_user(&app, "digest_taipei").await;
+        let new_york = test_utils::create_user(&app, "digest_new_york").await;
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/users/{}/settings", new_york.id))
+            .insert_header(new_york.auth())
+            .set_json(serde_json::json!({"timezone": "-05:00"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 200);
+        for user in [&taipei, &new_york] {
+            let parent = format!("habit-{}", user.id);
+            insert_task(&rb, &parent, &user.id, None, TaskStatus::DailyInProgress, None).await;
+            insert_task(&rb, &format!("missed-{}", user.id), &user.id, Some(&parent), TaskStatus::DailyInProgress, Some("2026-03-01")).await;
+        }
+
+        let groups = timezone_groups(&rb).await.unwrap();
+        let offsets: Vec<String> = groups.iter().map(|g| g.offset.to_string()).collect();
+        assert_eq!(offsets, vec![crate::local_date::default_timezone().to_string(), "-05:00".to_string()]);
+
+        // 預設時區（UTC+8）3/2 00:10 時只有台北那組到期，紐約仍是 3/1 上午
+        let taipei_run = Utc.with_ymd_and_hms(2026, 3, 1, 16, 10, 0).unwrap();
+        let due = due_groups(&groups, taipei_run, "00:10");
+        assert_eq!(due.len(), 1);
+        assert_eq!((due[0].0.offset, due[0].1), (groups[0].offset, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()));
+        let status = run_digest(&rb, due[0].0, due[0].1, 1, "scheduled").await.unwrap();
+        assert_eq!((status.users_processed, status.subtasks_marked_missed), (1, 1));
+
+        let missed = |user_id: String| {
+            let rb = rb.clone();
+            async move {
+                let status: i64 = rb
+                    .query_decode("SELECT status FROM task WHERE id = ?", vec![rbs::Value::String(format!("missed-{}", user_id))])
+                    .await
+                    .unwrap();
+                status == TaskStatus::DailyNotCompleted.to_i32() as i64
+            }
+        };
+        assert!(missed(taipei.id.clone()).await);
+        assert!(!missed(new_york.id.clone()).await);
+
+        // 紐約 3/2 00:10（UTC 05:10）才輪到紐約那組
+        let new_york_run = Utc.with_ymd_and_hms(2026, 3, 2, 5, 10, 0).unwrap();
+        let due = due_groups(&groups, new_york_run, "00:10");
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0.offset.to_string(), "-05:00");
+        assert_eq!(due[0].1, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        let status = run_digest(&rb, due[0].0, due[0].1, 1, "scheduled").await.unwrap();
+        assert_eq!(status.timezone, "-05:00");
+        assert_eq!((status.users_processed, status.subtasks_marked_missed), (1, 1));
+        assert!(missed(new_york.id.clone()).await);
+    }
+}
@@ -0,0 +1,177 @@
+// 通知類別開關：使用者可個別關閉各類推送（例如只要早安摘要、不要成就推送）
+//
+// 存於 user_notification_settings.categories（JSON 物件 { 類別: bool }）；
+// 缺少的類別視為開啟，更新時拒絕未知類別。總開關 enabled 關閉時所有類別都不推送。
+
+use std::collections::BTreeMap;
+
+use rbatis::RBatis;
+
+use crate::models::UserNotificationSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationCategory {
+    Morning,
+    Evening,
+    DueReminder,
+    Achievement,
+    StreakRisk,
+    Custom,
+}
+
+impl NotificationCategory {
+    pub const ALL: [NotificationCategory; 6] = [
+        NotificationCategory::Morning,
+        NotificationCategory::Evening,
+        NotificationCategory::DueReminder,
+        NotificationCategory::Achievement,
+        NotificationCategory::StreakRisk,
+        NotificationCategory::Custom,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationCategory::Morning => "morning",
+            NotificationCategory::Evening => "evening",
+            NotificationCategory::DueReminder => "due_reminder",
+            NotificationCategory::Achievement => "achievement",
+            NotificationCategory::StreakRisk => "streak_risk",
+            NotificationCategory::Custom => "custom",
+        }
+    }
+
+    #[cfg_attr(not(feature = "push-notifications"), allow(dead_code))]
+    pub fn parse(value: &str) -> Option<NotificationCategory> {
+        NotificationCategory::ALL.into_iter().find(|c| c.as_str() == value)
+    }
+
+    /// 事件通知對應的類別（None 表示不受類別開關控制）
+    pub fn for_event(event_type: &str) -> Option<NotificationCategory> {
+        match event_type {
+            "achievement_unlocked" | "level_up" | "mainline_completed" | "recurring_task_finished" | "challenge_finished" => {
+                Some(NotificationCategory::Achievement)
+            }
+            "morning" => Some(NotificationCategory::Morning),
+            "evening" => Some(NotificationCategory::Evening),
+            "custom" => Some(NotificationCategory::Custom),
+            "streak_risk" | "partner_nudge" => Some(NotificationCategory::StreakRisk),
+            _ => None,
+        }
+    }
+}
+
+/// 預設類別設定（全部開啟），migration 的欄位預設值與此一致
+#[cfg_attr(not(feature = "push-notifications"), allow(dead_code))]
+pub fn default_categories_json() -> String {
+    serde_json::to_string(&parse_categories(None)).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// 解析已存的類別設定並補上預設值；忽略未知類別與非布林值
+pub fn parse_categories(json: Option<&str>) -> BTreeMap<String, bool> {
+    let stored: serde_json::Map<String, serde_json::Value> =
+        json.and_then(|text| serde_json::from_str(text).ok()).unwrap_or_default();
+    NotificationCategory::ALL
+        .iter()
+        .map(|c| {
+            let enabled = stored.get(c.as_str()).and_then(|v| v.as_bool()).unwrap_or(true);
+            (c.as_str().to_string(), enabled)
+        })
+        .collect()
+}
+
+/// 將更新套用到已存的類別設定；含未知類別時回傳錯誤訊息
+#[cfg_attr(not(feature = "push-notifications"), allow(dead_code))]
+pub fn merge_categories(current: Option<&str>, updates: &BTreeMap<String, bool>) -> Result<String, String> {
+    let unknown: Vec<&str> = updates
+        .keys()
+        .filter(|key| NotificationCategory::parse(key).is_none())
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        let allowed: Vec<&str> = NotificationCategory::ALL.iter().map(|c| c.as_str()).collect();
+        return Err(format!("未知的通知類別: {}（可用類別: {}）", unknown.join(", "), allowed.join(", ")));
+    }
+
+    let mut categories = parse_categories(current);
+    categories.extend(updates.iter().map(|(k, v)| (k.clone(), *v)));
+    serde_json::to_string(&categories).map_err(|e| e.to_string())
+}
+
+/// 類別是否開啟（不檢查總開關）
+pub fn category_enabled(settings: &UserNotificationSettings, category: NotificationCategory) -> bool {
+    parse_categories(settings.categories.as_deref())
+        .get(category.as_str())
+        .copied()
+        .unwrap_or(true)
+}
+
+/// 是否可推送此類別（總開關與類別開關都開啟）
+pub fn is_enabled(settings: &UserNotificationSettings, category: NotificationCategory) -> bool {
+    settings.enabled.unwrap_or(true) && category_enabled(settings, category)
+}
+
+/// 載入使用者通知設定（未建立時為 None）
+pub async fn load_settings(rb: &RBatis, user_id: &str) -> Option<UserNotificationSettings> {
+    rb.query_decode(
+        "SELECT * FROM user_notification_settings WHERE user_id = ?",
+        vec![rbs::Value::String(user_id.to_string())],
+    )
+    .await
+    .unwrap_or(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fills_defaults_and_ignores_unknown() {
+        let categories = parse_categories(Some(r#"{"achievement":false,"bogus":false,"morning":"no"}"#));
+        assert_eq!(categories.len(), 6);
+        assert!(!categories["achievement"]);
+        assert!(categories["morning"]);
+        assert!(!categories.contains_key("bogus"));
+        assert!(parse_categories(None).values().all(|v| *v));
+    }
+
+    #[test]
+    fn test_merge_rejects_unknown_category() {
+        let updates = BTreeMap::from([("due_reminder".to_string(), false)]);
+        let merged = merge_categories(Some(r#"{"morning":false}"#), &updates).unwrap();
+        let parsed = parse_categories(Some(&merged));
+        assert!(!parsed["morning"] && !parsed["due_reminder"] && parsed["evening"]);
+
+        let updates = BTreeMap::from([("weekly".to_string(), true)]);
+        let err = merge_categories(None, &updates).unwrap_err();
+        assert!(err.contains("weekly"));
+    }
+
+    #[test]
+    fn test_event_categories() {
+        assert_eq!(NotificationCategory::for_event("level_up"), Some(NotificationCategory::Achievement));
+        assert_eq!(NotificationCategory::for_event("something_else"), None);
+        assert_eq!(NotificationCategory::parse("streak_risk"), Some(NotificationCategory::StreakRisk));
+    }
+
+    #[actix_web::test]
+    async fn test_migration_default_enables_all_categories() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "notify_categories").await;
+
+        rb.exec(
+            "INSERT INTO user_notification_settings (id, user_id) VALUES (?, ?)",
+            vec![rbs::Value::String("s1".to_string()), rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        let mut settings = load_settings(&rb, &user.id).await.unwrap();
+        assert_eq!(settings.categories.as_deref().map(|c| parse_categories(Some(c))), Some(parse_categories(None)));
+        assert!(NotificationCategory::ALL.iter().all(|c| is_enabled(&settings, *c)));
+
+        settings.categories =
+            merge_categories(settings.categories.as_deref(), &BTreeMap::from([("achievement".to_string(), false)])).ok();
+        assert!(!is_enabled(&settings, NotificationCategory::Achievement));
+        assert!(is_enabled(&settings, NotificationCategory::Morning));
+    }
+}
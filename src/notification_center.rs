@@ -0,0 +1,313 @@
+// 通知中心：以通知歷史呈現最近的通知與已讀狀態（不依賴 Web Push，iOS Safari 也能看到）
+//
+// 已讀（read_at）與已送達（seen_at）分開記錄：SSE / 回應附帶事件只代表送達，
+// 使用者在通知中心全部標為已讀時一併視為已送達。舊通知每天依保留策略清除。
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use serde::Serialize;
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::NotificationCenterConfig;
+use crate::models::NotificationHistory;
+
+// 通知中心列出的筆數
+const NOTIFICATION_CENTER_LIMIT: u64 = 50;
+// 清除舊通知的間隔
+const CLEANUP_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+static NOTIFICATION_CENTER_CONFIG: OnceLock<NotificationCenterConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: NotificationCenterConfig) {
+    log::info!(
+        "通知中心: 保留 {} 天、每位使用者最多 {} 則",
+        config.retention_days,
+        config.max_per_user
+    );
+    if NOTIFICATION_CENTER_CONFIG.set(config).is_err() {
+        log::warn!("通知中心設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static NotificationCenterConfig {
+    NOTIFICATION_CENTER_CONFIG.get_or_init(NotificationCenterConfig::default)
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationCenterItem {
+    pub id: String,
+    pub event_type: String,
+    pub title: String,
+    pub body: String,
+    pub data: serde_json::Value,
+    pub read: bool,
+    pub read_at: Option<String>,
+    pub created_at: Option<String>,
+}
+
+impl From<NotificationHistory> for NotificationCenterItem {
+    fn from(history: NotificationHistory) -> Self {
+        NotificationCenterItem {
+            id: history.id.unwrap_or_default(),
+            event_type: history.event_type.unwrap_or_default(),
+            title: history.title.unwrap_or_default(),
+            body: history.body.unwrap_or_default(),
+            data: history
+                .data
+                .as_deref()
+                .and_then(|d| serde_json::from_str(d).ok())
+                .unwrap_or(serde_json::Value::Null),
+            read: history.read_at.is_some(),
+            read_at: history.read_at.map(|t| t.to_rfc3339()),
+            created_at: history.created_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationCenter {
+    pub notifications: Vec<NotificationCenterItem>,
+    pub unread_count: i64,
+}
+
+/// 未讀通知數（供 App 圖示徽章使用）
+pub async fn unread_count(rb: &RBatis, user_id: &str) -> Result<i64, rbatis::Error> {
+    rb.query_decode(
+        "SELECT COUNT(*) AS count FROM notification_history WHERE user_id = ? AND read_at IS NULL",
+        vec![rbs::Value::String(user_id.to_string())],
+    )
+    .await
+}
+
+/// 將使用者所有未讀通知標為已讀；回傳這次標記的筆數
+pub async fn mark_all_read(rb: &RBatis, user_id: &str) -> Result<u64, rbatis::Error> {
+    let now = Utc::now().to_rfc3339();
+    let result = rb
+        .exec(
+            "UPDATE notification_history SET read_at = ?, seen_at = COALESCE(seen_at, ?) WHERE user_id = ? AND read_at IS NULL",
+            vec![
+                rbs::Value::String(now.clone()),
+                rbs::Value::String(now),
+                rbs::Value::String(user_id.to_string()),
+            ],
+        )
+        .await?;
+    Ok(result.rows_affected)
+}
+
+/// 依保留策略清除舊通知：超過保留天數，或每位使用者超過上限的較舊通知；回傳刪除筆數
+pub async fn cleanup(rb: &RBatis, config: &NotificationCenterConfig) -> Result<u64, rbatis::Error> {
+    let mut removed = 0;
+    if config.retention_days > 0 {
+        let cutoff = Utc::now() - chrono::Duration::days(config.retention_days);
+        removed += rb
+            .exec(
+                "DELETE FROM notification_history WHERE julianday(created_at) < julianday(?)",
+                vec![rbs::Value::String(cutoff.to_rfc3339())],
+            )
+            .await?
+            .rows_affected;
+    }
+    if config.max_per_user > 0 {
+        removed += rb
+            .exec(
+                "DELETE FROM notification_history WHERE id IN (
+                     SELECT id FROM (
+                         SELECT id, ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY julianday(created_at) DESC) AS rank
+                         FROM notification_history
+                     ) WHERE rank > ?
+                 )",
+                vec![rbs::Value::I64(config.max_per_user)],
+            )
+            .await?
+            .rows_affected;
+    }
+    Ok(removed)
+}
+
+/// 定期清除舊通知
+pub fn spawn_cleanup(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match cleanup(&rb, config()).await {
+                Ok(0) => {}
+                Ok(removed) => log::info!("已清除 {} 則舊通知", removed),
+                Err(e) => log::error!("清除舊通知失敗: {}", e),
+            }
+        }
+    });
+}
+
+/// 通知中心：最近 50 則通知與未讀數
+pub async fn get_notification_center(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let history: Result<Vec<NotificationHistory>, _> = rb
+        .query_decode(
+            "SELECT * FROM notification_history WHERE user_id = ? ORDER BY julianday(created_at) DESC LIMIT ?",
+            vec![rbs::Value::String(user_id.clone()), rbs::Value::U64(NOTIFICATION_CENTER_LIMIT)],
+        )
+        .await;
+    let result = match history {
+        Ok(history) => unread_count(rb.get_ref(), &user_id).await.map(|unread_count| NotificationCenter {
+            notifications: history.into_iter().map(NotificationCenterItem::from).collect(),
+            unread_count,
+        }),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(center) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(center),
+            message: "獲取通知中心成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取通知中心失敗: {}", e),
+        })),
+    }
+}
+
+/// 未讀通知數
+pub async fn get_unread_count(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match unread_count(rb.get_ref(), &user_id).await {
+        Ok(count) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "unread_count": count })),
+            message: "獲取未讀通知數成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取未讀通知數失敗: {}", e),
+        })),
+    }
+}
+
+/// 全部標為已讀
+pub async fn mark_all_notifications_read(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match mark_all_read(rb.get_ref(), &user_id).await {
+        Ok(marked) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "marked": marked, "unread_count": 0 })),
+            message: "已全部標為已讀".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("標記已讀失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_notification_center_read_state() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "notify_center").await;
+        let other = test_utils::create_user(&app, "notify_other").await;
+
+        crate::event_notifier::notify_level_up(&rb, &user.id, 1, 2).await;
+        let morning = serde_json::json!({"title": "早安！", "body": "今天有 2 個任務", "data": {"url": "/mission"}});
+        crate::event_notifier::notify_scheduled(&rb, &user.id, "morning", &morning).await;
+
+        let uri = format!("/api/users/{}/notification-center", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["unread_count"], 2);
+        let items = body["data"]["notifications"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().any(|n| n["event_type"] == "morning" && n["data"]["url"] == "/mission"));
+        assert!(items.iter().all(|n| n["read"] == false));
+
+        // 不能讀取其他使用者的通知中心
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, 403);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/mark-all-read", uri))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["marked"], 2);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/unread-count", uri))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["unread_count"], 0);
+        // 已讀的通知不再作為未送達事件附帶
+        assert!(crate::event_notifier::fetch_unseen_events(&rb, &user.id).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_cleanup_policy() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "notify_cleanup").await;
+
+        for days_ago in [0, 1, 2, 120] {
+            rb.exec(
+                "INSERT INTO notification_history (id, user_id, event_type, title, created_at) VALUES (?, ?, 'level_up', 't', ?)",
+                vec![
+                    rbs::Value::String(format!("n{}", days_ago)),
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::String((Utc::now() - chrono::Duration::days(days_ago)).to_rfc3339()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let config = NotificationCenterConfig { retention_days: 90, max_per_user: 2 };
+        assert_eq!(cleanup(&rb, &config).await.unwrap(), 2);
+        let remaining: Vec<serde_json::Value> = rb
+            .query_decode("SELECT id FROM notification_history ORDER BY id", vec![])
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![serde_json::json!({"id": "n0"}), serde_json::json!({"id": "n1"})]);
+    }
+}
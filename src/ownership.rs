@@ -0,0 +1,190 @@
+// 資源擁有權檢查：依 id 讀取任務、技能、職業主線、標籤、獎勵或專注時段並確認登入者可以存取
+//
+// 任務分兩種層級：
+//   assert_task_access — 擁有者或共享任務參與者（讀取、更新狀態、留言、附件等）
+//   assert_task_owner  — 只有擁有者（刪除、暫停、取消、重新開始、管理參與者等）
+// 找不到資源回傳 404，其他使用者的資源回傳 403；處理函式以 into_response() 轉為 ApiResponse。
+
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use rbatis::RBatis;
+use rbs::value;
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{FocusSession, Reward, Skill, Task};
+use crate::task_tags::Tag;
+
+#[derive(Debug)]
+pub enum AccessError {
+    /// 沒有登入者（未經 JwtAuth 的請求）
+    Unauthorized,
+    /// 資源不存在（內容為資源名稱，例如「任務」）
+    NotFound(&'static str),
+    /// 資源屬於其他使用者
+    Forbidden(&'static str),
+    Database(rbatis::Error),
+}
+
+impl AccessError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AccessError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AccessError::NotFound(_) => StatusCode::NOT_FOUND,
+            AccessError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AccessError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        let message = match &self {
+            AccessError::Unauthorized => "請先登入".to_string(),
+            AccessError::NotFound(resource) => format!("{}不存在", resource),
+            AccessError::Forbidden(resource) => format!("無權限存取此{}", resource),
+            AccessError::Database(e) => format!("查詢資料失敗: {}", e),
+        };
+        HttpResponse::build(self.status()).json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message,
+        })
+    }
+}
+
+impl From<rbatis::Error> for AccessError {
+    fn from(e: rbatis::Error) -> Self {
+        AccessError::Database(e)
+    }
+}
+
+/// 只需要登入的 API：回傳登入者
+pub fn assert_logged_in(auth_user: Option<&str>) -> Result<String, AccessError> {
+    auth_user.map(str::to_string).ok_or(AccessError::Unauthorized)
+}
+
+async fn load_task(rb: &RBatis, task_id: &str, auth_user: Option<&str>) -> Result<(Task, String), AccessError> {
+    let auth_user = auth_user.ok_or(AccessError::Unauthorized)?;
+    let task = Task::select_by_map(rb, value!{"id": task_id})
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(AccessError::NotFound("任務"))?;
+    Ok((task, auth_user.to_string()))
+}
+
+/// 讀取任務並確認登入者是擁有者
+pub async fn assert_task_owner(rb: &RBatis, task_id: &str, auth_user: Option<&str>) -> Result<Task, AccessError> {
+    let (task, auth_user) = load_task(rb, task_id, auth_user).await?;
+    if task.user_id.as_deref() != Some(auth_user.as_str()) {
+        log::warn!("使用者 {} 嘗試操作他人的任務 {}", auth_user, task_id);
+        return Err(AccessError::Forbidden("任務"));
+    }
+    Ok(task)
+}
+
+/// 讀取任務並確認登入者是擁有者或共享任務參與者
+pub async fn assert_task_access(rb: &RBatis, task_id: &str, auth_user: Option<&str>) -> Result<Task, AccessError> {
+    let (task, auth_user) = load_task(rb, task_id, auth_user).await?;
+    if !crate::shared_tasks::can_access_task(rb, &task, &auth_user).await {
+        log::warn!("使用者 {} 嘗試存取他人的任務 {}", auth_user, task_id);
+        return Err(AccessError::Forbidden("任務"));
+    }
+    Ok(task)
+}
+
+/// 讀取技能並確認登入者是擁有者
+pub async fn assert_skill_owner(rb: &RBatis, skill_id: &str, auth_user: Option<&str>) -> Result<Skill, AccessError> {
+    let auth_user = auth_user.ok_or(AccessError::Unauthorized)?;
+    let skill = Skill::select_by_map(rb, value!{"id": skill_id})
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(AccessError::NotFound("技能"))?;
+    if skill.user_id.as_deref() != Some(auth_user) {
+        log::warn!("使用者 {} 嘗試操作他人的技能 {}", auth_user, skill_id);
+        return Err(AccessError::Forbidden("技能"));
+    }
+    Ok(skill)
+}
+
+/// 確認登入者是職業主線的擁有者
+///
+/// 只讀 user_id：survey_answers 存的是 JSON 文字，SQLite 驅動會解成物件，整列解碼成 CareerMainlines 會失敗。
+pub async fn assert_mainline_owner(rb: &RBatis, mainline_id: &str, auth_user: Option<&str>) -> Result<(), AccessError> {
+    let auth_user = auth_user.ok_or(AccessError::Unauthorized)?;
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT user_id FROM career_mainlines WHERE id = ?",
+            vec![rbs::Value::String(mainline_id.to_string())],
+        )
+        .await?;
+    let row = rows.into_iter().next().ok_or(AccessError::NotFound("職業主線"))?;
+    if row["user_id"].as_str() != Some(auth_user) {
+        log::warn!("使用者 {} 嘗試操作他人的職業主線 {}", auth_user, mainline_id);
+        return Err(AccessError::Forbidden("職業主線"));
+    }
+    Ok(())
+}
+
+/// 讀取標籤並確認登入者是擁有者
+pub async fn assert_tag_owner(rb: &RBatis, tag_id: &str, auth_user: Option<&str>) -> Result<Tag, AccessError> {
+    let auth_user = auth_user.ok_or(AccessError::Unauthorized)?;
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT id, user_id, name, color, 0 AS usage_count, created_at FROM tag WHERE id = ?",
+            vec![rbs::Value::String(tag_id.to_string())],
+        )
+        .await?;
+    let row = rows.into_iter().next().ok_or(AccessError::NotFound("標籤"))?;
+    if row["user_id"].as_str() != Some(auth_user) {
+        log::warn!("使用者 {} 嘗試操作他人的標籤 {}", auth_user, tag_id);
+        return Err(AccessError::Forbidden("標籤"));
+    }
+    serde_json::from_value(row).map_err(|e| AccessError::Database(rbatis::Error::from(e.to_string())))
+}
+
+/// 讀取獎勵並確認登入者是擁有者
+pub async fn assert_reward_owner(rb: &RBatis, reward_id: &str, auth_user: Option<&str>) -> Result<Reward, AccessError> {
+    let auth_user = auth_user.ok_or(AccessError::Unauthorized)?;
+    let reward = Reward::select_by_map(rb, value!{"id": reward_id})
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(AccessError::NotFound("獎勵"))?;
+    if reward.user_id.as_deref() != Some(auth_user) {
+        log::warn!("使用者 {} 嘗試操作他人的獎勵 {}", auth_user, reward_id);
+        return Err(AccessError::Forbidden("獎勵"));
+    }
+    Ok(reward)
+}
+
+/// 讀取專注時段並確認登入者是擁有者
+pub async fn assert_focus_session_owner(rb: &RBatis, session_id: &str, auth_user: Option<&str>) -> Result<FocusSession, AccessError> {
+    let auth_user = auth_user.ok_or(AccessError::Unauthorized)?;
+    let session = FocusSession::select_by_map(rb, value!{"id": session_id})
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(AccessError::NotFound("專注時段"))?;
+    if session.user_id.as_deref() != Some(auth_user) {
+        log::warn!("使用者 {} 嘗試操作他人的專注時段 {}", auth_user, session_id);
+        return Err(AccessError::Forbidden("專注時段"));
+    }
+    Ok(session)
+}
+
+/// 以 user_id 參數指定對象的 API（例如聊天紀錄）：只能存取自己的資料
+pub fn assert_self(auth_user: Option<&str>, user_id: &str) -> Result<(), AccessError> {
+    match auth_user {
+        Some(auth_user) if auth_user == user_id => Ok(()),
+        Some(_) => Err(AccessError::Forbidden("使用者的資料")),
+        None => Err(AccessError::Unauthorized),
+    }
+}
+
+/// 同 assert_self，但管理員可以查看任何使用者的資料
+pub fn assert_self_or_admin(auth_user: Option<&str>, is_admin: bool, user_id: &str) -> Result<(), AccessError> {
+    if is_admin {
+        return Ok(());
+    }
+    assert_self(auth_user, user_id)
+}
@@ -55,6 +55,8 @@ pub enum ProgressEvent {
     Error {
         message: String,
         stage: String,
+        // 職業任務生成追蹤 ID（管理員可由 /api/admin/career/traces/{job_id} 查看各步驟）
+        trace_id: String,
     },
 }
 
@@ -77,20 +79,22 @@ pub async fn generate_career_tasks_progressive_sse(
     let req = request.into_inner();
     let rb_clone = rb.clone();
     let config_clone = config.clone();
+    let job_id = uuid::Uuid::new_v4().to_string();
 
     // 創建 SSE 通道
     let (tx, mut rx) = mpsc::channel::<ProgressEvent>(100);
 
-    // 在背景執行生成邏輯
-    tokio::spawn(async move {
-        if let Err(e) = run_progressive_generation(rb_clone, req, config_clone, tx.clone()).await {
-            log::error!("生成任務時發生錯誤: {}", e);
+    // 在背景執行生成邏輯（沿用請求的語言）
+    tokio::spawn(crate::language::in_current(async move {
+        if let Err(e) = run_progressive_generation(rb_clone, req, config_clone, &job_id, tx.clone()).await {
+            log::error!("生成任務時發生錯誤（追蹤 ID {}）: {}", job_id, e);
             let _ = tx.send(ProgressEvent::Error {
                 message: e.to_string(),
                 stage: "unknown".to_string(),
+                trace_id: job_id.clone(),
             }).await;
         }
-    });
+    }));
 
     // 建立 SSE 串流
     let stream = async_stream::stream! {
@@ -113,6 +117,7 @@ async fn run_progressive_generation(
     rb: web::Data<RBatis>,
     request: ProgressiveGenerationRequest,
     config: web::Data<crate::config::Config>,
+    job_id: &str,
     tx: mpsc::Sender<ProgressEvent>,
 ) -> anyhow::Result<()> {
 
@@ -135,6 +140,9 @@ async fn run_progressive_generation(
         }
     };
 
+    // 每個 AI 步驟的輸入雜湊、輸出與耗時都記入追蹤
+    let mut trace = crate::career_trace::CareerTrace::new(&rb, job_id, Some(&user_id));
+
     // 創建 AI 服務
     let ai_service = crate::ai_service::create_ai_service(&config.app.ai)?;
 
@@ -157,14 +165,22 @@ async fn run_progressive_generation(
 
     let outline_prompt = build_outline_prompt(&quiz_result, &request.selected_career, &request.survey_answers);
 
-    let outline_result = ai_service.generate_with_model(&config.app.ai.outline_model, &outline_prompt).await?;
+    let outline_result = trace
+        .step("outline", &config.app.ai.outline_model, &outline_prompt, ai_service.generate_with_model(&config.app.ai.outline_model, &outline_prompt))
+        .await?;
 
     // 解析大綱結果
-    let outline_json: serde_json::Value = serde_json::from_str(&outline_result.trim()
+    let outline_json: serde_json::Value = match serde_json::from_str(outline_result.trim()
         .trim_start_matches("```json")
         .trim_start_matches("```")
         .trim_end_matches("```")
-        .trim())?;
+        .trim()) {
+        Ok(outline) => outline,
+        Err(e) => {
+            trace.mark_failed(&format!("大綱 JSON 解析失敗: {}", e)).await;
+            return Err(e.into());
+        }
+    };
 
     tx.send(ProgressEvent::OutlineComplete {
         content: outline_json.clone(),
@@ -185,11 +201,20 @@ async fn run_progressive_generation(
 
     let detail_prompt = build_detail_prompt(&outline_result, &request.selected_career);
 
-    let detailed_result = ai_service.generate_with_model(&config.app.ai.detail_model, &detail_prompt).await?;
+    let detailed_result = trace
+        .step("details", &config.app.ai.detail_model, &detail_prompt, ai_service.generate_with_model(&config.app.ai.detail_model, &detail_prompt))
+        .await?;
 
     // 解析細節結果
-    let tasks_response = crate::career_routes::parse_ai_tasks_response(&detailed_result)
-        .map_err(|e| anyhow::anyhow!("解析任務失敗: {}", e))?;
+    let parsed_tasks = crate::career_routes::parse_ai_tasks_response(&detailed_result)
+        .map_err(|e| format!("解析任務失敗: {}", e));
+    let tasks_response = match parsed_tasks {
+        Ok(tasks) => tasks,
+        Err(message) => {
+            trace.mark_failed(&message).await;
+            return Err(anyhow::anyhow!(message));
+        }
+    };
     let tasks_json = serde_json::to_value(&tasks_response)?;
 
     tx.send(ProgressEvent::DetailsComplete {
@@ -216,7 +241,9 @@ async fn run_progressive_generation(
     log::debug!("資源推薦 prompt 前 200 字元: {}", preview);
 
     log::info!("📡 呼叫 Perplexity API 進行資源搜尋...");
-    let resource_result = ai_service.generate_with_model(&config.app.ai.resource_model, &resource_prompt).await
+    let resource_result = trace
+        .step("resources", &config.app.ai.resource_model, &resource_prompt, ai_service.generate_with_model(&config.app.ai.resource_model, &resource_prompt))
+        .await
         .unwrap_or_else(|e| {
             log::warn!("⚠️  資源推薦失敗（非致命）: {}", e);
             "{}".to_string()
@@ -238,12 +265,15 @@ async fn run_progressive_generation(
         .trim_end_matches("```")
         .trim();
 
-    let resources_json: serde_json::Value = serde_json::from_str(cleaned_resource_result)
-        .unwrap_or_else(|e| {
+    let resources_json: serde_json::Value = match serde_json::from_str(cleaned_resource_result) {
+        Ok(resources) => resources,
+        Err(e) => {
             log::error!("❌ 資源 JSON 解析失敗: {}", e);
             log::error!("前 500 字元: {}", truncate_str_safe(cleaned_resource_result, 500));
+            trace.mark_failed(&format!("資源 JSON 解析失敗: {}", e)).await;
             serde_json::json!({"resources": []})
-        });
+        }
+    };
 
     log::info!("📊 解析後的資源數據: {}", serde_json::to_string_pretty(&resources_json).unwrap_or_else(|_| "無法序列化".to_string()));
 
@@ -271,7 +301,9 @@ async fn run_progressive_generation(
         &tasks_response,
     );
 
-    let achievements_result = ai_service.generate_with_model(&config.app.ai.openai_model, &achievements_prompt).await?;
+    let achievements_result = trace
+        .step("achievements", &config.app.ai.openai_model, &achievements_prompt, ai_service.generate_with_model(&config.app.ai.openai_model, &achievements_prompt))
+        .await?;
 
     log::info!("✅ 成就生成 API 呼叫完成，回應長度: {} 字元", achievements_result.len());
 
@@ -282,12 +314,15 @@ async fn run_progressive_generation(
         .trim_end_matches("```")
         .trim();
 
-    let achievements_json: serde_json::Value = serde_json::from_str(cleaned_achievements_result)
-        .unwrap_or_else(|e| {
+    let achievements_json: serde_json::Value = match serde_json::from_str(cleaned_achievements_result) {
+        Ok(achievements) => achievements,
+        Err(e) => {
             log::error!("❌ 成就 JSON 解析失敗: {}", e);
             log::error!("前 500 字元: {}", truncate_str_safe(cleaned_achievements_result, 500));
+            trace.mark_failed(&format!("成就 JSON 解析失敗: {}", e)).await;
             serde_json::json!({"achievements": []})
-        });
+        }
+    };
 
     log::info!("📊 解析後的成就數據: {}", serde_json::to_string_pretty(&achievements_json).unwrap_or_else(|_| "無法序列化".to_string()));
 
@@ -343,6 +378,7 @@ async fn run_progressive_generation(
 
     let final_data = serde_json::json!({
         "preview_mode": true,
+        "trace_id": job_id,
         "quiz_result_id": request.quiz_result_id,
         "selected_career": request.selected_career,
         "user_id": user_id,
@@ -415,7 +451,7 @@ fn build_outline_prompt(
 }}
 ```
 
-**重要：只回傳 JSON，不要其他文字。必須使用繁體中文。**"#,
+**重要：只回傳 JSON，不要其他文字。必須使用{language}。**"#,
         career,
         extract_quiz_summary(&quiz_result.values_results),
         extract_quiz_summary(&quiz_result.interests_results),
@@ -424,6 +460,7 @@ fn build_outline_prompt(
         survey_answers.current_level,
         survey_answers.available_time,
         survey_answers.timeline,
+        language = crate::language::current().name(),
     )
 }
 
@@ -576,7 +613,7 @@ fn build_resource_prompt(tasks_json: &str, career: &str) -> String {
 - ⚠️ **優先推薦 2024-2025 年的最新內容**
 - ⚠️ **只回傳 JSON，不要其他文字**
 - ⚠️ **如果搜尋不到繁體中文資源，才推薦高品質英文資源**
-- ⚠️ **所有輸出內容（包括 description、title 等）必須使用繁體中文書寫**
+- ⚠️ **所有輸出內容（包括 description、title 等）必須使用{language}書寫**
 
 現在請開始搜尋並推薦資源："#,
         career,      // 第1個: 主標題
@@ -589,7 +626,8 @@ fn build_resource_prompt(tasks_json: &str, career: &str) -> String {
         career,      // 搜尋2-1
         career,      // 搜尋2-2
         career,      // 搜尋3-1
-        career       // 搜尋3-2
+        career,      // 搜尋3-2
+        language = crate::language::current().name()
     )
 }
 
@@ -609,6 +647,7 @@ fn build_achievements_prompt(
         .collect::<Vec<String>>()
         .join("\n");
 
+    let xp_range = crate::reward_config::current().career_achievement_xp;
     format!(
         r#"你是專業的遊戲化成就設計師。請為「{}」職業主線任務生成 4-8 個專屬成就。
 
@@ -646,14 +685,17 @@ fn build_achievements_prompt(
 
 ## 重要提醒
 - ⚠️ **只回傳 JSON，不要其他文字**
-- ⚠️ **所有內容必須使用繁體中文**
+- ⚠️ **所有內容必須使用{language}**
 - ⚠️ **生成 4-8 個成就，挑選最有意義的里程碑**
 - ⚠️ **每個成就必須關聯到具體的任務**
-- ⚠️ **experience_reward 根據難度設定為 30-100**
+- ⚠️ **experience_reward 根據難度設定為 {}-{}**
 
 現在請開始生成成就："#,
         career,
-        tasks_list
+        tasks_list,
+        xp_range.min,
+        xp_range.max,
+        language = crate::language::current().name()
     )
 }
 
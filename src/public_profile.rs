@@ -0,0 +1,386 @@
+// 公開個人檔案：使用者可將等級、稱號、主要技能與完成任務數公開給未登入的訪客
+//
+// 可見度分三種：private（預設，不公開）、unlisted（只能憑隨機 token 存取）、
+// public（另可用使用者自訂的唯一代號查詢）。每次查詢都直接讀取目前設定，變更立即生效。
+// 公開內容只包含白名單欄位，不含 email、任務內容與聊天紀錄。
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+
+const HANDLE_MIN_LEN: usize = 3;
+const HANDLE_MAX_LEN: usize = 30;
+// 公開檔案中列出的技能與成就數量
+const TOP_SKILL_LIMIT: i64 = 5;
+const RECENT_ACHIEVEMENT_LIMIT: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileVisibility {
+    Private,
+    Unlisted,
+    Public,
+}
+
+impl ProfileVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfileVisibility::Private => "private",
+            ProfileVisibility::Unlisted => "unlisted",
+            ProfileVisibility::Public => "public",
+        }
+    }
+
+    // 未知或空值一律視為不公開
+    pub fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some("unlisted") => ProfileVisibility::Unlisted,
+            Some("public") => ProfileVisibility::Public,
+            _ => ProfileVisibility::Private,
+        }
+    }
+}
+
+/// 擁有者看到的公開設定
+#[derive(Debug, Serialize)]
+pub struct ProfileVisibilitySettings {
+    pub visibility: ProfileVisibility,
+    pub handle: Option<String>,
+    // 不公開時不回傳 token
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileVisibilityRequest {
+    pub visibility: ProfileVisibility,
+    // 有帶時更新代號；空字串代表移除
+    pub handle: Option<String>,
+    #[serde(default)]
+    pub regenerate_token: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicSkill {
+    pub name: String,
+    pub icon: Option<String>,
+    pub level: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicAchievement {
+    pub name: String,
+    pub icon: Option<String>,
+    pub unlocked_at: Option<String>,
+}
+
+/// 訪客看到的個人檔案（只含白名單欄位）
+#[derive(Debug, Serialize)]
+pub struct PublicProfile {
+    pub display_name: String,
+    pub handle: Option<String>,
+    pub level: i64,
+    pub title: Option<String>,
+    pub achievements: Vec<PublicAchievement>,
+    pub top_skills: Vec<PublicSkill>,
+    pub completed_tasks: i64,
+}
+
+/// 代號規則：3～30 字元，英文小寫、數字、底線或連字號，且以英文字母開頭
+pub fn normalize_handle(handle: &str) -> std::result::Result<String, &'static str> {
+    let handle = handle.trim().to_lowercase();
+    if handle.len() < HANDLE_MIN_LEN || handle.len() > HANDLE_MAX_LEN {
+        return Err("代號長度需為 3～30 個字元");
+    }
+    if !handle.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Err("代號需以英文字母開頭");
+    }
+    if !handle.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-') {
+        return Err("代號只能包含英文字母、數字、底線或連字號");
+    }
+    Ok(handle)
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+fn internal_error(e: rbatis::Error) -> HttpResponse {
+    log::error!("公開個人檔案存取失敗: {}", e);
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("公開個人檔案存取失敗: {}", e))
+}
+
+async fn load_settings(rb: &RBatis, user_id: &str) -> std::result::Result<Option<ProfileVisibilitySettings>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT profile_visibility, profile_handle, profile_token FROM user_profile WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(rows.first().map(|row| {
+        let visibility = ProfileVisibility::parse(row["profile_visibility"].as_str());
+        ProfileVisibilitySettings {
+            visibility,
+            handle: row["profile_handle"].as_str().map(str::to_string),
+            token: (visibility != ProfileVisibility::Private)
+                .then(|| row["profile_token"].as_str().map(str::to_string))
+                .flatten(),
+        }
+    }))
+}
+
+/// 取得自己的公開設定
+pub async fn get_profile_visibility(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_settings(rb.get_ref(), &user_id).await {
+        Ok(Some(settings)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            message: "獲取公開設定成功".to_string(),
+        })),
+        Ok(None) => Ok(error_response(StatusCode::NOT_FOUND, "找不到該使用者資料")),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+/// 更新公開設定（首次公開或要求重新產生時配發新的 token，舊連結隨即失效）
+pub async fn update_profile_visibility(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<UpdateProfileVisibilityRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let rb = rb.get_ref();
+
+    let mut sets = vec!["profile_visibility = ?", "updated_at = ?"];
+    let mut args = vec![
+        rbs::Value::String(req.visibility.as_str().to_string()),
+        rbs::Value::String(Utc::now().to_rfc3339()),
+    ];
+    match req.handle.as_deref().map(str::trim) {
+        Some("") => {
+            sets.push("profile_handle = NULL");
+        }
+        Some(handle) => match normalize_handle(handle) {
+            Ok(handle) => {
+                sets.push("profile_handle = ?");
+                args.push(rbs::Value::String(handle));
+            }
+            Err(message) => return Ok(error_response(StatusCode::BAD_REQUEST, message)),
+        },
+        None => {}
+    }
+    if req.regenerate_token {
+        sets.push("profile_token = ?");
+    } else {
+        sets.push("profile_token = COALESCE(profile_token, ?)");
+    }
+    args.push(rbs::Value::String(uuid::Uuid::new_v4().simple().to_string()));
+    args.push(rbs::Value::String(user_id.clone()));
+
+    let sql = format!("UPDATE user_profile SET {} WHERE user_id = ?", sets.join(", "));
+    match rb.exec(&sql, args).await {
+        Ok(result) if result.rows_affected == 0 => {
+            return Ok(error_response(StatusCode::NOT_FOUND, "找不到該使用者資料"));
+        }
+        Ok(_) => {}
+        Err(e) if e.to_string().contains("UNIQUE") => {
+            return Ok(error_response(StatusCode::CONFLICT, "此代號已被其他使用者使用"));
+        }
+        Err(e) => return Ok(internal_error(e)),
+    }
+
+    match load_settings(rb, &user_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: settings,
+            message: "公開設定已更新".to_string(),
+        })),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+/// 依代號（僅 public）或 token（unlisted 與 public）找出對應的使用者
+async fn resolve_profile(rb: &RBatis, key: &str) -> std::result::Result<Option<serde_json::Value>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT p.user_id, p.level, p.title, p.profile_handle, p.profile_visibility, u.name
+             FROM user_profile p JOIN user u ON u.id = p.user_id
+             WHERE (p.profile_visibility = 'public' AND p.profile_handle = ?)
+                OR (p.profile_visibility IN ('unlisted', 'public') AND p.profile_token = ?)
+             LIMIT 1",
+            vec![
+                rbs::Value::String(key.to_lowercase()),
+                rbs::Value::String(key.to_string()),
+            ],
+        )
+        .await?;
+    Ok(rows.into_iter().next())
+}
+
+async fn load_public_profile(rb: &RBatis, key: &str) -> std::result::Result<Option<PublicProfile>, rbatis::Error> {
+    let Some(row) = resolve_profile(rb, key).await? else {
+        return Ok(None);
+    };
+    let user_id = row["user_id"].as_str().unwrap_or_default().to_string();
+
+    let skills: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT name, icon, level FROM skill WHERE user_id = ?
+             ORDER BY level DESC, experience DESC, name LIMIT ?",
+            vec![rbs::Value::String(user_id.clone()), rbs::Value::I64(TOP_SKILL_LIMIT)],
+        )
+        .await?;
+    let achievements: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT a.name, a.icon, ua.achieved_at FROM user_achievement ua
+             JOIN achievement a ON a.id = ua.achievement_id
+             WHERE ua.user_id = ? ORDER BY ua.achieved_at DESC LIMIT ?",
+            vec![rbs::Value::String(user_id.clone()), rbs::Value::I64(RECENT_ACHIEVEMENT_LIMIT)],
+        )
+        .await?;
+    let completed_tasks: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) AS count FROM task WHERE user_id = ? AND status IN (2, 6)",
+            vec![rbs::Value::String(user_id)],
+        )
+        .await?;
+
+    // 只有 public 才顯示代號，避免 unlisted 的 token 連結洩漏代號
+    let handle = (ProfileVisibility::parse(row["profile_visibility"].as_str()) == ProfileVisibility::Public)
+        .then(|| row["profile_handle"].as_str().map(str::to_string))
+        .flatten();
+
+    Ok(Some(PublicProfile {
+        display_name: row["name"].as_str().unwrap_or("冒險者").to_string(),
+        handle,
+        level: row["level"].as_i64().unwrap_or(1),
+        title: row["title"].as_str().map(str::to_string),
+        achievements: achievements
+            .iter()
+            .map(|a| PublicAchievement {
+                name: a["name"].as_str().unwrap_or("未知成就").to_string(),
+                icon: a["icon"].as_str().map(str::to_string),
+                unlocked_at: a["achieved_at"].as_str().map(str::to_string),
+            })
+            .collect(),
+        top_skills: skills
+            .iter()
+            .map(|s| PublicSkill {
+                name: s["name"].as_str().unwrap_or_default().to_string(),
+                icon: s["icon"].as_str().map(str::to_string),
+                level: s["level"].as_i64().unwrap_or(1),
+            })
+            .collect(),
+        completed_tasks,
+    }))
+}
+
+/// 公開個人檔案（不需登入）
+pub async fn get_public_profile(rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let key = path.into_inner();
+    match load_public_profile(rb.get_ref(), &key).await {
+        Ok(Some(profile)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(profile),
+            message: "獲取公開個人檔案成功".to_string(),
+        })),
+        Ok(None) => Ok(error_response(StatusCode::NOT_FOUND, "個人檔案不存在或未公開")),
+        Err(e) => Ok(internal_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_handle() {
+        assert_eq!(normalize_handle(" Hero_01 "), Ok("hero_01".to_string()));
+        assert!(normalize_handle("ab").is_err());
+        assert!(normalize_handle("1hero").is_err());
+        assert!(normalize_handle("hero!").is_err());
+        assert!(normalize_handle(&"a".repeat(31)).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_visibility_controls_public_lookup() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "profile_owner").await;
+        let settings_uri = format!("/api/users/{}/profile-visibility", user.id);
+        let lookup = |key: &str| actix_web::test::TestRequest::get().uri(&format!("/api/public/profiles/{}", key)).to_request();
+
+        // 預設不公開
+        let req = actix_web::test::TestRequest::get().uri(&settings_uri).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["visibility"], "private");
+        assert!(body["data"]["token"].is_null());
+
+        // unlisted：只能憑 token，代號查不到
+        let req = actix_web::test::TestRequest::put()
+            .uri(&settings_uri)
+            .insert_header(user.auth())
+            .set_json(json!({"visibility": "unlisted", "handle": "Hero"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let token = body["data"]["token"].as_str().unwrap().to_string();
+        assert_eq!(call_json(&app, lookup("hero")).await.0, 404);
+        let (status, body) = call_json(&app, lookup(&token)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["display_name"], "profile_owner");
+        assert!(body["data"]["handle"].is_null());
+        assert!(body["data"].get("email").is_none());
+
+        // public：代號可查（不分大小寫）
+        let req = actix_web::test::TestRequest::put()
+            .uri(&settings_uri)
+            .insert_header(user.auth())
+            .set_json(json!({"visibility": "public"}))
+            .to_request();
+        call_json(&app, req).await;
+        let (status, body) = call_json(&app, lookup("HERO")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["handle"], "hero");
+
+        // 代號重複
+        let other = test_utils::create_user(&app, "profile_other").await;
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("/api/users/{}/profile-visibility", other.id))
+            .insert_header(other.auth())
+            .set_json(json!({"visibility": "public", "handle": "hero"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 409);
+
+        // 改回不公開後立即失效
+        let req = actix_web::test::TestRequest::put()
+            .uri(&settings_uri)
+            .insert_header(user.auth())
+            .set_json(json!({"visibility": "private"}))
+            .to_request();
+        call_json(&app, req).await;
+        assert_eq!(call_json(&app, lookup("hero")).await.0, 404);
+        assert_eq!(call_json(&app, lookup(&token)).await.0, 404);
+    }
+}
@@ -1,13 +1,19 @@
-#![cfg(feature = "push-notifications")]
+// 定時通知排程：早安摘要、晚間總結、連續紀錄提醒與自訂時段
+//
+// 通知一律寫入通知歷史（通知中心），Web Push 只在啟用 push-notifications 時發送。
+// 管理員可用模擬 API 指定時間跑同一套判斷，取得每條規則的結果與會送出的通知內容。
 
+use actix_web::{web, HttpRequest, HttpResponse, Result};
 use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
 use tokio_cron_scheduler::{Job, JobScheduler};
 use log::{info, error};
-use chrono::{Utc, Timelike, FixedOffset, NaiveDate, TimeZone};
-use crate::models::{PushNotificationPayload, UserNotificationSettings};
-use crate::push_service::PushService;
+use chrono::{DateTime, Utc, Timelike, FixedOffset, NaiveDate, NaiveTime};
+use crate::ai_tasks::ApiResponse;
+use crate::models::UserNotificationSettings;
 use crate::notification_generator::NotificationGenerator;
 use crate::calendar_service::CalendarService;
+use crate::notification_categories::{self, NotificationCategory};
 
 /// 啟動推送通知調度器
 pub async fn start_push_scheduler(
@@ -31,23 +37,12 @@ pub async fn start_push_scheduler(
         let calendar = calendar_for_job.clone();
 
         Box::pin(async move {
-            // 使用 UTC+8 時區
-            let tz = FixedOffset::east_opt(8 * 3600).unwrap();
-            let now = Utc::now().with_timezone(&tz);
-            let current_time = format!("{:02}:{:02}", now.hour(), now.minute());
-            let today = now.date_naive();
-
-            info!("檢查定時推送通知任務 - 當前時間: {} (UTC+8)", current_time);
-
-            match process_scheduled_notifications(&rb, &calendar, &current_time, today).await {
-                Ok(total_sent) => {
-                    if total_sent > 0 {
-                        info!("定時推送完成：共發送 {} 個通知", total_sent);
-                    }
-                }
-                Err(e) => {
-                    error!("定時推送失敗: {}", e);
-                }
+            let tick = ScheduleTick::at(Utc::now(), &calendar);
+            info!("檢查定時推送通知任務 - 當前時間: {} (UTC+8)", tick.current_time);
+
+            let total_sent = process_scheduled_notifications(&rb, &tick).await;
+            if total_sent > 0 {
+                info!("定時推送完成：共發送 {} 個通知", total_sent);
             }
         })
     })?;
@@ -67,20 +62,69 @@ pub async fn start_push_scheduler(
     Ok(())
 }
 
-/// 處理定時推送通知
-async fn process_scheduled_notifications(
-    rb: &RBatis,
-    calendar: &CalendarService,
-    current_time: &str,
-    today: NaiveDate,
-) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
-    let mut total_sent = 0;
+/// 一次排程檢查的時間點（以 UTC+8 判斷時段與日期）
+pub struct ScheduleTick {
+    pub now: DateTime<Utc>,
+    // HH:MM，與通知設定中的時間比對
+    pub current_time: String,
+    pub local_time: NaiveTime,
+    pub today: NaiveDate,
+    pub is_holiday: bool,
+}
+
+impl ScheduleTick {
+    pub fn at(now: DateTime<Utc>, calendar: &CalendarService) -> Self {
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let local = now.with_timezone(&tz);
+        let today = local.date_naive();
+        ScheduleTick {
+            now,
+            current_time: format!("{:02}:{:02}", local.hour(), local.minute()),
+            local_time: local.time(),
+            today,
+            is_holiday: calendar.is_holiday(today),
+        }
+    }
+}
+
+/// 排程判斷過程：評估過的每條規則與（模擬時）會送出的通知
+#[derive(Debug, Default, Serialize)]
+pub struct SchedulerTrace {
+    pub rules: Vec<RuleEvaluation>,
+    pub notifications: Vec<PlannedNotification>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleEvaluation {
+    pub rule: String,
+    pub passed: bool,
+    pub detail: String,
+}
 
-    // 判斷今天是工作日還是假日
-    let is_holiday = calendar.is_holiday(today);
-    let is_workday = !is_holiday;
+/// 模擬模式中會寫入通知中心的通知
+#[derive(Debug, Serialize)]
+pub struct PlannedNotification {
+    pub user_id: String,
+    pub event_type: String,
+    pub title: String,
+    pub body: String,
+    pub data: serde_json::Value,
+    // 是否會發送 Web Push（仍需使用者已訂閱且伺服器啟用 push-notifications）
+    pub would_push: bool,
+    pub push_blocked_by: Option<&'static str>,
+}
 
-    info!("今天是 {}，工作日: {}, 假日: {}", today, is_workday, is_holiday);
+impl SchedulerTrace {
+    /// 記錄規則評估結果並回傳是否通過
+    fn check(&mut self, rule: impl Into<String>, passed: bool, detail: impl Into<String>) -> bool {
+        self.rules.push(RuleEvaluation { rule: rule.into(), passed, detail: detail.into() });
+        passed
+    }
+}
+
+/// 處理定時推送通知；回傳發送的通知數
+async fn process_scheduled_notifications(rb: &RBatis, tick: &ScheduleTick) -> usize {
+    info!("今天是 {}，工作日: {}, 假日: {}", tick.today, !tick.is_holiday, tick.is_holiday);
 
     // 查詢所有已啟用通知的用戶設定
     let settings_list: Vec<UserNotificationSettings> = rb
@@ -93,61 +137,170 @@ async fn process_scheduled_notifications(
 
     info!("找到 {} 個已啟用通知的用戶", settings_list.len());
 
+    let mut total_sent = 0;
     for settings in settings_list {
-        // 檢查是否應該在今天發送通知
-        let should_notify = if is_workday {
-            settings.notify_on_workdays.unwrap_or(true)
-        } else {
-            settings.notify_on_holidays.unwrap_or(false)
-        };
+        total_sent += evaluate_user(rb, &settings, tick, false, &mut SchedulerTrace::default()).await;
+    }
+    total_sent
+}
+
+/// 依使用者的通知設定決定此時點要送出的通知；dry_run 時只記錄在 trace，不寫入也不推送
+///
+/// 排程與模擬 API 共用此流程，模擬結果不會與實際行為分歧
+async fn evaluate_user(
+    rb: &RBatis,
+    settings: &UserNotificationSettings,
+    tick: &ScheduleTick,
+    dry_run: bool,
+    trace: &mut SchedulerTrace,
+) -> usize {
+    let Some(user_id) = settings.user_id.as_deref() else {
+        return 0;
+    };
+    let mut total = 0;
+
+    if !trace.check("enabled", settings.enabled == Some(true), "通知總開關") {
+        return 0;
+    }
 
-        if !should_notify {
-            continue;
+    // 檢查是否應該在今天發送通知
+    let (day_kind, should_notify) = if tick.is_holiday {
+        ("假日", settings.notify_on_holidays.unwrap_or(false))
+    } else {
+        ("工作日", settings.notify_on_workdays.unwrap_or(true))
+    };
+    let detail = format!("{} 是{}，{}", tick.today, day_kind, if should_notify { "設定為發送" } else { "設定為不發送" });
+    if !trace.check("calendar", should_notify, detail) {
+        return 0;
+    }
+
+    // 早上通知
+    if trace.check("morning.enabled", settings.morning_enabled.unwrap_or(false), "早安摘要開關")
+        && trace.check("morning.category", category_enabled(settings, NotificationCategory::Morning), "morning 類別開關")
+    {
+        let morning_time = settings.morning_time.as_deref().unwrap_or("08:00");
+        let detail = format!("排定 {}，目前 {}", morning_time, tick.current_time);
+        if trace.check("morning.time", tick.current_time == morning_time, detail) {
+            match NotificationGenerator::generate_morning_notification(rb, user_id).await {
+                Ok(notification) => total += deliver(rb, user_id, "morning", &notification, tick, dry_run, trace).await,
+                Err(e) => error!("產生早上通知失敗 (user_id: {}): {}", user_id, e),
+            }
         }
+    }
 
-        let user_id = match &settings.user_id {
-            Some(id) => id,
-            None => continue,
-        };
+    // 晚上通知
+    let evening_time = settings.evening_time.as_deref().unwrap_or("22:00");
+    if trace.check("evening.enabled", settings.evening_enabled.unwrap_or(false), "晚間總結開關")
+        && trace.check("evening.category", category_enabled(settings, NotificationCategory::Evening), "evening 類別開關")
+    {
+        let detail = format!("排定 {}，目前 {}", evening_time, tick.current_time);
+        if trace.check("evening.time", tick.current_time == evening_time, detail) {
+            match NotificationGenerator::generate_evening_notification(rb, user_id).await {
+                Ok(notification) => total += deliver(rb, user_id, "evening", &notification, tick, dry_run, trace).await,
+                Err(e) => error!("產生晚上通知失敗 (user_id: {}): {}", user_id, e),
+            }
 
-        // 檢查早上通知
-        if settings.morning_enabled.unwrap_or(false) {
-            let morning_time = settings.morning_time.as_deref().unwrap_or("08:00");
-            if current_time == morning_time {
-                info!("為用戶 {} 發送早上通知", user_id);
-                if send_morning_notification(rb, user_id).await.is_ok() {
-                    total_sent += 1;
+            // 今日零完成時提醒監督夥伴
+            if trace.check("partner_nudge.enabled", settings.notify_partner_on_miss.unwrap_or(false), "零完成時提醒夥伴") {
+                match crate::friends::partner_nudges(rb, user_id).await {
+                    Ok(nudges) => {
+                        let detail = format!("{} 位夥伴會收到提醒（今天已完成任務時不提醒）", nudges.len());
+                        trace.check("partner_nudge.missed", !nudges.is_empty(), detail);
+                        for (partner_id, notification) in nudges {
+                            total += deliver(rb, &partner_id, "partner_nudge", &notification, tick, dry_run, trace).await;
+                        }
+                    }
+                    Err(e) => error!("提醒夥伴失敗 (user_id: {}): {}", user_id, e),
                 }
             }
         }
+    }
 
-        // 檢查晚上通知
-        if settings.evening_enabled.unwrap_or(false) {
-            let evening_time = settings.evening_time.as_deref().unwrap_or("22:00");
-            if current_time == evening_time {
-                info!("為用戶 {} 發送晚上通知", user_id);
-                if send_evening_notification(rb, user_id).await.is_ok() {
-                    total_sent += 1;
+    // 晚間總結前提醒快中斷的連續紀錄（每天最多一則）
+    if trace.check("streak_risk.category", category_enabled(settings, NotificationCategory::StreakRisk), "streak_risk 類別開關") {
+        let reminder_time = crate::streak_reminder::reminder_time_for(evening_time);
+        let detail = format!("提醒時間 {}，目前 {}", reminder_time.as_deref().unwrap_or("無效"), tick.current_time);
+        if trace.check("streak_risk.time", reminder_time.as_deref() == Some(tick.current_time.as_str()), detail) {
+            match crate::streak_reminder::pending_reminder(rb, user_id).await {
+                Ok(Some(notification)) => {
+                    trace.check("streak_risk.at_risk", true, "有快中斷的連續紀錄");
+                    total += deliver(rb, user_id, "streak_risk", &notification, tick, dry_run, trace).await;
+                }
+                Ok(None) => {
+                    trace.check("streak_risk.at_risk", false, "沒有快中斷的連續紀錄，或今天已提醒過");
                 }
+                Err(e) => error!("連續紀錄中斷提醒失敗 (user_id: {}): {}", user_id, e),
             }
         }
+    }
 
-        // 檢查自定義通知時段
-        if let Some(custom_schedules_str) = &settings.custom_schedules {
-            if let Ok(custom_schedules) = serde_json::from_str::<Vec<CustomScheduleItem>>(custom_schedules_str) {
-                for schedule in custom_schedules {
-                    if schedule.enabled && current_time == schedule.time {
-                        info!("為用戶 {} 發送自定義通知", user_id);
-                        if send_custom_notification(rb, user_id).await.is_ok() {
-                            total_sent += 1;
-                        }
-                    }
+    // 自定義通知時段
+    if !trace.check("custom.category", category_enabled(settings, NotificationCategory::Custom), "custom 類別開關") {
+        return total;
+    }
+    if let Some(custom_schedules_str) = &settings.custom_schedules {
+        let Ok(custom_schedules) = serde_json::from_str::<Vec<CustomScheduleItem>>(custom_schedules_str) else {
+            trace.check("custom.schedules", false, "自訂時段格式無法解析");
+            return total;
+        };
+        for (index, schedule) in custom_schedules.into_iter().enumerate() {
+            let detail = format!(
+                "排定 {}（{}），目前 {}",
+                schedule.time,
+                if schedule.enabled { "啟用" } else { "停用" },
+                tick.current_time
+            );
+            if trace.check(format!("custom[{}]", index), schedule.enabled && tick.current_time == schedule.time, detail) {
+                match NotificationGenerator::generate_custom_notification(rb, user_id).await {
+                    Ok(notification) => total += deliver(rb, user_id, "custom", &notification, tick, dry_run, trace).await,
+                    Err(e) => error!("產生自定義通知失敗 (user_id: {}): {}", user_id, e),
                 }
             }
         }
     }
 
-    Ok(total_sent)
+    total
+}
+
+/// 送出通知（寫入通知中心並依設定推送）；dry_run 時改為記錄會送出的內容與推送判斷
+async fn deliver(
+    rb: &RBatis,
+    user_id: &str,
+    event_type: &str,
+    notification: &serde_json::Value,
+    tick: &ScheduleTick,
+    dry_run: bool,
+    trace: &mut SchedulerTrace,
+) -> usize {
+    if !dry_run {
+        info!("為用戶 {} 發送 {} 通知", user_id, event_type);
+        crate::event_notifier::notify_scheduled(rb, user_id, event_type, notification).await;
+        return 1;
+    }
+
+    let recipient_settings = notification_categories::load_settings(rb, user_id).await;
+    let blocked_by = crate::event_notifier::push_block_reason(recipient_settings.as_ref(), event_type, tick.local_time);
+    let detail = match blocked_by {
+        Some(reason) => format!("寫入通知中心，不推送（{}）", reason),
+        None => "寫入通知中心並推送".to_string(),
+    };
+    trace.check(format!("{}.push", event_type), blocked_by.is_none(), detail);
+
+    let (title, body, data) = crate::event_notifier::scheduled_parts(notification);
+    trace.notifications.push(PlannedNotification {
+        user_id: user_id.to_string(),
+        event_type: event_type.to_string(),
+        title,
+        body,
+        data,
+        would_push: blocked_by.is_none(),
+        push_blocked_by: blocked_by,
+    });
+    1
+}
+
+fn category_enabled(settings: &UserNotificationSettings, category: NotificationCategory) -> bool {
+    notification_categories::category_enabled(settings, category)
 }
 
 #[derive(serde::Deserialize)]
@@ -158,77 +311,114 @@ struct CustomScheduleItem {
     schedule_type: String,
 }
 
-/// 發送早上通知
-async fn send_morning_notification(
-    rb: &RBatis,
-    user_id: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // 生成通知內容
-    let notification = NotificationGenerator::generate_morning_notification(rb, user_id).await?;
-
-    // 轉換為 PushNotificationPayload
-    let payload = PushNotificationPayload {
-        title: notification["title"].as_str().unwrap_or("早安").to_string(),
-        body: notification["body"].as_str().unwrap_or("").to_string(),
-        icon: notification["icon"].as_str().map(|s| s.to_string()),
-        badge: notification["badge"].as_str().map(|s| s.to_string()),
-        tag: notification["tag"].as_str().map(|s| s.to_string()),
-        data: notification.get("data").cloned(),
-    };
-
-    // 發送推送
-    let push_service = PushService::new()?;
-    push_service.send_notification_to_user(rb, user_id, &payload).await?;
-
-    Ok(())
+#[derive(Deserialize)]
+pub struct SimulatePushRequest {
+    pub user_id: String,
+    // 模擬的目前時間（RFC 3339）
+    #[serde(with = "crate::datetime_format::rfc3339")]
+    pub now: DateTime<Utc>,
 }
 
-/// 發送晚上通知
-async fn send_evening_notification(
-    rb: &RBatis,
-    user_id: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // 生成通知內容
-    let notification = NotificationGenerator::generate_evening_notification(rb, user_id).await?;
-
-    // 轉換為 PushNotificationPayload
-    let payload = PushNotificationPayload {
-        title: notification["title"].as_str().unwrap_or("今天辛苦了").to_string(),
-        body: notification["body"].as_str().unwrap_or("").to_string(),
-        icon: notification["icon"].as_str().map(|s| s.to_string()),
-        badge: notification["badge"].as_str().map(|s| s.to_string()),
-        tag: notification["tag"].as_str().map(|s| s.to_string()),
-        data: notification.get("data").cloned(),
+/// POST /api/admin/push/simulate：以指定時間跑一次推送排程的判斷，不寫入也不推送
+///
+/// 時段與日期依模擬時間判斷；通知內容、連續紀錄與夥伴提醒依資料庫目前的資料產生。
+pub async fn simulate_push(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    body: web::Json<SimulatePushRequest>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+    let SimulatePushRequest { user_id, now } = body.into_inner();
+    let Some(settings) = notification_categories::load_settings(rb.get_ref(), &user_id).await else {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "此使用者尚未建立通知設定".to_string(),
+        }));
     };
 
-    // 發送推送
-    let push_service = PushService::new()?;
-    push_service.send_notification_to_user(rb, user_id, &payload).await?;
-
-    Ok(())
+    let tick = ScheduleTick::at(now, crate::calendar_service::shared());
+    let mut trace = SchedulerTrace::default();
+    let count = evaluate_user(rb.get_ref(), &settings, &tick, true, &mut trace).await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "user_id": user_id,
+            "now": tick.now.to_rfc3339(),
+            "local_time": tick.current_time,
+            "date": tick.today,
+            "is_holiday": tick.is_holiday,
+            "rules": trace.rules,
+            "notifications": trace.notifications,
+        })),
+        message: format!("模擬完成，此時點會送出 {} 則通知", count),
+    }))
 }
 
-/// 發送自定義通知
-async fn send_custom_notification(
-    rb: &RBatis,
-    user_id: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // 生成通知內容
-    let notification = NotificationGenerator::generate_custom_notification(rb, user_id).await?;
-
-    // 轉換為 PushNotificationPayload
-    let payload = PushNotificationPayload {
-        title: notification["title"].as_str().unwrap_or("人生升級系統").to_string(),
-        body: notification["body"].as_str().unwrap_or("").to_string(),
-        icon: notification["icon"].as_str().map(|s| s.to_string()),
-        badge: notification["badge"].as_str().map(|s| s.to_string()),
-        tag: notification["tag"].as_str().map(|s| s.to_string()),
-        data: notification.get("data").cloned(),
-    };
-
-    // 發送推送
-    let push_service = PushService::new()?;
-    push_service.send_notification_to_user(rb, user_id, &payload).await?;
-
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{self, call_json};
+    use rbs::Value;
+
+    #[actix_web::test]
+    async fn test_simulate_push_traces_rules_without_sending() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let admin = test_utils::create_user(&app, "push-sim-admin").await;
+        let user = test_utils::create_user(&app, "push-sim-user").await;
+        test_utils::grant_admin("push-sim-admin");
+        rb.exec(
+            "INSERT INTO user_notification_settings (id, user_id, enabled, notify_on_workdays, notify_on_holidays,
+                 morning_enabled, morning_time, evening_enabled, evening_time, quiet_hours_start, quiet_hours_end)
+             VALUES ('sim', ?, 1, 1, 1, 1, '08:00', 1, '22:00', '07:30', '08:30')",
+            vec![Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        // UTC 00:00 即 UTC+8 的 08:00
+        let payload = serde_json::json!({"user_id": user.id, "now": "2026-03-04T00:00:00Z"});
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/admin/push/simulate")
+            .insert_header(user.auth())
+            .set_json(&payload)
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/admin/push/simulate")
+            .insert_header(admin.auth())
+            .set_json(&payload)
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let data = &body["data"];
+        assert_eq!(data["local_time"], "08:00");
+
+        let rule = |name: &str| data["rules"].as_array().unwrap().iter().find(|r| r["rule"] == name).cloned();
+        assert_eq!(rule("morning.time").unwrap()["passed"], true);
+        assert_eq!(rule("evening.time").unwrap()["passed"], false);
+        assert_eq!(rule("morning.push").unwrap()["passed"], false);
+
+        let notifications = data["notifications"].as_array().unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0]["event_type"], "morning");
+        assert!(!notifications[0]["body"].as_str().unwrap().is_empty());
+        assert_eq!(notifications[0]["would_push"], false);
+        assert_eq!(notifications[0]["push_blocked_by"], "quiet_hours");
+
+        // 模擬不寫入通知中心
+        let written: i64 = rb
+            .query_decode("SELECT COUNT(*) AS count FROM notification_history WHERE user_id = ?", vec![Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(written, 0);
+    }
 }
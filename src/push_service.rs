@@ -5,28 +5,24 @@ use crate::models::{PushSubscription, SubscribeRequest, UnsubscribeRequest, Push
 use chrono::Utc;
 use uuid::Uuid;
 use web_push::*;
-use std::env;
-use std::fs::File;
 use log::{info, error};
 use url::Url;
 
 /// 推送服務 - 處理Web Push Notification相關功能
 pub struct PushService {
-    vapid_private_key: String,
+    vapid_private_key_pem: String,
     vapid_public_key: String,
 }
 
 impl PushService {
     /// 創建新的推送服務實例
     pub fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let vapid_private_key = env::var("VAPID_PRIVATE_KEY")
-            .map_err(|_| "環境變數中找不到 VAPID_PRIVATE_KEY")?;
-        let vapid_public_key = env::var("VAPID_PUBLIC_KEY")
-            .map_err(|_| "環境變數中找不到 VAPID_PUBLIC_KEY")?;
+        let keys = crate::vapid_keys::current()
+            .ok_or("尚未設定 VAPID 金鑰（VAPID_PUBLIC_KEY 與 vapid_private.pem）")?;
 
         Ok(Self {
-            vapid_private_key,
-            vapid_public_key,
+            vapid_private_key_pem: keys.private_key_pem,
+            vapid_public_key: keys.public_key,
         })
     }
 
@@ -173,11 +169,24 @@ impl PushService {
         Ok(subscriptions)
     }
 
-    /// 發送推送通知到指定訂閱
+    /// 發送推送通知到指定訂閱（結果記錄供管理員狀態查詢）
     pub async fn send_notification(
         &self,
         subscription: &PushSubscription,
         payload: &PushNotificationPayload,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let result = self.deliver(subscription, payload).await;
+        crate::vapid_keys::record_send_result(
+            subscription.endpoint.as_deref().unwrap_or_default(),
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+        result
+    }
+
+    async fn deliver(
+        &self,
+        subscription: &PushSubscription,
+        payload: &PushNotificationPayload,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // 構建訂閱信息
         let endpoint = subscription.endpoint.as_ref()
@@ -193,12 +202,9 @@ impl PushService {
             auth,
         );
 
-        // 構建 VAPID 簽名構建器 - 從 PEM 文件讀取
-        let pem_file = File::open("vapid_private.pem")
-            .map_err(|e| format!("無法開啟 PEM 檔案: {}", e))?;
-
+        // 構建 VAPID 簽名構建器 - 使用目前的 PEM 私鑰
         let mut builder = VapidSignatureBuilder::from_pem(
-            pem_file,
+            self.vapid_private_key_pem.as_bytes(),
             &subscription_info,
         ).map_err(|e| format!("無法建立 VAPID 簽名構建器: {:?}", e))?;
 
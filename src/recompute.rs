@@ -0,0 +1,754 @@
+use std::collections::{HashMap, HashSet};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Result};
+use chrono::{FixedOffset, NaiveDate, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{AchievementStats, CareerMainlines, DailyProgress, Task, UserAchievement};
+
+// 預設重建最近 7 天的每日進度，最多 90 天
+const DEFAULT_RECOMPUTE_DAYS: i64 = 7;
+const MAX_RECOMPUTE_DAYS: i64 = 90;
+const RATE_EPSILON: f64 = 1e-6;
+// 自助重建每日進度一次最多涵蓋的天數
+const MAX_REBUILD_DAYS: i64 = 366;
+
+#[derive(Deserialize)]
+pub struct RecomputeQuery {
+    pub dry_run: Option<bool>,
+    pub days: Option<i64>,
+}
+
+/// 單一欄位的差異
+#[derive(Deserialize)]
+pub struct RebuildDailyProgressQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub overwrite: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RebuildTotals {
+    pub completed_tasks: i32,
+    pub total_tasks: i32,
+    pub experience_gained: i32,
+}
+
+/// 每日進度重建結果；totals 為區間內依任務表計算的總和（不論是否寫入）
+#[derive(Debug, Serialize)]
+pub struct RebuildSummary {
+    pub user_id: String,
+    pub from: String,
+    pub to: String,
+    pub timezone: String,
+    pub overwrite: bool,
+    pub days_in_range: i64,
+    pub days_rebuilt: i64,
+    pub days_skipped: i64,
+    pub totals: RebuildTotals,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldDiff {
+    pub table: &'static str,
+    pub id: String,
+    pub field: &'static str,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecomputeReport {
+    pub user_id: String,
+    pub dry_run: bool,
+    pub days: i64,
+    pub changes: Vec<FieldDiff>,
+}
+
+// 待寫入的修正（同一筆紀錄可能有多個欄位差異）
+struct PlannedWrite {
+    sql: &'static str,
+    args: Vec<rbs::Value>,
+}
+
+#[derive(Default)]
+struct RecomputePlan {
+    diffs: Vec<FieldDiff>,
+    writes: Vec<PlannedWrite>,
+}
+
+impl RecomputePlan {
+    fn diff(&mut self, table: &'static str, id: &str, field: &'static str, before: serde_json::Value, after: serde_json::Value) {
+        self.diffs.push(FieldDiff { table, id: id.to_string(), field, before, after });
+    }
+}
+
+// 寫入（或覆寫）單日 daily_progress，含屬性成長
+const UPSERT_DAILY_PROGRESS_SQL: &str = "INSERT INTO daily_progress (id, user_id, date, completed_tasks, total_tasks, experience_gained, attributes_gained, created_at, updated_at)
+     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+     ON CONFLICT(user_id, date) DO UPDATE SET
+         completed_tasks = excluded.completed_tasks,
+         total_tasks = excluded.total_tasks,
+         experience_gained = excluded.experience_gained,
+         attributes_gained = excluded.attributes_gained,
+         updated_at = excluded.updated_at";
+
+/// 由任務表計算出的單日進度
+#[derive(Debug, PartialEq)]
+pub struct DailySnapshot {
+    pub completed_tasks: i32,
+    pub total_tasks: i32,
+    pub experience_gained: i32,
+    pub attributes_gained: serde_json::Value,
+}
+
+/// 任務歸屬的日期：每日任務以 task_date 為準，其餘以完成（最後更新）時在 tz 時區的日期為準
+fn task_day(task: &Task, tz: FixedOffset) -> Option<NaiveDate> {
+    task.task_date
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .or_else(|| task.updated_at.map(|dt| dt.with_timezone(&tz).date_naive()))
+}
+
+/// 依任務表計算某日的每日進度（不含父任務，避免與子任務重複計算）
+pub fn daily_snapshot(tasks: &[Task], date: NaiveDate) -> DailySnapshot {
+    daily_snapshot_in(tasks, date, crate::local_date::default_timezone())
+}
+
+/// 同 daily_snapshot，沒有 task_date 的任務依指定時區決定完成日
+pub fn daily_snapshot_in(tasks: &[Task], date: NaiveDate, tz: FixedOffset) -> DailySnapshot {
+    let mut completed_tasks = 0;
+    let mut total_tasks = 0;
+    let mut experience_gained = 0;
+    let mut gains: HashMap<String, i32> = HashMap::new();
+
+    for task in tasks.iter().filter(|t| t.is_parent_task != Some(1)) {
+        let completed = crate::shared_tasks::is_completed_status(task.status);
+        // 沒有 task_date 的任務只在完成當天計入
+        if task.task_date.is_none() && !completed {
+            continue;
+        }
+        if task_day(task, tz) != Some(date) {
+            continue;
+        }
+
+        total_tasks += 1;
+        if completed {
+            completed_tasks += 1;
+            experience_gained += task.experience.unwrap_or(0);
+            if let Some(attributes) = &task.attributes {
+                for (name, delta) in crate::attribute_rewards::parse_task_attributes(attributes) {
+                    *gains.entry(name).or_insert(0) += delta;
+                }
+            }
+        }
+    }
+
+    DailySnapshot {
+        completed_tasks,
+        total_tasks,
+        experience_gained,
+        attributes_gained: crate::attribute_rewards::merge_attribute_gains(None, &gains),
+    }
+}
+
+/// 父任務經驗值 = 子任務經驗值總和
+fn plan_parent_experience(plan: &mut RecomputePlan, tasks: &[Task]) {
+    let mut children: HashMap<&str, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        if let Some(parent_id) = task.parent_task_id.as_deref() {
+            children.entry(parent_id).or_default().push(task.clone());
+        }
+    }
+
+    for parent in tasks.iter().filter(|t| t.parent_task_id.is_none()) {
+        let Some(parent_id) = parent.id.as_deref() else { continue };
+        // 沒有子任務的父任務保持原有經驗值（與 update_parent_task_experience 一致）
+        let Some(subtasks) = children.get(parent_id) else { continue };
+        let expected = crate::services::task_hierarchy::subtask_experience_total(subtasks);
+        if parent.experience != Some(expected) {
+            plan.diff("task", parent_id, "experience", serde_json::json!(parent.experience), serde_json::json!(expected));
+            plan.writes.push(PlannedWrite {
+                sql: "UPDATE task SET experience = ? WHERE id = ?",
+                args: vec![rbs::Value::I32(expected), rbs::Value::String(parent_id.to_string())],
+            });
+        }
+    }
+}
+
+/// 重複性父任務的 completion_rate
+async fn plan_completion_rates(rb: &RBatis, plan: &mut RecomputePlan, tasks: &[Task]) -> Result<(), rbatis::Error> {
+    for parent in tasks.iter().filter(|t| t.parent_task_id.is_none() && t.is_recurring == Some(1)) {
+        let Some(parent_id) = parent.id.as_deref() else { continue };
+        let progress = crate::recurring_progress::compute_recurring_progress(rb, parent).await?;
+        let current = parent.completion_rate.unwrap_or(0.0);
+        if parent.completion_rate.is_none() || (current - progress.completion_rate).abs() > RATE_EPSILON {
+            plan.diff(
+                "task",
+                parent_id,
+                "completion_rate",
+                serde_json::json!(parent.completion_rate),
+                serde_json::json!(progress.completion_rate),
+            );
+            plan.writes.push(PlannedWrite {
+                sql: "UPDATE task SET completion_rate = ? WHERE id = ?",
+                args: vec![rbs::Value::F64(progress.completion_rate), rbs::Value::String(parent_id.to_string())],
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 職業主線的 progress_percentage
+async fn plan_mainline_progress(rb: &RBatis, plan: &mut RecomputePlan, user_id: &str) -> Result<(), rbatis::Error> {
+    for mainline in CareerMainlines::select_by_map(rb, value!{"user_id": user_id}).await? {
+        let Some(mainline_id) = mainline.id.as_deref() else { continue };
+        let expected = crate::career_routes::compute_mainline_progress(rb, mainline_id).await?;
+        let current = mainline.progress_percentage.unwrap_or(0.0);
+        if mainline.progress_percentage.is_none() || (current - expected).abs() > RATE_EPSILON {
+            plan.diff(
+                "career_mainlines",
+                mainline_id,
+                "progress_percentage",
+                serde_json::json!(mainline.progress_percentage),
+                serde_json::json!(expected),
+            );
+            plan.writes.push(PlannedWrite {
+                sql: "UPDATE career_mainlines SET progress_percentage = ? WHERE id = ?",
+                args: vec![rbs::Value::F64(expected), rbs::Value::String(mainline_id.to_string())],
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 使用者已解鎖成就的完成人數統計
+async fn plan_achievement_stats(rb: &RBatis, plan: &mut RecomputePlan, user_id: &str) -> Result<(), rbatis::Error> {
+    let now = Utc::now().to_rfc3339();
+    for unlocked in UserAchievement::select_by_map(rb, value!{"user_id": user_id}).await? {
+        let Some(achievement_id) = unlocked.achievement_id.as_deref() else { continue };
+        let expected = crate::services::achievement_stats::count_achievement_completions(rb, achievement_id).await?;
+        let current = AchievementStats::select_by_map(rb, value!{"achievement_id": achievement_id})
+            .await?
+            .into_iter()
+            .next()
+            .and_then(|s| s.completion_count);
+        if current != Some(expected) {
+            plan.diff("achievement_stats", achievement_id, "completion_count", serde_json::json!(current), serde_json::json!(expected));
+            plan.writes.push(PlannedWrite {
+                sql: "INSERT INTO achievement_stats (id, achievement_id, completion_count, created_at, updated_at)
+                      VALUES (?, ?, ?, ?, ?)
+                      ON CONFLICT(achievement_id) DO UPDATE SET
+                          completion_count = excluded.completion_count,
+                          updated_at = excluded.updated_at",
+                args: vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(achievement_id.to_string()),
+                    rbs::Value::I32(expected),
+                    rbs::Value::String(now.clone()),
+                    rbs::Value::String(now.clone()),
+                ],
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 由任務表重建最近 days 天的 daily_progress
+///
+/// 注意：重建後的 experience_gained 只含任務經驗值，手動調整的經驗值不會保留
+async fn plan_daily_progress(rb: &RBatis, plan: &mut RecomputePlan, user_id: &str, tasks: &[Task], days: i64) -> Result<(), rbatis::Error> {
+    let tz = crate::user_settings::user_timezone(rb, user_id).await;
+    let today = crate::local_date::local_today_in(tz);
+    let now = Utc::now().to_rfc3339();
+
+    for offset in 0..days {
+        let date = today - chrono::Duration::days(offset);
+        let date_str = date.format("%Y-%m-%d").to_string();
+        let expected = daily_snapshot_in(tasks, date, tz);
+        let current = DailyProgress::select_by_map(rb, value!{"user_id": user_id, "date": &date_str}).await?.into_iter().next();
+
+        let row_id = format!("{}@{}", user_id, date_str);
+        let (completed, total, experience, attributes) = match &current {
+            Some(p) => (
+                p.completed_tasks.unwrap_or(0),
+                p.total_tasks.unwrap_or(0),
+                p.experience_gained.unwrap_or(0),
+                // 資料庫中的 JSON 可能是字串，先正規化再比較
+                crate::attribute_rewards::merge_attribute_gains(p.attributes_gained.as_ref(), &HashMap::new()),
+            ),
+            None => (0, 0, 0, serde_json::json!({})),
+        };
+        let before = plan.diffs.len();
+        if completed != expected.completed_tasks {
+            plan.diff("daily_progress", &row_id, "completed_tasks", serde_json::json!(completed), serde_json::json!(expected.completed_tasks));
+        }
+        if total != expected.total_tasks {
+            plan.diff("daily_progress", &row_id, "total_tasks", serde_json::json!(total), serde_json::json!(expected.total_tasks));
+        }
+        if experience != expected.experience_gained {
+            plan.diff("daily_progress", &row_id, "experience_gained", serde_json::json!(experience), serde_json::json!(expected.experience_gained));
+        }
+        if attributes != expected.attributes_gained {
+            plan.diff("daily_progress", &row_id, "attributes_gained", attributes, expected.attributes_gained.clone());
+        }
+        if plan.diffs.len() == before {
+            continue;
+        }
+
+        plan.writes.push(PlannedWrite {
+            sql: UPSERT_DAILY_PROGRESS_SQL,
+            args: vec![
+                rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(date_str),
+                rbs::Value::I32(expected.completed_tasks),
+                rbs::Value::I32(expected.total_tasks),
+                rbs::Value::I32(expected.experience_gained),
+                rbs::Value::String(expected.attributes_gained.to_string()),
+                rbs::Value::String(now.clone()),
+                rbs::Value::String(now.clone()),
+            ],
+        });
+    }
+    Ok(())
+}
+
+/// 由任務表重新計算單日的 daily_progress（例如補記過去日期的完成後）；保留既有的屬性成長
+pub async fn refresh_daily_progress(rb: &RBatis, user_id: &str, date: NaiveDate) -> Result<DailySnapshot, rbatis::Error> {
+    let date_str = date.format("%Y-%m-%d").to_string();
+    let tasks: Vec<Task> = rb
+        .query_decode(
+            "SELECT * FROM task WHERE user_id = ? AND (task_date = ? OR task_date IS NULL)",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(date_str.clone())],
+        )
+        .await?;
+    let snapshot = daily_snapshot_in(&tasks, date, crate::user_settings::user_timezone(rb, user_id).await);
+    let now = Utc::now().to_rfc3339();
+    rb.exec(
+        "INSERT INTO daily_progress (id, user_id, date, completed_tasks, total_tasks, experience_gained, attributes_gained, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, '{}', ?, ?)
+         ON CONFLICT(user_id, date) DO UPDATE SET
+             completed_tasks = excluded.completed_tasks,
+             total_tasks = excluded.total_tasks,
+             experience_gained = excluded.experience_gained,
+             updated_at = excluded.updated_at",
+        vec![
+            rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::String(date_str),
+            rbs::Value::I32(snapshot.completed_tasks),
+            rbs::Value::I32(snapshot.total_tasks),
+            rbs::Value::I32(snapshot.experience_gained),
+            rbs::Value::String(now.clone()),
+            rbs::Value::String(now),
+        ],
+    )
+    .await?;
+    crate::gamified_cache::invalidate(user_id);
+    Ok(snapshot)
+}
+
+/// 依任務表重建 from..=to 的每日進度，日期以 tz 時區切分
+///
+/// 已有資料的日期只在 overwrite 時覆寫；沒有資料且當日沒有任務的日期不建立空白紀錄
+pub async fn rebuild_daily_progress_range(
+    rb: &RBatis,
+    user_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    tz: FixedOffset,
+    overwrite: bool,
+    job: Option<&crate::job_runner::JobContext>,
+) -> Result<RebuildSummary, rbatis::Error> {
+    let from_str = from.format("%Y-%m-%d").to_string();
+    let to_str = to.format("%Y-%m-%d").to_string();
+    let tasks = Task::select_by_map(rb, value!{"user_id": user_id}).await?;
+    let existing: Vec<DailyProgress> = rb
+        .query_decode(
+            "SELECT * FROM daily_progress WHERE user_id = ? AND date >= ? AND date <= ?",
+            vec![
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(from_str.clone()),
+                rbs::Value::String(to_str.clone()),
+            ],
+        )
+        .await?;
+    let existing: HashSet<String> = existing.into_iter().filter_map(|p| p.date).collect();
+
+    let days_in_range = (to - from).num_days() + 1;
+    let now = Utc::now().to_rfc3339();
+    let mut summary = RebuildSummary {
+        user_id: user_id.to_string(),
+        from: from_str,
+        to: to_str,
+        timezone: tz.to_string(),
+        overwrite,
+        days_in_range,
+        days_rebuilt: 0,
+        days_skipped: 0,
+        totals: RebuildTotals::default(),
+    };
+    let mut reported = 0;
+
+    for (index, date) in from.iter_days().take_while(|d| *d <= to).enumerate() {
+        let date_str = date.format("%Y-%m-%d").to_string();
+        let snapshot = daily_snapshot_in(&tasks, date, tz);
+        summary.totals.completed_tasks += snapshot.completed_tasks;
+        summary.totals.total_tasks += snapshot.total_tasks;
+        summary.totals.experience_gained += snapshot.experience_gained;
+
+        if existing.contains(&date_str) && !overwrite {
+            summary.days_skipped += 1;
+        } else if existing.contains(&date_str) || snapshot.total_tasks > 0 {
+            rb.exec(
+                UPSERT_DAILY_PROGRESS_SQL,
+                vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(user_id.to_string()),
+                    rbs::Value::String(date_str),
+                    rbs::Value::I32(snapshot.completed_tasks),
+                    rbs::Value::I32(snapshot.total_tasks),
+                    rbs::Value::I32(snapshot.experience_gained),
+                    rbs::Value::String(snapshot.attributes_gained.to_string()),
+                    rbs::Value::String(now.clone()),
+                    rbs::Value::String(now.clone()),
+                ],
+            )
+            .await?;
+            summary.days_rebuilt += 1;
+        }
+
+        // 進度只在百分比變動時寫入，避免每天都更新工作紀錄
+        let progress = ((index as i64 + 1) * 100 / days_in_range) as i32;
+        if progress != reported {
+            reported = progress;
+            if let Some(job) = job {
+                job.progress(progress).await;
+            }
+        }
+    }
+
+    if summary.days_rebuilt > 0 {
+        crate::gamified_cache::invalidate(user_id);
+    }
+    Ok(summary)
+}
+
+// 重新計算使用者的衍生資料；dry_run 時只回報差異不寫入。
+// 以背景工作執行時，每完成一個計算階段回報一次進度
+async fn recompute_with_progress(
+    rb: &RBatis,
+    user_id: &str,
+    days: i64,
+    dry_run: bool,
+    job: Option<&crate::job_runner::JobContext>,
+) -> Result<RecomputeReport, rbatis::Error> {
+    let report_progress = |progress: i32| async move {
+        if let Some(job) = job {
+            job.progress(progress).await;
+        }
+    };
+    let tasks = Task::select_by_map(rb, value!{"user_id": user_id}).await?;
+
+    let mut plan = RecomputePlan::default();
+    plan_parent_experience(&mut plan, &tasks);
+    plan_completion_rates(rb, &mut plan, &tasks).await?;
+    report_progress(20).await;
+    plan_mainline_progress(rb, &mut plan, user_id).await?;
+    report_progress(40).await;
+    plan_achievement_stats(rb, &mut plan, user_id).await?;
+    report_progress(60).await;
+    plan_daily_progress(rb, &mut plan, user_id, &tasks, days).await?;
+    report_progress(80).await;
+
+    if !dry_run && !plan.writes.is_empty() {
+        // 所有修正在同一個交易中寫入，任一失敗則全部回滾
+        let tx = rb.acquire_begin().await?;
+        for write in plan.writes {
+            if let Err(e) = tx.exec(write.sql, write.args).await {
+                let _ = tx.rollback().await;
+                return Err(e);
+            }
+        }
+        tx.commit().await?;
+        crate::gamified_cache::invalidate(user_id);
+    }
+
+    Ok(RecomputeReport {
+        user_id: user_id.to_string(),
+        dry_run,
+        days,
+        changes: plan.diffs,
+    })
+}
+
+/// 管理員 API：排入重新計算使用者衍生資料的背景工作，差異報告為工作結果
+pub async fn recompute_user(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<RecomputeQuery>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+
+    let user_id = path.into_inner();
+    let dry_run = query.dry_run.unwrap_or(false);
+    let days = query.days.unwrap_or(DEFAULT_RECOMPUTE_DAYS).clamp(1, MAX_RECOMPUTE_DAYS);
+
+    match crate::models::User::select_by_map(rb.get_ref(), value!{"id": &user_id}).await {
+        Ok(users) if users.is_empty() => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "用戶不存在".to_string(),
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢用戶失敗: {}", e),
+            }));
+        }
+    }
+
+    // 重新計算可能耗時，改為背景工作，立即回傳工作 ID（以 GET /api/jobs/{id} 查詢結果）
+    let job_rb = rb.get_ref().clone();
+    let job_user_id = user_id.clone();
+    let enqueued = crate::job_runner::JobRunner::new(rb.get_ref())
+        .enqueue(Some(&user_id), crate::job_runner::KIND_RECOMPUTE, move |ctx| async move {
+            let report = recompute_with_progress(&job_rb, &job_user_id, days, dry_run, Some(&ctx))
+                .await
+                .map_err(|e| format!("重新計算失敗: {}", e))?;
+            log::info!("重新計算使用者 {} 衍生資料完成 (dry_run: {}): {} 項差異", job_user_id, dry_run, report.changes.len());
+            serde_json::to_value(&report).map_err(|e| e.to_string())
+        })
+        .await;
+    match enqueued {
+        Ok(job_id) => Ok(HttpResponse::Accepted().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "job_id": job_id, "status": crate::job_runner::STATUS_QUEUED })),
+            message: if dry_run { "已排入試算工作" } else { "已排入重新計算工作" }.to_string(),
+        })),
+        Err(e) => {
+            log::error!("排入使用者 {} 重新計算工作失敗: {}", user_id, e);
+            Ok(HttpResponse::ServiceUnavailable().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: e,
+            }))
+        }
+    }
+}
+
+/// POST /api/users/{id}/daily-progress/rebuild?from=YYYY-MM-DD&to=YYYY-MM-DD[&overwrite=true]
+///
+/// 本人或管理員可用；排入背景工作，重建摘要為工作結果
+pub async fn rebuild_daily_progress(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<RebuildDailyProgressQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !crate::auth::is_admin_request(&http_req) {
+        return Ok(error(StatusCode::FORBIDDEN, "無權限重建此使用者的每日進度"));
+    }
+
+    let parse = |value: Option<&str>| value.and_then(|v| NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d").ok());
+    let (from, to) = match (parse(query.from.as_deref()), parse(query.to.as_deref())) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Ok(error(StatusCode::BAD_REQUEST, "from 與 to 為必填，格式為 YYYY-MM-DD")),
+    };
+    if from > to {
+        return Ok(error(StatusCode::BAD_REQUEST, "from 不能晚於 to"));
+    }
+    if (to - from).num_days() + 1 > MAX_REBUILD_DAYS {
+        return Ok(error(StatusCode::BAD_REQUEST, format!("一次最多重建 {} 天", MAX_REBUILD_DAYS)));
+    }
+    let overwrite = query.overwrite.unwrap_or(false);
+
+    match crate::models::User::select_by_map(rb.get_ref(), value!{"id": &user_id}).await {
+        Ok(users) if users.is_empty() => return Ok(error(StatusCode::NOT_FOUND, "用戶不存在")),
+        Ok(_) => {}
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢用戶失敗: {}", e))),
+    }
+    let settings = match crate::user_settings::load_ui_settings(rb.get_ref(), &user_id).await {
+        Ok(settings) => settings,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("讀取使用者設定失敗: {}", e))),
+    };
+    let tz = crate::local_date::parse_utc_offset(&settings.timezone).unwrap_or_else(crate::local_date::default_timezone);
+    if to > Utc::now().with_timezone(&tz).date_naive() {
+        return Ok(error(StatusCode::BAD_REQUEST, "不能重建未來日期的每日進度"));
+    }
+
+    let job_rb = rb.get_ref().clone();
+    let job_user_id = user_id.clone();
+    let enqueued = crate::job_runner::JobRunner::new(rb.get_ref())
+        .enqueue(Some(&user_id), crate::job_runner::KIND_DAILY_PROGRESS_REBUILD, move |ctx| async move {
+            let summary = rebuild_daily_progress_range(&job_rb, &job_user_id, from, to, tz, overwrite, Some(&ctx))
+                .await
+                .map_err(|e| format!("重建每日進度失敗: {}", e))?;
+            log::info!(
+                "重建使用者 {} 每日進度 {}~{} 完成：重建 {} 天，略過 {} 天",
+                job_user_id, summary.from, summary.to, summary.days_rebuilt, summary.days_skipped
+            );
+            serde_json::to_value(&summary).map_err(|e| e.to_string())
+        })
+        .await;
+    match enqueued {
+        Ok(job_id) => Ok(HttpResponse::Accepted().json(ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "job_id": job_id, "status": crate::job_runner::STATUS_QUEUED })),
+            message: "已排入每日進度重建工作".to_string(),
+        })),
+        Err(e) => {
+            log::error!("排入使用者 {} 每日進度重建工作失敗: {}", user_id, e);
+            Ok(error(StatusCode::SERVICE_UNAVAILABLE, e))
+        }
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskStatus;
+
+    fn task(id: &str, status: TaskStatus, task_date: Option<&str>, experience: i32) -> Task {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "status": status.to_i32(),
+            "task_date": task_date,
+            "experience": experience,
+            "attributes": {"focus": 1},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_daily_snapshot_counts_dated_and_completed_tasks() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let tasks = vec![
+            task("a", TaskStatus::DailyCompleted, Some("2026-03-02"), 20),
+            task("b", TaskStatus::DailyInProgress, Some("2026-03-02"), 20),
+            task("c", TaskStatus::DailyCompleted, Some("2026-03-01"), 20),
+            // 沒有 task_date 且未完成的任務不計入
+            task("d", TaskStatus::Pending, None, 50),
+        ];
+        let snapshot = daily_snapshot(&tasks, date);
+        assert_eq!(snapshot.total_tasks, 2);
+        assert_eq!(snapshot.completed_tasks, 1);
+        assert_eq!(snapshot.experience_gained, 20);
+        assert_eq!(snapshot.attributes_gained, serde_json::json!({"focus": 1}));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_without_writing() {
+        let path = std::env::temp_dir().join(format!("lifeup_recompute_{}.db", uuid::Uuid::new_v4()));
+        let rb = RBatis::new();
+        rb.init(rbdc_sqlite::driver::SqliteDriver {}, &format!("sqlite://{}", path.display())).unwrap();
+        crate::create_tables(&rb).await;
+        crate::migrate_database(&rb).await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, experience, is_parent_task) VALUES ('p1', 'u1', '學習日文', 0, 999, 1)",
+            vec![],
+        )
+        .await
+        .unwrap();
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, experience, parent_task_id) VALUES ('s1', 'u1', '背單字', 0, 30, 'p1'), ('s2', 'u1', '聽力', 0, 20, 'p1')",
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        let report = recompute_with_progress(&rb, "u1", 1, true, None).await.unwrap();
+        let diff = report.changes.iter().find(|d| d.id == "p1" && d.field == "experience").unwrap();
+        assert_eq!((diff.before.clone(), diff.after.clone()), (serde_json::json!(999), serde_json::json!(50)));
+        let parent = Task::select_by_map(&rb, value!{"id": "p1"}).await.unwrap().remove(0);
+        assert_eq!(parent.experience, Some(999));
+
+        let report = recompute_with_progress(&rb, "u1", 1, false, None).await.unwrap();
+        assert!(!report.changes.is_empty());
+        let parent = Task::select_by_map(&rb, value!{"id": "p1"}).await.unwrap().remove(0);
+        assert_eq!(parent.experience, Some(50));
+
+        // 修正後再次計算不應有差異
+        let report = recompute_with_progress(&rb, "u1", 1, true, None).await.unwrap();
+        assert!(report.changes.is_empty(), "{:?}", report.changes);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_range_uses_timezone_and_respects_overwrite() {
+        let path = std::env::temp_dir().join(format!("lifeup_rebuild_{}.db", uuid::Uuid::new_v4()));
+        let rb = RBatis::new();
+        rb.init(rbdc_sqlite::driver::SqliteDriver {}, &format!("sqlite://{}", path.display())).unwrap();
+        crate::create_tables(&rb).await;
+        crate::migrate_database(&rb).await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        // UTC 3/1 20:00 完成：UTC+8 為 3/2，UTC 為 3/1
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, status, experience, updated_at) VALUES ('t1', 'u1', '讀書', 2, 40, '2026-03-01T20:00:00Z')",
+            vec![],
+        )
+        .await
+        .unwrap();
+        rb.exec(
+            "INSERT INTO daily_progress (id, user_id, date, completed_tasks, total_tasks, experience_gained, attributes_gained) VALUES ('d1', 'u1', '2026-03-02', 9, 9, 999, '{}')",
+            vec![],
+        )
+        .await
+        .unwrap();
+        let from = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+        let row = |date: &'static str| {
+            let rb = rb.clone();
+            async move { DailyProgress::select_by_map(&rb, value!{"user_id": "u1", "date": date}).await.unwrap().into_iter().next() }
+        };
+
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let summary = rebuild_daily_progress_range(&rb, "u1", from, to, utc, false, None).await.unwrap();
+        assert_eq!((summary.days_in_range, summary.days_rebuilt, summary.days_skipped), (3, 1, 1));
+        assert_eq!(summary.totals.experience_gained, 40);
+        assert_eq!(row("2026-03-01").await.unwrap().experience_gained, Some(40));
+        // 未指定 overwrite 時保留既有資料
+        assert_eq!(row("2026-03-02").await.unwrap().experience_gained, Some(999));
+        assert!(row("2026-03-03").await.is_none());
+
+        let taipei = crate::local_date::default_timezone();
+        let summary = rebuild_daily_progress_range(&rb, "u1", from, to, taipei, true, None).await.unwrap();
+        assert_eq!((summary.days_rebuilt, summary.days_skipped), (2, 0));
+        let progress = row("2026-03-02").await.unwrap();
+        assert_eq!((progress.completed_tasks, progress.experience_gained), (Some(1), Some(40)));
+        // 3/1 已有紀錄，覆寫後依 UTC+8 歸零
+        assert_eq!(row("2026-03-01").await.unwrap().experience_gained, Some(0));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
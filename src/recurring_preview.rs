@@ -0,0 +1,262 @@
+// 重複性任務詳情：父任務、子任務模板、重複模式與接下來的產生日期預覽
+//
+// 供前端的習慣編輯畫面使用，回應格式固定（見 docs/openapi.yaml 的 RecurringTaskDetail）。
+// 預覽日期與實際產生子任務的規則一致（recurring_progress::is_scheduled_day），
+// 國定假日不影響產生，只以 is_holiday 標示供前端顯示。
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar_service::CalendarService;
+use crate::models::{RecurringTaskTemplate, Task};
+use crate::recurring_progress::is_scheduled_day;
+use crate::services::ApiResponse;
+
+const DEFAULT_PREVIEW_COUNT: usize = 7;
+const MAX_PREVIEW_COUNT: usize = 60;
+// 往後最多搜尋一年，避免期間很短或已結束時無限迴圈
+const MAX_LOOKAHEAD_DAYS: i64 = 366;
+
+const WEEKDAY_NAMES: [&str; 7] = ["週一", "週二", "週三", "週四", "週五", "週六", "週日"];
+
+#[derive(Deserialize)]
+pub struct RecurringPreviewQuery {
+    pub count: Option<usize>,
+}
+
+/// 解析後的重複模式
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RecurrenceInfo {
+    pub pattern: String,
+    pub label: String,
+    // weekly 模式執行的星期（0 = 週一），其餘模式為 null
+    pub weekday: Option<u32>,
+    pub start_date: String,
+    pub end_date: Option<String>,
+}
+
+/// 預覽中的一個產生日期
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PreviewDay {
+    pub date: String,
+    pub weekday: u32, // 0 = 週一
+    pub weekday_name: String,
+    pub is_holiday: bool,
+    pub subtask_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct RecurringTaskDetail {
+    pub task: Task,
+    pub templates: Vec<RecurringTaskTemplate>,
+    pub recurrence: RecurrenceInfo,
+    pub today_generated: bool,
+    pub upcoming: Vec<PreviewDay>,
+}
+
+/// 解析重複模式；未知模式與實際產生規則相同，視為每日
+pub fn recurrence_info(pattern: &str, start: NaiveDate, end: Option<NaiveDate>) -> RecurrenceInfo {
+    let (pattern, label) = match pattern {
+        "weekdays" => ("weekdays", "平日".to_string()),
+        "weekends" => ("weekends", "週末".to_string()),
+        "weekly" => ("weekly", format!("每{}", WEEKDAY_NAMES[start.weekday().num_days_from_monday() as usize])),
+        _ => ("daily", "每天".to_string()),
+    };
+    RecurrenceInfo {
+        pattern: pattern.to_string(),
+        label,
+        weekday: (pattern == "weekly").then(|| start.weekday().num_days_from_monday()),
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.map(|d| d.format("%Y-%m-%d").to_string()),
+    }
+}
+
+/// 從 from（含）起，任務期間內接下來 count 個會產生子任務的日期
+pub fn upcoming_dates(
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+    from: NaiveDate,
+    pattern: &str,
+    count: usize,
+) -> Vec<NaiveDate> {
+    let first = from.max(start);
+    (0..MAX_LOOKAHEAD_DAYS)
+        .map(|offset| first + Duration::days(offset))
+        .take_while(|day| end.is_none_or(|end| *day <= end))
+        .filter(|day| is_scheduled_day(start, *day, pattern))
+        .take(count)
+        .collect()
+}
+
+fn preview_days(dates: &[NaiveDate], calendar: &CalendarService, subtask_count: usize) -> Vec<PreviewDay> {
+    dates
+        .iter()
+        .map(|day| {
+            let weekday = day.weekday().num_days_from_monday();
+            PreviewDay {
+                date: day.format("%Y-%m-%d").to_string(),
+                weekday,
+                weekday_name: WEEKDAY_NAMES[weekday as usize].to_string(),
+                is_holiday: calendar.is_holiday(*day),
+                subtask_count,
+            }
+        })
+        .collect()
+}
+
+/// 取得重複性任務詳情與產生日期預覽
+/// GET /api/recurring-tasks/{parent_id}?count=7
+pub async fn get_recurring_task(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<RecurringPreviewQuery>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    let count = query.count.unwrap_or(DEFAULT_PREVIEW_COUNT).clamp(1, MAX_PREVIEW_COUNT);
+
+    let task = match crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if task.is_recurring != Some(1) || task.parent_task_id.is_some() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "此任務不是重複性任務".to_string(),
+        }));
+    }
+
+    let mut templates = match RecurringTaskTemplate::select_by_map(rb.get_ref(), value!{"parent_task_id": &task_id}).await {
+        Ok(templates) => templates,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("獲取任務模板失敗: {}", e),
+            }))
+        }
+    };
+    templates.sort_by_key(|t| t.task_order.unwrap_or(0));
+
+    let tz = crate::recurring_progress::owner_timezone(rb.get_ref(), &task).await;
+    let today = crate::local_date::local_today_in(tz);
+    let generated: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) FROM task WHERE parent_task_id = ? AND task_date = ?",
+            vec![
+                rbs::Value::String(task_id.clone()),
+                rbs::Value::String(today.format("%Y-%m-%d").to_string()),
+            ],
+        )
+        .await
+        .unwrap_or(0);
+
+    // 開始日期與 habit_stats 一致：未設定時以建立時間為準
+    let start = crate::local_date::local_date_in(task.start_date.or(task.created_at).unwrap_or_else(Utc::now), tz);
+    let end = task.end_date.map(|end| crate::local_date::local_date_in(end, tz));
+    let pattern = task.recurrence_pattern.clone().unwrap_or_else(|| "daily".to_string());
+    let dates = upcoming_dates(start, end, today, &pattern, count);
+
+    let detail = RecurringTaskDetail {
+        recurrence: recurrence_info(&pattern, start, end),
+        upcoming: preview_days(&dates, crate::calendar_service::shared(), templates.len()),
+        today_generated: generated > 0,
+        templates,
+        task,
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(detail),
+        message: "獲取重複性任務成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_upcoming_dates_follow_pattern_and_range() {
+        // 2026-03-02 為週一
+        let start = date(2026, 3, 2);
+        let weekdays = upcoming_dates(start, None, date(2026, 3, 6), "weekdays", 3);
+        assert_eq!(weekdays, vec![date(2026, 3, 6), date(2026, 3, 9), date(2026, 3, 10)]);
+
+        let weekly = upcoming_dates(start, None, date(2026, 3, 3), "weekly", 2);
+        assert_eq!(weekly, vec![date(2026, 3, 9), date(2026, 3, 16)]);
+
+        // 尚未開始時從開始日起算，結束日後不再產生
+        let ranged = upcoming_dates(start, Some(date(2026, 3, 4)), date(2026, 2, 1), "daily", 7);
+        assert_eq!(ranged, vec![date(2026, 3, 2), date(2026, 3, 3), date(2026, 3, 4)]);
+        assert!(upcoming_dates(start, Some(date(2026, 3, 4)), date(2026, 4, 1), "daily", 7).is_empty());
+    }
+
+    #[test]
+    fn test_recurrence_info_labels() {
+        let start = date(2026, 3, 4); // 週三
+        let weekly = recurrence_info("weekly", start, None);
+        assert_eq!((weekly.label.as_str(), weekly.weekday), ("每週三", Some(2)));
+        let unknown = recurrence_info("monthly", start, Some(date(2026, 4, 1)));
+        assert_eq!(unknown.pattern, "daily");
+        assert_eq!(unknown.end_date.as_deref(), Some("2026-04-01"));
+    }
+
+    #[actix_web::test]
+    async fn test_get_recurring_task_detail() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "habit_editor").await;
+        let other = test_utils::create_user(&app, "habit_stranger").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/recurring-tasks")
+            .insert_header(user.auth())
+            .set_json(json!({
+                "user_id": user.id,
+                "title": "每日閱讀",
+                "recurrence_pattern": "daily",
+                "subtask_templates": [
+                    {"title": "讀 10 頁", "description": null, "difficulty": 1, "experience": 10, "order": 2, "skill_tags": null},
+                    {"title": "寫心得", "description": null, "difficulty": 1, "experience": 5, "order": 1, "skill_tags": null}
+                ]
+            }))
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+        let uri = format!("/api/recurring-tasks/{}?count=5", task_id);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let data = &body["data"];
+        assert_eq!(data["task"]["id"], task_id.as_str());
+        assert_eq!(data["templates"][0]["title"], "寫心得");
+        assert_eq!(data["recurrence"]["pattern"], "daily");
+        assert_eq!(data["today_generated"], false);
+        let upcoming = data["upcoming"].as_array().unwrap();
+        assert_eq!(upcoming.len(), 5);
+        assert_eq!(upcoming[0]["date"], crate::local_date::local_today_string());
+        assert_eq!(upcoming[0]["subtask_count"], 2);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/tasks/{}/generate-daily", task_id))
+            .insert_header(user.auth())
+            .to_request();
+        call_json(&app, req).await;
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.1["data"]["today_generated"], true);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use chrono::{Datelike, FixedOffset, NaiveDate, Utc};
+use rbatis::RBatis;
+use rbs::value;
+
+use crate::config::RecurringConfig;
+use crate::models::{Task, TaskStatus};
+
+// 期滿檢查間隔
+const EXPIRY_SWEEP_INTERVAL_SECS: u64 = 3600;
+
+static RECURRING_CONFIG: OnceLock<RecurringConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: RecurringConfig) {
+    log::info!("重複性任務: 最多可補記 {} 天前的完成", config.catch_up_max_days);
+    if RECURRING_CONFIG.set(config).is_err() {
+        log::warn!("重複性任務設定已初始化，忽略重複設定");
+    }
+}
+
+/// 補記完成最多可往前的天數
+pub fn catch_up_max_days() -> i64 {
+    RECURRING_CONFIG.get_or_init(RecurringConfig::default).catch_up_max_days
+}
+
+/// 重複性任務進度（get_task_progress 與 completion_rate 共用）
+#[derive(Debug, Clone)]
+pub struct RecurringProgress {
+    pub total_days: i32,
+    pub completed_days: i32,
+    pub missed_days: i32,
+    pub completion_rate: f64,
+    pub target_rate: f64,
+    pub is_daily_completed: bool,
+    pub remaining_days: i32,
+}
+
+/// 依重複模式判斷某天是否需要執行（weekly 以開始日的星期為準）
+pub fn is_scheduled_day(start: NaiveDate, day: NaiveDate, pattern: &str) -> bool {
+    let weekday = day.weekday();
+    let is_weekend = weekday == chrono::Weekday::Sat || weekday == chrono::Weekday::Sun;
+    match pattern {
+        "weekdays" => !is_weekend,
+        "weekends" => is_weekend,
+        "weekly" => weekday == start.weekday(),
+        _ => true, // daily 與未知模式視為每日
+    }
+}
+
+/// 依重複模式計算從 start 起 days 天內應執行的天數
+pub fn count_scheduled_days(start: NaiveDate, days: i32, pattern: &str) -> i32 {
+    (0..days.max(0))
+        .map(|i| start + chrono::Duration::days(i as i64))
+        .filter(|day| is_scheduled_day(start, *day, pattern))
+        .count() as i32
+}
+
+/// 檢查是否可補記某個過去日期的完成：須為已過去、在回溯上限內、位於任務期間且依重複模式應執行的日子
+pub fn check_catch_up_date(parent_task: &Task, day: NaiveDate, today: NaiveDate, tz: FixedOffset, max_days: i64) -> Result<(), String> {
+    if parent_task.is_recurring != Some(1) || parent_task.parent_task_id.is_some() {
+        return Err("只有重複性任務可以補記完成".to_string());
+    }
+    if matches!(
+        parent_task.status.and_then(TaskStatus::from_i32),
+        Some(TaskStatus::Completed | TaskStatus::Cancelled | TaskStatus::DailyNotCompleted | TaskStatus::Failed)
+    ) {
+        return Err("任務已結束，無法補記完成".to_string());
+    }
+    if day >= today {
+        return Err("只能補記今天以前的日期".to_string());
+    }
+    if (today - day).num_days() > max_days {
+        return Err(format!("最多只能補記 {} 天內的日期", max_days));
+    }
+    let start_day = crate::local_date::local_date_in(parent_task.start_date.or(parent_task.created_at).unwrap_or_else(Utc::now), tz);
+    let after_end = parent_task.end_date.is_some_and(|end| day > crate::local_date::local_date_in(end, tz));
+    if day < start_day || after_end {
+        return Err("日期不在任務期間內".to_string());
+    }
+    let pattern = parent_task.recurrence_pattern.as_deref().unwrap_or("daily");
+    if !is_scheduled_day(start_day, day, pattern) {
+        return Err("該日期依重複模式不需執行".to_string());
+    }
+    Ok(())
+}
+
+/// 計算重複性父任務的進度
+pub async fn compute_recurring_progress(rb: &RBatis, parent_task: &Task) -> Result<RecurringProgress, rbatis::Error> {
+    let now = Utc::now();
+    let parent_task_id = parent_task.id.clone().unwrap_or_default();
+    let start_date = parent_task.start_date.unwrap_or(now);
+    let end_date = parent_task.end_date.unwrap_or(now + chrono::Duration::days(365));
+    let recurrence_pattern = parent_task.recurrence_pattern.as_deref().unwrap_or("daily");
+
+    // 日期一律以擁有者的時區計算，與 task_date 一致
+    let tz = owner_timezone(rb, parent_task).await;
+    let start_day = crate::local_date::local_date_in(start_date, tz);
+    let end_day = crate::local_date::local_date_in(end_date, tz);
+    let today = crate::local_date::local_today_in(tz);
+
+    let period_days = (end_day - start_day).num_days() as i32 + 1;
+    let total_days = count_scheduled_days(start_day, period_days, recurrence_pattern);
+
+    // 到今日為止應該執行的天數
+    let current_period_days = std::cmp::min((today - start_day).num_days() as i32 + 1, period_days);
+    let days_since_start = count_scheduled_days(start_day, current_period_days, recurrence_pattern);
+
+    // 有完成的日期（含已壓縮成每月彙總的子任務）
+    let last_day = std::cmp::min(today, end_day);
+    let completed_rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT task_date FROM task
+             WHERE parent_task_id = ? AND status = ? AND task_date IS NOT NULL
+             AND task_date >= ? AND task_date <= ?
+             GROUP BY task_date",
+            vec![
+                rbs::Value::String(parent_task_id.clone()),
+                rbs::Value::I32(TaskStatus::DailyCompleted.to_i32()),
+                rbs::Value::String(start_day.format("%Y-%m-%d").to_string()),
+                rbs::Value::String(last_day.format("%Y-%m-%d").to_string()),
+            ],
+        )
+        .await?;
+    let mut completed_dates = crate::daily_compaction::archived_completed_dates(rb, &parent_task_id).await?;
+    completed_dates.extend(
+        completed_rows
+            .iter()
+            .filter_map(|row| row.get("task_date").and_then(|v| v.as_str()))
+            .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()),
+    );
+    let completed_days = completed_dates.iter().filter(|day| **day >= start_day && **day <= last_day).count() as i32;
+
+    // 今日子任務是否全部完成
+    let today_rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT COUNT(*) as total, SUM(CASE WHEN status = ? THEN 1 ELSE 0 END) as completed
+             FROM task WHERE parent_task_id = ? AND task_date = ?",
+            vec![
+                rbs::Value::I32(TaskStatus::DailyCompleted.to_i32()),
+                rbs::Value::String(parent_task_id.clone()),
+                rbs::Value::String(today.format("%Y-%m-%d").to_string()),
+            ],
+        )
+        .await?;
+    let is_daily_completed = today_rows.first().is_some_and(|row| {
+        let total = row.get("total").and_then(|v| v.as_i64()).unwrap_or(0);
+        let completed = row.get("completed").and_then(|v| v.as_i64()).unwrap_or(0);
+        total > 0 && completed == total
+    });
+
+    let completion_rate = if total_days > 0 {
+        completed_days as f64 / total_days as f64
+    } else {
+        0.0
+    };
+    log::info!("任務 {} 完成率計算: {}/{} = {:.1}%", parent_task_id, completed_days, total_days, completion_rate * 100.0);
+
+    Ok(RecurringProgress {
+        total_days,
+        completed_days,
+        missed_days: std::cmp::max(0, days_since_start - completed_days),
+        completion_rate,
+        target_rate: parent_task.completion_target.unwrap_or(0.8),
+        is_daily_completed,
+        remaining_days: std::cmp::max(0, total_days - days_since_start),
+    })
+}
+
+/// 重新計算並寫回重複性父任務的 completion_rate；期滿時一併結算
+pub async fn refresh_completion_rate(rb: &RBatis, parent_task_id: &str) -> Result<Option<RecurringProgress>, rbatis::Error> {
+    let Some(parent_task) = Task::select_by_map(rb, value!{"id": parent_task_id}).await?.into_iter().next() else {
+        return Ok(None);
+    };
+    if parent_task.is_recurring != Some(1) {
+        return Ok(None);
+    }
+
+    let progress = compute_recurring_progress(rb, &parent_task).await?;
+    rb.exec(
+        "UPDATE task SET completion_rate = ? WHERE id = ?",
+        vec![rbs::Value::F64(progress.completion_rate), rbs::Value::String(parent_task_id.to_string())],
+    )
+    .await?;
+
+    finalize_if_ended(rb, &parent_task, &progress).await?;
+    Ok(Some(progress))
+}
+
+/// 任務擁有者的時區；重複性任務的 task_date、進度與期滿都以擁有者的日期計算
+pub async fn owner_timezone(rb: &RBatis, task: &Task) -> FixedOffset {
+    match task.user_id.as_deref() {
+        Some(user_id) => crate::user_settings::user_timezone(rb, user_id).await,
+        None => crate::local_date::default_timezone(),
+    }
+}
+
+/// 重複性任務是否已過結束日期（以 tz 時區的今天判斷）
+pub fn has_ended(task: &Task, tz: FixedOffset) -> bool {
+    task.end_date.is_some_and(|end| crate::local_date::local_today_in(tz) > crate::local_date::local_date_in(end, tz))
+}
+
+/// 期滿結算：達成目標完成率標記為已完成，否則標記為未完成
+async fn finalize_if_ended(rb: &RBatis, parent_task: &Task, progress: &RecurringProgress) -> Result<(), rbatis::Error> {
+    if !has_ended(parent_task, owner_timezone(rb, parent_task).await) {
+        return Ok(());
+    }
+
+    // 挑戰任務失敗時另有獨立狀態、通知與經驗值扣除
+    if crate::challenges::is_challenge(parent_task) {
+        crate::challenges::settle_recurring(rb, parent_task, progress.completion_rate, progress.target_rate).await?;
+        return Ok(());
+    }
+
+    let succeeded = progress.completion_rate >= progress.target_rate;
+    let new_status = if succeeded {
+        TaskStatus::Completed.to_i32()
+    } else {
+        TaskStatus::DailyNotCompleted.to_i32()
+    };
+
+    // 以條件更新避免重複結算（例如排程與使用者操作同時觸發）
+    let result = rb
+        .exec(
+            "UPDATE task SET status = ?, updated_at = ? WHERE id = ? AND status NOT IN (?, ?, ?)",
+            vec![
+                rbs::Value::I32(new_status),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(parent_task.id.clone().unwrap_or_default()),
+                rbs::Value::I32(TaskStatus::Completed.to_i32()),
+                rbs::Value::I32(TaskStatus::Cancelled.to_i32()),
+                rbs::Value::I32(TaskStatus::DailyNotCompleted.to_i32()),
+            ],
+        )
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(());
+    }
+
+    log::info!(
+        "重複性任務 {} 期滿結算: {} ({:.1}% / 目標 {:.1}%)",
+        parent_task.id.as_deref().unwrap_or_default(),
+        if succeeded { "達標" } else { "未達標" },
+        progress.completion_rate * 100.0,
+        progress.target_rate * 100.0
+    );
+
+    if let Some(user_id) = parent_task.user_id.clone() {
+        let title = parent_task.title.clone().unwrap_or_default();
+        crate::event_notifier::notify_recurring_task_finished(
+            rb,
+            &user_id,
+            &title,
+            succeeded,
+            progress.completion_rate,
+            progress.target_rate,
+        )
+        .await;
+
+        if succeeded {
+            crate::achievement_check::schedule(rb, &user_id);
+        }
+    }
+    Ok(())
+}
+
+/// 定期結算已過結束日期但尚未結算的重複性任務
+pub fn spawn_expiry_sweeper(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(EXPIRY_SWEEP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+
+            let candidates: Result<Vec<Task>, _> = rb
+                .query_decode(
+                    "SELECT * FROM task WHERE is_recurring = 1 AND parent_task_id IS NULL AND end_date IS NOT NULL AND status NOT IN (?, ?, ?, ?)",
+                    vec![
+                        rbs::Value::I32(TaskStatus::Completed.to_i32()),
+                        rbs::Value::I32(TaskStatus::Cancelled.to_i32()),
+                        rbs::Value::I32(TaskStatus::DailyNotCompleted.to_i32()),
+                        rbs::Value::I32(TaskStatus::Failed.to_i32()),
+                    ],
+                )
+                .await;
+            let candidates = match candidates {
+                Ok(tasks) => tasks,
+                Err(e) => {
+                    log::error!("查詢待結算重複性任務失敗: {}", e);
+                    continue;
+                }
+            };
+
+            for task in &candidates {
+                if !has_ended(task, owner_timezone(&rb, task).await) {
+                    continue;
+                }
+                if let Some(id) = &task.id {
+                    if let Err(e) = refresh_completion_rate(&rb, id).await {
+                        log::error!("結算重複性任務 {} 失敗: {}", id, e);
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_scheduled_days_by_pattern() {
+        // 2026-03-02 為週一，14 天含兩個完整週
+        let start = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert_eq!(count_scheduled_days(start, 14, "daily"), 14);
+        assert_eq!(count_scheduled_days(start, 14, "weekdays"), 10);
+        assert_eq!(count_scheduled_days(start, 14, "weekends"), 4);
+        assert_eq!(count_scheduled_days(start, 14, "weekly"), 2);
+    }
+
+    #[test]
+    fn test_count_scheduled_days_before_start() {
+        let start = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert_eq!(count_scheduled_days(start, -3, "daily"), 0);
+    }
+}
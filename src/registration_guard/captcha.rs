@@ -0,0 +1,68 @@
+// 人機驗證：註冊時驗證前端取得的驗證 token，可替換為其他實作（例如工作量證明）
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// 驗證註冊請求帶來的 token；Err 表示驗證服務無法使用
+#[async_trait::async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn verify(&self, token: &str, remote_ip: Option<&str>) -> Result<bool, String>;
+}
+
+/// hCaptcha（https://docs.hcaptcha.com/#verify-the-user-response-server-side）
+pub struct HCaptchaVerifier {
+    secret: String,
+    verify_url: String,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(rename = "error-codes", default)]
+    error_codes: Vec<String>,
+}
+
+impl HCaptchaVerifier {
+    pub fn new(secret: String, verify_url: String) -> Self {
+        HCaptchaVerifier {
+            secret,
+            verify_url,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CaptchaVerifier for HCaptchaVerifier {
+    fn name(&self) -> &'static str {
+        "hcaptcha"
+    }
+
+    async fn verify(&self, token: &str, remote_ip: Option<&str>) -> Result<bool, String> {
+        let mut form = vec![("secret", self.secret.as_str()), ("response", token)];
+        if let Some(ip) = remote_ip {
+            form.push(("remoteip", ip));
+        }
+        let response = self
+            .client
+            .post(&self.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("hCaptcha 驗證請求失敗: {}", e))?;
+        let result: SiteVerifyResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("hCaptcha 回應格式錯誤: {}", e))?;
+        if !result.success {
+            log::info!("hCaptcha 驗證未通過: {}", result.error_codes.join(", "));
+        }
+        Ok(result.success)
+    }
+}
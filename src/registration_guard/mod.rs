@@ -0,0 +1,315 @@
+// 註冊防濫用：拋棄式信箱網域、人機驗證與每個 IP 每日註冊上限
+//
+// 三項保護各自由設定開關，自架者可全部關閉。被拒絕的註冊回傳 400，
+// 以 code 區分原因（DISPOSABLE_EMAIL / CAPTCHA_FAILED / REGISTRATION_LIMIT），並計入執行期統計。
+
+mod captcha;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use actix_web::HttpResponse;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+pub use captcha::{CaptchaVerifier, HCaptchaVerifier};
+
+use crate::config::RegistrationGuardConfig;
+
+const BUNDLED_DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+
+pub const CODE_DISPOSABLE_EMAIL: &str = "DISPOSABLE_EMAIL";
+pub const CODE_CAPTCHA_FAILED: &str = "CAPTCHA_FAILED";
+pub const CODE_REGISTRATION_LIMIT: &str = "REGISTRATION_LIMIT";
+
+struct RegistrationMetrics {
+    disposable_email: AtomicU64,
+    captcha_failed: AtomicU64,
+    ip_limit: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegistrationMetricsSnapshot {
+    pub disposable_email: u64,
+    pub captcha_failed: u64,
+    pub ip_limit: u64,
+}
+
+static REGISTRATION_METRICS: RegistrationMetrics = RegistrationMetrics {
+    disposable_email: AtomicU64::new(0),
+    captcha_failed: AtomicU64::new(0),
+    ip_limit: AtomicU64::new(0),
+};
+
+pub fn metrics_snapshot() -> RegistrationMetricsSnapshot {
+    RegistrationMetricsSnapshot {
+        disposable_email: REGISTRATION_METRICS.disposable_email.load(Ordering::Relaxed),
+        captcha_failed: REGISTRATION_METRICS.captcha_failed.load(Ordering::Relaxed),
+        ip_limit: REGISTRATION_METRICS.ip_limit.load(Ordering::Relaxed),
+    }
+}
+
+/// 解析網域清單（每行一個網域，# 開頭為註解）
+pub fn parse_domain_list(text: &str) -> HashSet<String> {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches("*.").to_lowercase())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// email 網域（或其上層網域）是否在清單中
+pub fn is_disposable(domains: &HashSet<String>, email: &str) -> bool {
+    let Some((_, domain)) = email.trim().rsplit_once('@') else {
+        return false;
+    };
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let mut candidate = domain.as_str();
+    loop {
+        if domains.contains(candidate) {
+            return true;
+        }
+        match candidate.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => candidate = parent,
+            _ => return false,
+        }
+    }
+}
+
+/// 每個 IP 當日（使用者時區）成功註冊次數
+#[derive(Default)]
+pub struct IpRegistrationCounter {
+    counts: HashMap<String, (NaiveDate, u32)>,
+}
+
+impl IpRegistrationCounter {
+    pub fn count(&self, ip: &str, today: NaiveDate) -> u32 {
+        match self.counts.get(ip) {
+            Some((date, count)) if *date == today => *count,
+            _ => 0,
+        }
+    }
+
+    pub fn record(&mut self, ip: &str, today: NaiveDate) {
+        // 順便清掉前幾天的紀錄，避免無限增長
+        self.counts.retain(|_, (date, _)| *date == today);
+        self.counts.entry(ip.to_string()).or_insert((today, 0)).1 += 1;
+    }
+}
+
+struct RegistrationGuard {
+    config: RegistrationGuardConfig,
+    disposable_domains: HashSet<String>,
+    verifier: Option<Box<dyn CaptchaVerifier>>,
+    ip_counter: Mutex<IpRegistrationCounter>,
+}
+
+impl RegistrationGuard {
+    fn new(config: RegistrationGuardConfig) -> Self {
+        let disposable_domains = if !config.block_disposable_email {
+            HashSet::new()
+        } else if let Some(path) = &config.disposable_domains_file {
+            match std::fs::read_to_string(path) {
+                Ok(text) => parse_domain_list(&text),
+                Err(e) => {
+                    log::error!("讀取拋棄式信箱網域清單 {} 失敗，改用內建清單: {}", path, e);
+                    parse_domain_list(BUNDLED_DISPOSABLE_DOMAINS)
+                }
+            }
+        } else {
+            parse_domain_list(BUNDLED_DISPOSABLE_DOMAINS)
+        };
+
+        let verifier: Option<Box<dyn CaptchaVerifier>> = match config.captcha_provider.as_str() {
+            "none" => None,
+            "hcaptcha" => match &config.hcaptcha_secret {
+                Some(secret) => Some(Box::new(HCaptchaVerifier::new(
+                    secret.clone(),
+                    config.hcaptcha_verify_url.clone(),
+                ))),
+                None => {
+                    log::warn!("REGISTRATION_CAPTCHA_PROVIDER=hcaptcha 但未設定 HCAPTCHA_SECRET，停用人機驗證");
+                    None
+                }
+            },
+            other => {
+                log::warn!("未知的人機驗證方式 {}，停用人機驗證", other);
+                None
+            }
+        };
+
+        RegistrationGuard {
+            config,
+            disposable_domains,
+            verifier,
+            ip_counter: Mutex::new(IpRegistrationCounter::default()),
+        }
+    }
+}
+
+static REGISTRATION_GUARD: OnceLock<RegistrationGuard> = OnceLock::new();
+
+fn guard() -> &'static RegistrationGuard {
+    REGISTRATION_GUARD.get_or_init(|| RegistrationGuard::new(RegistrationGuardConfig::default()))
+}
+
+/// 啟動時套用設定
+pub fn init(config: RegistrationGuardConfig) {
+    let registration_guard = RegistrationGuard::new(config);
+    log::info!(
+        "註冊防濫用: 拋棄式信箱{}（{} 個網域），人機驗證 {}，每個 IP 每日上限 {}",
+        if registration_guard.config.block_disposable_email { "封鎖" } else { "不檢查" },
+        registration_guard.disposable_domains.len(),
+        registration_guard.verifier.as_ref().map(|v| v.name()).unwrap_or("停用"),
+        match registration_guard.config.max_registrations_per_ip_per_day {
+            0 => "不限制".to_string(),
+            max => max.to_string(),
+        }
+    );
+    if REGISTRATION_GUARD.set(registration_guard).is_err() {
+        log::warn!("註冊防濫用設定已初始化，忽略重複設定");
+    }
+}
+
+fn rejected_response(code: &str, message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "success": false,
+        "data": null,
+        "message": message,
+        "code": code,
+    }))
+}
+
+/// 註冊前檢查；被拒絕時回傳應直接送出的回應
+pub async fn blocked_response(
+    email: &str,
+    ip: Option<&str>,
+    captcha_token: Option<&str>,
+) -> Option<HttpResponse> {
+    let guard = guard();
+
+    if guard.config.block_disposable_email && is_disposable(&guard.disposable_domains, email) {
+        log::warn!("拒絕拋棄式信箱註冊: email={} ip={:?}", email, ip);
+        REGISTRATION_METRICS.disposable_email.fetch_add(1, Ordering::Relaxed);
+        return Some(rejected_response(CODE_DISPOSABLE_EMAIL, "不接受拋棄式信箱註冊，請改用常用的 email"));
+    }
+
+    // 沒有來源 IP 時（例如測試或內部呼叫）不計上限
+    let max = guard.config.max_registrations_per_ip_per_day;
+    if let (Some(ip), true) = (ip, max > 0) {
+        let count = guard
+            .ip_counter
+            .lock()
+            .map(|counter| counter.count(ip, crate::local_date::local_today()))
+            .unwrap_or(0);
+        if count >= max {
+            log::warn!("IP 今日註冊次數已達上限: ip={} count={}", ip, count);
+            REGISTRATION_METRICS.ip_limit.fetch_add(1, Ordering::Relaxed);
+            return Some(rejected_response(CODE_REGISTRATION_LIMIT, "今日註冊次數已達上限，請明天再試"));
+        }
+    }
+
+    if let Some(verifier) = &guard.verifier {
+        let passed = match captcha_token.map(str::trim).filter(|token| !token.is_empty()) {
+            Some(token) => verifier.verify(token, ip).await.unwrap_or_else(|e| {
+                log::error!("人機驗證服務錯誤: {}", e);
+                false
+            }),
+            None => false,
+        };
+        if !passed {
+            log::warn!("註冊人機驗證未通過: email={} ip={:?}", email, ip);
+            REGISTRATION_METRICS.captcha_failed.fetch_add(1, Ordering::Relaxed);
+            return Some(rejected_response(CODE_CAPTCHA_FAILED, "人機驗證失敗，請重新驗證後再試"));
+        }
+    }
+
+    None
+}
+
+/// 註冊成功後計入該 IP 當日次數
+pub fn record_success(ip: Option<&str>) {
+    let Some(ip) = ip else { return };
+    if guard().config.max_registrations_per_ip_per_day == 0 {
+        return;
+    }
+    if let Ok(mut counter) = guard().ip_counter.lock() {
+        counter.record(ip, crate::local_date::local_today());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[test]
+    fn test_disposable_domain_matching() {
+        let domains = parse_domain_list("# 註解\nmailinator.com\n*.trashmail.io\n\n");
+        assert_eq!(domains.len(), 2);
+        assert!(is_disposable(&domains, "bot@mailinator.com"));
+        assert!(is_disposable(&domains, "Bot@MAILINATOR.com"));
+        assert!(is_disposable(&domains, "bot@eu.trashmail.io"));
+        assert!(!is_disposable(&domains, "user@gmail.com"));
+        assert!(!is_disposable(&domains, "user@notmailinator.com"));
+        assert!(!is_disposable(&domains, "not-an-email"));
+        // 內建清單可以解析
+        assert!(is_disposable(&parse_domain_list(BUNDLED_DISPOSABLE_DOMAINS), "x@guerrillamail.com"));
+    }
+
+    #[test]
+    fn test_ip_counter_resets_daily() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let tomorrow = today.succ_opt().unwrap();
+        let mut counter = IpRegistrationCounter::default();
+        counter.record("10.0.0.1", today);
+        counter.record("10.0.0.1", today);
+        assert_eq!(counter.count("10.0.0.1", today), 2);
+        assert_eq!(counter.count("10.0.0.2", today), 0);
+        assert_eq!(counter.count("10.0.0.1", tomorrow), 0);
+        counter.record("10.0.0.2", tomorrow);
+        assert_eq!(counter.counts.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_disposable_email_registration_rejected() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let before = metrics_snapshot().disposable_email;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/users")
+            .set_json(json!({"name": "throwaway", "email": "bot@mailinator.com", "password": "Str0ngPassw0rd!"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(body["code"], CODE_DISPOSABLE_EMAIL);
+        assert!(metrics_snapshot().disposable_email > before);
+    }
+
+    #[actix_web::test]
+    async fn test_ip_limit_ignores_forwarded_headers() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let peer: std::net::SocketAddr = "198.51.100.77:40000".parse().unwrap();
+
+        // 同一個連線位址換著 X-Forwarded-For 註冊，仍計入同一個 IP 的上限
+        let register = |i: u32| {
+            actix_web::test::TestRequest::post()
+                .uri("/api/users")
+                .peer_addr(peer)
+                .insert_header(("X-Forwarded-For", format!("203.0.113.{}", i)))
+                .set_json(json!({"name": format!("spoof{}", i), "email": format!("spoof{}@lifeup.test", i), "password": "Str0ngPassw0rd!"}))
+                .to_request()
+        };
+        let max = RegistrationGuardConfig::default().max_registrations_per_ip_per_day;
+        for i in 0..max {
+            let (status, body) = call_json(&app, register(i)).await;
+            assert_eq!(status, 201, "{}", body);
+        }
+        let (status, body) = call_json(&app, register(max)).await;
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(body["code"], CODE_REGISTRATION_LIMIT);
+    }
+}
@@ -0,0 +1,177 @@
+// 請求上下文：每個請求的 request_id 與登入的 user_id，供結構化日誌帶入每一行
+//
+// 以 tokio task-local 保存（同一 worker 執行緒上交錯的請求不會互相覆寫）；
+// 背景工作（tokio::spawn）不在請求範圍內，日誌不帶這些欄位。
+
+use std::cell::{Cell, RefCell};
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::Error;
+use futures::future::LocalBoxFuture;
+
+/// 回應標頭：本次請求的 id（客戶端帶入合法的 X-Request-Id 時沿用）
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// 客戶端帶入的 request id 長度上限
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+pub struct RequestContext {
+    pub request_id: String,
+    user_id: RefCell<Option<String>>,
+    latency_ms: Cell<Option<u64>>,
+}
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+/// 日誌欄位快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextFields {
+    pub request_id: String,
+    pub user_id: Option<String>,
+    pub latency_ms: Option<u64>,
+}
+
+/// 目前請求的日誌欄位（不在請求範圍內時為 None）
+pub fn current_fields() -> Option<ContextFields> {
+    REQUEST_CONTEXT
+        .try_with(|ctx| ContextFields {
+            request_id: ctx.request_id.clone(),
+            user_id: ctx.user_id.borrow().clone(),
+            latency_ms: ctx.latency_ms.get(),
+        })
+        .ok()
+}
+
+/// JWT 驗證通過後記錄使用者，之後的日誌都會帶上 user_id
+pub fn set_user_id(user_id: &str) {
+    let _ = REQUEST_CONTEXT.try_with(|ctx| *ctx.user_id.borrow_mut() = Some(user_id.to_string()));
+}
+
+fn valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+// 請求上下文中間件：放在最外層；access_log 為 true 時在請求結束時記錄一行含耗時的存取日誌
+// （JSON 格式時取代 actix Logger，讓 latency 成為獨立欄位）
+pub struct RequestContextMiddleware {
+    pub access_log: bool,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestContextMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestContextService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestContextService {
+            service: Rc::new(service),
+            access_log: self.access_log,
+        }))
+    }
+}
+
+pub struct RequestContextService<S> {
+    service: Rc<S>,
+    access_log: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestContextService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let service = self.service.clone();
+        let access_log = self.access_log;
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| valid_request_id(v))
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        let context = RequestContext {
+            request_id: request_id.clone(),
+            user_id: RefCell::new(None),
+            latency_ms: Cell::new(None),
+        };
+        Box::pin(REQUEST_CONTEXT.scope(context, async move {
+            let method = req.method().to_string();
+            let path = req.path().to_string();
+            let mut res = service.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            if access_log {
+                let _ = REQUEST_CONTEXT.try_with(|ctx| ctx.latency_ms.set(Some(start.elapsed().as_millis() as u64)));
+                log::info!(target: "access", "{} {} {}", method, path, res.status().as_u16());
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse};
+
+    #[test]
+    fn test_valid_request_id() {
+        assert!(valid_request_id("abc-123_x.y"));
+        assert!(!valid_request_id(""));
+        assert!(!valid_request_id("has space"));
+        assert!(!valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_header_and_context() {
+        let app = actix_web::test::init_service(App::new().wrap(RequestContextMiddleware { access_log: false }).route(
+            "/ctx",
+            web::get().to(|| async {
+                set_user_id("u1");
+                let fields = current_fields().unwrap();
+                HttpResponse::Ok().json(serde_json::json!({"request_id": fields.request_id, "user_id": fields.user_id}))
+            }),
+        ))
+        .await;
+
+        // 沿用客戶端帶入的 id
+        let req = actix_web::test::TestRequest::get().uri("/ctx").insert_header((REQUEST_ID_HEADER, "req-42")).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "req-42");
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({"request_id": "req-42", "user_id": "u1"}));
+
+        // 不合法的 id 改為產生新的
+        let req = actix_web::test::TestRequest::get().uri("/ctx").insert_header((REQUEST_ID_HEADER, "bad id")).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        let generated = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        assert_ne!(generated, "bad id");
+        assert!(current_fields().is_none());
+    }
+}
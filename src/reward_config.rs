@@ -0,0 +1,162 @@
+// 經驗值數值表：每日任務、通用子任務模板、AI 成就與挑戰失敗扣除的經驗值
+//
+// 數值在建立任務、子任務、成就與挑戰時寫入各自的資料列（挑戰記錄在 task.challenge_fail_penalty），
+// 完成或結算時只讀取已寫入的值，因此調整設定後只影響新建立的資料，既有任務與成就維持原本的經驗值。
+
+use std::sync::OnceLock;
+
+use actix_web::{HttpRequest, HttpResponse, Result};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::RewardConfig;
+use crate::task_types::TaskType;
+
+static REWARDS: OnceLock<RewardConfig> = OnceLock::new();
+
+/// 啟動時套用經驗值數值表
+pub fn init(config: RewardConfig) {
+    log::info!(
+        "經驗值數值表: 每日任務 {}、子任務模板 {:?}、成就預設 {}（AI {}-{}、職業主線 {}-{}）、挑戰失敗扣除 {}",
+        config.daily_task_xp,
+        config.subtask_template_xp,
+        config.achievement_default_xp,
+        config.ai_achievement_xp.min,
+        config.ai_achievement_xp.max,
+        config.career_achievement_xp.min,
+        config.career_achievement_xp.max,
+        config.challenge_fail_penalty
+    );
+    if REWARDS.set(config).is_err() {
+        log::warn!("經驗值數值表已初始化，忽略重複設定");
+    }
+}
+
+/// 目前的經驗值數值表（未初始化時使用預設值）
+pub fn current() -> &'static RewardConfig {
+    REWARDS.get_or_init(RewardConfig::default)
+}
+
+/// 職業主線成就的經驗值：AI 未提供時使用預設值，並限制在設定範圍內
+pub fn career_achievement_xp(requested: Option<i32>) -> i32 {
+    let config = current();
+    config
+        .career_achievement_xp
+        .clamp(requested.unwrap_or(config.achievement_default_xp))
+}
+
+/// 建立挑戰任務本體時要記錄的失敗扣除經驗值（其他類型回傳 None）
+pub fn challenge_fail_penalty_for(task_type: Option<&str>) -> Option<i32> {
+    (TaskType::from_stored(task_type) == Some(TaskType::Challenge)).then(|| current().challenge_fail_penalty)
+}
+
+/// GET /api/admin/reward-config：檢視目前生效的經驗值數值表（只影響之後新建立的資料）
+pub async fn get_reward_config(http_req: HttpRequest) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(current()),
+        message: "數值調整只影響之後新建立的任務、子任務、成就與挑戰，既有資料維持建立時的經驗值".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::XpRange;
+    use crate::models::{Task, TaskStatus};
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_reward_knobs() {
+        assert_eq!(crate::config::parse_xp_list("5, 10,15"), Some(vec![5, 10, 15]));
+        assert_eq!(crate::config::parse_xp_list("5,-1"), None);
+        assert_eq!(crate::config::parse_xp_range("20-80"), Some(XpRange { min: 20, max: 80 }));
+        assert_eq!(crate::config::parse_xp_range("80-20"), None);
+        assert_eq!(career_achievement_xp(None), 50);
+        assert_eq!(career_achievement_xp(Some(1000)), 100);
+        assert_eq!(challenge_fail_penalty_for(Some("challenge")), Some(0));
+        assert_eq!(challenge_fail_penalty_for(Some("daily")), None);
+    }
+
+    #[actix_web::test]
+    async fn test_reward_config_endpoint_and_existing_entities() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let admin = test_utils::create_user(&app, "reward-admin").await;
+        let user = test_utils::create_user(&app, "reward-user").await;
+        test_utils::grant_admin("reward-admin");
+
+        let get = |auth: (&'static str, String)| {
+            actix_web::test::TestRequest::get().uri("/api/admin/reward-config").insert_header(auth).to_request()
+        };
+        assert_eq!(call_json(&app, get(user.auth())).await.0, 403);
+        let (status, body) = call_json(&app, get(admin.auth())).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["daily_task_xp"], 10);
+        assert_eq!(body["data"]["subtask_template_xp"], json!([20, 30, 50, 60, 80, 30]));
+        assert_eq!(body["data"]["achievement_default_xp"], 50);
+        assert_eq!(body["data"]["ai_achievement_xp"], json!({"min": 50, "max": 500}));
+        assert_eq!(body["data"]["challenge_fail_penalty"], 0);
+
+        // 新建立的每日任務與挑戰寫入目前的數值
+        let create = |body: serde_json::Value| {
+            actix_web::test::TestRequest::post().uri("/api/tasks").insert_header(user.auth()).set_json(body).to_request()
+        };
+        let (status, body) = call_json(&app, create(json!({"user_id": user.id, "title": "喝水"}))).await;
+        assert_eq!(status, 201, "{}", body);
+        assert_eq!(body["data"]["experience"], 10);
+        let daily_id = body["data"]["id"].as_str().unwrap().to_string();
+        let end_date = (chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339();
+        let (status, body) = call_json(
+            &app,
+            create(json!({"user_id": user.id, "title": "一週不熬夜", "task_type": "challenge", "end_date": end_date})),
+        )
+        .await;
+        assert_eq!(status, 201, "{}", body);
+        assert_eq!(body["data"]["challenge_fail_penalty"], 0);
+
+        // 模擬設定調整前建立的資料：經驗值與扣除值已寫入資料列，之後結算只讀取資料列
+        rb.exec(
+            "UPDATE task SET experience = 25, challenge_fail_penalty = 40 WHERE user_id = ?",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        rb.exec("UPDATE user_profile SET experience = 50 WHERE user_id = ?", vec![rbs::Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        let tasks: Vec<Task> = rb
+            .query_decode("SELECT * FROM task WHERE user_id = ? ORDER BY title", vec![rbs::Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        let challenge = tasks.iter().find(|t| t.id.as_deref() != Some(daily_id.as_str())).unwrap();
+        assert_eq!(crate::challenges::fail_penalty(challenge), 40);
+        assert!(crate::challenges::evaluate(&rb, challenge).await.unwrap());
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT t.id, t.status, t.experience, p.experience AS profile_experience FROM task t
+                 JOIN user_profile p ON p.user_id = t.user_id WHERE t.user_id = ?",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        for row in &rows {
+            assert_eq!(row["experience"], 25);
+            assert_eq!(row["profile_experience"], 10);
+            if row["id"] != daily_id.as_str() {
+                assert_eq!(row["status"], TaskStatus::Failed.to_i32());
+            }
+        }
+
+        // 沒有記錄的舊挑戰採用目前設定
+        let legacy = Task { challenge_fail_penalty: None, ..challenge.clone() };
+        assert_eq!(crate::challenges::fail_penalty(&legacy), current().challenge_fail_penalty);
+    }
+}
@@ -0,0 +1,441 @@
+// 獎勵商店：使用者自訂獎勵，以金幣兌換
+//
+// 金幣與經驗值 1:1 累積（見 services::experience），兌換只扣金幣，不影響經驗值與等級。
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Reward, RewardRedemption};
+
+const MAX_NAME_CHARS: usize = 100;
+const MAX_COST: i32 = 1_000_000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRewardRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cost: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRewardRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub cost: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RewardList {
+    pub coins: i32,
+    pub rewards: Vec<Reward>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedemptionHistory {
+    pub coins: i32,
+    pub redemptions: Vec<RewardRedemption>,
+}
+
+/// 增減使用者的金幣（不低於 0），經驗值變化時呼叫
+pub async fn credit_coins(rb: &RBatis, user_id: &str, amount: i32) -> Result<(), rbatis::Error> {
+    if amount == 0 {
+        return Ok(());
+    }
+    rb.exec(
+        "UPDATE user_profile SET coins = MAX(0, COALESCE(coins, 0) + ?) WHERE user_id = ?",
+        vec![rbs::Value::I32(amount), rbs::Value::String(user_id.to_string())],
+    )
+    .await?;
+    Ok(())
+}
+
+/// 使用者目前的金幣餘額
+pub async fn coin_balance(rb: &RBatis, user_id: &str) -> Result<i32, rbatis::Error> {
+    let balance: Option<i32> = rb
+        .query_decode(
+            "SELECT COALESCE(coins, 0) FROM user_profile WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(balance.unwrap_or(0))
+}
+
+fn validate_reward(name: Option<&str>, cost: Option<i32>) -> Option<String> {
+    if let Some(name) = name {
+        let name = name.trim();
+        if name.is_empty() {
+            return Some("獎勵名稱不能為空".to_string());
+        }
+        if name.chars().count() > MAX_NAME_CHARS {
+            return Some(format!("獎勵名稱不能超過 {} 個字", MAX_NAME_CHARS));
+        }
+    }
+    if let Some(cost) = cost {
+        if !(1..=MAX_COST).contains(&cost) {
+            return Some(format!("兌換所需金幣需介於 1 到 {} 之間", MAX_COST));
+        }
+    }
+    None
+}
+
+fn json_error(status: actix_web::http::StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// 列出自己的獎勵與金幣餘額
+pub async fn list_rewards(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let rewards = Reward::select_by_map(rb.get_ref(), value!{"user_id": &user_id}).await;
+    let coins = coin_balance(rb.get_ref(), &user_id).await;
+    match (rewards, coins) {
+        (Ok(mut rewards), Ok(coins)) => {
+            rewards.sort_by_key(|r| r.cost);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(RewardList { coins, rewards }),
+                message: "獲取獎勵列表成功".to_string(),
+            }))
+        }
+        (Err(e), _) | (_, Err(e)) => Ok(json_error(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("獲取獎勵列表失敗: {}", e),
+        )),
+    }
+}
+
+/// 新增獎勵
+pub async fn create_reward(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<CreateRewardRequest>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if let Some(message) = validate_reward(Some(&req.name), Some(req.cost)) {
+        return Ok(json_error(StatusCode::BAD_REQUEST, message));
+    }
+
+    let now = Utc::now();
+    let reward = Reward {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id),
+        name: Some(req.name.trim().to_string()),
+        description: req.description.clone(),
+        icon: req.icon.clone(),
+        cost: Some(req.cost),
+        created_at: Some(now),
+        updated_at: Some(now),
+    };
+    match Reward::insert(rb.get_ref(), &reward).await {
+        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(reward),
+            message: "獎勵建立成功".to_string(),
+        })),
+        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立獎勵失敗: {}", e))),
+    }
+}
+
+/// 更新獎勵（只更新有提供的欄位）
+pub async fn update_reward(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<UpdateRewardRequest>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let reward_id = path.into_inner();
+    let mut reward = match crate::ownership::assert_reward_owner(rb.get_ref(), &reward_id, Some(&user_id)).await {
+        Ok(reward) => reward,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if let Some(message) = validate_reward(req.name.as_deref(), req.cost) {
+        return Ok(json_error(StatusCode::BAD_REQUEST, message));
+    }
+
+    if let Some(name) = &req.name {
+        reward.name = Some(name.trim().to_string());
+    }
+    if req.description.is_some() {
+        reward.description = req.description.clone();
+    }
+    if req.icon.is_some() {
+        reward.icon = req.icon.clone();
+    }
+    if req.cost.is_some() {
+        reward.cost = req.cost;
+    }
+    reward.updated_at = Some(Utc::now());
+
+    match Reward::update_by_map(rb.get_ref(), &reward, value!{"id": &reward_id}).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(reward),
+            message: "獎勵更新成功".to_string(),
+        })),
+        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新獎勵失敗: {}", e))),
+    }
+}
+
+/// 刪除獎勵（兌換紀錄保留）
+pub async fn delete_reward(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let reward_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_reward_owner(rb.get_ref(), &reward_id, Some(&user_id)).await {
+        return Ok(e.into_response());
+    }
+    match Reward::delete_by_map(rb.get_ref(), value!{"id": &reward_id}).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "獎勵已刪除".to_string(),
+        })),
+        Err(e) => Ok(json_error(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("刪除獎勵失敗: {}", e),
+        )),
+    }
+}
+
+// 扣除金幣並寫入兌換紀錄（同一個交易）；金幣不足時回傳 Ok(None)
+async fn redeem(rb: &RBatis, user_id: &str, reward: &Reward) -> Result<Option<RewardRedemption>, rbatis::Error> {
+    let cost = reward.cost.unwrap_or(0);
+    let tx = rb.acquire_begin().await?;
+
+    // 以條件更新確保餘額足夠，避免同時兌換造成負餘額
+    let deducted = tx
+        .exec(
+            "UPDATE user_profile SET coins = coins - ? WHERE user_id = ? AND COALESCE(coins, 0) >= ?",
+            vec![rbs::Value::I32(cost), rbs::Value::String(user_id.to_string()), rbs::Value::I32(cost)],
+        )
+        .await;
+    match deducted {
+        Ok(result) if result.rows_affected == 0 => {
+            tx.rollback().await?;
+            return Ok(None);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            let _ = tx.rollback().await;
+            return Err(e);
+        }
+    }
+
+    let balance: Result<Option<i32>, rbatis::Error> = tx
+        .query_decode(
+            "SELECT coins FROM user_profile WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await;
+    let redemption = RewardRedemption {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id.to_string()),
+        reward_id: reward.id.clone(),
+        reward_name: reward.name.clone(),
+        cost: Some(cost),
+        balance_after: Some(balance.as_ref().ok().copied().flatten().unwrap_or(0)),
+        created_at: Some(Utc::now()),
+    };
+    let inserted = match balance {
+        Ok(_) => RewardRedemption::insert(&tx, &redemption).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = inserted {
+        let _ = tx.rollback().await;
+        return Err(e);
+    }
+    tx.commit().await?;
+    Ok(Some(redemption))
+}
+
+/// 兌換獎勵：扣除金幣並記錄（經驗值與等級不變）
+pub async fn redeem_reward(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let reward_id = path.into_inner();
+    let reward = match crate::ownership::assert_reward_owner(rb.get_ref(), &reward_id, Some(&user_id)).await {
+        Ok(reward) => reward,
+        Err(e) => return Ok(e.into_response()),
+    };
+
+    match redeem(rb.get_ref(), &user_id, &reward).await {
+        Ok(Some(redemption)) => {
+            log::info!(
+                "使用者 {} 兌換獎勵 {}（{} 金幣）",
+                user_id,
+                reward.name.as_deref().unwrap_or_default(),
+                reward.cost.unwrap_or(0)
+            );
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(redemption),
+                message: "兌換成功".to_string(),
+            }))
+        }
+        Ok(None) => {
+            let coins = coin_balance(rb.get_ref(), &user_id).await.unwrap_or(0);
+            Ok(HttpResponse::UnprocessableEntity().json(ApiResponse {
+                success: false,
+                data: Some(serde_json::json!({"coins": coins, "cost": reward.cost})),
+                message: format!("金幣不足（目前 {}，需要 {}）", coins, reward.cost.unwrap_or(0)),
+            }))
+        }
+        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("兌換獎勵失敗: {}", e))),
+    }
+}
+
+/// 兌換紀錄（本人或管理員），新的在前
+pub async fn get_redemptions(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let user_id = path.into_inner();
+    let is_self = crate::auth::current_user_id(&http_req).as_deref() == Some(user_id.as_str());
+    if !is_self && !crate::auth::is_admin_request(&http_req) {
+        return Ok(json_error(StatusCode::FORBIDDEN, "無權限查看此使用者的兌換紀錄"));
+    }
+
+    let redemptions = RewardRedemption::select_by_map(rb.get_ref(), value!{"user_id": &user_id}).await;
+    let coins = coin_balance(rb.get_ref(), &user_id).await;
+    match (redemptions, coins) {
+        (Ok(mut redemptions), Ok(coins)) => {
+            redemptions.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(RedemptionHistory { coins, redemptions }),
+                message: "獲取兌換紀錄成功".to_string(),
+            }))
+        }
+        (Err(e), _) | (_, Err(e)) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("獲取兌換紀錄失敗: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_redeem_spends_coins_without_touching_level() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "shopper").await;
+        let other = test_utils::create_user(&app, "window_shopper").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/rewards")
+            .insert_header(user.auth())
+            .set_json(json!({"name": "買一杯手搖", "icon": "🧋", "cost": 200}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let reward_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/rewards")
+            .insert_header(user.auth())
+            .set_json(json!({"name": " ", "cost": 0}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::BAD_REQUEST);
+
+        // 金幣不足
+        let redeem_uri = format!("/api/rewards/{}/redeem", reward_id);
+        let req = actix_web::test::TestRequest::post().uri(&redeem_uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["data"]["coins"], 0);
+
+        // 獲得經驗值時同步累積金幣（遊戲化資料在第一次讀取時建立）
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/gamified", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+        crate::services::experience::apply_experience_gain(&rb, &user.id, 250).await.unwrap();
+        let profile_before: serde_json::Value = rb
+            .query_decode("SELECT level, experience FROM user_profile WHERE user_id = ?", vec![user.id.clone().into()])
+            .await
+            .unwrap();
+
+        let req = actix_web::test::TestRequest::post().uri(&redeem_uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+
+        let req = actix_web::test::TestRequest::post().uri(&redeem_uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["balance_after"], 50);
+
+        let req = actix_web::test::TestRequest::post().uri(&redeem_uri).insert_header(user.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let profile_after: serde_json::Value = rb
+            .query_decode("SELECT level, experience FROM user_profile WHERE user_id = ?", vec![user.id.clone().into()])
+            .await
+            .unwrap();
+        assert_eq!(profile_before, profile_after);
+
+        // 刪除獎勵後兌換紀錄仍保留
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("/api/rewards/{}", reward_id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/redemptions", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["coins"], 50);
+        assert_eq!(body["data"]["redemptions"][0]["reward_name"], "買一杯手搖");
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/redemptions", user.id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,416 @@
+use actix_web::{web, HttpResponse, Result};
+use rbatis::RBatis;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::models::*;
+use crate::ai_service::convert_to_achievement_model;
+use rbs::value;
+use crate::services::ApiResponse;
+use crate::services::achievement_stats::{
+    get_achievement_with_stats, get_total_user_count, increment_achievement_completion_count, AchievementWithStats,
+};
+
+// 成就相關 API
+
+// 獲取所有成就
+pub async fn get_achievements(rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    match Achievement::select_all(rb.get_ref()).await {
+        Ok(achievements) => {
+            // 獲取總用戶數
+            let total_users = match get_total_user_count(rb.get_ref()).await {
+                Ok(count) => count,
+                Err(e) => {
+                    log::warn!("獲取總用戶數失敗: {}", e);
+                    0
+                }
+            };
+
+            // 為每個成就添加統計資訊
+            let mut achievements_with_stats = Vec::new();
+
+            for achievement in achievements {
+                let achievement_id = achievement.id.as_ref().map(|s| s.as_str()).unwrap_or("");
+
+                // 獲取統計資訊
+                let stats = AchievementStats::select_by_map(rb.get_ref(), value!{"achievement_id": achievement_id}).await.unwrap_or_default();
+                let completion_count = stats.first()
+                    .and_then(|s| s.completion_count)
+                    .unwrap_or(0);
+
+                // 計算完成率
+                let completion_rate = if total_users > 0 {
+                    completion_count as f64 / total_users as f64
+                } else {
+                    0.0
+                };
+
+                let achievement_with_stats = AchievementWithStats {
+                    id: achievement.id.as_ref().unwrap_or(&String::new()).clone(),
+                    name: achievement.name.as_ref().unwrap_or(&String::new()).clone(),
+                    description: achievement.description.clone(),
+                    icon: achievement.icon.clone(),
+                    category: achievement.category.clone(),
+                    requirement_type: achievement.requirement_type.as_ref().map(|rt| rt.to_string().to_owned()),
+                    requirement_value: achievement.requirement_value,
+                    experience_reward: achievement.experience_reward,
+                    completion_count,
+                    total_users,
+                    completion_rate,
+                    created_at: achievement.created_at,
+                };
+
+                achievements_with_stats.push(achievement_with_stats);
+            }
+
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(achievements_with_stats),
+                message: "獲取成就列表成功".to_string(),
+            }))
+        },
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取成就列表失敗: {}", e),
+        })),
+    }
+}
+
+// 獲取用戶已解鎖的成就
+pub async fn get_user_achievements(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    // 使用 SQL JOIN 查詢直接獲取用戶已解鎖的成就及其詳細資訊
+    let sql = r#"
+        SELECT
+            a.id, a.name, a.description, a.icon, a.category,
+            a.requirement_type, a.requirement_value, a.experience_reward,
+            ua.achieved_at, ua.progress
+        FROM
+            achievement a
+        JOIN
+            user_achievement ua ON a.id = ua.achievement_id
+        WHERE
+            ua.user_id = ?
+    "#;
+
+    // 定義一個結構來接收查詢結果
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
+    struct UnlockedAchievementData {
+        id: Option<String>,
+        name: Option<String>,
+        description: Option<String>,
+        icon: Option<String>,
+        category: Option<String>,
+        requirement_type: Option<String>,
+        requirement_value: Option<i32>,
+        experience_reward: Option<i32>,
+        achieved_at: Option<chrono::DateTime<chrono::Utc>>,
+        progress: Option<i32>,
+    }
+
+    match rb.query_decode::<Vec<UnlockedAchievementData>>(sql, vec![rbs::Value::String(user_id)]).await {
+        Ok(unlocked_achievements) => {
+            // 組合數據，添加 unlocked: true 字段
+            let result: Vec<serde_json::Value> = unlocked_achievements.iter().map(|ach| {
+                serde_json::json!({
+                    "id": ach.id,
+                    "name": ach.name,
+                    "description": ach.description,
+                    "icon": ach.icon,
+                    "category": ach.category,
+                    "requirement_type": ach.requirement_type,
+                    "requirement_value": ach.requirement_value,
+                    "experience_reward": ach.experience_reward,
+                    "unlocked": true, // 因為查詢結果都是已解鎖的
+                    "progress": ach.progress,
+                    "achieved_at": ach.achieved_at.as_ref().map(|dt| dt.to_string())
+                })
+            }).collect();
+
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(result),
+                message: "獲取用戶已解鎖的成就成功".to_string(),
+            }))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取用戶成就失敗: {}", e),
+        })),
+    }
+}
+
+// 獲取用戶的完整成就狀態（包含已解鎖和待完成）
+pub async fn get_user_achievements_status(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    // 獲取所有成就
+    let all_achievements = match Achievement::select_all(rb.get_ref()).await {
+        Ok(achievements) => achievements,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("獲取成就列表失敗: {}", e),
+            }));
+        }
+    };
+
+    // 獲取用戶已解鎖的成就
+    let user_achievements = match UserAchievement::select_by_map(
+        rb.get_ref(), 
+        value!{"user_id": user_id.clone()}
+    ).await {
+        Ok(achievements) => achievements,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("獲取用戶成就記錄失敗: {}", e),
+            }));
+        }
+    };
+
+    // 創建已解鎖成就的 HashMap 用於快速查找
+    let mut unlocked_map: std::collections::HashMap<String, &UserAchievement> = std::collections::HashMap::new();
+    for ua in &user_achievements {
+        if let Some(achievement_id) = &ua.achievement_id {
+            unlocked_map.insert(achievement_id.clone(), ua);
+        }
+    }
+
+    // 合併數據，為每個成就添加狀態信息
+    let default_id = String::new();
+    let result: Vec<serde_json::Value> = all_achievements.iter().map(|achievement| {
+        let achievement_id = achievement.id.as_ref().unwrap_or(&default_id);
+        let is_unlocked = unlocked_map.contains_key(achievement_id);
+        
+        let mut achievement_data = serde_json::json!({
+            "id": achievement.id,
+            "name": achievement.name,
+            "description": achievement.description,
+            "icon": achievement.icon,
+            "category": achievement.category,
+            "requirement_type": achievement.requirement_type,
+            "requirement_value": achievement.requirement_value,
+            "experience_reward": achievement.experience_reward,
+            "unlocked": is_unlocked,
+            "progress": 0,
+            "achieved_at": null
+        });
+
+        // 如果已解鎖，添加解鎖信息
+        if let Some(user_achievement) = unlocked_map.get(achievement_id) {
+            achievement_data["progress"] = serde_json::json!(user_achievement.progress.unwrap_or(0));
+            achievement_data["achieved_at"] = serde_json::json!(
+                user_achievement.achieved_at.as_ref().map(|dt| dt.to_string())
+            );
+        }
+
+        achievement_data
+    }).collect();
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(result),
+        message: "獲取用戶完整成就狀態成功".to_string(),
+    }))
+}
+
+// 解鎖用戶成就
+pub async fn unlock_user_achievement(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, achievement_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let now = Utc::now();
+    
+    // 檢查成就是否存在
+    match Achievement::select_by_map(rb.get_ref(), value!{"id": achievement_id.clone()}).await {
+        Ok(achievements) => {
+            if let Some(achievement) = achievements.first() {
+                // 檢查用戶是否已經解鎖此成就
+                match UserAchievement::select_by_map(
+                    rb.get_ref(), 
+                    value!{"user_id": user_id.clone(), "achievement_id": achievement_id.clone()}
+                ).await {
+                    Ok(user_achievements) => {
+                        if user_achievements.is_empty() {
+                            // 創建新的用戶成就記錄
+                            let user_achievement = UserAchievement {
+                                id: Some(Uuid::new_v4().to_string()),
+                                user_id: Some(user_id.clone()),
+                                achievement_id: Some(achievement_id.clone()),
+                                achieved_at: Some(now),
+                                progress: achievement.requirement_value.clone(),
+                            };
+                            
+                            match UserAchievement::insert(rb.get_ref(), &user_achievement).await {
+                                Ok(_) => {
+                                    // 成功插入用戶成就記錄後，更新成就統計
+                                    if let Err(e) = increment_achievement_completion_count(rb.get_ref(), &achievement_id).await {
+                                        log::warn!("更新成就統計失敗: {}", e);
+                                        // 不影響主要流程，只記錄警告
+                                    }
+
+                                    Ok(HttpResponse::Created().json(ApiResponse {
+                                        success: true,
+                                        data: Some(serde_json::json!({
+                                            "achievement": achievement,
+                                            "unlocked_at": now.to_rfc3339(),
+                                            "experience_reward": achievement.experience_reward
+                                        })),
+                                        message: format!("成就「{}」解鎖成功！", achievement.name.as_ref().unwrap_or(&"未知成就".to_string())),
+                                    }))
+                                },
+                                // 同時送出的重複解鎖由唯一索引擋下
+                                Err(e) if e.to_string().contains("UNIQUE") => Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                                    success: false,
+                                    data: None,
+                                    message: "成就已經解鎖".to_string(),
+                                })),
+                                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                                    success: false,
+                                    data: None,
+                                    message: format!("解鎖成就失敗: {}", e),
+                                })),
+                            }
+                        } else {
+                            Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                                success: false,
+                                data: None,
+                                message: "成就已經解鎖".to_string(),
+                            }))
+                        }
+                    }
+                    Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                        success: false,
+                        data: None,
+                        message: format!("檢查用戶成就失敗: {}", e),
+                    })),
+                }
+            } else {
+                Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "成就不存在".to_string(),
+                }))
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("查詢成就失敗: {}", e),
+        })),
+    }
+}
+
+// AI 成就生成相關
+
+#[derive(serde::Deserialize)]
+pub struct GenerateAchievementRequest {
+    pub user_id: String, // 使用者 ID，用於統計分析
+    #[serde(default)]
+    pub user_input: Option<String>, // 可選：相容舊版本
+}
+
+pub async fn generate_achievement_with_ai(
+    rb: web::Data<RBatis>,
+    req: web::Json<GenerateAchievementRequest>,
+) -> Result<HttpResponse> {
+    // 載入配置
+    let config = crate::config::Config::from_env();
+
+    // 創建 AI 服務
+    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+        Ok(service) => service,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("AI 服務初始化失敗: {}", e),
+            }));
+        }
+    };
+
+    // 生成成就 - 使用新的統計摘要策略
+    log::info!("開始為使用者 {} 生成成就（使用統計摘要優化）", req.user_id);
+
+    match ai_service.generate_achievement_from_user_id(rb.get_ref(), &req.user_id).await {
+        Ok(ai_achievement) => {
+            // 轉換為資料庫模型
+            let (achievement_model, sanitized) = convert_to_achievement_model(ai_achievement.clone());
+            
+            // 插入到資料庫
+            match Achievement::insert(rb.get_ref(), &achievement_model).await {
+                Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+                    success: true,
+                    data: Some(serde_json::json!({
+                        "ai_generated": ai_achievement,
+                        "database_record": achievement_model,
+                        "sanitized": sanitized
+                    })),
+                    message: format!("成功生成並儲存成就：{}", ai_achievement.name),
+                })),
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("儲存成就到資料庫失敗: {}", e),
+                })),
+            }
+        },
+        Err(e) => Ok(crate::ai_tasks::ai_failure_response("生成成就失敗", &e)),
+    }
+}
+
+// 獲取單個成就詳細資訊（包含統計數據）
+pub async fn get_achievement_details(
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let achievement_id = path.into_inner();
+
+    match get_achievement_with_stats(rb.get_ref(), &achievement_id).await {
+        Ok(Some(achievement_with_stats)) => {
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(achievement_with_stats),
+                message: "獲取成就詳細資訊成功".to_string(),
+            }))
+        }
+        Ok(None) => {
+            Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "成就不存在".to_string(),
+            }))
+        }
+        Err(e) => {
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("獲取成就詳細資訊失敗: {}", e),
+            }))
+        }
+    }
+}
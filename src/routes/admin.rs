@@ -0,0 +1,608 @@
+use actix_web::{web, HttpResponse, Result};
+use rbatis::RBatis;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::models::*;
+use rbs::value;
+use crate::services::ApiResponse;
+use crate::services::achievement_stats::sync_achievement_stats;
+
+// 重置類型枚舉
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetType {
+    All,           // 重置所有數據
+    Tasks,         // 只重置任務
+    Skills,        // 只重置技能
+    Chat,          // 只重置聊天記錄
+    Progress,      // 只重置進度數據
+    Achievements,  // 只重置成就
+    Profile,       // 只重置遊戲化資料
+}
+
+// 選擇性重置請求結構
+#[derive(serde::Deserialize)]
+pub struct SelectiveResetRequest {
+    pub reset_types: Vec<ResetType>,
+}
+
+// 重置查詢參數：dry_run=true 只預覽；實際刪除需帶入預覽回傳的 confirmation_token
+#[derive(serde::Deserialize)]
+pub struct ResetQuery {
+    pub dry_run: Option<bool>,
+    pub confirmation_token: Option<String>,
+}
+
+// 重置結果結構
+#[derive(serde::Serialize, Clone)]
+pub struct ResetResult {
+    pub total_deleted: i32,
+    pub details: std::collections::HashMap<String, i32>,
+}
+
+// 重置預覽結構（dry run）
+#[derive(serde::Serialize)]
+pub struct ResetPreview {
+    pub total_to_delete: i32,
+    pub details: std::collections::HashMap<String, i32>,
+    pub tables: std::collections::HashMap<String, i32>,
+    pub confirmation_token: String,
+    pub expires_at: chrono::DateTime<Utc>,
+}
+
+/// 重置步驟：同一份清單同時用於預覽計數與實際刪除，避免預覽與實際結果不一致
+struct ResetStep {
+    // 回傳 details 的分組名稱
+    group: &'static str,
+    table: &'static str,
+    // WHERE 條件，每個 ? 都綁定 user_id
+    condition: &'static str,
+}
+
+const BY_USER: &str = "user_id = ?";
+const BY_USER_OR_TASK: &str = "user_id = ? OR task_id IN (SELECT id FROM task WHERE user_id = ?)";
+const BY_TASK_OR_TAG: &str = "task_id IN (SELECT id FROM task WHERE user_id = ?) OR tag_id IN (SELECT id FROM tag WHERE user_id = ?)";
+const BY_PARENT_TASK: &str = "parent_task_id IN (SELECT id FROM task WHERE user_id = ?)";
+const SUBTASKS: &str = "user_id = ? AND parent_task_id IS NOT NULL";
+const PARENT_TASKS: &str = "user_id = ? AND parent_task_id IS NULL";
+
+// 預覽後多久內必須確認
+const RESET_CONFIRMATION_TTL_MINUTES: i64 = 10;
+
+fn step(group: &'static str, table: &'static str, condition: &'static str) -> ResetStep {
+    ResetStep { group, table, condition }
+}
+
+/// 任務相關的刪除步驟（附件、留言與標籤關聯需在任務刪除前處理）
+fn task_steps(group: &'static str) -> Vec<ResetStep> {
+    vec![
+        step(group, "task_attachment", BY_USER_OR_TASK),
+        step(group, "task_comment", BY_USER_OR_TASK),
+        step(group, "task_tag", BY_TASK_OR_TAG),
+        step(group, "cancellation_log", BY_USER),
+        step(group, "recurring_task_template", BY_PARENT_TASK),
+        step(group, "daily_task_summary", BY_USER),
+        step(group, "task", SUBTASKS),
+        step(group, "task", PARENT_TASKS),
+    ]
+}
+
+/// 完全重置的刪除步驟，按照外鍵依賴關係的順序
+fn full_reset_plan() -> Vec<ResetStep> {
+    let simple_tables = [
+        "achievement_share",
+        "user_achievement",
+        "weekly_attribute_snapshot",
+        "daily_progress",
+        "attribute_history",
+        "chat_message",
+        "chat_attachment",
+        "user_memory",
+        "focus_session",
+        "daily_quest",
+        "integration_secret",
+        "activity_mapping",
+        "integration_event",
+        "reward_redemption",
+        "reward",
+        "task_snapshot",
+        "coach_checkin_setting",
+        "difficulty_calibration",
+        "skill_experience_history",
+        "user_settings",
+        "api_token",
+        "task_pending_confirmation",
+        "career_generation_trace",
+        "background_job",
+        "monthly_report",
+        "career_review_item",
+        "career_review",
+    ];
+    let other_tables = [
+        "skill",
+        "user_attributes",
+        "user_profile",
+        "user_coach_preference",
+        "career_mainlines",
+        "quiz_results",
+        "tag",
+    ];
+
+    let mut plan: Vec<ResetStep> = simple_tables.iter().map(|table| step(table, table, BY_USER)).collect();
+    plan.extend(task_steps("task").into_iter().map(|s| ResetStep { group: s.table, ..s }));
+    plan.extend(other_tables.iter().map(|table| step(table, table, BY_USER)));
+    plan
+}
+
+/// 選擇性重置的刪除步驟；包含 All 時等同完全重置
+fn selective_reset_plan(reset_types: &[ResetType]) -> Vec<ResetStep> {
+    if reset_types.iter().any(|t| matches!(t, ResetType::All)) {
+        return full_reset_plan();
+    }
+    let mut plan = Vec::new();
+    for reset_type in reset_types {
+        match reset_type {
+            ResetType::All => {}
+            ResetType::Tasks => {
+                plan.push(step("tasks", "task_snapshot", BY_USER));
+                plan.extend(task_steps("tasks"));
+            }
+            ResetType::Skills => plan.push(step("skills", "skill", BY_USER)),
+            ResetType::Chat => {
+                plan.push(step("chat", "chat_message", BY_USER));
+                plan.push(step("chat", "chat_attachment", BY_USER));
+                plan.push(step("chat", "user_memory", BY_USER));
+            }
+            ResetType::Progress => {
+                for table in ["daily_progress", "weekly_attribute_snapshot", "attribute_history", "skill_experience_history"] {
+                    plan.push(step("progress", table, BY_USER));
+                }
+            }
+            ResetType::Achievements => {
+                plan.push(step("achievements", "achievement_share", BY_USER));
+                plan.push(step("achievements", "user_achievement", BY_USER));
+            }
+            ResetType::Profile => {
+                for table in [
+                    "user_attributes",
+                    "user_profile",
+                    "user_coach_preference",
+                    "career_review_item",
+                    "career_review",
+                    "career_mainlines",
+                    "quiz_results",
+                ] {
+                    plan.push(step("profile", table, BY_USER));
+                }
+            }
+        }
+    }
+    plan
+}
+
+/// 步驟清單的識別字串：確認碼只能用於與預覽相同的重置範圍
+fn plan_scope(plan: &[ResetStep]) -> String {
+    plan.iter().map(|s| format!("{}:{}", s.table, s.condition)).collect::<Vec<_>>().join("|")
+}
+
+fn step_params(step: &ResetStep, user_id: &str) -> Vec<rbs::Value> {
+    vec![rbs::Value::String(user_id.to_string()); step.condition.matches('?').count()]
+}
+
+struct PendingReset {
+    user_id: String,
+    scope: String,
+    expires_at: chrono::DateTime<Utc>,
+}
+
+static PENDING_RESETS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<String, PendingReset>>> =
+    std::sync::OnceLock::new();
+
+fn pending_resets() -> &'static std::sync::Mutex<std::collections::HashMap<String, PendingReset>> {
+    PENDING_RESETS.get_or_init(Default::default)
+}
+
+/// 發給預覽的確認碼，RESET_CONFIRMATION_TTL_MINUTES 分鐘內有效
+fn issue_confirmation(user_id: &str, scope: String) -> (String, chrono::DateTime<Utc>) {
+    let token = Uuid::new_v4().to_string();
+    let expires_at = Utc::now() + chrono::Duration::minutes(RESET_CONFIRMATION_TTL_MINUTES);
+    if let Ok(mut pending) = pending_resets().lock() {
+        let now = Utc::now();
+        pending.retain(|_, p| p.expires_at > now);
+        pending.insert(token.clone(), PendingReset { user_id: user_id.to_string(), scope, expires_at });
+    }
+    (token, expires_at)
+}
+
+/// 檢查並消耗確認碼（只能使用一次）
+fn consume_confirmation(token: &str, user_id: &str, scope: &str) -> bool {
+    let Ok(mut pending) = pending_resets().lock() else {
+        return false;
+    };
+    match pending.get(token) {
+        Some(p) if p.user_id == user_id && p.scope == scope && p.expires_at > Utc::now() => {
+            pending.remove(token);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn confirmation_required_response(token: Option<&str>) -> HttpResponse {
+    let message = if token.is_some() {
+        "確認碼無效、已使用或已過期，請重新以 dry_run=true 預覽"
+    } else {
+        "重置前請先以 dry_run=true 預覽，並帶入回傳的 confirmation_token"
+    };
+    HttpResponse::BadRequest().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.to_string(),
+    })
+}
+
+// 完全重置用戶數據 API
+pub async fn reset_user_data(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<ResetQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    run_reset(rb.get_ref(), &user_id, full_reset_plan(), query.into_inner()).await
+}
+
+// 選擇性重置用戶數據 API
+pub async fn reset_user_data_selective(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<ResetQuery>,
+    body: web::Json<SelectiveResetRequest>
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let request = body.into_inner();
+
+    log::info!("選擇性重置用戶 {} 的數據，重置類型: {:?}", user_id, request.reset_types.len());
+    run_reset(rb.get_ref(), &user_id, selective_reset_plan(&request.reset_types), query.into_inner()).await
+}
+
+/// 預覽或執行重置
+async fn run_reset(rb: &RBatis, user_id: &str, plan: Vec<ResetStep>, query: ResetQuery) -> Result<HttpResponse> {
+    let scope = plan_scope(&plan);
+
+    if query.dry_run.unwrap_or(false) {
+        let (token, expires_at) = issue_confirmation(user_id, scope);
+        let preview = preview_reset(rb, user_id, &plan, token, expires_at).await;
+        let message = format!("預覽完成，重置將刪除 {} 筆記錄", preview.total_to_delete);
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(preview),
+            message,
+        }));
+    }
+
+    let token = query.confirmation_token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    if !token.is_some_and(|token| consume_confirmation(token, user_id, &scope)) {
+        log::warn!("用戶 {} 的重置請求缺少有效確認碼", user_id);
+        return Ok(confirmation_required_response(token));
+    }
+
+    log::info!("開始重置用戶 {} 的數據...", user_id);
+    let result = execute_reset(rb, user_id, &plan).await;
+    crate::gamified_cache::invalidate(user_id);
+    log::info!("用戶 {} 數據重置成功，共刪除 {} 筆記錄", user_id, result.total_deleted);
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(result.clone()),
+        message: format!("用戶數據重置成功，共刪除 {} 筆記錄", result.total_deleted),
+    }))
+}
+
+/// 計算每個步驟會刪除的筆數，不刪除任何資料
+async fn preview_reset(
+    rb: &RBatis,
+    user_id: &str,
+    plan: &[ResetStep],
+    confirmation_token: String,
+    expires_at: chrono::DateTime<Utc>,
+) -> ResetPreview {
+    let mut preview = ResetPreview {
+        total_to_delete: 0,
+        details: std::collections::HashMap::new(),
+        tables: std::collections::HashMap::new(),
+        confirmation_token,
+        expires_at,
+    };
+
+    for step in plan {
+        let sql = format!("SELECT COUNT(*) FROM {} WHERE {}", step.table, step.condition);
+        let count = match rb.query_decode::<i64>(&sql, step_params(step, user_id)).await {
+            Ok(count) => count as i32,
+            Err(e) => {
+                log::warn!("計算 {} 表筆數時出現錯誤: {}", step.table, e);
+                0
+            }
+        };
+        *preview.details.entry(step.group.to_string()).or_insert(0) += count;
+        *preview.tables.entry(step.table.to_string()).or_insert(0) += count;
+        preview.total_to_delete += count;
+    }
+    preview
+}
+
+/// 依序執行刪除；單一步驟失敗只記錄警告，不中斷整個流程
+async fn execute_reset(rb: &RBatis, user_id: &str, plan: &[ResetStep]) -> ResetResult {
+    let mut total_deleted = 0i32;
+    let mut details = std::collections::HashMap::new();
+
+    for step in plan {
+        let deleted = match step.table {
+            // 附件需同時刪除檔案
+            "task_attachment" => crate::task_attachments::purge_user_attachments(rb, user_id).await,
+            "task_comment" => crate::task_comments::purge_user_comments(rb, user_id).await,
+            "chat_attachment" => crate::chat_attachments::purge_user_chat_attachments(rb, user_id).await,
+            table => {
+                // 刪除成就紀錄前先扣除全站完成次數，讓完成率保持正確
+                if table == "user_achievement" {
+                    if let Err(e) = crate::services::achievement_stats::decrement_completion_counts_for_user(rb, user_id).await {
+                        log::warn!("扣除成就完成次數時出現錯誤: {}", e);
+                    }
+                }
+                let sql = format!("DELETE FROM {} WHERE {}", table, step.condition);
+                rb.exec(&sql, step_params(step, user_id)).await.map(|result| result.rows_affected as i32)
+            }
+        };
+        match deleted {
+            Ok(deleted) => {
+                if deleted > 0 {
+                    log::info!("從 {} 表刪除了 {} 筆記錄", step.table, deleted);
+                }
+                *details.entry(step.group.to_string()).or_insert(0) += deleted;
+                total_deleted += deleted;
+            }
+            Err(e) => {
+                log::warn!("刪除 {} 表時出現錯誤: {}", step.table, e);
+            }
+        }
+    }
+
+    ResetResult {
+        total_deleted,
+        details,
+    }
+}
+
+// 同步成就統計數據的管理員 API
+pub async fn sync_achievement_statistics(rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    log::info!("開始同步成就統計數據...");
+
+    match sync_achievement_stats(rb.get_ref()).await {
+        Ok(synced_count) => {
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({
+                    "synced_achievements": synced_count,
+                    "message": format!("成功同步 {} 個成就的統計數據", synced_count)
+                })),
+                message: format!("成就統計數據同步完成，共處理 {} 個成就", synced_count),
+            }))
+        }
+        Err(e) => {
+            log::error!("同步成就統計數據失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("同步成就統計數據失敗: {}", e),
+            }))
+        }
+    }
+}
+
+// ============= 技能別名管理（管理員） =============
+
+#[derive(serde::Deserialize)]
+pub struct UpsertSkillAliasRequest {
+    pub alias: String,
+    pub canonical_name: String,
+}
+
+fn admin_forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "需要管理員權限".to_string(),
+    })
+}
+
+/// 列出所有技能別名
+pub async fn list_skill_aliases(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+
+    match SkillAlias::select_all(rb.get_ref()).await {
+        Ok(aliases) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(aliases),
+            message: "獲取技能別名成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取技能別名失敗: {}", e),
+        })),
+    }
+}
+
+/// 新增或更新技能別名（以摺疊後的別名為鍵）
+pub async fn upsert_skill_alias(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<UpsertSkillAliasRequest>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+
+    let alias = crate::skill_normalizer::fold_skill_name(&req.alias);
+    let canonical_name = req.canonical_name.trim().to_string();
+    if alias.is_empty() || canonical_name.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "別名與標準名稱不可為空".to_string(),
+        }));
+    }
+
+    let existing = match SkillAlias::select_by_map(rb.get_ref(), value!{"alias": &alias}).await {
+        Ok(list) => list.into_iter().next(),
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢技能別名失敗: {}", e),
+            }));
+        }
+    };
+
+    let result = match existing {
+        Some(mut record) => {
+            record.canonical_name = Some(canonical_name);
+            SkillAlias::update_by_map(rb.get_ref(), &record, value!{"id": &record.id})
+                .await
+                .map(|_| record)
+        }
+        None => {
+            let record = SkillAlias {
+                id: Some(Uuid::new_v4().to_string()),
+                alias: Some(alias),
+                canonical_name: Some(canonical_name),
+                created_at: Some(Utc::now()),
+            };
+            SkillAlias::insert(rb.get_ref(), &record).await.map(|_| record)
+        }
+    };
+
+    match result {
+        Ok(record) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(record),
+            message: "技能別名已儲存".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("儲存技能別名失敗: {}", e),
+        })),
+    }
+}
+
+/// 刪除技能別名
+pub async fn delete_skill_alias(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+
+    let alias_id = path.into_inner();
+    match SkillAlias::delete_by_map(rb.get_ref(), value!{"id": &alias_id}).await {
+        Ok(result) if result.rows_affected > 0 => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "技能別名已刪除".to_string(),
+        })),
+        Ok(_) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "技能別名不存在".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("刪除技能別名失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_reset_dry_run_requires_confirmation_token() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "resetter").await;
+        rb.exec(
+            "INSERT INTO achievement (id, name, requirement_type, requirement_value, experience_reward) VALUES ('ach_reset', '重置測試', 'task_complete', 1, 10)",
+            vec![],
+        )
+        .await
+        .unwrap();
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/users/{}/achievements/ach_reset/unlock", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 201);
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "要被重置的任務", "task_type": "side"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 201);
+
+        let selective = |query: &str| {
+            actix_web::test::TestRequest::post()
+                .uri(&format!("/api/users/{}/reset{}", user.id, query))
+                .insert_header(user.auth())
+                .set_json(json!({"reset_types": ["tasks", "achievements"]}))
+                .to_request()
+        };
+
+        // 預覽不刪除任何資料
+        let (status, body) = call_json(&app, selective("?dry_run=true")).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["details"]["tasks"], 1);
+        assert_eq!(body["data"]["details"]["achievements"], 1);
+        assert_eq!(body["data"]["tables"]["task"], 1);
+        assert_eq!(body["data"]["total_to_delete"], 2);
+        let token = body["data"]["confirmation_token"].as_str().unwrap().to_string();
+        let remaining: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM task WHERE user_id = ?", vec![rbs::Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(remaining, 1);
+
+        // 沒有確認碼、或確認碼用在不同範圍時拒絕
+        assert_eq!(call_json(&app, selective("")).await.0, 400);
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("/api/users/{}/reset?confirmation_token={}", user.id, token))
+            .insert_header(user.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 400);
+
+        let (status, body) = call_json(&app, selective(&format!("?confirmation_token={}", token))).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["total_deleted"], 2);
+        let stats = AchievementStats::select_by_map(&rb, value!{"achievement_id": "ach_reset"}).await.unwrap();
+        assert_eq!(stats[0].completion_count, Some(0));
+
+        // 確認碼只能使用一次
+        assert_eq!(call_json(&app, selective(&format!("?confirmation_token={}", token))).await.0, 400);
+    }
+}
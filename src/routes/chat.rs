@@ -0,0 +1,484 @@
+use actix_web::{web, HttpResponse, Result};
+use rbatis::RBatis;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::models::*;
+use rbs::value;
+use serde_json::json;
+use crate::services::ApiResponse;
+use crate::ai_service::{Expert, SharedAIService};
+
+// 聊天相關路由
+pub async fn get_chat_messages(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let user_id = query.get("user_id").map(|s| s.as_str());
+
+    let (sql, params): (String, Vec<rbs::Value>) = if let Some(uid) = user_id {
+        if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), uid) {
+            return Ok(e.into_response());
+        }
+        (
+            r#"
+                SELECT * FROM chat_message
+                WHERE user_id = ?
+                ORDER BY created_at DESC, role DESC
+                LIMIT 30
+            "#.to_string(),
+            vec![rbs::Value::String(uid.to_string())]
+        )
+    } else {
+        log::warn!("獲取聊天記錄時未提供 user_id，返回空結果");
+        // 不提供 user_id 時返回空結果，避免洩露其他用戶的對話
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(Vec::<crate::models::ChatMessage>::new()),
+            message: "請提供 user_id 參數".to_string(),
+        }));
+    };
+
+    match rb.query_decode::<Vec<crate::models::ChatMessage>>(&sql, params).await {
+        Ok(mut messages) => {
+            // 反轉順序，讓最早的消息在前
+            messages.reverse();
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(messages),
+                message: "獲取聊天記錄成功".to_string(),
+            }))
+        },
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取聊天記錄失敗: {}", e),
+        })),
+    }
+}
+
+// 獲取所有聊天記錄（用於下載）
+pub async fn get_all_chat_messages(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let user_id = query.get("user_id").map(|s| s.as_str());
+
+    let messages = if let Some(uid) = user_id {
+        if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), uid) {
+            return Ok(e.into_response());
+        }
+        // 只獲取指定用戶的聊天記錄
+        let sql = "SELECT * FROM chat_message WHERE user_id = ? ORDER BY created_at ASC";
+        match rb.query_decode::<Vec<crate::models::ChatMessage>>(sql, vec![rbs::Value::String(uid.to_string())]).await {
+            Ok(msgs) => msgs,
+            Err(e) => {
+                return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("獲取聊天記錄失敗: {}", e),
+                }));
+            }
+        }
+    } else {
+        log::warn!("下載聊天記錄時未提供 user_id，返回空結果");
+        Vec::new()
+    };
+
+    // UTF-8 BOM (Byte Order Mark) 用於確保正確編碼，特別是在 Windows 和手機上
+    let utf8_bom = "\u{FEFF}";
+    let mut text_content = String::from(utf8_bom);
+    text_content.push_str("=== AI 教練對話記錄 ===\n\n");
+
+    for msg in messages {
+        let role = msg.role.unwrap_or_else(|| "unknown".to_string());
+        let content = msg.content.unwrap_or_else(|| "".to_string());
+        let time = msg.created_at
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "未知時間".to_string());
+
+        let role_display = match (role.as_str(), &msg.expert_name) {
+            ("user", _) => "用戶".to_string(),
+            (_, Some(expert)) => format!("AI教練（{}）", expert),
+            _ => "AI教練".to_string(),
+        };
+        text_content.push_str(&format!("[{}] {} - {}\n{}\n\n", time, role_display, role, content));
+    }
+
+    // 返回文本檔案，添加 UTF-8 BOM 確保編碼正確
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header(("Content-Disposition", "attachment; filename=\"chat_history.txt\""))
+        .body(text_content))
+}
+
+pub async fn send_message(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<ChatRequest>,
+) -> Result<HttpResponse> {
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &req.user_id) {
+        return Ok(e.into_response());
+    }
+    let now = Utc::now();
+
+    // 儲存使用者訊息
+    let user_message = crate::models::ChatMessage {
+        id: Some(Uuid::new_v4().to_string()),
+        user_id: Some(req.user_id.clone()),
+        role: Some("user".to_string()),
+        content: Some(req.message.clone()),
+        source: Some(CHAT_SOURCE_BACKEND.to_string()),
+        expert_name: None,
+        attachment_id: None,
+        created_at: Some(now),
+    };
+
+    if let Err(e) = crate::models::ChatMessage::insert(rb.get_ref(), &user_message).await {
+        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("儲存使用者訊息失敗: {}", e),
+        }));
+    }
+
+    // 模擬 AI 回覆
+    let ai_response = format!("收到您的訊息：{}。我是您的 AI 教練，有什麼可以幫助您的嗎？", req.message);
+
+    let assistant_message = crate::models::ChatMessage {
+        id: Some(Uuid::new_v4().to_string()),
+        user_id: Some(req.user_id.clone()),
+        role: Some("assistant".to_string()),
+        content: Some(ai_response.clone()),
+        source: Some(CHAT_SOURCE_BACKEND.to_string()),
+        expert_name: None,
+        attachment_id: None,
+        created_at: Some(now),
+    };
+
+    match crate::models::ChatMessage::insert(rb.get_ref(), &assistant_message).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(assistant_message),
+            message: "訊息發送成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("儲存 AI 回覆失敗: {}", e),
+        })),
+    }
+}
+
+// 保存單條聊天訊息（用於系統訊息、專家訊息等）
+#[derive(serde::Deserialize)]
+pub struct SaveMessageRequest {
+    pub user_id: String,
+    pub role: String,       // "user", "assistant", "coach", "system"
+    pub content: String,
+}
+
+pub async fn save_chat_message(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<SaveMessageRequest>,
+) -> Result<HttpResponse> {
+    log::info!("收到保存聊天訊息請求: role={}, user_id={}", req.role, req.user_id);
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &req.user_id) {
+        return Ok(e.into_response());
+    }
+
+    if ChatRole::from_string(&req.role).is_none() {
+        return Ok(HttpResponse::UnprocessableEntity().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("無效的訊息角色: {}，可用值: {}", req.role, ChatRole::all_valid_strings().join(", ")),
+        }));
+    }
+
+    let now = Utc::now();
+    let chat_message = crate::models::ChatMessage {
+        id: Some(Uuid::new_v4().to_string()),
+        user_id: Some(req.user_id.clone()),
+        role: Some(req.role.clone()),
+        content: Some(req.content.clone()),
+        source: Some(CHAT_SOURCE_CLIENT.to_string()),
+        expert_name: None,
+        attachment_id: None,
+        created_at: Some(now),
+    };
+
+    match crate::models::ChatMessage::insert(rb.get_ref(), &chat_message).await {
+        Ok(_) => {
+            log::info!("成功保存聊天訊息");
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(chat_message),
+                message: "訊息保存成功".to_string(),
+            }))
+        },
+        Err(e) => {
+            log::error!("保存聊天訊息失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("保存訊息失敗: {}", e),
+            }))
+        }
+    }
+}
+
+// ChatGPT 聊天API端點
+#[derive(serde::Deserialize)]
+pub struct ChatGPTRequest {
+    pub message: String,
+    pub user_id: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ChatGPTResponse {
+    pub content: String,
+}
+
+/// 聊天回覆：text 為 AI 回覆本身，專家與教練個性以獨立欄位提供
+#[derive(Debug, serde::Serialize)]
+pub struct ChatReply {
+    pub text: String,
+    pub expert_name: String,
+    pub expert_emoji: String,
+    // 使用的教練個性，一般聊天（未套用個性）為 null
+    pub personality: Option<CoachPersonalityType>,
+}
+
+impl ChatReply {
+    pub fn new(text: String, expert: &Expert, personality: Option<CoachPersonalityType>) -> Self {
+        ChatReply {
+            text,
+            expert_name: expert.name.clone(),
+            expert_emoji: expert.emoji.clone(),
+            personality,
+        }
+    }
+
+    /// 回應用的內容：過渡期 text 仍加上「[emoji] 」前綴（資料庫保存的是未加前綴的內容）
+    pub fn into_response_data(mut self) -> Self {
+        if crate::api_envelope::legacy_expert_prefix_enabled() {
+            self.text = format!("[{}] {}", self.expert_emoji, self.text);
+        }
+        self
+    }
+}
+
+pub async fn send_message_to_chatgpt(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<SharedAIService>,
+    req: web::Json<ChatGPTRequest>,
+) -> Result<HttpResponse> {
+    log::info!("收到ChatGPT API請求: {}", req.message);
+    log::debug!("請求 user_id: {:?}", req.user_id);
+    // 指定 user_id 時只能以自己的身分對話（未指定時沿用預設測試用戶）
+    if let Some(id) = req.user_id.as_deref().filter(|s| !s.trim().is_empty()) {
+        if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), id) {
+            return Ok(e.into_response());
+        }
+    }
+    let now = Utc::now();
+
+    // 決定用戶ID（可選，如果沒有就不保存聊天記錄）
+    let user_id = if let Some(id) = req.user_id.clone().filter(|s| !s.trim().is_empty()) {
+        // 驗證提供的用戶ID是否存在
+        match User::select_by_map(rb.get_ref(), value!{"id": id.clone()}).await {
+            Ok(users) if !users.is_empty() => Some(id),
+            _ => {
+                log::warn!("提供的用戶ID不存在: {}", id);
+                // 嘗試使用預設測試用戶
+                match User::select_by_map(rb.get_ref(), value!{"email": "test@lifeup.com"}).await {
+                    Ok(users) if !users.is_empty() => {
+                        log::info!("使用預設測試用戶");
+                        Some(users[0].id.clone().unwrap())
+                    },
+                    _ => {
+                        log::warn!("找不到預設測試用戶，將以訪客身份對話");
+                        None
+                    }
+                }
+            }
+        }
+    } else {
+        // 沒有提供用戶ID，嘗試使用預設測試用戶
+        match User::select_by_map(rb.get_ref(), value!{"email": "test@lifeup.com"}).await {
+            Ok(users) if !users.is_empty() => {
+                log::info!("使用預設測試用戶");
+                Some(users[0].id.clone().unwrap())
+            },
+            _ => {
+                log::warn!("找不到預設測試用戶，將以訪客身份對話");
+                None
+            }
+        }
+    };
+    
+    // 如果有用戶ID，儲存使用者訊息到資料庫
+    if let Some(uid) = user_id.clone() {
+        let user_message = ChatMessage {
+            id: Some(Uuid::new_v4().to_string()),
+            user_id: Some(uid),
+            role: Some("user".to_string()),
+            content: Some(req.message.clone()),
+            source: Some(CHAT_SOURCE_BACKEND.to_string()),
+            expert_name: None,
+            attachment_id: None,
+            created_at: Some(now),
+        };
+
+        if let Err(e) = ChatMessage::insert(rb.get_ref(), &user_message).await {
+            log::error!("儲存使用者訊息失敗: {}", e);
+        } else {
+            log::info!("成功儲存使用者訊息");
+        }
+    } else {
+        log::info!("訪客模式，不保存用戶訊息");
+    }
+
+    // 呼叫ChatGPT API或使用本地回應
+    let reply = match call_chatgpt_api(ai.get_ref(), &req.message).await {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("AI 回應取得失敗: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("AI 服務調用失敗: {}", e),
+            }));
+        }
+    };
+
+    // 如果有用戶ID，儲存AI回覆到資料庫
+    if let Some(uid) = user_id.clone() {
+        let assistant_now = Utc::now();
+        let assistant_message = ChatMessage {
+            id: Some(Uuid::new_v4().to_string()),
+            user_id: Some(uid),
+            role: Some("assistant".to_string()),
+            content: Some(reply.text.clone()),
+            source: Some(CHAT_SOURCE_BACKEND.to_string()),
+            expert_name: Some(reply.expert_name.clone()),
+            attachment_id: None,
+            created_at: Some(assistant_now),
+        };
+
+        if let Err(e) = ChatMessage::insert(rb.get_ref(), &assistant_message).await {
+            log::error!("儲存AI回覆失敗: {}", e);
+        } else {
+            log::info!("成功儲存AI回覆");
+        }
+    } else {
+        log::info!("訪客模式，不保存AI回覆");
+    }
+    
+    // data.text 為正式欄位；最外層 text 僅供舊版前端過渡使用
+    Ok(crate::api_envelope::json_with_legacy_fields(HttpResponse::Ok(), ApiResponse {
+        success: true,
+        data: Some(reply.into_response_data()),
+        message: "AI 回應成功".to_string(),
+    }))
+}
+
+// 簡單的測試端點
+pub async fn test_endpoint() -> Result<HttpResponse> {
+    Ok(crate::api_envelope::json_with_legacy_fields(HttpResponse::Ok(), ApiResponse {
+        success: true,
+        data: Some(json!({ "timestamp": Utc::now().to_rfc3339() })),
+        message: "測試端點正常工作".to_string(),
+    }))
+}
+
+async fn call_chatgpt_api(ai: &SharedAIService, message: &str) -> Result<ChatReply, Box<dyn std::error::Error>> {
+    log::info!("開始呼叫AI 提供者");
+
+    // 取得共享的 AI 服務
+    let ai_service = match ai.get() {
+        Ok(service) => service,
+        Err(e) => {
+            log::error!("AI 服務初始化失敗: {}", e);
+            return Err(format!("AI 服務初始化失敗 ({})", e).into());
+        }
+    };
+
+    // 使用專家系統匹配最適合的專家（失敗時改用通用生活教練）
+    log::info!("開始為訊息匹配專家: {}", message);
+    let expert_match = crate::ai_service::match_expert_or_fallback(ai_service.as_ref(), message).await;
+
+    // 使用專家的專業知識構建提示詞
+    let prompt = format!(
+        "你是{}，{}。請根據你的專業知識為用戶提供建議。{}\n\n用戶訊息：{}",
+        expert_match.expert.name,
+        expert_match.expert.description,
+        crate::language::directive(),
+        message
+    );
+
+    log::info!("準備發送請求到 AI API (專家: {})", expert_match.expert.name);
+
+    match ai_service.generate_task_preview(&prompt).await {
+        Ok(response) => {
+            log::info!("成功從 AI API 獲取回應");
+            Ok(ChatReply::new(response, &expert_match.expert, None))
+        },
+        Err(e) => {
+            log::error!("AI API 調用失敗: {}", e);
+            Err(format!("AI API 調用失敗: {}", e).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+
+    #[actix_web::test]
+    async fn test_chat_falls_back_to_general_expert() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["先從一個小目標開始"]).without_expert_match();
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "lost_chatter").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/chat/chatgpt")
+            .insert_header(user.auth())
+            .set_json(json!({"message": "我不知道從哪裡開始", "user_id": user.id}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let data = &body["data"];
+        assert_eq!(data["expert_name"], "通用生活教練");
+        assert_eq!(data["expert_emoji"], "🧭");
+        assert!(data["personality"].is_null());
+        // 過渡期 text 仍帶前綴
+        assert_eq!(data["text"], "[🧭] 先從一個小目標開始");
+        assert!(mock.prompts("generate_task_preview")[0].contains("通用生活教練"));
+
+        // 保存的是未加前綴的內容與專家名稱
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/chat/messages?user_id={}", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let assistant = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["role"] == "assistant")
+            .cloned()
+            .unwrap();
+        assert_eq!(assistant["content"], "先從一個小目標開始");
+        assert_eq!(assistant["expert_name"], "通用生活教練");
+    }
+}
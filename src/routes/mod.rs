@@ -0,0 +1,936 @@
+// 路由模組：依領域拆分處理函數，configure 統一註冊所有路徑
+
+mod achievements;
+mod admin;
+mod chat;
+pub(crate) mod coach;
+#[cfg(feature = "push-notifications")]
+mod push;
+mod skills;
+mod tasks;
+mod users;
+
+use actix_web::{web, HttpResponse, Result};
+
+use crate::api_version::{ApiVersion, ApiVersioning};
+use crate::services::ApiResponse;
+
+use achievements::*;
+use admin::*;
+use chat::*;
+use coach::*;
+#[cfg(feature = "push-notifications")]
+use push::*;
+use skills::*;
+use tasks::*;
+use users::*;
+
+// 首頁整合 API 共用的查詢
+pub(crate) use tasks::load_homepage_tasks;
+pub(crate) use tasks::complete_recurring_day_for;
+pub(crate) use users::load_gamified_data;
+
+// 健康檢查
+pub async fn health_check() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some("LifeUp Backend is running!"),
+        message: "服務正常運行".to_string(),
+    }))
+}
+
+// 深度健康檢查：資料庫連線、目前的日誌檔與假日資料狀態（假日資料缺少時只降級，不影響健康狀態）
+pub async fn deep_health_check(rb: web::Data<rbatis::RBatis>) -> Result<HttpResponse> {
+    let database = rb.exec("SELECT 1", vec![]).await;
+    let data = serde_json::json!({
+        "database": match &database {
+            Ok(_) => "ok".to_string(),
+            Err(e) => format!("error: {}", e),
+        },
+        "log_file": crate::logging::active_log_file(),
+        "calendar": crate::calendar_service::shared().status(),
+    });
+    if database.is_err() {
+        return Ok(HttpResponse::ServiceUnavailable().json(ApiResponse {
+            success: false,
+            data: Some(data),
+            message: "資料庫無法連線".to_string(),
+        }));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(data),
+        message: "服務正常運行".to_string(),
+    }))
+}
+
+/// 註冊所有路由（HTTP 與 HTTPS 伺服器共用）
+///
+/// API 路由（configure_api）掛在 /api/v2、/api/v1 與不帶版本的 /api（v1 的別名），見 api_version。
+pub fn configure(cfg: &mut web::ServiceConfig, config: crate::config::Config) {
+    let versioning = config.app.api_versioning.clone();
+    cfg
+        // === 公開路由（不需要 JWT 認證）===
+        .route("/health", web::get().to(health_check))
+        .route("/health/deep", web::get().to(deep_health_check))
+        .route("/share/a/{token}", web::get().to(crate::achievement_share::share_page))
+        // 版本路徑需在 /api 之前註冊，否則會被 /api scope 攔截
+        .service(
+            web::scope("/api/v2")
+                .wrap(ApiVersioning::new(ApiVersion::V2))
+                .configure(|cfg| configure_api(cfg, &config)),
+        );
+    if versioning.v1_enabled {
+        cfg.service(
+            web::scope("/api/v1")
+                .wrap(ApiVersioning::new(ApiVersion::V1))
+                .configure(|cfg| configure_api(cfg, &config)),
+        )
+        .service(
+            web::scope("/api")
+                .wrap(ApiVersioning::legacy_alias(&versioning.v1_sunset))
+                .configure(|cfg| configure_api(cfg, &config)),
+        );
+    } else {
+        cfg.service(web::scope("/api").default_service(web::to(crate::api_version::v1_disabled)));
+    }
+    cfg
+        // 職業主線任務系統路由
+        .route("/api/quiz/save-results", web::post().to(crate::career_routes::save_quiz_results))
+        .route("/api/career/generate-tasks", web::post().to(crate::career_routes::generate_career_tasks))
+        .route("/api/career/accept-tasks", web::post().to(crate::career_routes::accept_career_tasks))
+        .route("/api/career/import", web::post().to(crate::career_routes::import_career_tasks))
+        // 多步驟漸進式任務生成（SSE）
+        .route("/api/career/generate-tasks-progressive", web::post().to(crate::progressive_career_gen::generate_career_tasks_progressive_sse))
+        .app_data(web::Data::new(config.clone()))
+        // 使用者資料重置路由
+        .route("/api/users/{user_id}/reset", web::delete().to(reset_user_data))
+        .route("/api/users/{user_id}/reset", web::post().to(reset_user_data_selective))
+        // 任務歷史路由
+        .route("/api/users/{user_id}/task-history", web::get().to(get_task_history))
+        // 推送通知相關路由（條件編譯）
+        .configure(configure_push_routes);
+}
+
+/// 註冊 API 路由（路徑不含 /api 與版本前綴，由 configure 掛到各版本 scope）
+fn configure_api(cfg: &mut web::ServiceConfig, config: &crate::config::Config) {
+    cfg
+        // === 公開路由（不需要 JWT 認證）===
+        .route("/auth/login", web::post().to(login))
+        .route("/users", web::post().to(create_user))  // 註冊
+        // 成就分享（憑分享 token 公開存取）
+        .route("/achievements/share/{user_achievement_id}", web::get().to(crate::achievement_share::get_shared_achievement))
+        // 公開個人檔案（依代號或 token，不需登入）
+        .route("/public/profiles/{key}", web::get().to(crate::public_profile::get_public_profile))
+        // 外部活動匯入（以整合密鑰簽章驗證）
+        .route("/integrations/ingest", web::post().to(crate::integrations::ingest))
+
+        // === 受保護路由（需要 JWT 認證）===
+        .service(
+            web::scope("")
+                .wrap(crate::ai_quota::AiQuota)  // AI 每日額度（需在 JwtAuth 之內執行）
+                .wrap(crate::auth::JwtAuth)  // 🔒 應用 JWT 認證中間件
+                // 登入裝置管理
+                .route("/auth/sessions", web::get().to(crate::sessions::list_sessions))
+                .route("/auth/sessions/revoke-all", web::post().to(crate::sessions::revoke_all))
+                .route("/auth/sessions/{id}", web::delete().to(crate::sessions::delete_session))
+                .route("/users/{id}/tokens", web::post().to(crate::api_tokens::create_token))
+                .route("/users/{id}/tokens", web::get().to(crate::api_tokens::list_tokens))
+                .route("/users/{id}/tokens/{token_id}", web::delete().to(crate::api_tokens::revoke_token))
+                // 任務標籤
+                .route("/tags", web::get().to(crate::task_tags::list_tags))
+                .route("/tags", web::post().to(crate::task_tags::create_tag))
+                .route("/tags/{id}", web::put().to(crate::task_tags::update_tag))
+                .route("/tags/{id}", web::delete().to(crate::task_tags::delete_tag))
+                .route("/tasks/{id}/tags", web::get().to(crate::task_tags::get_task_tags))
+                .route("/tasks/{id}/tags/{tag_id}", web::post().to(crate::task_tags::assign_tag))
+                .route("/tasks/{id}/tags/{tag_id}", web::delete().to(crate::task_tags::unassign_tag))
+                .route("/users/{id}/reports/monthly", web::get().to(crate::monthly_report::get_monthly_report))
+                .route("/users/{id}/daily-progress/rebuild", web::post().to(crate::recompute::rebuild_daily_progress))
+                .route("/users/{id}/cancellations", web::get().to(crate::task_cancellation::get_cancellation_summary))
+                .route("/users/{id}/memories", web::get().to(crate::user_memory::list_memories))
+                .route("/users/{id}/memories", web::post().to(crate::user_memory::create_memory))
+                .route("/users/{id}/memories/extract", web::post().to(crate::user_memory::extract_memories))
+                .route("/users/{id}/memories/{memory_id}", web::patch().to(crate::user_memory::update_memory))
+                .route("/users/{id}/memories/{memory_id}", web::delete().to(crate::user_memory::delete_memory))
+                .route("/users/{id}/memories/{memory_id}/confirm", web::post().to(crate::user_memory::confirm_memory))
+                // 背景工作狀態
+                .route("/jobs/{id}", web::get().to(crate::job_runner::get_job))
+                .route("/users/{id}/jobs", web::get().to(crate::job_runner::list_user_jobs))
+                // 認證相關
+                .route("/auth/logout", web::post().to(logout))
+                // 使用者相關
+                .route("/users", web::get().to(get_users))
+                .route("/users/{id}", web::get().to(get_user))
+                .route("/users/{id}/gamified", web::get().to(get_gamified_user_data))
+                .route("/users/{id}/heartbeat", web::post().to(user_heartbeat))
+                .route("/users/{id}/coach/checkin-settings", web::get().to(crate::coach_checkin::get_checkin_settings))
+                .route("/users/{id}/coach/checkin-settings", web::put().to(crate::coach_checkin::update_checkin_settings))
+                .route("/users/{id}/coach/fast-mode", web::get().to(crate::chat_fast_mode::get_fast_mode_settings))
+                .route("/users/{id}/coach/fast-mode", web::put().to(crate::chat_fast_mode::update_fast_mode_settings))
+                .route("/users/{id}/analytics/difficulty-calibration", web::get().to(crate::difficulty_calibration::get_difficulty_calibration))
+                .route("/users/{id}/analytics/difficulty-calibration/settings", web::put().to(crate::difficulty_calibration::update_calibration_settings))
+                .route("/users/{id}/experience", web::post().to(update_user_experience))
+                .route("/users/{id}/attributes", web::post().to(update_user_attributes))
+                .route("/users/{user_id}/achievements", web::get().to(get_user_achievements))
+                .route("/users/{user_id}/achievements/status", web::get().to(get_user_achievements_status))
+                .route("/users/{id}/achievements/auto-generate", web::get().to(crate::achievement_autogen::get_autogen_settings))
+                .route("/users/{id}/achievements/auto-generate", web::put().to(crate::achievement_autogen::update_autogen_settings))
+                .route("/users/{user_id}/achievements/{achievement_id}/unlock", web::post().to(unlock_user_achievement))
+                .route("/users/{user_id}/achievements/{achievement_id}/share", web::post().to(crate::achievement_share::create_share))
+                .route("/users/{user_id}/achievements/{achievement_id}/share", web::delete().to(crate::achievement_share::revoke_share))
+                .route("/users/{user_id}/attributes/weekly/{weeks_ago}", web::get().to(get_weekly_attributes))
+                .route("/users/{id}/attributes/compare", web::get().to(crate::attribute_compare::compare_weekly_attributes))
+                .route("/users/{user_id}/reset", web::delete().to(reset_user_data))
+                .route("/users/{user_id}/reset", web::post().to(reset_user_data_selective))
+                .route("/users/{user_id}/task-history", web::get().to(get_task_history))
+                .route("/users/{user_id}/notifications", web::get().to(crate::event_notifier::get_notification_history))
+                .route("/users/{user_id}/notification-center", web::get().to(crate::notification_center::get_notification_center))
+                .route("/users/{user_id}/notification-center/unread-count", web::get().to(crate::notification_center::get_unread_count))
+                .route("/users/{user_id}/notification-center/mark-all-read", web::post().to(crate::notification_center::mark_all_notifications_read))
+                .route("/notifications/preview-streak-risk/{user_id}", web::post().to(crate::streak_reminder::preview_streak_risk))
+                .route("/users/{user_id}/events/unseen", web::get().to(crate::event_notifier::get_unseen_events))
+                .route("/users/{user_id}/events/ack", web::post().to(crate::event_notifier::ack_events))
+                .route("/users/{user_id}/events", web::get().to(crate::event_notifier::stream_user_events))
+                .route("/users/{id}/ai-quota", web::get().to(crate::ai_quota::get_ai_quota))
+                .route("/users/{id}/focus/stats", web::get().to(crate::focus_sessions::get_focus_stats))
+                .route("/users/{id}/home", web::get().to(crate::home::get_home))
+                .route("/users/{id}/daily-quests", web::get().to(crate::daily_quests::get_daily_quests))
+                .route("/users/{id}/daily-quests/reroll", web::post().to(crate::daily_quests::reroll_daily_quests))
+                .route("/users/{id}/recommendations/attributes", web::get().to(crate::attribute_recommendations::get_attribute_recommendations))
+                .route("/users/{id}/redemptions", web::get().to(crate::reward_shop::get_redemptions))
+                .route("/users/{user_id}/leaderboard-visibility", web::put().to(crate::leaderboard::update_leaderboard_visibility))
+                .route("/users/{id}/profile-visibility", web::get().to(crate::public_profile::get_profile_visibility))
+                .route("/users/{id}/profile-visibility", web::put().to(crate::public_profile::update_profile_visibility))
+                .route("/users/{id}/settings", web::get().to(crate::user_settings::get_user_settings))
+                .route("/users/{id}/settings", web::patch().to(crate::user_settings::patch_user_settings))
+                .route("/users/{id}/data-retention", web::get().to(crate::data_retention::get_retention_settings))
+                .route("/users/{id}/data-retention", web::put().to(crate::data_retention::update_retention_settings))
+                // 排行榜
+                .route("/leaderboard", web::get().to(crate::leaderboard::get_leaderboard))
+                // 好友 / 監督夥伴
+                .route("/friends", web::get().to(crate::friends::list_friends))
+                .route("/friends/requests", web::post().to(crate::friends::send_friend_request))
+                .route("/friends/requests/{id}/accept", web::post().to(crate::friends::accept_friend_request))
+                .route("/friends/{id}", web::delete().to(crate::friends::remove_friend))
+                .route("/friends/{id}/summary", web::get().to(crate::friends::get_friend_summary))
+                .route("/tasks/{id}/participants", web::get().to(crate::shared_tasks::get_task_participants))
+                .route("/tasks/{id}/participants", web::post().to(crate::shared_tasks::add_task_participant))
+                .route("/tasks/{id}/participants/{user_id}", web::delete().to(crate::shared_tasks::remove_task_participant))
+                .route("/tasks/{id}/attachments", web::get().to(crate::task_attachments::list_attachments))
+                .route("/tasks/{id}/attachments", web::post().to(crate::task_attachments::upload_attachment))
+                .route("/tasks/{id}/attachments/{attachment_id}", web::get().to(crate::task_attachments::download_attachment))
+                .route("/tasks/{id}/attachments/{attachment_id}", web::delete().to(crate::task_attachments::delete_attachment))
+                .route("/tasks/{id}/comments", web::get().to(crate::task_comments::list_comments))
+                .route("/tasks/{id}/comments", web::post().to(crate::task_comments::create_comment))
+                .route("/tasks/{id}/comments/ask-coach", web::post().to(crate::task_comments::ask_coach))
+                .route("/focus/start", web::post().to(crate::focus_sessions::start_focus_session))
+                .route("/focus/{id}/complete", web::post().to(crate::focus_sessions::complete_focus_session))
+                .route("/focus/{id}/abort", web::post().to(crate::focus_sessions::abort_focus_session))
+                .route("/rewards", web::get().to(crate::reward_shop::list_rewards))
+                .route("/rewards", web::post().to(crate::reward_shop::create_reward))
+                .route("/rewards/{id}", web::put().to(crate::reward_shop::update_reward))
+                .route("/rewards/{id}", web::delete().to(crate::reward_shop::delete_reward))
+                .route("/rewards/{id}/redeem", web::post().to(crate::reward_shop::redeem_reward))
+                // 外部活動匯入設定
+                .route("/integrations/secret", web::get().to(crate::integrations::get_secret_status))
+                .route("/integrations/secret", web::post().to(crate::integrations::rotate_secret))
+                .route("/integrations/secret", web::delete().to(crate::integrations::delete_secret))
+                .route("/integrations/mappings", web::get().to(crate::integrations::list_mappings))
+                .route("/integrations/mappings", web::post().to(crate::integrations::create_mapping))
+                .route("/integrations/mappings/{id}", web::put().to(crate::integrations::update_mapping))
+                .route("/integrations/mappings/{id}", web::delete().to(crate::integrations::delete_mapping))
+                .route("/integrations/unmatched", web::get().to(crate::integrations::list_unmatched))
+                // 任務相關路由
+                .route("/tasks", web::get().to(get_tasks))
+                .route("/tasks", web::post().to(create_task))
+                .route("/tasks/homepage", web::get().to(get_homepage_tasks))
+                .route("/tasks/type/{task_type}", web::get().to(get_tasks_by_type))
+                .route("/tasks/{id}", web::get().to(get_task))
+                .route("/tasks/{id}", web::put().to(update_task))
+                .route("/tasks/{id}", web::delete().to(delete_task))
+                .route("/tasks/{id}/start", web::post().to(start_task))
+                .route("/tasks/{id}/confirm-completion", web::post().to(confirm_task_completion))
+                .route("/tasks/{id}/subtasks", web::get().to(get_subtasks))
+                .route("/tasks/{id}/tree", web::get().to(crate::task_tree::get_task_tree))
+                .route("/tasks/{id}/pause", web::put().to(pause_task))
+                .route("/tasks/{id}/cancel", web::put().to(cancel_task))
+                .route("/tasks/{id}/restart", web::put().to(restart_task))
+                .route("/tasks/{id}/generate-daily", web::post().to(generate_daily_tasks))
+                .route("/tasks/{id}/progress", web::get().to(get_task_progress))
+                .route("/tasks/{id}/habit-stats", web::get().to(crate::habit_stats::get_habit_stats))
+                .route("/tasks/{id}/days/{date}/complete", web::post().to(complete_recurring_day))
+                .route("/tasks/generate-skill-tags", web::post().to(generate_skill_tags))
+                // 技能別名管理（管理員）
+                .route("/admin/skill-aliases", web::get().to(list_skill_aliases))
+                .route("/admin/skill-aliases", web::post().to(upsert_skill_alias))
+                .route("/admin/skill-aliases/{id}", web::delete().to(delete_skill_alias))
+                .route("/admin/users/{id}/recompute", web::post().to(crate::recompute::recompute_user))
+                .route("/admin/cors/origins", web::get().to(crate::cors_policy::get_cors_origins))
+                .route("/admin/cors/reload", web::post().to(crate::cors_policy::reload_cors_origins))
+                .route("/admin/mail/test", web::post().to(crate::mailer::send_test_mail))
+                .route("/admin/metrics", web::get().to(crate::slow_log::get_metrics))
+                .route("/admin/digest/run", web::post().to(crate::nightly_digest::run_digest_now))
+                .route("/admin/career/traces/{job_id}", web::get().to(crate::career_trace::get_career_trace))
+                .route("/admin/users/{id}/ai-quota", web::put().to(crate::ai_quota::update_ai_quota))
+                .route("/admin/impersonate/{user_id}", web::post().to(crate::impersonation::impersonate_user))
+                .route("/admin/push/simulate", web::post().to(crate::push_scheduler::simulate_push))
+                .route("/admin/maintenance/compact-daily-tasks", web::post().to(crate::daily_compaction::compact_daily_tasks))
+                .route("/admin/reward-config", web::get().to(crate::reward_config::get_reward_config))
+                .route("/admin/calendar/reload", web::post().to(crate::calendar_service::reload_calendar))
+                // 推送金鑰管理（管理員，條件編譯）
+                .configure(configure_push_admin_routes)
+                .route("/tasks/generate", web::post().to(crate::ai_tasks::generate_task_with_ai))
+                .route("/tasks/generate-json", web::post().to(crate::ai_tasks::generate_task_json))
+                .route("/tasks/generate-daily-task-json", web::post().to(crate::ai_tasks::generate_daily_task_json))
+                .route("/tasks/insert-json", web::post().to(crate::ai_tasks::insert_task_from_json))
+                .route("/tasks/create-from-json", web::post().to(crate::ai_tasks::create_task_from_json))
+                .route("/tasks/validate-preview", web::post().to(crate::ai_tasks::validate_and_preview_task))
+                .route("/tasks/generate-from-chat", web::post().to(crate::ai_tasks::generate_task_from_chat))
+                .route("/tasks/generate-with-expert", web::post().to(crate::ai_tasks::generate_task_with_expert))
+                .route("/tasks/match-expert", web::post().to(crate::ai_tasks::match_expert_only))
+                .route("/tasks/expert-analysis", web::post().to(crate::ai_tasks::expert_analysis))
+                .route("/tasks/generate-subtasks", web::post().to(crate::ai_tasks::generate_subtasks_for_task))
+                .route("/tasks/classify-intent", web::post().to(crate::ai_tasks::classify_user_intent))
+                // 重複性任務路由
+                .route("/recurring-tasks", web::post().to(create_recurring_task))
+                .route("/recurring-tasks/{id}", web::get().to(crate::recurring_preview::get_recurring_task))
+                // 技能相關路由
+                .route("/skills", web::get().to(get_skills))
+                .route("/skills", web::post().to(create_skill))
+                .route("/skills/categories", web::get().to(crate::skill_categories::get_skill_categories))
+                .route("/skills/{id}", web::put().to(update_skill))
+                .route("/skills/{id}/experience", web::post().to(update_skill_experience))
+                .route("/skills/{id}/details", web::get().to(get_skill_details))
+                .route("/skills/{skill_name}/tasks", web::get().to(get_tasks_by_skill))
+                // 聊天相關路由
+                .route("/chat/messages", web::get().to(get_chat_messages))
+                .route("/chat/messages/all", web::get().to(get_all_chat_messages))
+                .route("/chat/send", web::post().to(send_message))
+                .route("/chat/save-message", web::post().to(save_chat_message))
+                .route("/chat/chatgpt", web::post().to(send_message_to_chatgpt))
+                // 附圖以 base64 或 multipart 傳送，body 上限依 CHAT_IMAGE_MAX_SIZE_BYTES 放寬
+                .service(
+                    web::resource("/chat/personality")
+                        .app_data(web::PayloadConfig::new(crate::chat_attachments::max_request_bytes()))
+                        .route(web::post().to(send_message_with_personality)),
+                )
+                .route("/chat/attachments/{id}", web::get().to(crate::chat_attachments::download_chat_attachment))
+                .route("/chat/test-personality", web::post().to(send_message_with_direct_personality))
+                .route("/chat/compare-personalities", web::post().to(compare_personalities))
+                .route("/chat/test", web::get().to(test_endpoint))
+                // 教練個性相關路由
+                .route("/coach/personalities", web::get().to(get_available_personalities))
+                .route("/coach/personality", web::post().to(set_coach_personality))
+                .route("/coach/personality/current", web::get().to(get_current_personality))
+                // 成就相關路由
+                .route("/achievements", web::get().to(get_achievements))
+                .route("/achievements/{id}", web::get().to(get_achievement_details))
+                .route("/achievements/sync-stats", web::post().to(sync_achievement_statistics))
+                .route("/achievements/generate", web::post().to(generate_achievement_with_ai))
+                .route("/achievements/generate-from-tasks/{user_id}", web::post().to(crate::ai_tasks::generate_achievement_from_tasks))
+                // 職業主線任務系統路由
+                .route("/quiz/save-results", web::post().to(crate::career_routes::save_quiz_results))
+                .route("/career/generate-tasks", web::post().to(crate::career_routes::generate_career_tasks))
+                .route("/career/accept-tasks", web::post().to(crate::career_routes::accept_career_tasks))
+                .route("/career/import", web::post().to(crate::career_routes::import_career_tasks))
+                .route("/career/generate-tasks-progressive", web::post().to(crate::progressive_career_gen::generate_career_tasks_progressive_sse))
+                .route("/career/mainlines/{id}/generate-achievements", web::post().to(crate::career_routes::generate_mainline_achievements))
+                .route("/career/mainlines/{id}/review", web::get().to(crate::career_review::get_review))
+                .route("/career/mainlines/{id}/review/commit", web::post().to(crate::career_review::commit_review))
+                .route("/career/mainlines/{id}/review/{item_id}", web::patch().to(crate::career_review::update_review_item))
+                .app_data(web::Data::new(config.clone()))
+        );
+}
+
+/// 配置推送通知相關路由（僅在啟用 push-notifications feature 時）
+#[cfg(feature = "push-notifications")]
+fn configure_push_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        // 推送通知路由
+        .route("/api/push/subscribe", web::post().to(subscribe_push))
+        .route("/api/push/unsubscribe", web::post().to(unsubscribe_push))
+        .route("/api/push/test/{user_id}", web::post().to(send_test_push))
+        .route("/api/push/vapid-public-key", web::get().to(get_vapid_public_key))
+        .route("/api/push/subscriptions", web::get().to(get_all_subscriptions))
+        .route("/api/push/clear-all", web::post().to(clear_all_subscriptions))
+        .route("/api/notifications/test-push/{user_id}", web::post().to(send_delayed_test_push))
+        // 通知設定路由
+        .route("/api/notification-settings/{user_id}", web::get().to(get_notification_settings))
+        .route("/api/notification-settings/{user_id}", web::put().to(update_notification_settings))
+        .route("/api/notifications/preview-morning/{user_id}", web::post().to(preview_morning_notification))
+        .route("/api/notifications/preview-evening/{user_id}", web::post().to(preview_evening_notification));
+}
+
+/// 配置推送金鑰管理路由（位於 /api scope 內，需要 JWT 的管理員身分）
+#[cfg(feature = "push-notifications")]
+fn configure_push_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        .route("/push/status", web::get().to(get_push_status))
+        .route("/admin/push/rotate-keys", web::post().to(rotate_vapid_keys));
+}
+
+#[cfg(not(feature = "push-notifications"))]
+fn configure_push_admin_routes(_cfg: &mut web::ServiceConfig) {}
+
+/// 配置推送通知相關路由的空實現（當未啟用 push-notifications feature 時）
+#[cfg(not(feature = "push-notifications"))]
+fn configure_push_routes(_cfg: &mut web::ServiceConfig) {
+    // 推送通知功能未啟用，不配置任何路由
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test;
+    use bcrypt::hash;
+    use rbs::Value;
+    use serde_json::json;
+
+    // 每次執行都會變動的欄位，比對前以固定字串取代
+    const DYNAMIC_KEYS: &[&str] = &["id", "user_id", "token", "password_hash", "created_at", "updated_at", "last_login_date", "task_date"];
+
+    fn normalize(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    if DYNAMIC_KEYS.contains(&key.as_str()) && !v.is_null() {
+                        *v = json!("<dynamic>");
+                    } else {
+                        normalize(v);
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(normalize),
+            _ => {}
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_deep_health_check() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let (status, body) = crate::test_utils::call_json(&app, test::TestRequest::get().uri("/health/deep").to_request()).await;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(body["data"]["database"], "ok");
+        // 測試未初始化日誌檔
+        assert!(body["data"]["log_file"].is_null());
+        assert_eq!(body["data"]["calendar"]["mode"], "full");
+    }
+
+    #[actix_web::test]
+    async fn test_route_snapshots() {
+        let rb = crate::test_utils::setup_db().await;
+        // 直接建立使用者（低成本雜湊，避免測試過慢），登入仍走正式路由
+        rb.exec(
+            "INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', ?)",
+            vec![Value::String(hash("Passw0rd!", 4).unwrap())],
+        )
+        .await
+        .unwrap();
+
+        let app = crate::test_utils::init_app(&rb).await;
+
+        let login = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({"email": "u1@lifeup.com", "password": "Passw0rd!"}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, login).await;
+        let token = body["data"]["token"].as_str().unwrap().to_string();
+        let auth = ("Authorization", format!("Bearer {}", token));
+
+        let created = test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(auth.clone())
+            .set_json(json!({"user_id": "u1", "title": "閱讀一本書", "task_type": "main", "difficulty": 2, "experience": 30}))
+            .to_request();
+        let created: serde_json::Value = test::call_and_read_body_json(&app, created).await;
+        let task_id = created["data"]["id"].as_str().unwrap().to_string();
+
+        let cases = vec![
+            ("health", test::TestRequest::get().uri("/health")),
+            ("users", test::TestRequest::get().uri("/api/users/u1")),
+            ("tasks", test::TestRequest::get().uri(&format!("/api/tasks/{}", task_id))),
+            ("skills", test::TestRequest::get().uri("/api/skills?user_id=u1")),
+            ("chat", test::TestRequest::get().uri("/api/chat/messages?user_id=u1")),
+            ("achievements", test::TestRequest::get().uri("/api/achievements")),
+            ("coach", test::TestRequest::get().uri("/api/coach/personalities?user_id=u1")),
+            ("admin", test::TestRequest::get().uri("/api/admin/skill-aliases")),
+        ];
+        let snapshots = vec![
+            (
+                200,
+                json!({
+                    "data": "LifeUp Backend is running!",
+                    "message": "服務正常運行",
+                    "success": true
+                }),
+            ),
+            (
+                200,
+                json!({
+                    "data": {
+                        "created_at": null,
+                        "email": "u1@lifeup.com",
+                        "id": "<dynamic>",
+                        "name": "測試用戶",
+                        "password_hash": "<dynamic>",
+                        "updated_at": null
+                    },
+                    "message": "獲取使用者成功",
+                    "success": true
+                }),
+            ),
+            (
+                200,
+                json!({
+                    "data": {
+                        "attributes": null,
+                        "cancel_count": 0,
+                        "career_mainline_id": null,
+                        "challenge_fail_penalty": null,
+                        "completion_mode": null,
+                        "completion_rate": 0.0,
+                        "comment_count": 0,
+                        "completion_target": 0.8,
+                        "created_at": "<dynamic>",
+                        "description": null,
+                        "difficulty": 2,
+                        "due_date": null,
+                        "end_date": null,
+                        "experience": 30,
+                        "id": "<dynamic>",
+                        "is_parent_task": 1,
+                        "is_recurring": 0,
+                        "last_cancelled_at": null,
+                        "parent_task_id": null,
+                        "priority": 1,
+                        "recurrence_pattern": null,
+                        "require_proof": 0,
+                        "requires_confirmation": 0,
+                        "retro_completed": 0,
+                        "skill_tags": null,
+                        "stake": null,
+                        "start_date": null,
+                        "status": 0,
+                        "task_category": null,
+                        "task_date": null,
+                        "task_order": 0,
+                        "task_type": "main",
+                        "title": "閱讀一本書",
+                        "updated_at": "<dynamic>",
+                        "user_id": "<dynamic>",
+                        "version": 0
+                    },
+                    "message": "獲取任務成功",
+                    "success": true
+                }),
+            ),
+            (
+                200,
+                json!({
+                    "data": [],
+                    "message": "獲取技能列表成功",
+                    "success": true
+                }),
+            ),
+            (
+                200,
+                json!({
+                    "data": [],
+                    "message": "獲取聊天記錄成功",
+                    "success": true
+                }),
+            ),
+            (
+                200,
+                json!({
+                    "data": [],
+                    "message": "獲取成就列表成功",
+                    "success": true
+                }),
+            ),
+            (
+                200,
+                json!({
+                    "data": {
+                        "current_personality": null,
+                        "personalities": [
+                            {
+                                "description": "直言不諱，用嚴厲的話語督促你成長",
+                                "display_name": "森氣氣",
+                                "emoji": "😤",
+                                "personality_type": "harsh_critic"
+                            },
+                            {
+                                "description": "溫暖體貼，提供情感支持和正向鼓勵",
+                                "display_name": "小太陽",
+                                "emoji": "🤗",
+                                "personality_type": "emotional_support"
+                            },
+                            {
+                                "description": "理性客觀，用數據和邏輯幫你分析問題",
+                                "display_name": "小書蟲",
+                                "emoji": "📊",
+                                "personality_type": "analytical"
+                            }
+                        ]
+                    },
+                    "message": "成功獲取可用教練個性",
+                    "success": true
+                }),
+            ),
+            (
+                403,
+                json!({
+                    "code": "FORBIDDEN",
+                    "data": null,
+                    "message": "需要管理員權限",
+                    "success": false
+                }),
+            ),
+        ];
+        assert_eq!(cases.len(), snapshots.len());
+        for ((name, req), (expected_status, expected_body)) in cases.into_iter().zip(snapshots) {
+            let resp = test::call_service(&app, req.insert_header(auth.clone()).to_request()).await;
+            assert_eq!(resp.status().as_u16(), expected_status, "{} 狀態碼不同", name);
+            let mut body: serde_json::Value = test::read_body_json(resp).await;
+            normalize(&mut body);
+            assert_eq!(body, expected_body, "{} 回應內容不同", name);
+        }
+    }
+
+    /// 從 configure 的原始碼列出所有已註冊的路由（method, 完整路徑）
+    fn registered_routes() -> Vec<(String, String)> {
+        let source = include_str!("mod.rs");
+        let source = source.split("#[cfg(test)]").next().unwrap();
+        source
+            .lines()
+            .filter_map(|line| {
+                let rest = line.trim().strip_prefix(".route(\"")?;
+                let (path, rest) = rest.split_once('"')?;
+                let method = rest.split_once("web::")?.1.split_once("()")?.0.to_string();
+                // configure_api 內的路由以相對路徑註冊，經由不帶版本的 /api 存取
+                let path = if path.starts_with("/health") || path.starts_with("/api/") || path.starts_with("/share/") {
+                    path.to_string()
+                } else {
+                    format!("/api{}", path)
+                };
+                Some((method, path))
+            })
+            .collect()
+    }
+
+    fn request_for(method: &str, path: &str) -> test::TestRequest {
+        let uri = path
+            .split('/')
+            .map(|segment| if segment.starts_with('{') { "nonexistent-id" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+        let req = match method {
+            "get" => test::TestRequest::get(),
+            "post" => test::TestRequest::post(),
+            "put" => test::TestRequest::put(),
+            "delete" => test::TestRequest::delete(),
+            "patch" => test::TestRequest::patch(),
+            other => panic!("未知的 HTTP 方法: {}", other),
+        };
+        req.uri(&uri)
+    }
+
+    fn assert_error_envelope(name: &str, status: u16, body: &[u8]) {
+        let body: serde_json::Value = serde_json::from_slice(body)
+            .unwrap_or_else(|_| panic!("{} ({}) 錯誤回應不是 JSON: {}", name, status, String::from_utf8_lossy(body)));
+        assert_eq!(body["success"], json!(false), "{} ({}) success 應為 false: {}", name, status, body);
+        assert!(body.get("data").is_some(), "{} ({}) 缺少 data: {}", name, status, body);
+        assert!(body["message"].is_string(), "{} ({}) 缺少 message: {}", name, status, body);
+        assert!(
+            body["code"].as_str().is_some_and(|c| !c.is_empty()),
+            "{} ({}) 缺少 code: {}",
+            name,
+            status,
+            body
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_every_route_error_path_returns_envelope() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "contract").await;
+
+        let routes = registered_routes();
+        assert!(routes.len() > 100, "路由解析數量異常: {}", routes.len());
+
+        // 未登入：/api 下的路由都應被 JwtAuth 擋下
+        for (method, path) in &routes {
+            let name = format!("{} {}", method.to_uppercase(), path);
+            let resp = test::call_service(&app, request_for(method, path).to_request()).await;
+            let status = resp.status();
+            if path.starts_with("/api/")
+                && path != "/api/auth/login"
+                && path != "/api/users"
+                && !path.starts_with("/api/achievements/share/")
+                && !path.starts_with("/api/public/")
+            {
+                assert_eq!(status.as_u16(), 401, "{} 未登入應回傳 401", name);
+            }
+            if status.is_client_error() || status.is_server_error() {
+                assert_error_envelope(&name, status.as_u16(), &test::read_body(resp).await);
+            }
+        }
+
+        // 已登入但請求內容錯誤（不存在的 id、無法解析的 JSON）：錯誤回應同樣是 ApiResponse
+        let mut error_count = 0;
+        for (method, path) in &routes {
+            // 會讓測試 token 失效的路由最後不再呼叫
+            if path == "/api/auth/logout" || path == "/api/auth/sessions/revoke-all" {
+                continue;
+            }
+            let name = format!("{} {}", method.to_uppercase(), path);
+            let req = request_for(method, path)
+                .insert_header(user.auth())
+                .insert_header(("Content-Type", "application/json"))
+                .set_payload("{");
+            let resp = test::call_service(&app, req.to_request()).await;
+            let status = resp.status();
+            if status.is_client_error() || status.is_server_error() {
+                error_count += 1;
+                assert_error_envelope(&name, status.as_u16(), &test::read_body(resp).await);
+            }
+        }
+        assert!(error_count > 50, "錯誤路徑數量異常: {}", error_count);
+    }
+
+    #[actix_web::test]
+    async fn test_task_and_skill_routes_reject_other_users() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let owner = crate::test_utils::create_user(&app, "resource_owner").await;
+        let intruder = crate::test_utils::create_user(&app, "intruder").await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(owner.auth())
+            .set_json(json!({"user_id": owner.id, "title": "私人任務", "task_type": "daily", "is_recurring": 1, "recurrence_pattern": "daily", "experience": 10}))
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 201, "{}", body);
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+        let req = test::TestRequest::post()
+            .uri("/api/skills")
+            .insert_header(owner.auth())
+            .set_json(json!({"name": "私人技能", "user_id": owner.id}))
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 201, "{}", body);
+        let skill_id = body["data"]["id"].as_str().unwrap().to_string();
+        rb.exec(
+            "INSERT INTO quiz_results (id, user_id, values_results, interests_results, talents_results, workstyle_results, completed_at)
+             VALUES ('owner-quiz', ?, '{}', '{}', '{}', '{}', '2026-01-01T00:00:00+00:00')",
+            vec![rbs::Value::String(owner.id.clone())],
+        )
+        .await
+        .unwrap();
+        rb.exec(
+            "INSERT INTO career_mainlines (id, user_id, quiz_result_id, selected_career) VALUES ('owner-mainline', ?, 'owner-quiz', '資料科學家')",
+            vec![rbs::Value::String(owner.id.clone())],
+        )
+        .await
+        .unwrap();
+        let mainline_id = "owner-mainline".to_string();
+        // 次要路徑參數也指向擁有者真實存在的資料，確認擋下的是擁有權檢查而不是 404
+        let seeds = [
+            "INSERT INTO tag (id, user_id, name) VALUES ('owner-tag', ?, '私人標籤')",
+            "INSERT INTO api_token (id, user_id, name, token_hash) VALUES ('owner-token', ?, '私人權杖', 'owner-token-hash')",
+            "INSERT INTO user_memory (id, user_id, content, source) VALUES ('owner-memory', ?, '私人記憶', 'manual')",
+            "INSERT INTO achievement (id, name, requirement_type) VALUES ('owner-achievement', '私人成就', 'task_complete')",
+            "INSERT INTO user_achievement (id, user_id, achievement_id) VALUES ('owner-user-achievement', ?, 'owner-achievement')",
+            "INSERT INTO career_review_item (id, mainline_id, user_id, task_group, item_order, title, difficulty, experience, payload)
+             VALUES ('owner-review-item', 'owner-mainline', ?, 'main', 0, '私人審核項目', 1, 10, '{}')",
+        ];
+        for sql in seeds {
+            let args = if sql.contains('?') { vec![rbs::Value::String(owner.id.clone())] } else { vec![] };
+            rb.exec(sql, args).await.unwrap();
+        }
+        rb.exec(
+            "INSERT INTO task_attachment (id, task_id, user_id, stored_name) VALUES ('owner-attachment', ?, ?, 'owner-attachment.png')",
+            vec![rbs::Value::String(task_id.clone()), rbs::Value::String(owner.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        // 以 id 定位任務、技能、職業主線或使用者的路由：其他使用者的 JWT 一律回傳 403 或 404
+        let scoped: Vec<(String, String, String)> = registered_routes()
+            .into_iter()
+            .filter_map(|(method, path)| {
+                let id = if path.starts_with("/api/tasks/{id}") {
+                    &task_id
+                } else if path.starts_with("/api/skills/{id}") {
+                    &skill_id
+                } else if path.starts_with("/api/career/mainlines/{id}") {
+                    &mainline_id
+                } else if path.starts_with("/api/users/{id}")
+                    || path.starts_with("/api/users/{user_id}")
+                    || path.starts_with("/api/achievements/generate-from-tasks/{user_id}")
+                {
+                    &owner.id
+                } else {
+                    return None;
+                };
+                let uri = path
+                    .split('/')
+                    .map(|segment| match segment {
+                        "{id}" => id.as_str(),
+                        "{user_id}" => owner.id.as_str(),
+                        "{date}" => "2026-01-01",
+                        "{weeks_ago}" => "0",
+                        "{tag_id}" => "owner-tag",
+                        "{token_id}" => "owner-token",
+                        "{memory_id}" => "owner-memory",
+                        "{achievement_id}" => "owner-achievement",
+                        "{attachment_id}" => "owner-attachment",
+                        "{item_id}" => "owner-review-item",
+                        s if s.starts_with('{') => panic!("{} 的 {} 沒有準備測試資料", path, s),
+                        s => s,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((method, path, uri))
+            })
+            .collect();
+        assert!(scoped.len() > 90, "以 id 定位的路由數量異常: {}", scoped.len());
+        for prefix in [
+            "/api/tasks/{id}",
+            "/api/skills/{id}",
+            "/api/career/mainlines/{id}",
+            "/api/users/{id}",
+            "/api/users/{user_id}",
+            "/api/achievements/generate-from-tasks/{user_id}",
+        ] {
+            assert!(scoped.iter().any(|(_, path, _)| path.starts_with(prefix)), "缺少 {} 路由", prefix);
+        }
+
+        let mut failures = Vec::new();
+        for (method, path, uri) in &scoped {
+            let name = format!("{} {}", method.to_uppercase(), path);
+            let req = request_for(method, path)
+                .uri(uri)
+                .insert_header(intruder.auth())
+                .set_json(json!({
+                    "user_id": intruder.id,
+                    "parent_task_id": task_id,
+                    "status": 2,
+                    "title": "被竄改",
+                    "name": "被竄改",
+                    "content": "被竄改",
+                    "experience_gain": 999,
+                    // 讓必填欄位通過 JSON 解析，確認擋下的是擁有權檢查
+                    "attributes": {},
+                    "reset_types": [],
+                    "event_ids": [],
+                    "apply_multipliers": false,
+                    "enabled": false,
+                    "visible": true,
+                    "visibility": "private",
+                }));
+            let resp = test::call_service(&app, req.to_request()).await;
+            let status = resp.status().as_u16();
+            let body = test::read_body(resp).await;
+            if status != 403 {
+                failures.push(format!("{} 實際 {}: {}", name, status, String::from_utf8_lossy(&body)));
+            }
+        }
+
+        // 以查詢字串或請求內容的 user_id 指定對象的路由
+        let by_user_id = [
+            ("get", format!("/api/tasks?user_id={}", owner.id)),
+            ("post", "/api/tasks".to_string()),
+            ("get", format!("/api/tasks/homepage?user_id={}", owner.id)),
+            ("get", format!("/api/tasks/type/daily?user_id={}", owner.id)),
+            ("post", "/api/recurring-tasks".to_string()),
+            ("get", format!("/api/skills?user_id={}", owner.id)),
+            ("post", "/api/skills".to_string()),
+            ("get", format!("/api/skills/私人技能/tasks?user_id={}", owner.id)),
+        ];
+        for (method, uri) in &by_user_id {
+            let name = format!("{} {}", method.to_uppercase(), uri);
+            let req = request_for(method, "/")
+                .uri(&uri.replace("私人技能", "%E7%A7%81%E4%BA%BA%E6%8A%80%E8%83%BD"))
+                .insert_header(intruder.auth())
+                .set_json(json!({
+                    "user_id": owner.id,
+                    "title": "冒名任務",
+                    "name": "冒名技能",
+                    "task_type": "daily",
+                    "recurrence_pattern": "daily",
+                    "subtask_templates": [],
+                }));
+            let resp = test::call_service(&app, req.to_request()).await;
+            let status = resp.status().as_u16();
+            let body = test::read_body(resp).await;
+            if status != 403 {
+                failures.push(format!("{} 實際 {}: {}", name, status, String::from_utf8_lossy(&body)));
+            }
+        }
+        assert!(failures.is_empty(), "其他使用者應回傳 403:\n{}", failures.join("\n"));
+
+        // 請求內容帶入他人的 parent_task_id：不能把子任務掛到別人的任務下
+        let req = test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(intruder.auth())
+            .set_json(json!({"user_id": intruder.id, "title": "寄生子任務", "task_type": "daily", "parent_task_id": task_id}))
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert!(matches!(status.as_u16(), 403 | 404), "建立子任務應被拒絕，實際 {}: {}", status, body);
+        let req = test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(intruder.auth())
+            .set_json(json!({"user_id": intruder.id, "title": "自己的任務", "task_type": "daily"}))
+            .to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 201, "{}", body);
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", body["data"]["id"].as_str().unwrap()))
+            .insert_header(intruder.auth())
+            .set_json(json!({"parent_task_id": task_id, "version": body["data"]["version"]}))
+            .to_request();
+        // 更新任務不接受 parent_task_id，欄位會被忽略
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+        assert!(body["data"]["parent_task_id"].is_null(), "{}", body);
+        let children: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM task WHERE parent_task_id = ?", vec![rbs::Value::String(task_id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(children, 0);
+
+        // 擁有者的資料沒有被修改
+        let req = test::TestRequest::get().uri(&format!("/api/tasks/{}", task_id)).insert_header(owner.auth()).to_request();
+        let (status, body) = crate::test_utils::call_json(&app, req).await;
+        assert_eq!(status.as_u16(), 200, "{}", body);
+        assert_eq!(body["data"]["title"], "私人任務");
+        assert_eq!(body["data"]["status"], 0);
+        let skill: crate::models::Skill = crate::models::Skill::select_by_map(&rb, rbs::value!{"id": &skill_id})
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(skill.name.as_deref(), Some("私人技能"));
+        assert_eq!(skill.experience.unwrap_or(0), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_legacy_fields_kept_during_transition() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "legacy").await;
+
+        let req = test::TestRequest::get().uri("/api/chat/test").insert_header(user.auth()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get(crate::api_envelope::DEPRECATION_HEADER).unwrap(), "true");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], json!(true));
+        assert!(body["data"]["timestamp"].is_string());
+        assert_eq!(body["timestamp"], body["data"]["timestamp"]);
+    }
+}
@@ -0,0 +1,648 @@
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use rbatis::RBatis;
+use chrono::Utc;
+use crate::models::*;
+use log::{info, error};
+use serde::Deserialize;
+use crate::services::ApiResponse;
+use crate::push_service::PushService;
+use crate::notification_generator::NotificationGenerator;
+use crate::notification_categories::{self, NotificationCategory};
+
+// ================= Push Notification Routes =================
+
+/// 訂閱推送通知
+pub async fn subscribe_push(
+    rb: web::Data<RBatis>,
+    req: web::Json<SubscribeRequest>,
+) -> Result<HttpResponse> {
+    match PushService::new() {
+        Ok(service) => {
+            match service.save_subscription(rb.get_ref(), req.into_inner()).await {
+                Ok(subscription) => Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(subscription),
+                    message: "訂閱推送通知成功".to_string(),
+                })),
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("訂閱失敗: {}", e),
+                })),
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("推送服務初始化失敗: {}", e),
+        })),
+    }
+}
+
+/// 取消訂閱推送通知
+pub async fn unsubscribe_push(
+    rb: web::Data<RBatis>,
+    req: web::Json<UnsubscribeRequest>,
+) -> Result<HttpResponse> {
+    match PushService::new() {
+        Ok(service) => {
+            match service.remove_subscription(rb.get_ref(), req.into_inner()).await {
+                Ok(success) => Ok(HttpResponse::Ok().json(ApiResponse {
+                    success,
+                    data: Some(success),
+                    message: if success { "取消訂閱成功".to_string() } else { "找不到該訂閱".to_string() },
+                })),
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("取消訂閱失敗: {}", e),
+                })),
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("推送服務初始化失敗: {}", e),
+        })),
+    }
+}
+
+/// 發送測試推送通知
+pub async fn send_test_push(
+    rb: web::Data<RBatis>,
+    user_id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+
+    match PushService::new() {
+        Ok(service) => {
+            let payload = PushNotificationPayload {
+                title: "測試通知".to_string(),
+                body: "這是一條測試推送通知！".to_string(),
+                icon: Some("/icon.svg".to_string()),
+                badge: Some("/icon.svg".to_string()),
+                tag: Some("test-notification".to_string()),
+                data: Some(serde_json::json!({
+                    "url": "/",
+                    "timestamp": Utc::now().to_rfc3339()
+                })),
+            };
+
+            match service.send_notification_to_user(rb.get_ref(), &user_id, &payload).await {
+                Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(serde_json::json!({
+                        "user_id": user_id
+                    })),
+                    message: format!("已向用戶 {} 發送測試通知", user_id),
+                })),
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("發送測試通知失敗: {}", e),
+                })),
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("推送服務初始化失敗: {}", e),
+        })),
+    }
+}
+
+/// 發送延遲測試推送通知
+#[derive(Deserialize)]
+pub struct DelayedTestPushRequest {
+    delay_seconds: Option<u64>,
+}
+
+pub async fn send_delayed_test_push(
+    rb: web::Data<RBatis>,
+    user_id: web::Path<String>,
+    req: web::Json<DelayedTestPushRequest>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    let user_id_clone = user_id.clone();
+    let delay = req.delay_seconds.unwrap_or(5);  // 默認5秒
+    let rb_clone = rb.get_ref().clone();
+
+    // 在後台任務中執行延遲發送
+    tokio::spawn(async move {
+        info!("延遲 {} 秒後發送測試通知給用戶: {}", delay, user_id_clone);
+
+        // 等待指定的秒數
+        tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+
+        match PushService::new() {
+            Ok(service) => {
+                let payload = PushNotificationPayload {
+                    title: "延遲測試通知".to_string(),
+                    body: format!("這是延遲 {} 秒的測試推送！", delay),
+                    icon: Some("/icon.svg".to_string()),
+                    badge: Some("/icon.svg".to_string()),
+                    tag: Some("delayed-test-notification".to_string()),
+                    data: Some(serde_json::json!({
+                        "url": "/",
+                        "type": "delayed-test",
+                        "timestamp": Utc::now().to_rfc3339(),
+                        "delay_seconds": delay
+                    })),
+                };
+
+                match service.send_notification_to_user(&rb_clone, &user_id_clone, &payload).await {
+                    Ok(_) => {
+                        info!("延遲測試通知已發送給用戶: {}", user_id_clone);
+                    }
+                    Err(e) => {
+                        error!("發送延遲測試通知失敗: {}", e);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("推送服務初始化失敗: {}", e);
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "user_id": user_id,
+            "delay_seconds": delay,
+            "scheduled_at": Utc::now().to_rfc3339()
+        })),
+        message: format!("已排程延遲測試通知給用戶 {}，將在 {} 秒後發送", user_id, delay),
+    }))
+}
+
+/// 獲取所有推送訂閱
+pub async fn get_all_subscriptions(rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    match PushService::new() {
+        Ok(service) => {
+            match service.get_all_subscriptions(rb.get_ref()).await {
+                Ok(subscriptions) => {
+                    let count = subscriptions.len();
+                    Ok(HttpResponse::Ok().json(ApiResponse {
+                        success: true,
+                        data: Some(subscriptions),
+                        message: format!("成功獲取 {} 個訂閱", count),
+                    }))
+                },
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("獲取訂閱列表失敗: {}", e),
+                })),
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("推送服務初始化失敗: {}", e),
+        })),
+    }
+}
+
+/// 清除用戶的所有推送訂閱
+#[derive(Deserialize)]
+pub struct ClearSubscriptionsRequest {
+    user_id: Option<String>,
+}
+
+pub async fn clear_all_subscriptions(
+    rb: web::Data<RBatis>,
+    req: web::Json<ClearSubscriptionsRequest>,
+) -> Result<HttpResponse> {
+    match PushService::new() {
+        Ok(service) => {
+            match service.remove_all_user_subscriptions(rb.get_ref(), req.user_id.clone()).await {
+                Ok(deleted_count) => Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(serde_json::json!({
+                        "deleted_count": deleted_count
+                    })),
+                    message: format!("成功清除 {} 個訂閱", deleted_count),
+                })),
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("清除訂閱失敗: {}", e),
+                })),
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("推送服務初始化失敗: {}", e),
+        })),
+    }
+}
+
+/// 獲取VAPID公鑰
+pub async fn get_vapid_public_key() -> Result<HttpResponse> {
+    match PushService::new() {
+        Ok(service) => {
+            let public_key = service.get_public_key();
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({
+                    "public_key": public_key
+                })),
+                message: "獲取VAPID公鑰成功".to_string(),
+            }))
+        }
+        // 未設定金鑰時前端無法訂閱，回 503 讓前端隱藏推送開關
+        Err(e) => Ok(HttpResponse::ServiceUnavailable().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("推送服務暫不可用: {}", e),
+        })),
+    }
+}
+
+fn admin_forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "需要管理員權限".to_string(),
+    })
+}
+
+/// 推送服務狀態（管理員）：金鑰是否設定、有效訂閱數與最近一次推送結果
+pub async fn get_push_status(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+
+    let active_subscriptions: i64 = match rb
+        .query_decode("SELECT COUNT(*) AS count FROM push_subscription", vec![])
+        .await
+    {
+        Ok(count) => count,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢推送訂閱失敗: {}", e),
+            }))
+        }
+    };
+    let keys = crate::vapid_keys::current();
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "keys_configured": keys.is_some(),
+            "key_source": keys.as_ref().map(|k| k.source),
+            "public_key": keys.map(|k| k.public_key),
+            "active_subscriptions": active_subscriptions,
+            "last_send": crate::vapid_keys::last_send_result(),
+        })),
+        message: "獲取推送狀態成功".to_string(),
+    }))
+}
+
+/// 輪替 VAPID 金鑰（管理員）：舊訂閱全部失效，並通知受影響的使用者重新開啟推送
+pub async fn rotate_vapid_keys(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(admin_forbidden());
+    }
+
+    let (public_key, affected_users) = match crate::vapid_keys::rotate(rb.get_ref()).await {
+        Ok(rotated) => rotated,
+        Err(e) => {
+            error!("輪替 VAPID 金鑰失敗: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("輪替 VAPID 金鑰失敗: {}", e),
+            }));
+        }
+    };
+    info!("VAPID 金鑰已輪替，{} 位使用者的推送訂閱已失效", affected_users.len());
+
+    let notification = serde_json::json!({
+        "title": "請重新開啟推送通知",
+        "body": "推送服務已更新，請到設定頁重新開啟推送通知，才能繼續收到提醒。",
+        "data": { "url": "/settings" },
+    });
+    for user_id in &affected_users {
+        crate::event_notifier::notify_scheduled(rb.get_ref(), user_id, "push_keys_rotated", &notification).await;
+    }
+
+    crate::audit_log::record(
+        rb.get_ref(),
+        crate::audit_log::ACTION_VAPID_KEYS_ROTATED,
+        None,
+        None,
+        serde_json::json!({
+            "invalidated_users": affected_users.len(),
+            "admin_user_id": crate::auth::current_user_id(&http_req),
+        }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "public_key": public_key,
+            "invalidated_users": affected_users.len(),
+        })),
+        message: "VAPID 金鑰已輪替，使用者需重新訂閱推送".to_string(),
+    }))
+}
+
+// ================= Notification Settings Routes =================
+
+/// 獲取用戶通知設定
+pub async fn get_notification_settings(
+    rb: web::Data<RBatis>,
+    user_id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+
+    let result: Option<UserNotificationSettings> = match rb
+        .query_decode(
+            "SELECT * FROM user_notification_settings WHERE user_id = ?",
+            vec![rbs::to_value!(&user_id)],
+        )
+        .await
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::error!("查詢通知設定失敗 (user_id: {}): {}", user_id, e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("載入通知設定失敗: {}", e),
+            }));
+        }
+    };
+
+    match result {
+        Some(mut settings) => {
+            // 舊資料可能缺少類別，回傳完整的類別設定
+            settings.categories = notification_categories::merge_categories(settings.categories.as_deref(), &Default::default()).ok();
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(settings),
+                message: "獲取通知設定成功".to_string(),
+            }))
+        }
+        None => {
+            // 如果不存在，創建默認設定
+            let default_settings = UserNotificationSettings {
+                id: Some(uuid::Uuid::new_v4().to_string()),
+                user_id: Some(user_id),
+                enabled: Some(true),
+                notify_on_workdays: Some(true),
+                notify_on_holidays: Some(false),
+                morning_enabled: Some(true),
+                morning_time: Some("08:00".to_string()),
+                evening_enabled: Some(true),
+                evening_time: Some("22:00".to_string()),
+                custom_schedules: Some("[]".to_string()),
+                quiet_hours_start: None,
+                quiet_hours_end: None,
+                notify_partner_on_miss: Some(false),
+                categories: Some(notification_categories::default_categories_json()),
+                created_at: Some(Utc::now()),
+                updated_at: Some(Utc::now()),
+            };
+
+            match UserNotificationSettings::insert(rb.get_ref(), &default_settings).await {
+                Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(default_settings),
+                    message: "創建默認通知設定成功".to_string(),
+                })),
+                Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("創建默認通知設定失敗: {}", e),
+                })),
+            }
+        }
+    }
+}
+
+/// 更新用戶通知設定
+pub async fn update_notification_settings(
+    rb: web::Data<RBatis>,
+    user_id: web::Path<String>,
+    req: web::Json<UpdateNotificationSettingsRequest>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    let updates = req.into_inner();
+
+    // 先驗證類別，未知類別不寫入任何變更
+    if let Some(categories) = &updates.categories {
+        if let Err(message) = notification_categories::merge_categories(None, categories) {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message,
+            }));
+        }
+    }
+
+    // 先獲取現有設定
+    let mut existing: Option<UserNotificationSettings> = rb
+        .query_decode(
+            "SELECT * FROM user_notification_settings WHERE user_id = ?",
+            vec![rbs::to_value!(user_id.clone())],
+        )
+        .await
+        .unwrap_or(None);
+
+    let settings = match &mut existing {
+        Some(settings) => {
+            // 更新字段
+            if let Some(enabled) = updates.enabled {
+                settings.enabled = Some(enabled);
+            }
+            if let Some(notify_on_workdays) = updates.notify_on_workdays {
+                settings.notify_on_workdays = Some(notify_on_workdays);
+            }
+            if let Some(notify_on_holidays) = updates.notify_on_holidays {
+                settings.notify_on_holidays = Some(notify_on_holidays);
+            }
+            if let Some(morning_enabled) = updates.morning_enabled {
+                settings.morning_enabled = Some(morning_enabled);
+            }
+            if let Some(morning_time) = updates.morning_time {
+                settings.morning_time = Some(morning_time);
+            }
+            if let Some(evening_enabled) = updates.evening_enabled {
+                settings.evening_enabled = Some(evening_enabled);
+            }
+            if let Some(evening_time) = updates.evening_time {
+                settings.evening_time = Some(evening_time);
+            }
+            if let Some(custom_schedules) = updates.custom_schedules {
+                settings.custom_schedules = Some(serde_json::to_string(&custom_schedules).unwrap_or_else(|_| "[]".to_string()));
+            }
+            // 傳入空字串表示清除勿擾時段
+            if let Some(quiet_hours_start) = updates.quiet_hours_start {
+                settings.quiet_hours_start = Some(quiet_hours_start).filter(|t| !t.is_empty());
+            }
+            if let Some(quiet_hours_end) = updates.quiet_hours_end {
+                settings.quiet_hours_end = Some(quiet_hours_end).filter(|t| !t.is_empty());
+            }
+            if let Some(notify_partner_on_miss) = updates.notify_partner_on_miss {
+                settings.notify_partner_on_miss = Some(notify_partner_on_miss);
+            }
+            settings.categories = notification_categories::merge_categories(
+                settings.categories.as_deref(),
+                &updates.categories.unwrap_or_default(),
+            )
+            .ok();
+            settings.updated_at = Some(Utc::now());
+            settings.clone()
+        }
+        None => {
+            // 創建新設定
+            UserNotificationSettings {
+                id: Some(uuid::Uuid::new_v4().to_string()),
+                user_id: Some(user_id.clone()),
+                enabled: updates.enabled.or(Some(true)),
+                notify_on_workdays: updates.notify_on_workdays.or(Some(true)),
+                notify_on_holidays: updates.notify_on_holidays.or(Some(false)),
+                morning_enabled: updates.morning_enabled.or(Some(true)),
+                morning_time: updates.morning_time.or(Some("08:00".to_string())),
+                evening_enabled: updates.evening_enabled.or(Some(true)),
+                evening_time: updates.evening_time.or(Some("22:00".to_string())),
+                custom_schedules: updates
+                    .custom_schedules
+                    .map(|s| serde_json::to_string(&s).unwrap_or_else(|_| "[]".to_string()))
+                    .or(Some("[]".to_string())),
+                quiet_hours_start: updates.quiet_hours_start.filter(|t| !t.is_empty()),
+                quiet_hours_end: updates.quiet_hours_end.filter(|t| !t.is_empty()),
+                notify_partner_on_miss: updates.notify_partner_on_miss.or(Some(false)),
+                categories: notification_categories::merge_categories(None, &updates.categories.unwrap_or_default()).ok(),
+                created_at: Some(Utc::now()),
+                updated_at: Some(Utc::now()),
+            }
+        }
+    };
+
+    // 保存到數據庫
+    let is_update = existing.is_some();
+    let settings_clone = settings.clone();
+
+    let save_result = if is_update {
+        rb.exec(
+            "UPDATE user_notification_settings
+             SET enabled = ?, notify_on_workdays = ?, notify_on_holidays = ?,
+                 morning_enabled = ?, morning_time = ?, evening_enabled = ?, evening_time = ?,
+                 custom_schedules = ?, quiet_hours_start = ?, quiet_hours_end = ?,
+                 notify_partner_on_miss = ?, categories = ?,
+                 updated_at = ?
+             WHERE user_id = ?",
+            vec![
+                rbs::to_value!(settings_clone.enabled.clone()),
+                rbs::to_value!(settings_clone.notify_on_workdays.clone()),
+                rbs::to_value!(settings_clone.notify_on_holidays.clone()),
+                rbs::to_value!(settings_clone.morning_enabled.clone()),
+                rbs::to_value!(settings_clone.morning_time.clone()),
+                rbs::to_value!(settings_clone.evening_enabled.clone()),
+                rbs::to_value!(settings_clone.evening_time.clone()),
+                rbs::to_value!(settings_clone.custom_schedules.clone()),
+                rbs::to_value!(settings_clone.quiet_hours_start.clone()),
+                rbs::to_value!(settings_clone.quiet_hours_end.clone()),
+                rbs::to_value!(settings_clone.notify_partner_on_miss.clone()),
+                rbs::to_value!(settings_clone.categories.clone()),
+                rbs::to_value!(Utc::now().to_rfc3339()),
+                rbs::to_value!(user_id),
+            ],
+        )
+        .await
+    } else {
+        UserNotificationSettings::insert(rb.get_ref(), &settings_clone).await
+    };
+
+    match save_result {
+        Ok(_) => {
+            // 勿擾時段包含在遊戲化資料的 settings 中
+            crate::gamified_cache::invalidate(settings.user_id.as_deref().unwrap_or_default());
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(settings),
+                message: "更新通知設定成功".to_string(),
+            }))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("更新通知設定失敗: {}", e),
+        })),
+    }
+}
+
+/// 預覽時尊重類別開關：類別停用時不產生內容
+async fn disabled_category_response(rb: &RBatis, user_id: &str, category: NotificationCategory) -> Option<HttpResponse> {
+    let settings = notification_categories::load_settings(rb, user_id).await?;
+    if notification_categories::category_enabled(&settings, category) {
+        return None;
+    }
+    Some(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({ "category": category.as_str(), "enabled": false })),
+        message: "此類別已停用".to_string(),
+    }))
+}
+
+/// 預覽早上通知內容
+pub async fn preview_morning_notification(
+    rb: web::Data<RBatis>,
+    user_id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    if let Some(response) = disabled_category_response(rb.get_ref(), &user_id, NotificationCategory::Morning).await {
+        return Ok(response);
+    }
+
+    match NotificationGenerator::generate_morning_notification(rb.get_ref(), &user_id).await {
+        Ok(notification) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(notification),
+            message: "生成早上通知預覽成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("生成通知預覽失敗: {}", e),
+        })),
+    }
+}
+
+/// 預覽晚上通知內容
+pub async fn preview_evening_notification(
+    rb: web::Data<RBatis>,
+    user_id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    if let Some(response) = disabled_category_response(rb.get_ref(), &user_id, NotificationCategory::Evening).await {
+        return Ok(response);
+    }
+
+    match NotificationGenerator::generate_evening_notification(rb.get_ref(), &user_id).await {
+        Ok(notification) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(notification),
+            message: "生成晚上通知預覽成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("生成通知預覽失敗: {}", e),
+        })),
+    }
+}
@@ -0,0 +1,1037 @@
+use actix_web::{web, HttpResponse, Result};
+use rbatis::RBatis;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::models::*;
+use rbs::{Value, value};
+use serde_json::json;
+use crate::services::ApiResponse;
+use crate::skill_categories::{SkillCategory, DEFAULT_SKILL_ICON};
+
+/// 技能等級上限
+pub const MAX_SKILL_LEVEL: i32 = 5;
+
+/// 技能等級曲線：該等級升到下一級所需經驗值（1 級沿用建立時的預設 100）
+pub fn skill_max_experience(level: i32) -> i32 {
+    if level <= 1 {
+        100
+    } else {
+        level * 200 + 100
+    }
+}
+
+/// 回傳給前端的技能：分類正規化為固定分類並附上顯示名稱
+fn skill_with_category(mut skill: Skill) -> serde_json::Value {
+    let category = SkillCategory::from_legacy(skill.category.as_deref());
+    skill.category = Some(category.to_string());
+    let mut value = serde_json::to_value(&skill).unwrap_or_default();
+    value["category_name"] = json!(category.display_name());
+    value
+}
+
+fn unprocessable(message: String) -> HttpResponse {
+    HttpResponse::UnprocessableEntity().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message,
+    })
+}
+
+// 技能相關路由
+pub async fn get_skills(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    // 獲取用戶ID參數
+    let user_id = match query.get("user_id") {
+        Some(id) => id,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "缺少user_id參數".to_string(),
+            }));
+        }
+    };
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), user_id) {
+        return Ok(e.into_response());
+    }
+
+    match Skill::select_by_map(rb.get_ref(), value!{"user_id": user_id.clone()}).await {
+        Ok(skills) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(skills.into_iter().map(skill_with_category).collect::<Vec<_>>()),
+            message: "獲取技能列表成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取技能列表失敗: {}", e),
+        })),
+    }
+}
+
+pub async fn create_skill(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<CreateSkillRequest>,
+) -> Result<HttpResponse> {
+    // 驗證 user_id 是否存在
+    let user_id = match &req.user_id {
+        Some(id) => id.clone(),
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "缺少user_id參數".to_string(),
+            }));
+        }
+    };
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    // 未指定分類時沿用欄位預設 technical；未知分類與非 emoji 圖示回傳 422
+    let category = match req.category.as_deref() {
+        Some(category) => match SkillCategory::parse(category) {
+            Ok(category) => category,
+            Err(message) => return Ok(unprocessable(message)),
+        },
+        None => SkillCategory::Technical,
+    };
+    let icon = match req.icon.as_deref() {
+        Some(icon) => match crate::skill_categories::validate_icon(icon) {
+            Ok(icon) => icon,
+            Err(message) => return Ok(unprocessable(message)),
+        },
+        None => DEFAULT_SKILL_ICON.to_string(),
+    };
+
+    let now = Utc::now();
+    let new_skill = crate::models::Skill {
+        id: Some(Uuid::new_v4().to_string()),
+        user_id: Some(user_id), // 使用驗證過的 user_id
+        name: Some(req.name.clone()),
+        description: req.description.clone(),
+        category: Some(category.to_string()),
+        attribute: req.attribute.clone(),
+        level: req.level,
+        experience: req.experience,
+        max_experience: req.max_experience,
+        icon: Some(icon),
+        created_at: Some(now),
+        updated_at: Some(now),
+    };
+
+    match crate::models::Skill::insert(rb.get_ref(), &new_skill).await {
+        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(skill_with_category(new_skill)),
+            message: "技能建立成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("技能建立失敗: {}", e),
+        })),
+    }
+}
+
+// 更新技能名稱、描述、分類、屬性或圖示（只有擁有者可修改）
+pub async fn update_skill(
+    rb: web::Data<RBatis>,
+    http_req: actix_web::HttpRequest,
+    path: web::Path<String>,
+    req: web::Json<UpdateSkillRequest>,
+) -> Result<HttpResponse> {
+    use validator::Validate;
+
+    let skill_id = path.into_inner();
+    if let Err(e) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("技能資料格式錯誤: {}", e),
+        }));
+    }
+
+    let mut skill = match crate::ownership::assert_skill_owner(rb.get_ref(), &skill_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(skill) => skill,
+        Err(e) => return Ok(e.into_response()),
+    };
+
+    if let Some(category) = req.category.as_deref() {
+        match SkillCategory::parse(category) {
+            Ok(category) => skill.category = Some(category.to_string()),
+            Err(message) => return Ok(unprocessable(message)),
+        }
+    }
+    if let Some(icon) = req.icon.as_deref() {
+        match crate::skill_categories::validate_icon(icon) {
+            Ok(icon) => skill.icon = Some(icon),
+            Err(message) => return Ok(unprocessable(message)),
+        }
+    }
+    if let Some(name) = &req.name {
+        skill.name = Some(name.clone());
+    }
+    if let Some(description) = &req.description {
+        skill.description = Some(description.clone());
+    }
+    if let Some(attribute) = &req.attribute {
+        skill.attribute = Some(attribute.clone());
+    }
+    skill.updated_at = Some(Utc::now());
+
+    match Skill::update_by_map(rb.get_ref(), &skill, value!{"id": skill_id}).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(skill_with_category(skill)),
+            message: "技能更新成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("技能更新失敗: {}", e),
+        })),
+    }
+}
+
+// 更新技能經驗值（只有擁有者可以累積）
+pub async fn update_skill_experience(
+    rb: web::Data<RBatis>,
+    http_req: actix_web::HttpRequest,
+    path: web::Path<String>,
+    req: web::Json<crate::models::UpdateSkillExperienceRequest>,
+) -> Result<HttpResponse> {
+    let skill_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_skill_owner(rb.get_ref(), &skill_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        return Ok(e.into_response());
+    }
+    
+    // 查詢技能
+    match crate::models::Skill::select_by_map(rb.get_ref(), value!{"id": skill_id.clone()}).await {
+        Ok(skills) => {
+            if let Some(mut skill) = skills.into_iter().next() {
+                // 增加經驗值
+                let current_exp = skill.experience.unwrap_or(0);
+                let new_exp = current_exp + req.experience_gain;
+                skill.experience = Some(new_exp);
+                
+                // 檢查升級
+                let current_level = skill.level.unwrap_or(1);
+                let mut max_exp = skill.max_experience.unwrap_or(100);
+                let mut final_exp = new_exp;
+                let mut final_level = current_level;
+                
+                // 升級邏輯：如果經驗值超過最大值且等級未達上限（一次可連升多級）
+                while final_exp >= max_exp && final_level < MAX_SKILL_LEVEL {
+                    final_exp -= max_exp;
+                    final_level += 1;
+                    // 每升一級，下一級所需經驗值增加
+                    max_exp = skill_max_experience(final_level);
+                    skill.max_experience = Some(max_exp);
+                }
+                
+                skill.experience = Some(final_exp);
+                skill.level = Some(final_level);
+                skill.updated_at = Some(Utc::now());
+                
+                // 更新資料庫
+                match crate::models::Skill::update_by_map(
+                    rb.get_ref(),
+                    &skill,
+                    value!{"id": skill_id}
+                ).await {
+                    Ok(_) => {
+                        record_experience_history(
+                            rb.get_ref(),
+                            &skill,
+                            req.experience_gain,
+                            current_level,
+                            final_level,
+                            req.reason.as_deref(),
+                        )
+                        .await;
+                        let level_up = final_level > current_level;
+                        let attribute_gain = if level_up {
+                            apply_level_up_attribute_gain(rb.get_ref(), &skill, final_level - current_level).await
+                        } else {
+                            None
+                        };
+                        let mut response_message = if level_up {
+                            format!("技能經驗值更新成功！恭喜升級到 {} 級！", final_level)
+                        } else {
+                            "技能經驗值更新成功".to_string()
+                        };
+                        if let Some(gain) = attribute_gain.as_ref().filter(|g| g.gain > 0) {
+                            response_message.push_str(&format!("{} +{}", gain.display_name, gain.gain));
+                        }
+                        
+                        Ok(HttpResponse::Ok().json(ApiResponse {
+                            success: true,
+                            data: Some(json!({
+                                "skill": skill,
+                                "experience_gained": req.experience_gain,
+                                "level_up": level_up,
+                                "previous_level": current_level,
+                                "new_level": final_level,
+                                "attribute_gain": attribute_gain,
+                                "reason": req.reason.clone().unwrap_or_default()
+                            })),
+                            message: response_message,
+                        }))
+                    },
+                    Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                        success: false,
+                        data: None,
+                        message: format!("更新技能經驗值失敗: {}", e),
+                    }))
+                }
+            } else {
+                Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "找不到該技能".to_string(),
+                }))
+            }
+        },
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("查詢技能失敗: {}", e),
+        }))
+    }
+}
+
+/// 技能升級帶來的屬性成長
+#[derive(Debug, serde::Serialize)]
+pub struct SkillAttributeGain {
+    pub attribute: String,
+    #[serde(skip)]
+    pub display_name: &'static str,
+    // 實際增加的點數（屬性已達上限時為 0）
+    pub gain: i32,
+    pub old_value: i32,
+    pub new_value: i32,
+}
+
+// 依設定提升技能對應的屬性，並以 skill_level_up 記入屬性變化紀錄；
+// 技能未設定屬性、屬性名稱無效或設定為 0 時不處理，失敗只記錄警告
+async fn apply_level_up_attribute_gain(rb: &RBatis, skill: &Skill, levels: i32) -> Option<SkillAttributeGain> {
+    let attribute = skill.attribute.as_deref()?;
+    let user_id = skill.user_id.as_deref()?;
+    let gain = crate::attribute_rewards::skill_level_up_gain(skill.category.as_deref(), levels);
+    if gain == 0 {
+        return None;
+    }
+    let result = crate::attribute_rewards::apply_attribute_deltas(
+        rb,
+        user_id,
+        &[(attribute.to_string(), gain)],
+        crate::attribute_rewards::SOURCE_SKILL_LEVEL_UP,
+        None,
+    )
+    .await;
+    match result {
+        Ok(changes) => {
+            let (old_value, new_value) = *changes.changes.get(attribute)?;
+            let display_name = crate::attribute_recommendations::ATTRIBUTES
+                .iter()
+                .find(|(name, _, _)| *name == attribute)
+                .map(|(_, display_name, _)| *display_name)
+                .unwrap_or("屬性");
+            Some(SkillAttributeGain {
+                attribute: attribute.to_string(),
+                display_name,
+                gain: new_value - old_value,
+                old_value,
+                new_value,
+            })
+        }
+        Err(e) => {
+            log::warn!("技能升級提升屬性失敗 (skill {:?}): {}", skill.id, e);
+            None
+        }
+    }
+}
+
+// 寫入技能經驗值流水；失敗只記錄警告，不影響經驗值更新結果
+async fn record_experience_history(
+    rb: &RBatis,
+    skill: &Skill,
+    experience_gain: i32,
+    old_level: i32,
+    new_level: i32,
+    reason: Option<&str>,
+) {
+    let (Some(skill_id), Some(user_id)) = (skill.id.as_ref(), skill.user_id.as_ref()) else {
+        return;
+    };
+    let result = rb
+        .exec(
+            "INSERT INTO skill_experience_history (id, user_id, skill_id, experience_gain, old_level, new_level, reason, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            vec![
+                Value::String(Uuid::new_v4().to_string()),
+                Value::String(user_id.clone()),
+                Value::String(skill_id.clone()),
+                Value::I32(experience_gain),
+                Value::I32(old_level),
+                Value::I32(new_level),
+                reason.map(|r| Value::String(r.to_string())).unwrap_or(Value::Null),
+                Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await;
+    if let Err(e) = result {
+        log::warn!("寫入技能經驗值紀錄失敗 (skill {}): {}", skill_id, e);
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct SkillDetailsQuery {
+    #[serde(default = "default_history_page")]
+    pub page: i64,
+    #[serde(default = "default_history_per_page")]
+    pub per_page: i64,
+}
+
+fn default_history_page() -> i64 {
+    1
+}
+
+fn default_history_per_page() -> i64 {
+    20
+}
+
+const MAX_HISTORY_PER_PAGE: i64 = 100;
+
+// 相關任務條件與 get_tasks_by_skill 一致：排除子任務，子任務有該標籤的父任務也算
+const LINKED_TASK_CONDITION: &str = "(t.task_type != 'subtask' OR t.task_type IS NULL) AND t.user_id = ? \
+    AND (t.skill_tags LIKE ? OR EXISTS (SELECT 1 FROM task c WHERE c.parent_task_id = t.id AND c.skill_tags LIKE ?))";
+
+/// 技能詳情：技能本身、等級進度、相關任務的狀態統計與分頁的經驗值歷史
+/// GET /api/skills/{id}/details?page=1&per_page=20
+pub async fn get_skill_details(
+    rb: web::Data<RBatis>,
+    http_req: actix_web::HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<SkillDetailsQuery>,
+) -> Result<HttpResponse> {
+    let skill_id = path.into_inner();
+    let page = query.page.max(1);
+    let per_page = query.per_page.clamp(1, MAX_HISTORY_PER_PAGE);
+
+    let skill = match crate::ownership::assert_skill_owner(rb.get_ref(), &skill_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(skill) => skill,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let user_id = skill.user_id.clone().unwrap_or_default();
+
+    // 相關任務依狀態分組計數（單一彙總查詢）
+    let skill_pattern = format!("%\"{}\"%", skill.name.clone().unwrap_or_default());
+    let status_sql = format!("SELECT t.status AS status, COUNT(*) AS count FROM task t WHERE {} GROUP BY t.status", LINKED_TASK_CONDITION);
+    let status_rows: Vec<serde_json::Value> = match rb
+        .query_decode(
+            &status_sql,
+            vec![Value::String(user_id.clone()), Value::String(skill_pattern.clone()), Value::String(skill_pattern)],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("統計相關任務失敗: {}", e),
+            }));
+        }
+    };
+    let task_counts = task_status_counts(&status_rows);
+
+    // 經驗值歷史：總筆數 + 分頁資料
+    let total: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) AS count FROM skill_experience_history WHERE skill_id = ?",
+            vec![Value::String(skill_id.clone())],
+        )
+        .await
+        .unwrap_or(0);
+    let history: Vec<serde_json::Value> = match rb
+        .query_decode(
+            "SELECT id, experience_gain, old_level, new_level, reason, created_at FROM skill_experience_history \
+             WHERE skill_id = ? ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?",
+            vec![Value::String(skill_id.clone()), Value::I64(per_page), Value::I64((page - 1) * per_page)],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢技能經驗值紀錄失敗: {}", e),
+            }));
+        }
+    };
+
+    let progression = skill_progression(&skill);
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(json!({
+            "skill": skill,
+            "progression": progression,
+            "task_counts": task_counts,
+            "history": {
+                "items": history,
+                "page": page,
+                "per_page": per_page,
+                "total": total,
+            },
+        })),
+        message: "獲取技能詳情成功".to_string(),
+    }))
+}
+
+// 依等級曲線計算升級進度；已達等級上限時沒有下一級門檻
+fn skill_progression(skill: &Skill) -> serde_json::Value {
+    let level = skill.level.unwrap_or(1);
+    let experience = skill.experience.unwrap_or(0);
+    let is_max_level = level >= MAX_SKILL_LEVEL;
+    let next_level_experience = (!is_max_level).then(|| skill_max_experience(level));
+    json!({
+        "level": level,
+        "max_level": MAX_SKILL_LEVEL,
+        "experience": experience,
+        "next_level_experience": next_level_experience,
+        "experience_to_next_level": next_level_experience.map(|needed| (needed - experience).max(0)),
+        "is_max_level": is_max_level,
+    })
+}
+
+// 將 GROUP BY status 的結果轉成 { 狀態字串: 數量 }，並附上總數
+fn task_status_counts(rows: &[serde_json::Value]) -> serde_json::Value {
+    let mut counts = std::collections::BTreeMap::new();
+    let mut total = 0i64;
+    for row in rows {
+        let count = row.get("count").and_then(|v| v.as_i64()).unwrap_or(0);
+        let status = row
+            .get("status")
+            .and_then(|v| v.as_i64())
+            .and_then(|s| TaskStatus::from_i32(s as i32))
+            .unwrap_or(TaskStatus::Pending);
+        *counts.entry(status.to_string()).or_insert(0i64) += count;
+        total += count;
+    }
+    json!({ "by_status": counts, "total": total })
+}
+
+// 根據技能名稱獲取相關任務
+pub async fn get_tasks_by_skill(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let skill_name = path.into_inner();
+    log::info!("獲取技能相關任務: {}", skill_name);
+
+    // 獲取用戶ID參數
+    let user_id = match query.get("user_id") {
+        Some(id) => id,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "缺少user_id參數".to_string(),
+            }));
+        }
+    };
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), user_id) {
+        return Ok(e.into_response());
+    }
+
+    // 查詢指定用戶的包含指定技能標籤的任務，但排除子任務；
+    // 父任務本身沒有該標籤、但子任務有時也算（子任務標籤向上彙總，不改動父任務的 skill_tags）
+    let sql = format!("SELECT * FROM task t WHERE {}", LINKED_TASK_CONDITION);
+    let skill_pattern = format!("%\"{}\"%", skill_name);
+    
+    match rb.query_decode::<Vec<Task>>(&sql, vec![Value::String(user_id.clone()), Value::String(skill_pattern.clone()), Value::String(skill_pattern)]).await {
+        Ok(tasks) => {
+            log::info!("成功獲取{}個「{}」相關任務", tasks.len(), skill_name);
+            let child_tags = match child_skill_tags(rb.get_ref(), &tasks).await {
+                Ok(tags) => tags,
+                Err(e) => {
+                    log::warn!("查詢子任務技能標籤失敗: {}", e);
+                    std::collections::HashMap::new()
+                }
+            };
+            
+            // 將任務狀態轉換為字串格式以供前端使用
+            let tasks_with_string_status: Vec<serde_json::Value> = tasks.iter().map(|task| {
+                let status_string = match task.status {
+                    Some(0) => "pending",
+                    Some(1) => "in_progress", 
+                    Some(2) => "completed",
+                    Some(3) => "cancelled",
+                    Some(4) => "paused",
+                    Some(5) => "daily_in_progress",
+                    Some(6) => "daily_completed", 
+                    Some(7) => "daily_not_completed",
+                    _ => "pending"
+                };
+                
+                serde_json::json!({
+                    "id": task.id,
+                    "title": task.title,
+                    "description": task.description,
+                    "status": status_string,
+                    "priority": task.priority,
+                    "task_type": task.task_type,
+                    "difficulty": task.difficulty,
+                    "experience": task.experience,
+                    "parent_task_id": task.parent_task_id,
+                    "is_parent_task": task.is_parent_task,
+                    "task_order": task.task_order,
+                    "due_date": task.due_date,
+                    "created_at": task.created_at,
+                    "updated_at": task.updated_at,
+                    "skill_tags": task.skill_tags,
+                    "effective_skill_tags": effective_skill_tags(
+                        task.skill_tags.as_deref().unwrap_or_default(),
+                        task.id.as_ref().and_then(|id| child_tags.get(id)).map(Vec::as_slice).unwrap_or_default(),
+                    )
+                })
+            }).collect();
+            
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(tasks_with_string_status),
+                message: format!("獲取「{}」相關任務成功", skill_name),
+            }))
+        },
+        Err(e) => {
+            log::error!("獲取「{}」相關任務失敗: {}", skill_name, e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("獲取「{}」相關任務失敗: {}", skill_name, e),
+            }))
+        }
+    }
+}
+
+// 父任務的有效技能標籤：自身標籤加上子任務的標籤（保留順序、去除重複）
+fn effective_skill_tags(own: &[String], children: &[Vec<String>]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in own.iter().chain(children.iter().flatten()) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    tags
+}
+
+// 一次查詢取得這些任務的子任務技能標籤：父任務 id -> 各子任務的標籤
+async fn child_skill_tags(rb: &RBatis, tasks: &[Task]) -> Result<std::collections::HashMap<String, Vec<Vec<String>>>, rbatis::Error> {
+    let parent_ids: Vec<String> = tasks.iter().filter_map(|t| t.id.clone()).collect();
+    let mut tags: std::collections::HashMap<String, Vec<Vec<String>>> = std::collections::HashMap::new();
+    if parent_ids.is_empty() {
+        return Ok(tags);
+    }
+    let placeholders = vec!["?"; parent_ids.len()].join(", ");
+    // 重複性任務的每日子任務標籤相同，依標籤分組避免載入大量資料列
+    let sql = format!(
+        "SELECT parent_task_id, skill_tags FROM task WHERE parent_task_id IN ({}) AND skill_tags IS NOT NULL GROUP BY parent_task_id, skill_tags",
+        placeholders
+    );
+    let rows: Vec<serde_json::Value> = rb.query_decode(&sql, parent_ids.into_iter().map(Value::String).collect()).await?;
+    for row in rows {
+        let Some(parent_id) = row.get("parent_task_id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        // SQLite 驅動會把 JSON 文字解析成陣列，舊資料也可能仍是字串
+        let child_tags: Vec<String> = match row.get("skill_tags") {
+            Some(serde_json::Value::String(s)) => serde_json::from_str(s).unwrap_or_default(),
+            Some(value) => serde_json::from_value(value.clone()).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        if !child_tags.is_empty() {
+            tags.entry(parent_id.to_string()).or_default().push(child_tags);
+        }
+    }
+    Ok(tags)
+}
+
+// ============= AI 技能標籤生成 =============
+
+#[derive(serde::Deserialize)]
+pub struct GenerateSkillTagsRequest {
+    pub task_title: String,
+    pub task_description: Option<String>,
+    pub user_id: String,
+    // 模糊比對門檻（0~1），未提供時使用預設值，設為 0 以下則停用模糊比對
+    pub fuzzy_threshold: Option<f64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct GenerateSkillTagsResponse {
+    pub skills: Vec<crate::skill_normalizer::NormalizedSkillTag>,
+}
+
+/// AI 生成技能標籤
+pub async fn generate_skill_tags(
+    rb: web::Data<RBatis>,
+    req: web::Json<GenerateSkillTagsRequest>,
+) -> Result<HttpResponse> {
+    log::info!("📝 收到技能標籤生成請求 - 任務: {}", req.task_title);
+
+    // 載入 AI 配置
+    let config = crate::config::Config::from_env();
+
+    // 創建 AI 服務
+    let ai_service = match crate::ai_service::create_ai_service(&config.app.ai) {
+        Ok(service) => service,
+        Err(e) => {
+            log::error!("AI 服務初始化失敗: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("AI 服務初始化失敗: {}", e),
+            }));
+        }
+    };
+
+    // 獲取使用者現有的技能列表
+    let user_existing_skills: Vec<String> = match Skill::select_by_map(
+        rb.get_ref(),
+        value!{"user_id": &req.user_id}
+    ).await {
+        Ok(skills) => skills
+            .iter()
+            .filter_map(|s| s.name.clone())
+            .collect(),
+        Err(e) => {
+            log::warn!("獲取使用者技能失敗，將使用空列表: {}", e);
+            Vec::new()
+        }
+    };
+
+    log::debug!("使用者現有技能: {:?}", user_existing_skills);
+
+    // 調用 AI 生成技能標籤
+    match ai_service.generate_skill_tags(
+        &req.task_title,
+        req.task_description.as_deref(),
+        &user_existing_skills
+    ).await {
+        Ok(result) => {
+            log::info!("✅ 成功生成技能標籤: {:?}", result.skills);
+
+            // 正規化：對應到既有技能，只保留真正的新技能
+            let aliases = crate::skill_normalizer::load_alias_map(rb.get_ref())
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("載入技能別名失敗，將略過別名比對: {}", e);
+                    Default::default()
+                });
+            let fuzzy_threshold = match req.fuzzy_threshold {
+                Some(t) if t <= 0.0 => None,
+                Some(t) => Some(t.min(1.0)),
+                None => Some(crate::skill_normalizer::DEFAULT_FUZZY_THRESHOLD),
+            };
+            let response_skills = crate::skill_normalizer::normalize_skill_tags(
+                &result,
+                &user_existing_skills,
+                &aliases,
+                fuzzy_threshold,
+            );
+            log::debug!("正規化後技能標籤: {:?}", response_skills);
+
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(GenerateSkillTagsResponse {
+                    skills: response_skills,
+                }),
+                message: "成功生成技能標籤".to_string(),
+            }))
+        }
+        Err(e) => Ok(crate::ai_tasks::ai_failure_response("生成技能標籤失敗", &e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    use crate::test_utils::{self, call_json};
+
+    #[test]
+    fn test_effective_skill_tags_union() {
+        let own = vec!["Reading".to_string()];
+        let children = vec![
+            vec!["Writing".to_string(), "Reading".to_string()],
+            vec!["Writing".to_string(), "Focus".to_string()],
+        ];
+        assert_eq!(super::effective_skill_tags(&own, &children), vec!["Reading", "Writing", "Focus"]);
+    }
+
+    #[actix_web::test]
+    async fn test_tasks_by_skill_includes_parent_of_tagged_subtask() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "skiller").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "寫一本書", "task_type": "main", "skill_tags": ["Reading"]}))
+            .to_request();
+        let (_, parent) = call_json(&app, req).await;
+        let parent_id = parent["data"]["id"].as_str().unwrap().to_string();
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "寫第一章", "task_type": "subtask", "parent_task_id": parent_id, "skill_tags": ["Writing"]}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::CREATED);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/skills/Writing/tasks?user_id={}", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let tasks = body["data"].as_array().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0]["id"], parent_id.as_str());
+        // 使用者設定的標籤不變，彙總結果另外提供
+        assert_eq!(tasks[0]["skill_tags"], json!(["Reading"]));
+        assert_eq!(tasks[0]["effective_skill_tags"], json!(["Reading", "Writing"]));
+    }
+
+    #[test]
+    fn test_skill_progression_curve() {
+        let skill = |level: i32, experience: i32| crate::models::Skill {
+            id: None,
+            user_id: None,
+            name: None,
+            description: None,
+            category: None,
+            attribute: None,
+            level: Some(level),
+            experience: Some(experience),
+            max_experience: None,
+            icon: None,
+            created_at: None,
+            updated_at: None,
+        };
+        let progression = super::skill_progression(&skill(2, 120));
+        assert_eq!(progression["next_level_experience"], 500);
+        assert_eq!(progression["experience_to_next_level"], 380);
+        let progression = super::skill_progression(&skill(super::MAX_SKILL_LEVEL, 50));
+        assert!(progression["is_max_level"].as_bool().unwrap());
+        assert!(progression["next_level_experience"].is_null());
+        assert_eq!(super::skill_max_experience(1), 100);
+    }
+
+    #[actix_web::test]
+    async fn test_skill_details_combines_history_and_task_counts() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "skill_owner").await;
+        let other = test_utils::create_user(&app, "skill_stranger").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/skills")
+            .insert_header(user.auth())
+            .set_json(json!({"name": "寫作", "user_id": user.id}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 201);
+        let skill_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        for gain in [60, 70, 30] {
+            let req = actix_web::test::TestRequest::post()
+                .uri(&format!("/api/skills/{}/experience", skill_id))
+                .insert_header(user.auth())
+                .set_json(json!({"experience_gain": gain, "reason": "練習"}))
+                .to_request();
+            assert_eq!(call_json(&app, req).await.0, 200);
+        }
+
+        for (id, status, tags) in [("t1", 2, r#"["寫作"]"#), ("t2", 0, r#"["寫作","閱讀"]"#), ("t3", 2, r#"["閱讀"]"#)] {
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, status, task_type, skill_tags) VALUES (?, ?, ?, ?, 'main', ?)",
+                vec![
+                    rbs::Value::String(id.to_string()),
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::String(id.to_string()),
+                    rbs::Value::I32(status),
+                    rbs::Value::String(tags.to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let details_uri = format!("/api/skills/{}/details?page=1&per_page=2", skill_id);
+        let req = actix_web::test::TestRequest::get().uri(&details_uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let data = &body["data"];
+        assert_eq!(data["skill"]["level"], 2);
+        assert_eq!(data["progression"]["next_level_experience"], 500);
+        assert_eq!(data["task_counts"]["total"], 2);
+        assert_eq!(data["task_counts"]["by_status"]["completed"], 1);
+        assert_eq!(data["task_counts"]["by_status"]["pending"], 1);
+        assert_eq!(data["history"]["total"], 3);
+        assert_eq!(data["history"]["items"].as_array().unwrap().len(), 2);
+
+        // 依時間新到舊，第二頁為最早的一筆
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/skills/{}/details?page=2&per_page=2", skill_id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let items = body["data"]["history"]["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["experience_gain"], 60);
+        assert_eq!(items[0]["reason"], "練習");
+
+        // 只有擁有者可查看
+        let req = actix_web::test::TestRequest::get().uri(&details_uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+
+    #[actix_web::test]
+    async fn test_multi_level_jump_applies_attribute_gain_per_level() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "skill_grinder").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/skills")
+            .insert_header(user.auth())
+            .set_json(json!({"name": "Rust", "user_id": user.id, "category": "technical", "attribute": "intelligence"}))
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let uri = format!("/api/skills/{}/experience", body["data"]["id"].as_str().unwrap());
+        let grant = |experience_gain: i32| {
+            actix_web::test::TestRequest::post()
+                .uri(&uri)
+                .insert_header(user.auth())
+                .set_json(json!({"experience_gain": experience_gain}))
+                .to_request()
+        };
+        let intelligence = || async {
+            let value: i32 = rb
+                .query_decode(
+                    "SELECT intelligence FROM user_attributes WHERE user_id = ?",
+                    vec![rbs::Value::String(user.id.clone())],
+                )
+                .await
+                .unwrap();
+            value
+        };
+        let before = intelligence().await;
+
+        // 700 經驗值：1 → 2 級（100），2 → 3 級（500），剩 100
+        let (status, body) = call_json(&app, grant(700)).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["previous_level"], 1);
+        assert_eq!(body["data"]["new_level"], 3);
+        assert_eq!(body["data"]["skill"]["experience"], 100);
+        assert_eq!(body["data"]["attribute_gain"]["attribute"], "intelligence");
+        assert_eq!(body["data"]["attribute_gain"]["gain"], 2);
+        assert!(body["message"].as_str().unwrap().contains("智力 +2"));
+        assert_eq!(intelligence().await, before + 2);
+
+        let history: Vec<serde_json::Value> = rb
+            .query_decode(
+                "SELECT attribute, delta FROM attribute_history WHERE user_id = ? AND source = 'skill_level_up'",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0]["delta"], 2);
+
+        // 沒升級不加屬性
+        let (_, body) = call_json(&app, grant(10)).await;
+        assert!(body["data"]["attribute_gain"].is_null());
+
+        // 屬性上限 100：連升兩級只實際增加 1
+        rb.exec(
+            "UPDATE user_attributes SET intelligence = 99 WHERE user_id = ?",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        let (_, body) = call_json(&app, grant(1590)).await;
+        assert_eq!(body["data"]["new_level"], 5);
+        assert_eq!(body["data"]["attribute_gain"]["gain"], 1);
+        assert_eq!(intelligence().await, 100);
+    }
+
+    #[actix_web::test]
+    async fn test_skill_category_and_icon_validation() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "skill_categorizer").await;
+        let other = test_utils::create_user(&app, "skill_category_stranger").await;
+
+        let req = actix_web::test::TestRequest::get().uri("/api/skills/categories").insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let categories = body["data"].as_array().unwrap();
+        assert_eq!(categories.len(), 6);
+        assert_eq!(categories[0], json!({"id": "technical", "name": "技術"}));
+
+        let create = |body: serde_json::Value| {
+            actix_web::test::TestRequest::post().uri("/api/skills").insert_header(user.auth()).set_json(body).to_request()
+        };
+        let (status, _) = call_json(&app, create(json!({"name": "烹飪", "user_id": user.id, "category": "cooking"}))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = call_json(&app, create(json!({"name": "烹飪", "user_id": user.id, "icon": "🍳🍳"}))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, body) = call_json(&app, create(json!({"name": "烹飪", "user_id": user.id, "category": "Creative"}))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["data"]["category"], "creative");
+        assert_eq!(body["data"]["category_name"], "創意");
+        assert_eq!(body["data"]["icon"], "⭐");
+        let uri = format!("/api/skills/{}", body["data"]["id"].as_str().unwrap());
+
+        let update = |auth: (&'static str, String), body: serde_json::Value| {
+            actix_web::test::TestRequest::put().uri(&uri).insert_header(auth).set_json(body).to_request()
+        };
+        let (status, _) = call_json(&app, update(user.auth(), json!({"category": "技術"}))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = call_json(&app, update(other.auth(), json!({"icon": "🍳"}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call_json(&app, update(user.auth(), json!({"category": "physical", "icon": "🍳"}))).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["icon"], "🍳");
+
+        // 舊資料的自由文字分類在列表中正規化
+        rb.exec(
+            "INSERT INTO skill (id, user_id, name, category) VALUES ('legacy', ?, '跑步', '運動')",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/skills?user_id={}", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let skills = body["data"].as_array().unwrap();
+        let legacy = skills.iter().find(|s| s["id"] == "legacy").unwrap();
+        assert_eq!((legacy["category"].as_str(), legacy["category_name"].as_str()), (Some("physical"), Some("體能")));
+        assert!(skills.iter().any(|s| s["category"] == "physical" && s["icon"] == "🍳"));
+    }
+}
@@ -0,0 +1,1020 @@
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use rbatis::RBatis;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::models::*;
+use rbs::value;
+use bcrypt::{hash, verify};
+use serde_json::json;
+use validator::Validate;
+use crate::services::ApiResponse;
+use crate::services::experience::apply_experience_gain;
+use crate::services::user_activity::{ensure_user_rows, record_activity};
+
+// Bcrypt 密碼雜湊成本 (14 比預設的 12 更安全)
+#[cfg(not(test))]
+const BCRYPT_COST: u32 = 14;
+// 測試時使用最低成本，避免整合測試註冊使用者過慢
+#[cfg(test)]
+const BCRYPT_COST: u32 = 4;
+
+// 使用者相關路由
+pub async fn get_users(rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    match User::select_all(rb.get_ref()).await {
+        Ok(users) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(users),
+            message: "獲取使用者列表成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取使用者列表失敗: {}", e),
+        })),
+    }
+}
+
+pub async fn get_user(http_req: actix_web::HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match User::select_by_map(rb.get_ref(), value!{"id": user_id}).await {
+        Ok(users) => {
+            if let Some(user) = users.first() {
+                Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(user.clone()),
+                    message: "獲取使用者成功".to_string(),
+                }))
+            } else {
+                Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "使用者不存在".to_string(),
+                }))
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取使用者失敗: {}", e),
+        })),
+    }
+}
+
+pub async fn create_user(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<CreateUserRequest>,
+) -> Result<HttpResponse> {
+    // 驗證輸入
+    if let Err(errors) = req.validate() {
+        let error_messages: Vec<String> = errors
+            .field_errors()
+            .iter()
+            .flat_map(|(field, errs)| {
+                errs.iter().map(move |e| {
+                    format!("{}: {}", field, e.code)
+                })
+            })
+            .collect();
+        log::warn!("註冊驗證失敗: {:?}", error_messages);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("輸入驗證失敗: {}", error_messages.join(", ")),
+        }));
+    }
+
+    // 正規化 email（去除空格並轉小寫）
+    let normalized_email = req.email.trim().to_lowercase();
+    log::info!("註冊請求: name={}, email={}", req.name, normalized_email);
+
+    // 防濫用檢查（拋棄式信箱、每個 IP 每日上限、人機驗證）；IP 取連線對端位址，轉發標頭可被偽造
+    let client_ip = crate::login_throttle::client_ip(&http_req);
+    if let Some(response) = crate::registration_guard::blocked_response(
+        &normalized_email,
+        client_ip.as_deref(),
+        req.captcha_token.as_deref(),
+    )
+    .await
+    {
+        return Ok(response);
+    }
+
+    // 快速檢查 email 是否已被註冊（避免無謂的密碼雜湊）；
+    // 同時註冊的競爭情況由寫入時的唯一索引判定
+    match User::select_by_map(rb.get_ref(), value!{"email": normalized_email.clone()}).await {
+        Ok(existing_users) => {
+            if !existing_users.is_empty() {
+                log::info!("註冊失敗：email 已存在 -> {}", normalized_email);
+                return Ok(email_taken_response());
+            }
+        }
+        Err(e) => {
+            log::error!("檢查 email 是否存在時發生錯誤: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("檢查email失敗: {}", e),
+            }));
+        }
+    }
+
+    // 哈希密碼 - 使用 cost 14 提升安全性
+    let password_hash = match hash(&req.password, BCRYPT_COST) {
+        Ok(hash) => hash,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("密碼處理失敗: {}", e),
+            }));
+        }
+    };
+
+    let now = Utc::now();
+    let new_user = User {
+        id: Some(Uuid::new_v4().to_string()),
+        name: Some(req.name.clone()),
+        email: Some(normalized_email),
+        password_hash: Some(password_hash),
+        created_at: Some(now),
+        updated_at: Some(now),
+    };
+
+    match register_user(rb.get_ref(), &new_user).await {
+        Ok(()) => {
+            crate::registration_guard::record_success(client_ip.as_deref());
+            Ok(HttpResponse::Created().json(ApiResponse {
+                success: true,
+                data: Some(new_user),
+                message: "使用者建立成功".to_string(),
+            }))
+        }
+        Err(RegistrationError::EmailTaken) => {
+            log::info!("註冊失敗（唯一索引）：email 已存在 -> {}", new_user.email.as_deref().unwrap_or_default());
+            Ok(email_taken_response())
+        }
+        Err(RegistrationError::Database(e)) => {
+            log::error!("使用者建立失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("使用者建立失敗: {}", e),
+            }))
+        }
+    }
+}
+
+fn email_taken_response() -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "該email已被註冊".to_string(),
+    })
+}
+
+/// 註冊寫入失敗原因
+#[derive(Debug)]
+enum RegistrationError {
+    /// email 已被註冊（由唯一索引判定）
+    EmailTaken,
+    Database(rbatis::Error),
+}
+
+impl From<rbatis::Error> for RegistrationError {
+    fn from(e: rbatis::Error) -> Self {
+        RegistrationError::Database(e)
+    }
+}
+
+// 依新帳號設定建立遊戲化資料與入門任務包
+async fn initialize_new_user(tx: &dyn rbatis::executor::Executor, user_id: &str) -> std::result::Result<(), rbatis::Error> {
+    ensure_user_rows(tx, user_id).await?;
+    if let Some(pack) = crate::new_user_defaults::current().starter_pack.as_deref() {
+        crate::new_user_defaults::create_starter_tasks(tx, user_id, pack).await?;
+    }
+    Ok(())
+}
+
+// 在同一個交易中建立使用者與遊戲化資料。email 衝突以 ON CONFLICT DO NOTHING 的影響列數判斷，
+// 不比對資料庫錯誤訊息（訊息內容會隨 SQLite 版本與語系不同）
+async fn register_user(rb: &RBatis, user: &User) -> std::result::Result<(), RegistrationError> {
+    let tx = rb.acquire_begin().await?;
+    let inserted = tx
+        .exec(
+            "INSERT INTO user (id, name, email, password_hash, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(email) DO NOTHING",
+            vec![
+                value!(&user.id),
+                value!(&user.name),
+                value!(&user.email),
+                value!(&user.password_hash),
+                value!(&user.created_at),
+                value!(&user.updated_at),
+            ],
+        )
+        .await;
+    let result = match inserted {
+        Ok(result) if result.rows_affected == 0 => Err(RegistrationError::EmailTaken),
+        Ok(_) => initialize_new_user(&tx, user.id.as_deref().unwrap_or_default()).await.map_err(RegistrationError::from),
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        let _ = tx.rollback().await;
+        return Err(e);
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// 登入路由
+pub async fn login(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    req: web::Json<LoginRequest>,
+) -> Result<HttpResponse> {
+    // 驗證輸入
+    if let Err(errors) = req.validate() {
+        let error_messages: Vec<String> = errors
+            .field_errors()
+            .iter()
+            .flat_map(|(field, errs)| {
+                errs.iter().map(move |e| {
+                    format!("{}: {}", field, e.code)
+                })
+            })
+            .collect();
+        log::warn!("登入驗證失敗: {:?}", error_messages);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("輸入驗證失敗: {}", error_messages.join(", ")),
+        }));
+    }
+
+    // 根據email查找用戶
+    let normalized_email = req.email.trim().to_lowercase();
+    log::info!("登入請求: email={}", normalized_email);
+
+    // 登入失敗節流：鎖定中的帳號回應與密碼錯誤相同；IP 取連線對端位址，轉發標頭可被偽造
+    let ip_address = http_req.connection_info().realip_remote_addr().map(|s| s.to_string());
+    let throttle_ip = crate::login_throttle::client_ip(&http_req);
+    if let Some(response) = crate::login_throttle::blocked_response(&normalized_email, throttle_ip.as_deref()) {
+        return Ok(response);
+    }
+    match User::select_by_map(rb.get_ref(), value!{"email": normalized_email.clone()}).await {
+        Ok(users) => {
+            if let Some(user) = users.first() {
+                // 驗證密碼
+                if let Some(password_hash) = &user.password_hash {
+                    match verify(&req.password, password_hash) {
+                        Ok(true) => {
+                            // 更新連續登入天數（以使用者時區的日期計算）
+                            if let Some(user_id) = &user.id {
+                                let today = crate::local_date::local_today_in(crate::user_settings::user_timezone(rb.get_ref(), user_id).await);
+                                match record_activity(rb.get_ref(), user_id, today).await {
+                                    Ok(Some(days)) => log::info!("用戶 {} 連續登入天數更新為: {}", user_id, days),
+                                    Ok(None) => {}
+                                    Err(e) => log::error!("更新連續登入天數失敗: {}", e),
+                                }
+                            }
+
+                            // 記錄登入裝置，token 綁定該 session 與目前的 token 版本
+                            let user_id = user.id.clone().unwrap_or_default();
+                            let user_agent = http_req.headers()
+                                .get(actix_web::http::header::USER_AGENT)
+                                .and_then(|v| v.to_str().ok());
+                            let (session_id, token_version) = match crate::sessions::create_session(
+                                rb.get_ref(),
+                                &user_id,
+                                user_agent,
+                                ip_address.as_deref(),
+                            ).await {
+                                Ok(session) => session,
+                                Err(e) => {
+                                    log::error!("建立登入裝置紀錄失敗: {}", e);
+                                    return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                                        success: false,
+                                        data: None,
+                                        message: "系統錯誤，請稍後再試".to_string(),
+                                    }));
+                                }
+                            };
+
+                            // 生成 JWT token
+                            let token = match crate::auth::generate_jwt(
+                                &user_id,
+                                &normalized_email,
+                                &session_id,
+                                token_version,
+                            ) {
+                                Ok(t) => t,
+                                Err(e) => {
+                                    log::error!("JWT 生成失敗: {}", e);
+                                    return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                                        success: false,
+                                        data: None,
+                                        message: format!("JWT 生成失敗: {}", e),
+                                    }));
+                                }
+                            };
+
+                            crate::login_throttle::record_success(&normalized_email);
+
+                            // 登入成功，返回用戶信息（不包含密碼哈希）和 JWT token
+                            let mut user_response = user.clone();
+                            user_response.password_hash = None; // 不返回密碼哈希
+
+                            log::info!("用戶 {} 登入成功，JWT token 已生成", user_response.id.as_ref().unwrap_or(&"unknown".to_string()));
+
+                            Ok(HttpResponse::Ok().json(ApiResponse {
+                                success: true,
+                                data: Some(LoginResponse {
+                                    user: user_response,
+                                    token,
+                                    message: "登入成功".to_string(),
+                                }),
+                                message: "登入成功".to_string(),
+                            }))
+                        }
+                        Ok(false) => {
+                            log::warn!("用戶 {} 登入失敗：密碼錯誤", req.email);
+                            Ok(crate::login_throttle::failed_login_response(rb.get_ref(), &normalized_email, throttle_ip.as_deref()).await)
+                        }
+                        Err(e) => {
+                            log::error!("密碼驗證失敗: {}", e);
+                            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                                success: false,
+                                data: None,
+                                message: "系統錯誤，請稍後再試".to_string(),
+                            }))
+                        }
+                    }
+                } else {
+                    log::warn!("用戶 {} 登入失敗：密碼未設定", req.email);
+                    Ok(crate::login_throttle::failed_login_response(rb.get_ref(), &normalized_email, throttle_ip.as_deref()).await)
+                }
+            } else {
+                log::warn!("用戶登入失敗：用戶不存在 (email: {})", req.email);
+                Ok(crate::login_throttle::failed_login_response(rb.get_ref(), &normalized_email, throttle_ip.as_deref()).await)
+            }
+        }
+        Err(e) => {
+            log::error!("用戶查找失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "系統錯誤，請稍後再試".to_string(),
+            }))
+        }
+    }
+}
+
+// 登出路由（目前簡單實現，未來可以配合session使用）
+pub async fn logout() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        success: true,
+        data: None,
+        message: "登出成功".to_string(),
+    }))
+}
+
+// 更新使用者經驗值
+pub async fn update_user_experience(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<crate::models::UpdateUserExperienceRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    match apply_experience_gain(rb.get_ref(), &user_id, req.experience_gain).await {
+        Ok(Some(change)) => {
+            let level_up = change.new_level > change.previous_level;
+            let level_down = change.new_level < change.previous_level;
+            let response_message = if level_up {
+                format!("經驗值更新成功！恭喜升級到 {} 級！", change.new_level)
+            } else if level_down {
+                format!("經驗值更新成功！降級到 {} 級", change.new_level)
+            } else {
+                "經驗值更新成功".to_string()
+            };
+
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(json!({
+                    "profile": change.profile,
+                    "experience_gained": req.experience_gain,
+                    "level_up": level_up,
+                    "level_down": level_down,
+                    "previous_level": change.previous_level,
+                    "new_level": change.new_level
+                })),
+                message: response_message,
+            }))
+        },
+        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "找不到該使用者資料".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("更新使用者經驗值失敗: {}", e),
+        }))
+    }
+}
+
+// 更新使用者屬性 API
+#[derive(serde::Deserialize)]
+pub struct UpdateUserAttributesRequest {
+    pub attributes: std::collections::HashMap<String, i32>,  // {"intelligence": 5, "creativity": -3}
+}
+
+pub async fn update_user_attributes(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<UpdateUserAttributesRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let deltas: Vec<(String, i32)> = req.attributes.iter().map(|(name, change)| (name.clone(), *change)).collect();
+
+    match crate::attribute_rewards::apply_attribute_deltas(
+        rb.get_ref(),
+        &user_id,
+        &deltas,
+        crate::attribute_rewards::SOURCE_MANUAL,
+        None,
+    ).await {
+        Ok(result) => {
+            log::info!("使用者 {} 屬性更新成功: {:?}", user_id, result.changes);
+
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(json!({
+                    "attributes": result.attributes,
+                    "changes": result.changes
+                })),
+                message: "屬性更新成功".to_string(),
+            }))
+        },
+        Err(e) => {
+            log::error!("更新使用者屬性失敗: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("更新使用者屬性失敗: {}", e),
+            }))
+        }
+    }
+}
+
+// 遊戲化數據相關 API
+
+// 組合遊戲化用戶數據（唯讀）；找不到資料或查詢失敗時回傳對應的錯誤回應
+pub(crate) async fn load_gamified_data(rb: &RBatis, user_id: &str) -> std::result::Result<serde_json::Value, HttpResponse> {
+    // 寫入路徑會清除快取，命中時直接回傳
+    if let Some(cached) = crate::gamified_cache::get(user_id) {
+        return Ok(cached);
+    }
+    // 組合期間若有寫入清除快取，put 會略過這份可能已過期的結果
+    let cache_generation = crate::gamified_cache::generation(user_id);
+    log::info!("正在獲取用戶 {} 的遊戲化數據", user_id);
+    
+    // 獲取基本用戶信息
+    log::info!("步驟 1: 獲取基本用戶信息");
+    let user = User::select_by_map(rb, value!{"id": user_id}).await
+        .map_err(|e| {
+            log::error!("獲取用戶失敗: {}", e);
+            format!("獲取用戶失敗: {}", e)
+        });
+    
+    // 獲取遊戲化資料
+    log::info!("步驟 2: 獲取遊戲化資料");
+    let profile = UserProfile::select_by_map(rb, value!{"user_id": user_id}).await
+        .map_err(|e| {
+            log::error!("獲取遊戲化資料失敗: {}", e);
+            format!("獲取遊戲化資料失敗: {}", e)
+        });
+    
+    // 獲取屬性
+    log::info!("步驟 3: 獲取屬性");
+    let attributes = UserAttributes::select_by_map(rb, value!{"user_id": user_id}).await
+        .map_err(|e| {
+            log::error!("獲取屬性失敗: {}", e);
+            format!("獲取屬性失敗: {}", e)
+        });
+    
+    // 獲取今日進度（使用者時區的今天）
+    let tz = crate::user_settings::user_timezone(rb, user_id).await;
+    let today = crate::local_date::local_today_string_in(tz);
+    log::info!("步驟 4: 獲取今日進度, 日期: {}", today);
+    let today_progress = DailyProgress::select_by_map(rb, value!{"user_id": user_id, "date": today}).await
+        .map_err(|e| {
+            log::error!("獲取今日進度失敗: {}", e);
+            format!("獲取今日進度失敗: {}", e)
+        });
+    
+    match (user, profile, attributes, today_progress) {
+        (Ok(users), Ok(profiles), Ok(attrs), Ok(progress_list)) => {
+            log::info!("獲取到的數據: users={}, profiles={}, attrs={}", users.len(), profiles.len(), attrs.len());
+            
+            let user = users.first();
+            let profile = profiles.first().cloned();
+            let attr = attrs.first().cloned();
+            
+            if user.is_none() {
+                log::error!("未找到用戶資料");
+                return Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "用戶不存在".to_string(),
+                }));
+            }
+            
+            // 遊戲化資料在註冊時建立，讀取時不補齊
+            if profile.is_none() || attr.is_none() {
+                log::error!(
+                    "用戶 {} 缺少資料：profile={} attrs={}",
+                    user_id,
+                    profile.is_none(),
+                    attr.is_none()
+                );
+                return Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: "用戶資料尚未初始化，請稍後重試".to_string(),
+                }));
+            }
+
+            let user = user.unwrap();
+            let profile = profile.unwrap();
+            let attr = attr.unwrap();
+
+            log::info!("成功獲取用戶數據: user={:?}, profile={:?}, attr={:?}", user.name, profile.level, attr.intelligence);
+
+            // 計算冒險天數（從賬號創建日期算起）
+            let adventure_days = if let Some(created_at) = profile.created_at {
+                let created_date = crate::local_date::local_date_in(created_at, tz);
+                let today_date = crate::local_date::local_today_in(tz);
+                let days_diff = (today_date - created_date).num_days();
+                // 創建當天算第1天，所以要 +1
+                (days_diff + 1) as i32
+            } else {
+                profile.adventure_days.unwrap_or(1)
+            };
+            log::info!("用戶 {} 冒險天數: {}", user_id, adventure_days);
+
+            // 處理今日進度 - 如果沒有數據就返回空值
+            let today_progress_data = if let Some(progress) = progress_list.first() {
+                log::info!("找到今日進度數據: {:?}", progress);
+                
+                // 直接使用 attributes_gained JSON Value
+                let attributes_gained = match &progress.attributes_gained {
+                    Some(json_val) => {
+                        log::info!("原始 attributes_gained 數據: {:?}", json_val);
+                        json_val.clone()
+                    }
+                    None => serde_json::json!({})
+                };
+                
+                serde_json::json!({
+                    "completedTasks": progress.completed_tasks.unwrap_or(0),
+                    "totalTasks": progress.total_tasks.unwrap_or(0),
+                    "experienceGained": progress.experience_gained.unwrap_or(0),
+                    "attributeGains": attributes_gained
+                })
+            } else {
+                log::info!("今日暫無進度數據");
+                serde_json::json!({
+                    "completedTasks": 0,
+                    "totalTasks": 0,
+                    "experienceGained": 0,
+                    "attributeGains": {}
+                })
+            };
+            
+            // 介面需要的使用者設定（時區、語系、排行榜、勿擾時段），免去額外請求
+            let settings = crate::user_settings::load_ui_settings(rb, user_id).await.map_err(|e| {
+                log::error!("獲取使用者設定失敗: {}", e);
+                HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: format!("獲取使用者設定失敗: {}", e),
+                })
+            })?;
+
+            // 組合完整的遊戲化用戶數據
+            let gamified_data = serde_json::json!({
+                "id": user.id,
+                "name": user.name,
+                "level": profile.level,
+                "experience": profile.experience,
+                "maxExperience": profile.max_experience,
+                "title": profile.title,
+                "adventureDays": adventure_days,
+                "consecutiveLoginDays": profile.consecutive_login_days,
+                "personaType": profile.persona_type,
+                "attributes": {
+                    "intelligence": attr.intelligence,
+                    "endurance": attr.endurance,
+                    "creativity": attr.creativity,
+                    "social": attr.social,
+                    "focus": attr.focus,
+                    "adaptability": attr.adaptability
+                },
+                "todayProgress": today_progress_data,
+                "settings": settings
+            });
+            crate::gamified_cache::put(user_id, cache_generation, &gamified_data);
+            
+            Ok(gamified_data)
+        }
+        (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+            log::error!("獲取遊戲化數據時發生錯誤: {}", e);
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: e,
+            }))
+        }
+    }
+}
+
+// 獲取完整的遊戲化用戶數據 (整合 API)
+pub async fn get_gamified_user_data(http_req: actix_web::HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_gamified_data(rb.get_ref(), &user_id).await {
+        Ok(gamified_data) => Ok(HttpResponse::Ok().json(crate::event_notifier::ApiResponseWithEvents {
+            success: true,
+            data: Some(gamified_data),
+            message: "獲取完整遊戲化用戶數據成功".to_string(),
+            unseen_events: crate::event_notifier::take_unseen_events(rb.get_ref(), &user_id).await,
+        })),
+        Err(response) => Ok(response),
+    }
+}
+
+// 客戶端主動回報活躍（更新連續登入天數）
+pub async fn user_heartbeat(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let today = crate::local_date::local_today_in(crate::user_settings::user_timezone(rb.get_ref(), &user_id).await);
+    match record_activity(rb.get_ref(), &user_id, today).await {
+        Ok(Some(days)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(json!({ "consecutiveLoginDays": days })),
+            message: "已記錄活躍".to_string(),
+        })),
+        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "用戶資料尚未初始化，請稍後重試".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("記錄活躍失敗: {}", e),
+        })),
+    }
+}
+
+// 週屬性相關 API
+
+// 獲取用戶指定週數的屬性快照（依使用者的週起始日偏好切分週）
+pub async fn get_weekly_attributes(
+    http_req: actix_web::HttpRequest,
+    rb: web::Data<RBatis>,  
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse> {
+    let (user_id, weeks_ago) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let week_start = crate::week_start::load(rb.get_ref(), &user_id).await.unwrap_or_else(|e| {
+        log::warn!("讀取用戶 {} 的週起始日失敗，改用週一: {}", user_id, e);
+        crate::week_start::WeekStart::default()
+    });
+
+    // 計算目標週的起訖日
+    let target_date = crate::local_date::local_today() - chrono::Duration::weeks(weeks_ago as i64);
+    let (start, end) = week_start.week_bounds(target_date);
+    let format_date = crate::week_start::format_date;
+
+    let snapshot = crate::week_start::find_snapshot(rb.get_ref(), &user_id, start).await;
+
+    match snapshot {
+        Ok(snapshot) => {
+            if let Some(snapshot) = snapshot {
+                let snapshot_start = snapshot
+                    .week_start_date
+                    .as_deref()
+                    .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                    .unwrap_or(start);
+                let mut data = json!(snapshot);
+                data["week_start_date"] = json!(format_date(snapshot_start));
+                data["week_end_date"] = json!(format_date(snapshot_start + chrono::Duration::days(6)));
+                data["week_start"] = json!(week_start);
+                Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(data),
+                    message: format!("獲取第{}週前屬性快照成功", weeks_ago),
+                }))
+            } else {
+                // 如果沒有快照，返回當前屬性作為fallback
+                match UserAttributes::select_by_map(rb.get_ref(), value!{"user_id": user_id}).await {
+                    Ok(current_attrs) => {
+                        if let Some(attrs) = current_attrs.first() {
+                            let fallback_snapshot = serde_json::json!({
+                                "intelligence": attrs.intelligence,
+                                "endurance": attrs.endurance,
+                                "creativity": attrs.creativity,
+                                "social": attrs.social,
+                                "focus": attrs.focus,
+                                "adaptability": attrs.adaptability,
+                                "week_start_date": format_date(start),
+                                "week_end_date": format_date(end),
+                                "week_start": week_start,
+                                "is_fallback": true
+                            });
+                            
+                            Ok(HttpResponse::Ok().json(ApiResponse {
+                                success: true,
+                                data: Some(fallback_snapshot),
+                                message: format!("第{}週前無快照數據，返回當前屬性", weeks_ago),
+                            }))
+                        } else {
+                            Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                                success: false,
+                                data: None,
+                                message: "用戶屬性不存在".to_string(),
+                            }))
+                        }
+                    }
+                    Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                        success: false,
+                        data: None,
+                        message: format!("獲取用戶屬性失敗: {}", e),
+                    })),
+                }
+            }
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取週屬性快照失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::json;
+
+    use crate::test_utils::{self, call_json, TEST_PASSWORD};
+
+    #[actix_web::test]
+    async fn test_register_login_and_access_protected_routes() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+
+        let user_id = test_utils::register_user(&app, "小明", "Ming@LifeUp.test", TEST_PASSWORD).await;
+
+        // 同一個 email（不分大小寫）不能重複註冊
+        let req = test::TestRequest::post()
+            .uri("/api/users")
+            .set_json(json!({"name": "小明二號", "email": "ming@lifeup.test", "password": TEST_PASSWORD}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["message"], "該email已被註冊");
+
+        // 錯誤密碼
+        let req = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({"email": "ming@lifeup.test", "password": "Wr0ngPass!"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["success"], false);
+
+        // 未帶 JWT 無法存取受保護路由
+        let req = test::TestRequest::get().uri(&format!("/api/users/{}", user_id)).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let token = test_utils::login(&app, "ming@lifeup.test", TEST_PASSWORD).await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users/{}", user_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["email"], "ming@lifeup.test");
+    }
+
+    #[actix_web::test]
+    async fn test_login_ip_throttle_ignores_forwarded_headers() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let peer: std::net::SocketAddr = "198.51.100.23:40000".parse().unwrap();
+
+        // 同一個連線位址換著 X-Forwarded-For 送錯誤密碼，仍以連線位址累計
+        let attempt = |i: usize| {
+            test::TestRequest::post()
+                .uri("/api/auth/login")
+                .peer_addr(peer)
+                .insert_header(("X-Forwarded-For", format!("203.0.113.{}", i)))
+                .set_json(json!({"email": format!("spoof{}@lifeup.test", i), "password": "Wr0ngPass!"}))
+                .to_request()
+        };
+        let max_failures = crate::config::LoginThrottleConfig::default().max_failures_per_ip as usize;
+        for i in 0..max_failures {
+            let (status, body) = call_json(&app, attempt(i)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+        }
+        let (status, body) = call_json(&app, attempt(max_failures)).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS, "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_registration_with_same_email() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+
+        let register = |name: &str| {
+            test::TestRequest::post()
+                .uri("/api/users")
+                .set_json(json!({"name": name, "email": "Race@LifeUp.test", "password": TEST_PASSWORD}))
+                .to_request()
+        };
+        let ((status_a, body_a), (status_b, body_b)) =
+            futures::join!(call_json(&app, register("racer_a")), call_json(&app, register("racer_b")));
+        let mut statuses = [status_a, status_b];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::BAD_REQUEST]);
+        let (created, rejected) = if status_a == StatusCode::CREATED { (body_a, body_b) } else { (body_b, body_a) };
+        assert_eq!(rejected["message"], "該email已被註冊");
+
+        // 只留下一位使用者，且遊戲化資料隨註冊一併建立
+        let user_id = created["data"]["id"].as_str().unwrap().to_string();
+        let count = |sql: &'static str| {
+            let rb = rb.clone();
+            let user_id = user_id.clone();
+            async move { rb.query_decode::<i64>(sql, vec![rbs::Value::String(user_id)]).await.unwrap() }
+        };
+        assert_eq!(count("SELECT COUNT(*) FROM user WHERE email = (SELECT email FROM user WHERE id = ?)").await, 1);
+        assert_eq!(count("SELECT COUNT(*) FROM user_profile WHERE user_id = ?").await, 1);
+        assert_eq!(count("SELECT COUNT(*) FROM user_attributes WHERE user_id = ?").await, 1);
+
+        // 略過快速檢查直接寫入時，由唯一索引判定重複
+        let duplicate = crate::models::User {
+            id: Some("dup-user".to_string()),
+            name: Some("racer_c".to_string()),
+            email: Some("race@lifeup.test".to_string()),
+            password_hash: Some("x".to_string()),
+            created_at: Some(chrono::Utc::now()),
+            updated_at: Some(chrono::Utc::now()),
+        };
+        assert!(matches!(super::register_user(&rb, &duplicate).await, Err(super::RegistrationError::EmailTaken)));
+        let orphan: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM user_profile WHERE user_id = 'dup-user'", vec![])
+            .await
+            .unwrap();
+        assert_eq!(orphan, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_gamified_get_is_read_only() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "gamified_read").await;
+        let other = test_utils::create_user(&app, "gamified_other").await;
+
+        let yesterday = (crate::local_date::local_today() - chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+        rb.exec(
+            "UPDATE user_profile SET consecutive_login_days = 5, last_login_date = ? WHERE user_id = ?",
+            vec![rbs::Value::String(yesterday.clone()), rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        // 兩個同時的 GET 讀到相同的連續天數，且不改動登入日期
+        let uri = format!("/api/users/{}/gamified", user.id);
+        let first = test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let second = test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let ((status_a, body_a), (status_b, body_b)) =
+            futures::join!(call_json(&app, first), call_json(&app, second));
+        assert_eq!(status_a, StatusCode::OK);
+        assert_eq!(status_b, StatusCode::OK);
+        assert_eq!(body_a["data"]["consecutiveLoginDays"], 5);
+        assert_eq!(body_b["data"]["consecutiveLoginDays"], 5);
+        let last_login: Option<String> = rb
+            .query_decode(
+                "SELECT last_login_date FROM user_profile WHERE user_id = ?",
+                vec![rbs::Value::String(user.id.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(last_login, Some(yesterday));
+
+        // heartbeat 才會更新連續天數，同一天重複呼叫不累加
+        let heartbeat = format!("/api/users/{}/heartbeat", user.id);
+        for _ in 0..2 {
+            let req = test::TestRequest::post().uri(&heartbeat).insert_header(user.auth()).to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["data"]["consecutiveLoginDays"], 6);
+        }
+
+        let req = test::TestRequest::post().uri(&heartbeat).insert_header(other.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_weekly_attributes_follow_week_start_preference() {
+        use crate::week_start::{format_date, WeekStart};
+
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "weekly").await;
+        let today = crate::local_date::local_today();
+
+        // 改為週日起算前存下的週一快照
+        let monday = WeekStart::Monday.week_start_of(today);
+        let (year, week_number) = crate::week_start::iso_key(monday);
+        rb.exec(
+            "INSERT INTO weekly_attribute_snapshot (id, user_id, week_start_date, year, week_number, intelligence)
+             VALUES ('monday-week', ?, ?, ?, ?, 77)",
+            vec![
+                rbs::Value::String(user.id.clone()),
+                rbs::Value::String(format_date(monday)),
+                rbs::Value::I32(year),
+                rbs::Value::I32(week_number),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/api/users/{}/settings", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"week_start": "sun"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["week_start"], "sun");
+
+        // 週日起算的週最多與舊快照相差一天，仍回傳舊快照並標明它實際涵蓋的日期
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users/{}/attributes/weekly/0", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let sunday = WeekStart::Sunday.week_start_of(today);
+        if (monday - sunday).num_days().abs() <= 1 {
+            assert_eq!(body["data"]["intelligence"], 77);
+            assert_eq!(body["data"]["week_start_date"], format_date(monday));
+            assert_eq!(body["data"]["week_end_date"], format_date(monday + chrono::Duration::days(6)));
+        }
+        assert_eq!(body["data"]["week_start"], "sun");
+
+        // 沒有快照時回傳目前屬性，起訖日依偏好計算
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users/{}/attributes/weekly/4", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["is_fallback"], true);
+        let (start, end) = WeekStart::Sunday.week_bounds(today - chrono::Duration::weeks(4));
+        assert_eq!(body["data"]["week_start_date"], format_date(start));
+        assert_eq!(body["data"]["week_end_date"], format_date(end));
+    }
+}
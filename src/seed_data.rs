@@ -7,6 +7,9 @@ use log::{info, error};
 use rand::Rng;
 use crate::models::TaskStatus;
 use crate::achievement_service::AchievementService;
+use crate::config::NewUserDefaultsConfig;
+use crate::new_user_defaults::create_starter_tasks;
+use crate::services::user_activity::ensure_user_rows_with;
 
 /// 插入種子資料到資料庫
 pub async fn seed_database(rb: &RBatis) -> Result<(), Box<dyn std::error::Error>> {
@@ -157,6 +160,50 @@ pub async fn seed_minimum_user_data(rb: &RBatis) -> Result<String, Box<dyn std::
     Ok(user_id)
 }
 
+/// 種子設定檔：以非預設的新帳號設定建立使用者，用來確認初始資料設定確實寫入
+pub fn seed_profile(name: &str) -> Option<NewUserDefaultsConfig> {
+    match name {
+        // 家庭版：給孩子用的帳號，屬性從較低值起步、升級門檻較低，並附帶家庭入門任務
+        "family" => Some(NewUserDefaultsConfig {
+            attribute_baseline: 30,
+            attribute_overrides: [("creativity".to_string(), 60), ("social".to_string(), 45)].into_iter().collect(),
+            level: 1,
+            max_experience: 60,
+            title: "小小探險家".to_string(),
+            starter_pack: Some("family".to_string()),
+        }),
+        _ => None,
+    }
+}
+
+/// 依種子設定檔建立一位使用者（密碼 12345678）；回傳使用者 id
+pub async fn seed_profile_user(rb: &RBatis, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let profile = seed_profile(name).ok_or_else(|| format!("未知的種子設定檔: {}", name))?;
+    let user_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let password_hash = bcrypt::hash("12345678", bcrypt::DEFAULT_COST)?;
+
+    rb.exec(
+        "INSERT INTO user (id, name, email, password_hash, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+        vec![
+            user_id.clone().into(),
+            format!("{} 種子使用者", name).into(),
+            format!("{}@lifeup.com", name).into(),
+            password_hash.into(),
+            now.clone().into(),
+            now.into(),
+        ],
+    )
+    .await?;
+    ensure_user_rows_with(rb, &user_id, &profile).await?;
+    let created = match profile.starter_pack.as_deref() {
+        Some(pack) => create_starter_tasks(rb, &user_id, pack).await?,
+        None => 0,
+    };
+    info!("種子設定檔 {} 的使用者建立成功: {}（{} 個入門任務，密碼: 12345678）", name, user_id, created);
+    Ok(user_id)
+}
+
 /// 插入測試使用者
 async fn insert_test_user(rb: &RBatis) -> Result<String, Box<dyn std::error::Error>> {
     let user_id = Uuid::new_v4().to_string();
@@ -1004,6 +1051,8 @@ async fn insert_achievements(rb: &RBatis) -> Result<(), Box<dyn std::error::Erro
         ("智慧之光", "智力屬性達到 80", "💡", "attribute", "intelligence_attribute", 80, 130),
         ("堅毅如山", "毅力屬性達到 80", "⛰️", "attribute", "endurance_attribute", 80, 100),
         ("靈活應變", "適應力屬性達到 85", "🌊", "attribute", "adaptability_attribute", 85, 115),
+        ("專注達人", "完成 20 次專注時段", "⏱️", "habit", "focus_session_complete", 20, 120),
+        ("今日三選", "完成 7 次每日三任務", "🗓️", "habit", "daily_quest_complete", 7, 150),
     ];
 
     let now = Utc::now().to_rfc3339();
@@ -1108,16 +1157,13 @@ async fn insert_weekly_attribute_snapshots(rb: &RBatis, user_id: &str) -> Result
     // 生成過去 8 週的屬性快照資料（包含本週）
     for weeks_ago in 0..8 {
         let target_date = now - Duration::weeks(weeks_ago);
-        let target_naive = target_date.naive_utc().date();
+        let target_naive = crate::local_date::local_date(target_date);
         
-        // 計算該週的週一日期（ISO 8601 標準）
-        let days_from_monday = target_naive.weekday().days_since(chrono::Weekday::Mon);
-        let week_start = target_naive - Duration::days(days_from_monday as i64);
+        // 依使用者的週起始日計算該週第一天（種子使用者為預設的週一）
+        let week_start = crate::week_start::WeekStart::default().week_start_of(target_naive);
         
-        // 計算 ISO 週數和年份
-        let iso_week = week_start.iso_week();
-        let year = iso_week.year();
-        let week_number = iso_week.week();
+        // 該週週一所在的 ISO 週數和年份
+        let (year, week_number) = crate::week_start::iso_key(week_start);
         
         let snapshot_id = Uuid::new_v4().to_string();
         let created_at = target_date.to_rfc3339();
@@ -1162,7 +1208,7 @@ async fn insert_weekly_attribute_snapshots(rb: &RBatis, user_id: &str) -> Result
             user_id.into(),
             week_start.format("%Y-%m-%d").to_string().into(),
             year.into(),
-            (week_number as i32).into(),
+            week_number.into(),
             intelligence.into(),
             endurance.into(),
             creativity.into(),
@@ -0,0 +1,209 @@
+// 成就完成統計（完成次數、完成率）
+
+use rbatis::RBatis;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::models::*;
+use rbs::{Value, value};
+
+#[derive(serde::Serialize)]
+pub struct AchievementWithStats {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub category: Option<String>,
+    pub requirement_type: Option<String>,
+    pub requirement_value: Option<i32>,
+    pub experience_reward: Option<i32>,
+    pub completion_count: i32,
+    pub total_users: i32,
+    pub completion_rate: f64,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// 成就統計相關輔助函數
+/// 成就完成次數 +1（單一 UPSERT，同時解鎖不會遺失更新）
+pub async fn increment_achievement_completion_count(rb: &RBatis, achievement_id: &str) -> rbatis::Result<()> {
+    let now = Utc::now().to_rfc3339();
+    rb.exec(
+        "INSERT INTO achievement_stats (id, achievement_id, completion_count, created_at, updated_at)
+         VALUES (?, ?, 1, ?, ?)
+         ON CONFLICT(achievement_id) DO UPDATE SET
+             completion_count = COALESCE(completion_count, 0) + 1,
+             updated_at = excluded.updated_at",
+        vec![
+            Value::String(Uuid::new_v4().to_string()),
+            Value::String(achievement_id.to_string()),
+            Value::String(now.clone()),
+            Value::String(now),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// 使用者的成就紀錄被刪除前呼叫：把該使用者已解鎖成就的完成次數各減 1（不低於 0）
+pub async fn decrement_completion_counts_for_user(rb: &RBatis, user_id: &str) -> rbatis::Result<u64> {
+    let result = rb
+        .exec(
+            "UPDATE achievement_stats
+             SET completion_count = MAX(COALESCE(completion_count, 0) - 1, 0), updated_at = ?
+             WHERE achievement_id IN (SELECT achievement_id FROM user_achievement WHERE user_id = ?)",
+            vec![Value::String(Utc::now().to_rfc3339()), Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(result.rows_affected)
+}
+
+pub async fn get_total_user_count(rb: &RBatis) -> rbatis::Result<i32> {
+    let sql = "SELECT COUNT(*) as count FROM user";
+    let result: Vec<serde_json::Value> = rb.query_decode(sql, vec![]).await?;
+
+    if let Some(row) = result.first() {
+        if let Some(count) = row.get("count").and_then(|v| v.as_i64()) {
+            return Ok(count as i32);
+        }
+    }
+
+    Ok(0)
+}
+
+pub async fn get_achievement_with_stats(rb: &RBatis, achievement_id: &str) -> rbatis::Result<Option<AchievementWithStats>> {
+    // 獲取成就基本資訊
+    let achievements = Achievement::select_by_map(rb, value!{"id": achievement_id}).await?;
+    let achievement = match achievements.first() {
+        Some(ach) => ach,
+        None => return Ok(None),
+    };
+
+    // 獲取統計資訊
+    let stats = AchievementStats::select_by_map(rb, value!{"achievement_id": achievement_id}).await?;
+    let completion_count = stats.first()
+        .and_then(|s| s.completion_count)
+        .unwrap_or(0);
+
+    // 獲取總用戶數
+    let total_users = get_total_user_count(rb).await?;
+
+    // 計算完成率
+    let completion_rate = if total_users > 0 {
+        completion_count as f64 / total_users as f64
+    } else {
+        0.0
+    };
+
+    let achievement_with_stats = AchievementWithStats {
+        id: achievement.id.as_ref().unwrap_or(&String::new()).clone(),
+        name: achievement.name.as_ref().unwrap_or(&String::new()).clone(),
+        description: achievement.description.clone(),
+        icon: achievement.icon.clone(),
+        category: achievement.category.clone(),
+        requirement_type: achievement.requirement_type.as_ref().map(|rt| rt.to_string().to_owned()),
+        requirement_value: achievement.requirement_value,
+        experience_reward: achievement.experience_reward,
+        completion_count,
+        total_users,
+        completion_rate,
+        created_at: achievement.created_at,
+    };
+
+    Ok(Some(achievement_with_stats))
+}
+
+/// 統計成就被多少用戶完成（以 user_achievement 為準）
+pub async fn count_achievement_completions(rb: &RBatis, achievement_id: &str) -> rbatis::Result<i32> {
+    let sql = "SELECT COUNT(*) as count FROM user_achievement WHERE achievement_id = ?";
+    let result: Vec<serde_json::Value> = rb.query_decode(sql, vec![Value::String(achievement_id.to_string())]).await?;
+
+    Ok(result.first()
+        .and_then(|row| row.get("count").and_then(|v| v.as_i64()))
+        .unwrap_or(0) as i32)
+}
+
+// 同步成就統計數據 - 重建所有成就的統計記錄
+pub async fn sync_achievement_stats(rb: &RBatis) -> rbatis::Result<i32> {
+    let now = Utc::now();
+    let mut synced_count = 0;
+
+    // 獲取所有成就
+    let achievements = Achievement::select_all(rb).await?;
+
+    for achievement in achievements {
+        let achievement_id = match &achievement.id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        // 統計該成就被多少用戶完成
+        let completion_count = count_achievement_completions(rb, achievement_id).await?;
+
+        // 檢查是否已存在統計記錄
+        let existing_stats = AchievementStats::select_by_map(rb, value!{"achievement_id": achievement_id}).await?;
+
+        if let Some(existing_stat) = existing_stats.first() {
+            // 更新現有記錄
+            let update_sql = "UPDATE achievement_stats SET completion_count = ?, updated_at = ? WHERE achievement_id = ?";
+            rb.exec(update_sql, vec![
+                Value::from(completion_count),
+                Value::String(now.to_rfc3339()),
+                Value::String(achievement_id.clone())
+            ]).await?;
+        } else {
+            // 創建新記錄
+            let new_stat = AchievementStats {
+                id: Some(Uuid::new_v4().to_string()),
+                achievement_id: Some(achievement_id.clone()),
+                completion_count: Some(completion_count),
+                created_at: Some(now),
+                updated_at: Some(now),
+            };
+            AchievementStats::insert(rb, &new_stat).await?;
+        }
+
+        synced_count += 1;
+        log::info!("同步成就 {} 統計數據：完成人數 {}", achievement_id, completion_count);
+    }
+
+    log::info!("成就統計數據同步完成，共處理 {} 個成就", synced_count);
+    Ok(synced_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    #[actix_web::test]
+    async fn test_concurrent_unlocks_keep_stats_consistent() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        rb.exec(
+            "INSERT INTO achievement (id, name, requirement_type, requirement_value, experience_reward) VALUES ('ach_stress', '壓力測試', 'task_complete', 1, 10)",
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        let mut users = Vec::new();
+        for i in 0..50 {
+            users.push(test_utils::create_user(&app, &format!("stress_unlock_{}", i)).await);
+        }
+
+        // 50 位使用者同時解鎖同一個成就
+        let requests = users.iter().map(|user| {
+            let req = actix_web::test::TestRequest::post()
+                .uri(&format!("/api/users/{}/achievements/ach_stress/unlock", user.id))
+                .insert_header(user.auth())
+                .to_request();
+            call_json(&app, req)
+        });
+        let results = futures::future::join_all(requests).await;
+        assert!(results.iter().all(|(status, _)| *status == 201));
+
+        let stats = AchievementStats::select_by_map(&rb, value!{"achievement_id": "ach_stress"}).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].completion_count, Some(50));
+        assert_eq!(count_achievement_completions(&rb, "ach_stress").await.unwrap(), 50);
+    }
+}
@@ -0,0 +1,91 @@
+// 使用者經驗值與等級計算
+
+use rbatis::RBatis;
+use chrono::Utc;
+use crate::models::*;
+use rbs::value;
+
+// 經驗值變化結果
+pub struct ExperienceChange {
+    pub profile: UserProfile,
+    pub previous_level: i32,
+    pub new_level: i32,
+}
+
+/// 為使用者增減經驗值並處理升降級（寫入經驗值流水、升級時發送通知）
+///
+/// 找不到使用者資料時回傳 Ok(None)
+pub async fn apply_experience_gain(
+    rb: &RBatis,
+    user_id: &str,
+    experience_gain: i32,
+) -> std::result::Result<Option<ExperienceChange>, rbatis::Error> {
+    let Some(mut profile) = UserProfile::select_by_map(rb, value!{"user_id": user_id}).await?.into_iter().next() else {
+        return Ok(None);
+    };
+
+    // 增加經驗值
+    let current_exp = profile.experience.unwrap_or(0);
+    let new_exp = current_exp + experience_gain;
+
+    // 檢查升級或降級
+    let current_level = profile.level.unwrap_or(1);
+    let max_exp = profile.max_experience.unwrap_or(100);
+    let mut final_exp = new_exp;
+    let mut final_level = current_level;
+    let mut new_max_exp = max_exp;
+
+    // 升級邏輯：經驗值超過最大值時升級
+    while final_exp >= new_max_exp && final_level > 0 {
+        final_exp -= new_max_exp;
+        final_level += 1;
+        // 每升一級，下一級所需經驗值增加 10%
+        new_max_exp = (new_max_exp as f64 * 1.1) as i32;
+    }
+
+    // 降級邏輯：經驗值為負數時降級
+    while final_exp < 0 && final_level > 1 {
+        final_level -= 1;
+        // 計算上一級的最大經驗值（反向計算）
+        new_max_exp = (new_max_exp as f64 / 1.1) as i32;
+        final_exp += new_max_exp;
+    }
+
+    // 如果等級已經是1且經驗值仍為負，將經驗值設為0
+    if final_level <= 1 && final_exp < 0 {
+        final_level = 1;
+        final_exp = 0;
+        new_max_exp = 100; // 重置為初始最大經驗值
+    }
+
+    profile.experience = Some(final_exp);
+    profile.level = Some(final_level);
+    profile.max_experience = Some(new_max_exp);
+    profile.updated_at = Some(Utc::now());
+
+    // 更新資料庫（金幣另外以原子更新累加，避免覆蓋同時進行的兌換扣款）
+    let coins_before = profile.coins.take().unwrap_or(0);
+    UserProfile::update_by_map(rb, &profile, value!{"user_id": user_id}).await?;
+    crate::reward_shop::credit_coins(rb, user_id, experience_gain).await?;
+    profile.coins = Some((coins_before + experience_gain).max(0));
+    crate::gamified_cache::invalidate(user_id);
+
+    // 記錄經驗值流水（排行榜使用）
+    if let Err(e) = crate::leaderboard::record_experience_gain(rb, user_id, experience_gain).await {
+        log::warn!("記錄經驗值流水失敗: {}", e);
+    }
+
+    if final_level > current_level {
+        let rb_clone = rb.clone();
+        let user_id_clone = user_id.to_string();
+        tokio::spawn(async move {
+            crate::event_notifier::notify_level_up(&rb_clone, &user_id_clone, current_level, final_level).await;
+        });
+    }
+
+    Ok(Some(ExperienceChange {
+        profile,
+        previous_level: current_level,
+        new_level: final_level,
+    }))
+}
@@ -0,0 +1,15 @@
+// 服務層：多個路由模組共用的回應格式與業務邏輯
+
+pub mod achievement_stats;
+pub mod experience;
+pub mod task_hierarchy;
+pub mod user_activity;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub message: String,
+}
@@ -0,0 +1,179 @@
+// 父子任務：依子任務狀態更新父任務的狀態與經驗值
+
+use rbatis::executor::Executor;
+use rbatis::RBatis;
+use crate::models::*;
+use rbs::{Value, value};
+
+// 檢查並更新父任務狀態
+pub async fn check_and_update_parent_task_status(rb: &RBatis, parent_task_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("檢查父任務 {} 的狀態", parent_task_id);
+
+    // 先獲取父任務資訊
+    let parent_tasks = Task::select_by_map(rb, value!{"id": parent_task_id}).await?;
+    let parent_task = match parent_tasks.first() {
+        Some(task) => task,
+        None => {
+            log::warn!("找不到父任務: {}", parent_task_id);
+            return Ok(());
+        }
+    };
+
+    // 判斷是否為重複性任務；重複性任務的「今日」以擁有者的時區為準
+    let is_recurring = parent_task.is_recurring.unwrap_or(0) == 1;
+    let tz = crate::recurring_progress::owner_timezone(rb, parent_task).await;
+
+    // 已期滿的重複性任務由 recurring_progress 結算，不再依今日子任務改寫狀態
+    if is_recurring && crate::recurring_progress::has_ended(parent_task, tz) {
+        log::info!("重複性父任務 {} 已過結束日期，略過狀態推導", parent_task_id);
+        return Ok(());
+    }
+
+    // 查詢子任務
+    let all_subtasks = Task::select_by_map(rb, value!{"parent_task_id": parent_task_id}).await?;
+
+    if all_subtasks.is_empty() {
+        log::info!("父任務 {} 沒有子任務", parent_task_id);
+        return Ok(());
+    }
+
+    // 根據任務類型過濾相關子任務
+    let relevant_subtasks: Vec<&Task> = if is_recurring {
+        // 重複性任務：只看今日的子任務
+        let today = crate::local_date::local_today_string_in(tz);
+        all_subtasks.iter()
+            .filter(|task| {
+                task.task_date.as_ref().map(|d| d == &today).unwrap_or(false)
+            })
+            .collect()
+    } else {
+        // 普通任務：看所有子任務
+        all_subtasks.iter().collect()
+    };
+
+    if relevant_subtasks.is_empty() {
+        log::info!("父任務 {} 沒有相關的子任務（今日：{}）", parent_task_id, is_recurring);
+        // 如果沒有相關子任務，父任務應該是 pending 狀態
+        update_parent_task_status(rb, parent_task_id, 0).await?;
+        return Ok(());
+    }
+
+    // 統計子任務狀態
+    let total_subtasks = relevant_subtasks.len();
+    let completed_subtasks = relevant_subtasks.iter()
+        .filter(|task| {
+            if is_recurring {
+                // 重複性任務：daily_completed 或 completed 都算完成
+                task.status == Some(6) || task.status == Some(2)
+            } else {
+                // 普通任務：只有 completed 算完成
+                task.status == Some(2)
+            }
+        })
+        .count();
+
+    let in_progress_subtasks = relevant_subtasks.iter()
+        .filter(|task| {
+            if is_recurring {
+                // 重複性任務：daily_in_progress 或 in_progress 都算進行中
+                task.status == Some(5) || task.status == Some(1)
+            } else {
+                // 普通任務：只有 in_progress 算進行中
+                task.status == Some(1)
+            }
+        })
+        .count();
+
+    // 統計 pending 狀態的子任務
+    let pending_subtasks = relevant_subtasks.iter()
+        .filter(|task| task.status == Some(0)) // pending
+        .count();
+
+    log::info!("父任務 {} (重複性: {}) 有 {} 個相關子任務，其中 {} 個已完成，{} 個進行中，{} 個待處理",
+               parent_task_id, is_recurring, total_subtasks, completed_subtasks, in_progress_subtasks, pending_subtasks);
+
+    // 根據子任務狀態推導父任務狀態
+    let new_parent_status = if completed_subtasks == total_subtasks {
+        // 所有子任務完成 → 父任務完成
+        2 // completed
+    } else if pending_subtasks == total_subtasks {
+        // 所有子任務都是 pending → 父任務 pending
+        0 // pending
+    } else {
+        // 其他情況（有任何子任務非 pending 狀態）→ 父任務進行中
+        // 包括：有 in_progress、有 completed 但未全部完成、混合狀態等
+        1 // in_progress
+    };
+
+    // 檢查是否需要更新父任務狀態
+    if parent_task.status != Some(new_parent_status) {
+        log::info!("父任務 {} 狀態需要更新: {} → {}",
+                   parent_task_id,
+                   parent_task.status.unwrap_or(-1),
+                   new_parent_status);
+        update_parent_task_status(rb, parent_task_id, new_parent_status).await?;
+    } else {
+        log::info!("父任務 {} 狀態無需更新，保持: {}", parent_task_id, new_parent_status);
+    }
+
+    Ok(())
+}
+
+// 輔助函數：更新父任務狀態
+async fn update_parent_task_status(rb: &RBatis, parent_task_id: &str, new_status: i32) -> Result<(), Box<dyn std::error::Error>> {
+    let update_sql = "UPDATE task SET status = ?, updated_at = ? WHERE id = ?";
+    rb.exec(
+        update_sql,
+        vec![
+            Value::I32(new_status),
+            Value::String(chrono::Utc::now().to_rfc3339()),
+            Value::String(parent_task_id.to_string()),
+        ],
+    ).await?;
+
+    let status_name = match new_status {
+        0 => "pending",
+        1 => "in_progress",
+        2 => "completed",
+        _ => "unknown",
+    };
+    log::info!("父任務 {} 狀態更新為: {}", parent_task_id, status_name);
+    Ok(())
+}
+
+/// 父任務經驗值 = 所有子任務經驗值總和
+pub fn subtask_experience_total(subtasks: &[crate::models::Task]) -> i32 {
+    subtasks.iter()
+        .map(|subtask| subtask.experience.unwrap_or(0))
+        .sum()
+}
+
+// 輔助函數：更新父任務經驗值為所有子任務經驗值總和
+pub async fn update_parent_task_experience(rb: &dyn Executor, parent_task_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // 查詢所有子任務
+    let subtasks = crate::models::Task::select_by_map(rb, value!{"parent_task_id": parent_task_id}).await?;
+
+    if subtasks.is_empty() {
+        log::info!("父任務 {} 沒有子任務，保持原有經驗值", parent_task_id);
+        return Ok(());
+    }
+
+    // 計算所有子任務的經驗值總和（含已壓縮成每月彙總的子任務）
+    let total_experience = subtask_experience_total(&subtasks)
+        + crate::daily_compaction::archived_experience(rb, parent_task_id).await?;
+
+    // 更新父任務的經驗值
+    let update_sql = "UPDATE task SET experience = ?, updated_at = ? WHERE id = ?";
+    rb.exec(
+        update_sql,
+        vec![
+            Value::I32(total_experience),
+            Value::String(chrono::Utc::now().to_rfc3339()),
+            Value::String(parent_task_id.to_string()),
+        ],
+    ).await?;
+
+    log::info!("父任務 {} 經驗值已更新為子任務總和: {} (共 {} 個子任務)",
+               parent_task_id, total_experience, subtasks.len());
+    Ok(())
+}
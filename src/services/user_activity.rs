@@ -0,0 +1,162 @@
+// 使用者遊戲化資料初始化與活躍紀錄（連續登入天數）
+//
+// 遊戲化資料在註冊時建立；連續登入天數只在登入或 heartbeat 時更新，讀取 API 不寫入。
+
+use rbatis::executor::Executor;
+use rbatis::RBatis;
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::config::NewUserDefaultsConfig;
+
+/// 為使用者建立缺少的 user_profile / user_attributes（已存在則不變）；可在交易中執行
+pub async fn ensure_user_rows(rb: &dyn Executor, user_id: &str) -> std::result::Result<(), rbatis::Error> {
+    ensure_user_rows_with(rb, user_id, crate::new_user_defaults::current()).await
+}
+
+/// 同 ensure_user_rows，但使用指定的初始資料設定
+pub async fn ensure_user_rows_with(
+    rb: &dyn Executor,
+    user_id: &str,
+    defaults: &NewUserDefaultsConfig,
+) -> std::result::Result<(), rbatis::Error> {
+    let now = Utc::now().to_rfc3339();
+    rb.exec(
+        "INSERT INTO user_profile (
+             id, user_id, level, experience, max_experience, title,
+             adventure_days, consecutive_login_days, persona_type, created_at, updated_at
+         )
+         SELECT ?, ?, ?, 0, ?, ?, 1, 1, 'internal', ?, ?
+         WHERE NOT EXISTS (SELECT 1 FROM user_profile WHERE user_id = ?)",
+        vec![
+            rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::I32(defaults.level),
+            rbs::Value::I32(defaults.max_experience),
+            rbs::Value::String(defaults.title.clone()),
+            rbs::Value::String(now.clone()),
+            rbs::Value::String(now.clone()),
+            rbs::Value::String(user_id.to_string()),
+        ],
+    )
+    .await?;
+    rb.exec(
+        "INSERT INTO user_attributes (
+             id, user_id, intelligence, endurance, creativity, social, focus, adaptability, created_at, updated_at
+         )
+         SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
+         WHERE NOT EXISTS (SELECT 1 FROM user_attributes WHERE user_id = ?)",
+        vec![
+            rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::I32(defaults.attribute("intelligence")),
+            rbs::Value::I32(defaults.attribute("endurance")),
+            rbs::Value::I32(defaults.attribute("creativity")),
+            rbs::Value::I32(defaults.attribute("social")),
+            rbs::Value::I32(defaults.attribute("focus")),
+            rbs::Value::I32(defaults.attribute("adaptability")),
+            rbs::Value::String(now.clone()),
+            rbs::Value::String(now),
+            rbs::Value::String(user_id.to_string()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// 為所有缺少遊戲化資料的既有使用者補齊（啟動時的資料遷移）；回傳補齊的使用者數
+pub async fn backfill_missing_rows(rb: &RBatis) -> std::result::Result<usize, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT id FROM user
+             WHERE id NOT IN (SELECT user_id FROM user_profile WHERE user_id IS NOT NULL)
+                OR id NOT IN (SELECT user_id FROM user_attributes WHERE user_id IS NOT NULL)",
+            vec![],
+        )
+        .await?;
+    let user_ids: Vec<&str> = rows.iter().filter_map(|row| row["id"].as_str()).collect();
+    for user_id in &user_ids {
+        ensure_user_rows(rb, user_id).await?;
+    }
+    Ok(user_ids.len())
+}
+
+/// 記錄使用者今天有活動並更新連續登入天數；回傳更新後的天數（找不到資料時為 None）
+///
+/// 以單一條件式 UPDATE 依上次登入日期計算，同時發生的請求不會重複累加
+pub async fn record_activity(
+    rb: &RBatis,
+    user_id: &str,
+    today: NaiveDate,
+) -> std::result::Result<Option<i32>, rbatis::Error> {
+    let today_str = today.format("%Y-%m-%d").to_string();
+    let yesterday_str = (today - Duration::days(1)).format("%Y-%m-%d").to_string();
+    // 以 last_login_date 作為條件：今天已記錄過的列不會再被更新
+    let updated = rb.exec(
+        "UPDATE user_profile SET
+             consecutive_login_days = CASE
+                 WHEN last_login_date = ? THEN COALESCE(consecutive_login_days, 0) + 1
+                 ELSE 1
+             END,
+             last_login_date = ?,
+             updated_at = ?
+         WHERE user_id = ? AND (last_login_date IS NULL OR last_login_date <> ?)",
+        vec![
+            rbs::Value::String(yesterday_str),
+            rbs::Value::String(today_str.clone()),
+            rbs::Value::String(Utc::now().to_rfc3339()),
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::String(today_str),
+        ],
+    )
+    .await?;
+    if updated.rows_affected > 0 {
+        crate::gamified_cache::invalidate(user_id);
+    }
+
+    let days: Option<i32> = rb
+        .query_decode(
+            "SELECT consecutive_login_days FROM user_profile WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[actix_web::test]
+    async fn test_record_activity_streak() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "activity_streak").await;
+        let day = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+
+        assert_eq!(record_activity(&rb, &user.id, day).await.unwrap(), Some(1));
+        // 同一天重複記錄不累加
+        assert_eq!(record_activity(&rb, &user.id, day).await.unwrap(), Some(1));
+        assert_eq!(record_activity(&rb, &user.id, day + Duration::days(1)).await.unwrap(), Some(2));
+        // 中斷一天後重置
+        assert_eq!(record_activity(&rb, &user.id, day + Duration::days(3)).await.unwrap(), Some(1));
+    }
+
+    #[actix_web::test]
+    async fn test_backfill_missing_rows() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "activity_backfill").await;
+        rb.exec("DELETE FROM user_profile WHERE user_id = ?", vec![rbs::Value::String(user.id.clone())])
+            .await
+            .unwrap();
+
+        assert_eq!(backfill_missing_rows(&rb).await.unwrap(), 1);
+        assert_eq!(backfill_missing_rows(&rb).await.unwrap(), 0);
+        let count: i64 = rb
+            .query_decode("SELECT COUNT(*) AS count FROM user_profile WHERE user_id = ?", vec![rbs::Value::String(user.id)])
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,349 @@
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, Utc};
+use rbatis::RBatis;
+use serde::Deserialize;
+
+use crate::ai_tasks::ApiResponse;
+use crate::auth::Claims;
+use crate::models::UserSession;
+
+// last_seen_at 的最短更新間隔，避免每個請求都寫入資料庫
+const LAST_SEEN_THROTTLE_SECS: i64 = 300;
+
+/// token 的登入狀態檢查結果
+#[derive(Debug, PartialEq)]
+pub enum SessionCheck {
+    Valid,
+    // 該裝置已被登出或 session 不存在
+    Revoked,
+    // 使用者已登出所有裝置，token 版本過期
+    VersionMismatch,
+    UserNotFound,
+}
+
+impl SessionCheck {
+    pub fn message(&self) -> &'static str {
+        match self {
+            SessionCheck::Valid => "登入狀態有效",
+            SessionCheck::Revoked => "此裝置已被登出，請重新登入",
+            SessionCheck::VersionMismatch => "已登出所有裝置，請重新登入",
+            SessionCheck::UserNotFound => "用戶不存在",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionStateRow {
+    token_version: Option<i32>,
+    session_id: Option<String>,
+    revoked_at: Option<String>,
+}
+
+/// 由 User-Agent 推測裝置名稱，例如「Chrome on Android」
+pub fn describe_device(user_agent: &str) -> String {
+    let ua = user_agent.to_lowercase();
+    let os = if ua.contains("iphone") {
+        "iPhone"
+    } else if ua.contains("ipad") {
+        "iPad"
+    } else if ua.contains("android") {
+        "Android"
+    } else if ua.contains("windows") {
+        "Windows"
+    } else if ua.contains("mac os x") || ua.contains("macintosh") {
+        "macOS"
+    } else if ua.contains("linux") {
+        "Linux"
+    } else {
+        "未知系統"
+    };
+    // Edge 與 Chrome 的 UA 都含 Chrome/、Chrome 的 UA 也含 Safari/，需依序判斷
+    let browser = if ua.contains("edg/") {
+        "Edge"
+    } else if ua.contains("firefox/") || ua.contains("fxios/") {
+        "Firefox"
+    } else if ua.contains("chrome/") || ua.contains("crios/") {
+        "Chrome"
+    } else if ua.contains("safari/") {
+        "Safari"
+    } else if ua.is_empty() {
+        "未知瀏覽器"
+    } else {
+        "App"
+    };
+    format!("{} on {}", browser, os)
+}
+
+/// 登入時建立 session，回傳 session id 與使用者目前的 token 版本
+pub async fn create_session(
+    rb: &RBatis,
+    user_id: &str,
+    user_agent: Option<&str>,
+    ip_address: Option<&str>,
+) -> Result<(String, i32), rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT COALESCE(token_version, 0) as token_version FROM user WHERE id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let token_version = rows
+        .first()
+        .and_then(|row| row.get("token_version"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0) as i32;
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let user_agent = user_agent.unwrap_or_default();
+    rb.exec(
+        "INSERT INTO user_session (id, user_id, device_name, user_agent, ip_address, created_at, last_seen_at, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        vec![
+            rbs::Value::String(session_id.clone()),
+            rbs::Value::String(user_id.to_string()),
+            rbs::Value::String(describe_device(user_agent)),
+            rbs::Value::String(user_agent.to_string()),
+            ip_address.map(|ip| rbs::Value::String(ip.to_string())).unwrap_or(rbs::Value::Null),
+            rbs::Value::String(now.to_rfc3339()),
+            rbs::Value::String(now.to_rfc3339()),
+            rbs::Value::String((now + Duration::hours(crate::auth::JWT_EXPIRATION_HOURS)).to_rfc3339()),
+        ],
+    )
+    .await?;
+    Ok((session_id, token_version))
+}
+
+/// 檢查 token 是否仍有效（供 JwtAuth 中間件使用），並更新裝置最後活動時間
+pub async fn validate_session(rb: &RBatis, claims: &Claims) -> Result<SessionCheck, rbatis::Error> {
+    let row: Option<SessionStateRow> = rb
+        .query_decode(
+            "SELECT COALESCE(u.token_version, 0) as token_version, s.id as session_id, s.revoked_at as revoked_at
+             FROM user u LEFT JOIN user_session s ON s.id = ? AND s.user_id = u.id
+             WHERE u.id = ?",
+            vec![
+                claims.sid.clone().map(rbs::Value::String).unwrap_or(rbs::Value::Null),
+                rbs::Value::String(claims.sub.clone()),
+            ],
+        )
+        .await?;
+
+    let Some(row) = row else {
+        return Ok(SessionCheck::UserNotFound);
+    };
+    if row.token_version.unwrap_or(0) != claims.ver {
+        return Ok(SessionCheck::VersionMismatch);
+    }
+    // 舊 token 沒有 sid，只以版本號判斷
+    let Some(session_id) = &claims.sid else {
+        return Ok(SessionCheck::Valid);
+    };
+    if row.session_id.is_none() || row.revoked_at.is_some() {
+        return Ok(SessionCheck::Revoked);
+    }
+
+    let now = Utc::now();
+    rb.exec(
+        "UPDATE user_session SET last_seen_at = ? WHERE id = ? AND (last_seen_at IS NULL OR last_seen_at < ?)",
+        vec![
+            rbs::Value::String(now.to_rfc3339()),
+            rbs::Value::String(session_id.clone()),
+            rbs::Value::String((now - Duration::seconds(LAST_SEEN_THROTTLE_SECS)).to_rfc3339()),
+        ],
+    )
+    .await?;
+    Ok(SessionCheck::Valid)
+}
+
+/// 登出單一裝置；回傳是否有 session 被撤銷
+pub async fn revoke_session(rb: &RBatis, user_id: &str, session_id: &str) -> Result<bool, rbatis::Error> {
+    let result = rb
+        .exec(
+            "UPDATE user_session SET revoked_at = ? WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+            vec![
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(session_id.to_string()),
+                rbs::Value::String(user_id.to_string()),
+            ],
+        )
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// 登出所有裝置：遞增 token 版本讓所有已簽發的 token 失效；回傳撤銷的 session 數
+pub async fn revoke_all_sessions(rb: &RBatis, user_id: &str) -> Result<u64, rbatis::Error> {
+    rb.exec(
+        "UPDATE user SET token_version = COALESCE(token_version, 0) + 1 WHERE id = ?",
+        vec![rbs::Value::String(user_id.to_string())],
+    )
+    .await?;
+    let result = rb
+        .exec(
+            "UPDATE user_session SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+            vec![rbs::Value::String(Utc::now().to_rfc3339()), rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(result.rows_affected)
+}
+
+fn unauthenticated() -> HttpResponse {
+    HttpResponse::Unauthorized().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "需要 JWT 認證".to_string(),
+    })
+}
+
+/// 列出使用者目前有效的登入裝置
+pub async fn list_sessions(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthenticated());
+    };
+    let current_sid = http_req.extensions().get::<Claims>().and_then(|c| c.sid.clone());
+
+    let sessions: Result<Vec<UserSession>, _> = rb
+        .query_decode(
+            "SELECT * FROM user_session WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ? ORDER BY last_seen_at DESC",
+            vec![rbs::Value::String(user_id), rbs::Value::String(Utc::now().to_rfc3339())],
+        )
+        .await;
+
+    match sessions {
+        Ok(sessions) => {
+            let data: Vec<serde_json::Value> = sessions
+                .into_iter()
+                .map(|session| {
+                    let current = session.id.is_some() && session.id == current_sid;
+                    let mut value = serde_json::to_value(session).unwrap_or_default();
+                    if let Some(obj) = value.as_object_mut() {
+                        obj.remove("user_id");
+                        obj.insert("current".to_string(), serde_json::Value::Bool(current));
+                    }
+                    value
+                })
+                .collect();
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(data),
+                message: "獲取登入裝置成功".to_string(),
+            }))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取登入裝置失敗: {}", e),
+        })),
+    }
+}
+
+/// 登出指定裝置
+pub async fn delete_session(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthenticated());
+    };
+    let session_id = path.into_inner();
+
+    match revoke_session(rb.get_ref(), &user_id, &session_id).await {
+        Ok(true) => {
+            log::info!("用戶 {} 已登出裝置 {}", user_id, session_id);
+            Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+                success: true,
+                data: None,
+                message: "已登出該裝置".to_string(),
+            }))
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "找不到該登入裝置".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("登出裝置失敗: {}", e),
+        })),
+    }
+}
+
+/// 登出所有裝置（包含目前的裝置）
+pub async fn revoke_all(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let Some(user_id) = crate::auth::current_user_id(&http_req) else {
+        return Ok(unauthenticated());
+    };
+
+    match revoke_all_sessions(rb.get_ref(), &user_id).await {
+        Ok(revoked) => {
+            log::info!("用戶 {} 已登出所有裝置（{} 個 session）", user_id, revoked);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({ "revoked_sessions": revoked })),
+                message: "已登出所有裝置，請重新登入".to_string(),
+            }))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("登出所有裝置失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_device() {
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+        let edge = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Safari/537.36 Edg/120.0";
+        let android = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Mobile Safari/537.36";
+        assert_eq!(describe_device(iphone), "Safari on iPhone");
+        assert_eq!(describe_device(edge), "Edge on Windows");
+        assert_eq!(describe_device(android), "Chrome on Android");
+        assert_eq!(describe_device(""), "未知瀏覽器 on 未知系統");
+    }
+
+    #[tokio::test]
+    async fn test_revoked_tokens_are_rejected() {
+        let path = std::env::temp_dir().join(format!("lifeup_sessions_{}.db", uuid::Uuid::new_v4()));
+        let rb = RBatis::new();
+        rb.init(rbdc_sqlite::driver::SqliteDriver {}, &format!("sqlite://{}", path.display())).unwrap();
+        crate::create_tables(&rb).await;
+        crate::migrate_database(&rb).await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+
+        let claims_for = |sid: &str, ver: i32| Claims {
+            sub: "u1".to_string(),
+            email: "u1@lifeup.com".to_string(),
+            exp: 0,
+            iat: 0,
+            sid: Some(sid.to_string()),
+            ver,
+            act: None,
+            read_only: false,
+        };
+
+        let (phone, ver) = create_session(&rb, "u1", Some("iPhone Safari/604.1"), None).await.unwrap();
+        let (laptop, _) = create_session(&rb, "u1", None, Some("127.0.0.1")).await.unwrap();
+        assert_eq!(validate_session(&rb, &claims_for(&phone, ver)).await.unwrap(), SessionCheck::Valid);
+
+        // 登出單一裝置只影響該裝置
+        assert!(revoke_session(&rb, "u1", &phone).await.unwrap());
+        assert_eq!(validate_session(&rb, &claims_for(&phone, ver)).await.unwrap(), SessionCheck::Revoked);
+        assert_eq!(validate_session(&rb, &claims_for(&laptop, ver)).await.unwrap(), SessionCheck::Valid);
+
+        // 登出所有裝置後，舊版本的 token 全部失效
+        assert_eq!(revoke_all_sessions(&rb, "u1").await.unwrap(), 1);
+        assert_eq!(validate_session(&rb, &claims_for(&laptop, ver)).await.unwrap(), SessionCheck::VersionMismatch);
+        let (_, new_ver) = create_session(&rb, "u1", None, None).await.unwrap();
+        assert_eq!(new_ver, ver + 1);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+use serde::Deserialize;
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Task, TaskParticipant, TaskStatus};
+
+pub const COMPLETION_MODE_ANY: &str = "any";
+pub const COMPLETION_MODE_ALL: &str = "all";
+
+#[derive(Deserialize)]
+pub struct AddParticipantRequest {
+    pub user_id: String,
+}
+
+/// 共享任務完成判定結果
+#[derive(Debug, PartialEq)]
+pub enum SharedCompletion {
+    // 非共享任務，照一般流程處理
+    NotShared,
+    // 達成完成條件；members 為需要另外發放經驗值的參與者（不含本次操作者）
+    Completed { members: Vec<String> },
+    // all 模式下尚有參與者未完成
+    Waiting { remaining: usize },
+}
+
+pub fn is_valid_completion_mode(mode: &str) -> bool {
+    mode == COMPLETION_MODE_ANY || mode == COMPLETION_MODE_ALL
+}
+
+/// 依完成模式判定是否達成完成條件
+pub fn evaluate_completion(mode: &str, members: &HashSet<String>, completed: &HashSet<String>) -> Option<usize> {
+    if mode == COMPLETION_MODE_ALL {
+        let remaining = members.difference(completed).count();
+        if remaining > 0 {
+            return Some(remaining);
+        }
+    }
+    None
+}
+
+/// 取得共享任務的根任務 id（子任務沿用父任務的參與者與完成模式）
+fn root_task_id(task: &Task) -> Option<String> {
+    task.parent_task_id.clone().or_else(|| task.id.clone())
+}
+
+/// 取得任務的參與者（不含擁有者）
+pub async fn participant_ids(rb: &RBatis, task_id: &str) -> Result<Vec<String>, rbatis::Error> {
+    let rows = TaskParticipant::select_by_map(rb, value!{"task_id": task_id}).await?;
+    Ok(rows.into_iter().filter_map(|p| p.user_id).collect())
+}
+
+/// 使用者可見的共享任務 id（作為參與者加入的根任務）
+pub async fn shared_task_ids_for_user(rb: &RBatis, user_id: &str) -> Result<Vec<String>, rbatis::Error> {
+    let rows = TaskParticipant::select_by_map(rb, value!{"user_id": user_id}).await?;
+    Ok(rows.into_iter().filter_map(|p| p.task_id).collect())
+}
+
+/// 使用者是否可存取任務：任務擁有者或共享任務的參與者
+pub async fn can_access_task(rb: &RBatis, task: &Task, user_id: &str) -> bool {
+    if task.user_id.as_deref() == Some(user_id) {
+        return true;
+    }
+    let Some(root_id) = root_task_id(task) else {
+        return false;
+    };
+    match participant_ids(rb, &root_id).await {
+        Ok(ids) => ids.iter().any(|id| id == user_id),
+        Err(e) => {
+            log::warn!("查詢任務參與者失敗: {}", e);
+            false
+        }
+    }
+}
+
+/// 處理共享任務的完成：記錄操作者的完成並依 any/all 判定任務是否完成
+pub async fn resolve_completion(
+    rb: &RBatis,
+    task: &Task,
+    acting_user_id: &str,
+) -> Result<SharedCompletion, rbatis::Error> {
+    let Some(root_id) = root_task_id(task) else {
+        return Ok(SharedCompletion::NotShared);
+    };
+    let participants = participant_ids(rb, &root_id).await?;
+    if participants.is_empty() {
+        return Ok(SharedCompletion::NotShared);
+    }
+
+    let root = if task.id.as_deref() == Some(root_id.as_str()) {
+        Some(task.clone())
+    } else {
+        Task::select_by_map(rb, value!{"id": &root_id}).await?.into_iter().next()
+    };
+    let owner_id = root.as_ref().and_then(|t| t.user_id.clone()).unwrap_or_default();
+    let mode = root
+        .and_then(|t| t.completion_mode)
+        .unwrap_or_else(|| COMPLETION_MODE_ANY.to_string());
+
+    let mut members: HashSet<String> = participants.into_iter().collect();
+    members.insert(owner_id);
+
+    let task_id = task.id.clone().unwrap_or_default();
+    rb.exec(
+        "INSERT OR IGNORE INTO task_completion (id, task_id, user_id, completed_at) VALUES (?, ?, ?, ?)",
+        vec![
+            rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+            rbs::Value::String(task_id.clone()),
+            rbs::Value::String(acting_user_id.to_string()),
+            rbs::Value::String(Utc::now().to_rfc3339()),
+        ],
+    )
+    .await?;
+
+    let completed_rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT user_id FROM task_completion WHERE task_id = ?",
+            vec![rbs::Value::String(task_id)],
+        )
+        .await?;
+    let completed: HashSet<String> = completed_rows
+        .iter()
+        .filter_map(|r| r.get("user_id").and_then(|v| v.as_str()).map(|s| s.to_string()))
+        .collect();
+
+    if let Some(remaining) = evaluate_completion(&mode, &members, &completed) {
+        return Ok(SharedCompletion::Waiting { remaining });
+    }
+
+    members.remove(acting_user_id);
+    Ok(SharedCompletion::Completed { members: members.into_iter().collect() })
+}
+
+/// 發放共享任務經驗值給其他參與者（依各自的等級曲線計算）
+pub async fn award_members(rb: &RBatis, members: &[String], difficulty: Option<i32>, experience: i32) {
+    for member in members {
+        // 各參與者依自己的難度校準設定調整經驗值
+        let experience = crate::difficulty_calibration::adjusted_experience(rb, member, difficulty, experience).await;
+        match crate::services::experience::apply_experience_gain(rb, member, experience).await {
+            Ok(_) => log::info!("共享任務經驗值已發放給 {}: +{}", member, experience),
+            Err(e) => log::error!("共享任務經驗值發放失敗 ({}): {}", member, e),
+        }
+    }
+}
+
+/// 取得任務參與者
+pub async fn get_task_participants(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        return Ok(e.into_response());
+    }
+    match TaskParticipant::select_by_map(rb.get_ref(), value!{"task_id": &task_id}).await {
+        Ok(participants) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(participants),
+            message: "獲取任務參與者成功".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("獲取任務參與者失敗: {}", e),
+        })),
+    }
+}
+
+/// 新增任務參與者（僅任務擁有者，且對象須為已接受的好友）
+pub async fn add_task_participant(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<AddParticipantRequest>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    let task = match crate::ownership::assert_task_owner(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if task.parent_task_id.is_some() {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "任務不存在或不是父任務".to_string(),
+        }));
+    }
+
+    let owner_id = task.user_id.clone().unwrap_or_default();
+    if req.user_id == owner_id {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "擁有者已是任務成員".to_string(),
+        }));
+    }
+    match crate::friends::is_accepted_friend(rb.get_ref(), &owner_id, &req.user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: "只能與已接受的好友共享任務".to_string(),
+            }));
+        }
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢好友關係失敗: {}", e),
+            }));
+        }
+    }
+
+    // 第一次共享時若未指定完成模式，預設為任一參與者完成
+    if task.completion_mode.is_none() {
+        let _ = rb
+            .exec(
+                "UPDATE task SET completion_mode = ? WHERE id = ?",
+                vec![rbs::Value::String(COMPLETION_MODE_ANY.to_string()), rbs::Value::String(task_id.clone())],
+            )
+            .await;
+    }
+
+    let participant = TaskParticipant {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        task_id: Some(task_id),
+        user_id: Some(req.user_id.clone()),
+        created_at: Some(Utc::now()),
+    };
+    match TaskParticipant::insert(rb.get_ref(), &participant).await {
+        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(participant),
+            message: "已新增任務參與者".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("新增任務參與者失敗（可能已是參與者）: {}", e),
+        })),
+    }
+}
+
+/// 移除任務參與者（擁有者移除他人，或參與者自行退出）；任務本身保留給擁有者
+pub async fn remove_task_participant(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (task_id, user_id) = path.into_inner();
+
+    // 擁有者或參與者才能看到任務；參與者只能移除自己
+    let caller = crate::auth::current_user_id(&http_req);
+    let task = match crate::ownership::assert_task_access(rb.get_ref(), &task_id, caller.as_deref()).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if caller.as_deref() != Some(user_id.as_str()) && task.user_id != caller {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "沒有權限移除該參與者".to_string(),
+        }));
+    }
+
+    match TaskParticipant::delete_by_map(rb.get_ref(), value!{"task_id": &task_id, "user_id": &user_id}).await {
+        Ok(result) if result.rows_affected > 0 => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            data: None,
+            message: "已移除任務參與者".to_string(),
+        })),
+        Ok(_) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "該使用者不是任務參與者".to_string(),
+        })),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("移除任務參與者失敗: {}", e),
+        })),
+    }
+}
+
+/// 是否為完成狀態
+pub fn is_completed_status(status: Option<i32>) -> bool {
+    status == Some(TaskStatus::Completed.to_i32()) || status == Some(TaskStatus::DailyCompleted.to_i32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_any_mode_completes_with_one_member() {
+        assert_eq!(evaluate_completion(COMPLETION_MODE_ANY, &set(&["a", "b"]), &set(&["b"])), None);
+    }
+
+    #[test]
+    fn test_all_mode_waits_for_everyone() {
+        assert_eq!(evaluate_completion(COMPLETION_MODE_ALL, &set(&["a", "b", "c"]), &set(&["a"])), Some(2));
+        assert_eq!(evaluate_completion(COMPLETION_MODE_ALL, &set(&["a", "b"]), &set(&["a", "b"])), None);
+    }
+}
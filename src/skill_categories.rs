@@ -0,0 +1,212 @@
+// 技能分類登錄
+//
+// skill.category 在資料庫中以分類代碼保存；API 只接受下列固定分類（大小寫不拘），
+// 未知分類回傳 422。舊資料中的自由文字分類在啟動遷移時盡量對應，無法對應的歸為 other。
+
+use std::fmt;
+
+use actix_web::{HttpResponse, Result};
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Serialize, Serializer};
+
+use crate::ai_tasks::ApiResponse;
+
+/// 技能未指定圖示時的預設值（與 skill.icon 欄位預設相同）
+pub const DEFAULT_SKILL_ICON: &str = "⭐";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SkillCategory {
+    Technical, // 技術
+    Soft,      // 軟實力
+    Physical,  // 體能
+    Creative,  // 創意
+    Social,    // 社交
+    Other,     // 其他
+}
+
+impl SkillCategory {
+    pub const ALL: [SkillCategory; 6] = [
+        SkillCategory::Technical,
+        SkillCategory::Soft,
+        SkillCategory::Physical,
+        SkillCategory::Creative,
+        SkillCategory::Social,
+        SkillCategory::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkillCategory::Technical => "technical",
+            SkillCategory::Soft => "soft",
+            SkillCategory::Physical => "physical",
+            SkillCategory::Creative => "creative",
+            SkillCategory::Social => "social",
+            SkillCategory::Other => "other",
+        }
+    }
+
+    /// 繁體中文顯示名稱
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            SkillCategory::Technical => "技術",
+            SkillCategory::Soft => "軟實力",
+            SkillCategory::Physical => "體能",
+            SkillCategory::Creative => "創意",
+            SkillCategory::Social => "社交",
+            SkillCategory::Other => "其他",
+        }
+    }
+
+    /// 解析 API 傳入的分類代碼；未知分類回傳錯誤訊息
+    pub fn parse(value: &str) -> std::result::Result<SkillCategory, String> {
+        let key = value.trim().to_lowercase();
+        SkillCategory::ALL.into_iter().find(|c| c.as_str() == key).ok_or_else(|| {
+            let allowed: Vec<&str> = SkillCategory::ALL.iter().map(SkillCategory::as_str).collect();
+            format!("未知的技能分類: {}（可用分類: {}）", value.trim(), allowed.join(", "))
+        })
+    }
+
+    /// 舊資料或 AI 產生的自由文字分類：盡量對應到固定分類，無法辨識時為 other
+    pub fn from_legacy(value: Option<&str>) -> SkillCategory {
+        let Some(value) = value else {
+            return SkillCategory::Other;
+        };
+        if let Ok(category) = SkillCategory::parse(value) {
+            return category;
+        }
+        let key: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect();
+        LEGACY_CATEGORIES
+            .iter()
+            .find(|(names, _)| names.contains(&key.as_str()))
+            .map(|(_, category)| *category)
+            .unwrap_or(SkillCategory::Other)
+    }
+}
+
+// 舊分類名稱（已去除空白、底線、連字號並轉小寫）對應表
+const LEGACY_CATEGORIES: &[(&[&str], SkillCategory)] = &[
+    (&["tech", "hard", "hardskill", "technicalskill", "programming", "技術", "技術技能", "技術類", "硬實力", "專業", "專業技能", "程式"], SkillCategory::Technical),
+    (&["softskill", "softskills", "軟技能", "軟實力", "軟性技能", "通用技能", "思維"], SkillCategory::Soft),
+    (&["fitness", "health", "sport", "sports", "體能", "運動", "健身", "健康"], SkillCategory::Physical),
+    (&["art", "arts", "design", "creativity", "創意", "創作", "藝術", "設計"], SkillCategory::Creative),
+    (&["communication", "interpersonal", "socialskill", "社交", "人際", "溝通"], SkillCategory::Social),
+];
+
+impl fmt::Display for SkillCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for SkillCategory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// 驗證技能圖示：必須是單一 emoji，前後空白會被去除
+pub fn validate_icon(icon: &str) -> std::result::Result<String, String> {
+    let icon = icon.trim();
+    if crate::ai_service::is_single_emoji(icon) {
+        Ok(icon.to_string())
+    } else {
+        Err(format!("技能圖示必須是單一 emoji: {}", icon))
+    }
+}
+
+/// GET /api/skills/categories：可用的技能分類與顯示名稱
+pub async fn get_skill_categories() -> Result<HttpResponse> {
+    let categories: Vec<serde_json::Value> = SkillCategory::ALL
+        .iter()
+        .map(|c| serde_json::json!({ "id": c.as_str(), "name": c.display_name() }))
+        .collect();
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(categories),
+        message: "獲取技能分類成功".to_string(),
+    }))
+}
+
+/// 將資料庫中非固定分類的 skill.category 改為對應的分類；回傳更新的技能數
+pub async fn normalize_stored_categories(rb: &RBatis) -> std::result::Result<u64, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb.query_decode("SELECT DISTINCT category FROM skill", vec![]).await?;
+    let mut updated = 0;
+    for row in &rows {
+        let stored = row["category"].as_str();
+        let category = SkillCategory::from_legacy(stored);
+        if stored == Some(category.as_str()) {
+            continue;
+        }
+        let result = match stored {
+            Some(stored) => {
+                rb.exec(
+                    "UPDATE skill SET category = ? WHERE category = ?",
+                    vec![Value::String(category.to_string()), Value::String(stored.to_string())],
+                )
+                .await?
+            }
+            None => {
+                rb.exec("UPDATE skill SET category = ? WHERE category IS NULL", vec![Value::String(category.to_string())])
+                    .await?
+            }
+        };
+        log::info!("技能分類 {:?} 對應為 {}（{} 筆）", stored, category, result.rows_affected);
+        updated += result.rows_affected;
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_parse_accepts_known_codes_only() {
+        assert_eq!(SkillCategory::parse(" Technical "), Ok(SkillCategory::Technical));
+        assert!(SkillCategory::parse("技術").unwrap_err().contains("可用分類"));
+        assert_eq!(SkillCategory::from_legacy(Some("技術")), SkillCategory::Technical);
+        assert_eq!(SkillCategory::from_legacy(Some("Soft Skill")), SkillCategory::Soft);
+        assert_eq!(SkillCategory::from_legacy(Some("烹飪")), SkillCategory::Other);
+        assert_eq!(SkillCategory::from_legacy(None), SkillCategory::Other);
+    }
+
+    #[test]
+    fn test_icon_must_be_single_emoji() {
+        assert_eq!(validate_icon(" 🎯 ").as_deref(), Ok("🎯"));
+        assert!(validate_icon("👨‍💻").is_ok());
+        assert!(validate_icon("🎯🎯").is_err());
+        assert!(validate_icon("A").is_err());
+        assert!(validate_icon("").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_normalize_stored_categories() {
+        let rb = test_utils::setup_db().await;
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        for (id, category) in [("s1", Some("Technical")), ("s2", Some("技術")), ("s3", Some("烹飪")), ("s4", None), ("s5", Some("soft"))] {
+            rb.exec(
+                "INSERT INTO skill (id, user_id, name, category) VALUES (?, 'u1', ?, ?)",
+                vec![
+                    Value::String(id.to_string()),
+                    Value::String(id.to_string()),
+                    category.map(|c| Value::String(c.to_string())).unwrap_or(Value::Null),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(normalize_stored_categories(&rb).await.unwrap(), 4);
+        let rows: Vec<serde_json::Value> = rb.query_decode("SELECT id, category FROM skill ORDER BY id", vec![]).await.unwrap();
+        let categories: Vec<&str> = rows.iter().map(|r| r["category"].as_str().unwrap()).collect();
+        assert_eq!(categories, ["technical", "technical", "other", "other", "soft"]);
+        assert_eq!(normalize_stored_categories(&rb).await.unwrap(), 0);
+    }
+}
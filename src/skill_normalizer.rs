@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+use rbatis::RBatis;
+use serde::Serialize;
+
+use crate::ai_service::AIGeneratedSkillTags;
+use crate::models::SkillAlias;
+
+// 預設模糊比對門檻（0~1，越高越嚴格）
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.8;
+
+// 內建別名（別名 -> 標準名稱），首次啟動時寫入 skill_alias 表
+pub const DEFAULT_SKILL_ALIASES: &[(&str, &str)] = &[
+    ("english", "英文"),
+    ("英語", "英文"),
+    ("英語會話", "英文"),
+    ("英文會話", "英文"),
+    ("japanese", "日文"),
+    ("日語", "日文"),
+    ("coding", "程式設計"),
+    ("programming", "程式設計"),
+    ("寫程式", "程式設計"),
+    ("編程", "程式設計"),
+    ("fitness", "健身"),
+    ("運動健身", "健身"),
+    ("time management", "時間管理"),
+    ("communication", "溝通"),
+    ("溝通能力", "溝通"),
+];
+
+// 正規化後的技能標籤
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NormalizedSkillTag {
+    pub skill: String,
+    pub attribute: String,
+    // 是否為使用者尚未擁有的新技能（前端需確認後才建立）
+    #[serde(rename = "new")]
+    pub is_new: bool,
+    // 若被對應到既有技能，記錄 AI 原始輸出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+}
+
+/// 技能名稱摺疊：去除前後空白、轉小寫、移除內部空白與常見標點、全形轉半形
+pub fn fold_skill_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| match c {
+            // 全形 ASCII 轉半形
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '_' | '.' | '·' | '・' | '「' | '」' | '"' | '\''))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 以編輯距離計算兩個已摺疊名稱的相似度（0~1）
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    1.0 - prev[b.len()] as f64 / max_len as f64
+}
+
+/// 將 AI 輸出的技能標籤對應到使用者既有技能
+///
+/// 比對順序：摺疊後完全相同 → 別名表 → 模糊比對（threshold 為 None 時停用）。
+/// 對應不到的才視為新技能，並以摺疊後名稱去重。
+pub fn normalize_skill_tags(
+    generated: &AIGeneratedSkillTags,
+    existing_skills: &[String],
+    aliases: &HashMap<String, String>,
+    fuzzy_threshold: Option<f64>,
+) -> Vec<NormalizedSkillTag> {
+    let existing: Vec<(String, &String)> = existing_skills
+        .iter()
+        .map(|s| (fold_skill_name(s), s))
+        .collect();
+    let find_existing = |folded: &str| existing.iter().find(|(f, _)| f == folded).map(|(_, s)| (*s).clone());
+
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+
+    for tag in &generated.skills {
+        let raw = tag.skill.trim();
+        let folded = fold_skill_name(raw);
+        if folded.is_empty() {
+            continue;
+        }
+
+        // 1. 完全相同（忽略大小寫與空白）
+        let mut matched = find_existing(&folded);
+
+        // 2. 別名表
+        let canonical = aliases.get(&folded).cloned();
+        if matched.is_none() {
+            if let Some(canonical) = &canonical {
+                matched = find_existing(&fold_skill_name(canonical));
+            }
+        }
+
+        // 3. 模糊比對
+        if matched.is_none() {
+            if let Some(threshold) = fuzzy_threshold {
+                matched = existing
+                    .iter()
+                    .map(|(f, s)| (similarity(&folded, f), *s))
+                    .filter(|(score, _)| *score >= threshold)
+                    .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(_, s)| s.clone());
+            }
+        }
+
+        let (skill, is_new) = match matched {
+            Some(name) => (name, false),
+            // 別名指向尚未擁有的技能時，以標準名稱建立
+            None => (canonical.unwrap_or_else(|| raw.to_string()), true),
+        };
+
+        if !seen.insert(fold_skill_name(&skill)) {
+            continue;
+        }
+
+        let original = if skill != raw { Some(raw.to_string()) } else { None };
+        result.push(NormalizedSkillTag {
+            skill,
+            attribute: tag.attribute.clone(),
+            is_new,
+            original,
+        });
+    }
+
+    result
+}
+
+/// 從資料庫載入別名表（key 為摺疊後的別名）
+pub async fn load_alias_map(rb: &RBatis) -> Result<HashMap<String, String>, rbatis::Error> {
+    let aliases = SkillAlias::select_all(rb).await?;
+    Ok(aliases
+        .into_iter()
+        .filter_map(|a| Some((fold_skill_name(&a.alias?), a.canonical_name?)))
+        .collect())
+}
+
+/// 寫入內建別名（已存在的別名不會被覆蓋）
+pub async fn seed_default_aliases(rb: &RBatis) {
+    for (alias, canonical) in DEFAULT_SKILL_ALIASES {
+        let result = rb
+            .exec(
+                "INSERT OR IGNORE INTO skill_alias (id, alias, canonical_name, created_at) VALUES (?, ?, ?, ?)",
+                vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(fold_skill_name(alias)),
+                    rbs::Value::String(canonical.to_string()),
+                    rbs::Value::String(chrono::Utc::now().to_rfc3339()),
+                ],
+            )
+            .await;
+        if let Err(e) = result {
+            log::warn!("寫入預設技能別名失敗: {} -> {}", alias, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_service::SkillWithAttribute;
+
+    fn tags(names: &[&str]) -> AIGeneratedSkillTags {
+        AIGeneratedSkillTags {
+            skills: names
+                .iter()
+                .map(|n| SkillWithAttribute { skill: n.to_string(), attribute: "intelligence".to_string() })
+                .collect(),
+        }
+    }
+
+    fn default_aliases() -> HashMap<String, String> {
+        DEFAULT_SKILL_ALIASES
+            .iter()
+            .map(|(a, c)| (fold_skill_name(a), c.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fold_skill_name() {
+        assert_eq!(fold_skill_name("  Python 程式設計 "), "python程式設計");
+        assert_eq!(fold_skill_name("ＵＩ設計"), "ui設計");
+        assert_eq!(fold_skill_name("Time-Management"), "timemanagement");
+    }
+
+    #[test]
+    fn test_messy_model_output_corpus() {
+        let existing = vec![
+            "英文".to_string(),
+            "Python 程式設計".to_string(),
+            "時間管理".to_string(),
+            "UI 設計".to_string(),
+        ];
+        let corpus = tags(&[
+            "English",
+            "英語會話",
+            " python程式設計 ",
+            "PYTHON 程式設計",
+            "時間管理術",
+            "ＵＩ 設計",
+            "Programming",
+            "攝影",
+            "",
+            "攝影 ",
+        ]);
+
+        let result = normalize_skill_tags(&corpus, &existing, &default_aliases(), Some(DEFAULT_FUZZY_THRESHOLD));
+        let names: Vec<(&str, bool)> = result.iter().map(|t| (t.skill.as_str(), t.is_new)).collect();
+
+        assert_eq!(
+            names,
+            vec![
+                ("英文", false),
+                ("Python 程式設計", false),
+                ("時間管理", false),
+                ("UI 設計", false),
+                ("程式設計", true),
+                ("攝影", true),
+            ]
+        );
+        assert_eq!(result[0].original.as_deref(), Some("English"));
+        assert_eq!(result[5].original, None);
+    }
+
+    #[test]
+    fn test_fuzzy_match_disabled() {
+        let existing = vec!["時間管理".to_string()];
+        let result = normalize_skill_tags(&tags(&["時間管裡"]), &existing, &HashMap::new(), None);
+        assert_eq!(result.len(), 1);
+        assert!(result[0].is_new);
+        assert_eq!(result[0].skill, "時間管裡");
+    }
+
+    #[test]
+    fn test_fuzzy_threshold_rejects_unrelated() {
+        let existing = vec!["健身".to_string()];
+        let result = normalize_skill_tags(&tags(&["演講"]), &existing, &HashMap::new(), Some(DEFAULT_FUZZY_THRESHOLD));
+        assert!(result[0].is_new);
+    }
+}
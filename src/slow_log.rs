@@ -0,0 +1,307 @@
+// 慢查詢與慢請求日誌：超過門檻的 SQL（rbatis 攔截器）與 HTTP 請求（中間件）寫入警告日誌並累計次數
+//
+// 門檻以下只多一次 Instant::now 與計時表的存取；累計結果由 /api/admin/metrics 提供。
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpRequest, HttpResponse, Result};
+use futures::future::LocalBoxFuture;
+use rbatis::executor::Executor;
+use rbatis::intercept::{Intercept, ResultType};
+use rbatis::rbdc::db::ExecResult;
+use rbs::Value;
+use serde::Serialize;
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::SlowLogConfig;
+
+// 日誌中 SQL 的最大長度（避免大量 IN (...) 洗版）
+const MAX_LOGGED_SQL_CHARS: usize = 500;
+
+static SLOW_LOG: OnceLock<SlowLogConfig> = OnceLock::new();
+
+fn config() -> &'static SlowLogConfig {
+    SLOW_LOG.get_or_init(SlowLogConfig::default)
+}
+
+/// 啟動時套用設定
+pub fn init(config: SlowLogConfig) {
+    log::info!(
+        "慢查詢門檻: SQL {}ms / 請求 {}ms",
+        config.sql_threshold_ms,
+        config.request_threshold_ms
+    );
+    if SLOW_LOG.set(config).is_err() {
+        log::warn!("慢查詢設定已初始化，忽略重複設定");
+    }
+}
+
+fn sql_threshold() -> Duration {
+    Duration::from_millis(config().sql_threshold_ms)
+}
+
+fn request_threshold() -> Duration {
+    Duration::from_millis(config().request_threshold_ms)
+}
+
+/// 慢查詢與慢請求統計（程序啟動後累計）
+struct SlowLogMetrics {
+    slow_queries: AtomicU64,
+    slowest_query_ms: AtomicU64,
+    slow_requests: AtomicU64,
+    slowest_request_ms: AtomicU64,
+}
+
+static SLOW_LOG_METRICS: SlowLogMetrics = SlowLogMetrics {
+    slow_queries: AtomicU64::new(0),
+    slowest_query_ms: AtomicU64::new(0),
+    slow_requests: AtomicU64::new(0),
+    slowest_request_ms: AtomicU64::new(0),
+};
+
+// 路由樣式 -> 慢請求次數（只在超過門檻時寫入）
+static SLOW_ROUTES: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+
+fn slow_routes() -> &'static Mutex<HashMap<String, u64>> {
+    SLOW_ROUTES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlowLogSnapshot {
+    pub sql_threshold_ms: u64,
+    pub request_threshold_ms: u64,
+    pub slow_queries: u64,
+    pub slowest_query_ms: u64,
+    pub slow_requests: u64,
+    pub slowest_request_ms: u64,
+    pub slow_requests_by_route: BTreeMap<String, u64>,
+}
+
+pub fn metrics_snapshot() -> SlowLogSnapshot {
+    let slow_requests_by_route = slow_routes()
+        .lock()
+        .map(|routes| routes.iter().map(|(route, count)| (route.clone(), *count)).collect())
+        .unwrap_or_default();
+    SlowLogSnapshot {
+        sql_threshold_ms: config().sql_threshold_ms,
+        request_threshold_ms: config().request_threshold_ms,
+        slow_queries: SLOW_LOG_METRICS.slow_queries.load(Ordering::Relaxed),
+        slowest_query_ms: SLOW_LOG_METRICS.slowest_query_ms.load(Ordering::Relaxed),
+        slow_requests: SLOW_LOG_METRICS.slow_requests.load(Ordering::Relaxed),
+        slowest_request_ms: SLOW_LOG_METRICS.slowest_request_ms.load(Ordering::Relaxed),
+        slow_requests_by_route,
+    }
+}
+
+fn record_slow_query(elapsed: Duration) {
+    let ms = elapsed.as_millis() as u64;
+    SLOW_LOG_METRICS.slow_queries.fetch_add(1, Ordering::Relaxed);
+    SLOW_LOG_METRICS.slowest_query_ms.fetch_max(ms, Ordering::Relaxed);
+}
+
+fn record_slow_request(route: &str, elapsed: Duration) {
+    let ms = elapsed.as_millis() as u64;
+    SLOW_LOG_METRICS.slow_requests.fetch_add(1, Ordering::Relaxed);
+    SLOW_LOG_METRICS.slowest_request_ms.fetch_max(ms, Ordering::Relaxed);
+    if let Ok(mut routes) = slow_routes().lock() {
+        *routes.entry(route.to_string()).or_insert(0) += 1;
+    }
+}
+
+fn truncate_sql(sql: &str) -> String {
+    let compact = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    if compact.chars().count() <= MAX_LOGGED_SQL_CHARS {
+        compact
+    } else {
+        format!("{}…", compact.chars().take(MAX_LOGGED_SQL_CHARS).collect::<String>())
+    }
+}
+
+// rbatis 攔截器：before 記下開始時間（依連線/交易 id），after 計算耗時
+//
+// 同一條連線上的語句依序執行，因此以 task_id 為鍵即可對應 before/after。
+#[derive(Debug, Default)]
+pub struct SlowQueryIntercept {
+    started: Mutex<HashMap<i64, Instant>>,
+}
+
+impl SlowQueryIntercept {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[rbatis::async_trait]
+impl Intercept for SlowQueryIntercept {
+    async fn before(
+        &self,
+        task_id: i64,
+        _rb: &dyn Executor,
+        _sql: &mut String,
+        _args: &mut Vec<Value>,
+        _result: ResultType<&mut Result<ExecResult, rbatis::Error>, &mut Result<Vec<Value>, rbatis::Error>>,
+    ) -> Result<Option<bool>, rbatis::Error> {
+        if let Ok(mut started) = self.started.lock() {
+            started.insert(task_id, Instant::now());
+        }
+        Ok(Some(true))
+    }
+
+    async fn after(
+        &self,
+        task_id: i64,
+        _rb: &dyn Executor,
+        sql: &mut String,
+        _args: &mut Vec<Value>,
+        result: ResultType<&mut Result<ExecResult, rbatis::Error>, &mut Result<Vec<Value>, rbatis::Error>>,
+    ) -> Result<Option<bool>, rbatis::Error> {
+        let Some(start) = self.started.lock().ok().and_then(|mut started| started.remove(&task_id)) else {
+            return Ok(Some(true));
+        };
+        let elapsed = start.elapsed();
+        if elapsed < sql_threshold() {
+            return Ok(Some(true));
+        }
+
+        let rows = match result {
+            ResultType::Exec(Ok(r)) => format!("rows_affected={}", r.rows_affected),
+            ResultType::Query(Ok(rows)) => format!("rows={}", rows.len()),
+            ResultType::Exec(Err(e)) | ResultType::Query(Err(e)) => format!("error={}", e),
+        };
+        record_slow_query(elapsed);
+        log::warn!("🐢 慢查詢 {}ms {} `{}`", elapsed.as_millis(), rows, truncate_sql(sql));
+        Ok(Some(true))
+    }
+}
+
+// 慢請求中間件：放在最外層，涵蓋 JWT 驗證與處理函數的總耗時
+//
+// 路由以樣式（例如 /api/tasks/{id}）記錄，避免每個 id 各自一筆統計；
+// 使用者 id 由 JwtAuth 寫入 request extensions，公開路由則為 -。
+pub struct SlowRequestLog;
+
+impl<S, B> Transform<S, ServiceRequest> for SlowRequestLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SlowRequestLogMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SlowRequestLogMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct SlowRequestLogMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for SlowRequestLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let service = self.service.clone();
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            let elapsed = start.elapsed();
+            if elapsed >= request_threshold() {
+                let request = res.request();
+                let route = request.match_pattern().unwrap_or_else(|| request.path().to_string());
+                let user_id = crate::auth::current_user_id(request).unwrap_or_else(|| "-".to_string());
+                record_slow_request(&route, elapsed);
+                log::warn!(
+                    "🐢 慢請求 {}ms {} {} status={} user={}",
+                    elapsed.as_millis(),
+                    request.method(),
+                    route,
+                    res.status().as_u16(),
+                    user_id
+                );
+            }
+            Ok(res)
+        })
+    }
+}
+
+/// 管理員查看執行期統計（慢查詢、慢請求、郵件發送、資料保留清除、聊天延遲、每晚彙整、背景工作佇列）
+pub async fn get_metrics(http_req: HttpRequest) -> Result<HttpResponse> {
+    if !crate::auth::is_admin_request(&http_req) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: "需要管理員權限".to_string(),
+        }));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "slow_log": metrics_snapshot(),
+            "mail": crate::mailer::metrics_snapshot(),
+            "data_retention": crate::data_retention::metrics_snapshot(),
+            "chat_latency": crate::chat_fast_mode::metrics_snapshot(),
+            "nightly_digest": crate::nightly_digest::metrics_snapshot(),
+            "background_jobs": crate::background_jobs::metrics_snapshot(),
+            "registration_guard": crate::registration_guard::metrics_snapshot(),
+        })),
+        message: "獲取執行期統計成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App};
+
+    #[test]
+    fn test_truncate_sql_compacts_whitespace() {
+        assert_eq!(truncate_sql("SELECT *\n   FROM task\n  WHERE id = ?"), "SELECT * FROM task WHERE id = ?");
+        let long = format!("SELECT {}", "a, ".repeat(400));
+        let truncated = truncate_sql(&long);
+        assert_eq!(truncated.chars().count(), MAX_LOGGED_SQL_CHARS + 1);
+        assert!(truncated.ends_with('…'));
+    }
+
+    #[actix_web::test]
+    async fn test_slow_request_counted_by_route_pattern() {
+        // 未初始化時使用預設門檻（1 秒），測試路由刻意超過
+        let app = actix_web::test::init_service(App::new().wrap(SlowRequestLog).route(
+            "/slow/{id}",
+            web::get().to(|| async {
+                actix_web::rt::time::sleep(Duration::from_millis(1050)).await;
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+
+        let before = metrics_snapshot();
+        let req = actix_web::test::TestRequest::get().uri("/slow/42").to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let after = metrics_snapshot();
+        assert!(after.slow_requests > before.slow_requests);
+        assert!(after.slowest_request_ms >= 1000);
+        assert!(after.slow_requests_by_route.get("/slow/{id}").copied().unwrap_or(0) >= 1);
+    }
+}
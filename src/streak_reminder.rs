@@ -0,0 +1,279 @@
+// 連續紀錄中斷提醒：晚間總結前，提醒今天還沒完成、連續天數達門檻的重複性任務
+//
+// 每位使用者每天最多一則（以通知歷史 data.date 去重）；推送經由 event_notifier，
+// 套用 streak_risk 類別開關與勿擾時段。
+
+use std::sync::OnceLock;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, NaiveTime};
+use rbatis::RBatis;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::StreakReminderConfig;
+use crate::models::{Task, TaskStatus};
+
+static STREAK_REMINDER_CONFIG: OnceLock<StreakReminderConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: StreakReminderConfig) {
+    log::info!(
+        "連續紀錄提醒: 連續 {} 天以上、晚間總結前 {} 分鐘",
+        config.min_streak,
+        config.minutes_before_evening
+    );
+    if STREAK_REMINDER_CONFIG.set(config).is_err() {
+        log::warn!("連續紀錄提醒設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static StreakReminderConfig {
+    STREAK_REMINDER_CONFIG.get_or_init(StreakReminderConfig::default)
+}
+
+/// 今天尚未完成且連續紀錄達門檻的重複性任務
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreakAtRisk {
+    pub task_id: String,
+    pub title: String,
+    pub streak: i32,
+}
+
+/// 提醒時間：晚間總結時間往前推（跨午夜時回到前一天的時間）
+pub fn reminder_time(evening_time: &str, minutes_before: i64) -> Option<String> {
+    let evening = NaiveTime::parse_from_str(evening_time, "%H:%M").ok()?;
+    let (time, _) = evening.overflowing_sub_signed(Duration::minutes(minutes_before.max(0)));
+    Some(time.format("%H:%M").to_string())
+}
+
+/// 依設定計算使用者的提醒時間
+pub fn reminder_time_for(evening_time: &str) -> Option<String> {
+    reminder_time(evening_time, config().minutes_before_evening)
+}
+
+/// 找出今天應執行但尚未完成、連續天數達門檻的重複性任務（連續天數高者優先）
+pub async fn find_at_risk_tasks(rb: &RBatis, user_id: &str) -> std::result::Result<Vec<StreakAtRisk>, rbatis::Error> {
+    let tasks: Vec<Task> = rb
+        .query_decode(
+            "SELECT * FROM task WHERE user_id = ? AND is_recurring = 1 AND parent_task_id IS NULL AND status NOT IN (?, ?, ?, ?)",
+            vec![
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::I32(TaskStatus::Completed.to_i32()),
+                rbs::Value::I32(TaskStatus::Cancelled.to_i32()),
+                rbs::Value::I32(TaskStatus::Paused.to_i32()),
+                rbs::Value::I32(TaskStatus::DailyNotCompleted.to_i32()),
+            ],
+        )
+        .await?;
+
+    let today = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb, user_id).await);
+    let mut risks = Vec::new();
+    for task in tasks {
+        let stats = crate::habit_stats::load_habit_stats(rb, &task).await?;
+        // 今天尚未完成不會中斷 current_streak，因此它就是到昨天為止的連續天數
+        let pending_today = stats
+            .calendar
+            .last()
+            .is_some_and(|day| day.date == today && day.scheduled && !day.completed);
+        if pending_today && stats.current_streak >= config().min_streak {
+            risks.push(StreakAtRisk {
+                task_id: stats.task_id,
+                title: task.title.unwrap_or_default(),
+                streak: stats.current_streak,
+            });
+        }
+    }
+    risks.sort_by_key(|risk| std::cmp::Reverse(risk.streak));
+    Ok(risks)
+}
+
+/// 產生提醒內容（點名連續天數最長的任務）；沒有快中斷的紀錄時為 None
+pub fn build_notification(risks: &[StreakAtRisk], date: &str) -> Option<serde_json::Value> {
+    let first = risks.first()?;
+    let mut body = format!("你今天的{}還沒完成，連續 {} 天快斷了！", first.title, first.streak);
+    if risks.len() > 1 {
+        body.push_str(&format!("另外還有 {} 個習慣今天尚未完成。", risks.len() - 1));
+    }
+
+    Some(json!({
+        "title": "🔥 連續紀錄快中斷了",
+        "body": body,
+        "icon": "/icon.svg",
+        "badge": "/icon.svg",
+        "tag": "streak-risk-notification",
+        "data": {
+            "url": "/mission",
+            "type": "streak_risk",
+            "date": date,
+            "task_id": first.task_id,
+            "streak": first.streak,
+            "tasks": risks,
+        }
+    }))
+}
+
+// 今天是否已提醒過（每天最多一則）
+async fn already_reminded(rb: &RBatis, user_id: &str, date: &str) -> bool {
+    let count: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) AS count FROM notification_history
+             WHERE user_id = ? AND event_type = 'streak_risk' AND json_extract(data, '$.date') = ?",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(date.to_string())],
+        )
+        .await
+        .unwrap_or(0);
+    count > 0
+}
+
+/// 今天應發送的提醒內容：有快中斷的連續紀錄且今天尚未提醒過時才有
+pub async fn pending_reminder(rb: &RBatis, user_id: &str) -> std::result::Result<Option<serde_json::Value>, rbatis::Error> {
+    let today = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb, user_id).await);
+    if already_reminded(rb, user_id, &today).await {
+        return Ok(None);
+    }
+    let risks = find_at_risk_tasks(rb, user_id).await?;
+    Ok(build_notification(&risks, &today))
+}
+
+/// 預覽連續紀錄中斷提醒內容（不檢查今天是否已提醒，也不發送）
+pub async fn preview_streak_risk(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+
+    let category = crate::notification_categories::NotificationCategory::StreakRisk;
+    if let Some(settings) = crate::notification_categories::load_settings(rb.get_ref(), &user_id).await {
+        if !crate::notification_categories::category_enabled(&settings, category) {
+            return Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(json!({ "category": category.as_str(), "enabled": false })),
+                message: "此類別已停用".to_string(),
+            }));
+        }
+    }
+
+    match find_at_risk_tasks(rb.get_ref(), &user_id).await {
+        Ok(risks) => {
+            let today = crate::local_date::local_today_string_in(crate::user_settings::user_timezone(rb.get_ref(), &user_id).await);
+            let notification = build_notification(&risks, &today);
+            let message = if notification.is_some() {
+                "生成連續紀錄提醒預覽成功"
+            } else {
+                "目前沒有快中斷的連續紀錄"
+            };
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: notification,
+                message: message.to_string(),
+            }))
+        }
+        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("生成通知預覽失敗: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use chrono::Utc;
+
+    use crate::test_utils::call_json;
+
+    // 建立連續完成 5 天、今天尚未完成的每日任務「晨跑」
+    async fn seed_running_streak(rb: &RBatis, user_id: &str) {
+        let start = Utc::now() - Duration::days(5);
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, is_recurring, recurrence_pattern, start_date, status) VALUES ('run', ?, '晨跑', 1, 'daily', ?, ?)",
+            vec![
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(start.to_rfc3339()),
+                rbs::Value::I32(TaskStatus::DailyInProgress.to_i32()),
+            ],
+        )
+        .await
+        .unwrap();
+        let today = crate::local_date::local_today();
+        for offset in 0..=5 {
+            let date = (today - Duration::days(offset)).format("%Y-%m-%d").to_string();
+            let status = if offset == 0 { TaskStatus::DailyInProgress } else { TaskStatus::DailyCompleted };
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, parent_task_id, task_date, status) VALUES (?, ?, '晨跑', 'run', ?, ?)",
+                vec![
+                    rbs::Value::String(format!("run-{}", offset)),
+                    rbs::Value::String(user_id.to_string()),
+                    rbs::Value::String(date),
+                    rbs::Value::I32(status.to_i32()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reminder_time() {
+        assert_eq!(reminder_time("22:00", 30).as_deref(), Some("21:30"));
+        assert_eq!(reminder_time("00:15", 30).as_deref(), Some("23:45"));
+        assert_eq!(reminder_time("22:00", 0).as_deref(), Some("22:00"));
+        assert!(reminder_time("late", 30).is_none());
+    }
+
+    #[test]
+    fn test_build_notification_names_longest_streak() {
+        let risks = vec![
+            StreakAtRisk { task_id: "a".to_string(), title: "晨跑".to_string(), streak: 23 },
+            StreakAtRisk { task_id: "b".to_string(), title: "閱讀".to_string(), streak: 5 },
+        ];
+        let notification = build_notification(&risks, "2026-03-02").unwrap();
+        assert_eq!(notification["body"], "你今天的晨跑還沒完成，連續 23 天快斷了！另外還有 1 個習慣今天尚未完成。");
+        assert_eq!(notification["data"]["date"], "2026-03-02");
+        assert!(build_notification(&[], "2026-03-02").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_remind_once_per_day() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "streak_reminder").await;
+        seed_running_streak(&rb, &user.id).await;
+
+        let risks = find_at_risk_tasks(&rb, &user.id).await.unwrap();
+        assert_eq!(risks.len(), 1);
+        assert_eq!(risks[0].streak, 5);
+
+        // 發送後當天不再提醒
+        let notification = pending_reminder(&rb, &user.id).await.unwrap().unwrap();
+        crate::event_notifier::notify_scheduled(&rb, &user.id, "streak_risk", &notification).await;
+        assert!(pending_reminder(&rb, &user.id).await.unwrap().is_none());
+    }
+    #[actix_web::test]
+    async fn test_preview_route_is_reachable_and_owner_only() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let owner = crate::test_utils::create_user(&app, "streak_preview_owner").await;
+        let other = crate::test_utils::create_user(&app, "streak_preview_other").await;
+        seed_running_streak(&rb, &owner.id).await;
+
+        let uri = format!("/api/notifications/preview-streak-risk/{}", owner.id);
+        let req = TestRequest::post().uri(&uri).insert_header(owner.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["body"], "你今天的晨跑還沒完成，連續 5 天快斷了！");
+
+        let req = TestRequest::post().uri(&uri).insert_header(other.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", body);
+    }
+}
@@ -0,0 +1,664 @@
+// 任務附件（完成證明）：上傳圖片/檔案、列出、下載與刪除
+//
+// 檔案存放於 {storage_dir}/{上傳者 user_id}/，檔名為雜湊值，不使用使用者提供的名稱；
+// 設定 require_proof 的任務必須至少有一個附件才能標記完成。
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use actix_web::http::header::{ContentDisposition, CONTENT_TYPE};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use rbatis::RBatis;
+use rbs::value;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::ai_tasks::ApiResponse;
+use crate::config::AttachmentConfig;
+use crate::models::TaskAttachment;
+
+// multipart 標頭與邊界的額外空間
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+static ATTACHMENTS: OnceLock<AttachmentConfig> = OnceLock::new();
+
+pub(crate) fn config() -> &'static AttachmentConfig {
+    ATTACHMENTS.get_or_init(|| {
+        // 測試時寫到暫存目錄，避免在專案目錄留下檔案
+        #[cfg(test)]
+        return AttachmentConfig {
+            storage_dir: std::env::temp_dir()
+                .join(format!("lifeup_attachments_test_{}", std::process::id()))
+                .to_string_lossy()
+                .to_string(),
+            max_size_bytes: 64 * 1024,
+            chat_image_max_bytes: 64 * 1024,
+            ..AttachmentConfig::default()
+        };
+        #[cfg(not(test))]
+        AttachmentConfig::default()
+    })
+}
+
+/// 啟動時套用設定
+pub fn init(config: AttachmentConfig) {
+    log::info!(
+        "任務附件: 目錄 {}，上限 {} bytes，允許類型 {}",
+        config.storage_dir,
+        config.max_size_bytes,
+        config.allowed_mime_types.join(", ")
+    );
+    let _ = ATTACHMENTS.set(config);
+}
+
+/// 回傳給客戶端的附件資訊（不含儲存檔名）
+#[derive(Debug, Serialize)]
+pub struct AttachmentView {
+    pub id: String,
+    pub task_id: String,
+    pub user_id: String,
+    pub original_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    #[serde(with = "crate::datetime_format::rfc3339_option")]
+    pub created_at: Option<DateTime<Utc>>,
+    pub download_url: String,
+}
+
+impl From<TaskAttachment> for AttachmentView {
+    fn from(a: TaskAttachment) -> Self {
+        let id = a.id.unwrap_or_default();
+        let task_id = a.task_id.unwrap_or_default();
+        AttachmentView {
+            download_url: format!("/api/tasks/{}/attachments/{}", task_id, id),
+            id,
+            task_id,
+            user_id: a.user_id.unwrap_or_default(),
+            original_name: a.original_name.unwrap_or_default(),
+            mime_type: a.mime_type.unwrap_or_default(),
+            size_bytes: a.size_bytes.unwrap_or(0),
+            sha256: a.sha256.unwrap_or_default(),
+            created_at: a.created_at,
+        }
+    }
+}
+
+/// multipart 中的檔案欄位
+#[derive(Debug, PartialEq)]
+pub struct UploadedFile {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// multipart 中的一個欄位（文字欄位的 filename 為 None）
+#[derive(Debug, PartialEq)]
+pub struct MultipartPart {
+    pub name: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+fn boundary_of(content_type: &str) -> Option<String> {
+    content_type.split(';').map(str::trim).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+// Content-Disposition 中的參數值（例如 filename="a.jpg"）
+fn disposition_param(header: &str, name: &str) -> Option<String> {
+    header.split(';').map(str::trim).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        (key.trim() == name).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// 解析 multipart/form-data 的所有欄位
+pub fn parse_multipart_parts(content_type: &str, body: &[u8]) -> std::result::Result<Vec<MultipartPart>, String> {
+    if !content_type.to_lowercase().starts_with("multipart/form-data") {
+        return Err("請使用 multipart/form-data 上傳檔案".to_string());
+    }
+    let boundary = boundary_of(content_type).ok_or("multipart 缺少 boundary")?;
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut parts = Vec::new();
+    let mut pos = find(body, &delimiter, 0).ok_or("multipart 格式錯誤")? + delimiter.len();
+    loop {
+        // 結束邊界為 --boundary--
+        if body[pos..].starts_with(b"--") {
+            return Ok(parts);
+        }
+        let part_start = pos + 2; // 略過邊界後的 \r\n
+        let next = find(body, &delimiter, part_start).ok_or("multipart 格式錯誤")?;
+        let part = &body[part_start.min(next)..next];
+        let header_end = find(part, b"\r\n\r\n", 0).ok_or("multipart 欄位缺少標頭")?;
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let data = &part[header_end + 4..];
+        let data = data.strip_suffix(b"\r\n").unwrap_or(data);
+
+        let mut name = None;
+        let mut filename = None;
+        let mut part_type = "application/octet-stream".to_string();
+        for line in headers.lines() {
+            let Some((header, value)) = line.split_once(':') else { continue };
+            if header.trim().eq_ignore_ascii_case("content-disposition") {
+                name = disposition_param(value, "name");
+                filename = disposition_param(value, "filename");
+            } else if header.trim().eq_ignore_ascii_case("content-type") {
+                part_type = value.trim().to_lowercase();
+            }
+        }
+        parts.push(MultipartPart { name, filename, content_type: part_type, data: data.to_vec() });
+        pos = next + delimiter.len();
+    }
+}
+
+/// 解析 multipart/form-data，回傳第一個帶有檔名的欄位
+pub fn parse_multipart(content_type: &str, body: &[u8]) -> std::result::Result<Option<UploadedFile>, String> {
+    Ok(parse_multipart_parts(content_type, body)?.into_iter().find_map(|part| {
+        let filename = part.filename.filter(|f| !f.is_empty())?;
+        Some(UploadedFile { filename, content_type: part.content_type, data: part.data })
+    }))
+}
+
+fn extension_for(mime_type: &str, filename: &str) -> String {
+    let known = match mime_type {
+        "image/jpeg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/webp" => Some("webp"),
+        "image/gif" => Some("gif"),
+        "image/heic" => Some("heic"),
+        "application/pdf" => Some("pdf"),
+        _ => None,
+    };
+    if let Some(ext) = known {
+        return ext.to_string();
+    }
+    Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.len() <= 8 && e.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|e| e.to_lowercase())
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+// 使用者 id 作為目錄名稱前先確認不含路徑字元
+pub(crate) fn user_dir_in(base: &Path, user_id: &str) -> Option<PathBuf> {
+    let safe = !user_id.is_empty() && user_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    safe.then(|| base.join(user_id))
+}
+
+fn user_dir(user_id: &str) -> Option<PathBuf> {
+    user_dir_in(Path::new(&config().storage_dir), user_id)
+}
+
+// 儲存檔名由後端產生，讀取前仍確認不含路徑字元
+pub(crate) fn stored_file_in(dir: PathBuf, stored_name: &str) -> Option<PathBuf> {
+    if stored_name.contains(['/', '\\']) || stored_name.starts_with('.') {
+        return None;
+    }
+    Some(dir.join(stored_name))
+}
+
+fn file_path(attachment: &TaskAttachment) -> Option<PathBuf> {
+    stored_file_in(user_dir(attachment.user_id.as_deref()?)?, attachment.stored_name.as_deref()?)
+}
+
+/// 以雜湊值命名寫入檔案，回傳 (儲存檔名, 內容的 sha256)
+pub(crate) fn write_stored_file(
+    dir: &Path,
+    id: &str,
+    mime_type: &str,
+    filename: &str,
+    data: &[u8],
+) -> std::io::Result<(String, String)> {
+    let sha256 = hex::encode(Sha256::digest(data));
+    let stored_name = format!(
+        "{}.{}",
+        hex::encode(Sha256::digest(format!("{}:{}", id, sha256).as_bytes())),
+        extension_for(mime_type, filename)
+    );
+    std::fs::create_dir_all(dir).and_then(|_| std::fs::write(dir.join(&stored_name), data))?;
+    Ok((stored_name, sha256))
+}
+
+pub(crate) fn remove_path(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("刪除附件檔案失敗 {:?}: {}", path, e);
+        }
+    }
+}
+
+fn remove_file(attachment: &TaskAttachment) {
+    if let Some(path) = file_path(attachment) {
+        remove_path(&path);
+    }
+}
+
+/// 任務是否已有附件（require_proof 任務完成前檢查）
+pub async fn has_attachments(rb: &RBatis, task_id: &str) -> Result<bool, rbatis::Error> {
+    let rows = TaskAttachment::select_by_map(rb, value!{"task_id": task_id}).await?;
+    Ok(!rows.is_empty())
+}
+
+/// 刪除任務的所有附件（檔案與資料列）
+pub async fn delete_task_attachments(rb: &RBatis, task_id: &str) -> Result<usize, rbatis::Error> {
+    let rows = TaskAttachment::select_by_map(rb, value!{"task_id": task_id}).await?;
+    for attachment in &rows {
+        remove_file(attachment);
+    }
+    TaskAttachment::delete_by_map(rb, value!{"task_id": task_id}).await?;
+    Ok(rows.len())
+}
+
+/// 重置使用者時清除附件：使用者上傳的檔案，以及其任務上由其他參與者上傳的檔案
+pub async fn purge_user_attachments(rb: &RBatis, user_id: &str) -> Result<i32, rbatis::Error> {
+    let rows: Vec<TaskAttachment> = rb
+        .query_decode(
+            "SELECT * FROM task_attachment WHERE user_id = ? OR task_id IN (SELECT id FROM task WHERE user_id = ?)",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    for attachment in &rows {
+        remove_file(attachment);
+    }
+    let result = rb
+        .exec(
+            "DELETE FROM task_attachment WHERE user_id = ? OR task_id IN (SELECT id FROM task WHERE user_id = ?)",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    if let Some(dir) = user_dir(user_id) {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("刪除使用者附件目錄失敗 {:?}: {}", dir, e);
+            }
+        }
+    }
+    Ok(result.rows_affected as i32)
+}
+
+/// 重置資料庫時刪除整個附件目錄（含聊天圖片）
+pub fn remove_all_files() {
+    let dir = Path::new(&config().storage_dir);
+    if let Err(e) = std::fs::remove_dir_all(dir) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("刪除附件目錄失敗 {:?}: {}", dir, e);
+        }
+    }
+}
+
+fn error_response(status: actix_web::http::StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+async fn find_attachment(rb: &RBatis, task_id: &str, attachment_id: &str) -> std::result::Result<TaskAttachment, HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    match TaskAttachment::select_by_map(rb, value!{"id": attachment_id, "task_id": task_id}).await {
+        Ok(rows) => rows
+            .into_iter()
+            .next()
+            .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "附件不存在")),
+        Err(e) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢附件失敗: {}", e))),
+    }
+}
+
+/// 上傳任務附件（multipart/form-data，取第一個檔案欄位）
+pub async fn upload_attachment(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let task_id = path.into_inner();
+    let caller = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(caller) => caller,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), &task_id, Some(&caller)).await {
+        return Ok(e.into_response());
+    }
+    let config = config();
+
+    // 邊讀邊檢查大小，超過上限立即中止
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > config.max_size_bytes + MULTIPART_OVERHEAD_BYTES {
+            return Ok(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("檔案超過大小上限 {} bytes", config.max_size_bytes),
+            ));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let content_type = http_req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let file = match parse_multipart(content_type, &body) {
+        Ok(Some(file)) => file,
+        Ok(None) => return Ok(error_response(StatusCode::BAD_REQUEST, "請求中沒有檔案")),
+        Err(message) => return Ok(error_response(StatusCode::BAD_REQUEST, message)),
+    };
+    if file.data.is_empty() {
+        return Ok(error_response(StatusCode::BAD_REQUEST, "檔案內容為空"));
+    }
+    if file.data.len() > config.max_size_bytes {
+        return Ok(error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("檔案超過大小上限 {} bytes", config.max_size_bytes),
+        ));
+    }
+    let mime_type = file.content_type.split(';').next().unwrap_or_default().trim().to_string();
+    if !config.allowed_mime_types.iter().any(|m| m == &mime_type) {
+        return Ok(error_response(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("不支援的檔案類型: {}（允許: {}）", mime_type, config.allowed_mime_types.join(", ")),
+        ));
+    }
+
+    let Some(dir) = user_dir(&caller) else {
+        return Ok(error_response(StatusCode::BAD_REQUEST, "無效的使用者 ID"));
+    };
+    let id = uuid::Uuid::new_v4().to_string();
+    let (stored_name, sha256) = match write_stored_file(&dir, &id, &mime_type, &file.filename, &file.data) {
+        Ok(stored) => stored,
+        Err(e) => {
+            log::error!("寫入附件檔案失敗: {}", e);
+            return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, "儲存附件失敗"));
+        }
+    };
+
+    let attachment = TaskAttachment {
+        id: Some(id),
+        task_id: Some(task_id),
+        user_id: Some(caller),
+        original_name: Some(file.filename),
+        stored_name: Some(stored_name),
+        mime_type: Some(mime_type),
+        size_bytes: Some(file.data.len() as i64),
+        sha256: Some(sha256),
+        created_at: Some(Utc::now()),
+    };
+    if let Err(e) = TaskAttachment::insert(rb.get_ref(), &attachment).await {
+        remove_file(&attachment);
+        return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("儲存附件紀錄失敗: {}", e)));
+    }
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(AttachmentView::from(attachment)),
+        message: "附件上傳成功".to_string(),
+    }))
+}
+
+/// 列出任務附件
+pub async fn list_attachments(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        return Ok(e.into_response());
+    }
+    match TaskAttachment::select_by_map(rb.get_ref(), value!{"task_id": &task_id}).await {
+        Ok(mut rows) => {
+            rows.sort_by_key(|a| a.created_at);
+            let views: Vec<AttachmentView> = rows.into_iter().map(AttachmentView::from).collect();
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(views),
+                message: "獲取附件列表成功".to_string(),
+            }))
+        }
+        Err(e) => Ok(error_response(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("獲取附件列表失敗: {}", e),
+        )),
+    }
+}
+
+/// 下載任務附件
+pub async fn download_attachment(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let (task_id, attachment_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        return Ok(e.into_response());
+    }
+    let attachment = match find_attachment(rb.get_ref(), &task_id, &attachment_id).await {
+        Ok(attachment) => attachment,
+        Err(response) => return Ok(response),
+    };
+    let Some(data) = file_path(&attachment).and_then(|p| std::fs::read(p).ok()) else {
+        return Ok(error_response(StatusCode::NOT_FOUND, "附件檔案不存在"));
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.mime_type.clone().unwrap_or_else(|| "application/octet-stream".to_string()))
+        .insert_header(ContentDisposition::attachment(attachment.original_name.clone().unwrap_or_default()))
+        .body(data))
+}
+
+/// 刪除任務附件（上傳者或任務擁有者）
+pub async fn delete_attachment(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let (task_id, attachment_id) = path.into_inner();
+    let caller = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(caller) => caller,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let task = match crate::ownership::assert_task_access(rb.get_ref(), &task_id, Some(&caller)).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let attachment = match find_attachment(rb.get_ref(), &task_id, &attachment_id).await {
+        Ok(attachment) => attachment,
+        Err(response) => return Ok(response),
+    };
+    if attachment.user_id.as_deref() != Some(caller.as_str()) && task.user_id.as_deref() != Some(caller.as_str()) {
+        return Ok(error_response(StatusCode::FORBIDDEN, "只有上傳者或任務擁有者可以刪除附件"));
+    }
+
+    if let Err(e) = TaskAttachment::delete_by_map(rb.get_ref(), value!{"id": &attachment_id}).await {
+        return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除附件失敗: {}", e)));
+    }
+    remove_file(&attachment);
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        success: true,
+        data: None,
+        message: "附件已刪除".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    const BOUNDARY: &str = "lifeupboundary";
+
+    fn multipart_body(filename: &str, content_type: &str, data: &[u8]) -> Vec<u8> {
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\n完成了\r\n--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\nContent-Type: {t}\r\n\r\n",
+            b = BOUNDARY,
+            f = filename,
+            t = content_type
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        body
+    }
+
+    fn upload_request(task_id: &str, user: &test_utils::TestUser, filename: &str, content_type: &str, data: &[u8]) -> actix_http::Request {
+        actix_web::test::TestRequest::post()
+            .uri(&format!("/api/tasks/{}/attachments", task_id))
+            .insert_header(user.auth())
+            .insert_header((CONTENT_TYPE, format!("multipart/form-data; boundary={}", BOUNDARY)))
+            .set_payload(multipart_body(filename, content_type, data))
+            .to_request()
+    }
+
+    #[test]
+    fn test_parse_multipart_returns_first_file_part() {
+        let body = multipart_body("proof.png", "image/png", b"\x89PNG\r\n--data");
+        let file = parse_multipart(&format!("multipart/form-data; boundary=\"{}\"", BOUNDARY), &body)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.filename, "proof.png");
+        assert_eq!(file.content_type, "image/png");
+        assert_eq!(file.data, b"\x89PNG\r\n--data");
+
+        assert!(parse_multipart("application/json", &body).is_err());
+        assert!(parse_multipart("multipart/form-data", &body).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_attachment_upload_download_and_proof_requirement() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let owner = test_utils::create_user(&app, "proof_owner").await;
+        let other = test_utils::create_user(&app, "proof_other").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(owner.auth())
+            .set_json(json!({"user_id": owner.id, "title": "晨跑五公里", "task_type": "side", "difficulty": 1, "experience": 10, "require_proof": true}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+        let version = body["data"]["version"].as_i64().unwrap();
+
+        // 尚未上傳附件時不能完成
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", task_id))
+            .insert_header(owner.auth())
+            .set_json(json!({"status": crate::models::TaskStatus::Completed.to_i32(), "version": version}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+
+        // 不允許的類型與過大的檔案
+        let (status, _) = call_json(&app, upload_request(&task_id, &owner, "run.exe", "application/x-msdownload", b"MZ")).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let too_large = vec![0u8; config().max_size_bytes + 1];
+        let resp = actix_web::test::call_service(&app, upload_request(&task_id, &owner, "big.png", "image/png", &too_large)).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // 其他使用者無法上傳或列出
+        let (status, _) = call_json(&app, upload_request(&task_id, &other, "proof.png", "image/png", b"png")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = call_json(&app, upload_request(&task_id, &owner, "../../proof.png", "image/png", b"png-bytes")).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        assert!(body["data"].get("stored_name").is_none());
+        let attachment_id = body["data"]["id"].as_str().unwrap().to_string();
+        assert_eq!(body["data"]["sha256"], hex::encode(Sha256::digest(b"png-bytes")));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks/{}/attachments", task_id))
+            .insert_header(owner.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks/{}/attachments/{}", task_id, attachment_id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks/{}/attachments/{}", task_id, attachment_id))
+            .insert_header(owner.auth())
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "image/png");
+        assert_eq!(actix_web::test::read_body(resp).await.as_ref(), b"png-bytes");
+
+        // 有附件後即可完成
+        let req = actix_web::test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", task_id))
+            .insert_header(owner.auth())
+            .set_json(json!({"status": crate::models::TaskStatus::Completed.to_i32(), "version": version}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("/api/tasks/{}/attachments/{}", task_id, attachment_id))
+            .insert_header(owner.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+        assert!(!has_attachments(&rb, &task_id).await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_purge_user_attachments_removes_rows_and_files() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let owner = test_utils::create_user(&app, "purge_owner").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(owner.auth())
+            .set_json(json!({"user_id": owner.id, "title": "閱讀一章", "task_type": "side", "difficulty": 1, "experience": 10}))
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        let (status, _) = call_json(&app, upload_request(&task_id, &owner, "notes.pdf", "application/pdf", b"%PDF-1.4")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let dir = user_dir(&owner.id).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert_eq!(purge_user_attachments(&rb, &owner.id).await.unwrap(), 1);
+        assert!(!has_attachments(&rb, &task_id).await.unwrap());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,359 @@
+// 任務取消紀錄：每次取消都寫入 cancellation_log（含取消原因）
+//
+// 同一任務的取消次數超過設定的軟上限後，cancel_task 必須附上取消原因；
+// 剛超過上限的那一次會以使用者選擇的教練個性（背景模型）傳一則聊天訊息，
+// 邀請使用者回顧一再取消的原因。GET /api/users/{id}/cancellations 提供分析頁的統計。
+
+use std::sync::OnceLock;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{Duration, Utc};
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_service::SharedAIService;
+use crate::ai_tasks::ApiResponse;
+use crate::config::TaskCancellationConfig;
+use crate::models::{CancellationLog, ChatMessage, Task, CHAT_SOURCE_BACKEND};
+
+// 取消原因的長度上限（字元）
+pub const MAX_REASON_CHARS: usize = 500;
+// 統計的預設與最長天數
+const DEFAULT_SUMMARY_DAYS: i64 = 90;
+const MAX_SUMMARY_DAYS: i64 = 365;
+// 統計回傳的最近紀錄筆數
+const RECENT_LIMIT: usize = 20;
+
+static TASK_CANCELLATION_CONFIG: OnceLock<TaskCancellationConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: TaskCancellationConfig) {
+    log::info!("任務取消: 同一任務取消超過 {} 次後需填寫原因", config.soft_limit);
+    if TASK_CANCELLATION_CONFIG.set(config).is_err() {
+        log::warn!("任務取消設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static TaskCancellationConfig {
+    TASK_CANCELLATION_CONFIG.get_or_init(TaskCancellationConfig::default)
+}
+
+pub fn soft_limit() -> i32 {
+    config().soft_limit
+}
+
+/// 第 cancel_count 次取消是否必須附上原因
+pub fn reason_required(cancel_count: i32) -> bool {
+    cancel_count > soft_limit()
+}
+
+/// 第 cancel_count 次取消是否剛好超過上限（只在這一次請教練關心）
+pub fn crosses_limit(cancel_count: i32) -> bool {
+    cancel_count == soft_limit() + 1
+}
+
+/// 整理使用者輸入的取消原因：去除前後空白，空字串視為未填
+pub fn normalize_reason(reason: Option<&str>) -> std::result::Result<Option<String>, String> {
+    let Some(reason) = reason.map(str::trim).filter(|r| !r.is_empty()) else {
+        return Ok(None);
+    };
+    if reason.chars().count() > MAX_REASON_CHARS {
+        return Err(format!("取消原因不可超過 {} 個字", MAX_REASON_CHARS));
+    }
+    Ok(Some(reason.to_string()))
+}
+
+/// 寫入一筆取消紀錄
+pub async fn record(rb: &RBatis, task: &Task, cancel_count: i32, reason: Option<&str>) -> std::result::Result<(), rbatis::Error> {
+    let log = CancellationLog {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        task_id: task.id.clone(),
+        user_id: task.user_id.clone(),
+        task_title: task.title.clone(),
+        cancel_count: Some(cancel_count),
+        reason: reason.map(str::to_string),
+        created_at: Some(Utc::now()),
+    };
+    CancellationLog::insert(rb, &log).await?;
+    Ok(())
+}
+
+pub fn build_reflection_prompt(system_prompt: &str, task_title: &str, cancel_count: i32, reason: &str) -> String {
+    format!(
+        "{}\n\n使用者剛剛第 {} 次取消任務「{}」，這次填寫的原因是：「{}」。\n\n\
+         請寫一則 1～2 句的簡短訊息：\n\
+         - {}\n\
+         - 溫和地提到這個任務已經取消了好幾次，不要責備或讓使用者有罪惡感\n\
+         - 以問句結尾，邀請使用者聊聊這個任務是否需要調整（拆小、換時間或乾脆放下）\n\
+         - 只輸出訊息本身",
+        system_prompt, cancel_count, task_title, reason, crate::language::directive()
+    )
+}
+
+/// 產生教練的回顧訊息並寫入聊天紀錄；回傳是否已寫入
+pub async fn send_reflection(
+    rb: &RBatis,
+    ai: &SharedAIService,
+    task: &Task,
+    cancel_count: i32,
+    reason: &str,
+) -> std::result::Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let user_id = task.user_id.clone().unwrap_or_default();
+    let personality = crate::routes::coach::get_user_personality_type(rb, Some(user_id.clone()))
+        .await
+        .unwrap_or(crate::models::CoachPersonalityType::EmotionalSupport);
+    let prompt = build_reflection_prompt(
+        personality.system_prompt(),
+        task.title.as_deref().unwrap_or_default(),
+        cancel_count,
+        reason,
+    );
+    let message = ai.get()?.generate_with_model(ai.background_model(), &prompt).await?;
+    let message = message.trim().to_string();
+    if message.is_empty() {
+        return Ok(false);
+    }
+    let chat_message = ChatMessage {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        user_id: Some(user_id),
+        role: Some("assistant".to_string()),
+        content: Some(message),
+        source: Some(CHAT_SOURCE_BACKEND.to_string()),
+        expert_name: None,
+        attachment_id: None,
+        created_at: Some(Utc::now()),
+    };
+    ChatMessage::insert(rb, &chat_message).await?;
+    Ok(true)
+}
+
+/// 經由背景工作佇列請教練傳回顧訊息（不阻塞取消流程）；佇列已滿時回傳 false
+pub fn spawn_reflection(rb: RBatis, ai: SharedAIService, task: Task, cancel_count: i32, reason: String) -> bool {
+    crate::background_jobs::submit("task_cancellation_reflection", async move {
+        match send_reflection(&rb, &ai, &task, cancel_count, &reason).await {
+            Ok(true) => log::info!("已請教練回顧任務 {} 的取消（第 {} 次）", task.id.as_deref().unwrap_or_default(), cancel_count),
+            Ok(false) => {}
+            Err(e) => log::error!("產生任務取消回顧訊息失敗: {}", e),
+        }
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancellationSummaryQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskCancellationStat {
+    pub task_id: String,
+    pub task_title: Option<String>,
+    pub cancellations: i64,
+    pub last_cancelled_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancellationSummary {
+    pub days: i64,
+    pub soft_limit: i32,
+    pub total: usize,
+    pub with_reason: usize,
+    // 期間內取消次數超過上限的任務數
+    pub tasks_over_limit: usize,
+    pub by_task: Vec<TaskCancellationStat>,
+    pub recent: Vec<CancellationLog>,
+}
+
+/// 依任務彙總取消紀錄（紀錄需依時間由新到舊排序）；取消次數多的任務排前面
+pub fn summarize(logs: Vec<CancellationLog>, days: i64) -> CancellationSummary {
+    let mut by_task: Vec<TaskCancellationStat> = Vec::new();
+    for log in &logs {
+        let task_id = log.task_id.clone().unwrap_or_default();
+        match by_task.iter_mut().find(|stat| stat.task_id == task_id) {
+            Some(stat) => stat.cancellations += 1,
+            None => by_task.push(TaskCancellationStat {
+                task_id,
+                task_title: log.task_title.clone(),
+                cancellations: 1,
+                last_cancelled_at: log.created_at.map(|t| t.to_rfc3339()),
+            }),
+        }
+    }
+    // 穩定排序：次數相同時保留最近取消的在前
+    by_task.sort_by_key(|stat| std::cmp::Reverse(stat.cancellations));
+
+    let limit = i64::from(soft_limit());
+    CancellationSummary {
+        days,
+        soft_limit: soft_limit(),
+        total: logs.len(),
+        with_reason: logs.iter().filter(|log| log.reason.is_some()).count(),
+        tasks_over_limit: by_task.iter().filter(|stat| stat.cancellations > limit).count(),
+        by_task,
+        recent: logs.into_iter().take(RECENT_LIMIT).collect(),
+    }
+}
+
+/// 使用者的任務取消統計
+/// GET /api/users/{id}/cancellations?days=90
+pub async fn get_cancellation_summary(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<CancellationSummaryQuery>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let days = query.days.unwrap_or(DEFAULT_SUMMARY_DAYS);
+    if !(1..=MAX_SUMMARY_DAYS).contains(&days) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("days 必須介於 1 到 {} 之間", MAX_SUMMARY_DAYS),
+        }));
+    }
+
+    let since = (Utc::now() - Duration::days(days)).to_rfc3339();
+    let logs: Vec<CancellationLog> = match rb
+        .query_decode(
+            "SELECT * FROM cancellation_log WHERE user_id = ? AND created_at >= ? ORDER BY created_at DESC",
+            vec![Value::String(user_id), Value::String(since)],
+        )
+        .await
+    {
+        Ok(logs) => logs,
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢取消紀錄失敗: {}", e),
+            }));
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(summarize(logs, days)),
+        message: "獲取任務取消統計成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::test::TestRequest;
+    use rbs::value;
+    use serde_json::json;
+
+    use crate::models::ChatMessage;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+
+    #[test]
+    fn test_reason_rules() {
+        // 預設上限 2：第三次取消開始需要原因，且只有第三次請教練關心
+        assert!(!super::reason_required(2));
+        assert!(super::reason_required(3));
+        assert!(super::crosses_limit(3));
+        assert!(!super::crosses_limit(4));
+        assert_eq!(super::normalize_reason(Some("  ")), Ok(None));
+        assert_eq!(super::normalize_reason(Some(" 太累了 ")), Ok(Some("太累了".to_string())));
+        assert!(super::normalize_reason(Some(&"字".repeat(super::MAX_REASON_CHARS + 1))).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_third_cancellation_requires_reason_and_asks_coach() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["這個任務好像一直卡住，要不要一起把它拆小一點？"]);
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "canceller").await;
+
+        let req = TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "整理車庫", "task_type": "side", "experience": 20}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 201, "{}", body);
+        let task_id = body["data"]["id"].as_str().unwrap().to_string();
+
+        let cancel = |reason: Option<&str>| {
+            let req = TestRequest::put().uri(&format!("/api/tasks/{}/cancel", task_id)).insert_header(user.auth());
+            match reason {
+                Some(reason) => req.set_json(json!({"reason": reason})),
+                None => req,
+            }
+            .to_request()
+        };
+
+        // 前兩次不需要原因
+        for count in 1..=2 {
+            let (status, body) = call_json(&app, cancel(None)).await;
+            assert_eq!(status, 200, "{}", body);
+            assert_eq!(body["data"]["cancel_count"], count);
+            rb.exec("UPDATE task SET status = 0 WHERE id = ?", vec![rbs::Value::String(task_id.clone())]).await.unwrap();
+        }
+
+        // 第三次沒有原因時拒絕，任務維持原狀
+        let (status, body) = call_json(&app, cancel(None)).await;
+        assert_eq!(status, 422, "{}", body);
+        assert_eq!(body["data"]["reason_required"], true);
+        assert_eq!(body["data"]["cancel_count"], 2);
+
+        let (status, body) = call_json(&app, cancel(Some("最近加班沒時間"))).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["cancel_count"], 3);
+        assert_eq!(body["data"]["coach_reflection"], true);
+
+        // 教練訊息由背景工作產生
+        let rb_ref = &rb;
+        let user_id = user.id.clone();
+        assert!(
+            test_utils::wait_until(|| {
+                let user_id = user_id.clone();
+                async move {
+                    let messages: Vec<ChatMessage> = ChatMessage::select_by_map(rb_ref, value!{"user_id": user_id}).await.unwrap();
+                    !messages.is_empty()
+                }
+            })
+            .await
+        );
+        let prompts = mock.prompts("generate_with_model");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("第 3 次取消任務「整理車庫」"), "{}", prompts[0]);
+        assert!(prompts[0].contains("最近加班沒時間"));
+
+        // 任務詳情與列表都帶取消次數與時間
+        let req = TestRequest::get().uri(&format!("/api/tasks/{}", task_id)).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["cancel_count"], 3);
+        assert!(body["data"]["last_cancelled_at"].is_string());
+        let req = TestRequest::get().uri(&format!("/api/tasks?user_id={}", user.id)).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"][0]["cancel_count"], 3);
+        assert!(body["data"][0]["last_cancelled_at"].is_string());
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/users/{}/cancellations", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let data = &body["data"];
+        assert_eq!(data["total"], 3);
+        assert_eq!(data["with_reason"], 1);
+        assert_eq!(data["tasks_over_limit"], 1);
+        assert_eq!(data["by_task"][0]["task_title"], "整理車庫");
+        assert_eq!(data["by_task"][0]["cancellations"], 3);
+        assert_eq!(data["recent"][0]["reason"], "最近加班沒時間");
+
+        let other = test_utils::create_user(&app, "cancel_stranger").await;
+        let req = TestRequest::get()
+            .uri(&format!("/api/users/{}/cancellations", user.id))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+}
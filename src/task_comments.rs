@@ -0,0 +1,462 @@
+// 任務留言串：使用者針對特定任務留言，並可請 AI 教練依任務內容與留言回覆
+//
+// 留言最多兩層（留言與其回覆）；教練回覆以 author = coach 儲存，
+// 回覆時依使用者設定的教練個性產生內容。
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+use serde::Deserialize;
+
+use crate::ai_service::SharedAIService;
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Task, TaskComment, TaskStatus};
+
+pub const AUTHOR_USER: &str = "user";
+pub const AUTHOR_COACH: &str = "coach";
+
+// 留言串最大深度（1 = 留言，2 = 回覆）
+const MAX_THREAD_DEPTH: usize = 2;
+const MAX_CONTENT_CHARS: usize = 2000;
+// 提示詞中最多帶入的留言數（取最新的）
+const MAX_PROMPT_COMMENTS: usize = 20;
+// 提示詞中最多列出的子任務數
+const MAX_PROMPT_SUBTASKS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentRequest {
+    pub content: String,
+    pub parent_comment_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AskCoachRequest {
+    // 可選：先以使用者身分留下問題，教練回覆在同一串
+    pub content: Option<String>,
+    // 可選：要教練回覆的留言串
+    pub parent_comment_id: Option<String>,
+}
+
+fn error_response(status: actix_web::http::StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// 任務的留言數（get_task 回應使用）
+pub async fn comment_count(rb: &RBatis, task_id: &str) -> Result<i64, rbatis::Error> {
+    let count: Option<i64> = rb
+        .query_decode(
+            "SELECT COUNT(*) AS count FROM task_comment WHERE task_id = ?",
+            vec![rbs::Value::String(task_id.to_string())],
+        )
+        .await?;
+    Ok(count.unwrap_or(0))
+}
+
+/// 刪除任務的所有留言
+pub async fn delete_task_comments(rb: &RBatis, task_id: &str) -> Result<u64, rbatis::Error> {
+    let result = TaskComment::delete_by_map(rb, value!{"task_id": task_id}).await?;
+    Ok(result.rows_affected)
+}
+
+/// 重置使用者時清除留言：使用者的留言，以及其任務上由其他參與者留下的留言
+pub async fn purge_user_comments(rb: &RBatis, user_id: &str) -> Result<i32, rbatis::Error> {
+    let result = rb
+        .exec(
+            "DELETE FROM task_comment WHERE user_id = ? OR task_id IN (SELECT id FROM task WHERE user_id = ?)",
+            vec![rbs::Value::String(user_id.to_string()), rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(result.rows_affected as i32)
+}
+
+async fn task_comments(rb: &RBatis, task_id: &str) -> Result<Vec<TaskComment>, rbatis::Error> {
+    let mut rows = TaskComment::select_by_map(rb, value!{"task_id": task_id}).await?;
+    rows.sort_by_key(|c| c.created_at);
+    Ok(rows)
+}
+
+/// 留言所在的深度（1 = 最上層）；找不到上層留言時回傳 None
+pub fn thread_depth(comments: &[TaskComment], comment_id: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut current = Some(comment_id.to_string());
+    while let Some(id) = current {
+        let comment = comments.iter().find(|c| c.id.as_deref() == Some(id.as_str()))?;
+        depth += 1;
+        if depth > comments.len() {
+            return None; // 資料異常（循環引用）
+        }
+        current = comment.parent_comment_id.clone();
+    }
+    Some(depth)
+}
+
+// 檢查回覆的上層留言存在且深度未達上限
+fn validate_parent(comments: &[TaskComment], parent_comment_id: &str) -> std::result::Result<(), HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    match thread_depth(comments, parent_comment_id) {
+        None => Err(error_response(StatusCode::NOT_FOUND, "回覆的留言不存在")),
+        Some(depth) if depth >= MAX_THREAD_DEPTH => Err(error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("留言串最多 {} 層，請回覆上一層留言", MAX_THREAD_DEPTH),
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
+fn validate_content(content: &str) -> std::result::Result<String, HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(error_response(StatusCode::BAD_REQUEST, "留言內容不可為空"));
+    }
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("留言內容不可超過 {} 字", MAX_CONTENT_CHARS),
+        ));
+    }
+    Ok(content.to_string())
+}
+
+async fn insert_comment(
+    rb: &RBatis,
+    task_id: &str,
+    user_id: &str,
+    author: &str,
+    content: String,
+    parent_comment_id: Option<String>,
+) -> Result<TaskComment, rbatis::Error> {
+    let comment = TaskComment {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        task_id: Some(task_id.to_string()),
+        user_id: Some(user_id.to_string()),
+        author: Some(author.to_string()),
+        content: Some(content),
+        parent_comment_id,
+        created_at: Some(Utc::now()),
+    };
+    TaskComment::insert(rb, &comment).await?;
+    Ok(comment)
+}
+
+/// 組合教練回覆的提示詞：任務內容、子任務進度與留言串（由舊到新）
+pub fn build_coach_prompt(system_prompt: &str, task: &Task, subtasks: &[Task], thread: &[TaskComment]) -> String {
+    let mut prompt = format!("{}\n\n使用者正在進行以下任務，請針對這個任務給予回饋。\n", system_prompt);
+    prompt.push_str(&format!("任務：{}\n", task.title.as_deref().unwrap_or("未命名任務")));
+    if let Some(description) = task.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        prompt.push_str(&format!("描述：{}\n", description));
+    }
+    let status = task.status.and_then(TaskStatus::from_i32).map(|s| s.to_string()).unwrap_or("pending");
+    prompt.push_str(&format!("狀態：{}\n", status));
+    if let Some(due_date) = task.due_date {
+        prompt.push_str(&format!("截止日期：{}\n", crate::local_date::local_date(due_date).format("%Y-%m-%d")));
+    }
+
+    if !subtasks.is_empty() {
+        let completed = subtasks
+            .iter()
+            .filter(|s| matches!(s.status.and_then(TaskStatus::from_i32), Some(TaskStatus::Completed | TaskStatus::DailyCompleted)))
+            .count();
+        prompt.push_str(&format!("子任務進度：{}/{}\n", completed, subtasks.len()));
+        for subtask in subtasks.iter().take(MAX_PROMPT_SUBTASKS) {
+            let status = subtask.status.and_then(TaskStatus::from_i32).map(|s| s.to_string()).unwrap_or("pending");
+            prompt.push_str(&format!("- {}（{}）\n", subtask.title.as_deref().unwrap_or("未命名"), status));
+        }
+    }
+
+    let start = thread.len().saturating_sub(MAX_PROMPT_COMMENTS);
+    if !thread[start..].is_empty() {
+        prompt.push_str("\n留言串：\n");
+        for comment in &thread[start..] {
+            let speaker = if comment.author.as_deref() == Some(AUTHOR_COACH) { "教練" } else { "使用者" };
+            prompt.push_str(&format!("{}：{}\n", speaker, comment.content.as_deref().unwrap_or_default()));
+        }
+    }
+    prompt.push_str(&format!("\n請以教練身分回覆，內容具體並與此任務相關，不超過 300 字。{}", crate::language::directive()));
+    prompt
+}
+
+/// 列出任務留言（依時間排序）
+pub async fn list_comments(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        return Ok(e.into_response());
+    }
+    match task_comments(rb.get_ref(), &task_id).await {
+        Ok(comments) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(comments),
+            message: "獲取留言成功".to_string(),
+        })),
+        Err(e) => Ok(error_response(
+            actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("獲取留言失敗: {}", e),
+        )),
+    }
+}
+
+/// 新增任務留言（可回覆上一層留言）
+pub async fn create_comment(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<CreateCommentRequest>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let task_id = path.into_inner();
+    let caller = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(caller) => caller,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if let Err(e) = crate::ownership::assert_task_access(rb.get_ref(), &task_id, Some(&caller)).await {
+        return Ok(e.into_response());
+    }
+    let body = body.into_inner();
+    let content = match validate_content(&body.content) {
+        Ok(content) => content,
+        Err(response) => return Ok(response),
+    };
+    if let Some(parent_id) = &body.parent_comment_id {
+        let comments = match task_comments(rb.get_ref(), &task_id).await {
+            Ok(comments) => comments,
+            Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢留言失敗: {}", e))),
+        };
+        if let Err(response) = validate_parent(&comments, parent_id) {
+            return Ok(response);
+        }
+    }
+
+    match insert_comment(rb.get_ref(), &task_id, &caller, AUTHOR_USER, content, body.parent_comment_id).await {
+        Ok(comment) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(comment),
+            message: "留言成功".to_string(),
+        })),
+        Err(e) => Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("留言失敗: {}", e))),
+    }
+}
+
+/// 請 AI 教練依任務內容與留言串回覆，回覆存為 coach 留言
+///
+/// 有 content 時先存成使用者留言，教練回覆在同一串；回覆位置超過深度上限時掛在該串的最上層留言下。
+pub async fn ask_coach(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<SharedAIService>,
+    path: web::Path<String>,
+    body: Option<web::Json<AskCoachRequest>>,
+) -> Result<HttpResponse> {
+    use actix_web::http::StatusCode;
+
+    let task_id = path.into_inner();
+    let caller = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(caller) => caller,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let task = match crate::ownership::assert_task_access(rb.get_ref(), &task_id, Some(&caller)).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let body = body.map(|b| b.into_inner()).unwrap_or_default();
+    let mut comments = match task_comments(rb.get_ref(), &task_id).await {
+        Ok(comments) => comments,
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢留言失敗: {}", e))),
+    };
+    if let Some(parent_id) = &body.parent_comment_id {
+        // 只帶問題時需檢查深度；只指定留言串時教練回覆會掛在最上層留言下
+        let check = match &body.content {
+            Some(_) => validate_parent(&comments, parent_id),
+            None => thread_depth(&comments, parent_id)
+                .map(|_| ())
+                .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "回覆的留言不存在")),
+        };
+        if let Err(response) = check {
+            return Ok(response);
+        }
+    }
+
+    // 教練回覆的留言串（anchor 為最後一則相關留言）
+    let mut anchor = body.parent_comment_id.clone();
+    if let Some(content) = &body.content {
+        let content = match validate_content(content) {
+            Ok(content) => content,
+            Err(response) => return Ok(response),
+        };
+        match insert_comment(rb.get_ref(), &task_id, &caller, AUTHOR_USER, content, body.parent_comment_id.clone()).await {
+            Ok(comment) => {
+                anchor = comment.id.clone();
+                comments.push(comment);
+            }
+            Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("留言失敗: {}", e))),
+        }
+    }
+    let root_id = anchor.as_ref().and_then(|id| {
+        comments
+            .iter()
+            .find(|c| c.id.as_ref() == Some(id))
+            .map(|c| c.parent_comment_id.clone().unwrap_or_else(|| id.clone()))
+    });
+    // 指定留言串時只帶入該串，否則帶入整個任務的留言
+    let thread: Vec<TaskComment> = match &root_id {
+        Some(root) => comments
+            .iter()
+            .filter(|c| c.id.as_ref() == Some(root) || c.parent_comment_id.as_ref() == Some(root))
+            .cloned()
+            .collect(),
+        None => comments.clone(),
+    };
+
+    let subtasks = if task.is_parent_task == Some(1) {
+        Task::select_by_map(rb.get_ref(), value!{"parent_task_id": &task_id}).await.unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let personality = crate::routes::coach::get_user_personality_type(rb.get_ref(), Some(caller.clone()))
+        .await
+        .unwrap_or(crate::models::CoachPersonalityType::EmotionalSupport);
+    let prompt = build_coach_prompt(personality.system_prompt(), &task, &subtasks, &thread);
+
+    let ai_service = match ai.get() {
+        Ok(service) => service,
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("AI 服務初始化失敗: {}", e))),
+    };
+    let reply = match ai_service.generate_task_preview(&prompt).await {
+        Ok(reply) => reply,
+        Err(e) => return Ok(crate::ai_tasks::ai_failure_response("教練回覆失敗", &e)),
+    };
+
+    match insert_comment(rb.get_ref(), &task_id, &caller, AUTHOR_COACH, reply.trim().to_string(), root_id).await {
+        Ok(comment) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(comment),
+            message: format!("{}已回覆", personality.display_name()),
+        })),
+        Err(e) => Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("儲存教練回覆失敗: {}", e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json, mock_ai::MockAIService};
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn comment(id: &str, parent: Option<&str>) -> TaskComment {
+        TaskComment {
+            id: Some(id.to_string()),
+            task_id: Some("t1".to_string()),
+            user_id: Some("u1".to_string()),
+            author: Some(AUTHOR_USER.to_string()),
+            content: Some(id.to_string()),
+            parent_comment_id: parent.map(str::to_string),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_thread_depth() {
+        let comments = vec![comment("a", None), comment("b", Some("a")), comment("c", Some("missing"))];
+        assert_eq!(thread_depth(&comments, "a"), Some(1));
+        assert_eq!(thread_depth(&comments, "b"), Some(2));
+        assert_eq!(thread_depth(&comments, "c"), None);
+        assert_eq!(thread_depth(&comments, "x"), None);
+        assert!(validate_parent(&comments, "a").is_ok());
+        assert_eq!(validate_parent(&comments, "b").unwrap_err().status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[actix_web::test]
+    async fn test_comment_thread_and_ask_coach() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&["先把第一章讀完，再安排固定閱讀時段。"]);
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "commenter").await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tasks")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "title": "閱讀一本書", "task_type": "main"}))
+            .to_request();
+        let (_, created) = call_json(&app, req).await;
+        let task_id = created["data"]["id"].as_str().unwrap().to_string();
+        let comments_uri = format!("/api/tasks/{}/comments", task_id);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&comments_uri)
+            .insert_header(user.auth())
+            .set_json(json!({"content": "第一章好難讀"}))
+            .to_request();
+        let (status, top) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let top_id = top["data"]["id"].as_str().unwrap().to_string();
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&comments_uri)
+            .insert_header(user.auth())
+            .set_json(json!({"content": "補充：每天只有半小時", "parent_comment_id": top_id}))
+            .to_request();
+        let (status, reply) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // 第三層被拒絕
+        let req = actix_web::test::TestRequest::post()
+            .uri(&comments_uri)
+            .insert_header(user.auth())
+            .set_json(json!({"content": "再回覆", "parent_comment_id": reply["data"]["id"]}))
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // 指定第二層留言時，教練回覆掛在最上層留言下
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/ask-coach", comments_uri))
+            .insert_header(user.auth())
+            .set_json(json!({"parent_comment_id": reply["data"]["id"]}))
+            .to_request();
+        let (status, coach) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(coach["data"]["author"], AUTHOR_COACH);
+        assert_eq!(coach["data"]["parent_comment_id"], top_id.as_str());
+        assert_eq!(coach["data"]["content"], "先把第一章讀完，再安排固定閱讀時段。");
+
+        let prompts = mock.prompts("generate_task_preview");
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("任務：閱讀一本書"));
+        assert!(prompts[0].contains("使用者：第一章好難讀"));
+        assert!(prompts[0].contains("使用者：補充：每天只有半小時"));
+
+        let req = actix_web::test::TestRequest::get().uri(&comments_uri).insert_header(user.auth()).to_request();
+        let (_, list) = call_json(&app, req).await;
+        assert_eq!(list["data"].as_array().unwrap().len(), 3);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks/{}", task_id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, task) = call_json(&app, req).await;
+        assert_eq!(task["data"]["comment_count"], 3);
+
+        // 其他使用者無法留言
+        let other = test_utils::create_user(&app, "outsider").await;
+        let req = actix_web::test::TestRequest::post()
+            .uri(&comments_uri)
+            .insert_header(other.auth())
+            .set_json(json!({"content": "路過"}))
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,130 @@
+// 任務完成二次確認：設定 requires_confirmation 的任務標記完成後先進入待確認（PendingConfirmation），
+//
+// 使用者確認（POST /api/tasks/{id}/confirm-completion，或更新時帶 confirm=true）後才真正完成，
+// 經驗值、屬性、成就與主線進度都在那時才處理。待確認超過設定的時間未確認時，
+// 自動還原為標記完成前的狀態。
+
+use std::sync::OnceLock;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use rbatis::RBatis;
+use rbs::Value;
+
+use crate::config::TaskConfirmationConfig;
+use crate::models::{Task, TaskStatus};
+
+const SWEEP_INTERVAL_SECS: u64 = 300;
+
+static TASK_CONFIRMATION_CONFIG: OnceLock<TaskConfirmationConfig> = OnceLock::new();
+
+/// 啟動時套用設定
+pub fn init(config: TaskConfirmationConfig) {
+    log::info!("任務完成確認: 待確認 {} 分鐘後自動還原", config.window_minutes);
+    if TASK_CONFIRMATION_CONFIG.set(config).is_err() {
+        log::warn!("任務完成確認設定已初始化，忽略重複設定");
+    }
+}
+
+fn config() -> &'static TaskConfirmationConfig {
+    TASK_CONFIRMATION_CONFIG.get_or_init(TaskConfirmationConfig::default)
+}
+
+pub fn is_pending(status: Option<i32>) -> bool {
+    status == Some(TaskStatus::PendingConfirmation.to_i32())
+}
+
+/// 記錄待確認；重複標記完成時保留原本的前一狀態與開始時間
+pub async fn record_pending(
+    rb: &RBatis,
+    task: &Task,
+    previous_status: Option<i32>,
+    target_status: i32,
+) -> Result<(), rbatis::Error> {
+    rb.exec(
+        "INSERT INTO task_pending_confirmation (task_id, user_id, previous_status, target_status, requested_at)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(task_id) DO UPDATE SET target_status = excluded.target_status",
+        vec![
+            Value::String(task.id.clone().unwrap_or_default()),
+            task.user_id.clone().map(Value::String).unwrap_or(Value::Null),
+            Value::I32(previous_status.unwrap_or(TaskStatus::Pending.to_i32())),
+            Value::I32(target_status),
+            Value::String(Utc::now().to_rfc3339()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// 任務離開待確認狀態後移除紀錄
+pub async fn clear_pending(rb: &RBatis, task_id: &str) -> Result<(), rbatis::Error> {
+    rb.exec(
+        "DELETE FROM task_pending_confirmation WHERE task_id = ?",
+        vec![Value::String(task_id.to_string())],
+    )
+    .await?;
+    Ok(())
+}
+
+/// 確認後要套用的完成狀態（沒有紀錄時視為一般完成）
+pub async fn target_status(rb: &RBatis, task_id: &str) -> Result<i32, rbatis::Error> {
+    let target: Option<i32> = rb
+        .query_decode(
+            "SELECT target_status FROM task_pending_confirmation WHERE task_id = ?",
+            vec![Value::String(task_id.to_string())],
+        )
+        .await?;
+    Ok(target.unwrap_or(TaskStatus::Completed.to_i32()))
+}
+
+/// 還原超過確認期限的任務，回傳還原筆數
+pub async fn revert_expired(rb: &RBatis, window: Duration) -> Result<u64, rbatis::Error> {
+    let cutoff = (Utc::now() - window).to_rfc3339();
+    let expired: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT task_id, previous_status FROM task_pending_confirmation WHERE julianday(requested_at) <= julianday(?)",
+            vec![Value::String(cutoff)],
+        )
+        .await?;
+
+    let mut reverted = 0;
+    for row in expired {
+        let Some(task_id) = row["task_id"].as_str() else { continue };
+        let previous_status = row["previous_status"]
+            .as_i64()
+            .map(|s| s as i32)
+            .unwrap_or(TaskStatus::Pending.to_i32());
+        // 只還原仍在待確認的任務（期間可能已被確認或改為其他狀態）
+        let result = rb
+            .exec(
+                "UPDATE task SET status = ?, version = COALESCE(version, 0) + 1, updated_at = ?
+                 WHERE id = ? AND status = ?",
+                vec![
+                    Value::I32(previous_status),
+                    Value::String(Utc::now().to_rfc3339()),
+                    Value::String(task_id.to_string()),
+                    Value::I32(TaskStatus::PendingConfirmation.to_i32()),
+                ],
+            )
+            .await?;
+        reverted += result.rows_affected;
+        clear_pending(rb, task_id).await?;
+    }
+    Ok(reverted)
+}
+
+/// 定期還原逾時未確認的任務
+pub fn spawn_scheduler(rb: RBatis) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(SWEEP_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match revert_expired(&rb, Duration::minutes(config().window_minutes)).await {
+                Ok(0) => {}
+                Ok(reverted) => log::info!("已還原 {} 個逾時未確認完成的任務", reverted),
+                Err(e) => log::error!("還原逾時未確認任務失敗: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,271 @@
+// 任務列表篩選：GET /api/tasks 與 /api/tasks/type/{task_type} 的查詢參數
+//
+// 篩選條件一律以參數綁定組成 WHERE 子句，條件之間以 AND 結合。
+// 日期可用 RFC3339 或 YYYY-MM-DD（視為使用者時區當天 00:00）；due_after 含邊界、due_before 不含。
+// tags 以逗號分隔標籤名稱（不分大小寫），tags_match=any（預設）符合任一、all 須全部符合。
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::models::TaskStatus;
+
+/// 已套用的篩選條件（同時回傳給前端以便除錯）
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct TaskListFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Vec<i32>>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "crate::datetime_format::rfc3339_option::serialize")]
+    pub due_before: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "crate::datetime_format::rfc3339_option::serialize")]
+    pub due_after: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_due_date: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub career_mainline_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_recurring: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags_match: Option<TagMatch>,
+}
+
+/// 多個標籤的比對方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    Any,
+    All,
+}
+
+impl TaskListFilters {
+    /// 解析查詢參數；格式錯誤或未知的狀態名稱回傳錯誤訊息
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let param = |name: &str| query.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+        Ok(TaskListFilters {
+            status: param("status").map(parse_statuses).transpose()?,
+            due_before: param("due_before").map(|v| parse_datetime("due_before", v)).transpose()?,
+            due_after: param("due_after").map(|v| parse_datetime("due_after", v)).transpose()?,
+            has_due_date: param("has_due_date").map(|v| parse_bool("has_due_date", v)).transpose()?,
+            career_mainline_id: param("career_mainline_id").map(str::to_string),
+            is_recurring: param("is_recurring").map(|v| parse_bool("is_recurring", v)).transpose()?,
+            tags: param("tags").map(parse_tags).transpose()?,
+            tags_match: match param("tags") {
+                Some(_) => Some(param("tags_match").map(parse_tag_match).transpose()?.unwrap_or(TagMatch::Any)),
+                None => None,
+            },
+        })
+    }
+
+    /// 將篩選條件附加到 SQL（呼叫端的 SQL 需已有 WHERE 子句）
+    pub fn apply(&self, sql: &mut String, args: &mut Vec<rbs::Value>) {
+        if let Some(statuses) = &self.status {
+            let placeholders = vec!["?"; statuses.len()].join(", ");
+            sql.push_str(&format!(" AND status IN ({})", placeholders));
+            args.extend(statuses.iter().map(|s| rbs::Value::I32(*s)));
+        }
+        if let Some(before) = self.due_before {
+            sql.push_str(" AND julianday(due_date) < julianday(?)");
+            args.push(rbs::Value::String(before.to_rfc3339()));
+        }
+        if let Some(after) = self.due_after {
+            sql.push_str(" AND julianday(due_date) >= julianday(?)");
+            args.push(rbs::Value::String(after.to_rfc3339()));
+        }
+        match self.has_due_date {
+            Some(true) => sql.push_str(" AND due_date IS NOT NULL AND due_date <> ''"),
+            Some(false) => sql.push_str(" AND (due_date IS NULL OR due_date = '')"),
+            None => {}
+        }
+        if let Some(mainline_id) = &self.career_mainline_id {
+            sql.push_str(" AND career_mainline_id = ?");
+            args.push(rbs::Value::String(mainline_id.clone()));
+        }
+        if let Some(recurring) = self.is_recurring {
+            sql.push_str(" AND COALESCE(is_recurring, 0) = ?");
+            args.push(rbs::Value::I32(recurring as i32));
+        }
+        if let Some(tags) = &self.tags {
+            // 只比對任務擁有者的標籤；tag.name 為 NOCASE，比對不分大小寫
+            let placeholders = vec!["?"; tags.len()].join(", ");
+            let matched = format!(
+                "SELECT COUNT(DISTINCT tg.id) FROM task_tag tt JOIN tag tg ON tg.id = tt.tag_id \
+                 WHERE tt.task_id = task.id AND tg.user_id = task.user_id AND tg.name IN ({})",
+                placeholders
+            );
+            match self.tags_match.unwrap_or(TagMatch::Any) {
+                TagMatch::Any => sql.push_str(&format!(" AND ({}) > 0", matched)),
+                TagMatch::All => sql.push_str(&format!(" AND ({}) = {}", matched, tags.len())),
+            }
+            args.extend(tags.iter().map(|tag| rbs::Value::String(tag.clone())));
+        }
+    }
+}
+
+fn parse_tags(value: &str) -> Result<Vec<String>, String> {
+    let mut tags: Vec<String> = Vec::new();
+    for item in value.split(',').map(|s| s.trim().trim_start_matches('#')).filter(|s| !s.is_empty()) {
+        if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(item)) {
+            tags.push(item.to_string());
+        }
+    }
+    if tags.is_empty() {
+        return Err("tags 不可為空".to_string());
+    }
+    Ok(tags)
+}
+
+fn parse_tag_match(value: &str) -> Result<TagMatch, String> {
+    match value.to_lowercase().as_str() {
+        "any" => Ok(TagMatch::Any),
+        "all" => Ok(TagMatch::All),
+        _ => Err("tags_match 只能是 any 或 all".to_string()),
+    }
+}
+
+fn parse_statuses(value: &str) -> Result<Vec<i32>, String> {
+    let mut statuses = Vec::new();
+    for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let status = match item.parse::<i32>() {
+            Ok(number) => TaskStatus::from_i32(number),
+            Err(_) => TaskStatus::from_string(&item.to_lowercase()),
+        }
+        .ok_or_else(|| format!("未知的任務狀態: {}", item))?;
+        if !statuses.contains(&status.to_i32()) {
+            statuses.push(status.to_i32());
+        }
+    }
+    if statuses.is_empty() {
+        return Err("status 不可為空".to_string());
+    }
+    Ok(statuses)
+}
+
+fn parse_datetime(name: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| crate::local_date::default_timezone().from_local_datetime(&date.and_hms_opt(0, 0, 0)?).single())
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| format!("{} 日期格式錯誤，請使用 RFC3339 或 YYYY-MM-DD", name))
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("{} 只能是 true 或 false", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        let filters = TaskListFilters::from_query(&query(&[
+            ("status", "pending, 1,in_progress"),
+            ("due_before", "2026-03-08"),
+            ("has_due_date", "true"),
+            ("is_recurring", "0"),
+        ]))
+        .unwrap();
+        assert_eq!(filters.status, Some(vec![0, 1]));
+        // 日期視為台灣時間當天 00:00
+        assert_eq!(filters.due_before, Some(Utc.with_ymd_and_hms(2026, 3, 7, 16, 0, 0).unwrap()));
+        assert_eq!(filters.has_due_date, Some(true));
+        assert_eq!(filters.is_recurring, Some(false));
+        assert_eq!(filters.career_mainline_id, None);
+
+        let mut sql = String::from("SELECT * FROM task WHERE user_id = ?");
+        let mut args = vec![rbs::Value::String("u1".to_string())];
+        filters.apply(&mut sql, &mut args);
+        assert_eq!(
+            sql,
+            "SELECT * FROM task WHERE user_id = ? AND status IN (?, ?) AND julianday(due_date) < julianday(?) \
+             AND due_date IS NOT NULL AND due_date <> '' AND COALESCE(is_recurring, 0) = ?"
+        );
+        assert_eq!(args.len(), 5);
+    }
+
+    #[test]
+    fn test_invalid_filters_rejected() {
+        assert_eq!(
+            TaskListFilters::from_query(&query(&[("status", "pending,done")])).unwrap_err(),
+            "未知的任務狀態: done"
+        );
+        assert!(TaskListFilters::from_query(&query(&[("status", "10")])).is_err());
+        assert!(TaskListFilters::from_query(&query(&[("due_after", "next week")])).is_err());
+        assert!(TaskListFilters::from_query(&query(&[("has_due_date", "maybe")])).is_err());
+        assert!(TaskListFilters::from_query(&query(&[("tags", "work"), ("tags_match", "some")])).is_err());
+        assert_eq!(
+            TaskListFilters::from_query(&query(&[("tags", "#work, WORK,health")])).unwrap().tags,
+            Some(vec!["work".to_string(), "health".to_string()])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_get_tasks_with_filters() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "task_filters").await;
+
+        for (title, task_type, status, due_date) in [
+            ("本週主線", "main", 0, Some("2026-03-04T10:00:00Z")),
+            ("已完成主線", "main", 2, Some("2026-03-04T10:00:00Z")),
+            ("下週主線", "main", 1, Some("2026-03-12T10:00:00Z")),
+            ("沒期限主線", "main", 0, None),
+            ("本週支線", "side", 0, Some("2026-03-05T10:00:00Z")),
+        ] {
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, task_type, status, due_date, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(user.id.clone()),
+                    rbs::Value::String(title.to_string()),
+                    rbs::Value::String(task_type.to_string()),
+                    rbs::Value::I32(status),
+                    due_date.map(|d| rbs::Value::String(d.to_string())).unwrap_or(rbs::Value::Null),
+                    rbs::Value::String(Utc::now().to_rfc3339()),
+                ],
+            )
+            .await
+            .unwrap();
+        }
+
+        let uri = format!(
+            "/api/tasks/type/main?user_id={}&status=pending,in_progress&due_after=2026-03-02&due_before=2026-03-09",
+            user.id
+        );
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        let titles: Vec<&str> = body["data"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+        assert_eq!(titles, vec!["本週主線"]);
+        assert_eq!(body["filters"]["status"], serde_json::json!([0, 1]));
+
+        let uri = format!("/api/tasks?user_id={}&has_due_date=false", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        let titles: Vec<&str> = body["data"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+        assert_eq!(titles, vec!["沒期限主線"]);
+        assert_eq!(body["filters"], serde_json::json!({"has_due_date": false}));
+
+        let uri = format!("/api/tasks?user_id={}&status=archived", user.id);
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["message"], "未知的任務狀態: archived");
+    }
+}
@@ -0,0 +1,521 @@
+// 任務標籤：與 skill_tags（訓練哪些技能）分開的自訂分類，例如 #work、#health、#q4-goal
+//
+// 標籤名稱在同一使用者內不分大小寫唯一；只有任務擁有者能為任務加上自己的標籤。
+// 刪除標籤只移除標籤與任務的關聯，不會刪除任務。
+
+use std::collections::HashMap;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+
+const NAME_MAX_CHARS: usize = 32;
+const MAX_TAGS_PER_USER: i64 = 200;
+pub const DEFAULT_COLOR: &str = "#6b7280";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    // 使用此標籤的任務數（GET /api/tags 篩選介面使用）
+    #[serde(default)]
+    pub usage_count: i64,
+    pub created_at: Option<String>,
+}
+
+/// 任務上的標籤（任務列表回應使用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskTag {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTagRequest {
+    pub name: String,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTagRequest {
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+/// 正規化標籤名稱：去除前後空白與開頭的 #；不可含逗號（篩選參數以逗號分隔）
+pub fn normalize_name(name: &str) -> std::result::Result<String, String> {
+    let name = name.trim().trim_start_matches('#').trim();
+    if name.is_empty() || name.chars().count() > NAME_MAX_CHARS {
+        return Err(format!("標籤名稱需為 1～{} 個字", NAME_MAX_CHARS));
+    }
+    if name.contains(',') {
+        return Err("標籤名稱不可包含逗號".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// 顏色需為 #RRGGBB
+pub fn normalize_color(color: &str) -> std::result::Result<String, String> {
+    let color = color.trim();
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err("標籤顏色需為 #RRGGBB 格式".to_string());
+    }
+    Ok(color.to_lowercase())
+}
+
+async fn name_taken(rb: &RBatis, user_id: &str, name: &str, except_id: Option<&str>) -> std::result::Result<bool, rbatis::Error> {
+    let count: i64 = rb
+        .query_decode(
+            "SELECT COUNT(*) FROM tag WHERE user_id = ? AND name = ? AND id <> ?",
+            vec![
+                Value::String(user_id.to_string()),
+                Value::String(name.to_string()),
+                Value::String(except_id.unwrap_or_default().to_string()),
+            ],
+        )
+        .await?;
+    Ok(count > 0)
+}
+
+/// 使用者的所有標籤與使用次數
+pub async fn list_tags(http_req: HttpRequest, rb: web::Data<RBatis>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let tags: std::result::Result<Vec<Tag>, _> = rb
+        .query_decode(
+            "SELECT t.id, t.name, t.color, COUNT(tt.task_id) AS usage_count, t.created_at
+             FROM tag t LEFT JOIN task_tag tt ON tt.tag_id = t.id
+             WHERE t.user_id = ?
+             GROUP BY t.id
+             ORDER BY t.name",
+            vec![Value::String(user_id)],
+        )
+        .await;
+    match tags {
+        Ok(tags) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(tags),
+            message: "獲取標籤列表成功".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("獲取標籤列表失敗: {}", e))),
+    }
+}
+
+pub async fn create_tag(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    body: web::Json<CreateTagRequest>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let name = match normalize_name(&body.name) {
+        Ok(name) => name,
+        Err(message) => return Ok(error(StatusCode::BAD_REQUEST, message)),
+    };
+    let color = match body.color.as_deref().map(normalize_color).transpose() {
+        Ok(color) => color.unwrap_or_else(|| DEFAULT_COLOR.to_string()),
+        Err(message) => return Ok(error(StatusCode::BAD_REQUEST, message)),
+    };
+
+    let existing: std::result::Result<i64, _> = rb
+        .query_decode("SELECT COUNT(*) FROM tag WHERE user_id = ?", vec![Value::String(user_id.clone())])
+        .await;
+    match existing {
+        Ok(count) if count >= MAX_TAGS_PER_USER => {
+            return Ok(error(StatusCode::CONFLICT, format!("最多只能建立 {} 個標籤", MAX_TAGS_PER_USER)))
+        }
+        Ok(_) => {}
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立標籤失敗: {}", e))),
+    }
+    match name_taken(rb.get_ref(), &user_id, &name, None).await {
+        Ok(true) => return Ok(error(StatusCode::CONFLICT, format!("標籤「{}」已存在", name))),
+        Ok(false) => {}
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立標籤失敗: {}", e))),
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let tag = Tag {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        color,
+        usage_count: 0,
+        created_at: Some(now.clone()),
+    };
+    let result = rb
+        .exec(
+            "INSERT INTO tag (id, user_id, name, color, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+            vec![
+                Value::String(tag.id.clone()),
+                Value::String(user_id),
+                Value::String(tag.name.clone()),
+                Value::String(tag.color.clone()),
+                Value::String(now.clone()),
+                Value::String(now),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(tag),
+            message: "標籤建立成功".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("建立標籤失敗: {}", e))),
+    }
+}
+
+pub async fn update_tag(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<UpdateTagRequest>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let mut tag = match crate::ownership::assert_tag_owner(rb.get_ref(), &path.into_inner(), Some(&user_id)).await {
+        Ok(tag) => tag,
+        Err(e) => return Ok(e.into_response()),
+    };
+    if let Some(name) = &body.name {
+        tag.name = match normalize_name(name) {
+            Ok(name) => name,
+            Err(message) => return Ok(error(StatusCode::BAD_REQUEST, message)),
+        };
+        match name_taken(rb.get_ref(), &user_id, &tag.name, Some(&tag.id)).await {
+            Ok(true) => return Ok(error(StatusCode::CONFLICT, format!("標籤「{}」已存在", tag.name))),
+            Ok(false) => {}
+            Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新標籤失敗: {}", e))),
+        }
+    }
+    if let Some(color) = &body.color {
+        tag.color = match normalize_color(color) {
+            Ok(color) => color,
+            Err(message) => return Ok(error(StatusCode::BAD_REQUEST, message)),
+        };
+    }
+
+    let result = rb
+        .exec(
+            "UPDATE tag SET name = ?, color = ?, updated_at = ? WHERE id = ?",
+            vec![
+                Value::String(tag.name.clone()),
+                Value::String(tag.color.clone()),
+                Value::String(Utc::now().to_rfc3339()),
+                Value::String(tag.id.clone()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(tag),
+            message: "標籤更新成功".to_string(),
+        })),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("更新標籤失敗: {}", e))),
+    }
+}
+
+/// 刪除標籤與其所有任務關聯（任務本身保留）
+pub async fn delete_tag(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let tag = match crate::ownership::assert_tag_owner(rb.get_ref(), &path.into_inner(), Some(&user_id)).await {
+        Ok(tag) => tag,
+        Err(e) => return Ok(e.into_response()),
+    };
+
+    let tx = match rb.acquire_begin().await {
+        Ok(tx) => tx,
+        Err(e) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除標籤失敗: {}", e))),
+    };
+    let result: std::result::Result<u64, rbatis::Error> = async {
+        let unassigned = tx
+            .exec("DELETE FROM task_tag WHERE tag_id = ?", vec![Value::String(tag.id.clone())])
+            .await?
+            .rows_affected;
+        tx.exec("DELETE FROM tag WHERE id = ?", vec![Value::String(tag.id.clone())]).await?;
+        Ok(unassigned)
+    }
+    .await;
+    match result {
+        Ok(unassigned) => {
+            if let Err(e) = tx.commit().await {
+                return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除標籤失敗: {}", e)));
+            }
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({ "id": tag.id, "unassigned_tasks": unassigned })),
+                message: format!("標籤「{}」已刪除", tag.name),
+            }))
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除標籤失敗: {}", e)))
+        }
+    }
+}
+
+/// 任務 ID → 標籤（依名稱排序）
+pub async fn tags_for_tasks(rb: &RBatis, task_ids: &[String]) -> std::result::Result<HashMap<String, Vec<TaskTag>>, rbatis::Error> {
+    let mut tags: HashMap<String, Vec<TaskTag>> = HashMap::new();
+    if task_ids.is_empty() {
+        return Ok(tags);
+    }
+    let placeholders = vec!["?"; task_ids.len()].join(", ");
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            &format!(
+                "SELECT tt.task_id, t.id, t.name, t.color FROM task_tag tt JOIN tag t ON t.id = tt.tag_id
+                 WHERE tt.task_id IN ({}) ORDER BY t.name",
+                placeholders
+            ),
+            task_ids.iter().map(|id| Value::String(id.clone())).collect(),
+        )
+        .await?;
+    for row in rows {
+        let Some(task_id) = row["task_id"].as_str().map(str::to_string) else { continue };
+        if let Ok(tag) = serde_json::from_value::<TaskTag>(row) {
+            tags.entry(task_id).or_default().push(tag);
+        }
+    }
+    Ok(tags)
+}
+
+async fn task_tags_response(rb: &RBatis, task_id: &str, message: &str) -> HttpResponse {
+    match tags_for_tasks(rb, &[task_id.to_string()]).await {
+        Ok(mut tags) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(tags.remove(task_id).unwrap_or_default()),
+            message: message.to_string(),
+        }),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢任務標籤失敗: {}", e)),
+    }
+}
+
+pub async fn get_task_tags(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let task_id = path.into_inner();
+    // 只有任務擁有者可以管理任務標籤
+    if let Err(e) = crate::ownership::assert_task_owner(rb.get_ref(), &task_id, Some(&user_id)).await {
+        return Ok(e.into_response());
+    }
+    Ok(task_tags_response(rb.get_ref(), &task_id, "獲取任務標籤成功").await)
+}
+
+/// 為任務加上標籤（重複加上不會出錯）
+pub async fn assign_tag(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let (task_id, tag_id) = path.into_inner();
+    // 只有任務擁有者可以管理任務標籤
+    if let Err(e) = crate::ownership::assert_task_owner(rb.get_ref(), &task_id, Some(&user_id)).await {
+        return Ok(e.into_response());
+    }
+    if let Err(e) = crate::ownership::assert_tag_owner(rb.get_ref(), &tag_id, Some(&user_id)).await {
+        return Ok(e.into_response());
+    }
+    let result = rb
+        .exec(
+            "INSERT OR IGNORE INTO task_tag (task_id, tag_id, created_at) VALUES (?, ?, ?)",
+            vec![
+                Value::String(task_id.clone()),
+                Value::String(tag_id),
+                Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(task_tags_response(rb.get_ref(), &task_id, "已加上標籤").await),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("加上標籤失敗: {}", e))),
+    }
+}
+
+pub async fn unassign_tag(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let user_id = match crate::ownership::assert_logged_in(crate::auth::current_user_id(&http_req).as_deref()) {
+        Ok(user_id) => user_id,
+        Err(e) => return Ok(e.into_response()),
+    };
+    let (task_id, tag_id) = path.into_inner();
+    // 只有任務擁有者可以管理任務標籤
+    if let Err(e) = crate::ownership::assert_task_owner(rb.get_ref(), &task_id, Some(&user_id)).await {
+        return Ok(e.into_response());
+    }
+    let result = rb
+        .exec(
+            "DELETE FROM task_tag WHERE task_id = ? AND tag_id = ?",
+            vec![Value::String(task_id.clone()), Value::String(tag_id)],
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(task_tags_response(rb.get_ref(), &task_id, "已移除標籤").await),
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, format!("移除標籤失敗: {}", e))),
+    }
+}
+
+/// 刪除任務前移除其標籤關聯
+pub async fn delete_task_tags(rb: &RBatis, task_id: &str) -> std::result::Result<u64, rbatis::Error> {
+    let result = rb
+        .exec("DELETE FROM task_tag WHERE task_id = ?", vec![Value::String(task_id.to_string())])
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_name_and_color() {
+        assert_eq!(normalize_name("  #work ").unwrap(), "work");
+        assert!(normalize_name("#").is_err());
+        assert!(normalize_name("a,b").is_err());
+        assert!(normalize_name(&"長".repeat(NAME_MAX_CHARS + 1)).is_err());
+        assert_eq!(normalize_color("#A1B2C3").unwrap(), "#a1b2c3");
+        assert!(normalize_color("red").is_err());
+        assert!(normalize_color("#12345g").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_tag_assignment_filtering_and_deletion() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "tagger").await;
+        let other = test_utils::create_user(&app, "tag-other").await;
+
+        let mut tag_ids = HashMap::new();
+        for name in ["work", "health"] {
+            let req = actix_web::test::TestRequest::post()
+                .uri("/api/tags")
+                .insert_header(user.auth())
+                .set_json(json!({"name": format!("#{}", name), "color": "#22C55E"}))
+                .to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, 201, "{}", body);
+            tag_ids.insert(name, body["data"]["id"].as_str().unwrap().to_string());
+        }
+        // 名稱不分大小寫唯一
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/tags")
+            .insert_header(user.auth())
+            .set_json(json!({"name": "WORK"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 409);
+
+        let mut task_ids = HashMap::new();
+        for title in ["報告", "跑步", "午休"] {
+            let id = uuid::Uuid::new_v4().to_string();
+            rb.exec(
+                "INSERT INTO task (id, user_id, title, status, created_at) VALUES (?, ?, ?, 0, ?)",
+                vec![
+                    Value::String(id.clone()),
+                    Value::String(user.id.clone()),
+                    Value::String(title.to_string()),
+                    Value::String(Utc::now().to_rfc3339()),
+                ],
+            )
+            .await
+            .unwrap();
+            task_ids.insert(title, id);
+        }
+        for (title, tag) in [("報告", "work"), ("跑步", "health"), ("午休", "work"), ("午休", "health")] {
+            let req = actix_web::test::TestRequest::post()
+                .uri(&format!("/api/tasks/{}/tags/{}", task_ids[title], tag_ids[tag]))
+                .insert_header(user.auth())
+                .to_request();
+            let (status, body) = call_json(&app, req).await;
+            assert_eq!(status, 200, "{}", body);
+        }
+        // 其他使用者不能為別人的任務加標籤
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("/api/tasks/{}/tags/{}", task_ids["報告"], tag_ids["work"]))
+            .insert_header(other.auth())
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+
+        let titles = |body: &serde_json::Value| {
+            let mut titles: Vec<String> =
+                body["data"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect();
+            titles.sort();
+            titles
+        };
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks?user_id={}&tags=work,health", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(titles(&body), vec!["午休", "報告", "跑步"]);
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/tasks?user_id={}&tags=Work,health&tags_match=all", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(titles(&body), vec!["午休"]);
+        assert_eq!(body["data"][0]["tags"].as_array().unwrap().len(), 2);
+
+        let req = actix_web::test::TestRequest::get().uri("/api/tags").insert_header(user.auth()).to_request();
+        let (_, body) = call_json(&app, req).await;
+        let usage: HashMap<String, i64> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| (t["name"].as_str().unwrap().to_string(), t["usage_count"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(usage, HashMap::from([("work".to_string(), 2), ("health".to_string(), 2)]));
+
+        // 刪除標籤只移除關聯，任務保留
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("/api/tags/{}", tag_ids["work"]))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["unassigned_tasks"], 2);
+        let remaining: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM task WHERE user_id = ?", vec![Value::String(user.id.clone())])
+            .await
+            .unwrap();
+        assert_eq!(remaining, 3);
+    }
+}
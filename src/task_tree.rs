@@ -0,0 +1,362 @@
+// 任務樹：一次取得任務、子任務（可選孫任務）與重複性子任務的今日完成情況
+//
+// 取代詳情頁的 get_task + get_subtasks + 逐一 get_task_progress。讀取任務（含權限檢查）之後，
+// 子孫任務以一次查詢取得；重複性任務的每日子任務只取今日，用來計算 today，不列在 children 中。
+
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::models::{Task, TaskStatus};
+
+const DEFAULT_DEPTH: u8 = 1;
+const MAX_DEPTH: u8 = 2;
+
+// 子孫任務：父任務為本任務，或（depth 2 或父任務為重複性任務時）為本任務的子任務。
+// 重複性任務底下只取今日的每日子任務
+const DESCENDANTS_SQL: &str = "SELECT t.* FROM task t JOIN task p ON t.parent_task_id = p.id
+     WHERE (p.id = ? OR (p.parent_task_id = ? AND (? >= 2 OR p.is_recurring = 1)))
+       AND (COALESCE(p.is_recurring, 0) = 0 OR t.task_date = ?)
+     ORDER BY t.task_order ASC, t.created_at ASC";
+
+#[derive(Debug, Deserialize)]
+pub struct TaskTreeQuery {
+    pub depth: Option<u8>,
+    // 只回傳未完成（completed / daily_completed 以外）的子孫任務
+    pub only_incomplete: Option<bool>,
+}
+
+/// 重複性任務今日的完成情況（與 get_task_progress 的 is_daily_completed 定義相同）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TodayCompletion {
+    pub date: String,
+    pub total: usize,
+    pub completed: usize,
+    pub is_completed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskTreeNode {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub task_type: Option<String>,
+    pub status: Option<i32>,
+    pub experience: Option<i32>,
+    pub difficulty: Option<i32>,
+    pub task_order: Option<i32>,
+    pub task_date: Option<String>,
+    pub is_recurring: bool,
+    // 是否有子任務；超過 depth 的層級不會展開，可依此決定是否再次查詢
+    pub is_parent_task: bool,
+    // 重複性任務的今日完成情況；超過查詢範圍的孫任務為 null
+    pub today: Option<TodayCompletion>,
+    pub children: Vec<TaskTreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskTree {
+    pub task: Task,
+    pub depth: u8,
+    pub today: Option<TodayCompletion>,
+    pub children: Vec<TaskTreeNode>,
+}
+
+fn is_completed(task: &Task) -> bool {
+    matches!(
+        task.status.and_then(TaskStatus::from_i32),
+        Some(TaskStatus::Completed) | Some(TaskStatus::DailyCompleted)
+    )
+}
+
+fn today_completion(instances: &[Task], today: &str) -> TodayCompletion {
+    let total = instances.len();
+    let completed = instances
+        .iter()
+        .filter(|t| t.status == Some(TaskStatus::DailyCompleted.to_i32()))
+        .count();
+    TodayCompletion { date: today.to_string(), total, completed, is_completed: total > 0 && completed == total }
+}
+
+struct TreeBuilder<'a> {
+    by_parent: HashMap<String, Vec<Task>>,
+    depth: u8,
+    only_incomplete: bool,
+    today: &'a str,
+}
+
+impl TreeBuilder<'_> {
+    fn new(descendants: Vec<Task>, depth: u8, only_incomplete: bool, today: &str) -> TreeBuilder<'_> {
+        let mut by_parent: HashMap<String, Vec<Task>> = HashMap::new();
+        for task in descendants {
+            if let Some(parent_id) = task.parent_task_id.clone() {
+                by_parent.entry(parent_id).or_default().push(task);
+            }
+        }
+        TreeBuilder { by_parent, depth, only_incomplete, today }
+    }
+
+    // 重複性任務只有根與子任務層取得了今日的每日子任務
+    fn today(&self, task: &Task, level: u8) -> Option<TodayCompletion> {
+        if task.is_recurring != Some(1) || level >= 2 {
+            return None;
+        }
+        let instances = task.id.as_ref().and_then(|id| self.by_parent.get(id)).map(Vec::as_slice).unwrap_or_default();
+        Some(today_completion(instances, self.today))
+    }
+
+    fn children(&self, task: &Task, level: u8) -> Vec<TaskTreeNode> {
+        if task.is_recurring == Some(1) || level >= self.depth {
+            return Vec::new();
+        }
+        task.id
+            .as_ref()
+            .and_then(|id| self.by_parent.get(id))
+            .into_iter()
+            .flatten()
+            .filter(|child| !(self.only_incomplete && is_completed(child)))
+            .map(|child| self.node(child, level + 1))
+            .collect()
+    }
+
+    fn node(&self, task: &Task, level: u8) -> TaskTreeNode {
+        TaskTreeNode {
+            id: task.id.clone(),
+            title: task.title.clone(),
+            task_type: task.task_type.clone(),
+            status: task.status,
+            experience: task.experience,
+            difficulty: task.difficulty,
+            task_order: task.task_order,
+            task_date: task.task_date.clone(),
+            is_recurring: task.is_recurring == Some(1),
+            is_parent_task: task.is_parent_task == Some(1),
+            today: self.today(task, level),
+            children: self.children(task, level),
+        }
+    }
+
+    fn build(self, task: Task) -> TaskTree {
+        TaskTree {
+            depth: self.depth,
+            today: self.today(&task, 0),
+            children: self.children(&task, 0),
+            task,
+        }
+    }
+}
+
+/// 取得任務樹
+/// GET /api/tasks/{id}/tree?depth=1|2&only_incomplete=true
+pub async fn get_task_tree(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    query: web::Query<TaskTreeQuery>,
+) -> Result<HttpResponse> {
+    let task_id = path.into_inner();
+    let depth = query.depth.unwrap_or(DEFAULT_DEPTH);
+    if !(1..=MAX_DEPTH).contains(&depth) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: format!("depth 必須介於 1 到 {}", MAX_DEPTH),
+        }));
+    }
+    let task = match crate::ownership::assert_task_access(rb.get_ref(), &task_id, crate::auth::current_user_id(&http_req).as_deref()).await {
+        Ok(task) => task,
+        Err(e) => return Ok(e.into_response()),
+    };
+
+    // 每日子任務的 task_date 以擁有者時區蓋章
+    let today = crate::local_date::local_today_string_in(crate::recurring_progress::owner_timezone(rb.get_ref(), &task).await);
+    let descendants: Vec<Task> = match rb
+        .query_decode(
+            DESCENDANTS_SQL,
+            vec![
+                rbs::Value::String(task_id.clone()),
+                rbs::Value::String(task_id.clone()),
+                rbs::Value::I32(depth as i32),
+                rbs::Value::String(today.clone()),
+            ],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            log::error!("查詢任務樹失敗，任務ID: {}, 錯誤: {}", task_id, e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: format!("查詢任務樹失敗: {}", e),
+            }));
+        }
+    };
+
+    let tree = TreeBuilder::new(descendants, depth, query.only_incomplete.unwrap_or(false), &today).build(task);
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(tree),
+        message: "獲取任務樹成功".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use actix_web::{http::StatusCode, test::TestRequest};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    const TREE_SHAPE: &str = include_str!("test_utils/snapshots/task_tree_shape.json");
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_task(rb: &RBatis, id: &str, user_id: &str, parent: Option<&str>, status: TaskStatus, recurring: bool, task_date: Option<&str>, order: i32) {
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, task_type, status, parent_task_id, is_parent_task, is_recurring, task_date, task_order, experience, difficulty, created_at, updated_at)
+             VALUES (?, ?, ?, 'main', ?, ?, 0, ?, ?, ?, 20, 2, ?, ?)",
+            vec![
+                rbs::Value::String(id.to_string()),
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(id.to_string()),
+                rbs::Value::I32(status.to_i32()),
+                parent.map(|p| rbs::Value::String(p.to_string())).unwrap_or(rbs::Value::Null),
+                rbs::Value::I32(recurring as i32),
+                task_date.map(|d| rbs::Value::String(d.to_string())).unwrap_or(rbs::Value::Null),
+                rbs::Value::I32(order),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+                rbs::Value::String(Utc::now().to_rfc3339()),
+            ],
+        )
+        .await
+        .unwrap();
+        if let Some(parent) = parent {
+            Task::update_is_parent_task(rb, parent, true).await.unwrap();
+        }
+    }
+
+    // 以型別取代值，用於比對回應結構；陣列的元素合併為一個（null 以其他元素的結構取代）
+    fn shape(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), shape(v))).collect()),
+            Value::Array(items) => Value::Array(items.iter().map(shape).reduce(merge).into_iter().collect()),
+            Value::String(_) => json!("string"),
+            Value::Number(_) => json!("number"),
+            Value::Bool(_) => json!("bool"),
+            Value::Null => json!("null"),
+        }
+    }
+
+    fn merge(a: Value, b: Value) -> Value {
+        match (a, b) {
+            (Value::Object(mut a), Value::Object(b)) => {
+                for (k, v) in b {
+                    let merged = match a.remove(&k) {
+                        Some(existing) => merge(existing, v),
+                        None => v,
+                    };
+                    a.insert(k, merged);
+                }
+                Value::Object(a)
+            }
+            (Value::Array(a), Value::Array(b)) => Value::Array(a.into_iter().chain(b).reduce(merge).into_iter().collect()),
+            (a, b) if b == json!("null") => a,
+            (_, b) => b,
+        }
+    }
+
+    fn titles(nodes: &Value) -> Vec<&str> {
+        nodes.as_array().unwrap().iter().map(|n| n["title"].as_str().unwrap()).collect()
+    }
+
+    #[actix_web::test]
+    async fn test_task_tree_shape_and_filters() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "tree_walker").await;
+        let other = test_utils::create_user(&app, "tree_peeker").await;
+        let today = crate::local_date::local_today_string();
+        let yesterday = (crate::local_date::local_today() - chrono::Duration::days(1)).to_string();
+
+        // 主線 → 階段（含步驟）、每日練習（重複性）、已完成的階段
+        insert_task(&rb, "mainline", &user.id, None, TaskStatus::InProgress, false, None, 0).await;
+        insert_task(&rb, "stage", &user.id, Some("mainline"), TaskStatus::InProgress, false, None, 1).await;
+        insert_task(&rb, "step-a", &user.id, Some("stage"), TaskStatus::Completed, false, None, 1).await;
+        insert_task(&rb, "step-b", &user.id, Some("stage"), TaskStatus::Pending, false, None, 2).await;
+        insert_task(&rb, "practice", &user.id, Some("mainline"), TaskStatus::DailyInProgress, true, None, 2).await;
+        insert_task(&rb, "practice-today", &user.id, Some("practice"), TaskStatus::DailyCompleted, false, Some(&today), 0).await;
+        insert_task(&rb, "practice-yesterday", &user.id, Some("practice"), TaskStatus::DailyNotCompleted, false, Some(&yesterday), 0).await;
+        insert_task(&rb, "done-stage", &user.id, Some("mainline"), TaskStatus::Completed, false, None, 3).await;
+
+        let get = |uri: String, auth: (&'static str, String)| TestRequest::get().uri(&uri).insert_header(auth).to_request();
+
+        let (status, body) = call_json(&app, get("/api/tasks/mainline/tree?depth=2".to_string(), user.auth())).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let tree = &body["data"];
+        assert_eq!(tree["task"]["id"], "mainline");
+        assert_eq!(titles(&tree["children"]), vec!["stage", "practice", "done-stage"]);
+        assert_eq!(titles(&tree["children"][0]["children"]), vec!["step-a", "step-b"]);
+        // 重複性子任務只回傳今日完成情況，不列出每日子任務
+        let practice = &tree["children"][1];
+        assert_eq!(practice["today"], json!({"date": today, "total": 1, "completed": 1, "is_completed": true}));
+        assert_eq!(practice["children"], json!([]));
+        assert!(tree["children"][0]["today"].is_null());
+
+        let expected: Value = serde_json::from_str(TREE_SHAPE).unwrap();
+        assert_eq!(shape(tree), expected, "回應結構改變，請確認相容性後更新 test_utils/snapshots/task_tree_shape.json");
+
+        // depth=1 只展開一層，仍提供今日完成情況
+        let (_, body) = call_json(&app, get("/api/tasks/mainline/tree".to_string(), user.auth())).await;
+        assert_eq!(body["data"]["depth"], 1);
+        assert_eq!(body["data"]["children"][0]["children"], json!([]));
+        assert_eq!(body["data"]["children"][0]["is_parent_task"], true);
+        assert_eq!(body["data"]["children"][1]["today"]["is_completed"], true);
+
+        let (_, body) = call_json(&app, get("/api/tasks/mainline/tree?depth=2&only_incomplete=true".to_string(), user.auth())).await;
+        assert_eq!(titles(&body["data"]["children"]), vec!["stage", "practice"]);
+        assert_eq!(titles(&body["data"]["children"][0]["children"]), vec!["step-b"]);
+
+        // 重複性任務本身：今日完成情況放在最外層
+        let (_, body) = call_json(&app, get("/api/tasks/practice/tree".to_string(), user.auth())).await;
+        assert_eq!(body["data"]["today"]["total"], 1);
+        assert_eq!(body["data"]["children"], json!([]));
+
+        let (status, _) = call_json(&app, get("/api/tasks/mainline/tree?depth=3".to_string(), user.auth())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call_json(&app, get("/api/tasks/mainline/tree".to_string(), other.auth())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_today_follows_owner_timezone() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "tree_night_owl").await;
+
+        // 找一個此刻日期與預設時區不同的 UTC 偏移
+        let default_today = crate::local_date::local_today_string();
+        let tz = (-12..=14)
+            .filter_map(|hours| chrono::FixedOffset::east_opt(hours * 3600))
+            .find(|tz| crate::local_date::local_today_string_in(*tz) != default_today)
+            .unwrap();
+        let user_today = crate::local_date::local_today_string_in(tz);
+        let req = TestRequest::patch()
+            .uri(&format!("/api/users/{}/settings", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"timezone": tz.to_string()}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, StatusCode::OK);
+
+        insert_task(&rb, "practice", &user.id, None, TaskStatus::DailyInProgress, true, None, 0).await;
+        insert_task(&rb, "practice-user-today", &user.id, Some("practice"), TaskStatus::DailyCompleted, false, Some(&user_today), 0).await;
+        insert_task(&rb, "practice-server-today", &user.id, Some("practice"), TaskStatus::DailyInProgress, false, Some(&default_today), 0).await;
+
+        let req = TestRequest::get().uri("/api/tasks/practice/tree").insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["today"], json!({"date": user_today, "total": 1, "completed": 1, "is_completed": true}));
+    }
+}
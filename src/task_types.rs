@@ -0,0 +1,240 @@
+// 任務類型登錄
+//
+// task_type 在資料庫中以字串保存；程式內的比較一律透過 TaskType，新增類型時
+// 編譯器會指出所有需要處理的 match。API 只接受內建類型與 CUSTOM_TASK_TYPES 允許的自訂類型。
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config::TaskTypeConfig;
+
+static CUSTOM_TYPES: OnceLock<Vec<String>> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TaskType {
+    Main,           // 主線任務
+    Side,           // 支線任務
+    Challenge,      // 挑戰任務
+    Daily,          // 每日任務
+    Subtask,        // 大任務拆出的子任務
+    DailyRecurring, // 重複性任務產生的每日實例
+    Recurring,      // 重複性任務本體
+    Custom(String), // 設定允許的自訂類型
+}
+
+impl TaskType {
+    pub const BUILT_IN: [TaskType; 7] = [
+        TaskType::Main,
+        TaskType::Side,
+        TaskType::Challenge,
+        TaskType::Daily,
+        TaskType::Subtask,
+        TaskType::DailyRecurring,
+        TaskType::Recurring,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            TaskType::Main => "main",
+            TaskType::Side => "side",
+            TaskType::Challenge => "challenge",
+            TaskType::Daily => "daily",
+            TaskType::Subtask => "subtask",
+            TaskType::DailyRecurring => "daily_recurring",
+            TaskType::Recurring => "recurring",
+            TaskType::Custom(name) => name,
+        }
+    }
+
+    /// 讀取資料庫中的值；空值或無法辨識時為 None
+    pub fn from_stored(value: Option<&str>) -> Option<TaskType> {
+        value.and_then(|v| v.parse().ok())
+    }
+
+    /// 沒有父任務時，此類型的任務是否視為大任務
+    pub fn is_parent_by_default(&self) -> bool {
+        match self {
+            TaskType::Main | TaskType::Side | TaskType::Challenge => true,
+            TaskType::Daily
+            | TaskType::Subtask
+            | TaskType::DailyRecurring
+            | TaskType::Recurring
+            | TaskType::Custom(_) => false,
+        }
+    }
+
+    /// 建立時未指定經驗值的預設值：每日任務依 REWARD_DAILY_TASK_XP（預設 10），其他類型的父任務由子任務累積，初始為 0
+    pub fn default_experience(&self) -> i32 {
+        match self {
+            TaskType::Daily => crate::reward_config::current().daily_task_xp,
+            TaskType::Main
+            | TaskType::Side
+            | TaskType::Challenge
+            | TaskType::Subtask
+            | TaskType::DailyRecurring
+            | TaskType::Recurring
+            | TaskType::Custom(_) => 0,
+        }
+    }
+
+    /// AI 生成的任務可以使用的類型
+    pub fn is_ai_generatable(&self) -> bool {
+        match self {
+            TaskType::Main | TaskType::Side | TaskType::Challenge | TaskType::Daily => true,
+            TaskType::Subtask | TaskType::DailyRecurring | TaskType::Recurring | TaskType::Custom(_) => false,
+        }
+    }
+
+    fn built_in(value: &str) -> Option<TaskType> {
+        TaskType::BUILT_IN.into_iter().find(|t| t.as_str() == value)
+    }
+}
+
+impl fmt::Display for TaskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TaskType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(task_type) = TaskType::built_in(value) {
+            return Ok(task_type);
+        }
+        if custom_types().iter().any(|t| t == value) {
+            return Ok(TaskType::Custom(value.to_string()));
+        }
+        let allowed: Vec<&str> = TaskType::BUILT_IN
+            .iter()
+            .map(TaskType::as_str)
+            .chain(custom_types().iter().map(String::as_str))
+            .collect();
+        Err(format!("未知的任務類型: {}（可用類型: {}）", value, allowed.join(", ")))
+    }
+}
+
+impl Serialize for TaskType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TaskType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 啟動時套用自訂類型的允許清單（與內建類型同名或格式不符的會被略過）
+pub fn init(config: TaskTypeConfig) {
+    let custom: Vec<String> = config
+        .custom_types
+        .into_iter()
+        .filter(|name| {
+            let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid || TaskType::built_in(name).is_some() {
+                log::warn!("略過自訂任務類型 {}：必須是小寫英數與底線，且不能與內建類型同名", name);
+                return false;
+            }
+            true
+        })
+        .collect();
+    if !custom.is_empty() {
+        log::info!("自訂任務類型: {}", custom.join(", "));
+    }
+    if CUSTOM_TYPES.set(custom).is_err() {
+        log::warn!("任務類型設定已初始化，忽略重複設定");
+    }
+}
+
+fn custom_types() -> &'static [String] {
+    CUSTOM_TYPES.get_or_init(Vec::new)
+}
+
+/// 比對用的鍵：忽略大小寫、空白、底線與連字號（dailyrecurring、Daily-Recurring 都對應 daily_recurring）
+fn loose_key(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 將資料庫中拼寫不一的 task_type 改為正式名稱；回傳更新的任務數。無法辨識的值保留並記錄警告
+pub async fn normalize_stored_types(rb: &RBatis) -> Result<u64, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode("SELECT DISTINCT task_type FROM task WHERE task_type IS NOT NULL", vec![])
+        .await?;
+    let known: Vec<TaskType> = TaskType::BUILT_IN
+        .into_iter()
+        .chain(custom_types().iter().cloned().map(TaskType::Custom))
+        .collect();
+    let mut updated = 0;
+    for stored in rows.iter().filter_map(|row| row["task_type"].as_str()) {
+        if TaskType::from_stored(Some(stored)).is_some_and(|t| t.as_str() == stored) {
+            continue;
+        }
+        let key = loose_key(stored);
+        match known.iter().find(|t| loose_key(t.as_str()) == key) {
+            Some(task_type) => {
+                let result = rb
+                    .exec(
+                        "UPDATE task SET task_type = ? WHERE task_type = ?",
+                        vec![Value::String(task_type.to_string()), Value::String(stored.to_string())],
+                    )
+                    .await?;
+                log::info!("任務類型 {:?} 修正為 {}（{} 筆）", stored, task_type, result.rows_affected);
+                updated += result.rows_affected;
+            }
+            None => log::warn!("資料庫中有無法辨識的任務類型 {:?}，保留原值", stored),
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn test_task_type_round_trip() {
+        for task_type in TaskType::BUILT_IN {
+            let json = serde_json::to_string(&task_type).unwrap();
+            assert_eq!(json, format!("\"{}\"", task_type.as_str()));
+            assert_eq!(serde_json::from_str::<TaskType>(&json).unwrap(), task_type);
+        }
+        assert!(serde_json::from_str::<TaskType>("\"dailyrecurring\"").is_err());
+        assert!("quest".parse::<TaskType>().unwrap_err().contains("daily_recurring"));
+    }
+
+    #[actix_web::test]
+    async fn test_normalize_stored_types() {
+        let rb = test_utils::setup_db().await;
+        for (id, task_type) in [("t1", "dailyrecurring"), ("t2", " Daily "), ("t3", "main"), ("t4", "quest")] {
+            rb.exec(
+                "INSERT INTO task (id, title, task_type, status) VALUES (?, '任務', ?, 0)",
+                vec![Value::String(id.to_string()), Value::String(task_type.to_string())],
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(normalize_stored_types(&rb).await.unwrap(), 2);
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT id, task_type FROM task ORDER BY id", vec![])
+            .await
+            .unwrap();
+        let types: Vec<&str> = rows.iter().filter_map(|row| row["task_type"].as_str()).collect();
+        assert_eq!(types, vec!["daily_recurring", "daily", "main", "quest"]);
+        assert_eq!(normalize_stored_types(&rb).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,271 @@
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::{value, Value};
+
+use crate::models::{Task, UpdateTaskRequest};
+
+/// 任務更新結果
+#[derive(Debug)]
+pub enum TaskUpdateOutcome {
+    Updated(Task),
+    // 版本不符，附上伺服器目前的任務狀態供客戶端合併
+    Conflict(Task),
+    NotFound,
+}
+
+/// 可清空欄位：null 寫入 NULL，有值則轉為資料庫值
+fn nullable<T>(value: &Option<T>, to_value: impl Fn(&T) -> Value) -> Value {
+    value.as_ref().map(to_value).unwrap_or(Value::Null)
+}
+
+/// 只收集請求中有提供的欄位；status 由呼叫端決定（共享任務可能改寫）
+pub fn collect_changes(req: &UpdateTaskRequest, status: Option<i32>) -> Vec<(&'static str, Value)> {
+    let mut changes = Vec::new();
+    if let Some(title) = &req.title {
+        changes.push(("title", Value::String(title.clone())));
+    }
+    if let Some(description) = &req.description {
+        changes.push(("description", nullable(description, |d| Value::String(d.clone()))));
+    }
+    if let Some(status) = status {
+        changes.push(("status", Value::I32(status)));
+    }
+    if let Some(priority) = req.priority {
+        changes.push(("priority", Value::I32(priority)));
+    }
+    if let Some(task_type) = &req.task_type {
+        changes.push(("task_type", Value::String(task_type.clone())));
+    }
+    if let Some(difficulty) = req.difficulty {
+        changes.push(("difficulty", Value::I32(difficulty)));
+    }
+    if let Some(experience) = req.experience {
+        changes.push(("experience", Value::I32(experience)));
+    }
+    if let Some(due_date) = &req.due_date {
+        changes.push(("due_date", nullable(due_date, |d| Value::String(d.to_rfc3339()))));
+    }
+    if let Some(task_order) = req.task_order {
+        changes.push(("task_order", Value::I32(task_order)));
+    }
+    if let Some(task_date) = &req.task_date {
+        changes.push(("task_date", nullable(task_date, |d| Value::String(d.clone()))));
+    }
+    if let Some(completion_target) = &req.completion_target {
+        changes.push(("completion_target", nullable(completion_target, |t| Value::F64(*t))));
+    }
+    if let Some(skill_tags) = &req.skill_tags {
+        changes.push((
+            "skill_tags",
+            nullable(skill_tags, |tags| Value::String(serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string()))),
+        ));
+    }
+    if let Some(attributes) = &req.attributes {
+        changes.push(("attributes", nullable(attributes, |a| Value::String(a.to_string()))));
+    }
+    if let Some(require_proof) = req.require_proof {
+        changes.push(("require_proof", Value::I32(require_proof as i32)));
+    }
+    if let Some(stake) = &req.stake {
+        changes.push(("stake", nullable(stake, |s| Value::String(s.clone()))));
+    }
+    if let Some(requires_confirmation) = req.requires_confirmation {
+        changes.push(("requires_confirmation", Value::I32(requires_confirmation as i32)));
+    }
+    changes
+}
+
+/// 以樂觀鎖更新任務：版本相符才寫入，成功後版本號遞增
+pub async fn apply_task_update(
+    rb: &RBatis,
+    task_id: &str,
+    expected_version: i32,
+    changes: Vec<(&'static str, Value)>,
+) -> Result<TaskUpdateOutcome, rbatis::Error> {
+    let mut assignments: Vec<String> = changes.iter().map(|(column, _)| format!("{} = ?", column)).collect();
+    assignments.push("updated_at = ?".to_string());
+    assignments.push("version = COALESCE(version, 0) + 1".to_string());
+
+    let mut args: Vec<Value> = changes.into_iter().map(|(_, v)| v).collect();
+    args.push(Value::String(Utc::now().to_rfc3339()));
+    args.push(Value::String(task_id.to_string()));
+    args.push(Value::I32(expected_version));
+
+    let sql = format!(
+        "UPDATE task SET {} WHERE id = ? AND COALESCE(version, 0) = ?",
+        assignments.join(", ")
+    );
+    let result = rb.exec(&sql, args).await?;
+
+    let current = Task::select_by_map(rb, value!{"id": task_id}).await?.into_iter().next();
+    Ok(match current {
+        None => TaskUpdateOutcome::NotFound,
+        Some(task) if result.rows_affected > 0 => TaskUpdateOutcome::Updated(task),
+        Some(task) => TaskUpdateOutcome::Conflict(task),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbdc_sqlite::driver::SqliteDriver;
+
+    async fn setup() -> (RBatis, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("lifeup_task_update_{}.db", uuid::Uuid::new_v4()));
+        let rb = RBatis::new();
+        rb.init(SqliteDriver {}, &format!("sqlite://{}", path.display())).unwrap();
+        crate::create_tables(&rb).await;
+        crate::migrate_database(&rb).await;
+
+        rb.exec("INSERT INTO user (id, name, email, password_hash) VALUES ('u1', '測試用戶', 'u1@lifeup.com', '')", vec![])
+            .await
+            .unwrap();
+        rb.exec(
+            "INSERT INTO task (id, user_id, title, description, status, priority, due_date, task_date, completion_target, skill_tags, attributes, version) \
+             VALUES ('t1', 'u1', '學習日文', '每天背 10 個單字', 0, 2, '2026-01-31 23:59:59 UTC', '2026-01-01', 0.8, '[\"日文\"]', '{\"intelligence\":5}', 0)",
+            vec![],
+        )
+        .await
+        .unwrap();
+        (rb, path)
+    }
+
+    fn status_only(status: i32) -> UpdateTaskRequest {
+        serde_json::from_value(serde_json::json!({ "status": status, "version": 0 })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stale_writer_gets_conflict() {
+        let (rb, path) = setup().await;
+
+        // 兩台裝置都讀到 version 0
+        let first = apply_task_update(&rb, "t1", 0, collect_changes(&status_only(1), Some(1))).await.unwrap();
+        let second = apply_task_update(&rb, "t1", 0, collect_changes(&status_only(2), Some(2))).await.unwrap();
+
+        match first {
+            TaskUpdateOutcome::Updated(task) => assert_eq!(task.version, Some(1)),
+            other => panic!("第一次寫入應成功: {:?}", other),
+        }
+        match second {
+            TaskUpdateOutcome::Conflict(task) => {
+                assert_eq!(task.status, Some(1));
+                assert_eq!(task.version, Some(1));
+            }
+            other => panic!("過期版本應回傳衝突: {:?}", other),
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writers_only_one_wins() {
+        let (rb, path) = setup().await;
+
+        let (a, b) = tokio::join!(
+            apply_task_update(&rb, "t1", 0, collect_changes(&status_only(1), Some(1))),
+            apply_task_update(&rb, "t1", 0, collect_changes(&status_only(4), Some(4))),
+        );
+        let outcomes = [a.unwrap(), b.unwrap()];
+        let updated = outcomes.iter().filter(|o| matches!(o, TaskUpdateOutcome::Updated(_))).count();
+        let conflicts = outcomes.iter().filter(|o| matches!(o, TaskUpdateOutcome::Conflict(_))).count();
+        assert_eq!((updated, conflicts), (1, 1));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_partial_update_keeps_other_fields() {
+        let (rb, path) = setup().await;
+
+        let outcome = apply_task_update(&rb, "t1", 0, collect_changes(&status_only(2), Some(2))).await.unwrap();
+        let TaskUpdateOutcome::Updated(task) = outcome else {
+            panic!("更新應成功");
+        };
+        assert_eq!(task.status, Some(2));
+        assert_eq!(task.title.as_deref(), Some("學習日文"));
+        assert_eq!(task.description.as_deref(), Some("每天背 10 個單字"));
+        assert_eq!(task.priority, Some(2));
+        assert!(task.due_date.is_some());
+        assert_eq!(task.task_date.as_deref(), Some("2026-01-01"));
+        assert_eq!(task.completion_target, Some(0.8));
+        assert_eq!(task.skill_tags, Some(vec!["日文".to_string()]));
+        assert!(task.attributes.is_some());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_null_is_distinguished_from_missing() {
+        let req: UpdateTaskRequest = serde_json::from_value(serde_json::json!({
+            "description": null,
+            "due_date": null,
+            "skill_tags": ["日文", "聽力"],
+            "version": 0
+        }))
+        .unwrap();
+        assert_eq!(req.description, Some(None));
+        assert_eq!(req.due_date, Some(None));
+        assert_eq!(req.task_date, None);
+        assert_eq!(req.skill_tags, Some(Some(vec!["日文".to_string(), "聽力".to_string()])));
+
+        let columns: Vec<&str> = collect_changes(&req, None).into_iter().map(|(c, _)| c).collect();
+        assert_eq!(columns, vec!["description", "due_date", "skill_tags"]);
+    }
+
+    #[tokio::test]
+    async fn test_null_clears_field_and_new_fields_are_editable() {
+        let (rb, path) = setup().await;
+
+        let req: UpdateTaskRequest = serde_json::from_value(serde_json::json!({
+            "description": null,
+            "due_date": null,
+            "task_date": "2026-02-01",
+            "completion_target": 0.5,
+            "skill_tags": ["日文", "聽力"],
+            "attributes": { "focus": 3 },
+            "version": 0
+        }))
+        .unwrap();
+        let outcome = apply_task_update(&rb, "t1", 0, collect_changes(&req, None)).await.unwrap();
+        let TaskUpdateOutcome::Updated(task) = outcome else {
+            panic!("更新應成功");
+        };
+        assert_eq!(task.description, None);
+        assert_eq!(task.due_date, None);
+        assert_eq!(task.title.as_deref(), Some("學習日文"));
+        assert_eq!(task.status, Some(0));
+        assert_eq!(task.task_date.as_deref(), Some("2026-02-01"));
+        assert_eq!(task.completion_target, Some(0.5));
+        assert_eq!(task.skill_tags, Some(vec!["日文".to_string(), "聽力".to_string()]));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_due_date_is_stored_as_rfc3339() {
+        let (rb, path) = setup().await;
+
+        let req: UpdateTaskRequest = serde_json::from_value(serde_json::json!({
+            "due_date": "2026-03-01T09:30:00Z",
+            "version": 0
+        }))
+        .unwrap();
+        apply_task_update(&rb, "t1", 0, collect_changes(&req, None)).await.unwrap();
+
+        let rows: Vec<serde_json::Value> = rb
+            .query_decode("SELECT due_date FROM task WHERE id = 't1'", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["due_date"], "2026-03-01T09:30:00+00:00");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_missing_task_is_not_found() {
+        let (rb, path) = setup().await;
+        let outcome = apply_task_update(&rb, "missing", 0, Vec::new()).await.unwrap();
+        assert!(matches!(outcome, TaskUpdateOutcome::NotFound));
+        let _ = std::fs::remove_file(path);
+    }
+}
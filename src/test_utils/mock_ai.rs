@@ -0,0 +1,260 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use rbatis::RBatis;
+
+use crate::ai_service::{
+    AIContentFilteredError, AIGeneratedAchievement, AIGeneratedSkillTags, AIGeneratedTask, AIGeneratedTaskPlan, AIService, ChatImage, ExpertMatch,
+    VISION_UNSUPPORTED,
+};
+
+// 固定的 AI 回應（JSON 格式與真實 AI 回傳解析後的結構相同）
+const TASK_FIXTURE: &str = include_str!("fixtures/task.json");
+const TASK_PLAN_FIXTURE: &str = include_str!("fixtures/task_plan.json");
+const EXPERT_MATCH_FIXTURE: &str = include_str!("fixtures/expert_match.json");
+const ACHIEVEMENT_FIXTURE: &str = include_str!("fixtures/achievement.json");
+const SKILL_TAGS_FIXTURE: &str = include_str!("fixtures/skill_tags.json");
+const INTENT_FIXTURE: &str = include_str!("fixtures/intent.json");
+
+const DEFAULT_TEXT_REPLY: &str = "這是模擬的 AI 回應";
+
+/// 模擬服務收到的一次呼叫
+#[derive(Debug, Clone)]
+pub struct MockCall {
+    pub method: &'static str,
+    pub prompt: String,
+}
+
+/// 模擬 AI 服務：不呼叫外部 API
+///
+/// 結構化結果固定回傳 fixtures 目錄中的 JSON，文字回應依序取用預先設定的內容；
+/// 每次呼叫的提示詞都會記錄下來供測試斷言（複製出的實例共用同一份紀錄）。
+#[derive(Debug, Clone, Default)]
+pub struct MockAIService {
+    replies: Arc<Mutex<VecDeque<String>>>,
+    calls: Arc<Mutex<Vec<MockCall>>>,
+    content_filtered: bool,
+    expert_match_fails: bool,
+    vision_unsupported: bool,
+}
+
+impl MockAIService {
+    /// 依序回傳指定的文字回應，用完後回到預設回應
+    pub fn with_replies(replies: &[&str]) -> Self {
+        let mock = MockAIService::default();
+        mock.replies.lock().unwrap().extend(replies.iter().map(|r| r.to_string()));
+        mock
+    }
+
+    /// 模擬供應商以安全政策擋下所有內容（呼叫仍會被記錄）
+    pub fn content_filtered() -> Self {
+        MockAIService { content_filtered: true, ..Default::default() }
+    }
+
+    /// 專家匹配失敗，其餘呼叫照常回應
+    pub fn without_expert_match(mut self) -> Self {
+        self.expert_match_fails = true;
+        self
+    }
+
+    /// 模擬不支援圖片的模型
+    pub fn without_vision(mut self) -> Self {
+        self.vision_unsupported = true;
+        self
+    }
+
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// 指定方法收到的所有提示詞
+    pub fn prompts(&self, method: &str) -> Vec<String> {
+        self.calls().into_iter().filter(|c| c.method == method).map(|c| c.prompt).collect()
+    }
+
+    fn record(&self, method: &'static str, prompt: String) {
+        self.calls.lock().unwrap().push(MockCall { method, prompt });
+    }
+
+    fn check_filtered(&self) -> Result<()> {
+        if self.content_filtered {
+            return Err(AIContentFilteredError {
+                provider: "Mock".to_string(),
+                reason: "SAFETY".to_string(),
+            }.into());
+        }
+        Ok(())
+    }
+
+    fn text_reply(&self) -> String {
+        self.replies.lock().unwrap().pop_front().unwrap_or_else(|| DEFAULT_TEXT_REPLY.to_string())
+    }
+}
+
+fn fixture<T: serde::de::DeserializeOwned>(json: &str) -> Result<T> {
+    Ok(serde_json::from_str(json)?)
+}
+
+#[async_trait::async_trait]
+impl AIService for MockAIService {
+    async fn generate_achievement_from_text(&self, user_input: &str) -> Result<AIGeneratedAchievement> {
+        self.record("generate_achievement_from_text", user_input.to_string());
+        self.check_filtered()?;
+        fixture(ACHIEVEMENT_FIXTURE)
+    }
+
+    async fn generate_achievement_from_user_id(&self, _rb: &RBatis, user_id: &str) -> Result<AIGeneratedAchievement> {
+        self.record("generate_achievement_from_user_id", user_id.to_string());
+        self.check_filtered()?;
+        fixture(ACHIEVEMENT_FIXTURE)
+    }
+
+    async fn generate_task_preview(&self, prompt: &str) -> Result<String> {
+        self.record("generate_task_preview", prompt.to_string());
+        self.check_filtered()?;
+        Ok(self.text_reply())
+    }
+
+    async fn generate_task_preview_with_history(&self, system_prompt: &str, history: &[(String, String)], current_message: &str) -> Result<String> {
+        let history: Vec<String> = history.iter().map(|(user, ai)| format!("{}\n{}", user, ai)).collect();
+        self.record(
+            "generate_task_preview_with_history",
+            format!("{}\n\n{}\n\n{}", system_prompt, history.join("\n"), current_message),
+        );
+        self.check_filtered()?;
+        Ok(self.text_reply())
+    }
+
+    async fn generate_task_from_text(&self, user_input: &str) -> Result<AIGeneratedTask> {
+        self.record("generate_task_from_text", user_input.to_string());
+        self.check_filtered()?;
+        fixture(TASK_FIXTURE)
+    }
+
+    async fn match_expert_for_task(&self, user_input: &str) -> Result<ExpertMatch> {
+        self.record("match_expert_for_task", user_input.to_string());
+        self.check_filtered()?;
+        if self.expert_match_fails {
+            anyhow::bail!("模擬的專家匹配失敗");
+        }
+        fixture(EXPERT_MATCH_FIXTURE)
+    }
+
+    async fn generate_task_with_expert(&self, user_input: &str, expert_match: &ExpertMatch) -> Result<AIGeneratedTaskPlan> {
+        self.record(
+            "generate_task_with_expert",
+            format!("{}：{}\n\n{}", expert_match.ai_expert_name, expert_match.ai_expert_description, user_input),
+        );
+        self.check_filtered()?;
+        fixture(TASK_PLAN_FIXTURE)
+    }
+
+    async fn analyze_with_expert(&self, user_input: &str, expert_name: &str, expert_description: &str, analysis_type: &str) -> Result<String> {
+        self.record(
+            "analyze_with_expert",
+            format!("[{}] {}：{}\n\n{}", analysis_type, expert_name, expert_description, user_input),
+        );
+        self.check_filtered()?;
+        Ok(self.text_reply())
+    }
+
+    async fn generate_subtasks_for_main_task(&self, main_task_title: &str, main_task_description: &str, expert_match: &ExpertMatch) -> Result<Vec<AIGeneratedTask>> {
+        self.record(
+            "generate_subtasks_for_main_task",
+            format!("{}：{}\n\n{}\n{}", expert_match.ai_expert_name, expert_match.ai_expert_description, main_task_title, main_task_description),
+        );
+        self.check_filtered()?;
+        let plan: AIGeneratedTaskPlan = fixture(TASK_PLAN_FIXTURE)?;
+        Ok(plan.subtasks)
+    }
+
+    async fn generate_with_model(&self, model: &str, prompt: &str) -> Result<String> {
+        self.record("generate_with_model", format!("[{}] {}", model, prompt));
+        self.check_filtered()?;
+        Ok(self.text_reply())
+    }
+
+    fn supports_vision(&self, _model: &str) -> bool {
+        !self.vision_unsupported
+    }
+
+    async fn generate_with_image(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        history: &[(String, String)],
+        message: &str,
+        image: &ChatImage,
+    ) -> Result<String> {
+        let history: Vec<String> = history.iter().map(|(user, ai)| format!("{}\n{}", user, ai)).collect();
+        self.record(
+            "generate_with_image",
+            format!(
+                "[{}] [{} {} bytes] {}\n\n{}\n\n{}",
+                model,
+                image.mime_type,
+                image.data.len(),
+                system_prompt,
+                history.join("\n"),
+                message
+            ),
+        );
+        if self.vision_unsupported {
+            return Err(anyhow::anyhow!(VISION_UNSUPPORTED));
+        }
+        self.check_filtered()?;
+        Ok(self.text_reply())
+    }
+
+    async fn generate_daily_task_from_text(&self, user_input: &str) -> Result<AIGeneratedTask> {
+        self.record("generate_daily_task_from_text", user_input.to_string());
+        self.check_filtered()?;
+        fixture(TASK_FIXTURE)
+    }
+
+    async fn classify_user_intent(&self, user_input: &str) -> Result<crate::ai_tasks::ClassifyIntentResponse> {
+        self.record("classify_user_intent", user_input.to_string());
+        self.check_filtered()?;
+        fixture(INTENT_FIXTURE)
+    }
+
+    async fn generate_skill_tags(
+        &self,
+        task_title: &str,
+        task_description: Option<&str>,
+        user_existing_skills: &[String]
+    ) -> Result<AIGeneratedSkillTags> {
+        self.record(
+            "generate_skill_tags",
+            format!("{}\n{}\n{}", task_title, task_description.unwrap_or_default(), user_existing_skills.join(",")),
+        );
+        self.check_filtered()?;
+        fixture(SKILL_TAGS_FIXTURE)
+    }
+}
+
+// 未經由 app data 取得 AI 服務的呼叫點（例如建立任務後在背景生成成就）仍使用 create_ai_service，
+// 測試時以目前執行緒安裝的模擬服務取代；actix 測試執行於單一執行緒，背景任務也會看到同一個設定
+thread_local! {
+    static INSTALLED: RefCell<Option<MockAIService>> = const { RefCell::new(None) };
+}
+
+/// 讓目前執行緒的 create_ai_service 回傳模擬服務，guard 離開作用域時移除
+pub fn install(mock: MockAIService) -> MockGuard {
+    INSTALLED.with(|slot| *slot.borrow_mut() = Some(mock));
+    MockGuard
+}
+
+pub fn installed() -> Option<MockAIService> {
+    INSTALLED.with(|slot| slot.borrow().clone())
+}
+
+pub struct MockGuard;
+
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        INSTALLED.with(|slot| *slot.borrow_mut() = None);
+    }
+}
@@ -0,0 +1,166 @@
+// 整合測試工具：以 sqlite::memory: 建立完整的 App（與正式環境相同的路由與中間件），
+// 並提供註冊、登入取得 JWT 等輔助函數
+
+pub mod mock_ai;
+
+use std::sync::Arc;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, web, App};
+use rbatis::RBatis;
+use serde_json::{json, Value};
+
+use crate::ai_service::{AIService, SharedAIService};
+
+/// 符合密碼強度規則的測試密碼
+pub const TEST_PASSWORD: &str = "Passw0rd!";
+
+/// 建立獨立的記憶體資料庫並執行建表與遷移
+pub async fn setup_db() -> RBatis {
+    let rb = RBatis::new();
+    rb.init(rbdc_sqlite::driver::SqliteDriver {}, "sqlite::memory:").unwrap();
+    // 記憶體資料庫以共享快取連線，限制為單一連線避免表格鎖定錯誤
+    rb.get_pool().unwrap().set_max_open_conns(1).await;
+    crate::create_tables(&rb).await;
+    crate::migrate_database(&rb).await;
+    rb
+}
+
+/// 以 routes::configure 建立測試用 App（AI 服務依環境變數設定建立，與正式環境相同）
+pub async fn init_app(rb: &RBatis) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+    let config = crate::config::Config::from_env();
+    let ai_service = SharedAIService::from_config(&config.app.ai);
+    build_app(rb, ai_service, config).await
+}
+
+/// 建立注入指定 AI 服務（通常是 MockAIService）的測試用 App
+pub async fn init_app_with_ai(
+    rb: &RBatis,
+    ai_service: Arc<dyn AIService + Send + Sync>,
+) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+    init_app_with_shared_ai(rb, SharedAIService::new(ai_service)).await
+}
+
+/// 建立注入指定 SharedAIService 的測試用 App（例如調整模型允許清單）
+pub async fn init_app_with_shared_ai(
+    rb: &RBatis,
+    ai_service: SharedAIService,
+) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+    let config = crate::config::Config::from_env();
+    build_app(rb, ai_service, config).await
+}
+
+/// 以指定設定建立測試用 App（例如停用 API v1）
+pub async fn init_app_with_config(
+    rb: &RBatis,
+    config: crate::config::Config,
+) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+    let ai_service = SharedAIService::from_config(&config.app.ai);
+    build_app(rb, ai_service, config).await
+}
+
+async fn build_app(
+    rb: &RBatis,
+    ai_service: SharedAIService,
+    config: crate::config::Config,
+) -> impl Service<Request, Response = ServiceResponse, Error = actix_web::Error> {
+    test::init_service(
+        App::new()
+            .wrap(crate::api_envelope::ApiEnvelope)
+            .app_data(web::Data::new(rb.clone()))
+            .app_data(web::Data::new(ai_service))
+            .configure(|cfg| crate::routes::configure(cfg, config)),
+    )
+    .await
+}
+
+/// 已註冊並登入的測試使用者
+#[derive(Debug, Clone)]
+pub struct TestUser {
+    pub id: String,
+    pub token: String,
+}
+
+impl TestUser {
+    pub fn auth(&self) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", self.token))
+    }
+}
+
+/// 透過註冊 API 建立使用者，回傳 user id
+pub async fn register_user<S>(app: &S, name: &str, email: &str, password: &str) -> String
+where
+    S: Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/users")
+        .set_json(json!({"name": name, "email": email, "password": password}))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(app, req).await;
+    assert_eq!(body["success"], true, "註冊失敗: {}", body);
+    body["data"]["id"].as_str().unwrap().to_string()
+}
+
+/// 透過登入 API 取得 JWT
+pub async fn login<S>(app: &S, email: &str, password: &str) -> String
+where
+    S: Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({"email": email, "password": password}))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(app, req).await;
+    assert_eq!(body["success"], true, "登入失敗: {}", body);
+    body["data"]["token"].as_str().unwrap().to_string()
+}
+
+/// 註冊並登入，email 由名稱產生
+pub async fn create_user<S>(app: &S, name: &str) -> TestUser
+where
+    S: Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let email = format!("{}@lifeup.test", name);
+    let id = register_user(app, name, &email, TEST_PASSWORD).await;
+    let token = login(app, &email, TEST_PASSWORD).await;
+    TestUser { id, token }
+}
+
+/// 將 create_user 建立的使用者加入 ADMIN_EMAILS（附加在既有名單後，平行執行的測試互不覆蓋）
+pub fn grant_admin(name: &str) {
+    static ADMIN_EMAILS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = ADMIN_EMAILS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut emails = std::env::var("ADMIN_EMAILS").unwrap_or_default();
+    if !emails.is_empty() {
+        emails.push(',');
+    }
+    emails.push_str(&format!("{}@lifeup.test", name));
+    std::env::set_var("ADMIN_EMAILS", emails);
+}
+
+/// 送出請求並回傳狀態碼與 JSON 內容
+pub async fn call_json<S>(app: &S, req: Request) -> (actix_web::http::StatusCode, Value)
+where
+    S: Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let resp = test::call_service(app, req).await;
+    let status = resp.status();
+    let body: Value = test::read_body_json(resp).await;
+    (status, body)
+}
+
+/// 等待背景任務（tokio::spawn）完成寫入，條件成立或逾時後返回
+pub async fn wait_until<F, Fut>(mut condition: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for _ in 0..100 {
+        if condition().await {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    false
+}
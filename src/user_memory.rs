@@ -0,0 +1,576 @@
+// 教練記憶：使用者希望教練長期記住的簡短事實（例如「上夜班，早上才睡覺」）
+//
+// source = user 為使用者自行新增；inferred 為 AI 從聊天紀錄整理出的提議，使用者確認後才會生效。
+// 個性化聊天每則訊息重新讀取啟用且已確認的記憶放進系統提示詞，超過 token 預算時從最舊的開始捨去，
+// 因此修改後下一則訊息即生效。GET /api/users/{id}/memories 回傳所有記憶與實際提供給教練的文字。
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use rbatis::RBatis;
+use rbs::value;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ai_service::SharedAIService;
+use crate::ai_tasks::ApiResponse;
+use crate::models::UserMemory;
+
+pub const SOURCE_USER: &str = "user";
+pub const SOURCE_INFERRED: &str = "inferred";
+
+/// 系統提示詞中記憶區塊的 token 上限（以 career_trace::estimate_tokens 估算）
+pub const PROMPT_TOKEN_BUDGET: i64 = 200;
+const MAX_CONTENT_CHARS: usize = 200;
+// 每位使用者最多保存的記憶數（含待確認的提議）
+const MAX_MEMORIES: usize = 100;
+// 整理記憶時讀取的最近使用者訊息數
+const EXTRACT_MESSAGE_LIMIT: i64 = 30;
+// 單次整理最多提議的記憶數
+const MAX_PROPOSALS: usize = 5;
+
+const PROMPT_HEADER: &str = "【使用者請你長期記住的事】（使用者親自提供或確認，給建議時務必遵守）";
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMemoryRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateMemoryRequest {
+    pub content: Option<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryView {
+    #[serde(flatten)]
+    pub memory: UserMemory,
+    // 是否包含在目前提供給教練的記憶區塊（停用、待確認或超過預算時為 false）
+    pub in_prompt: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryList {
+    pub memories: Vec<MemoryView>,
+    // 下一則訊息時教練實際看到的記憶區塊；沒有可用的記憶時為 null
+    pub prompt: Option<String>,
+    pub prompt_tokens: i64,
+    pub token_budget: i64,
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: message.into(),
+    })
+}
+
+fn is_usable(memory: &UserMemory) -> bool {
+    memory.active == Some(true) && memory.confirmed == Some(true)
+}
+
+/// 組成記憶區塊：由新到舊放入預算內的記憶，輸出時依建立時間排列；回傳區塊與納入的記憶 id
+pub fn render(memories: &[UserMemory], token_budget: i64) -> (Option<String>, Vec<String>) {
+    let mut usable: Vec<&UserMemory> = memories.iter().filter(|m| is_usable(m)).collect();
+    usable.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+
+    let mut tokens = crate::career_trace::estimate_tokens(PROMPT_HEADER);
+    let mut included = Vec::new();
+    for memory in usable {
+        let line = format!("- {}", memory.content.as_deref().unwrap_or_default());
+        let cost = crate::career_trace::estimate_tokens(&line) + 1;
+        if tokens + cost > token_budget {
+            break;
+        }
+        tokens += cost;
+        included.push(memory);
+    }
+    if included.is_empty() {
+        return (None, Vec::new());
+    }
+    included.reverse();
+    let lines: Vec<String> = included
+        .iter()
+        .map(|m| format!("- {}", m.content.as_deref().unwrap_or_default()))
+        .collect();
+    let ids = included.iter().filter_map(|m| m.id.clone()).collect();
+    (Some(format!("{}\n{}", PROMPT_HEADER, lines.join("\n"))), ids)
+}
+
+async fn user_memories(rb: &RBatis, user_id: &str) -> Result<Vec<UserMemory>, rbatis::Error> {
+    let mut memories = UserMemory::select_by_map(rb, value!{"user_id": user_id}).await?;
+    memories.sort_by_key(|m| m.created_at);
+    Ok(memories)
+}
+
+/// 個性化聊天使用的記憶區塊；沒有可用記憶或查詢失敗時回傳 None
+pub async fn prompt_block(rb: &RBatis, user_id: &str) -> Option<String> {
+    match user_memories(rb, user_id).await {
+        Ok(memories) => {
+            let (block, ids) = render(&memories, PROMPT_TOKEN_BUDGET);
+            if block.is_some() {
+                log::info!("教練記憶: 用戶 {}，提供 {} 則", user_id, ids.len());
+            }
+            block
+        }
+        Err(e) => {
+            log::warn!("讀取教練記憶失敗: {}", e);
+            None
+        }
+    }
+}
+
+fn validate_content(content: &str) -> std::result::Result<String, HttpResponse> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(error_response(StatusCode::BAD_REQUEST, "記憶內容不可為空"));
+    }
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("記憶內容不可超過 {} 字", MAX_CONTENT_CHARS),
+        ));
+    }
+    Ok(content.to_string())
+}
+
+// 比對重複時忽略大小寫與空白
+fn normalize(content: &str) -> String {
+    content.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect()
+}
+
+async fn load_memory(rb: &RBatis, user_id: &str, memory_id: &str) -> std::result::Result<UserMemory, HttpResponse> {
+    match UserMemory::select_by_map(rb, value!{"id": memory_id, "user_id": user_id}).await {
+        Ok(mut rows) if !rows.is_empty() => Ok(rows.remove(0)),
+        Ok(_) => Err(error_response(StatusCode::NOT_FOUND, "記憶不存在")),
+        Err(e) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢記憶失敗: {}", e))),
+    }
+}
+
+async fn insert_memory(rb: &RBatis, user_id: &str, content: String, source: &str) -> Result<UserMemory, rbatis::Error> {
+    let now = Utc::now();
+    let memory = UserMemory {
+        id: Some(Uuid::new_v4().to_string()),
+        user_id: Some(user_id.to_string()),
+        content: Some(content),
+        source: Some(source.to_string()),
+        active: Some(true),
+        // AI 整理出的記憶需使用者確認
+        confirmed: Some(source == SOURCE_USER),
+        created_at: Some(now),
+        updated_at: Some(now),
+    };
+    UserMemory::insert(rb, &memory).await?;
+    Ok(memory)
+}
+
+/// 列出所有記憶（含停用與待確認）與教練實際看到的記憶區塊
+/// GET /api/users/{id}/memories
+pub async fn list_memories(http_req: HttpRequest, rb: web::Data<RBatis>, path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let memories = match user_memories(rb.get_ref(), &user_id).await {
+        Ok(memories) => memories,
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢記憶失敗: {}", e))),
+    };
+    let (prompt, included) = render(&memories, PROMPT_TOKEN_BUDGET);
+    let prompt_tokens = prompt.as_deref().map(crate::career_trace::estimate_tokens).unwrap_or(0);
+    let memories = memories
+        .into_iter()
+        .map(|memory| {
+            let in_prompt = memory.id.as_ref().is_some_and(|id| included.contains(id));
+            MemoryView { memory, in_prompt }
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(MemoryList { memories, prompt, prompt_tokens, token_budget: PROMPT_TOKEN_BUDGET }),
+        message: "獲取教練記憶成功".to_string(),
+    }))
+}
+
+/// 新增記憶（使用者提供，立即生效）
+/// POST /api/users/{id}/memories
+pub async fn create_memory(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    req: web::Json<CreateMemoryRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let content = match validate_content(&req.content) {
+        Ok(content) => content,
+        Err(response) => return Ok(response),
+    };
+    let existing = match user_memories(rb.get_ref(), &user_id).await {
+        Ok(memories) => memories,
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢記憶失敗: {}", e))),
+    };
+    if existing.len() >= MAX_MEMORIES {
+        return Ok(error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("記憶最多 {} 則，請先刪除不需要的記憶", MAX_MEMORIES),
+        ));
+    }
+    match insert_memory(rb.get_ref(), &user_id, content, SOURCE_USER).await {
+        Ok(memory) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            data: Some(memory),
+            message: "記憶已新增".to_string(),
+        })),
+        Err(e) => Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("新增記憶失敗: {}", e))),
+    }
+}
+
+/// 修改記憶內容或啟用狀態
+/// PATCH /api/users/{id}/memories/{memory_id}
+pub async fn update_memory(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+    req: web::Json<UpdateMemoryRequest>,
+) -> Result<HttpResponse> {
+    let (user_id, memory_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let mut memory = match load_memory(rb.get_ref(), &user_id, &memory_id).await {
+        Ok(memory) => memory,
+        Err(response) => return Ok(response),
+    };
+    if let Some(content) = &req.content {
+        match validate_content(content) {
+            Ok(content) => memory.content = Some(content),
+            Err(response) => return Ok(response),
+        }
+    }
+    if let Some(active) = req.active {
+        memory.active = Some(active);
+    }
+    memory.updated_at = Some(Utc::now());
+    if let Err(e) = UserMemory::update_by_map(rb.get_ref(), &memory, value!{"id": &memory_id}).await {
+        return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("更新記憶失敗: {}", e)));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(memory),
+        message: "記憶已更新".to_string(),
+    }))
+}
+
+/// 確認 AI 整理出的記憶，確認後才會提供給教練
+/// POST /api/users/{id}/memories/{memory_id}/confirm
+pub async fn confirm_memory(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, memory_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let mut memory = match load_memory(rb.get_ref(), &user_id, &memory_id).await {
+        Ok(memory) => memory,
+        Err(response) => return Ok(response),
+    };
+    memory.confirmed = Some(true);
+    memory.active = Some(true);
+    memory.updated_at = Some(Utc::now());
+    if let Err(e) = UserMemory::update_by_map(rb.get_ref(), &memory, value!{"id": &memory_id}).await {
+        return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("確認記憶失敗: {}", e)));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(memory),
+        message: "記憶已確認".to_string(),
+    }))
+}
+
+/// 刪除記憶（也用於拒絕 AI 的提議）
+/// DELETE /api/users/{id}/memories/{memory_id}
+pub async fn delete_memory(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, memory_id) = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    if let Err(response) = load_memory(rb.get_ref(), &user_id, &memory_id).await {
+        return Ok(response);
+    }
+    if let Err(e) = UserMemory::delete_by_map(rb.get_ref(), value!{"id": &memory_id}).await {
+        return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("刪除記憶失敗: {}", e)));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        success: true,
+        data: None,
+        message: "記憶已刪除".to_string(),
+    }))
+}
+
+fn build_extract_prompt(messages: &[String], existing: &[UserMemory]) -> String {
+    let existing: Vec<String> = existing
+        .iter()
+        .filter_map(|m| m.content.as_deref())
+        .map(|content| format!("- {}", content))
+        .collect();
+    format!(
+        "以下是使用者最近對生活教練說的話（由舊到新）。請找出值得長期記住、會影響建議的個人事實，\
+例如作息、工作型態、健康限制、家庭狀況與長期目標。只列出使用者明確說過的事，不要推測，\
+每則一句、不超過 {} 字；已記住的事不要重複，最多 {} 則，沒有就回傳空陣列。\n\
+只回傳 JSON：{{\"memories\": [\"...\"]}}\n\n已記住的事：\n{}\n\n使用者的訊息：\n{}\n\n{}",
+        MAX_CONTENT_CHARS,
+        MAX_PROPOSALS,
+        if existing.is_empty() { "（無）".to_string() } else { existing.join("\n") },
+        messages.iter().map(|m| format!("- {}", m)).collect::<Vec<_>>().join("\n"),
+        crate::language::directive(),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct RawProposals {
+    #[serde(default)]
+    memories: Vec<String>,
+}
+
+/// 解析 AI 的提議：去除空白與過長的項目，以及與既有記憶重複者
+fn parse_proposals(reply: &str, existing: &[UserMemory]) -> std::result::Result<Vec<String>, String> {
+    let cleaned = reply
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let parsed: RawProposals = serde_json::from_str(cleaned).map_err(|e| format!("AI 回應格式錯誤: {}", e))?;
+    let mut seen: std::collections::HashSet<String> =
+        existing.iter().filter_map(|m| m.content.as_deref()).map(normalize).collect();
+    Ok(parsed
+        .memories
+        .into_iter()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty() && content.chars().count() <= MAX_CONTENT_CHARS)
+        .filter(|content| seen.insert(normalize(content)))
+        .take(MAX_PROPOSALS)
+        .collect())
+}
+
+/// 由 AI 從最近的聊天訊息整理記憶提議（source = inferred，確認前不會提供給教練）
+/// POST /api/users/{id}/memories/extract
+pub async fn extract_memories(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    ai: web::Data<SharedAIService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    let rows: Vec<serde_json::Value> = match rb
+        .query_decode(
+            "SELECT content FROM chat_message WHERE user_id = ? AND role = 'user' ORDER BY created_at DESC LIMIT ?",
+            vec![rbs::Value::String(user_id.clone()), rbs::Value::I64(EXTRACT_MESSAGE_LIMIT)],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢聊天紀錄失敗: {}", e))),
+    };
+    let mut messages: Vec<String> = rows
+        .iter()
+        .filter_map(|row| row["content"].as_str())
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+        .collect();
+    messages.reverse();
+    if messages.is_empty() {
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(Vec::<UserMemory>::new()),
+            message: "沒有可整理的聊天紀錄".to_string(),
+        }));
+    }
+
+    let existing = match user_memories(rb.get_ref(), &user_id).await {
+        Ok(memories) => memories,
+        Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查詢記憶失敗: {}", e))),
+    };
+    let prompt = build_extract_prompt(&messages, &existing);
+    let reply = match ai.get() {
+        Ok(service) => service.generate_with_model(ai.small_model(), &prompt).await,
+        Err(e) => Err(e),
+    };
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(e) => return Ok(crate::ai_tasks::ai_failure_response("AI 整理記憶失敗", &e)),
+    };
+    let proposals = match parse_proposals(&reply, &existing) {
+        Ok(proposals) => proposals,
+        Err(message) => {
+            log::warn!("{}: {}", message, reply);
+            return Ok(error_response(StatusCode::BAD_GATEWAY, message));
+        }
+    };
+
+    let room = MAX_MEMORIES.saturating_sub(existing.len());
+    let mut saved = Vec::new();
+    for content in proposals.into_iter().take(room) {
+        match insert_memory(rb.get_ref(), &user_id, content, SOURCE_INFERRED).await {
+            Ok(memory) => saved.push(memory),
+            Err(e) => return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("儲存記憶提議失敗: {}", e))),
+        }
+    }
+    log::info!("教練記憶: 用戶 {} 整理出 {} 則提議", user_id, saved.len());
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: format!("整理出 {} 則記憶，確認後才會提供給教練", saved.len()),
+        data: Some(saved),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_ai::MockAIService;
+    use crate::test_utils::{self, call_json};
+    use chrono::{Duration, TimeZone};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn memory(id: &str, content: &str, minutes: i64, active: bool, confirmed: bool) -> UserMemory {
+        UserMemory {
+            id: Some(id.to_string()),
+            user_id: Some("u1".to_string()),
+            content: Some(content.to_string()),
+            source: Some(SOURCE_USER.to_string()),
+            active: Some(active),
+            confirmed: Some(confirmed),
+            created_at: Some(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes)),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_render_trims_oldest_within_budget() {
+        let memories = vec![
+            memory("old", &"很久以前的事".repeat(10), 0, true, true),
+            memory("off", "已停用", 1, false, true),
+            memory("pending", "待確認", 2, true, false),
+            memory("mid", "上夜班，早上才睡覺", 3, true, true),
+            memory("new", "膝蓋受傷，避免跑步", 4, true, true),
+        ];
+        let (block, ids) = render(&memories, 80);
+        let block = block.unwrap();
+        assert_eq!(ids, vec!["mid".to_string(), "new".to_string()]);
+        assert!(block.ends_with("- 上夜班，早上才睡覺\n- 膝蓋受傷，避免跑步"), "{}", block);
+        assert!(crate::career_trace::estimate_tokens(&block) <= 80);
+
+        let (block, ids) = render(&memories, 500);
+        assert_eq!(ids.len(), 3);
+        assert!(block.unwrap().contains("很久以前的事"));
+        assert_eq!(render(&memories[1..3], 500), (None, Vec::new()));
+    }
+
+    #[actix_web::test]
+    async fn test_memories_reach_next_message_and_inferred_need_confirmation() {
+        let rb = test_utils::setup_db().await;
+        let mock = MockAIService::with_replies(&[r#"{"memories": ["上夜班，早上才睡覺", "喜歡爬山"]}"#]);
+        let app = test_utils::init_app_with_ai(&rb, Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "night-shift").await;
+        let other = test_utils::create_user(&app, "memory-snoop").await;
+        let uri = format!("/api/users/{}/memories", user.id);
+
+        // 從聊天整理出的記憶先列為待確認
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/chat/save-message")
+            .insert_header(user.auth())
+            .set_json(json!({"user_id": user.id, "role": "user", "content": "我上夜班，早上才睡覺"}))
+            .to_request();
+        call_json(&app, req).await;
+        let req = actix_web::test::TestRequest::post().uri(&format!("{}/extract", uri)).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert_eq!(body["data"][0]["source"], SOURCE_INFERRED);
+        assert_eq!(body["data"][0]["confirmed"], false);
+        assert!(mock.prompts("generate_with_model")[0].contains("我上夜班，早上才睡覺"));
+        let night_shift = body["data"][0]["id"].as_str().unwrap().to_string();
+        let hiking = body["data"][1]["id"].as_str().unwrap().to_string();
+
+        let chat = |message: &str| {
+            actix_web::test::TestRequest::post()
+                .uri("/api/chat/personality")
+                .insert_header(user.auth())
+                .set_json(json!({"message": message, "user_id": user.id}))
+                .to_request()
+        };
+        call_json(&app, chat("幫我排早上的計畫")).await;
+        let prompt = mock.prompts("generate_task_preview_with_history").pop().unwrap();
+        assert!(!prompt.contains("上夜班"), "未確認的記憶不應提供給教練: {}", prompt);
+
+        // 確認一則、拒絕一則，並自行新增一則
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/{}/confirm", uri, night_shift))
+            .insert_header(user.auth())
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let req = actix_web::test::TestRequest::delete().uri(&format!("{}/{}", uri, hiking)).insert_header(user.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let req = actix_web::test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({"content": "不喝咖啡"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let coffee = body["data"]["id"].as_str().unwrap().to_string();
+
+        call_json(&app, chat("幫我排早上的計畫")).await;
+        let prompt = mock.prompts("generate_task_preview_with_history").pop().unwrap();
+        assert!(prompt.contains("- 上夜班，早上才睡覺\n- 不喝咖啡"), "{}", prompt);
+        assert!(!prompt.contains("喜歡爬山"), "{}", prompt);
+
+        // 列表顯示教練看到的完整內容
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["data"]["memories"].as_array().unwrap().len(), 2);
+        assert!(prompt.contains(body["data"]["prompt"].as_str().unwrap()));
+        assert_eq!(body["data"]["memories"][0]["in_prompt"], true);
+
+        // 停用後下一則訊息即不再提供
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", uri, coffee))
+            .insert_header(user.auth())
+            .set_json(json!({"active": false}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        call_json(&app, chat("晚餐吃什麼")).await;
+        let prompt = mock.prompts("generate_task_preview_with_history").pop().unwrap();
+        assert!(prompt.contains("上夜班"), "{}", prompt);
+        assert!(!prompt.contains("不喝咖啡"), "{}", prompt);
+
+        // 其他使用者無法讀取或修改
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("/api/users/{}/memories/{}", other.id, night_shift))
+            .insert_header(other.auth())
+            .to_request();
+        let (status, _) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
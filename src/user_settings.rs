@@ -0,0 +1,610 @@
+// 使用者設定整合 API：把分散在各處的偏好整理成單一 JSON 文件
+//
+// 儲存位置不變，原本的專用 API 仍讀寫同一份資料：
+// - 時區、語系、週起始日、自動生成成就、教練資料分享、資料保留天數：user_settings
+// - 排行榜公開：user_profile.leaderboard_visible（/leaderboard-visibility）
+// - 勿擾時段：user_notification_settings.quiet_hours_*（/notification-settings）
+// PATCH 只合併有帶的欄位，並逐一驗證；未知欄位直接拒絕。
+
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
+use chrono::{FixedOffset, NaiveTime, Utc};
+use rbatis::RBatis;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::ai_tasks::ApiResponse;
+use crate::data_retention::RetentionPolicy;
+use crate::week_start::WeekStart;
+
+/// 未設定時的時區（DEFAULT_USER_TIMEZONE，見 local_date::default_timezone）
+pub fn default_timezone_setting() -> String {
+    crate::local_date::default_timezone().to_string()
+}
+/// 支援的語系，第一個為預設（同時決定 AI 輸出語言與回應訊息語系，見 language）
+pub const LOCALES: [&str; 4] = ["zh-TW", "zh-CN", "en", "ja"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    // HH:MM，可跨午夜（例如 23:00 ~ 07:00）
+    pub start: String,
+    pub end: String,
+}
+
+/// 完整的設定文件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserSettingsDocument {
+    // UTC 偏移（例如 +08:00）；task_date、連續天數與每日統計都以此時區的日期為準
+    pub timezone: String,
+    pub locale: String,
+    // 每週統計的起始日（mon / sun）
+    pub week_start: WeekStart,
+    pub leaderboard_visible: bool,
+    // 建立任務時自動生成成就
+    pub achievement_autogen: bool,
+    // 教練對話時附上等級、技能、今日待辦等狀況（預設開啟）
+    pub share_with_coach: bool,
+    pub retention: RetentionPolicy,
+    // null 代表不啟用勿擾時段
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// 介面需要的設定子集，附在遊戲化資料（含首頁 profile 區塊）中，免去額外請求
+#[derive(Debug, Serialize)]
+pub struct UiSettings {
+    pub timezone: String,
+    pub locale: String,
+    pub week_start: WeekStart,
+    pub leaderboard_visible: bool,
+    pub share_with_coach: bool,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl From<UserSettingsDocument> for UiSettings {
+    fn from(document: UserSettingsDocument) -> Self {
+        UiSettings {
+            timezone: document.timezone,
+            locale: document.locale,
+            week_start: document.week_start,
+            leaderboard_visible: document.leaderboard_visible,
+            share_with_coach: document.share_with_coach,
+            quiet_hours: document.quiet_hours,
+        }
+    }
+}
+
+// 可清空欄位的三種狀態：未提供 = None、null = Some(None)、有值 = Some(Some(v))
+fn nullable<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Some(Option::deserialize(deserializer)?))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionPatch {
+    #[serde(default, deserialize_with = "nullable")]
+    pub chat_days: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub notification_days: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub attribute_history_days: Option<Option<i64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserSettingsPatch {
+    pub timezone: Option<String>,
+    pub locale: Option<String>,
+    pub week_start: Option<WeekStart>,
+    pub leaderboard_visible: Option<bool>,
+    pub achievement_autogen: Option<bool>,
+    pub share_with_coach: Option<bool>,
+    pub retention: Option<RetentionPatch>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub quiet_hours: Option<Option<QuietHours>>,
+}
+
+/// 驗證 UTC 偏移格式 ±HH:MM（-12:00 ~ +14:00，分鐘為 00 / 30 / 45）
+pub fn validate_timezone(value: &str) -> std::result::Result<(), String> {
+    let invalid = || format!("時區格式錯誤: {}（需為 ±HH:MM，例如 +08:00）", value);
+    let (sign, rest) = match value.split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = rest.split_once(':').ok_or_else(invalid)?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return Err(invalid());
+    }
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    let offset = sign * (hours * 60 + minutes);
+    if !matches!(minutes, 0 | 30 | 45) || !(-12 * 60..=14 * 60).contains(&offset) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+fn validate_quiet_hours(quiet_hours: &QuietHours) -> std::result::Result<(), String> {
+    for time in [&quiet_hours.start, &quiet_hours.end] {
+        if NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+            return Err(format!("勿擾時段格式錯誤: {}（需為 HH:MM）", time));
+        }
+    }
+    if quiet_hours.start == quiet_hours.end {
+        return Err("勿擾時段的開始與結束不可相同".to_string());
+    }
+    Ok(())
+}
+
+/// 把 PATCH 合併進目前的設定，只處理有帶的欄位；任何欄位驗證失敗則整份拒絕
+pub fn apply_patch(
+    mut document: UserSettingsDocument,
+    patch: UserSettingsPatch,
+) -> std::result::Result<UserSettingsDocument, String> {
+    if let Some(timezone) = patch.timezone {
+        validate_timezone(&timezone)?;
+        document.timezone = timezone;
+    }
+    if let Some(locale) = patch.locale {
+        if !LOCALES.contains(&locale.as_str()) {
+            return Err(format!("不支援的語系: {}（可用: {}）", locale, LOCALES.join(", ")));
+        }
+        document.locale = locale;
+    }
+    if let Some(week_start) = patch.week_start {
+        document.week_start = week_start;
+    }
+    if let Some(visible) = patch.leaderboard_visible {
+        document.leaderboard_visible = visible;
+    }
+    if let Some(enabled) = patch.achievement_autogen {
+        document.achievement_autogen = enabled;
+    }
+    if let Some(enabled) = patch.share_with_coach {
+        document.share_with_coach = enabled;
+    }
+    if let Some(retention) = patch.retention {
+        if let Some(days) = retention.chat_days {
+            document.retention.chat_days = days;
+        }
+        if let Some(days) = retention.notification_days {
+            document.retention.notification_days = days;
+        }
+        if let Some(days) = retention.attribute_history_days {
+            document.retention.attribute_history_days = days;
+        }
+        document.retention.validate()?;
+    }
+    if let Some(quiet_hours) = patch.quiet_hours {
+        if let Some(quiet_hours) = &quiet_hours {
+            validate_quiet_hours(quiet_hours)?;
+        }
+        document.quiet_hours = quiet_hours;
+    }
+    Ok(document)
+}
+
+/// 讀取設定文件；使用者不存在時回傳 None
+pub async fn load_settings(rb: &RBatis, user_id: &str) -> std::result::Result<Option<UserSettingsDocument>, rbatis::Error> {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT s.timezone, s.locale, s.week_start, s.achievement_autogen, s.share_with_coach,
+                    s.chat_retention_days, s.notification_retention_days, s.attribute_history_retention_days,
+                    p.leaderboard_visible, n.quiet_hours_start, n.quiet_hours_end
+             FROM user u
+             LEFT JOIN user_settings s ON s.user_id = u.id
+             LEFT JOIN user_profile p ON p.user_id = u.id
+             LEFT JOIN user_notification_settings n ON n.user_id = u.id
+             WHERE u.id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await?;
+    let Some(row) = rows.first() else {
+        return Ok(None);
+    };
+    let text = |key: &str| row[key].as_str().filter(|s| !s.is_empty()).map(str::to_string);
+    Ok(Some(UserSettingsDocument {
+        timezone: text("timezone").unwrap_or_else(default_timezone_setting),
+        locale: text("locale").unwrap_or_else(|| LOCALES[0].to_string()),
+        week_start: WeekStart::from_db(text("week_start").as_deref()),
+        leaderboard_visible: row["leaderboard_visible"].as_i64() == Some(1),
+        achievement_autogen: row["achievement_autogen"].as_i64() == Some(1),
+        share_with_coach: row["share_with_coach"].as_i64() != Some(0),
+        retention: RetentionPolicy {
+            chat_days: row["chat_retention_days"].as_i64(),
+            notification_days: row["notification_retention_days"].as_i64(),
+            attribute_history_days: row["attribute_history_retention_days"].as_i64(),
+        },
+        quiet_hours: match (text("quiet_hours_start"), text("quiet_hours_end")) {
+            (Some(start), Some(end)) => Some(QuietHours { start, end }),
+            _ => None,
+        },
+    }))
+}
+
+/// 遊戲化資料與首頁使用的設定子集（使用者不存在時為預設值）
+pub async fn load_ui_settings(rb: &RBatis, user_id: &str) -> std::result::Result<UiSettings, rbatis::Error> {
+    Ok(load_settings(rb, user_id).await?.map(UiSettings::from).unwrap_or_else(|| UiSettings {
+        timezone: default_timezone_setting(),
+        locale: LOCALES[0].to_string(),
+        week_start: WeekStart::default(),
+        leaderboard_visible: false,
+        share_with_coach: true,
+        quiet_hours: None,
+    }))
+}
+
+/// 使用者的時區；未設定、格式錯誤或查詢失敗時使用預設時區
+pub async fn user_timezone(rb: &RBatis, user_id: &str) -> FixedOffset {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT timezone FROM user_settings WHERE user_id = ?",
+            vec![rbs::Value::String(user_id.to_string())],
+        )
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("讀取使用者 {} 的時區失敗: {}", user_id, e);
+            Vec::new()
+        });
+    rows.first()
+        .and_then(|row| row["timezone"].as_str())
+        .and_then(crate::local_date::parse_utc_offset)
+        .unwrap_or_else(crate::local_date::default_timezone)
+}
+
+fn optional_i64(value: Option<i64>) -> rbs::Value {
+    value.map(rbs::Value::I64).unwrap_or(rbs::Value::Null)
+}
+
+// 在同一個交易中寫回各自的資料表；排行榜與勿擾時段只在有帶時才寫入
+async fn save_settings(
+    rb: &RBatis,
+    user_id: &str,
+    document: &UserSettingsDocument,
+    patch_leaderboard: bool,
+    patch_quiet_hours: bool,
+) -> std::result::Result<(), rbatis::Error> {
+    let now = Utc::now().to_rfc3339();
+    let tx = rb.acquire_begin().await?;
+    let result: std::result::Result<(), rbatis::Error> = async {
+        tx.exec(
+            "INSERT INTO user_settings (user_id, timezone, locale, week_start, achievement_autogen, share_with_coach,
+                 chat_retention_days, notification_retention_days, attribute_history_retention_days, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(user_id) DO UPDATE SET
+                 timezone = excluded.timezone,
+                 locale = excluded.locale,
+                 week_start = excluded.week_start,
+                 achievement_autogen = excluded.achievement_autogen,
+                 share_with_coach = excluded.share_with_coach,
+                 chat_retention_days = excluded.chat_retention_days,
+                 notification_retention_days = excluded.notification_retention_days,
+                 attribute_history_retention_days = excluded.attribute_history_retention_days,
+                 updated_at = excluded.updated_at",
+            vec![
+                rbs::Value::String(user_id.to_string()),
+                rbs::Value::String(document.timezone.clone()),
+                rbs::Value::String(document.locale.clone()),
+                rbs::Value::String(document.week_start.as_str().to_string()),
+                rbs::Value::I32(document.achievement_autogen as i32),
+                rbs::Value::I32(document.share_with_coach as i32),
+                optional_i64(document.retention.chat_days),
+                optional_i64(document.retention.notification_days),
+                optional_i64(document.retention.attribute_history_days),
+                rbs::Value::String(now.clone()),
+            ],
+        )
+        .await?;
+        if patch_leaderboard {
+            tx.exec(
+                "UPDATE user_profile SET leaderboard_visible = ?, updated_at = ? WHERE user_id = ?",
+                vec![
+                    rbs::Value::I32(document.leaderboard_visible as i32),
+                    rbs::Value::String(now.clone()),
+                    rbs::Value::String(user_id.to_string()),
+                ],
+            )
+            .await?;
+        }
+        if patch_quiet_hours {
+            let (start, end) = match &document.quiet_hours {
+                Some(q) => (rbs::Value::String(q.start.clone()), rbs::Value::String(q.end.clone())),
+                None => (rbs::Value::Null, rbs::Value::Null),
+            };
+            tx.exec(
+                "INSERT INTO user_notification_settings (id, user_id, quiet_hours_start, quiet_hours_end, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(user_id) DO UPDATE SET
+                     quiet_hours_start = excluded.quiet_hours_start,
+                     quiet_hours_end = excluded.quiet_hours_end,
+                     updated_at = excluded.updated_at",
+                vec![
+                    rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+                    rbs::Value::String(user_id.to_string()),
+                    start,
+                    end,
+                    rbs::Value::String(now.clone()),
+                    rbs::Value::String(now),
+                ],
+            )
+            .await?;
+        }
+        Ok(())
+    }
+    .await;
+    match result {
+        Ok(()) => {
+            tx.commit().await?;
+            crate::gamified_cache::invalidate(user_id);
+            crate::language::invalidate(user_id);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: "用戶不存在".to_string(),
+    })
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message,
+    })
+}
+
+fn internal_error(action: &str, e: rbatis::Error) -> HttpResponse {
+    log::error!("{}使用者設定失敗: {}", action, e);
+    HttpResponse::InternalServerError().json(ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: format!("{}使用者設定失敗: {}", action, e),
+    })
+}
+
+/// 取得完整使用者設定
+pub async fn get_user_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    match load_settings(rb.get_ref(), &user_id).await {
+        Ok(Some(document)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(document),
+            message: "獲取使用者設定成功".to_string(),
+        })),
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(internal_error("獲取", e)),
+    }
+}
+
+/// 部分更新使用者設定（只合併有帶的欄位），回傳更新後的完整設定
+pub async fn patch_user_settings(
+    http_req: HttpRequest,
+    rb: web::Data<RBatis>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    if let Err(e) = crate::ownership::assert_self(crate::auth::current_user_id(&http_req).as_deref(), &user_id) {
+        return Ok(e.into_response());
+    }
+    if !body.is_object() {
+        return Ok(bad_request("設定必須是 JSON 物件".to_string()));
+    }
+    let patch: UserSettingsPatch = match serde_json::from_value(body.into_inner()) {
+        Ok(patch) => patch,
+        Err(e) => return Ok(bad_request(format!("設定格式錯誤: {}", e))),
+    };
+    let current = match load_settings(rb.get_ref(), &user_id).await {
+        Ok(Some(document)) => document,
+        Ok(None) => return Ok(not_found()),
+        Err(e) => return Ok(internal_error("獲取", e)),
+    };
+    let patch_leaderboard = patch.leaderboard_visible.is_some();
+    let patch_quiet_hours = patch.quiet_hours.is_some();
+    let document = match apply_patch(current, patch) {
+        Ok(document) => document,
+        Err(message) => return Ok(bad_request(message)),
+    };
+
+    match save_settings(rb.get_ref(), &user_id, &document, patch_leaderboard, patch_quiet_hours).await {
+        Ok(()) => {
+            // 本次回應就使用新的語系
+            if let Some(language) = crate::language::Language::from_code(&document.locale) {
+                http_req.extensions_mut().insert(language);
+            }
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(document),
+                message: "使用者設定已更新".to_string(),
+            }))
+        }
+        Err(e) => Ok(internal_error("更新", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, call_json};
+    use serde_json::json;
+
+    fn defaults() -> UserSettingsDocument {
+        UserSettingsDocument {
+            timezone: default_timezone_setting(),
+            locale: LOCALES[0].to_string(),
+            week_start: WeekStart::Monday,
+            leaderboard_visible: false,
+            achievement_autogen: false,
+            share_with_coach: true,
+            retention: RetentionPolicy::default(),
+            quiet_hours: None,
+        }
+    }
+
+    fn patch(value: serde_json::Value) -> std::result::Result<UserSettingsDocument, String> {
+        let patch: UserSettingsPatch = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        apply_patch(defaults(), patch)
+    }
+
+    #[test]
+    fn test_patch_validates_each_key() {
+        assert!(validate_timezone("+08:00").is_ok());
+        assert!(validate_timezone("-03:30").is_ok());
+        assert!(validate_timezone("+05:45").is_ok());
+        assert!(validate_timezone("+15:00").is_err());
+        assert!(validate_timezone("08:00").is_err());
+        assert!(validate_timezone("Asia/Taipei").is_err());
+
+        assert!(patch(json!({"locale": "ja"})).is_ok());
+        assert!(patch(json!({"locale": "ko"})).is_err());
+        assert!(patch(json!({"retention": {"chat_days": 1}})).is_err());
+        assert!(patch(json!({"quiet_hours": {"start": "25:00", "end": "07:00"}})).is_err());
+        assert!(patch(json!({"quiet_hours": {"start": "23:00"}})).is_err());
+        assert!(patch(json!({"theme": "dark"})).unwrap_err().contains("theme"));
+        assert!(patch(json!({"leaderboard_visible": "yes"})).is_err());
+        assert!(patch(json!({"week_start": "sat"})).is_err());
+        assert_eq!(patch(json!({"week_start": "sun"})).unwrap().week_start, WeekStart::Sunday);
+
+        let merged = patch(json!({"locale": "en", "retention": {"chat_days": 30}})).unwrap();
+        assert_eq!(merged.locale, "en");
+        assert_eq!(merged.retention.chat_days, Some(30));
+        assert_eq!(merged.timezone, default_timezone_setting());
+    }
+
+    #[actix_web::test]
+    async fn test_patch_merges_and_shares_storage_with_specialized_endpoints() {
+        let rb = test_utils::setup_db().await;
+        let app = test_utils::init_app(&rb).await;
+        let user = test_utils::create_user(&app, "settings").await;
+        let uri = format!("/api/users/{}/settings", user.id);
+
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(user.auth()).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["timezone"], default_timezone_setting());
+        assert!(body["data"]["quiet_hours"].is_null());
+
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({
+                "locale": "en",
+                "leaderboard_visible": true,
+                "retention": {"notification_days": 30},
+                "quiet_hours": {"start": "23:00", "end": "07:00"}
+            }))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["data"]["locale"], "en");
+
+        // 第二次只帶一個欄位，其餘維持
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({"retention": {"chat_days": 90}}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["data"]["retention"]["chat_days"], 90);
+        assert_eq!(body["data"]["retention"]["notification_days"], 30);
+        assert_eq!(body["data"]["quiet_hours"]["start"], "23:00");
+        assert_eq!(body["data"]["leaderboard_visible"], true);
+
+        // 驗證失敗時整份不寫入
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header(user.auth())
+            .set_json(json!({"locale": "zh-TW", "timezone": "UTC"}))
+            .to_request();
+        assert_eq!(call_json(&app, req).await.0, 400);
+
+        // 專用 API 讀到同一份資料
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/data-retention", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["chat_days"], 90);
+        assert_eq!(body["data"]["notification_days"], 30);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/users/{}/gamified", user.id))
+            .insert_header(user.auth())
+            .to_request();
+        let (_, body) = call_json(&app, req).await;
+        assert_eq!(body["data"]["settings"]["locale"], "en");
+        assert_eq!(body["data"]["settings"]["leaderboard_visible"], true);
+        assert_eq!(body["data"]["settings"]["quiet_hours"]["end"], "07:00");
+
+        let other = test_utils::create_user(&app, "settings-other").await;
+        let req = actix_web::test::TestRequest::get().uri(&uri).insert_header(other.auth()).to_request();
+        assert_eq!(call_json(&app, req).await.0, 403);
+    }
+
+    #[actix_web::test]
+    async fn test_locale_selects_ai_language_and_response_messages() {
+        let rb = test_utils::setup_db().await;
+        let mock = test_utils::mock_ai::MockAIService::with_replies(&["Start with ten minutes today."]);
+        let app = test_utils::init_app_with_ai(&rb, std::sync::Arc::new(mock.clone())).await;
+        let user = test_utils::create_user(&app, "english-speaker").await;
+
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("/api/users/{}/settings", user.id))
+            .insert_header(user.auth())
+            .set_json(json!({"locale": "en"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["message"], "Settings updated");
+
+        // 教練提示詞改用英文指示
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/chat/test-personality")
+            .insert_header(user.auth())
+            .set_json(json!({"message": "I keep skipping my reading", "personality_type": "harsh_critic"}))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 200, "{}", body);
+        let prompts = mock.prompts("generate_task_preview");
+        assert!(prompts[0].contains("Always respond in English."), "{}", prompts[0]);
+        assert!(!prompts[0].contains("繁體中文"), "{}", prompts[0]);
+
+        // 錯誤訊息也換成英文
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/tasks/no-such-task/progress")
+            .insert_header(user.auth())
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 404, "{}", body);
+        assert_eq!(body["message"], "Task not found");
+
+        // 未登入的請求依 Accept-Language
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/tasks/no-such-task/progress")
+            .insert_header(("Accept-Language", "ja-JP,ja;q=0.9"))
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, 401, "{}", body);
+        assert_eq!(body["message"], "Authorization ヘッダーがありません");
+    }
+}
@@ -0,0 +1,208 @@
+#![cfg(feature = "push-notifications")]
+
+// VAPID 金鑰管理：優先使用管理員輪替後存於資料庫的金鑰，否則使用環境變數
+// VAPID_PUBLIC_KEY 與 vapid_private.pem 私鑰檔。
+//
+// 輪替後舊金鑰簽署的訂閱全部失效，需由前端重新訂閱。
+
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use base64::Engine;
+use chrono::Utc;
+use openssl::bn::BigNumContext;
+use openssl::ec::{EcGroup, EcKey, PointConversionForm};
+use openssl::nid::Nid;
+use rbatis::RBatis;
+use serde::Serialize;
+
+const PRIVATE_KEY_FILE: &str = "vapid_private.pem";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    Database,
+    Environment,
+}
+
+#[derive(Debug, Clone)]
+pub struct VapidKeys {
+    pub public_key: String,
+    pub private_key_pem: String,
+    pub source: KeySource,
+}
+
+/// 最近一次推送結果（管理員狀態頁使用）
+#[derive(Debug, Clone, Serialize)]
+pub struct LastSendResult {
+    pub at: String,
+    pub success: bool,
+    pub endpoint: String,
+    pub error: Option<String>,
+}
+
+static ACTIVE_KEYS: OnceLock<RwLock<Option<VapidKeys>>> = OnceLock::new();
+static LAST_SEND: Mutex<Option<LastSendResult>> = Mutex::new(None);
+
+fn active_keys() -> &'static RwLock<Option<VapidKeys>> {
+    ACTIVE_KEYS.get_or_init(|| RwLock::new(keys_from_env()))
+}
+
+fn keys_from_env() -> Option<VapidKeys> {
+    let public_key = std::env::var("VAPID_PUBLIC_KEY").ok().filter(|k| !k.trim().is_empty())?;
+    let private_key_pem = std::fs::read_to_string(PRIVATE_KEY_FILE).ok()?;
+    Some(VapidKeys {
+        public_key: public_key.trim().to_string(),
+        private_key_pem,
+        source: KeySource::Environment,
+    })
+}
+
+/// 啟動時載入金鑰：資料庫中最新的輪替金鑰優先
+pub async fn load(rb: &RBatis) {
+    let rows: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT public_key, private_key_pem FROM vapid_key ORDER BY created_at DESC LIMIT 1",
+            vec![],
+        )
+        .await
+        .unwrap_or_default();
+    let stored = rows.first().and_then(|row| {
+        Some(VapidKeys {
+            public_key: row.get("public_key")?.as_str()?.to_string(),
+            private_key_pem: row.get("private_key_pem")?.as_str()?.to_string(),
+            source: KeySource::Database,
+        })
+    });
+
+    let keys = stored.or_else(keys_from_env);
+    match &keys {
+        Some(keys) => log::info!("VAPID 金鑰已載入（來源: {:?}）", keys.source),
+        None => log::warn!("⚠️ 未設定 VAPID 金鑰，Web Push 不會發送（通知仍會寫入通知中心）"),
+    }
+    if let Ok(mut active) = active_keys().write() {
+        *active = keys;
+    }
+}
+
+/// 目前使用的金鑰（未設定時為 None）
+pub fn current() -> Option<VapidKeys> {
+    active_keys().read().ok().and_then(|keys| keys.clone())
+}
+
+/// 產生新的 P-256 金鑰對：回傳 (base64url 公鑰, SEC1 PEM 私鑰)
+pub fn generate() -> Result<(String, String), openssl::error::ErrorStack> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = EcKey::generate(&group)?;
+    let mut ctx = BigNumContext::new()?;
+    let public_bytes = key.public_key().to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut ctx)?;
+    let private_pem = String::from_utf8_lossy(&key.private_key_to_pem()?).to_string();
+    Ok((base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(public_bytes), private_pem))
+}
+
+/// 產生並保存新金鑰、清除所有訂閱；回傳 (新公鑰, 受影響的使用者)
+pub async fn rotate(rb: &RBatis) -> Result<(String, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
+    let (public_key, private_key_pem) = generate()?;
+
+    let users: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT DISTINCT user_id FROM push_subscription WHERE user_id IS NOT NULL",
+            vec![],
+        )
+        .await?;
+    let affected_users: Vec<String> = users
+        .iter()
+        .filter_map(|row| row.get("user_id").and_then(|v| v.as_str()).map(str::to_string))
+        .collect();
+
+    rb.exec(
+        "INSERT INTO vapid_key (id, public_key, private_key_pem, created_at) VALUES (?, ?, ?, ?)",
+        vec![
+            rbs::Value::String(uuid::Uuid::new_v4().to_string()),
+            rbs::Value::String(public_key.clone()),
+            rbs::Value::String(private_key_pem.clone()),
+            rbs::Value::String(Utc::now().to_rfc3339()),
+        ],
+    )
+    .await?;
+    rb.exec("DELETE FROM push_subscription", vec![]).await?;
+
+    if let Ok(mut active) = active_keys().write() {
+        *active = Some(VapidKeys {
+            public_key: public_key.clone(),
+            private_key_pem,
+            source: KeySource::Database,
+        });
+    }
+    Ok((public_key, affected_users))
+}
+
+/// 記錄推送結果
+pub fn record_send_result(endpoint: &str, error: Option<String>) {
+    if let Ok(mut last) = LAST_SEND.lock() {
+        *last = Some(LastSendResult {
+            at: Utc::now().to_rfc3339(),
+            success: error.is_none(),
+            endpoint: endpoint.to_string(),
+            error,
+        });
+    }
+}
+
+pub fn last_send_result() -> Option<LastSendResult> {
+    LAST_SEND.lock().ok().and_then(|last| last.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_match() {
+        let (public_key, pem) = generate().unwrap();
+        let builder = web_push::VapidSignatureBuilder::from_pem_no_sub(pem.as_bytes()).unwrap();
+        let expected = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(builder.get_public_key());
+        assert_eq!(public_key, expected);
+        // 未壓縮的 P-256 公鑰為 65 bytes
+        assert_eq!(builder.get_public_key().len(), 65);
+    }
+
+    #[actix_web::test]
+    async fn test_rotate_persists_and_clears_subscriptions() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "vapid_rotate").await;
+        rb.exec(
+            "INSERT INTO push_subscription (id, user_id, endpoint, p256dh_key, auth_key) VALUES ('s1', ?, 'https://push.example/1', 'p', 'a')",
+            vec![rbs::Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        let (public_key, affected) = rotate(&rb).await.unwrap();
+        assert_eq!(affected, vec![user.id.clone()]);
+        assert_eq!(current().map(|k| k.public_key), Some(public_key.clone()));
+        let remaining: i64 = rb.query_decode("SELECT COUNT(*) AS count FROM push_subscription", vec![]).await.unwrap();
+        assert_eq!(remaining, 0);
+
+        load(&rb).await;
+        let keys = current().unwrap();
+        assert_eq!(keys.public_key, public_key);
+        assert_eq!(keys.source, KeySource::Database);
+    }
+
+    #[actix_web::test]
+    async fn test_push_admin_routes_require_admin() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "vapid_non_admin").await;
+
+        for req in [
+            actix_web::test::TestRequest::get().uri("/api/push/status"),
+            actix_web::test::TestRequest::post().uri("/api/admin/push/rotate-keys"),
+        ] {
+            let (status, body) = crate::test_utils::call_json(&app, req.insert_header(user.auth()).to_request()).await;
+            assert_eq!(status, 403);
+            assert_eq!(body["message"], "需要管理員權限");
+        }
+    }
+}
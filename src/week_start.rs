@@ -0,0 +1,262 @@
+// 週的起始日偏好（週一 / 週日）：週屬性快照與每週統計依此切分週
+//
+// 快照以 week_start_date（該週第一天，YYYY-MM-DD）為鍵；year / week_number 保留為該週週一所在的 ISO 週，
+// 僅供舊客戶端顯示。使用者改變偏好後，新快照以新的起始日儲存，舊快照維持原本的週不變。
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use rbatis::RBatis;
+use rbs::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::models::WeeklyAttributeSnapshot;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeekStart {
+    #[default]
+    #[serde(rename = "mon")]
+    Monday,
+    #[serde(rename = "sun")]
+    Sunday,
+}
+
+impl WeekStart {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WeekStart::Monday => "mon",
+            WeekStart::Sunday => "sun",
+        }
+    }
+
+    /// 資料庫值轉換；未設定或無法辨識時視為週一
+    pub fn from_db(value: Option<&str>) -> Self {
+        match value {
+            Some("sun") => WeekStart::Sunday,
+            _ => WeekStart::Monday,
+        }
+    }
+
+    fn first_weekday(&self) -> Weekday {
+        match self {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+        }
+    }
+
+    /// 日期所在週的第一天
+    pub fn week_start_of(&self, date: NaiveDate) -> NaiveDate {
+        date - Duration::days(date.weekday().days_since(self.first_weekday()) as i64)
+    }
+
+    /// 日期所在週的第一天與最後一天
+    pub fn week_bounds(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let start = self.week_start_of(date);
+        (start, start + Duration::days(6))
+    }
+}
+
+/// 快照的 (year, week_number)：該週週一所在的 ISO 週
+pub fn iso_key(week_start_date: NaiveDate) -> (i32, i32) {
+    let monday = WeekStart::Monday.week_start_of(week_start_date + Duration::days(1));
+    let iso_week = monday.iso_week();
+    (iso_week.year(), iso_week.week() as i32)
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// 讀取使用者的週起始日偏好
+pub async fn load(rb: &RBatis, user_id: &str) -> Result<WeekStart, rbatis::Error> {
+    let value: Option<String> = rb
+        .query_decode(
+            "SELECT week_start FROM user_settings WHERE user_id = ?",
+            vec![Value::String(user_id.to_string())],
+        )
+        .await?;
+    Ok(WeekStart::from_db(value.as_deref()))
+}
+
+/// 以起始日為 start 的那一週的屬性快照
+///
+/// 快照以起始日為鍵；偏好變更前的快照起始日會差一天，此時取重疊最多的那一週
+pub async fn find_snapshot(
+    rb: &RBatis,
+    user_id: &str,
+    start: NaiveDate,
+) -> Result<Option<WeeklyAttributeSnapshot>, rbatis::Error> {
+    let snapshots: Vec<WeeklyAttributeSnapshot> = rb
+        .query_decode(
+            "SELECT * FROM weekly_attribute_snapshot
+             WHERE user_id = ? AND week_start_date IN (?, ?, ?)
+             ORDER BY week_start_date = ? DESC LIMIT 1",
+            vec![
+                Value::String(user_id.to_string()),
+                Value::String(format_date(start - Duration::days(1))),
+                Value::String(format_date(start)),
+                Value::String(format_date(start + Duration::days(1))),
+                Value::String(format_date(start)),
+            ],
+        )
+        .await?;
+    Ok(snapshots.into_iter().next())
+}
+
+// 以 week_start_date 為唯一鍵的快照表（與 create_tables 相同）
+const SNAPSHOT_TABLE_SQL: &str = "
+    CREATE TABLE weekly_attribute_snapshot_new (
+        id TEXT PRIMARY KEY,
+        user_id TEXT NOT NULL,
+        week_start_date TEXT NOT NULL,
+        year INTEGER NOT NULL,
+        week_number INTEGER NOT NULL,
+        intelligence INTEGER DEFAULT 50,
+        endurance INTEGER DEFAULT 50,
+        creativity INTEGER DEFAULT 50,
+        social INTEGER DEFAULT 50,
+        focus INTEGER DEFAULT 50,
+        adaptability INTEGER DEFAULT 50,
+        created_at TEXT,
+        UNIQUE(user_id, week_start_date),
+        FOREIGN KEY (user_id) REFERENCES user (id)
+    )";
+
+/// 啟動遷移：補齊缺少的 week_start_date（由 year / week_number 推算 ISO 週一），
+/// 並把舊版以 (year, week_number) 為唯一鍵的快照表改為以 week_start_date 為鍵
+pub async fn migrate_weekly_snapshots(rb: &RBatis) -> Result<(), rbatis::Error> {
+    let missing: Vec<serde_json::Value> = rb
+        .query_decode(
+            "SELECT id, year, week_number FROM weekly_attribute_snapshot
+             WHERE week_start_date IS NULL OR TRIM(week_start_date) = '' OR date(week_start_date) IS NULL",
+            vec![],
+        )
+        .await?;
+    let mut backfilled = 0;
+    for row in &missing {
+        let (Some(id), Some(year), Some(week)) = (row["id"].as_str(), row["year"].as_i64(), row["week_number"].as_i64()) else {
+            continue;
+        };
+        let Some(start) = NaiveDate::from_isoywd_opt(year as i32, week as u32, Weekday::Mon) else {
+            log::warn!("週屬性快照 {} 的年份與週數無效（{} 年第 {} 週），無法補齊起始日", id, year, week);
+            continue;
+        };
+        rb.exec(
+            "UPDATE weekly_attribute_snapshot SET week_start_date = ? WHERE id = ?",
+            vec![Value::String(format_date(start)), Value::String(id.to_string())],
+        )
+        .await?;
+        backfilled += 1;
+    }
+    if backfilled > 0 {
+        log::info!("已為 {} 筆週屬性快照補齊 week_start_date", backfilled);
+    }
+
+    let table_sql: Option<String> = rb
+        .query_decode(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'weekly_attribute_snapshot'",
+            vec![],
+        )
+        .await?;
+    let keyed_by_week_number = table_sql
+        .map(|sql| sql.split_whitespace().collect::<String>().contains("UNIQUE(user_id,year,week_number)"))
+        .unwrap_or(false);
+    if !keyed_by_week_number {
+        return Ok(());
+    }
+
+    let tx = rb.acquire_begin().await?;
+    let result: Result<(), rbatis::Error> = async {
+        tx.exec("DROP TABLE IF EXISTS weekly_attribute_snapshot_new", vec![]).await?;
+        tx.exec(SNAPSHOT_TABLE_SQL, vec![]).await?;
+        tx.exec(
+            "INSERT OR IGNORE INTO weekly_attribute_snapshot_new
+             SELECT id, user_id, week_start_date, year, week_number, intelligence, endurance, creativity,
+                    social, focus, adaptability, created_at
+             FROM weekly_attribute_snapshot",
+            vec![],
+        )
+        .await?;
+        tx.exec("DROP TABLE weekly_attribute_snapshot", vec![]).await?;
+        tx.exec("ALTER TABLE weekly_attribute_snapshot_new RENAME TO weekly_attribute_snapshot", vec![]).await?;
+        Ok(())
+    }
+    .await;
+    match result {
+        Ok(()) => {
+            tx.commit().await?;
+            log::info!("週屬性快照已改以 week_start_date 為唯一鍵");
+            Ok(())
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_week_bounds_for_each_preference() {
+        // 2026-03-04 是週三
+        assert_eq!(WeekStart::Monday.week_bounds(date("2026-03-04")), (date("2026-03-02"), date("2026-03-08")));
+        assert_eq!(WeekStart::Sunday.week_bounds(date("2026-03-04")), (date("2026-03-01"), date("2026-03-07")));
+        // 週日：週一起算時屬於前一週的最後一天，週日起算時是新一週的第一天
+        assert_eq!(WeekStart::Monday.week_start_of(date("2026-03-08")), date("2026-03-02"));
+        assert_eq!(WeekStart::Sunday.week_start_of(date("2026-03-08")), date("2026-03-08"));
+        // 兩種起始日的同一週使用相同的 ISO 週數
+        assert_eq!(iso_key(date("2026-03-01")), iso_key(date("2026-03-02")));
+        assert_eq!(iso_key(date("2026-03-02")), (2026, 10));
+    }
+
+    #[actix_web::test]
+    async fn test_migration_backfills_dates_and_rekeys_legacy_table() {
+        let rb = crate::test_utils::setup_db().await;
+        let app = crate::test_utils::init_app(&rb).await;
+        let user = crate::test_utils::create_user(&app, "week-start").await;
+        rb.exec("DROP TABLE weekly_attribute_snapshot", vec![]).await.unwrap();
+        rb.exec(
+            &SNAPSHOT_TABLE_SQL
+                .replace("weekly_attribute_snapshot_new", "weekly_attribute_snapshot")
+                .replace("UNIQUE(user_id, week_start_date)", "UNIQUE(user_id, year, week_number)"),
+            vec![],
+        )
+        .await
+        .unwrap();
+        rb.exec(
+            "INSERT INTO weekly_attribute_snapshot (id, user_id, week_start_date, year, week_number)
+             VALUES ('legacy', ?, '', 2026, 10)",
+            vec![Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+
+        migrate_weekly_snapshots(&rb).await.unwrap();
+        let start: String = rb
+            .query_decode("SELECT week_start_date FROM weekly_attribute_snapshot WHERE id = 'legacy'", vec![])
+            .await
+            .unwrap();
+        assert_eq!(start, "2026-03-02");
+
+        // 改為週日起算後，同一個 ISO 週的新快照可以並存
+        rb.exec(
+            "INSERT INTO weekly_attribute_snapshot (id, user_id, week_start_date, year, week_number)
+             VALUES ('sunday', ?, '2026-03-01', 2026, 10)",
+            vec![Value::String(user.id.clone())],
+        )
+        .await
+        .unwrap();
+        // 再次執行不會重建或改動資料
+        migrate_weekly_snapshots(&rb).await.unwrap();
+        let count: i64 = rb
+            .query_decode("SELECT COUNT(*) FROM weekly_attribute_snapshot", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}